@@ -1,5 +1,5 @@
-use crate::models::crypto::get_current_timestamp;
-use crate::models::types::SessionDetails;
+use crate::models::crypto::{get_current_timestamp, parse_api_key_id, DoubleSHA256Hash};
+use crate::models::types::AppEnv;
 
 use super::dtos::Claims;
 use super::error::AuthError;
@@ -7,20 +7,19 @@ use super::error::AuthError;
 use std::future::{ready, Ready};
 use std::sync::Arc;
 
-use actix_web::http::header::{self};
+use actix_web::http::header::{self, HeaderMap};
 use actix_web::HttpMessage;
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
     Error,
 };
-use dashmap::DashMap;
 use futures_util::future::LocalBoxFuture;
 
 // There are two steps in middleware processing.
 // 1. Middleware initialization, middleware factory gets called with
 //    next service in chain as parameter.
 // 2. Middleware's call method gets called with normal request.
-pub(crate) struct AuthenticationMiddleware(pub Arc<DashMap<String, SessionDetails>>);
+pub(crate) struct AuthenticationMiddleware(pub Arc<AppEnv>);
 
 // Middleware factory is `Transform` trait
 // `S` - type of the next service
@@ -40,14 +39,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(AuthenticationMiddlewareService {
             service,
-            active_sessions: self.0.clone(),
+            ain_env: self.0.clone(),
         }))
     }
 }
 
 pub(crate) struct AuthenticationMiddlewareService<S> {
     service: S,
-    active_sessions: Arc<DashMap<String, SessionDetails>>,
+    ain_env: Arc<AppEnv>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthenticationMiddlewareService<S>
@@ -63,7 +62,7 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let claims = authentication_middleware(&req, &self.active_sessions);
+        let claims = authenticate(req.headers(), &self.ain_env);
 
         let claims = match claims {
             Ok(claims) => claims,
@@ -80,29 +79,58 @@ where
     }
 }
 
-fn authentication_middleware(
-    req: &ServiceRequest,
-    active_sessions: &DashMap<String, SessionDetails>,
-) -> Result<Claims, AuthError> {
-    let auth_header = req.headers().get(header::AUTHORIZATION);
+// Extracts the bearer token (session token or API key) from the
+// `Authorization` header
+pub(crate) fn extract_access_token(headers: &HeaderMap) -> Result<&str, AuthError> {
+    let auth_header = headers.get(header::AUTHORIZATION);
     let auth_header = auth_header.ok_or(AuthError::InvalidToken)?;
     let auth_header = auth_header.to_str().map_err(|_| AuthError::InvalidToken)?;
 
     let mut header = auth_header.split_whitespace();
     let (_, access_token) = (header.next(), header.next().ok_or(AuthError::InvalidToken)?);
-    let session = active_sessions
+    Ok(access_token)
+}
+
+/// Returns the claims of the session token or API key the request is
+/// authenticated with
+pub(crate) fn authenticate(headers: &HeaderMap, ain_env: &AppEnv) -> Result<Claims, AuthError> {
+    let access_token = extract_access_token(headers)?;
+    let current_time = get_current_timestamp();
+
+    if let Some(api_key_id) = parse_api_key_id(access_token) {
+        let api_key = ain_env
+            .api_keys
+            .get(api_key_id)
+            .ok_or(AuthError::InvalidToken)?;
+        // check the key in constant time to prevent timing attacks
+        if !DoubleSHA256Hash::new(access_token.as_bytes()).verify_eq(&api_key.key_hash) {
+            return Err(AuthError::InvalidToken);
+        }
+        if api_key
+            .expires_at
+            .is_some_and(|expires_at| current_time >= expires_at)
+        {
+            return Err(AuthError::InvalidToken);
+        }
+        return Ok(Claims {
+            iat: api_key.created_at,
+            exp: api_key.expires_at.unwrap_or(u64::MAX),
+            username: api_key.username,
+        });
+    }
+
+    let session = ain_env
+        .active_sessions
         .get(access_token)
         .ok_or(AuthError::InvalidToken)?;
-    let current_time = get_current_timestamp();
     if current_time >= session.expires_at {
-        drop(session);
-        active_sessions.remove(access_token);
+        ain_env.active_sessions.remove(access_token)?;
         return Err(AuthError::InvalidToken);
     }
     let claims = Claims {
         iat: session.created_at,
         exp: session.expires_at,
-        username: session.user.username.clone(),
+        username: session.user.username,
     };
     Ok(claims)
 }
@@ -1,12 +1,14 @@
 use actix_web::{
     web::{self},
-    HttpResponse, Result,
+    HttpRequest, HttpResponse, Result,
 };
 
 use crate::app_context::AppContext;
 
 use super::{
-    dtos::{CreateSessionDTO, Session},
+    authentication_middleware::extract_access_token,
+    dtos::{ApiKeyInfo, Claims, CreateApiKeyDto, CreateSessionDTO, CreatedApiKey, Session},
+    error::AuthError,
     service,
 };
 
@@ -30,3 +32,118 @@ pub(crate) async fn create_session(
     let res = service::create_session(create_session_dto, ctx.into_inner()).await?;
     Ok(HttpResponse::Ok().json(res))
 }
+
+/// Refresh the current session
+///
+/// Issues a new session token with a fresh lifetime and invalidates the one
+/// used to make the request
+#[utoipa::path(
+    post,
+    path = "/auth/refresh-session",
+    responses(
+        (status = 200, description = "Session refreshed successfully", body = Session),
+        (status = 400, description = "Request was not made with a session token"),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "auth"
+)]
+pub(crate) async fn refresh_session(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, AuthError> {
+    let access_token = extract_access_token(req.headers())?;
+    let res = service::refresh_session(access_token, ctx.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// End the current session (logout)
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    responses(
+        (status = 204, description = "Session ended successfully"),
+        (status = 400, description = "Request was not made with a session token"),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "auth"
+)]
+pub(crate) async fn logout(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, AuthError> {
+    let access_token = extract_access_token(req.headers())?;
+    service::logout(access_token, ctx.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Create an API key
+///
+/// Creates a long-lived API key for the current user, accepted as a bearer
+/// token in place of a session token
+#[utoipa::path(
+    post,
+    path = "/auth/api-keys",
+    request_body = CreateApiKeyDto,
+    responses(
+        (status = 201, description = "API key created successfully", body = CreatedApiKey),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "auth"
+)]
+pub(crate) async fn create_api_key(
+    claims: Claims,
+    web::Json(create_api_key_dto): web::Json<CreateApiKeyDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, AuthError> {
+    let res =
+        service::create_api_key(claims.username, create_api_key_dto, ctx.into_inner()).await?;
+    Ok(HttpResponse::Created().json(res))
+}
+
+/// List API keys
+///
+/// Lists the API keys of the current user, without the keys themselves
+#[utoipa::path(
+    get,
+    path = "/auth/api-keys",
+    responses(
+        (status = 200, description = "API keys retrieved successfully", body = [ApiKeyInfo]),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "auth"
+)]
+pub(crate) async fn list_api_keys(
+    claims: Claims,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, AuthError> {
+    let res = service::list_api_keys(&claims.username, ctx.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Revoke an API key
+#[utoipa::path(
+    delete,
+    path = "/auth/api-keys/{key_id}",
+    params(
+        ("key_id" = String, Path, description = "API key identifier")
+    ),
+    responses(
+        (status = 204, description = "API key revoked successfully"),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 404, description = "API key not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "auth"
+)]
+pub(crate) async fn revoke_api_key(
+    claims: Claims,
+    key_id: web::Path<String>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, AuthError> {
+    service::revoke_api_key(&claims.username, &key_id, ctx.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -25,6 +25,36 @@ pub(crate) struct Session {
     pub expires_at: u64,
 }
 
+/// DTO for creating an API key
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct CreateApiKeyDto {
+    /// Human readable name to identify the key by
+    pub name: String,
+    /// Number of days after which the key expires, never expires if omitted
+    pub expires_in_days: Option<u64>,
+}
+
+/// Newly created API key, the key itself is only ever returned once
+#[derive(Serialize, ToSchema)]
+pub(crate) struct CreatedApiKey {
+    /// Identifier of the key, used to revoke it
+    pub id: String,
+    pub name: String,
+    /// The API key, to be sent as a bearer token
+    pub key: String,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+/// API key details, without the key itself
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ApiKeyInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 // a structure for holding claims data used in JWT tokens
 // resembles payload in NodeJS world
@@ -9,6 +9,9 @@ pub enum AuthError {
     WrongCredentials,
     InvalidToken,
     FailedToExtractTokenFromRequest,
+    NotASessionToken,
+    ApiKeyNotFound(String),
+    DatabaseError(String),
 }
 
 impl Display for AuthError {
@@ -19,6 +22,9 @@ impl Display for AuthError {
             Self::FailedToExtractTokenFromRequest => {
                 write!(f, "Failed to extract token from request!")
             }
+            Self::NotASessionToken => write!(f, "Only session tokens can be used here!"),
+            Self::ApiKeyNotFound(id) => write!(f, "API key '{}' not found!", id),
+            Self::DatabaseError(msg) => write!(f, "Database error: {}", msg),
         }
     }
 }
@@ -34,6 +40,15 @@ impl ResponseError for AuthError {
             Self::WrongCredentials => StatusCode::BAD_REQUEST,
             Self::InvalidToken => StatusCode::UNAUTHORIZED,
             Self::FailedToExtractTokenFromRequest => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotASessionToken => StatusCode::BAD_REQUEST,
+            Self::ApiKeyNotFound(_) => StatusCode::NOT_FOUND,
+            Self::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
+
+impl From<lmdb::Error> for AuthError {
+    fn from(err: lmdb::Error) -> Self {
+        Self::DatabaseError(err.to_string())
+    }
+}
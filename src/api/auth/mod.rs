@@ -1,13 +1,35 @@
+use std::sync::Arc;
+
 use actix_web::{web, Scope};
+use authentication_middleware::AuthenticationMiddleware;
+
+use crate::models::types::AppEnv;
+
 pub(crate) mod authentication_middleware;
 pub mod controller;
 pub mod dtos;
 mod error;
 mod service;
 
-pub(crate) fn auth_module() -> Scope {
-    web::scope("/auth").route(
-        "/create-session",
-        web::post().to(controller::create_session),
-    )
+pub(crate) fn auth_module(ain_env: Arc<AppEnv>) -> Scope {
+    web::scope("/auth")
+        .route(
+            "/create-session",
+            web::post().to(controller::create_session),
+        )
+        .service(
+            web::scope("")
+                .wrap(AuthenticationMiddleware(ain_env))
+                .route(
+                    "/refresh-session",
+                    web::post().to(controller::refresh_session),
+                )
+                .route("/logout", web::post().to(controller::logout))
+                .route("/api-keys", web::post().to(controller::create_api_key))
+                .route("/api-keys", web::get().to(controller::list_api_keys))
+                .route(
+                    "/api-keys/{key_id}",
+                    web::delete().to(controller::revoke_api_key),
+                ),
+        )
 }
@@ -3,13 +3,13 @@ use std::{str::FromStr, sync::Arc};
 use crate::{
     app_context::AppContext,
     models::{
-        crypto::{self, SingleSHA256Hash},
-        types::SessionDetails,
+        crypto::{self, DoubleSHA256Hash, SingleSHA256Hash},
+        types::{ApiKey, SessionDetails},
     },
 };
 
 use super::{
-    dtos::{CreateSessionDTO, Session},
+    dtos::{ApiKeyInfo, CreateApiKeyDto, CreateSessionDTO, CreatedApiKey, Session},
     error::AuthError,
 };
 
@@ -19,6 +19,9 @@ pub(crate) async fn create_session(
     create_session_dto: CreateSessionDTO,
     ctx: Arc<AppContext>,
 ) -> Result<Session, AuthError> {
+    // held until the session is stored, so that an admin key rotation can't
+    // happen in between and leave behind a session created with the old key
+    let admin_key = ctx.ain_env.admin_key.read();
     let user = ctx
         .ain_env
         .users_map
@@ -31,23 +34,20 @@ pub(crate) async fn create_session(
         return Err(AuthError::WrongCredentials)?;
     }
 
-    let (access_token, timestamp) = crypto::create_session(
-        &create_session_dto.username,
-        &ctx.ain_env.admin_key,
-        &password_hash,
-    );
+    let (access_token, timestamp) =
+        crypto::create_session(&create_session_dto.username, &admin_key, &password_hash);
 
     let created_at = timestamp;
     let expires_at = timestamp + TOKEN_LIFETIME;
 
     ctx.ain_env.active_sessions.insert(
-        access_token.clone(),
+        &access_token,
         SessionDetails {
             created_at,
             expires_at,
             user,
         },
-    );
+    )?;
 
     Ok(Session {
         access_token,
@@ -55,3 +55,108 @@ pub(crate) async fn create_session(
         expires_at,
     })
 }
+
+pub(crate) async fn refresh_session(
+    access_token: &str,
+    ctx: Arc<AppContext>,
+) -> Result<Session, AuthError> {
+    // keeps an admin key rotation from ending the sessions while this one is
+    // being replaced
+    let _admin_key = ctx.ain_env.admin_key.read();
+    let session = ctx
+        .ain_env
+        .active_sessions
+        .get(access_token)
+        .ok_or(AuthError::NotASessionToken)?;
+
+    // the password isn't available here to derive the token from, so the
+    // refreshed session gets a random one
+    let new_access_token = crypto::generate_random_token(32);
+    let created_at = crypto::get_current_timestamp();
+    let expires_at = created_at + TOKEN_LIFETIME;
+
+    ctx.ain_env.active_sessions.insert(
+        &new_access_token,
+        SessionDetails {
+            created_at,
+            expires_at,
+            user: session.user,
+        },
+    )?;
+    ctx.ain_env.active_sessions.remove(access_token)?;
+
+    Ok(Session {
+        access_token: new_access_token,
+        created_at,
+        expires_at,
+    })
+}
+
+pub(crate) async fn logout(access_token: &str, ctx: Arc<AppContext>) -> Result<(), AuthError> {
+    ctx.ain_env
+        .active_sessions
+        .remove(access_token)?
+        .ok_or(AuthError::NotASessionToken)?;
+    Ok(())
+}
+
+pub(crate) async fn create_api_key(
+    username: String,
+    create_api_key_dto: CreateApiKeyDto,
+    ctx: Arc<AppContext>,
+) -> Result<CreatedApiKey, AuthError> {
+    let (id, key) = crypto::create_api_key();
+    let created_at = crypto::get_current_timestamp();
+    let expires_at = create_api_key_dto
+        .expires_in_days
+        .map(|days| created_at + days * 24 * 60 * 60);
+
+    ctx.ain_env.api_keys.add(ApiKey {
+        id: id.clone(),
+        name: create_api_key_dto.name.clone(),
+        username,
+        key_hash: DoubleSHA256Hash::new(key.as_bytes()),
+        created_at,
+        expires_at,
+    })?;
+
+    Ok(CreatedApiKey {
+        id,
+        name: create_api_key_dto.name,
+        key,
+        created_at,
+        expires_at,
+    })
+}
+
+pub(crate) async fn list_api_keys(
+    username: &str,
+    ctx: Arc<AppContext>,
+) -> Result<Vec<ApiKeyInfo>, AuthError> {
+    Ok(ctx
+        .ain_env
+        .api_keys
+        .list_for_user(username)
+        .into_iter()
+        .map(|api_key| ApiKeyInfo {
+            id: api_key.id,
+            name: api_key.name,
+            created_at: api_key.created_at,
+            expires_at: api_key.expires_at,
+        })
+        .collect())
+}
+
+pub(crate) async fn revoke_api_key(
+    username: &str,
+    id: &str,
+    ctx: Arc<AppContext>,
+) -> Result<(), AuthError> {
+    // keys owned by other users are reported as missing
+    match ctx.ain_env.api_keys.get(id) {
+        Some(api_key) if api_key.username == username => {}
+        _ => return Err(AuthError::ApiKeyNotFound(id.to_string())),
+    }
+    ctx.ain_env.api_keys.remove(id)?;
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use crate::api::openapi::{
-    AuthApiDoc, CollectionsApiDoc, CombinedApiDoc, IndexesApiDoc, SearchApiDoc, StreamingApiDoc,
-    TransactionsApiDoc, VectorsApiDoc, VersionsApiDoc,
+    AdminApiDoc, AuthApiDoc, CollectionsApiDoc, CombinedApiDoc, ImportsApiDoc, IndexesApiDoc,
+    MetadataSchemaApiDoc, MonitoringApiDoc, ReplicationApiDoc, SearchApiDoc, SnapshotsApiDoc,
+    StreamingApiDoc, TenantsApiDoc, TransactionsApiDoc, VectorsApiDoc, VersionsApiDoc,
 };
 use actix_web::{web, HttpResponse, Scope};
 use utoipa::OpenApi;
@@ -28,6 +29,25 @@ pub(crate) fn api_docs_module() -> Scope {
             "/streaming/openapi.json",
             web::get().to(streaming_openapi_json),
         )
+        .route("/admin/openapi.json", web::get().to(admin_openapi_json))
+        .route("/tenants/openapi.json", web::get().to(tenants_openapi_json))
+        .route(
+            "/snapshots/openapi.json",
+            web::get().to(snapshots_openapi_json),
+        )
+        .route("/imports/openapi.json", web::get().to(imports_openapi_json))
+        .route(
+            "/metadata_schema/openapi.json",
+            web::get().to(metadata_schema_openapi_json),
+        )
+        .route(
+            "/replication/openapi.json",
+            web::get().to(replication_openapi_json),
+        )
+        .route(
+            "/monitoring/openapi.json",
+            web::get().to(monitoring_openapi_json),
+        )
 }
 
 async fn openapi_json() -> HttpResponse {
@@ -65,3 +85,31 @@ async fn versions_openapi_json() -> HttpResponse {
 async fn streaming_openapi_json() -> HttpResponse {
     HttpResponse::Ok().json(StreamingApiDoc::openapi())
 }
+
+async fn admin_openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(AdminApiDoc::openapi())
+}
+
+async fn tenants_openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(TenantsApiDoc::openapi())
+}
+
+async fn snapshots_openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(SnapshotsApiDoc::openapi())
+}
+
+async fn imports_openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ImportsApiDoc::openapi())
+}
+
+async fn metadata_schema_openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(MetadataSchemaApiDoc::openapi())
+}
+
+async fn replication_openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ReplicationApiDoc::openapi())
+}
+
+async fn monitoring_openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(MonitoringApiDoc::openapi())
+}
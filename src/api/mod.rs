@@ -1,4 +1,5 @@
 pub(crate) mod auth;
 pub(crate) mod docs;
+pub(crate) mod monitoring;
 pub(crate) mod openapi;
 pub(crate) mod vectordb;
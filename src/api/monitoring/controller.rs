@@ -0,0 +1,106 @@
+use actix_web::{error::ErrorInternalServerError, web, HttpRequest, HttpResponse, Result};
+
+use super::{
+    dtos::{HealthDto, ReadinessDto, ServerInfoDto},
+    error::MonitoringError,
+    service,
+};
+use crate::{
+    api::auth::authentication_middleware::authenticate,
+    app_context::AppContext,
+    models::{metrics, types::ADMIN_USERNAME},
+};
+
+/// Get metrics
+///
+/// Returns the metrics of the server in the Prometheus text format:
+/// search request counts and latencies per collection and search type,
+/// indexing throughput, WAL backlog, cache hits, misses and evictions,
+/// buffer flushes, loaded collections and active sessions. Only available
+/// to the admin user, unless `server.public_metrics` is set.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", content_type = "text/plain"),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 500, description = "Server error")
+    ),
+    tag = "monitoring"
+)]
+pub(crate) async fn get_metrics(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse> {
+    if !ctx.config.server.public_metrics {
+        let claims = authenticate(req.headers(), &ctx.ain_env)?;
+        if claims.username != ADMIN_USERNAME {
+            return Err(MonitoringError::Forbidden.into());
+        }
+    }
+    let ctx = ctx.into_inner();
+    let body = web::block(move || metrics::render(&ctx))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(body))
+}
+
+/// Liveness probe
+///
+/// Responds as long as the server is running.
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Server is live", body = HealthDto)
+    ),
+    tag = "monitoring"
+)]
+pub(crate) async fn get_health() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(HealthDto {
+        status: "ok".to_string(),
+    }))
+}
+
+/// Readiness probe
+///
+/// Responds with 200 once the versions that weren't indexed before the
+/// server stopped are indexed again, and with 503 until then. The
+/// `/vectordb` endpoints are unavailable meanwhile.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    responses(
+        (status = 200, description = "Server is ready", body = ReadinessDto),
+        (status = 503, description = "Collections are being recovered, or their recovery failed", body = ReadinessDto)
+    ),
+    tag = "monitoring"
+)]
+pub(crate) async fn get_readiness() -> Result<HttpResponse> {
+    let readiness = service::readiness();
+    let mut response = if readiness.status == "ready" {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    Ok(response.json(readiness))
+}
+
+/// Get server info
+///
+/// Returns the version of the server, the cargo features it was built with,
+/// its uptime and a summary of its config.
+#[utoipa::path(
+    get,
+    path = "/info",
+    responses(
+        (status = 200, description = "Server info", body = ServerInfoDto)
+    ),
+    tag = "monitoring"
+)]
+pub(crate) async fn get_info(ctx: web::Data<AppContext>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(service::server_info(&ctx)))
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct HealthDto {
+    /// Always `ok`, the server is live if it responds
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ReadinessDto {
+    /// `ready`, `recovering` or `failed`
+    pub status: String,
+    /// Collections whose unindexed versions are still being indexed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_collections: Option<usize>,
+    /// Why the restart recovery failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ServerInfoDto {
+    pub version: String,
+    /// Cargo features the server was built with, e.g. `grpc-server`
+    pub features: Vec<String>,
+    #[schema(value_type = String, example = "2023-01-01T12:00:00Z")]
+    pub started_at: DateTime<Utc>,
+    pub uptime_seconds: u64,
+    pub config: ConfigSummaryDto,
+}
+
+/// Settings of the server, without any credentials
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ConfigSummaryDto {
+    /// `http` or `https`
+    pub mode: String,
+    pub host: String,
+    pub port: u16,
+    pub thread_pool_size: usize,
+    /// `sequential` or `batch`
+    pub indexing_mode: String,
+    pub max_loaded_collections: usize,
+    /// `local` or `s3`
+    pub storage_backend: String,
+    /// `leader` or `follower`
+    pub replication_role: String,
+    pub compaction_enabled: bool,
+    pub slow_query_log_enabled: bool,
+    pub otlp_export_enabled: bool,
+}
@@ -0,0 +1,43 @@
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    HttpResponse, ResponseError,
+};
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum MonitoringError {
+    // collections are still being recovered after a restart
+    NotReady(String),
+    // the metrics cover all tenants, so only the admin can read them
+    Forbidden,
+}
+
+impl Display for MonitoringError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotReady(msg) => write!(f, "Server isn't ready: {}", msg),
+            Self::Forbidden => write!(f, "Only the admin user can read the metrics"),
+        }
+    }
+}
+
+impl ResponseError for MonitoringError {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let message = self.to_string();
+        HttpResponse::build(status)
+            .insert_header(ContentType::json())
+            .json(serde_json::json!({
+                "error": status.canonical_reason().unwrap_or("Error"),
+                "code": status.as_u16(),
+                "message": message
+            }))
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::NotReady(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use actix_web::web;
+
+pub mod controller;
+pub(crate) mod dtos;
+pub(crate) mod error;
+pub(crate) mod readiness_middleware;
+pub(crate) mod service;
+
+// Served at the root, outside of `/vectordb`, and without authentication so
+// that probes can reach them. `/metrics` authenticates the admin itself,
+// unless it's configured to be public.
+pub(crate) fn monitoring_module(cfg: &mut web::ServiceConfig) {
+    cfg.route("/metrics", web::get().to(controller::get_metrics))
+        .route("/health", web::get().to(controller::get_health))
+        .route("/ready", web::get().to(controller::get_readiness))
+        .route("/info", web::get().to(controller::get_info));
+}
@@ -0,0 +1,70 @@
+use std::future::{ready, Ready};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+
+use super::error::MonitoringError;
+use crate::models::recovery::{self, RecoveryStatus};
+
+// Rejects requests until the collections are recovered after a restart, as
+// their indexes are missing the versions that are being indexed again
+pub(crate) struct ReadinessMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for ReadinessMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ReadinessMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ReadinessMiddlewareService { service }))
+    }
+}
+
+pub(crate) struct ReadinessMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for ReadinessMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let error = match recovery::status() {
+            RecoveryStatus::Ready => None,
+            RecoveryStatus::Recovering {
+                pending_collections,
+            } => Some(format!(
+                "{} collections are being recovered",
+                pending_collections
+            )),
+            RecoveryStatus::Failed(msg) => Some(msg),
+        };
+        if let Some(msg) = error {
+            return Box::pin(async move { Err(MonitoringError::NotReady(msg).into()) });
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            Ok(res)
+        })
+    }
+}
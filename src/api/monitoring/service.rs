@@ -0,0 +1,77 @@
+use chrono::Utc;
+
+use super::dtos::{ConfigSummaryDto, ReadinessDto, ServerInfoDto};
+use crate::{
+    app_context::AppContext,
+    config_loader::{StorageBackendKind, VectorsIndexingMode},
+    models::recovery::{self, RecoveryStatus},
+};
+
+pub(crate) fn readiness() -> ReadinessDto {
+    match recovery::status() {
+        RecoveryStatus::Ready => ReadinessDto {
+            status: "ready".to_string(),
+            pending_collections: None,
+            error: None,
+        },
+        RecoveryStatus::Recovering {
+            pending_collections,
+        } => ReadinessDto {
+            status: "recovering".to_string(),
+            pending_collections: Some(pending_collections),
+            error: None,
+        },
+        RecoveryStatus::Failed(error) => ReadinessDto {
+            status: "failed".to_string(),
+            pending_collections: None,
+            error: Some(error),
+        },
+    }
+}
+
+// Features enabled in Cargo.toml, listed by hand as cargo doesn't expose
+// them to the crate
+fn enabled_features() -> Vec<String> {
+    let mut features = Vec::new();
+    if cfg!(feature = "grpc-server") {
+        features.push("grpc-server".to_string());
+    }
+    if cfg!(feature = "local-embeddings") {
+        features.push("local-embeddings".to_string());
+    }
+    features
+}
+
+pub(crate) fn server_info(ctx: &AppContext) -> ServerInfoDto {
+    let config = &ctx.config;
+    ServerInfoDto {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        features: enabled_features(),
+        started_at: ctx.started_at,
+        uptime_seconds: (Utc::now() - ctx.started_at).num_seconds().max(0) as u64,
+        config: ConfigSummaryDto {
+            mode: config.server.mode.protocol().to_string(),
+            host: config.server.host.to_string(),
+            port: config.server.port.into(),
+            thread_pool_size: config.thread_pool.pool_size,
+            indexing_mode: match config.indexing.mode {
+                VectorsIndexingMode::Sequential => "sequential",
+                VectorsIndexingMode::Batch { .. } => "batch",
+            }
+            .to_string(),
+            max_loaded_collections: config.cache.max_collections,
+            storage_backend: match config.storage.backend {
+                StorageBackendKind::Local => "local",
+                StorageBackendKind::S3 => "s3",
+            }
+            .to_string(),
+            replication_role: config.replication.role.as_str().to_string(),
+            compaction_enabled: config
+                .retention
+                .compaction_interval
+                .is_some_and(|interval| interval > 0),
+            slow_query_log_enabled: config.tracing.slow_query_threshold_ms.is_some(),
+            otlp_export_enabled: config.tracing.otlp_endpoint.is_some(),
+        },
+    }
+}
@@ -24,13 +24,21 @@ fn api_info() -> openapi::Info {
 #[derive(OpenApi)]
 #[openapi(
     paths(
-        crate::api::auth::controller::create_session
+        crate::api::auth::controller::create_session,
+        crate::api::auth::controller::refresh_session,
+        crate::api::auth::controller::logout,
+        crate::api::auth::controller::create_api_key,
+        crate::api::auth::controller::list_api_keys,
+        crate::api::auth::controller::revoke_api_key
     ),
     components(
         schemas(
             crate::api::auth::dtos::CreateSessionDTO,
             crate::api::auth::dtos::Session,
-            crate::api::auth::dtos::Claims
+            crate::api::auth::dtos::Claims,
+            crate::api::auth::dtos::CreateApiKeyDto,
+            crate::api::auth::dtos::CreatedApiKey,
+            crate::api::auth::dtos::ApiKeyInfo
         )
     ),
     tags(
@@ -51,6 +59,7 @@ pub struct AuthApiDoc;
         crate::api::vectordb::collections::controller::delete_collection_by_id,
         crate::api::vectordb::collections::controller::load_collection,
         crate::api::vectordb::collections::controller::unload_collection,
+        crate::api::vectordb::collections::controller::compact_collection,
         crate::api::vectordb::collections::controller::get_loaded_collections
     ),
     components(
@@ -63,9 +72,12 @@ pub struct AuthApiDoc;
             crate::api::vectordb::collections::dtos::MetadataSchemaParam,
             crate::api::vectordb::collections::dtos::SupportedCondition,
             crate::api::vectordb::collections::dtos::ConditionOp,
+            crate::api::vectordb::collections::dtos::CompactCollectionDto,
+            crate::api::vectordb::collections::dtos::CompactCollectionResponseDto,
             crate::models::collection::CollectionConfig,
             crate::models::collection::DenseVectorOptions,
             crate::models::collection::SparseVectorOptions,
+            crate::models::embeddings::EmbeddingConfig,
             crate::models::collection::TFIDFOptions,
             CollectionIndexingStatusResponse
         )
@@ -85,6 +97,7 @@ pub struct CollectionsApiDoc;
         crate::api::vectordb::indexes::controller::create_sparse_index,
         crate::api::vectordb::indexes::controller::create_tf_idf_index,
         crate::api::vectordb::indexes::controller::get_index,
+        crate::api::vectordb::indexes::controller::get_tf_idf_stats,
         crate::api::vectordb::indexes::controller::delete_index
     ),
     components(
@@ -106,6 +119,8 @@ pub struct CollectionsApiDoc;
             crate::api::vectordb::indexes::dtos::DenseIndexInfo,
             crate::api::vectordb::indexes::dtos::SparseIndexInfo,
             crate::api::vectordb::indexes::dtos::TfIdfIndexInfo,
+            crate::api::vectordb::indexes::dtos::TfIdfCorpusStatsDto,
+            crate::api::vectordb::indexes::dtos::TfIdfTermStatsDto,
             crate::api::vectordb::indexes::dtos::QuantizationInfo,
             crate::api::vectordb::indexes::dtos::RangeInfo,
             crate::api::vectordb::indexes::dtos::HnswParamsInfo
@@ -124,6 +139,7 @@ pub struct IndexesApiDoc;
     paths(
         crate::api::vectordb::search::controller::dense_search,
         crate::api::vectordb::search::controller::batch_dense_search,
+        crate::api::vectordb::search::controller::multi_vector_search,
         crate::api::vectordb::search::controller::sparse_search,
         crate::api::vectordb::search::controller::batch_sparse_search,
         crate::api::vectordb::search::controller::hybrid_search,
@@ -136,6 +152,7 @@ pub struct IndexesApiDoc;
             crate::api::vectordb::search::dtos::DenseSearchRequestDto,
             crate::api::vectordb::search::dtos::BatchDenseSearchRequestDto,
             crate::api::vectordb::search::dtos::BatchDenseSearchRequestQueryDto,
+            crate::api::vectordb::search::dtos::MultiVectorSearchRequestDto,
             crate::api::vectordb::search::dtos::SparseSearchRequestDto,
             crate::api::vectordb::search::dtos::BatchSparseSearchRequestDto,
             crate::api::vectordb::search::dtos::HybridSearchRequestDto,
@@ -143,7 +160,16 @@ pub struct IndexesApiDoc;
             crate::api::vectordb::search::dtos::HybridSearchQuery,
             crate::api::vectordb::search::dtos::FindSimilarTFIDFDocumentDto,
             crate::api::vectordb::search::dtos::BatchSearchTFIDFDocumentsDto,
+            crate::api::vectordb::search::dtos::GroupingDto,
+            crate::api::vectordb::search::dtos::GroupBy,
+            crate::api::vectordb::search::dtos::GroupAggregation,
+            crate::api::vectordb::search::dtos::ResultFieldsDto,
+            crate::api::vectordb::vectors::dtos::VectorField,
             crate::api::vectordb::search::dtos::SearchResultItemDto,
+            crate::api::vectordb::search::dtos::SearchResultGroupDto,
+            crate::api::vectordb::search::dtos::ScoreExplanationDto,
+            crate::api::vectordb::search::dtos::BM25TermExplanationDto,
+            crate::api::vectordb::search::dtos::SparseTermExplanationDto,
             crate::api::vectordb::search::dtos::SearchResponseDto,
             crate::api::vectordb::search::dtos::BatchSearchResponseDto
         )
@@ -186,6 +212,7 @@ pub struct VersionsApiDoc;
         crate::api::vectordb::transactions::controller::get_transaction_status,
         crate::api::vectordb::transactions::controller::create_vector_in_transaction,
         crate::api::vectordb::transactions::controller::delete_vector_by_id,
+        crate::api::vectordb::transactions::controller::delete_vectors,
         crate::api::vectordb::transactions::controller::abort_transaction,
         crate::api::vectordb::transactions::controller::upsert
     ),
@@ -193,7 +220,10 @@ pub struct VersionsApiDoc;
         schemas(
             crate::api::vectordb::transactions::dtos::CreateTransactionResponseDto,
             crate::api::vectordb::transactions::dtos::UpsertDto,
+            crate::api::vectordb::transactions::dtos::DeleteVectorsDto,
+            crate::api::vectordb::transactions::dtos::DeleteVectorsResponseDto,
             crate::models::collection_transaction::TransactionStatus,
+            crate::models::collection_transaction::ConflictResolution,
             crate::models::collection_transaction::ProcessingStats
         )
     ),
@@ -209,6 +239,7 @@ pub struct TransactionsApiDoc;
 #[openapi(
     paths(
         crate::api::vectordb::vectors::controller::query_vectors,
+        crate::api::vectordb::vectors::controller::scroll_vectors,
         crate::api::vectordb::vectors::controller::get_vector_by_id,
         crate::api::vectordb::vectors::controller::check_vector_existence,
         crate::api::vectordb::vectors::controller::fetch_vector_neighbors
@@ -217,7 +248,10 @@ pub struct TransactionsApiDoc;
         schemas(
             crate::api::vectordb::vectors::dtos::VectorsQueryDto,
             crate::api::vectordb::vectors::dtos::CreateVectorDto,
-            crate::api::vectordb::vectors::dtos::SimilarVector
+            crate::api::vectordb::vectors::dtos::SimilarVector,
+            crate::api::vectordb::vectors::dtos::VectorField,
+            crate::api::vectordb::vectors::dtos::ScrollVectorsDto,
+            crate::api::vectordb::vectors::dtos::ScrollVectorsResponseDto
         )
     ),
     tags(
@@ -232,11 +266,14 @@ pub struct VectorsApiDoc;
 #[openapi(
     paths(
         crate::api::vectordb::streaming::controller::upsert,
-        crate::api::vectordb::streaming::controller::delete_vector_by_id
+        crate::api::vectordb::streaming::controller::delete_vector_by_id,
+        crate::api::vectordb::streaming::controller::delete_vectors
     ),
     components(
         schemas(
-            crate::api::vectordb::transactions::dtos::UpsertDto
+            crate::api::vectordb::transactions::dtos::UpsertDto,
+            crate::api::vectordb::transactions::dtos::DeleteVectorsDto,
+            crate::api::vectordb::transactions::dtos::DeleteVectorsResponseDto
         )
     ),
     tags(
@@ -246,11 +283,190 @@ pub struct VectorsApiDoc;
 )]
 pub struct StreamingApiDoc;
 
+/// API documentation for admin endpoints
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::api::vectordb::admin::controller::get_storage_usage,
+        crate::api::vectordb::admin::controller::rotate_admin_key
+    ),
+    components(
+        schemas(
+            crate::api::vectordb::admin::dtos::StorageUsageDto,
+            crate::api::vectordb::admin::dtos::RotateAdminKeyDto
+        )
+    ),
+    tags(
+        (name = "admin", description = "Admin endpoints")
+    ),
+    modifiers(&AdminApiDoc)
+)]
+pub struct AdminApiDoc;
+
+/// API documentation for tenant endpoints
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::api::vectordb::tenants::controller::create_tenant,
+        crate::api::vectordb::tenants::controller::list_tenants,
+        crate::api::vectordb::tenants::controller::get_tenant,
+        crate::api::vectordb::tenants::controller::delete_tenant,
+        crate::api::vectordb::tenants::controller::update_tenant_quotas,
+        crate::api::vectordb::tenants::controller::create_tenant_user,
+        crate::api::vectordb::tenants::controller::remove_tenant_user
+    ),
+    components(
+        schemas(
+            crate::api::vectordb::tenants::dtos::CreateTenantDto,
+            crate::api::vectordb::tenants::dtos::TenantDto,
+            crate::api::vectordb::tenants::dtos::TenantUsageDto,
+            crate::api::vectordb::tenants::dtos::CreateTenantUserDto,
+            crate::models::tenants::TenantQuotas
+        )
+    ),
+    tags(
+        (name = "tenants", description = "Tenant management endpoints")
+    ),
+    modifiers(&TenantsApiDoc)
+)]
+pub struct TenantsApiDoc;
+
+/// API documentation for snapshot endpoints
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::api::vectordb::snapshots::controller::create_snapshot,
+        crate::api::vectordb::snapshots::controller::list_snapshots,
+        crate::api::vectordb::snapshots::controller::download_snapshot,
+        crate::api::vectordb::snapshots::controller::upload_snapshot,
+        crate::api::vectordb::snapshots::controller::delete_snapshot,
+        crate::api::vectordb::snapshots::controller::restore_snapshot
+    ),
+    components(
+        schemas(
+            crate::api::vectordb::snapshots::dtos::CreateSnapshotDto,
+            crate::api::vectordb::snapshots::dtos::SnapshotDto,
+            crate::api::vectordb::snapshots::dtos::RestoreSnapshotDto,
+            crate::api::vectordb::snapshots::dtos::RestoreSnapshotResponseDto
+        )
+    ),
+    tags(
+        (name = "snapshots", description = "Collection snapshot endpoints")
+    ),
+    modifiers(&SnapshotsApiDoc)
+)]
+pub struct SnapshotsApiDoc;
+
+/// API documentation for import endpoints
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::api::vectordb::imports::controller::import_file,
+        crate::api::vectordb::imports::controller::import_upload,
+        crate::api::vectordb::imports::controller::get_import
+    ),
+    components(
+        schemas(
+            crate::api::vectordb::imports::dtos::ImportFormat,
+            crate::api::vectordb::imports::dtos::ImportFileDto,
+            crate::api::vectordb::imports::dtos::ImportUploadQueryDto,
+            crate::api::vectordb::imports::dtos::ImportState,
+            crate::api::vectordb::imports::dtos::ImportJobDto
+        )
+    ),
+    tags(
+        (name = "imports", description = "Bulk import endpoints")
+    ),
+    modifiers(&ImportsApiDoc)
+)]
+pub struct ImportsApiDoc;
+
+/// API documentation for metadata schema endpoints
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::api::vectordb::metadata_schema::controller::update_metadata_schema,
+        crate::api::vectordb::metadata_schema::controller::get_reindex
+    ),
+    components(
+        schemas(
+            crate::api::vectordb::metadata_schema::dtos::UpdateMetadataSchemaDto,
+            crate::api::vectordb::metadata_schema::dtos::UpdateMetadataSchemaResponseDto,
+            crate::api::vectordb::metadata_schema::dtos::ReindexState,
+            crate::api::vectordb::metadata_schema::dtos::ReindexJobDto,
+            crate::api::vectordb::collections::dtos::MetadataField,
+            crate::api::vectordb::collections::dtos::SupportedCondition,
+            crate::api::vectordb::collections::dtos::ConditionOp
+        )
+    ),
+    tags(
+        (name = "metadata_schema", description = "Metadata schema endpoints")
+    ),
+    modifiers(&MetadataSchemaApiDoc)
+)]
+pub struct MetadataSchemaApiDoc;
+
+/// API documentation for replication endpoints
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::api::vectordb::replication::controller::get_replication_status,
+        crate::api::vectordb::replication::controller::list_replicated_collections,
+        crate::api::vectordb::replication::controller::get_versions,
+        crate::api::vectordb::replication::controller::download_snapshot
+    ),
+    components(
+        schemas(
+            crate::api::vectordb::replication::dtos::ReplicationStatusDto,
+            crate::api::vectordb::replication::dtos::CollectionReplicationDto,
+            crate::api::vectordb::replication::dtos::FollowerStatusDto,
+            crate::api::vectordb::replication::dtos::FollowerCollectionDto,
+            crate::models::replication::ReplicatedCollection,
+            crate::models::replication::ReplicatedVersion,
+            crate::models::replication::ReplicationBatch
+        )
+    ),
+    tags(
+        (name = "replication", description = "Leader-follower replication endpoints")
+    ),
+    modifiers(&ReplicationApiDoc)
+)]
+pub struct ReplicationApiDoc;
+
+/// API documentation for monitoring endpoints
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::api::monitoring::controller::get_metrics,
+        crate::api::monitoring::controller::get_health,
+        crate::api::monitoring::controller::get_readiness,
+        crate::api::monitoring::controller::get_info
+    ),
+    components(
+        schemas(
+            crate::api::monitoring::dtos::HealthDto,
+            crate::api::monitoring::dtos::ReadinessDto,
+            crate::api::monitoring::dtos::ServerInfoDto,
+            crate::api::monitoring::dtos::ConfigSummaryDto
+        )
+    ),
+    tags(
+        (name = "monitoring", description = "Monitoring endpoints")
+    ),
+    modifiers(&MonitoringApiDoc)
+)]
+pub struct MonitoringApiDoc;
+
 /// Combined API documentation
 #[derive(OpenApi)]
 #[openapi(
     paths(
         crate::api::auth::controller::create_session,
+        crate::api::auth::controller::refresh_session,
+        crate::api::auth::controller::logout,
+        crate::api::auth::controller::create_api_key,
+        crate::api::auth::controller::list_api_keys,
+        crate::api::auth::controller::revoke_api_key,
         crate::api::vectordb::collections::controller::create_collection,
         crate::api::vectordb::collections::controller::get_collections,
         crate::api::vectordb::collections::controller::get_collection_by_id,
@@ -258,14 +474,17 @@ pub struct StreamingApiDoc;
         crate::api::vectordb::collections::controller::delete_collection_by_id,
         crate::api::vectordb::collections::controller::load_collection,
         crate::api::vectordb::collections::controller::unload_collection,
+        crate::api::vectordb::collections::controller::compact_collection,
         crate::api::vectordb::collections::controller::get_loaded_collections,
         crate::api::vectordb::indexes::controller::create_dense_index,
         crate::api::vectordb::indexes::controller::create_sparse_index,
         crate::api::vectordb::indexes::controller::create_tf_idf_index,
         crate::api::vectordb::indexes::controller::get_index,
+        crate::api::vectordb::indexes::controller::get_tf_idf_stats,
         crate::api::vectordb::indexes::controller::delete_index,
         crate::api::vectordb::search::controller::dense_search,
         crate::api::vectordb::search::controller::batch_dense_search,
+        crate::api::vectordb::search::controller::multi_vector_search,
         crate::api::vectordb::search::controller::sparse_search,
         crate::api::vectordb::search::controller::batch_sparse_search,
         crate::api::vectordb::search::controller::hybrid_search,
@@ -273,6 +492,7 @@ pub struct StreamingApiDoc;
         crate::api::vectordb::search::controller::tf_idf_search,
         crate::api::vectordb::search::controller::batch_tf_idf_search,
         crate::api::vectordb::vectors::controller::query_vectors,
+        crate::api::vectordb::vectors::controller::scroll_vectors,
         crate::api::vectordb::vectors::controller::get_vector_by_id,
         crate::api::vectordb::vectors::controller::check_vector_existence,
         crate::api::vectordb::vectors::controller::fetch_vector_neighbors,
@@ -284,16 +504,49 @@ pub struct StreamingApiDoc;
         crate::api::vectordb::transactions::controller::get_transaction_status,
         crate::api::vectordb::transactions::controller::create_vector_in_transaction,
         crate::api::vectordb::transactions::controller::delete_vector_by_id,
+        crate::api::vectordb::transactions::controller::delete_vectors,
         crate::api::vectordb::transactions::controller::abort_transaction,
         crate::api::vectordb::transactions::controller::upsert,
         crate::api::vectordb::streaming::controller::upsert,
-        crate::api::vectordb::streaming::controller::delete_vector_by_id
+        crate::api::vectordb::streaming::controller::delete_vector_by_id,
+        crate::api::vectordb::streaming::controller::delete_vectors,
+        crate::api::vectordb::admin::controller::get_storage_usage,
+        crate::api::vectordb::admin::controller::rotate_admin_key,
+        crate::api::vectordb::tenants::controller::create_tenant,
+        crate::api::vectordb::tenants::controller::list_tenants,
+        crate::api::vectordb::tenants::controller::get_tenant,
+        crate::api::vectordb::tenants::controller::delete_tenant,
+        crate::api::vectordb::tenants::controller::update_tenant_quotas,
+        crate::api::vectordb::tenants::controller::create_tenant_user,
+        crate::api::vectordb::tenants::controller::remove_tenant_user,
+        crate::api::vectordb::snapshots::controller::create_snapshot,
+        crate::api::vectordb::snapshots::controller::list_snapshots,
+        crate::api::vectordb::snapshots::controller::download_snapshot,
+        crate::api::vectordb::snapshots::controller::upload_snapshot,
+        crate::api::vectordb::snapshots::controller::delete_snapshot,
+        crate::api::vectordb::snapshots::controller::restore_snapshot,
+        crate::api::vectordb::imports::controller::import_file,
+        crate::api::vectordb::imports::controller::import_upload,
+        crate::api::vectordb::imports::controller::get_import,
+        crate::api::vectordb::metadata_schema::controller::update_metadata_schema,
+        crate::api::vectordb::metadata_schema::controller::get_reindex,
+        crate::api::vectordb::replication::controller::get_replication_status,
+        crate::api::vectordb::replication::controller::list_replicated_collections,
+        crate::api::vectordb::replication::controller::get_versions,
+        crate::api::vectordb::replication::controller::download_snapshot,
+        crate::api::monitoring::controller::get_metrics,
+        crate::api::monitoring::controller::get_health,
+        crate::api::monitoring::controller::get_readiness,
+        crate::api::monitoring::controller::get_info
     ),
     components(
         schemas(
             crate::api::auth::dtos::CreateSessionDTO,
             crate::api::auth::dtos::Session,
             crate::api::auth::dtos::Claims,
+            crate::api::auth::dtos::CreateApiKeyDto,
+            crate::api::auth::dtos::CreatedApiKey,
+            crate::api::auth::dtos::ApiKeyInfo,
             crate::api::vectordb::collections::dtos::CreateCollectionDto,
             crate::api::vectordb::collections::dtos::CreateCollectionDtoResponse,
             crate::api::vectordb::collections::dtos::GetCollectionsDto,
@@ -302,9 +555,12 @@ pub struct StreamingApiDoc;
             crate::api::vectordb::collections::dtos::MetadataSchemaParam,
             crate::api::vectordb::collections::dtos::SupportedCondition,
             crate::api::vectordb::collections::dtos::ConditionOp,
+            crate::api::vectordb::collections::dtos::CompactCollectionDto,
+            crate::api::vectordb::collections::dtos::CompactCollectionResponseDto,
             crate::models::collection::CollectionConfig,
             crate::models::collection::DenseVectorOptions,
             crate::models::collection::SparseVectorOptions,
+            crate::models::embeddings::EmbeddingConfig,
             crate::models::collection::TFIDFOptions,
             CollectionIndexingStatusResponse,
             crate::api::vectordb::indexes::dtos::CreateDenseIndexDto,
@@ -324,12 +580,15 @@ pub struct StreamingApiDoc;
             crate::api::vectordb::indexes::dtos::DenseIndexInfo,
             crate::api::vectordb::indexes::dtos::SparseIndexInfo,
             crate::api::vectordb::indexes::dtos::TfIdfIndexInfo,
+            crate::api::vectordb::indexes::dtos::TfIdfCorpusStatsDto,
+            crate::api::vectordb::indexes::dtos::TfIdfTermStatsDto,
             crate::api::vectordb::indexes::dtos::QuantizationInfo,
             crate::api::vectordb::indexes::dtos::RangeInfo,
             crate::api::vectordb::indexes::dtos::HnswParamsInfo,
             crate::api::vectordb::search::dtos::DenseSearchRequestDto,
             crate::api::vectordb::search::dtos::BatchDenseSearchRequestDto,
             crate::api::vectordb::search::dtos::BatchDenseSearchRequestQueryDto,
+            crate::api::vectordb::search::dtos::MultiVectorSearchRequestDto,
             crate::api::vectordb::search::dtos::SparseSearchRequestDto,
             crate::api::vectordb::search::dtos::BatchSparseSearchRequestDto,
             crate::api::vectordb::search::dtos::HybridSearchRequestDto,
@@ -337,19 +596,64 @@ pub struct StreamingApiDoc;
             crate::api::vectordb::search::dtos::HybridSearchQuery,
             crate::api::vectordb::search::dtos::FindSimilarTFIDFDocumentDto,
             crate::api::vectordb::search::dtos::BatchSearchTFIDFDocumentsDto,
+            crate::api::vectordb::search::dtos::GroupingDto,
+            crate::api::vectordb::search::dtos::GroupBy,
+            crate::api::vectordb::search::dtos::GroupAggregation,
+            crate::api::vectordb::search::dtos::ResultFieldsDto,
             crate::api::vectordb::search::dtos::SearchResultItemDto,
+            crate::api::vectordb::search::dtos::SearchResultGroupDto,
+            crate::api::vectordb::search::dtos::ScoreExplanationDto,
+            crate::api::vectordb::search::dtos::BM25TermExplanationDto,
+            crate::api::vectordb::search::dtos::SparseTermExplanationDto,
             crate::api::vectordb::search::dtos::SearchResponseDto,
             crate::api::vectordb::search::dtos::BatchSearchResponseDto,
             crate::api::vectordb::vectors::dtos::VectorsQueryDto,
             crate::api::vectordb::vectors::dtos::CreateVectorDto,
             crate::api::vectordb::vectors::dtos::SimilarVector,
+            crate::api::vectordb::vectors::dtos::VectorField,
+            crate::api::vectordb::vectors::dtos::ScrollVectorsDto,
+            crate::api::vectordb::vectors::dtos::ScrollVectorsResponseDto,
             crate::api::vectordb::versions::dtos::VersionMetadata,
             crate::api::vectordb::versions::dtos::VersionListResponse,
             crate::api::vectordb::versions::dtos::CurrentVersionResponse,
             crate::api::vectordb::transactions::dtos::CreateTransactionResponseDto,
             crate::api::vectordb::transactions::dtos::UpsertDto,
+            crate::api::vectordb::transactions::dtos::DeleteVectorsDto,
+            crate::api::vectordb::transactions::dtos::DeleteVectorsResponseDto,
             crate::models::collection_transaction::TransactionStatus,
-            crate::models::collection_transaction::ProcessingStats
+            crate::models::collection_transaction::ConflictResolution,
+            crate::models::collection_transaction::ProcessingStats,
+            crate::api::vectordb::admin::dtos::StorageUsageDto,
+            crate::api::vectordb::admin::dtos::RotateAdminKeyDto,
+            crate::api::vectordb::tenants::dtos::CreateTenantDto,
+            crate::api::vectordb::tenants::dtos::TenantDto,
+            crate::api::vectordb::tenants::dtos::TenantUsageDto,
+            crate::api::vectordb::tenants::dtos::CreateTenantUserDto,
+            crate::models::tenants::TenantQuotas,
+            crate::api::vectordb::snapshots::dtos::CreateSnapshotDto,
+            crate::api::vectordb::snapshots::dtos::SnapshotDto,
+            crate::api::vectordb::snapshots::dtos::RestoreSnapshotDto,
+            crate::api::vectordb::snapshots::dtos::RestoreSnapshotResponseDto,
+            crate::api::vectordb::imports::dtos::ImportFormat,
+            crate::api::vectordb::imports::dtos::ImportFileDto,
+            crate::api::vectordb::imports::dtos::ImportUploadQueryDto,
+            crate::api::vectordb::imports::dtos::ImportState,
+            crate::api::vectordb::imports::dtos::ImportJobDto,
+            crate::api::vectordb::metadata_schema::dtos::UpdateMetadataSchemaDto,
+            crate::api::vectordb::metadata_schema::dtos::UpdateMetadataSchemaResponseDto,
+            crate::api::vectordb::metadata_schema::dtos::ReindexState,
+            crate::api::vectordb::metadata_schema::dtos::ReindexJobDto,
+            crate::api::vectordb::replication::dtos::ReplicationStatusDto,
+            crate::api::vectordb::replication::dtos::CollectionReplicationDto,
+            crate::api::vectordb::replication::dtos::FollowerStatusDto,
+            crate::api::vectordb::replication::dtos::FollowerCollectionDto,
+            crate::models::replication::ReplicatedCollection,
+            crate::models::replication::ReplicatedVersion,
+            crate::models::replication::ReplicationBatch,
+            crate::api::monitoring::dtos::HealthDto,
+            crate::api::monitoring::dtos::ReadinessDto,
+            crate::api::monitoring::dtos::ServerInfoDto,
+            crate::api::monitoring::dtos::ConfigSummaryDto
         )
     ),
     tags(
@@ -360,7 +664,14 @@ pub struct StreamingApiDoc;
         (name = "vectors", description = "Vector management endpoints"),
         (name = "versions", description = "Version management endpoints"),
         (name = "transactions", description = "Transaction management endpoints"),
-        (name = "streaming", description = "Streaming endpoints")
+        (name = "streaming", description = "Streaming endpoints"),
+        (name = "admin", description = "Admin endpoints"),
+        (name = "tenants", description = "Tenant management endpoints"),
+        (name = "snapshots", description = "Collection snapshot endpoints"),
+        (name = "imports", description = "Bulk import endpoints"),
+        (name = "metadata_schema", description = "Metadata schema endpoints"),
+        (name = "replication", description = "Leader-follower replication endpoints"),
+        (name = "monitoring", description = "Monitoring endpoints")
     ),
     modifiers(&CombinedApiDoc)
 )]
@@ -414,6 +725,48 @@ impl utoipa::Modify for StreamingApiDoc {
     }
 }
 
+impl utoipa::Modify for AdminApiDoc {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info = api_info();
+    }
+}
+
+impl utoipa::Modify for TenantsApiDoc {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info = api_info();
+    }
+}
+
+impl utoipa::Modify for ImportsApiDoc {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info = api_info();
+    }
+}
+
+impl utoipa::Modify for MetadataSchemaApiDoc {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info = api_info();
+    }
+}
+
+impl utoipa::Modify for SnapshotsApiDoc {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info = api_info();
+    }
+}
+
+impl utoipa::Modify for ReplicationApiDoc {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info = api_info();
+    }
+}
+
+impl utoipa::Modify for MonitoringApiDoc {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info = api_info();
+    }
+}
+
 impl utoipa::Modify for CombinedApiDoc {
     fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
         openapi.info = api_info();
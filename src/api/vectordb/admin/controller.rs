@@ -0,0 +1,66 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{api::auth::dtos::Claims, app_context::AppContext, models::types::ADMIN_USERNAME};
+
+use super::{
+    dtos::{RotateAdminKeyDto, StorageUsageDto},
+    error::AdminError,
+    service,
+};
+
+/// Get storage usage
+///
+/// Reports how much of the LMDB memory map holding collection metadata,
+/// versions and users is in use. Only available to the admin user.
+#[utoipa::path(
+    get,
+    path = "/vectordb/admin/storage",
+    responses(
+        (status = 200, description = "Storage usage retrieved successfully", body = StorageUsageDto),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "admin"
+)]
+pub(crate) async fn get_storage_usage(
+    claims: Claims,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, AdminError> {
+    if claims.username != ADMIN_USERNAME {
+        return Err(AdminError::Forbidden);
+    }
+    let usage = service::get_storage_usage(ctx.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+/// Rotate the admin key
+///
+/// Replaces the admin key, which is also the admin user's password. All
+/// active sessions are ended, the admin's API keys are revoked, and the
+/// server must be started with the new key from now on. Only available to
+/// the admin user.
+#[utoipa::path(
+    post,
+    path = "/vectordb/admin/rotate-key",
+    request_body = RotateAdminKeyDto,
+    responses(
+        (status = 204, description = "Admin key rotated successfully"),
+        (status = 400, description = "Invalid new admin key", body = serde_json::Value),
+        (status = 401, description = "Invalid authentication token or wrong old admin key", body = serde_json::Value),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "admin"
+)]
+pub(crate) async fn rotate_admin_key(
+    claims: Claims,
+    web::Json(rotate_admin_key_dto): web::Json<RotateAdminKeyDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, AdminError> {
+    if claims.username != ADMIN_USERNAME {
+        return Err(AdminError::Forbidden);
+    }
+    service::rotate_admin_key(rotate_admin_key_dto, ctx.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Usage of the LMDB environment holding collection metadata, versions and
+/// users
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct StorageUsageDto {
+    /// Current size of the memory map in bytes
+    pub map_size: usize,
+    /// Bytes of the memory map in use
+    pub used_size: usize,
+    /// Percentage of the memory map in use
+    pub used_percent: f32,
+    /// Size in bytes the memory map is never grown beyond, unbounded if absent
+    pub max_map_size: Option<usize>,
+    pub page_size: u32,
+    /// Maximum number of named databases
+    pub max_dbs: u32,
+    pub max_readers: u32,
+    /// Number of reader slots in use
+    pub readers: u32,
+}
+
+/// DTO for replacing the admin key
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct RotateAdminKeyDto {
+    /// The admin key the server is currently running with
+    pub old_key: String,
+    /// The admin key to replace it with, the server must be started with
+    /// this key from now on
+    pub new_key: String,
+}
@@ -0,0 +1,53 @@
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    HttpResponse, ResponseError,
+};
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum AdminError {
+    Forbidden,
+    WrongAdminKey,
+    InvalidParams(String),
+    DatabaseError(String),
+}
+
+impl Display for AdminError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Forbidden => write!(f, "Only the admin user can access this endpoint"),
+            Self::WrongAdminKey => write!(f, "The old admin key is incorrect"),
+            Self::InvalidParams(msg) => write!(f, "Invalid params: {}", msg),
+            Self::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl ResponseError for AdminError {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let message = self.to_string();
+        HttpResponse::build(status)
+            .insert_header(ContentType::json())
+            .json(serde_json::json!({
+                "error": status.canonical_reason().unwrap_or("Error"),
+                "code": status.as_u16(),
+                "message": message
+            }))
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::WrongAdminKey => StatusCode::UNAUTHORIZED,
+            Self::InvalidParams(_) => StatusCode::BAD_REQUEST,
+            Self::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<lmdb::Error> for AdminError {
+    fn from(err: lmdb::Error) -> Self {
+        Self::DatabaseError(err.to_string())
+    }
+}
@@ -0,0 +1,12 @@
+use actix_web::{web, Scope};
+
+pub mod controller;
+pub(crate) mod dtos;
+mod error;
+mod service;
+
+pub(crate) fn admin_module() -> Scope {
+    web::scope("/admin")
+        .route("/storage", web::get().to(controller::get_storage_usage))
+        .route("/rotate-key", web::post().to(controller::rotate_admin_key))
+}
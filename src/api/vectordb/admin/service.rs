@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use crate::{app_context::AppContext, models::lmdb_map};
+
+use super::{
+    dtos::{RotateAdminKeyDto, StorageUsageDto},
+    error::AdminError,
+};
+
+pub(crate) async fn get_storage_usage(ctx: Arc<AppContext>) -> Result<StorageUsageDto, AdminError> {
+    let usage = lmdb_map::map_usage(&ctx.ain_env.persist)?;
+
+    Ok(StorageUsageDto {
+        map_size: usage.map_size,
+        used_size: usage.used_size,
+        used_percent: usage.used_size as f32 / usage.map_size as f32 * 100.0,
+        max_map_size: usage.max_map_size,
+        page_size: usage.page_size,
+        max_dbs: ctx.config.lmdb.max_dbs,
+        max_readers: usage.max_readers,
+        readers: usage.readers,
+    })
+}
+
+pub(crate) async fn rotate_admin_key(
+    rotate_admin_key_dto: RotateAdminKeyDto,
+    ctx: Arc<AppContext>,
+) -> Result<(), AdminError> {
+    if rotate_admin_key_dto.new_key.is_empty() {
+        return Err(AdminError::InvalidParams(
+            "new admin key must not be empty".to_string(),
+        ));
+    }
+    if rotate_admin_key_dto.new_key == rotate_admin_key_dto.old_key {
+        return Err(AdminError::InvalidParams(
+            "new admin key must differ from the old one".to_string(),
+        ));
+    }
+
+    let rotated = ctx
+        .ain_env
+        .rotate_admin_key(&rotate_admin_key_dto.old_key, &rotate_admin_key_dto.new_key)?;
+    if !rotated {
+        return Err(AdminError::WrongAdminKey);
+    }
+    Ok(())
+}
@@ -1,12 +1,18 @@
 use actix_web::{web, HttpResponse, Result};
 
-use crate::app_context::AppContext;
+use crate::{
+    api::vectordb::tenants::tenant_scope_middleware::TenantScope,
+    app_context::AppContext,
+    models::tenants::{qualified_collection_name, split_collection_name, TENANT_SEPARATOR},
+};
 
 use super::{
     dtos::{
-        CollectionWithVectorCountsDto, CreateCollectionDto, CreateCollectionDtoResponse,
-        GetCollectionsDto, GetCollectionsResponseDto,
+        CollectionWithVectorCountsDto, CompactCollectionDto, CompactCollectionResponseDto,
+        CreateCollectionDto, CreateCollectionDtoResponse, GetCollectionsDto,
+        GetCollectionsResponseDto,
     },
+    error::CollectionsError,
     service,
 };
 use crate::api::openapi::CollectionIndexingStatusResponse;
@@ -28,11 +34,31 @@ use crate::api::openapi::CollectionIndexingStatusResponse;
     tag = "collections"
 )]
 pub(crate) async fn create_collection(
-    web::Json(create_collection_dto): web::Json<CreateCollectionDto>,
+    web::Json(mut create_collection_dto): web::Json<CreateCollectionDto>,
+    tenant: Option<web::ReqData<TenantScope>>,
     ctx: web::Data<AppContext>,
 ) -> Result<HttpResponse> {
-    let create_collection_response_dto =
+    if let Some(tenant) = &tenant {
+        if create_collection_dto.name.contains(TENANT_SEPARATOR) {
+            return Err(CollectionsError::FailedToCreateCollection(format!(
+                "collection names must not contain '{}'",
+                TENANT_SEPARATOR
+            ))
+            .into());
+        }
+        create_collection_dto.name =
+            qualified_collection_name(&tenant.0, &create_collection_dto.name);
+    }
+
+    let mut create_collection_response_dto =
         service::create_collection(ctx.into_inner(), create_collection_dto).await?;
+    if tenant.is_some() {
+        let name = split_collection_name(&create_collection_response_dto.name)
+            .1
+            .to_string();
+        create_collection_response_dto.id = name.clone();
+        create_collection_response_dto.name = name;
+    }
 
     Ok(HttpResponse::Created().json(create_collection_response_dto))
 }
@@ -54,9 +80,19 @@ pub(crate) async fn create_collection(
 )]
 pub(crate) async fn get_collections(
     web::Query(get_collections_dto): web::Query<GetCollectionsDto>,
+    tenant: Option<web::ReqData<TenantScope>>,
     ctx: web::Data<AppContext>,
 ) -> Result<HttpResponse> {
-    let collections = service::get_collections(ctx.into_inner(), get_collections_dto).await?;
+    let mut collections = service::get_collections(ctx.into_inner(), get_collections_dto).await?;
+    if let Some(tenant) = tenant {
+        collections.retain_mut(|collection| match split_collection_name(&collection.name) {
+            (Some(collection_tenant), name) if collection_tenant == tenant.0 => {
+                collection.name = name.to_string();
+                true
+            }
+            _ => false,
+        });
+    }
     Ok(HttpResponse::Ok().json(collections))
 }
 
@@ -78,10 +114,16 @@ pub(crate) async fn get_collections(
 )]
 pub(crate) async fn get_collection_by_id(
     collection_id: web::Path<String>,
+    tenant: Option<web::ReqData<TenantScope>>,
     ctx: web::Data<AppContext>,
 ) -> Result<HttpResponse> {
-    let collection_with_counts =
+    let mut collection_with_counts =
         service::get_collection_by_id(ctx.into_inner(), &collection_id).await?;
+    if tenant.is_some() {
+        collection_with_counts.name = split_collection_name(&collection_with_counts.name)
+            .1
+            .to_string();
+    }
     Ok(HttpResponse::Ok().json(collection_with_counts))
 }
 
@@ -146,6 +188,45 @@ pub(crate) async fn delete_collection_by_id(
     Ok(HttpResponse::NoContent().finish())
 }
 
+/// Compact collection
+///
+/// Removes the versions that aren't retained, by the configured retention
+/// policy or the rules given in the request, and reclaims the disk space of
+/// their id mappings. The indexes are rebuilt without the deleted vectors,
+/// next to the current ones which keep serving requests until they're
+/// replaced. The request body may be left empty.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/compact",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier")
+    ),
+    request_body(content = Option<CompactCollectionDto>, description = "Retention rules overriding the configured ones"),
+    responses(
+        (status = 200, description = "Collection compacted", body = CompactCollectionResponseDto),
+        (status = 400, description = "Collection not found or malformed request body"),
+        (status = 409, description = "Collection has a transaction in progress or is already being compacted"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "collections"
+)]
+pub(crate) async fn compact_collection(
+    collection_id: web::Path<String>,
+    body: web::Bytes,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse> {
+    let compact_collection_dto = if body.iter().all(u8::is_ascii_whitespace) {
+        CompactCollectionDto::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|e| CollectionsError::InvalidRequest(e.to_string()))?
+    };
+    let response =
+        service::compact_collection(ctx.into_inner(), &collection_id, compact_collection_dto)
+            .await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
 /// Load collection into memory
 ///
 /// Loads a collection into memory for faster access.
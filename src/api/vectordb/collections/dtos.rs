@@ -2,6 +2,7 @@ use crate::metadata;
 use crate::models::collection::{
     CollectionConfig, DenseVectorOptions, SparseVectorOptions, TFIDFOptions,
 };
+use crate::models::versioning::VersionNumber;
 use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToSchema};
 
@@ -156,3 +157,28 @@ pub(crate) struct CollectionWithVectorCountsDto {
     pub store_raw_text: bool,
     pub vectors_count: u64,
 }
+
+/// Retention rules overriding the configured ones for a single compaction,
+/// versions are kept if either rule retains them
+#[derive(Deserialize, Debug, Default, ToSchema)]
+pub(crate) struct CompactCollectionDto {
+    /// Number of most recent versions to keep
+    pub keep_versions: Option<u32>,
+    /// Keep the versions created within this many hours
+    pub keep_hours: Option<u64>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub(crate) struct CompactCollectionResponseDto {
+    /// Whether there were any versions to remove
+    pub compacted: bool,
+    /// Oldest version still available, if any were removed
+    pub retained_from_version: Option<VersionNumber>,
+    pub versions_removed: u32,
+    /// Whether the indexes were rebuilt without the deleted vectors
+    pub indexes_rebuilt: bool,
+    /// Size in bytes of the collection's files before the compaction
+    pub disk_usage_before: u64,
+    /// Size in bytes of the collection's files after the compaction
+    pub disk_usage_after: u64,
+}
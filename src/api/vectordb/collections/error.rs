@@ -1,4 +1,4 @@
-use crate::models::common::WaCustomError;
+use crate::models::{common::WaCustomError, tenants::QuotaError};
 use actix_web::{
     http::{header::ContentType, StatusCode},
     HttpResponse, ResponseError,
@@ -14,6 +14,9 @@ pub enum CollectionsError {
     FailedToCreateCollection(String),
     WaCustomError(WaCustomError),
     ServerError(String),
+    QuotaExceeded(String),
+    Busy(String),
+    InvalidRequest(String),
 }
 
 impl Display for CollectionsError {
@@ -29,6 +32,9 @@ impl Display for CollectionsError {
             }
             CollectionsError::WaCustomError(e) => write!(f, "LMDB database error: {e:?}"),
             CollectionsError::ServerError(e) => write!(f, "Server error: {e}"),
+            CollectionsError::QuotaExceeded(msg) => write!(f, "Quota exceeded: {msg}"),
+            CollectionsError::Busy(msg) => write!(f, "Collection is busy: {msg}"),
+            CollectionsError::InvalidRequest(msg) => write!(f, "Invalid request: {msg}"),
         }
     }
 }
@@ -47,6 +53,19 @@ impl ResponseError for CollectionsError {
             CollectionsError::FailedToCreateCollection(_) => StatusCode::BAD_REQUEST,
             CollectionsError::WaCustomError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             CollectionsError::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            CollectionsError::QuotaExceeded(_) => StatusCode::FORBIDDEN,
+            CollectionsError::Busy(_) => StatusCode::CONFLICT,
+            CollectionsError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl From<QuotaError> for CollectionsError {
+    fn from(err: QuotaError) -> Self {
+        match err {
+            QuotaError::TenantNotFound(_) => Self::FailedToCreateCollection(err.to_string()),
+            QuotaError::Io(_) => Self::ServerError(err.to_string()),
+            err => Self::QuotaExceeded(err.to_string()),
         }
     }
 }
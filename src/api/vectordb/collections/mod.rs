@@ -30,4 +30,8 @@ pub(crate) fn collections_module() -> Scope {
             "/{collection_id}/unload",
             web::post().to(controller::unload_collection),
         )
+        .route(
+            "/{collection_id}/compact",
+            web::post().to(controller::compact_collection),
+        )
 }
@@ -2,11 +2,13 @@ use std::sync::Arc;
 
 use crate::{
     app_context::AppContext,
+    config_loader::RetentionConfig,
     models::{
-        collection::{Collection, CollectionIndexingStatus},
+        collection::{Collection, CollectionIndexingStatus, CompactionStats},
         common::WaCustomError,
         meta_persist::{update_background_version, update_current_version},
         types::MetaDb,
+        utils::dir_size,
         versioning::VersionControl,
     },
 };
@@ -33,6 +35,12 @@ pub(crate) async fn create_collection(
     if ctx.ain_env.collections_map.get_collection(&name).is_some() {
         return Err(CollectionsError::AlreadyExists(name));
     }
+    ctx.ain_env
+        .tenants_map
+        .check_collection_quota(&ctx.ain_env.collections_map, &name)?;
+    ctx.embedding_providers
+        .validate(&dense_vector, &sparse_vector)
+        .map_err(|err| CollectionsError::FailedToCreateCollection(err.to_string()))?;
 
     let env = &ctx.ain_env.persist;
     let collections_db = &ctx.ain_env.collections_map.lmdb_collections_db;
@@ -136,6 +144,31 @@ pub(crate) async fn get_collection_indexing_status(
         .map_err(CollectionsError::WaCustomError)
 }
 
+pub(crate) async fn compact_collection(
+    ctx: Arc<AppContext>,
+    name: &str,
+    policy: RetentionConfig,
+) -> Result<(Option<CompactionStats>, u64), CollectionsError> {
+    let collection = get_collection_by_name(ctx.clone(), name).await?;
+
+    let stats = match collection.compact(&ctx.ain_env.collections_map, &ctx.config, &policy) {
+        Ok(stats) => stats,
+        Err(WaCustomError::LockError(msg)) => return Err(CollectionsError::Busy(msg)),
+        Err(err) => return Err(CollectionsError::WaCustomError(err)),
+    };
+    if stats.is_some() {
+        ctx.collection_cache_manager
+            .reload_collection(name)
+            .map_err(CollectionsError::WaCustomError)?;
+    }
+    let disk_usage = match stats {
+        Some(stats) => stats.disk_usage_after,
+        None => dir_size(&collection.get_path())
+            .map_err(|e| CollectionsError::ServerError(e.to_string()))?,
+    };
+    Ok((stats, disk_usage))
+}
+
 pub(crate) async fn delete_collection_by_name(
     ctx: Arc<AppContext>,
     name: &str,
@@ -2,13 +2,15 @@ use std::sync::Arc;
 
 use crate::{
     app_context::AppContext,
+    config_loader::RetentionConfig,
     models::collection::{Collection, CollectionIndexingStatus},
 };
 
 use super::{
     dtos::{
-        CollectionWithVectorCountsDto, CreateCollectionDto, CreateCollectionDtoResponse,
-        GetCollectionsDto, GetCollectionsResponseDto,
+        CollectionWithVectorCountsDto, CompactCollectionDto, CompactCollectionResponseDto,
+        CreateCollectionDto, CreateCollectionDtoResponse, GetCollectionsDto,
+        GetCollectionsResponseDto,
     },
     error::CollectionsError,
     repo,
@@ -83,6 +85,48 @@ pub(crate) async fn delete_collection_by_id(
     Ok(collection)
 }
 
+/// compacts a collection with the configured retention policy, unless the
+/// request specifies its own retention rules
+pub(crate) async fn compact_collection(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    compact_collection_dto: CompactCollectionDto,
+) -> Result<CompactCollectionResponseDto, CollectionsError> {
+    let CompactCollectionDto {
+        keep_versions,
+        keep_hours,
+    } = compact_collection_dto;
+    let policy = if keep_versions.is_some() || keep_hours.is_some() {
+        RetentionConfig {
+            keep_versions,
+            keep_hours,
+            ..ctx.config.retention
+        }
+    } else {
+        ctx.config.retention
+    };
+
+    let (stats, disk_usage) = repo::compact_collection(ctx, collection_id, policy).await?;
+    Ok(match stats {
+        Some(stats) => CompactCollectionResponseDto {
+            compacted: true,
+            retained_from_version: Some(stats.retained_from),
+            versions_removed: stats.versions_removed,
+            indexes_rebuilt: stats.indexes_rebuilt,
+            disk_usage_before: stats.disk_usage_before,
+            disk_usage_after: stats.disk_usage_after,
+        },
+        None => CompactCollectionResponseDto {
+            compacted: false,
+            retained_from_version: None,
+            versions_removed: 0,
+            indexes_rebuilt: false,
+            disk_usage_before: disk_usage,
+            disk_usage_after: disk_usage,
+        },
+    })
+}
+
 pub(crate) async fn load_collection(
     ctx: Arc<AppContext>,
     collection_id: &str,
@@ -0,0 +1,135 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    api::auth::dtos::Claims,
+    app_context::AppContext,
+    models::{
+        collection_cache::CollectionCacheExt, collection_transaction::ExplicitTransactionID,
+        types::ADMIN_USERNAME,
+    },
+};
+
+use super::{
+    dtos::{ImportFileDto, ImportFormat, ImportJobDto, ImportUploadQueryDto},
+    error::ImportError,
+    service,
+};
+
+/// Import a server-side file
+///
+/// Imports the vectors of a file in the server's import directory, which is
+/// `imports` in the data directory unless configured, and may be a JSONL
+/// file with one vector per line in the upsert format, a Parquet file with
+/// an id column and a list of floats vector column, or a NumPy `.npy` or
+/// `.fvecs` file of dense vectors. The vectors are written to a new explicit
+/// transaction in the background, which is committed once the whole file is
+/// read, and whose status reports the progress. Only available to the admin
+/// user.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/import",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier")
+    ),
+    request_body = ImportFileDto,
+    responses(
+        (status = 202, description = "Import started", body = ImportJobDto),
+        (status = 400, description = "Invalid file or format, or a file outside the import directory"),
+        (status = 403, description = "Only the admin user can import server-side files"),
+        (status = 404, description = "Collection not found"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "imports"
+)]
+pub(crate) async fn import_file(
+    claims: Claims,
+    collection_id: web::Path<String>,
+    body: web::Json<ImportFileDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, ImportError> {
+    if claims.username != ADMIN_USERNAME {
+        return Err(ImportError::Forbidden);
+    }
+    // checked before the cache, which fails for missing collections, and
+    // before the file is probed
+    ctx.ain_env
+        .collections_map
+        .get_collection(&collection_id)
+        .ok_or(ImportError::CollectionNotFound)?;
+    ctx.update_collection_for_transaction(&collection_id)
+        .map_err(|e| ImportError::ServerError(format!("Cache error: {}", e)))?;
+    let job = service::import_file(ctx.into_inner(), &collection_id, body.into_inner()).await?;
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+/// Import an uploaded file
+///
+/// Imports the vectors of the file in the request body, in any of the
+/// formats accepted when importing a server-side file. The vectors of NumPy
+/// and `.fvecs` files are numbered from 0.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/import/upload",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        ("format" = ImportFormat, Query, description = "Format of the file"),
+        ("id_column" = Option<String>, Query, description = "Parquet column holding the vector ids, `id` by default"),
+        ("vector_column" = Option<String>, Query, description = "Parquet column holding the dense vectors, `vector` by default")
+    ),
+    request_body(content = Vec<u8>, description = "File to import", content_type = "application/octet-stream"),
+    responses(
+        (status = 202, description = "Import started", body = ImportJobDto),
+        (status = 400, description = "Invalid file or format"),
+        (status = 404, description = "Collection not found"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "imports"
+)]
+pub(crate) async fn import_upload(
+    collection_id: web::Path<String>,
+    query: web::Query<ImportUploadQueryDto>,
+    payload: web::Payload,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, ImportError> {
+    // checked before the cache, which fails for missing collections, and
+    // before the upload is read
+    ctx.ain_env
+        .collections_map
+        .get_collection(&collection_id)
+        .ok_or(ImportError::CollectionNotFound)?;
+    ctx.update_collection_for_transaction(&collection_id)
+        .map_err(|e| ImportError::ServerError(format!("Cache error: {}", e)))?;
+    let job = service::import_upload(
+        ctx.into_inner(),
+        &collection_id,
+        query.into_inner(),
+        payload,
+    )
+    .await?;
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+/// Get an import
+///
+/// Returns the progress of an import started since the server started, and
+/// the error it failed with, if any.
+#[utoipa::path(
+    get,
+    path = "/vectordb/collections/{collection_id}/import/{transaction_id}",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction the import writes to")
+    ),
+    responses(
+        (status = 200, description = "Import retrieved successfully", body = ImportJobDto),
+        (status = 404, description = "Import not found")
+    ),
+    tag = "imports"
+)]
+pub(crate) async fn get_import(
+    params: web::Path<(String, ExplicitTransactionID)>,
+) -> Result<HttpResponse, ImportError> {
+    let (collection_id, transaction_id) = params.into_inner();
+    let job = service::get_import(&collection_id, transaction_id).await?;
+    Ok(HttpResponse::Ok().json(job))
+}
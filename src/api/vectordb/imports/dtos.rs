@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::collection_transaction::ExplicitTransactionID;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ImportFormat {
+    /// One vector per line, in the format accepted by the upsert endpoints
+    Jsonl,
+    /// One vector per row, with the vector in a list of floats column
+    Parquet,
+    /// A 2-dimensional little-endian `float32` or `float64` NumPy array
+    Npy,
+    /// Vectors stored as their dimension followed by their `float32` values
+    Fvecs,
+}
+
+impl ImportFormat {
+    /// The format of a file, from its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            "parquet" => Some(Self::Parquet),
+            "npy" => Some(Self::Npy),
+            "fvecs" => Some(Self::Fvecs),
+            _ => None,
+        }
+    }
+}
+
+/// DTO for importing a file stored on the server
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct ImportFileDto {
+    /// Path of the file, relative to the server's import directory, or an
+    /// absolute path inside it
+    pub path: String,
+    /// Format of the file, inferred from its extension if omitted
+    pub format: Option<ImportFormat>,
+    /// Parquet column holding the vector ids, `id` by default
+    pub id_column: Option<String>,
+    /// Parquet column holding the dense vectors, `vector` by default
+    pub vector_column: Option<String>,
+    /// Path of a file in the import directory holding the ids of NumPy or
+    /// fvecs vectors, one per line. Vectors are numbered from 0 if omitted.
+    pub ids_path: Option<String>,
+}
+
+/// Query parameters for importing an uploaded file
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct ImportUploadQueryDto {
+    pub format: ImportFormat,
+    /// Parquet column holding the vector ids, `id` by default
+    pub id_column: Option<String>,
+    /// Parquet column holding the dense vectors, `vector` by default
+    pub vector_column: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ImportState {
+    /// The file is being written to the transaction
+    Reading,
+    /// The transaction was committed and is being indexed
+    Committed,
+    /// The import failed and its transaction was aborted
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct ImportJobDto {
+    /// The explicit transaction the vectors are written to
+    pub transaction_id: ExplicitTransactionID,
+    pub format: ImportFormat,
+    pub state: ImportState,
+    pub records_read: u32,
+    pub percentage_read: f32,
+    pub error: Option<String>,
+    #[schema(value_type = String, example = "2023-01-01T12:00:00Z")]
+    pub started_at: DateTime<Utc>,
+    #[schema(value_type = Option<String>, example = "2023-01-01T12:00:00Z")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,66 @@
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    HttpResponse, ResponseError,
+};
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Forbidden,
+    CollectionNotFound,
+    JobNotFound,
+    InvalidRequest(String),
+    InvalidData(String),
+    QuotaExceeded(String),
+    // the import's transaction was aborted or committed by another request
+    TransactionClosed,
+    ServerError(String),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Forbidden => write!(f, "Only the admin user can import server-side files"),
+            Self::CollectionNotFound => write!(f, "Collection not found"),
+            Self::JobNotFound => write!(f, "Import not found"),
+            Self::InvalidRequest(msg) => write!(f, "{}", msg),
+            Self::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
+            Self::QuotaExceeded(msg) => write!(f, "Quota exceeded: {}", msg),
+            Self::TransactionClosed => write!(
+                f,
+                "The import's transaction was closed before the import finished"
+            ),
+            Self::ServerError(msg) => write!(f, "Server error: {}", msg),
+        }
+    }
+}
+
+impl ResponseError for ImportError {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let message = self.to_string();
+        HttpResponse::build(status)
+            .insert_header(ContentType::json())
+            .json(serde_json::json!({
+                "error": status.canonical_reason().unwrap_or("Error"),
+                "code": status.as_u16(),
+                "message": message
+            }))
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Forbidden | Self::QuotaExceeded(_) => StatusCode::FORBIDDEN,
+            Self::CollectionNotFound | Self::JobNotFound => StatusCode::NOT_FOUND,
+            Self::TransactionClosed => StatusCode::CONFLICT,
+            Self::InvalidRequest(_) | Self::InvalidData(_) => StatusCode::BAD_REQUEST,
+            Self::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(error: std::io::Error) -> Self {
+        Self::ServerError(error.to_string())
+    }
+}
@@ -0,0 +1,14 @@
+use actix_web::{web, Scope};
+
+pub mod controller;
+pub(crate) mod dtos;
+mod error;
+mod readers;
+mod service;
+
+pub(crate) fn imports_module() -> Scope {
+    web::scope("/collections/{collection_id}/import")
+        .route("", web::post().to(controller::import_file))
+        .route("/upload", web::post().to(controller::import_upload))
+        .route("/{transaction_id}", web::get().to(controller::get_import))
+}
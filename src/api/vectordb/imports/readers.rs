@@ -0,0 +1,652 @@
+// Readers of the file formats vectors are imported from. Records are read
+// one at a time, so that files larger than the memory can be imported.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use parquet::{
+    file::reader::{FileReader, SerializedFileReader},
+    record::{reader::RowIter, Field},
+};
+
+use crate::{
+    api::vectordb::vectors::dtos::CreateVectorDto,
+    models::{
+        collection::RawVectorEmbedding,
+        types::{DocumentId, VectorId},
+    },
+};
+
+use super::{dtos::ImportFormat, error::ImportError};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+const DEFAULT_ID_COLUMN: &str = "id";
+const DEFAULT_VECTOR_COLUMN: &str = "vector";
+
+pub(crate) trait RecordReader: Send {
+    /// Reads the next record, `None` once the whole file is read
+    fn next_record(&mut self) -> Result<Option<RawVectorEmbedding>, ImportError>;
+
+    /// Share of the file read so far, from 0 to 100
+    fn percentage_read(&self) -> f32;
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ReaderOptions {
+    pub id_column: Option<String>,
+    pub vector_column: Option<String>,
+    pub ids_path: Option<PathBuf>,
+}
+
+/// Opens the file at `path`, failing if it isn't a valid `format` file
+pub(crate) fn open_reader(
+    path: &Path,
+    format: ImportFormat,
+    options: ReaderOptions,
+) -> Result<Box<dyn RecordReader>, ImportError> {
+    let file = open_file(path)?;
+    let len = file.metadata()?.len();
+    let ids = match &options.ids_path {
+        Some(ids_path) => Ids::File(BufReader::new(open_file(ids_path)?)),
+        None => Ids::Rows,
+    };
+
+    Ok(match format {
+        ImportFormat::Jsonl => Box::new(JsonlReader {
+            reader: BufReader::new(file),
+            line: 0,
+            read: 0,
+            len,
+        }),
+        ImportFormat::Parquet => Box::new(ParquetReader::new(file, options)?),
+        ImportFormat::Npy => Box::new(NpyReader::new(file, ids)?),
+        ImportFormat::Fvecs => Box::new(FvecsReader {
+            reader: BufReader::new(file),
+            ids,
+            row: 0,
+            read: 0,
+            len,
+        }),
+    })
+}
+
+fn open_file(path: &Path) -> Result<File, ImportError> {
+    File::open(path).map_err(|err| {
+        ImportError::InvalidRequest(format!("Failed to open '{}': {}", path.display(), err))
+    })
+}
+
+fn percentage(read: u64, total: u64) -> f32 {
+    if total == 0 {
+        return 100.0;
+    }
+    read as f32 * 100.0 / total as f32
+}
+
+fn dense_embedding(id: VectorId, dense_values: Vec<f32>) -> RawVectorEmbedding {
+    RawVectorEmbedding {
+        id,
+        document_id: None,
+        dense_values: Some(dense_values),
+        metadata: None,
+        sparse_values: None,
+        text: None,
+        expires_at: None,
+    }
+}
+
+fn truncated(err: io::Error, row: u64) -> ImportError {
+    if err.kind() == io::ErrorKind::UnexpectedEof {
+        ImportError::InvalidData(format!("the file is truncated at vector {}", row))
+    } else {
+        err.into()
+    }
+}
+
+// ids of the vectors of formats without ids
+enum Ids {
+    // the vectors are numbered from 0
+    Rows,
+    // read from a file, one per line
+    File(BufReader<File>),
+}
+
+impl Ids {
+    fn id(&mut self, row: u64) -> Result<VectorId, ImportError> {
+        match self {
+            Self::Rows => Ok(VectorId::from(row.to_string())),
+            Self::File(reader) => {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    return Err(ImportError::InvalidData(format!(
+                        "the ids file has no id for vector {}",
+                        row
+                    )));
+                }
+                Ok(VectorId::from(
+                    line.trim_end_matches(['\r', '\n']).to_string(),
+                ))
+            }
+        }
+    }
+}
+
+struct JsonlReader {
+    reader: BufReader<File>,
+    line: u64,
+    read: u64,
+    len: u64,
+}
+
+impl RecordReader for JsonlReader {
+    fn next_record(&mut self) -> Result<Option<RawVectorEmbedding>, ImportError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = self.reader.read_line(&mut line)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.read += read as u64;
+            self.line += 1;
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+        let vector: CreateVectorDto = serde_json::from_str(&line)
+            .map_err(|err| ImportError::InvalidData(format!("line {}: {}", self.line, err)))?;
+        Ok(Some(vector.into()))
+    }
+
+    fn percentage_read(&self) -> f32 {
+        percentage(self.read, self.len)
+    }
+}
+
+struct ParquetReader {
+    rows: RowIter<'static>,
+    row: u64,
+    total: u64,
+    id_column: String,
+    vector_column: String,
+}
+
+impl ParquetReader {
+    fn new(file: File, options: ReaderOptions) -> Result<Self, ImportError> {
+        let reader = SerializedFileReader::new(file)
+            .map_err(|err| ImportError::InvalidRequest(format!("Invalid Parquet file: {}", err)))?;
+        let id_column = options
+            .id_column
+            .unwrap_or_else(|| DEFAULT_ID_COLUMN.to_string());
+        let vector_column = options
+            .vector_column
+            .unwrap_or_else(|| DEFAULT_VECTOR_COLUMN.to_string());
+
+        let metadata = reader.metadata().file_metadata();
+        let fields = metadata.schema().get_fields();
+        for column in [&id_column, &vector_column] {
+            if !fields.iter().any(|field| field.name() == column) {
+                return Err(ImportError::InvalidRequest(format!(
+                    "The Parquet file has no '{}' column",
+                    column
+                )));
+            }
+        }
+        let total = metadata.num_rows() as u64;
+
+        Ok(Self {
+            rows: RowIter::from_file_into(Box::new(reader)),
+            row: 0,
+            total,
+            id_column,
+            vector_column,
+        })
+    }
+}
+
+impl RecordReader for ParquetReader {
+    fn next_record(&mut self) -> Result<Option<RawVectorEmbedding>, ImportError> {
+        let Some(row) = self.rows.next() else {
+            return Ok(None);
+        };
+        let row = row.map_err(|err| ImportError::InvalidData(err.to_string()))?;
+        let invalid = |column: &str| {
+            ImportError::InvalidData(format!(
+                "row {} has an invalid '{}' column",
+                self.row, column
+            ))
+        };
+
+        let mut id = None;
+        let mut dense_values = None;
+        let mut document_id = None;
+        let mut text = None;
+        for (name, field) in row.get_column_iter() {
+            if *name == self.id_column {
+                id = Some(match field {
+                    Field::Str(id) => id.clone(),
+                    Field::Int(id) => id.to_string(),
+                    Field::Long(id) => id.to_string(),
+                    Field::UInt(id) => id.to_string(),
+                    Field::ULong(id) => id.to_string(),
+                    _ => return Err(invalid(name)),
+                });
+            } else if *name == self.vector_column {
+                let Field::ListInternal(list) = field else {
+                    return Err(invalid(name));
+                };
+                let values = list
+                    .elements()
+                    .iter()
+                    .map(|value| match value {
+                        Field::Float(value) => Some(*value),
+                        Field::Double(value) => Some(*value as f32),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| invalid(name))?;
+                dense_values = Some(values);
+            } else if name == "document_id" {
+                if let Field::Str(value) = field {
+                    document_id = Some(DocumentId::from(value.clone()));
+                }
+            } else if name == "text" {
+                if let Field::Str(value) = field {
+                    text = Some(value.clone());
+                }
+            }
+        }
+
+        let id = id.ok_or_else(|| invalid(&self.id_column))?;
+        let dense_values = dense_values.ok_or_else(|| invalid(&self.vector_column))?;
+        self.row += 1;
+        Ok(Some(RawVectorEmbedding {
+            document_id,
+            text,
+            ..dense_embedding(VectorId::from(id), dense_values)
+        }))
+    }
+
+    fn percentage_read(&self) -> f32 {
+        percentage(self.row, self.total)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NpyType {
+    F32,
+    F64,
+}
+
+struct NpyReader {
+    reader: BufReader<File>,
+    ids: Ids,
+    dtype: NpyType,
+    dim: usize,
+    row: u64,
+    rows: u64,
+}
+
+impl NpyReader {
+    fn new(file: File, ids: Ids) -> Result<Self, ImportError> {
+        let mut reader = BufReader::new(file);
+        let (dtype, rows, dim) = read_npy_header(&mut reader)?;
+        Ok(Self {
+            reader,
+            ids,
+            dtype,
+            dim,
+            row: 0,
+            rows,
+        })
+    }
+}
+
+// Returns the type, the number of rows and the dimension of the array
+fn read_npy_header(reader: &mut impl Read) -> Result<(NpyType, u64, usize), ImportError> {
+    let invalid = |msg: &str| ImportError::InvalidRequest(format!("Invalid NumPy file: {}", msg));
+
+    let mut prefix = [0u8; 8];
+    reader
+        .read_exact(&mut prefix)
+        .map_err(|_| invalid("missing header"))?;
+    if &prefix[..6] != NPY_MAGIC {
+        return Err(invalid("missing header"));
+    }
+    let header_len = if prefix[6] == 1 {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        u16::from_le_bytes(len) as usize
+    } else {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        u32::from_le_bytes(len) as usize
+    };
+    let mut header = vec![0u8; header_len];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| invalid("truncated header"))?;
+    let header = String::from_utf8_lossy(&header);
+
+    let value = |key: &str| {
+        let pattern = format!("'{}':", key);
+        let start = header.find(&pattern)? + pattern.len();
+        Some(header[start..].trim_start())
+    };
+    let descr = value("descr")
+        .and_then(|value| value.strip_prefix('\''))
+        .and_then(|value| value.split('\'').next())
+        .ok_or_else(|| invalid("missing 'descr'"))?;
+    let dtype = match descr {
+        "<f4" => NpyType::F32,
+        "<f8" => NpyType::F64,
+        descr => {
+            return Err(invalid(&format!(
+                "unsupported type '{}', expected '<f4' or '<f8'",
+                descr
+            )))
+        }
+    };
+    if value("fortran_order").is_some_and(|value| value.starts_with("True")) {
+        return Err(invalid("Fortran ordered arrays aren't supported"));
+    }
+    let shape = value("shape")
+        .and_then(|value| value.strip_prefix('('))
+        .and_then(|value| value.split(')').next())
+        .ok_or_else(|| invalid("missing 'shape'"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid("invalid 'shape'"))?;
+    let [rows, dim] = shape[..] else {
+        return Err(invalid("expected a 2-dimensional array"));
+    };
+
+    Ok((dtype, rows, dim as usize))
+}
+
+impl RecordReader for NpyReader {
+    fn next_record(&mut self) -> Result<Option<RawVectorEmbedding>, ImportError> {
+        if self.row == self.rows {
+            return Ok(None);
+        }
+        let dense_values = match self.dtype {
+            NpyType::F32 => {
+                let mut bytes = vec![0u8; self.dim * 4];
+                self.reader
+                    .read_exact(&mut bytes)
+                    .map_err(|err| truncated(err, self.row))?;
+                bytes
+                    .chunks_exact(4)
+                    .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                    .collect()
+            }
+            NpyType::F64 => {
+                let mut bytes = vec![0u8; self.dim * 8];
+                self.reader
+                    .read_exact(&mut bytes)
+                    .map_err(|err| truncated(err, self.row))?;
+                bytes
+                    .chunks_exact(8)
+                    .map(|value| f64::from_le_bytes(value.try_into().unwrap()) as f32)
+                    .collect()
+            }
+        };
+        let id = self.ids.id(self.row)?;
+        self.row += 1;
+        Ok(Some(dense_embedding(id, dense_values)))
+    }
+
+    fn percentage_read(&self) -> f32 {
+        percentage(self.row, self.rows)
+    }
+}
+
+struct FvecsReader {
+    reader: BufReader<File>,
+    ids: Ids,
+    row: u64,
+    read: u64,
+    len: u64,
+}
+
+impl RecordReader for FvecsReader {
+    fn next_record(&mut self) -> Result<Option<RawVectorEmbedding>, ImportError> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let mut dim = [0u8; 4];
+        self.reader
+            .read_exact(&mut dim)
+            .map_err(|err| truncated(err, self.row))?;
+        let dim = i32::from_le_bytes(dim);
+        if dim <= 0 {
+            return Err(ImportError::InvalidData(format!(
+                "vector {} has an invalid dimension {}",
+                self.row, dim
+            )));
+        }
+        let mut bytes = vec![0u8; dim as usize * 4];
+        self.reader
+            .read_exact(&mut bytes)
+            .map_err(|err| truncated(err, self.row))?;
+        let dense_values = bytes
+            .chunks_exact(4)
+            .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+            .collect();
+
+        let id = self.ids.id(self.row)?;
+        self.row += 1;
+        self.read += 4 + bytes.len() as u64;
+        Ok(Some(dense_embedding(id, dense_values)))
+    }
+
+    fn percentage_read(&self) -> f32 {
+        percentage(self.read, self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Arc};
+
+    use parquet::{
+        data_type::{ByteArray, ByteArrayType, FloatType},
+        file::{properties::WriterProperties, writer::SerializedFileWriter},
+        schema::parser::parse_message_type,
+    };
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    fn write_file(bytes: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(bytes).unwrap();
+        file
+    }
+
+    fn read_all(
+        file: &NamedTempFile,
+        format: ImportFormat,
+        options: ReaderOptions,
+    ) -> Vec<(String, Vec<f32>)> {
+        let mut reader = open_reader(file.path(), format, options).unwrap();
+        let mut records = Vec::new();
+        while let Some(record) = reader.next_record().unwrap() {
+            records.push((record.id.to_string(), record.dense_values.unwrap()));
+        }
+        assert_eq!(reader.percentage_read(), 100.0);
+        records
+    }
+
+    fn npy(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr, shape
+        );
+        // the header is padded so that the data is aligned to 64 bytes
+        while (NPY_MAGIC.len() + 4 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn test_read_jsonl() {
+        let file = write_file(
+            b"{\"id\": \"a\", \"dense_values\": [1.0, 2.0]}\n\n{\"id\": \"b\", \"dense_values\": [3.0, 4.0]}\n",
+        );
+        let records = read_all(&file, ImportFormat::Jsonl, ReaderOptions::default());
+        assert_eq!(
+            records,
+            vec![
+                ("a".to_string(), vec![1.0, 2.0]),
+                ("b".to_string(), vec![3.0, 4.0])
+            ]
+        );
+
+        let file = write_file(b"{\"id\": \"a\", \"dense_values\": [1.0]}\nnot json\n");
+        let mut reader =
+            open_reader(file.path(), ImportFormat::Jsonl, ReaderOptions::default()).unwrap();
+        assert!(reader.next_record().unwrap().is_some());
+        assert!(matches!(
+            reader.next_record(),
+            Err(ImportError::InvalidData(msg)) if msg.starts_with("line 2:")
+        ));
+    }
+
+    #[test]
+    fn test_read_npy() {
+        let data: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let file = write_file(&npy("<f4", "(2, 3)", &data));
+        assert_eq!(
+            read_all(&file, ImportFormat::Npy, ReaderOptions::default()),
+            vec![
+                ("0".to_string(), vec![1.0, 2.0, 3.0]),
+                ("1".to_string(), vec![4.0, 5.0, 6.0])
+            ]
+        );
+
+        let data: Vec<u8> = [1.0f64, 2.0, 3.0, 4.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let file = write_file(&npy("<f8", "(2, 2)", &data));
+        let ids = write_file(b"first\nsecond\n");
+        let options = ReaderOptions {
+            ids_path: Some(ids.path().to_path_buf()),
+            ..Default::default()
+        };
+        assert_eq!(
+            read_all(&file, ImportFormat::Npy, options),
+            vec![
+                ("first".to_string(), vec![1.0, 2.0]),
+                ("second".to_string(), vec![3.0, 4.0])
+            ]
+        );
+
+        let file = write_file(&npy("<i4", "(1, 1)", &[0; 4]));
+        assert!(matches!(
+            open_reader(file.path(), ImportFormat::Npy, ReaderOptions::default()),
+            Err(ImportError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_fvecs() {
+        let mut data = Vec::new();
+        for vector in [[1.0f32, 2.0], [3.0, 4.0]] {
+            data.extend(2i32.to_le_bytes());
+            data.extend(vector.iter().flat_map(|value| value.to_le_bytes()));
+        }
+        let file = write_file(&data);
+        assert_eq!(
+            read_all(&file, ImportFormat::Fvecs, ReaderOptions::default()),
+            vec![
+                ("0".to_string(), vec![1.0, 2.0]),
+                ("1".to_string(), vec![3.0, 4.0])
+            ]
+        );
+
+        let file = write_file(&data[..data.len() - 2]);
+        let mut reader =
+            open_reader(file.path(), ImportFormat::Fvecs, ReaderOptions::default()).unwrap();
+        assert!(reader.next_record().unwrap().is_some());
+        assert!(matches!(
+            reader.next_record(),
+            Err(ImportError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_parquet() {
+        let schema = Arc::new(
+            parse_message_type(
+                "message schema {
+                    REQUIRED BYTE_ARRAY key (UTF8);
+                    REQUIRED group embedding (LIST) {
+                        REPEATED group list {
+                            REQUIRED FLOAT element;
+                        }
+                    }
+                }",
+            )
+            .unwrap(),
+        );
+        let file = NamedTempFile::new().unwrap();
+        let properties = Arc::new(WriterProperties::builder().build());
+        let mut writer =
+            SerializedFileWriter::new(file.reopen().unwrap(), schema, properties).unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&[ByteArray::from("a"), ByteArray::from("b")], None, None)
+            .unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<FloatType>()
+            .write_batch(
+                &[1.0, 2.0, 3.0, 4.0],
+                Some(&[1, 1, 1, 1]),
+                Some(&[0, 1, 0, 1]),
+            )
+            .unwrap();
+        column.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+
+        let options = ReaderOptions {
+            id_column: Some("key".to_string()),
+            vector_column: Some("embedding".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            read_all(&file, ImportFormat::Parquet, options),
+            vec![
+                ("a".to_string(), vec![1.0, 2.0]),
+                ("b".to_string(), vec![3.0, 4.0])
+            ]
+        );
+
+        assert!(matches!(
+            open_reader(file.path(), ImportFormat::Parquet, ReaderOptions::default()),
+            Err(ImportError::InvalidRequest(msg)) if msg.contains("'id'")
+        ));
+    }
+}
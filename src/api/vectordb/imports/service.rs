@@ -0,0 +1,271 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock},
+    thread,
+};
+
+use actix_web::web;
+use chrono::Utc;
+use dashmap::DashMap;
+use futures_util::StreamExt;
+use tempfile::NamedTempFile;
+
+use crate::{
+    api::vectordb::transactions::{error::TransactionError, repo as transactions_repo},
+    app_context::AppContext,
+    models::{
+        collection::Collection,
+        collection_transaction::{
+            ConflictResolution, ExplicitTransactionID, ProcessingStats, TransactionStatus,
+        },
+        paths::get_data_path,
+    },
+};
+
+use super::{
+    dtos::{ImportFileDto, ImportFormat, ImportJobDto, ImportState, ImportUploadQueryDto},
+    error::ImportError,
+    readers::{open_reader, ReaderOptions, RecordReader},
+};
+
+// number of records written to the transaction at once
+const BATCH_SIZE: usize = 1000;
+
+// the imports started since the server started, by collection and
+// transaction
+static IMPORTS: LazyLock<DashMap<(String, ExplicitTransactionID), ImportJobDto>> =
+    LazyLock::new(DashMap::new);
+
+/// imports a file stored on the server into `collection_id`
+pub(crate) async fn import_file(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    import_file_dto: ImportFileDto,
+) -> Result<ImportJobDto, ImportError> {
+    let root = ctx.config.imports.root();
+    let path = resolve_import_path(&root, &import_file_dto.path)?;
+    let format = import_file_dto
+        .format
+        .or_else(|| ImportFormat::from_path(&path))
+        .ok_or_else(|| {
+            ImportError::InvalidRequest(format!(
+                "The format of '{}' can't be inferred from its extension",
+                import_file_dto.path
+            ))
+        })?;
+    let ids_path = import_file_dto
+        .ids_path
+        .map(|ids_path| resolve_import_path(&root, &ids_path))
+        .transpose()?;
+    let options = ReaderOptions {
+        id_column: import_file_dto.id_column,
+        vector_column: import_file_dto.vector_column,
+        ids_path,
+    };
+    let reader = open_reader(&path, format, options)?;
+    start_import(ctx, collection_id, format, reader, None).await
+}
+
+// resolves a path relative to the import root, rejecting the paths that
+// are outside it once symlinks and `..` are resolved
+fn resolve_import_path(root: &Path, path: &str) -> Result<PathBuf, ImportError> {
+    fs::create_dir_all(root)?;
+    let root = root.canonicalize()?;
+    let resolved = root.join(path).canonicalize().map_err(|err| {
+        ImportError::InvalidRequest(format!("Failed to open '{}': {}", path, err))
+    })?;
+    if !resolved.starts_with(&root) {
+        return Err(ImportError::InvalidRequest(format!(
+            "'{}' is outside the import directory",
+            path
+        )));
+    }
+    Ok(resolved)
+}
+
+/// imports a file uploaded in the request body into `collection_id`
+pub(crate) async fn import_upload(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    query: ImportUploadQueryDto,
+    mut payload: web::Payload,
+) -> Result<ImportJobDto, ImportError> {
+    // the upload is removed once the import finishes
+    let dir = get_data_path().join("imports");
+    fs::create_dir_all(&dir)?;
+    let mut file = NamedTempFile::new_in(&dir)?;
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| ImportError::ServerError(e.to_string()))?;
+        file.write_all(&chunk)?;
+    }
+    file.flush()?;
+
+    let options = ReaderOptions {
+        id_column: query.id_column,
+        vector_column: query.vector_column,
+        ids_path: None,
+    };
+    let reader = open_reader(file.path(), query.format, options)?;
+    start_import(ctx, collection_id, query.format, reader, Some(file)).await
+}
+
+pub(crate) async fn get_import(
+    collection_id: &str,
+    transaction_id: ExplicitTransactionID,
+) -> Result<ImportJobDto, ImportError> {
+    IMPORTS
+        .get(&(collection_id.to_string(), transaction_id))
+        .map(|job| job.clone())
+        .ok_or(ImportError::JobNotFound)
+}
+
+// opens an explicit transaction and writes the records to it in the
+// background, committing it once the whole file is read
+async fn start_import(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    format: ImportFormat,
+    reader: Box<dyn RecordReader>,
+    upload: Option<NamedTempFile>,
+) -> Result<ImportJobDto, ImportError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or(ImportError::CollectionNotFound)?;
+    let transaction = transactions_repo::create_transaction(ctx.clone(), collection_id)
+        .await
+        .map_err(|err| match err {
+            TransactionError::CollectionNotFound => ImportError::CollectionNotFound,
+            err => ImportError::ServerError(err.to_string()),
+        })?;
+    let transaction_id = transaction.transaction_id;
+
+    let job = ImportJobDto {
+        transaction_id,
+        format,
+        state: ImportState::Reading,
+        records_read: 0,
+        percentage_read: 0.0,
+        error: None,
+        started_at: transaction.created_at,
+        finished_at: None,
+    };
+    let key = (collection_id.to_string(), transaction_id);
+    IMPORTS.insert(key.clone(), job.clone());
+
+    thread::Builder::new()
+        .name("import".to_string())
+        .spawn(move || {
+            let _upload = upload;
+            let result = run_import(&ctx, &collection, transaction_id, reader, &key);
+            if let Err(err) = &result {
+                log::error!(
+                    "Failed to import into collection '{}': {}",
+                    collection.meta.name,
+                    err
+                );
+                // fails if it was already closed by another request
+                let _ = transactions_repo::abort_open_transaction(&collection, transaction_id);
+            }
+            if let Some(mut job) = IMPORTS.get_mut(&key) {
+                job.finished_at = Some(Utc::now());
+                match result {
+                    Ok(()) => job.state = ImportState::Committed,
+                    Err(err) => {
+                        job.state = ImportState::Failed;
+                        job.error = Some(err.to_string());
+                    }
+                }
+            }
+        })?;
+
+    Ok(job)
+}
+
+fn run_import(
+    ctx: &AppContext,
+    collection: &Collection,
+    transaction_id: ExplicitTransactionID,
+    mut reader: Box<dyn RecordReader>,
+    key: &(String, ExplicitTransactionID),
+) -> Result<(), ImportError> {
+    let started_at = Utc::now();
+    let mut records_read = 0;
+    let mut batches = 0;
+    loop {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        while batch.len() < BATCH_SIZE {
+            let Some(record) = reader.next_record()? else {
+                break;
+            };
+            batch.push(record);
+        }
+        if batch.is_empty() {
+            break;
+        }
+        let batch_len = batch.len();
+
+        {
+            let open_transactions_guard = collection.open_explicit_transactions.read();
+            let Some(transaction) = open_transactions_guard.get(&transaction_id) else {
+                return Err(ImportError::TransactionClosed);
+            };
+            transactions_repo::check_vector_quota(
+                ctx,
+                collection,
+                batch.iter().map(|record| &record.id),
+            )
+            .map_err(|err| match err {
+                TransactionError::QuotaExceeded(msg) => ImportError::QuotaExceeded(msg),
+                err => ImportError::ServerError(err.to_string()),
+            })?;
+            collection
+                .run_upload(batch, &HashSet::new(), transaction)
+                .map_err(|err| ImportError::InvalidData(err.to_string()))?;
+        }
+
+        records_read += batch_len as u32;
+        batches += 1;
+        let percentage_read = reader.percentage_read();
+        if let Some(mut job) = IMPORTS.get_mut(key) {
+            job.records_read = records_read;
+            job.percentage_read = percentage_read;
+        }
+        if let Some(status) = collection
+            .transaction_status_map
+            .get_latest(&transaction_id)
+        {
+            let now = Utc::now();
+            let elapsed = (now - started_at).num_milliseconds().max(1) as f32 / 1000.0;
+            *status.write() = TransactionStatus::InProgress {
+                stats: ProcessingStats {
+                    records_upserted: records_read,
+                    records_deleted: 0,
+                    total_operations: batches,
+                    percentage_complete: percentage_read,
+                    processing_time_seconds: None,
+                    average_throughput: None,
+                    current_processing_rate: Some(records_read as f32 / elapsed),
+                    estimated_completion: None,
+                    version_created: None,
+                },
+                started_at,
+                last_updated: now,
+            };
+        }
+    }
+
+    transactions_repo::commit_open_transaction(
+        collection,
+        transaction_id,
+        ConflictResolution::LastCommitterWins,
+    )
+    .map_err(|err| match err {
+        TransactionError::NotFound => ImportError::TransactionClosed,
+        err => ImportError::ServerError(err.to_string()),
+    })
+}
@@ -2,7 +2,10 @@ use actix_web::{web, HttpResponse, Result};
 
 use crate::app_context::AppContext;
 
-use super::dtos::{CreateTFIDFIndexDto, IndexDetailsDto, IndexResponseDto, IndexType};
+use super::dtos::{
+    CreateTFIDFIndexDto, IndexDetailsDto, IndexResponseDto, IndexType, TfIdfCorpusStatsDto,
+    TfIdfStatsQueryDto,
+};
 use super::error::IndexesError;
 use super::{
     dtos::{CreateDenseIndexDto, CreateSparseIndexDto},
@@ -138,6 +141,34 @@ pub(crate) async fn get_index(
     Ok(HttpResponse::Ok().json(index_details))
 }
 
+/// Get TF-IDF corpus statistics for a collection
+///
+/// Returns the corpus level statistics used for BM25 scoring, along with the
+/// document frequency and IDF of the requested terms
+#[utoipa::path(
+    get,
+    path = "/vectordb/collections/{collection_id}/indexes/tf-idf/stats",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        TfIdfStatsQueryDto
+    ),
+    responses(
+        (status = 200, description = "Statistics retrieved successfully", body = TfIdfCorpusStatsDto),
+        (status = 404, description = "Collection or index not found", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "indexes"
+)]
+pub(crate) async fn get_tf_idf_stats(
+    collection_id: web::Path<String>,
+    web::Query(query): web::Query<TfIdfStatsQueryDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, IndexesError> {
+    let stats =
+        service::get_tf_idf_stats(collection_id.into_inner(), query, ctx.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(stats))
+}
+
 /// Delete an index from a collection
 ///
 /// Deletes the specified index from a collection
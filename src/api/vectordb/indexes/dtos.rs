@@ -1,5 +1,5 @@
 use serde::{Deserialize, Deserializer, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
     config_loader::Config, indexes::hnsw::types::HNSWHyperParams,
@@ -95,6 +95,29 @@ pub struct TfIdfIndexInfo {
     pub b: f32,
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TfIdfStatsQueryDto {
+    /// Comma separated list of terms to report the document frequency of
+    pub terms: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TfIdfTermStatsDto {
+    pub term: String,
+    pub document_frequency: u32,
+    pub idf: f32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TfIdfCorpusStatsDto {
+    pub collection_name: String,
+    pub total_documents: u32,
+    pub average_document_length: f32,
+    pub k1: f32,
+    pub b: f32,
+    pub terms: Vec<TfIdfTermStatsDto>,
+}
+
 impl<'de> Deserialize<'de> for SparseIndexQuantization {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -13,5 +13,6 @@ pub(crate) fn indexes_module() -> Scope {
         .route("/dense", web::post().to(create_dense_index))
         .route("/sparse", web::post().to(create_sparse_index))
         .route("/tf-idf", web::post().to(create_tf_idf_index))
+        .route("/tf-idf/stats", web::get().to(controller::get_tf_idf_stats))
         .route("/{index_type}", web::delete().to(delete_index))
 }
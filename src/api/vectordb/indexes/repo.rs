@@ -8,12 +8,16 @@ use crate::{
         init_tf_idf_index_for_collection,
     },
     app_context::AppContext,
+    indexes::{DENSE_INDEX_DIR, SPARSE_INDEX_DIR, TF_IDF_INDEX_DIR},
     models::types::{DistanceMetric, QuantizationMetric},
     quantization::StorageType,
 };
 
 use super::{
-    dtos::{DenseIndexParamsDto, DenseIndexQuantizationDto, IndexType, SparseIndexQuantization},
+    dtos::{
+        DenseIndexParamsDto, DenseIndexQuantizationDto, IndexType, SparseIndexQuantization,
+        TfIdfCorpusStatsDto, TfIdfTermStatsDto,
+    },
     error::IndexesError,
 };
 
@@ -66,7 +70,6 @@ pub(crate) async fn create_dense_index(
         sample_threshold,
         is_configured,
     )
-    .await
     .map_err(|e| IndexesError::FailedToCreateIndex(e.to_string()))?;
 
     Ok(())
@@ -186,6 +189,48 @@ pub(crate) async fn get_index(
     }))
 }
 
+pub(crate) async fn get_tf_idf_stats(
+    ctx: Arc<AppContext>,
+    collection_name: String,
+    terms: &[String],
+) -> Result<TfIdfCorpusStatsDto, IndexesError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(&collection_name)
+        .ok_or_else(|| {
+            IndexesError::NotFound(format!("Collection '{}' not found", collection_name))
+        })?;
+
+    let tf_idf_index = collection.get_tf_idf_index().ok_or_else(|| {
+        IndexesError::NotFound(format!(
+            "TF-IDF index does not exist for collection '{}'",
+            collection_name
+        ))
+    })?;
+
+    let stats = tf_idf_index
+        .corpus_stats(terms)
+        .map_err(|e| IndexesError::WaCustom(e.into()))?;
+
+    Ok(TfIdfCorpusStatsDto {
+        collection_name,
+        total_documents: stats.total_documents_count,
+        average_document_length: stats.average_document_length,
+        k1: stats.k1,
+        b: stats.b,
+        terms: stats
+            .terms
+            .into_iter()
+            .map(|term| TfIdfTermStatsDto {
+                term: term.term,
+                document_frequency: term.document_frequency,
+                idf: term.idf,
+            })
+            .collect(),
+    })
+}
+
 pub(crate) async fn delete_index(
     ctx: Arc<AppContext>,
     collection_name: String,
@@ -209,7 +254,8 @@ pub(crate) async fn delete_index(
                     collection_name
                 )));
             }
-            ctx.ain_env
+            let removed_index = ctx
+                .ain_env
                 .collections_map
                 .remove_hnsw_index(&collection_name)
                 .map_err(|e| {
@@ -219,7 +265,10 @@ pub(crate) async fn delete_index(
                     ))
                 })?;
 
-            let index_path = collection_path.join("dense_hnsw");
+            let index_path = removed_index.map_or_else(
+                || collection_path.join(DENSE_INDEX_DIR),
+                |index| index.dir.path().to_path_buf(),
+            );
             if index_path.exists() {
                 log::info!(
                     "Attempting to remove dense index directory: {:?}",
@@ -254,7 +303,8 @@ pub(crate) async fn delete_index(
                     collection_name
                 )));
             }
-            ctx.ain_env
+            let removed_index = ctx
+                .ain_env
                 .collections_map
                 .remove_inverted_index(&collection_name)
                 .map_err(|e| {
@@ -264,7 +314,10 @@ pub(crate) async fn delete_index(
                     ))
                 })?;
 
-            let index_path = collection_path.join("sparse_inverted_index");
+            let index_path = removed_index.map_or_else(
+                || collection_path.join(SPARSE_INDEX_DIR),
+                |index| index.dir.path().to_path_buf(),
+            );
             if index_path.exists() {
                 log::info!(
                     "Attempting to remove sparse index directory: {:?}",
@@ -299,7 +352,8 @@ pub(crate) async fn delete_index(
                     collection_name
                 )));
             }
-            ctx.ain_env
+            let removed_index = ctx
+                .ain_env
                 .collections_map
                 .remove_tf_idf_index(&collection_name)
                 .map_err(|e| {
@@ -309,7 +363,10 @@ pub(crate) async fn delete_index(
                     ))
                 })?;
 
-            let index_path = collection_path.join("tf_idf_index");
+            let index_path = removed_index.map_or_else(
+                || collection_path.join(TF_IDF_INDEX_DIR),
+                |index| index.dir.path().to_path_buf(),
+            );
             if index_path.exists() {
                 log::info!(
                     "Attempting to remove TF-IDF index directory: {:?}",
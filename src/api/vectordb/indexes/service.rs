@@ -5,6 +5,7 @@ use crate::app_context::AppContext;
 use super::{
     dtos::{
         CreateDenseIndexDto, CreateSparseIndexDto, CreateTFIDFIndexDto, IndexDetailsDto, IndexType,
+        TfIdfCorpusStatsDto, TfIdfStatsQueryDto,
     },
     error::IndexesError,
     repo,
@@ -68,6 +69,23 @@ pub(crate) async fn get_index(
         .map_err(|_e| IndexesError::FailedToGetAppEnv)
 }
 
+pub(crate) async fn get_tf_idf_stats(
+    collection_id: String,
+    query: TfIdfStatsQueryDto,
+    ctx: Arc<AppContext>,
+) -> Result<TfIdfCorpusStatsDto, IndexesError> {
+    let terms: Vec<String> = query
+        .terms
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|term| !term.is_empty())
+        .map(str::to_string)
+        .collect();
+    repo::get_tf_idf_stats(ctx, collection_id, &terms).await
+}
+
 pub(crate) async fn delete_index(
     collection_id: String,
     index_type: IndexType,
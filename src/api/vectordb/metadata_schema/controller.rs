@@ -0,0 +1,75 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{app_context::AppContext, models::collection_cache::CollectionCacheExt};
+
+use super::{
+    dtos::{ReindexJobDto, UpdateMetadataSchemaDto, UpdateMetadataSchemaResponseDto},
+    error::MetadataSchemaError,
+    service,
+};
+
+/// Update the metadata schema of a collection
+///
+/// Adds values to existing fields, new fields and new supported conditions.
+/// If the changes fit in the dimensions already allocated for the metadata of
+/// the dense index, such as values of a field that doesn't have all of its
+/// values yet, the new schema applies immediately and `200` is returned.
+/// Otherwise the dense index is rebuilt in the background with the vectors
+/// it contains, and `202` is returned with the progress of the rebuild,
+/// which can be polled. The current index and schema keep serving requests
+/// until the rebuilt index replaces them, the vectors keep their versions.
+#[utoipa::path(
+    patch,
+    path = "/vectordb/collections/{collection_id}/metadata_schema",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier")
+    ),
+    request_body = UpdateMetadataSchemaDto,
+    responses(
+        (status = 200, description = "Metadata schema updated", body = UpdateMetadataSchemaResponseDto),
+        (status = 202, description = "Metadata schema updated, dense index being rebuilt", body = UpdateMetadataSchemaResponseDto),
+        (status = 400, description = "Invalid schema change"),
+        (status = 404, description = "Collection not found"),
+        (status = 409, description = "A rebuild is in progress")
+    ),
+    tag = "metadata_schema"
+)]
+pub(crate) async fn update_metadata_schema(
+    collection_id: web::Path<String>,
+    web::Json(update_dto): web::Json<UpdateMetadataSchemaDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, MetadataSchemaError> {
+    ctx.update_collection_for_transaction(&collection_id)
+        .map_err(|e| MetadataSchemaError::ServerError(format!("Cache error: {}", e)))?;
+    let response =
+        service::update_metadata_schema(ctx.into_inner(), &collection_id, update_dto).await?;
+    if response.reindex.is_some() {
+        Ok(HttpResponse::Accepted().json(response))
+    } else {
+        Ok(HttpResponse::Ok().json(response))
+    }
+}
+
+/// Get the rebuild of the dense index
+///
+/// Returns the progress of the latest rebuild of the dense index of the
+/// collection for a metadata schema change since the server started, and the
+/// error it failed with, if any.
+#[utoipa::path(
+    get,
+    path = "/vectordb/collections/{collection_id}/metadata_schema/reindex",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier")
+    ),
+    responses(
+        (status = 200, description = "Rebuild retrieved successfully", body = ReindexJobDto),
+        (status = 404, description = "No rebuild found")
+    ),
+    tag = "metadata_schema"
+)]
+pub(crate) async fn get_reindex(
+    collection_id: web::Path<String>,
+) -> Result<HttpResponse, MetadataSchemaError> {
+    let job = service::get_reindex(&collection_id).await?;
+    Ok(HttpResponse::Ok().json(job))
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::api::vectordb::collections::dtos::{MetadataField, SupportedCondition};
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct UpdateMetadataSchemaDto {
+    /// Values to add to existing fields
+    #[serde(default)]
+    pub values: Vec<MetadataField>,
+    /// Fields to add
+    #[serde(default)]
+    pub fields: Vec<MetadataField>,
+    /// Conditions to add, which may refer to the added fields
+    #[serde(default)]
+    pub supported_conditions: Vec<SupportedCondition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ReindexState {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Progress of rebuilding the dense index of a collection for a metadata
+/// schema with different dimensions
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct ReindexJobDto {
+    pub state: ReindexState,
+    pub vectors_total: u32,
+    pub vectors_reindexed: u32,
+    pub percentage_complete: f32,
+    pub error: Option<String>,
+    #[schema(value_type = String, example = "2023-01-01T12:00:00Z")]
+    pub started_at: DateTime<Utc>,
+    #[schema(value_type = Option<String>, example = "2023-01-01T12:00:00Z")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct UpdateMetadataSchemaResponseDto {
+    /// The rebuild of the dense index, if the dimensions of the schema
+    /// changed, `null` if the new schema is already in effect
+    pub reindex: Option<ReindexJobDto>,
+}
@@ -0,0 +1,69 @@
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    HttpResponse, ResponseError,
+};
+use std::fmt::Display;
+
+use crate::{metadata, models::common::WaCustomError};
+
+#[derive(Debug)]
+pub enum MetadataSchemaError {
+    CollectionNotFound,
+    NoMetadataSchema,
+    ReindexNotFound,
+    InvalidSchema(metadata::Error),
+    // a rebuild of the dense index is in progress
+    Conflict(String),
+    ServerError(String),
+}
+
+impl Display for MetadataSchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CollectionNotFound => write!(f, "Collection not found"),
+            Self::NoMetadataSchema => write!(f, "Collection has no metadata schema"),
+            Self::ReindexNotFound => write!(f, "The dense index wasn't rebuilt"),
+            Self::InvalidSchema(err) => write!(f, "{}", err),
+            Self::Conflict(msg) => write!(f, "{}", msg),
+            Self::ServerError(msg) => write!(f, "Server error: {}", msg),
+        }
+    }
+}
+
+impl ResponseError for MetadataSchemaError {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let message = self.to_string();
+        HttpResponse::build(status)
+            .insert_header(ContentType::json())
+            .json(serde_json::json!({
+                "error": status.canonical_reason().unwrap_or("Error"),
+                "code": status.as_u16(),
+                "message": message
+            }))
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::CollectionNotFound | Self::ReindexNotFound => StatusCode::NOT_FOUND,
+            Self::NoMetadataSchema | Self::InvalidSchema(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<metadata::Error> for MetadataSchemaError {
+    fn from(error: metadata::Error) -> Self {
+        Self::InvalidSchema(error)
+    }
+}
+
+impl From<WaCustomError> for MetadataSchemaError {
+    fn from(error: WaCustomError) -> Self {
+        match error {
+            WaCustomError::LockError(msg) => Self::Conflict(msg),
+            err => Self::ServerError(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use actix_web::{web, Scope};
+
+pub mod controller;
+pub(crate) mod dtos;
+mod error;
+mod service;
+
+pub(crate) fn metadata_schema_module() -> Scope {
+    web::scope("/collections/{collection_id}/metadata_schema")
+        .route("", web::patch().to(controller::update_metadata_schema))
+        .route("/reindex", web::get().to(controller::get_reindex))
+}
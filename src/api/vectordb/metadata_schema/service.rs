@@ -0,0 +1,171 @@
+use std::{
+    sync::{atomic::Ordering, Arc, LazyLock, Mutex},
+    thread,
+};
+
+use chrono::Utc;
+use dashmap::DashMap;
+
+use crate::{
+    app_context::AppContext,
+    metadata::MetadataSchema,
+    models::{collection::Collection, common::WaCustomError, index_rebuild::rebuild_dense_index},
+};
+
+use super::{
+    dtos::{ReindexJobDto, ReindexState, UpdateMetadataSchemaDto, UpdateMetadataSchemaResponseDto},
+    error::MetadataSchemaError,
+};
+
+// the latest rebuild of the dense index of each collection since the
+// server started
+static REINDEXES: LazyLock<DashMap<String, ReindexJobDto>> = LazyLock::new(DashMap::new);
+
+// held while a schema is extended, so that concurrent updates of the same
+// schema aren't lost
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+pub(crate) async fn update_metadata_schema(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    update_dto: UpdateMetadataSchemaDto,
+) -> Result<UpdateMetadataSchemaResponseDto, MetadataSchemaError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or(MetadataSchemaError::CollectionNotFound)?;
+
+    let _guard = UPDATE_LOCK.lock().unwrap();
+    if REINDEXES
+        .get(collection_id)
+        .is_some_and(|job| job.state == ReindexState::Running)
+    {
+        return Err(MetadataSchemaError::Conflict(
+            "The dense index is still being rebuilt for the previous change".to_string(),
+        ));
+    }
+    let schema = collection
+        .meta
+        .metadata_schema
+        .get()
+        .ok_or(MetadataSchemaError::NoMetadataSchema)?;
+
+    let values = update_dto
+        .values
+        .into_iter()
+        .map(|field| (field.name, field.values.into_iter().collect()))
+        .collect();
+    let fields = update_dto
+        .fields
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect::<Result<_, _>>()?;
+    let conditions = update_dto
+        .supported_conditions
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect::<Result<_, _>>()?;
+    let (schema, needs_reindex) = schema.extend(values, fields, conditions)?;
+
+    let hnsw_index = collection.get_hnsw_index();
+    if !needs_reindex || hnsw_index.is_none() {
+        // the pseudo nodes of the dense index already cover all the value
+        // ids that fit in the dimensions of a field
+        if let Some(hnsw_index) = hnsw_index {
+            hnsw_index.metadata_schema.set(schema.clone());
+        }
+        collection.meta.metadata_schema.set(schema);
+        collection.persist(
+            &ctx.ain_env.persist,
+            ctx.ain_env.collections_map.lmdb_collections_db,
+        )?;
+        return Ok(UpdateMetadataSchemaResponseDto { reindex: None });
+    }
+    let job = start_reindex(ctx, collection, schema)?;
+    Ok(UpdateMetadataSchemaResponseDto { reindex: Some(job) })
+}
+
+pub(crate) async fn get_reindex(collection_id: &str) -> Result<ReindexJobDto, MetadataSchemaError> {
+    REINDEXES
+        .get(collection_id)
+        .map(|job| job.clone())
+        .ok_or(MetadataSchemaError::ReindexNotFound)
+}
+
+// rebuilds the dense index for `schema` in the background, next to the
+// current one which keeps serving requests with the current schema until
+// both are replaced
+fn start_reindex(
+    ctx: Arc<AppContext>,
+    collection: Arc<Collection>,
+    schema: MetadataSchema,
+) -> Result<ReindexJobDto, MetadataSchemaError> {
+    let end = collection.internal_id_counter.load(Ordering::Relaxed);
+    let job = ReindexJobDto {
+        state: ReindexState::Running,
+        // updated once the rebuild lists the vectors
+        vectors_total: collection.live_internal_ids(0..end).len() as u32,
+        vectors_reindexed: 0,
+        percentage_complete: 0.0,
+        error: None,
+        started_at: Utc::now(),
+        finished_at: None,
+    };
+    REINDEXES.insert(collection.meta.name.clone(), job.clone());
+
+    thread::Builder::new()
+        .name("reindex".to_string())
+        .spawn(move || {
+            let result = run_reindex(&ctx, &collection, schema);
+            if let Err(err) = &result {
+                log::error!(
+                    "Failed to rebuild the dense index of collection '{}': {}",
+                    collection.meta.name,
+                    err
+                );
+            }
+            if let Some(mut job) = REINDEXES.get_mut(&collection.meta.name) {
+                job.finished_at = Some(Utc::now());
+                match result {
+                    Ok(()) => {
+                        job.state = ReindexState::Completed;
+                        job.percentage_complete = 100.0;
+                    }
+                    Err(err) => {
+                        job.state = ReindexState::Failed;
+                        job.error = Some(err.to_string());
+                    }
+                }
+            }
+        })
+        .map_err(|e| MetadataSchemaError::ServerError(e.to_string()))?;
+
+    Ok(job)
+}
+
+// indexes the vectors under their current internal ids and versions, so
+// that conditional writes and scrolls pinned to a version aren't affected
+fn run_reindex(
+    ctx: &AppContext,
+    collection: &Collection,
+    schema: MetadataSchema,
+) -> Result<(), WaCustomError> {
+    // waits for a compaction in progress, which rebuilds the index too
+    let _compaction_guard = collection.compaction_lock.lock();
+    rebuild_dense_index(
+        &ctx.ain_env.collections_map,
+        collection,
+        &ctx.config,
+        schema,
+        |vectors_reindexed, vectors_total| {
+            if let Some(mut job) = REINDEXES.get_mut(&collection.meta.name) {
+                job.vectors_reindexed = vectors_reindexed as u32;
+                job.vectors_total = vectors_total as u32;
+                job.percentage_complete = vectors_reindexed as f32 * 100.0 / vectors_total as f32;
+            }
+        },
+    )?;
+    ctx.collection_cache_manager
+        .reload_collection(&collection.meta.name)
+}
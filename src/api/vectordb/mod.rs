@@ -7,3 +7,10 @@ pub(crate) mod transactions;
 
 pub(crate) mod indexes;
 pub(crate) mod versions;
+
+pub(crate) mod admin;
+pub(crate) mod imports;
+pub(crate) mod metadata_schema;
+pub(crate) mod replication;
+pub(crate) mod snapshots;
+pub(crate) mod tenants;
@@ -0,0 +1,150 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    api::{auth::dtos::Claims, vectordb::snapshots::controller::file_stream},
+    app_context::AppContext,
+    models::{replication::ReplicationBatch, types::ADMIN_USERNAME},
+};
+
+use super::{
+    dtos::{FollowerQueryDto, ReplicationStatusDto, VersionsQueryDto},
+    error::ReplicationError,
+    service,
+};
+
+/// Get the replication status
+///
+/// On the leader, reports the followers of the replicated collections, the
+/// versions they applied and how far behind they are. On a follower,
+/// reports how far behind the leader its replicas are. Only available to
+/// the admin user.
+#[utoipa::path(
+    get,
+    path = "/vectordb/replication/status",
+    responses(
+        (status = 200, description = "Replication status", body = ReplicationStatusDto),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 500, description = "Server error", body = serde_json::Value)
+    ),
+    tag = "replication"
+)]
+pub(crate) async fn get_replication_status(
+    claims: Claims,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, ReplicationError> {
+    if claims.username != ADMIN_USERNAME {
+        return Err(ReplicationError::Forbidden);
+    }
+    let status = service::get_replication_status(ctx.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// List replicated collections
+///
+/// Returns the collections with a replication factor above 1, which
+/// followers replicate. Called by followers on the leader, only available
+/// to the admin user.
+#[utoipa::path(
+    get,
+    path = "/vectordb/replication/collections",
+    params(FollowerQueryDto),
+    responses(
+        (status = 200, description = "Replicated collections", body = [crate::models::replication::ReplicatedCollection]),
+        (status = 400, description = "Not the replication leader", body = serde_json::Value),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value)
+    ),
+    tag = "replication"
+)]
+pub(crate) async fn list_replicated_collections(
+    claims: Claims,
+    web::Query(query): web::Query<FollowerQueryDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, ReplicationError> {
+    if claims.username != ADMIN_USERNAME {
+        return Err(ReplicationError::Forbidden);
+    }
+    let collections =
+        service::list_replicated_collections(ctx.into_inner(), query.follower_id).await?;
+    Ok(HttpResponse::Ok().json(collections))
+}
+
+/// Get committed versions
+///
+/// Returns the committed versions of a replicated collection following
+/// `after`, with their WAL files, and records `after` as the last version
+/// the follower applied. Called by followers on the leader, only available
+/// to the admin user.
+#[utoipa::path(
+    get,
+    path = "/vectordb/replication/collections/{collection_id}/versions",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        VersionsQueryDto
+    ),
+    responses(
+        (status = 200, description = "Versions following `after`", body = ReplicationBatch),
+        (status = 400, description = "Not the replication leader", body = serde_json::Value),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 404, description = "Replicated collection not found", body = serde_json::Value),
+        (status = 409, description = "The follower is ahead of the leader and must restore a snapshot", body = serde_json::Value),
+        (status = 410, description = "The versions following `after` were pruned, the follower must restore a snapshot", body = serde_json::Value)
+    ),
+    tag = "replication"
+)]
+pub(crate) async fn get_versions(
+    claims: Claims,
+    collection_id: web::Path<String>,
+    web::Query(query): web::Query<VersionsQueryDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, ReplicationError> {
+    if claims.username != ADMIN_USERNAME {
+        return Err(ReplicationError::Forbidden);
+    }
+    let batch = service::get_versions(
+        ctx.into_inner(),
+        &collection_id,
+        query.after,
+        query.follower_id,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(batch))
+}
+
+/// Download a snapshot for a replica
+///
+/// Takes a snapshot of a replicated collection and returns the archive,
+/// followers restore it to create their replica. Called by followers on the
+/// leader, only available to the admin user.
+#[utoipa::path(
+    get,
+    path = "/vectordb/replication/collections/{collection_id}/snapshot",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier")
+    ),
+    responses(
+        (status = 200, description = "Snapshot archive", content_type = "application/octet-stream"),
+        (status = 400, description = "Not the replication leader", body = serde_json::Value),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 404, description = "Replicated collection not found", body = serde_json::Value),
+        (status = 503, description = "The collection has a transaction in progress", body = serde_json::Value)
+    ),
+    tag = "replication"
+)]
+pub(crate) async fn download_snapshot(
+    claims: Claims,
+    collection_id: web::Path<String>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, ReplicationError> {
+    if claims.username != ADMIN_USERNAME {
+        return Err(ReplicationError::Forbidden);
+    }
+    let (file, len) = service::create_snapshot(ctx.into_inner(), &collection_id).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .no_chunking(len)
+        .streaming(file_stream(file)))
+}
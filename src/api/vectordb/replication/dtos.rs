@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub(crate) struct FollowerQueryDto {
+    /// Node id of the follower making the request
+    pub follower_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub(crate) struct VersionsQueryDto {
+    /// Last version the follower has applied
+    pub after: u32,
+    /// Node id of the follower making the request
+    pub follower_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ReplicationStatusDto {
+    /// `leader` or `follower`
+    pub role: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_url: Option<String>,
+    /// Last successful sync with the leader, on followers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "2023-01-01T12:00:00Z")]
+    pub last_contact: Option<DateTime<Utc>>,
+    /// Error of the last failed sync with the leader, on followers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub collections: Vec<CollectionReplicationDto>,
+    /// Followers that contacted the leader, on the leader
+    pub followers: Vec<FollowerStatusDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct CollectionReplicationDto {
+    pub name: String,
+    pub replication_factor: u32,
+    /// Current version of the collection on this server
+    pub version: u32,
+    /// Current version of the collection on the leader, on followers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_version: Option<u32>,
+    /// Number of versions this replica is behind the leader, on followers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lag_versions: Option<u32>,
+    /// Number of active followers with the current version, on the leader
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_sync_replicas: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct FollowerStatusDto {
+    pub node_id: String,
+    #[schema(value_type = String, example = "2023-01-01T12:00:00Z")]
+    pub last_seen: DateTime<Utc>,
+    /// Whether the follower contacted the leader recently
+    pub active: bool,
+    pub collections: Vec<FollowerCollectionDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct FollowerCollectionDto {
+    pub name: String,
+    /// Last version the follower applied
+    pub applied_version: u32,
+    /// Number of versions the follower is behind the leader
+    pub lag_versions: u32,
+    /// Age of the oldest version the follower hasn't applied, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lag_seconds: Option<i64>,
+}
@@ -0,0 +1,93 @@
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    HttpResponse, ResponseError,
+};
+use std::fmt::Display;
+
+use crate::models::common::WaCustomError;
+
+#[derive(Debug)]
+pub enum ReplicationError {
+    Forbidden,
+    ReadOnlyReplica,
+    NotLeader,
+    CollectionNotFound(String),
+    // the follower is ahead of the leader, and must restore a snapshot
+    Diverged(String),
+    // the versions the follower needs were pruned, it must restore a
+    // snapshot
+    Pruned(u32),
+    Busy(String),
+    ServerError(String),
+}
+
+impl Display for ReplicationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Forbidden => write!(f, "Only the admin user can access this endpoint"),
+            Self::ReadOnlyReplica => write!(
+                f,
+                "This server is a read replica, writes must be sent to the leader"
+            ),
+            Self::NotLeader => write!(f, "This server isn't a replication leader"),
+            Self::CollectionNotFound(name) => {
+                write!(f, "Replicated collection '{}' not found", name)
+            }
+            Self::Diverged(msg) => write!(f, "{}", msg),
+            Self::Pruned(version) => write!(
+                f,
+                "Versions up to {} are no longer available, restore a snapshot",
+                version
+            ),
+            Self::Busy(msg) => write!(f, "Collection is busy: {}", msg),
+            Self::ServerError(msg) => write!(f, "Server error: {}", msg),
+        }
+    }
+}
+
+impl ResponseError for ReplicationError {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let message = self.to_string();
+        HttpResponse::build(status)
+            .insert_header(ContentType::json())
+            .json(serde_json::json!({
+                "error": status.canonical_reason().unwrap_or("Error"),
+                "code": status.as_u16(),
+                "message": message
+            }))
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Forbidden | Self::ReadOnlyReplica => StatusCode::FORBIDDEN,
+            Self::NotLeader => StatusCode::BAD_REQUEST,
+            Self::CollectionNotFound(_) => StatusCode::NOT_FOUND,
+            Self::Diverged(_) => StatusCode::CONFLICT,
+            Self::Pruned(_) => StatusCode::GONE,
+            Self::Busy(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<WaCustomError> for ReplicationError {
+    fn from(error: WaCustomError) -> Self {
+        match error {
+            WaCustomError::LockError(msg) => Self::Busy(msg),
+            _ => Self::ServerError(error.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for ReplicationError {
+    fn from(error: std::io::Error) -> Self {
+        Self::ServerError(error.to_string())
+    }
+}
+
+impl From<lmdb::Error> for ReplicationError {
+    fn from(error: lmdb::Error) -> Self {
+        Self::ServerError(error.to_string())
+    }
+}
@@ -0,0 +1,24 @@
+use actix_web::{web, Scope};
+
+pub mod controller;
+pub(crate) mod dtos;
+mod error;
+pub(crate) mod read_only_middleware;
+mod service;
+
+pub(crate) fn replication_module() -> Scope {
+    web::scope("/replication")
+        .route("/status", web::get().to(controller::get_replication_status))
+        .route(
+            "/collections",
+            web::get().to(controller::list_replicated_collections),
+        )
+        .route(
+            "/collections/{collection_id}/versions",
+            web::get().to(controller::get_versions),
+        )
+        .route(
+            "/collections/{collection_id}/snapshot",
+            web::get().to(controller::download_snapshot),
+        )
+}
@@ -0,0 +1,74 @@
+use std::future::{ready, Ready};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::Method,
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+
+use super::error::ReplicationError;
+
+// Rejects writes on followers, whose collections are replicas of the
+// leader's. Searches are sent as POST requests, and remain allowed along
+// with the admin routes.
+pub(crate) struct ReadOnlyReplicaMiddleware(pub bool);
+
+impl<S, B> Transform<S, ServiceRequest> for ReadOnlyReplicaMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ReadOnlyReplicaMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ReadOnlyReplicaMiddlewareService {
+            service,
+            read_only: self.0,
+        }))
+    }
+}
+
+pub(crate) struct ReadOnlyReplicaMiddlewareService<S> {
+    service: S,
+    read_only: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for ReadOnlyReplicaMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if self.read_only && !is_allowed_on_replica(&req) {
+            return Box::pin(async move { Err(ReplicationError::ReadOnlyReplica.into()) });
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            Ok(res)
+        })
+    }
+}
+
+fn is_allowed_on_replica(req: &ServiceRequest) -> bool {
+    let method = req.method();
+    if method == Method::GET || method == Method::HEAD {
+        return true;
+    }
+    let path = req.path();
+    path.contains("/search/") || path.starts_with("/vectordb/admin/")
+}
@@ -0,0 +1,251 @@
+use std::{fs::File, sync::Arc};
+
+use actix_web::web;
+use chrono::Utc;
+use tempfile::NamedTempFile;
+
+use crate::{
+    app_context::AppContext,
+    config_loader::ReplicationRole,
+    models::{
+        collection::Collection,
+        meta_persist::retrieve_background_version,
+        paths::get_data_path,
+        replication::{
+            index_names, is_replica, is_replicated, prune_log, pruned_through, read_log,
+            ReplicatedCollection, ReplicationBatch,
+        },
+        versioning::VersionNumber,
+    },
+};
+
+use super::{
+    dtos::{
+        CollectionReplicationDto, FollowerCollectionDto, FollowerStatusDto, ReplicationStatusDto,
+    },
+    error::ReplicationError,
+};
+
+fn replicated_collections(ctx: &AppContext) -> Vec<Arc<Collection>> {
+    let mut collections: Vec<_> = ctx
+        .ain_env
+        .collections_map
+        .iter_collections()
+        .map(|collection| collection.value().clone())
+        .filter(|collection| is_replicated(collection))
+        .collect();
+    collections.sort_by(|a, b| a.meta.name.cmp(&b.meta.name));
+    collections
+}
+
+fn ensure_leader(ctx: &AppContext) -> Result<(), ReplicationError> {
+    if ctx.config.replication.role != ReplicationRole::Leader {
+        return Err(ReplicationError::NotLeader);
+    }
+    Ok(())
+}
+
+fn get_replicated_collection(
+    ctx: &AppContext,
+    name: &str,
+) -> Result<Arc<Collection>, ReplicationError> {
+    ctx.ain_env
+        .collections_map
+        .get_collection(name)
+        .filter(|collection| is_replicated(collection))
+        .ok_or_else(|| ReplicationError::CollectionNotFound(name.to_string()))
+}
+
+pub(crate) async fn get_replication_status(
+    ctx: Arc<AppContext>,
+) -> Result<ReplicationStatusDto, ReplicationError> {
+    let replication = &ctx.config.replication;
+    if replication.role == ReplicationRole::Follower {
+        let leader = ctx.replication.leader();
+        let mut collections = Vec::new();
+        for collection in replicated_collections(&ctx) {
+            if !is_replica(&collection) {
+                continue;
+            }
+            let version = **collection.current_version.read();
+            let leader_version = leader.versions.get(&collection.meta.name).copied();
+            collections.push(CollectionReplicationDto {
+                name: collection.meta.name.clone(),
+                replication_factor: collection.meta.config.replication_factor.unwrap_or(1),
+                version,
+                leader_version,
+                lag_versions: leader_version.map(|leader| leader.saturating_sub(version)),
+                in_sync_replicas: None,
+            });
+        }
+        return Ok(ReplicationStatusDto {
+            role: replication.role.as_str().to_string(),
+            leader_url: replication.leader_url.clone(),
+            last_contact: leader.last_contact,
+            last_error: leader.last_error,
+            collections,
+            followers: Vec::new(),
+        });
+    }
+
+    let mut collections = Vec::new();
+    for collection in replicated_collections(&ctx) {
+        let version = *retrieve_background_version(&collection.lmdb)?;
+        collections.push((collection, version));
+    }
+
+    let now = Utc::now();
+    let followers = ctx
+        .replication
+        .followers()
+        .into_iter()
+        .map(|(node_id, follower)| {
+            let mut follower_collections = Vec::new();
+            for (collection, leader_version) in &collections {
+                let Some(&applied_version) = follower.applied.get(&collection.meta.name) else {
+                    continue;
+                };
+                let lag_versions = leader_version.saturating_sub(applied_version);
+                // age of the first version the follower is missing
+                let lag_seconds = if lag_versions == 0 {
+                    Some(0)
+                } else {
+                    collection
+                        .vcs
+                        .get_version(VersionNumber::from(applied_version + 1))
+                        .ok()
+                        .map(|info| (now - info.created_at).num_seconds().max(0))
+                };
+                follower_collections.push(FollowerCollectionDto {
+                    name: collection.meta.name.clone(),
+                    applied_version,
+                    lag_versions,
+                    lag_seconds,
+                });
+            }
+            FollowerStatusDto {
+                node_id,
+                last_seen: follower.last_seen,
+                active: follower.is_active(),
+                collections: follower_collections,
+            }
+        })
+        .collect();
+
+    let collections = collections
+        .into_iter()
+        .map(|(collection, version)| CollectionReplicationDto {
+            name: collection.meta.name.clone(),
+            replication_factor: collection.meta.config.replication_factor.unwrap_or(1),
+            version,
+            leader_version: None,
+            lag_versions: None,
+            in_sync_replicas: Some(
+                ctx.replication
+                    .in_sync_replicas(&collection.meta.name, version),
+            ),
+        })
+        .collect();
+
+    Ok(ReplicationStatusDto {
+        role: replication.role.as_str().to_string(),
+        leader_url: None,
+        last_contact: None,
+        last_error: None,
+        collections,
+        followers,
+    })
+}
+
+pub(crate) async fn list_replicated_collections(
+    ctx: Arc<AppContext>,
+    follower_id: Option<String>,
+) -> Result<Vec<ReplicatedCollection>, ReplicationError> {
+    ensure_leader(&ctx)?;
+    if let Some(follower_id) = &follower_id {
+        ctx.replication.record_follower(follower_id);
+    }
+
+    replicated_collections(&ctx)
+        .into_iter()
+        .map(|collection| {
+            Ok(ReplicatedCollection {
+                name: collection.meta.name.clone(),
+                replication_factor: collection.meta.config.replication_factor.unwrap_or(1),
+                version: **collection.current_version.read(),
+                indexes: index_names(&collection),
+            })
+        })
+        .collect()
+}
+
+pub(crate) async fn get_versions(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    after: u32,
+    follower_id: Option<String>,
+) -> Result<ReplicationBatch, ReplicationError> {
+    ensure_leader(&ctx)?;
+    let collection = get_replicated_collection(&ctx, collection_id)?;
+
+    let current_version = **collection.current_version.read();
+    if after > current_version {
+        return Err(ReplicationError::Diverged(format!(
+            "Version {} is ahead of the leader's version {}, restore a snapshot",
+            after, current_version
+        )));
+    }
+    let pruned = pruned_through(&collection)?;
+    if after < pruned {
+        return Err(ReplicationError::Pruned(pruned));
+    }
+
+    if let Some(follower_id) = &follower_id {
+        ctx.replication
+            .record_progress(follower_id, collection_id, after);
+        let replication_factor = collection.meta.config.replication_factor.unwrap_or(1);
+        if let Some(applied) = ctx
+            .replication
+            .applied_by_all(collection_id, replication_factor)
+        {
+            if applied > pruned {
+                prune_log(&collection, applied)?;
+            }
+        }
+    }
+
+    let leader_version = *retrieve_background_version(&collection.lmdb)?;
+    let versions = web::block(move || read_log(&collection, after))
+        .await
+        .map_err(|e| ReplicationError::ServerError(e.to_string()))??;
+    Ok(ReplicationBatch {
+        leader_version,
+        versions,
+    })
+}
+
+/// Takes a snapshot of the collection, returns the opened snapshot file and
+/// its length
+pub(crate) async fn create_snapshot(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+) -> Result<(File, u64), ReplicationError> {
+    ensure_leader(&ctx)?;
+    let collection = get_replicated_collection(&ctx, collection_id)?;
+
+    web::block(move || {
+        let snapshots_path = get_data_path().join("snapshots");
+        std::fs::create_dir_all(&snapshots_path)?;
+        // the file is deleted when dropped, it remains readable through the
+        // opened handle until the download is over
+        let tmp_file = NamedTempFile::new_in(&snapshots_path)?;
+        ctx.ain_env
+            .collections_map
+            .write_snapshot(&collection, &ctx.config, tmp_file.path())?;
+        let file = File::open(tmp_file.path())?;
+        let len = file.metadata()?.len();
+        Ok((file, len))
+    })
+    .await
+    .map_err(|e| ReplicationError::ServerError(e.to_string()))?
+}
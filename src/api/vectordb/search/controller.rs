@@ -4,9 +4,10 @@ use crate::app_context::AppContext;
 use crate::models::collection_cache::CollectionCacheExt;
 
 use super::dtos::{
-    BatchDenseSearchRequestDto, BatchSearchResponseDto, BatchSearchTFIDFDocumentsDto,
-    BatchSparseSearchRequestDto, DenseSearchRequestDto, FindSimilarTFIDFDocumentDto,
-    HybridSearchRequestDto, SearchResponseDto, SparseSearchRequestDto, BatchHybridSearchRequestDto,
+    BatchDenseSearchRequestDto, BatchHybridSearchRequestDto, BatchSearchResponseDto,
+    BatchSearchTFIDFDocumentsDto, BatchSparseSearchRequestDto, DenseSearchRequestDto,
+    FindSimilarTFIDFDocumentDto, HybridSearchRequestDto, MultiVectorSearchRequestDto,
+    SearchResponseDto, SparseSearchRequestDto,
 };
 use super::error::SearchError;
 
@@ -79,6 +80,37 @@ pub(crate) async fn batch_dense_search(
     Ok(HttpResponse::Ok().json(results))
 }
 
+/// Search multi-vector documents by late interaction
+///
+/// Retrieves the nearest vectors of each query vector, and ranks their documents by MaxSim over all the vectors of each document.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/search/multi-vector",
+    tag = "search",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier")
+    ),
+    request_body = MultiVectorSearchRequestDto,
+    responses(
+        (status = 200, description = "Search successfully completed", body = SearchResponseDto),
+        (status = 404, description = "Collection not found", body = String),
+        (status = 400, description = "Invalid filter or other request error", body = String),
+        (status = 500, description = "Internal server error", body = String)
+    )
+)]
+pub(crate) async fn multi_vector_search(
+    path: web::Path<String>,
+    web::Json(body): web::Json<MultiVectorSearchRequestDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, SearchError> {
+    let collection_id = path.into_inner();
+    ctx.update_collection_for_query(&collection_id)
+        .map_err(|e| SearchError::InternalServerError(format!("Cache update error: {}", e)))?;
+
+    let results = service::multi_vector_search(ctx.into_inner(), &collection_id, body).await?;
+    Ok(HttpResponse::Ok().json(results))
+}
+
 /// Search using sparse vector embeddings
 ///
 /// Performs a similarity search using sparse vector embeddings.
@@ -1,4 +1,6 @@
-use crate::metadata::query_filtering::Filter;
+use crate::api::vectordb::vectors::dtos::VectorField;
+use crate::indexes::SearchResult;
+use crate::metadata::{query_filtering::Filter, MetadataFields};
 use crate::models::types::VectorId;
 use crate::{indexes::inverted::types::SparsePair, models::types::DocumentId};
 use serde::{Deserialize, Serialize};
@@ -11,42 +13,146 @@ fn default_fusion_constant_k() -> f32 {
     60.0
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+fn default_candidates_per_vector() -> usize {
+    100
+}
+
+/// Field the results of a search are grouped by
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum GroupBy {
+    DocumentId,
+}
+
+/// How the scores of the results of a group make its score
+#[derive(Deserialize, Debug, Clone, Copy, Default, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum GroupAggregation {
+    #[default]
+    Max,
+    Sum,
+    Mean,
+}
+
+/// Grouping of the results of a search, so that the results of a single
+/// document don't crowd out the others
+#[derive(Deserialize, Debug, Clone, Default, utoipa::ToSchema)]
+pub(crate) struct GroupingDto {
+    /// Returns `groups` of results instead of `results`, vectors without a
+    /// document being groups of their own
+    pub group_by: Option<GroupBy>,
+    /// Best results returned per group, 3 by default
+    pub group_size: Option<usize>,
+    /// Groups returned, `top_k` by default
+    pub groups_limit: Option<usize>,
+    #[serde(default)]
+    pub group_aggregation: GroupAggregation,
+}
+
+/// Fields of the vectors returned inline with the results of a search,
+/// which otherwise only have their ids and scores
+#[derive(Deserialize, Debug, Clone, Default, utoipa::ToSchema)]
+pub(crate) struct ResultFieldsDto {
+    /// Returns the metadata of the vectors, same as `"fields": ["metadata"]`
+    #[serde(default)]
+    pub with_payload: bool,
+    /// Fields of the vectors to return
+    #[serde(default)]
+    pub fields: Vec<VectorField>,
+}
+
+impl ResultFieldsDto {
+    pub fn has_field(&self, field: VectorField) -> bool {
+        self.fields.contains(&field) || (self.with_payload && field == VectorField::Metadata)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && !self.with_payload
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct DenseSearchRequestDto {
+    #[serde(default)]
     pub query_vector: Vec<f32>,
+    /// Embedded with the collection's dense embedding provider, instead of
+    /// searching `query_vector`
+    pub query_text: Option<String>,
     pub top_k: Option<usize>,
     #[schema(value_type = Option<String>)]
     pub filter: Option<Filter>,
     #[serde(default)]
     pub return_raw_text: bool,
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct BatchDenseSearchRequestQueryDto {
     pub vector: Vec<f32>,
     #[schema(value_type = Option<String>)]
     pub filter: Option<Filter>,
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct BatchDenseSearchRequestDto {
     pub queries: Vec<BatchDenseSearchRequestQueryDto>,
     pub top_k: Option<usize>,
     #[serde(default)]
     pub return_raw_text: bool,
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+/// Late-interaction search of multi-vector documents, whose token vectors
+/// are the vectors sharing a `document_id`
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
+pub(crate) struct MultiVectorSearchRequestDto {
+    /// Token vectors of the query
+    pub query_vectors: Vec<Vec<f32>>,
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    /// Nearest vectors retrieved for each query vector, whose documents are
+    /// then reranked by MaxSim over all their vectors
+    #[serde(default = "default_candidates_per_vector")]
+    pub candidates_per_vector: usize,
+    #[schema(value_type = Option<String>)]
+    pub filter: Option<Filter>,
+    #[serde(default)]
+    pub return_raw_text: bool,
+    /// Documents are scored as a whole, so each group has a single hit, the
+    /// best matching vector of the document, whatever the `group_size`
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
+}
+
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct SparseSearchRequestDto {
     #[schema(value_type = Vec<String>)]
+    #[serde(default)]
     pub query_terms: Vec<SparsePair>,
+    /// Embedded with the collection's sparse embedding provider, instead of
+    /// searching `query_terms`
+    pub query_text: Option<String>,
     pub top_k: Option<usize>,
     pub early_terminate_threshold: Option<f32>,
     #[serde(default)]
     pub return_raw_text: bool,
+    #[serde(default)]
+    pub explain: bool,
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct BatchSparseSearchRequestDto {
     #[schema(value_type = Vec<Vec<String>>)]
     pub query_terms_list: Vec<Vec<SparsePair>>,
@@ -54,9 +160,15 @@ pub(crate) struct BatchSparseSearchRequestDto {
     pub early_terminate_threshold: Option<f32>,
     #[serde(default)]
     pub return_raw_text: bool,
+    #[serde(default)]
+    pub explain: bool,
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct HybridSearchRequestDto {
     #[serde(flatten)]
     pub query: HybridSearchQuery,
@@ -66,9 +178,13 @@ pub(crate) struct HybridSearchRequestDto {
     pub fusion_constant_k: f32,
     #[serde(default)]
     pub return_raw_text: bool,
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 #[serde(untagged)]
 pub(crate) enum HybridSearchQuery {
     DenseAndSparse {
@@ -87,9 +203,15 @@ pub(crate) enum HybridSearchQuery {
         query_text: String,
         sparse_early_terminate_threshold: Option<f32>,
     },
+    /// Embedded with the collection's dense embedding provider, and with its
+    /// sparse one if configured, otherwise searched with TF-IDF
+    Text {
+        query_text: String,
+        sparse_early_terminate_threshold: Option<f32>,
+    },
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct BatchHybridSearchRequestDto {
     pub queries: Vec<HybridSearchQuery>,
     #[serde(default = "default_top_k")]
@@ -98,6 +220,10 @@ pub(crate) struct BatchHybridSearchRequestDto {
     pub fusion_constant_k: f32,
     #[serde(default)]
     pub return_raw_text: bool,
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
 }
 
 #[derive(Serialize, Debug, Clone, utoipa::ToSchema)]
@@ -108,12 +234,86 @@ pub(crate) struct SearchResultItemDto {
     pub document_id: Option<DocumentId>,
     pub score: f32,
     pub text: Option<String>,
+    #[schema(value_type = Object, nullable = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataFields>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dense_values: Option<Vec<f32>>,
+    #[schema(value_type = Object, nullable = true)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparse_values: Option<Vec<SparsePair>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<ScoreExplanationDto>,
+}
+
+impl From<SearchResult> for SearchResultItemDto {
+    fn from((id, document_id, score, text): SearchResult) -> Self {
+        Self {
+            id,
+            document_id,
+            score,
+            text,
+            metadata: None,
+            dense_values: None,
+            sparse_values: None,
+            explanation: None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, utoipa::ToSchema)]
+pub(crate) struct BM25TermExplanationDto {
+    pub term: String,
+    pub document_frequency: u32,
+    pub idf: f32,
+    pub term_frequency: Option<u32>,
+    pub length_normalization: Option<f32>,
+    pub bm25_term_frequency: f32,
+    pub contribution: f32,
+}
+
+#[derive(Serialize, Debug, Clone, utoipa::ToSchema)]
+pub(crate) struct SparseTermExplanationDto {
+    pub index: u32,
+    pub query_value: f32,
+    pub document_value: f32,
+    pub contribution: f32,
+}
+
+/// Per-term breakdown of a search result's score, returned when the
+/// request sets `explain`
+#[derive(Serialize, Debug, Clone, utoipa::ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ScoreExplanationDto {
+    Bm25 {
+        k1: f32,
+        b: f32,
+        average_document_length: f32,
+        total_documents: u32,
+        document_length: Option<u32>,
+        terms: Vec<BM25TermExplanationDto>,
+    },
+    // contributions are computed from the raw sparse values, so they may
+    // not add up to the score when the search ran on quantized values
+    Sparse {
+        terms: Vec<SparseTermExplanationDto>,
+    },
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub(crate) struct SearchResultGroupDto {
+    #[schema(value_type = Option<String>)]
+    pub document_id: Option<DocumentId>,
+    pub score: f32,
+    pub hits: Vec<SearchResultItemDto>,
 }
 
 #[derive(Serialize, Debug, utoipa::ToSchema)]
 pub(crate) struct SearchResponseDto {
     pub results: Vec<SearchResultItemDto>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<SearchResultGroupDto>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub warning: Option<String>,
 }
 
@@ -124,18 +324,30 @@ pub(crate) struct BatchSearchResponseDto {
     pub warning: Option<String>,
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct FindSimilarTFIDFDocumentDto {
     pub query: String,
     pub top_k: Option<usize>,
     #[serde(default)]
     pub return_raw_text: bool,
+    #[serde(default)]
+    pub explain: bool,
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
 }
 
-#[derive(Deserialize, Debug, utoipa::ToSchema)]
+#[derive(Deserialize, Debug, Clone, utoipa::ToSchema)]
 pub(crate) struct BatchSearchTFIDFDocumentsDto {
     pub queries: Vec<String>,
     pub top_k: Option<usize>,
     #[serde(default)]
     pub return_raw_text: bool,
+    #[serde(default)]
+    pub explain: bool,
+    #[serde(flatten)]
+    pub grouping: GroupingDto,
+    #[serde(flatten)]
+    pub result_fields: ResultFieldsDto,
 }
@@ -13,7 +13,7 @@ pub(crate) enum SearchError {
     InvalidFilter(String),
     InternalServerError(String),
     WaCustom(WaCustomError),
-    #[allow(dead_code)]
+    EmbeddingFailed(String),
     InvalidInput(String),
 }
 
@@ -25,6 +25,7 @@ impl Display for SearchError {
             SearchError::InvalidFilter(msg) => write!(f, "Invalid metadata filter: {}", msg),
             SearchError::InternalServerError(msg) => write!(f, "Internal server error: {}", msg),
             SearchError::WaCustom(e) => write!(f, "Internal search error: {:?}", e),
+            SearchError::EmbeddingFailed(msg) => write!(f, "Failed to embed query text: {}", msg),
             Self::InvalidInput(msg) => write!(f, "Invalid input for search: {}", msg),
         }
     }
@@ -50,6 +51,7 @@ impl ResponseError for SearchError {
             SearchError::InvalidFilter(_) => StatusCode::BAD_REQUEST,
             SearchError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             SearchError::WaCustom(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            SearchError::EmbeddingFailed(_) => StatusCode::BAD_GATEWAY,
             Self::InvalidInput(_) => StatusCode::BAD_REQUEST,
         }
     }
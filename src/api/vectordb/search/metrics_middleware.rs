@@ -0,0 +1,88 @@
+use std::future::{ready, Ready};
+use std::time::Instant;
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::StatusCode,
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+
+use crate::models::{metrics, tracing};
+
+const SEARCH_TYPES: [&str; 9] = [
+    "dense",
+    "batch-dense",
+    "multi-vector",
+    "sparse",
+    "batch-sparse",
+    "tf-idf",
+    "batch-tf-idf",
+    "hybrid",
+    "batch-hybrid",
+];
+
+// Records the request count, errors and latency of the searches of each
+// collection, wraps the search scope so that the collection is known. The
+// searches also carry their trace along with their future.
+pub(crate) struct SearchMetricsMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for SearchMetricsMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SearchMetricsMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SearchMetricsMiddlewareService { service }))
+    }
+}
+
+pub(crate) struct SearchMetricsMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for SearchMetricsMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        // the rest of the path within the scope, e.g. `/dense`
+        let search_type = SEARCH_TYPES
+            .into_iter()
+            .find(|search_type| req.match_info().unprocessed() == format!("/{}", search_type));
+        let collection = req.match_info().get("collection_id").map(str::to_string);
+        let start = Instant::now();
+
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            // hybrid searches hold their trace across the `.await`s of the
+            // query embedding
+            let res = tracing::traced(fut).await;
+            let status = res.as_ref().ok().map(|res| res.status());
+            // searches of missing collections are not recorded, so that
+            // requests can't create arbitrary labels
+            if let (Some(search_type), Some(collection)) = (search_type, collection) {
+                if status != Some(StatusCode::NOT_FOUND) {
+                    let ok = status.is_some_and(|status| status.is_success());
+                    metrics::record_search(&collection, search_type, start.elapsed(), ok);
+                }
+            }
+            res
+        })
+    }
+}
@@ -1,15 +1,125 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
+use actix_web::web;
+use chrono::Utc;
 use rustc_hash::FxHashMap;
 
 use super::dtos;
 use super::error::SearchError;
+use crate::api::vectordb::vectors::dtos::VectorField;
 use crate::app_context::AppContext;
 use crate::indexes::hnsw::{DenseSearchInput, DenseSearchOptions};
+use crate::indexes::inverted::types::SparsePair;
 use crate::indexes::inverted::{SparseSearchInput, SparseSearchOptions};
 use crate::indexes::tf_idf::{TFIDFSearchInput, TFIDFSearchOptions};
 use crate::indexes::{IndexOps, SearchResult};
-use crate::models::types::{DocumentId, VectorId};
+use crate::metadata::query_filtering::Filter;
+use crate::models::collection::{Collection, RawVectorEmbedding};
+use crate::models::dot_product::dot_product_f32;
+use crate::models::sparse_ann_query::explain_sparse_score;
+use crate::models::tracing::{self, SlowQuery, TraceGuard};
+use crate::models::types::{DistanceMetric, DocumentId, InternalId, VectorId};
+
+// Traces the search, which is written to the slow query log if it takes
+// longer than the configured threshold
+fn trace_search(
+    collection_id: &str,
+    query_type: &'static str,
+    top_k: Option<usize>,
+    filter: Option<&Filter>,
+) -> TraceGuard {
+    tracing::start_search(SlowQuery {
+        collection: collection_id.to_string(),
+        query_type,
+        top_k,
+        filter: filter.map(|filter| format!("{:?}", filter)),
+    })
+}
+
+// embeds `query_text` with the dense embedding provider of the collection
+pub(crate) async fn embed_dense_query(
+    ctx: &AppContext,
+    collection_id: &str,
+    query_text: String,
+) -> Result<Vec<f32>, SearchError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let dense_vector = collection.meta.dense_vector.clone();
+    if dense_vector.embedding.is_none() {
+        return Err(SearchError::InvalidInput(format!(
+            "Collection '{}' has no dense embedding provider",
+            collection_id
+        )));
+    }
+
+    let providers = ctx.embedding_providers.clone();
+    web::block(move || providers.embed_dense(&dense_vector, &[&query_text]))
+        .await
+        .map_err(|err| SearchError::InternalServerError(err.to_string()))?
+        .map(|mut embeddings| embeddings.remove(0))
+        .map_err(|err| SearchError::EmbeddingFailed(err.to_string()))
+}
+
+// embeds `query_text` with the sparse embedding provider of the collection
+pub(crate) async fn embed_sparse_query(
+    ctx: &AppContext,
+    collection_id: &str,
+    query_text: String,
+) -> Result<Vec<SparsePair>, SearchError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let sparse_vector = collection.meta.sparse_vector.clone();
+    if sparse_vector.embedding.is_none() {
+        return Err(SearchError::InvalidInput(format!(
+            "Collection '{}' has no sparse embedding provider",
+            collection_id
+        )));
+    }
+
+    let providers = ctx.embedding_providers.clone();
+    web::block(move || providers.embed_sparse(&sparse_vector, &[&query_text]))
+        .await
+        .map_err(|err| SearchError::InternalServerError(err.to_string()))?
+        .map(|mut embeddings| embeddings.remove(0))
+        .map_err(|err| SearchError::EmbeddingFailed(err.to_string()))
+}
+
+// embeds the text of a `Text` hybrid query, which is then searched like
+// the other kinds of queries
+async fn embed_hybrid_query(
+    ctx: &AppContext,
+    collection: &Collection,
+    query: dtos::HybridSearchQuery,
+) -> Result<dtos::HybridSearchQuery, SearchError> {
+    let dtos::HybridSearchQuery::Text {
+        query_text,
+        sparse_early_terminate_threshold,
+    } = query
+    else {
+        return Ok(query);
+    };
+
+    let collection_id = &collection.meta.name;
+    let query_vector = embed_dense_query(ctx, collection_id, query_text.clone()).await?;
+    if collection.meta.sparse_vector.embedding.is_none() {
+        return Ok(dtos::HybridSearchQuery::DenseAndTFIDF {
+            query_vector,
+            query_text,
+        });
+    }
+    Ok(dtos::HybridSearchQuery::DenseAndSparse {
+        query_vector,
+        query_terms: embed_sparse_query(ctx, collection_id, query_text).await?,
+        sparse_early_terminate_threshold,
+    })
+}
 
 pub(crate) async fn dense_search(
     ctx: Arc<AppContext>,
@@ -21,6 +131,12 @@ pub(crate) async fn dense_search(
         .collections_map
         .get_collection(collection_id)
         .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(
+        collection_id,
+        "dense",
+        request.top_k,
+        request.filter.as_ref(),
+    );
 
     let hnsw_index = collection.get_hnsw_index().ok_or_else(|| {
         SearchError::IndexNotFound(format!("HNSW index for collection '{}'", collection_id))
@@ -57,6 +173,7 @@ pub(crate) async fn batch_dense_search(
         .collections_map
         .get_collection(collection_id)
         .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(collection_id, "batch-dense", request.top_k, None);
 
     let hnsw_index = collection.get_hnsw_index().ok_or_else(|| {
         SearchError::IndexNotFound(format!("HNSW index for collection '{}'", collection_id))
@@ -87,6 +204,166 @@ pub(crate) async fn batch_dense_search(
     ))
 }
 
+// Searches the nearest vectors of each query vector, and reranks their
+// documents by MaxSim over all the vectors of each document. Vectors
+// without a document are documents of their own.
+pub(crate) async fn multi_vector_search(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    request: dtos::MultiVectorSearchRequestDto,
+) -> Result<(Vec<SearchResult>, Option<String>), SearchError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(
+        collection_id,
+        "multi-vector",
+        Some(request.top_k),
+        request.filter.as_ref(),
+    );
+
+    let hnsw_index = collection.get_hnsw_index().ok_or_else(|| {
+        SearchError::IndexNotFound(format!("HNSW index for collection '{}'", collection_id))
+    })?;
+    if request.query_vectors.is_empty() {
+        return Err(SearchError::InvalidInput(
+            "`query_vectors` must not be empty".to_string(),
+        ));
+    }
+    if let Some(vector) = request
+        .query_vectors
+        .iter()
+        .find(|vector| vector.len() != hnsw_index.dim)
+    {
+        return Err(SearchError::InvalidInput(format!(
+            "Expected dimension of query vectors to be {}, found {}",
+            hnsw_index.dim,
+            vector.len()
+        )));
+    }
+
+    let warning = collection.is_indexing().then(|| {
+        "Embeddings are currently being indexed; some results may be temporarily unavailable."
+            .to_string()
+    });
+
+    let candidates = hnsw_index
+        .batch_search(
+            &collection,
+            request
+                .query_vectors
+                .iter()
+                .map(|vector| DenseSearchInput(vector.clone(), request.filter.clone()))
+                .collect(),
+            &DenseSearchOptions {
+                top_k: Some(request.candidates_per_vector),
+            },
+            &ctx.config,
+            false,
+        )
+        .map_err(SearchError::WaCustom)?;
+
+    // similarities are cosine ones, unless the index uses the dot product
+    let is_dot_product = matches!(
+        *hnsw_index.distance_metric.read().unwrap(),
+        DistanceMetric::DotProduct
+    );
+    let query_vectors: Vec<Vec<f32>> = if is_dot_product {
+        request.query_vectors
+    } else {
+        request
+            .query_vectors
+            .iter()
+            .map(|vector| normalize(vector))
+            .collect()
+    };
+
+    let now = Utc::now();
+    let mut scored = HashSet::new();
+    let mut results = Vec::new();
+    for (vector_id, document_id, _, _) in candidates.into_iter().flatten() {
+        let vectors = match &document_id {
+            Some(document_id) => {
+                if !scored.insert(document_id.clone()) {
+                    continue;
+                }
+                collection.vectors_of_document(document_id)
+            }
+            None => collection
+                .external_to_internal_map
+                .get_latest(&vector_id)
+                .and_then(|internal_id| collection.get_raw_emb_by_internal_id(internal_id))
+                .into_iter()
+                .collect(),
+        };
+        let vectors: Vec<_> = vectors
+            .into_iter()
+            .filter(|vector| !vector.is_expired(now))
+            .collect();
+        let Some((score, best_vector)) = max_sim(&query_vectors, &vectors, is_dot_product) else {
+            continue;
+        };
+        results.push((
+            best_vector.id.clone(),
+            document_id,
+            score,
+            best_vector.text.clone().filter(|_| request.return_raw_text),
+        ));
+    }
+
+    results.sort_unstable_by(|a, b| b.2.total_cmp(&a.2));
+    results.truncate(request.top_k);
+    Ok((results, warning))
+}
+
+fn normalize(values: &[f32]) -> Vec<f32> {
+    let norm = dot_product_f32(values, values).sqrt();
+    if norm == 0.0 {
+        return values.to_vec();
+    }
+    values.iter().map(|value| value / norm).collect()
+}
+
+// Returns the MaxSim score of a document, the sum over the query vectors of
+// their highest similarity to a vector of the document, along with the
+// vector of the document most similar to a query vector
+fn max_sim<'a>(
+    query_vectors: &[Vec<f32>],
+    vectors: &[&'a RawVectorEmbedding],
+    is_dot_product: bool,
+) -> Option<(f32, &'a RawVectorEmbedding)> {
+    let document_vectors: Vec<(Vec<f32>, &RawVectorEmbedding)> = vectors
+        .iter()
+        .filter_map(|vector| {
+            let values = vector.dense_values.as_deref()?;
+            let values = if is_dot_product {
+                values.to_vec()
+            } else {
+                normalize(values)
+            };
+            Some((values, *vector))
+        })
+        .collect();
+
+    let (_, first_vector) = document_vectors.first()?;
+    let mut best = (f32::NEG_INFINITY, *first_vector);
+    let mut score = 0.0;
+    for query_vector in query_vectors {
+        let mut max_similarity = f32::NEG_INFINITY;
+        for (values, vector) in &document_vectors {
+            let similarity = dot_product_f32(query_vector, values);
+            max_similarity = max_similarity.max(similarity);
+            if similarity > best.0 {
+                best = (similarity, vector);
+            }
+        }
+        score += max_similarity;
+    }
+    Some((score, best.1))
+}
+
 pub(crate) async fn sparse_search(
     ctx: Arc<AppContext>,
     collection_id: &str,
@@ -97,6 +374,7 @@ pub(crate) async fn sparse_search(
         .collections_map
         .get_collection(collection_id)
         .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(collection_id, "sparse", request.top_k, None);
 
     let inverted_index = collection.get_inverted_index().ok_or_else(|| {
         SearchError::IndexNotFound(format!("Sparse index for collection '{}'", collection_id))
@@ -134,6 +412,7 @@ pub(crate) async fn batch_sparse_search(
         .collections_map
         .get_collection(collection_id)
         .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(collection_id, "batch-sparse", request.top_k, None);
 
     let inverted_index = collection.get_inverted_index().ok_or_else(|| {
         SearchError::IndexNotFound(format!("Sparse index for collection '{}'", collection_id))
@@ -175,8 +454,9 @@ pub(crate) async fn hybrid_search(
         .collections_map
         .get_collection(collection_id)
         .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(collection_id, "hybrid", Some(request.top_k), None);
 
-    let results_pair = match request.query {
+    let results_pair = match embed_hybrid_query(&ctx, &collection, request.query).await? {
         dtos::HybridSearchQuery::DenseAndSparse {
             query_vector,
             query_terms,
@@ -301,6 +581,7 @@ pub(crate) async fn hybrid_search(
 
             (sparse_results, tf_idf_results)
         }
+        dtos::HybridSearchQuery::Text { .. } => unreachable!("text queries are embedded first"),
     };
 
     let warning = collection.is_indexing().then(|| {
@@ -350,6 +631,7 @@ pub(crate) async fn batch_hybrid_search(
         .collections_map
         .get_collection(collection_id)
         .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(collection_id, "batch-hybrid", Some(request.top_k), None);
 
     let warning = collection.is_indexing().then(|| {
         "Embeddings are currently being indexed; some results may be temporarily unavailable."
@@ -364,7 +646,7 @@ pub(crate) async fn batch_hybrid_search(
     let mut query_mapping = Vec::new(); // Track which queries use which types
 
     for (query_idx, query) in request.queries.into_iter().enumerate() {
-        match query {
+        match embed_hybrid_query(&ctx, &collection, query).await? {
             dtos::HybridSearchQuery::DenseAndSparse {
                 query_vector,
                 query_terms,
@@ -415,6 +697,9 @@ pub(crate) async fn batch_hybrid_search(
                 sparse_queries.push(query_terms);
                 tfidf_queries.push(query_text);
             }
+            dtos::HybridSearchQuery::Text { .. } => {
+                unreachable!("text queries are embedded first")
+            }
         }
     }
 
@@ -429,6 +714,8 @@ pub(crate) async fn batch_hybrid_search(
                         queries: dense_queries,
                         top_k: Some(request.top_k * 3), // Same as hybrid_search
                         return_raw_text: request.return_raw_text,
+                        grouping: dtos::GroupingDto::default(),
+                        result_fields: dtos::ResultFieldsDto::default(),
                     },
                 )
                 .await
@@ -446,6 +733,9 @@ pub(crate) async fn batch_hybrid_search(
                         top_k: Some(request.top_k * 3),
                         early_terminate_threshold: None,
                         return_raw_text: request.return_raw_text,
+                        explain: false,
+                        grouping: dtos::GroupingDto::default(),
+                        result_fields: dtos::ResultFieldsDto::default(),
                     },
                 )
                 .await
@@ -462,6 +752,9 @@ pub(crate) async fn batch_hybrid_search(
                         queries: tfidf_queries,
                         top_k: Some(request.top_k * 3), // Same as hybrid_search
                         return_raw_text: request.return_raw_text,
+                        explain: false,
+                        grouping: dtos::GroupingDto::default(),
+                        result_fields: dtos::ResultFieldsDto::default(),
                     },
                 )
                 .await
@@ -564,6 +857,7 @@ pub(crate) async fn tf_idf_search(
         .collections_map
         .get_collection(collection_id)
         .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(collection_id, "tf-idf", request.top_k, None);
 
     let tf_idf_index = collection.get_tf_idf_index().ok_or_else(|| {
         SearchError::IndexNotFound(format!("TF-IDF index for collection '{}'", collection_id))
@@ -600,6 +894,7 @@ pub(crate) async fn batch_tf_idf_search(
         .collections_map
         .get_collection(collection_id)
         .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let _trace = trace_search(collection_id, "batch-tf-idf", request.top_k, None);
 
     let tf_idf_index = collection.get_tf_idf_index().ok_or_else(|| {
         SearchError::IndexNotFound(format!("TF-IDF index for collection '{}'", collection_id))
@@ -625,3 +920,159 @@ pub(crate) async fn batch_tf_idf_search(
         warning,
     ))
 }
+
+/// Looks up the internal id and raw embedding of every search result
+fn resolve_search_results<'a>(
+    collection: &'a Collection,
+    results: &[SearchResult],
+) -> Result<Vec<(InternalId, &'a RawVectorEmbedding)>, SearchError> {
+    results
+        .iter()
+        .map(|(id, ..)| {
+            let internal_id = *collection
+                .external_to_internal_map
+                .get_latest(id)
+                .ok_or_else(|| {
+                    SearchError::InternalServerError(format!("Vector '{}' not found", id))
+                })?;
+            let raw_emb = collection
+                .internal_to_external_map
+                .get_latest(&internal_id)
+                .ok_or_else(|| {
+                    SearchError::InternalServerError(format!(
+                        "Raw embedding of vector '{}' not found",
+                        id
+                    ))
+                })?;
+            Ok((internal_id, raw_emb))
+        })
+        .collect()
+}
+
+/// Sets the selected fields of the vectors of the search results, all
+/// looked up with a single read of the collection. Vectors deleted since
+/// the search are left without them.
+pub(crate) async fn fill_result_fields(
+    ctx: &AppContext,
+    collection_id: &str,
+    result_fields: &dtos::ResultFieldsDto,
+    items: Vec<&mut dtos::SearchResultItemDto>,
+) -> Result<(), SearchError> {
+    if result_fields.is_empty() || items.is_empty() {
+        return Ok(());
+    }
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+
+    for item in items {
+        let Some(raw_emb) = collection
+            .external_to_internal_map
+            .get_latest(&item.id)
+            .and_then(|internal_id| collection.get_raw_emb_by_internal_id(internal_id))
+        else {
+            continue;
+        };
+        if result_fields.has_field(VectorField::Metadata) {
+            item.metadata = raw_emb.metadata.clone();
+        }
+        if result_fields.has_field(VectorField::DenseValues) {
+            item.dense_values = raw_emb.dense_values.clone();
+        }
+        if result_fields.has_field(VectorField::SparseValues) {
+            item.sparse_values = raw_emb.sparse_values.clone();
+        }
+        if result_fields.has_field(VectorField::Text) {
+            item.text = raw_emb.text.clone();
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn explain_tf_idf_results(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    query: &str,
+    results: &[SearchResult],
+) -> Result<Vec<dtos::ScoreExplanationDto>, SearchError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+
+    let tf_idf_index = collection.get_tf_idf_index().ok_or_else(|| {
+        SearchError::IndexNotFound(format!("TF-IDF index for collection '{}'", collection_id))
+    })?;
+
+    let documents: Vec<_> = resolve_search_results(&collection, results)?
+        .into_iter()
+        .map(|(internal_id, raw_emb)| (internal_id, raw_emb.text.as_deref()))
+        .collect();
+    let explanations = tf_idf_index
+        .explain(query, &documents)
+        .map_err(|e| SearchError::WaCustom(e.into()))?;
+
+    let average_document_length = *tf_idf_index.average_document_length.read().unwrap();
+    let total_documents = tf_idf_index
+        .root
+        .total_documents_count
+        .load(std::sync::atomic::Ordering::Relaxed);
+
+    Ok(explanations
+        .into_iter()
+        .map(|explanation| dtos::ScoreExplanationDto::Bm25 {
+            k1: tf_idf_index.k1,
+            b: tf_idf_index.b,
+            average_document_length,
+            total_documents,
+            document_length: explanation.document_length,
+            terms: explanation
+                .terms
+                .into_iter()
+                .map(|term| dtos::BM25TermExplanationDto {
+                    term: term.term,
+                    document_frequency: term.document_frequency,
+                    idf: term.idf,
+                    term_frequency: term.term_frequency,
+                    length_normalization: term.length_normalization,
+                    bm25_term_frequency: term.bm25_term_frequency,
+                    contribution: term.contribution,
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+pub(crate) async fn explain_sparse_results(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    query_terms: &[SparsePair],
+    results: &[SearchResult],
+) -> Result<Vec<dtos::ScoreExplanationDto>, SearchError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+
+    Ok(resolve_search_results(&collection, results)?
+        .into_iter()
+        .map(|(_, raw_emb)| dtos::ScoreExplanationDto::Sparse {
+            terms: explain_sparse_score(
+                query_terms,
+                raw_emb.sparse_values.as_deref().unwrap_or_default(),
+            )
+            .into_iter()
+            .map(|term| dtos::SparseTermExplanationDto {
+                index: term.index,
+                query_value: term.query_value,
+                document_value: term.document_value,
+                contribution: term.contribution,
+            })
+            .collect(),
+        })
+        .collect())
+}
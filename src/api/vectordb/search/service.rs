@@ -1,34 +1,57 @@
 use crate::app_context::AppContext;
+use crate::indexes::SearchResult;
+use crate::models::collection::Collection;
+use crate::models::types::{DocumentId, VectorId};
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 
 use super::dtos::{
     BatchDenseSearchRequestDto, BatchHybridSearchRequestDto, BatchSearchResponseDto,
     BatchSearchTFIDFDocumentsDto, BatchSparseSearchRequestDto, DenseSearchRequestDto,
-    FindSimilarTFIDFDocumentDto, HybridSearchRequestDto, SearchResponseDto, SearchResultItemDto,
-    SparseSearchRequestDto,
+    FindSimilarTFIDFDocumentDto, GroupAggregation, GroupBy, GroupingDto, HybridSearchRequestDto,
+    MultiVectorSearchRequestDto, ResultFieldsDto, ScoreExplanationDto, SearchResponseDto,
+    SearchResultGroupDto, SearchResultItemDto, SparseSearchRequestDto,
 };
 use super::error::SearchError;
 use super::repo;
 
+// results returned per group by default
+const DEFAULT_GROUP_SIZE: usize = 3;
+// groups returned by default by searches without a `top_k`
+const DEFAULT_GROUPS_LIMIT: usize = 10;
+// results searched at most per query to find the groups
+const MAX_GROUPED_RESULTS: usize = 10_000;
+
 pub(crate) async fn dense_search(
     ctx: Arc<AppContext>,
     collection_id: &str,
-    request: DenseSearchRequestDto,
+    mut request: DenseSearchRequestDto,
 ) -> Result<SearchResponseDto, SearchError> {
-    let (results, warning) = repo::dense_search(ctx, collection_id, request).await?;
+    if let Some(query_text) = request.query_text.take() {
+        request.query_vector = repo::embed_dense_query(&ctx, collection_id, query_text).await?;
+    }
 
-    Ok(SearchResponseDto {
-        results: results
-            .into_iter()
-            .map(|(id, document_id, score, text)| SearchResultItemDto {
-                id,
-                document_id,
-                score,
-                text,
-            })
-            .collect(),
-        warning,
-    })
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &request.grouping,
+        request.top_k,
+        |top_k| {
+            let request = DenseSearchRequestDto {
+                top_k,
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let (results, warning) = repo::dense_search(ctx, collection_id, request).await?;
+                Ok((vec![into_result_items(results, None)], warning))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(single_response(responses, warning))
 }
 
 pub(crate) async fn batch_dense_search(
@@ -36,47 +59,112 @@ pub(crate) async fn batch_dense_search(
     collection_id: &str,
     request: BatchDenseSearchRequestDto,
 ) -> Result<BatchSearchResponseDto, SearchError> {
-    let (results_list, warning) = repo::batch_dense_search(ctx, collection_id, request).await?;
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &request.grouping,
+        request.top_k,
+        |top_k| {
+            let request = BatchDenseSearchRequestDto {
+                top_k,
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let (results_list, warning) =
+                    repo::batch_dense_search(ctx, collection_id, request).await?;
+                Ok((
+                    results_list
+                        .into_iter()
+                        .map(|results| into_result_items(results, None))
+                        .collect(),
+                    warning,
+                ))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(BatchSearchResponseDto { responses, warning })
+}
 
-    Ok(BatchSearchResponseDto {
-        responses: results_list
-            .into_iter()
-            .map(|results| SearchResponseDto {
-                results: results
-                    .into_iter()
-                    .map(|(id, document_id, score, text)| SearchResultItemDto {
-                        id,
-                        document_id,
-                        score,
-                        text,
-                    })
-                    .collect(),
-                warning: None,
-            })
-            .collect(),
-        warning,
-    })
+pub(crate) async fn multi_vector_search(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    request: MultiVectorSearchRequestDto,
+) -> Result<SearchResponseDto, SearchError> {
+    // documents are scored as a whole, a single hit represents them
+    let grouping = GroupingDto {
+        group_size: Some(1),
+        ..request.grouping.clone()
+    };
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &grouping,
+        Some(request.top_k),
+        |top_k| {
+            let request = MultiVectorSearchRequestDto {
+                top_k: top_k.unwrap_or(request.top_k),
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let (results, warning) =
+                    repo::multi_vector_search(ctx, collection_id, request).await?;
+                Ok((vec![into_result_items(results, None)], warning))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(single_response(responses, warning))
 }
 
 pub(crate) async fn sparse_search(
     ctx: Arc<AppContext>,
     collection_id: &str,
-    request: SparseSearchRequestDto,
+    mut request: SparseSearchRequestDto,
 ) -> Result<SearchResponseDto, SearchError> {
-    let (results, warning) = repo::sparse_search(ctx, collection_id, request).await?;
+    if let Some(query_text) = request.query_text.take() {
+        request.query_terms = repo::embed_sparse_query(&ctx, collection_id, query_text).await?;
+    }
 
-    Ok(SearchResponseDto {
-        results: results
-            .into_iter()
-            .map(|(id, document_id, score, text)| SearchResultItemDto {
-                id,
-                document_id,
-                score,
-                text,
-            })
-            .collect(),
-        warning,
-    })
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &request.grouping,
+        request.top_k,
+        |top_k| {
+            let request = SparseSearchRequestDto {
+                top_k,
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let query_terms = request.explain.then(|| request.query_terms.clone());
+                let (results, warning) =
+                    repo::sparse_search(ctx.clone(), collection_id, request).await?;
+
+                let explanations = match query_terms {
+                    Some(query_terms) => Some(
+                        repo::explain_sparse_results(
+                            ctx.clone(),
+                            collection_id,
+                            &query_terms,
+                            &results,
+                        )
+                        .await?,
+                    ),
+                    None => None,
+                };
+                Ok((vec![into_result_items(results, explanations)], warning))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(single_response(responses, warning))
 }
 
 pub(crate) async fn batch_sparse_search(
@@ -84,26 +172,45 @@ pub(crate) async fn batch_sparse_search(
     collection_id: &str,
     request: BatchSparseSearchRequestDto,
 ) -> Result<BatchSearchResponseDto, SearchError> {
-    let (results_list, warning) = repo::batch_sparse_search(ctx, collection_id, request).await?;
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &request.grouping,
+        request.top_k,
+        |top_k| {
+            let request = BatchSparseSearchRequestDto {
+                top_k,
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let query_terms_list = request.explain.then(|| request.query_terms_list.clone());
+                let (results_list, warning) =
+                    repo::batch_sparse_search(ctx.clone(), collection_id, request).await?;
 
-    Ok(BatchSearchResponseDto {
-        responses: results_list
-            .into_iter()
-            .map(|results| SearchResponseDto {
-                results: results
-                    .into_iter()
-                    .map(|(id, document_id, score, text)| SearchResultItemDto {
-                        id,
-                        document_id,
-                        score,
-                        text,
-                    })
-                    .collect(),
-                warning: None,
-            })
-            .collect(),
-        warning,
-    })
+                let mut items_list = Vec::with_capacity(results_list.len());
+                for (i, results) in results_list.into_iter().enumerate() {
+                    let explanations = match &query_terms_list {
+                        Some(query_terms_list) => Some(
+                            repo::explain_sparse_results(
+                                ctx.clone(),
+                                collection_id,
+                                &query_terms_list[i],
+                                &results,
+                            )
+                            .await?,
+                        ),
+                        None => None,
+                    };
+                    items_list.push(into_result_items(results, explanations));
+                }
+                Ok((items_list, warning))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(BatchSearchResponseDto { responses, warning })
 }
 
 pub(crate) async fn hybrid_search(
@@ -111,20 +218,26 @@ pub(crate) async fn hybrid_search(
     collection_id: &str,
     request: HybridSearchRequestDto,
 ) -> Result<SearchResponseDto, SearchError> {
-    let (results, warning) = repo::hybrid_search(ctx, collection_id, request).await?;
-
-    Ok(SearchResponseDto {
-        results: results
-            .into_iter()
-            .map(|(id, document_id, score, text)| SearchResultItemDto {
-                id,
-                document_id,
-                score,
-                text,
-            })
-            .collect(),
-        warning,
-    })
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &request.grouping,
+        Some(request.top_k),
+        |top_k| {
+            let request = HybridSearchRequestDto {
+                top_k: top_k.unwrap_or(request.top_k),
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let (results, warning) = repo::hybrid_search(ctx, collection_id, request).await?;
+                Ok((vec![into_result_items(results, None)], warning))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(single_response(responses, warning))
 }
 
 pub(crate) async fn batch_hybrid_search(
@@ -132,26 +245,33 @@ pub(crate) async fn batch_hybrid_search(
     collection_id: &str,
     request: BatchHybridSearchRequestDto,
 ) -> Result<BatchSearchResponseDto, SearchError> {
-    let (results_list, warning) = repo::batch_hybrid_search(ctx, collection_id, request).await?;
-
-    Ok(BatchSearchResponseDto {
-        responses: results_list
-            .into_iter()
-            .map(|results| SearchResponseDto {
-                results: results
-                    .into_iter()
-                    .map(|(id, document_id, score, text)| SearchResultItemDto {
-                        id,
-                        document_id,
-                        score,
-                        text,
-                    })
-                    .collect(),
-                warning: None,
-            })
-            .collect(),
-        warning,
-    })
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &request.grouping,
+        Some(request.top_k),
+        |top_k| {
+            let request = BatchHybridSearchRequestDto {
+                top_k: top_k.unwrap_or(request.top_k),
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let (results_list, warning) =
+                    repo::batch_hybrid_search(ctx, collection_id, request).await?;
+                Ok((
+                    results_list
+                        .into_iter()
+                        .map(|results| into_result_items(results, None))
+                        .collect(),
+                    warning,
+                ))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(BatchSearchResponseDto { responses, warning })
 }
 
 pub(crate) async fn tf_idf_search(
@@ -159,20 +279,36 @@ pub(crate) async fn tf_idf_search(
     collection_id: &str,
     request: FindSimilarTFIDFDocumentDto,
 ) -> Result<SearchResponseDto, SearchError> {
-    let (results, warning) = repo::tf_idf_search(ctx, collection_id, request).await?;
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &request.grouping,
+        request.top_k,
+        |top_k| {
+            let request = FindSimilarTFIDFDocumentDto {
+                top_k,
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let query = request.explain.then(|| request.query.clone());
+                let (results, warning) =
+                    repo::tf_idf_search(ctx.clone(), collection_id, request).await?;
 
-    Ok(SearchResponseDto {
-        results: results
-            .into_iter()
-            .map(|(id, document_id, score, text)| SearchResultItemDto {
-                id,
-                document_id,
-                score,
-                text,
-            })
-            .collect(),
-        warning,
-    })
+                let explanations = match query {
+                    Some(query) => Some(
+                        repo::explain_tf_idf_results(ctx.clone(), collection_id, &query, &results)
+                            .await?,
+                    ),
+                    None => None,
+                };
+                Ok((vec![into_result_items(results, explanations)], warning))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(single_response(responses, warning))
 }
 
 pub(crate) async fn batch_tf_idf_search(
@@ -180,24 +316,225 @@ pub(crate) async fn batch_tf_idf_search(
     collection_id: &str,
     request: BatchSearchTFIDFDocumentsDto,
 ) -> Result<BatchSearchResponseDto, SearchError> {
-    let (results_list, warning) = repo::batch_tf_idf_search(ctx, collection_id, request).await?;
+    let (mut responses, warning) = search_responses(
+        &ctx,
+        collection_id,
+        &request.grouping,
+        request.top_k,
+        |top_k| {
+            let request = BatchSearchTFIDFDocumentsDto {
+                top_k,
+                ..request.clone()
+            };
+            let ctx = ctx.clone();
+            async move {
+                let queries = request.explain.then(|| request.queries.clone());
+                let (results_list, warning) =
+                    repo::batch_tf_idf_search(ctx.clone(), collection_id, request).await?;
+
+                let mut items_list = Vec::with_capacity(results_list.len());
+                for (i, results) in results_list.into_iter().enumerate() {
+                    let explanations = match &queries {
+                        Some(queries) => Some(
+                            repo::explain_tf_idf_results(
+                                ctx.clone(),
+                                collection_id,
+                                &queries[i],
+                                &results,
+                            )
+                            .await?,
+                        ),
+                        None => None,
+                    };
+                    items_list.push(into_result_items(results, explanations));
+                }
+                Ok((items_list, warning))
+            }
+        },
+    )
+    .await?;
+    fill_result_fields(&ctx, collection_id, &request.result_fields, &mut responses).await?;
+    Ok(BatchSearchResponseDto { responses, warning })
+}
 
-    Ok(BatchSearchResponseDto {
-        responses: results_list
+/// Runs `search` for `top_k` results per query, or if the results are
+/// grouped, for as many results as needed for each query to have
+/// `groups_limit` complete groups, searching again for more results until
+/// it does or all of its results are found. Groups are complete with
+/// `group_size` results, or with all the vectors of their document if it
+/// has fewer.
+async fn search_responses<F, Fut>(
+    ctx: &AppContext,
+    collection_id: &str,
+    grouping: &GroupingDto,
+    top_k: Option<usize>,
+    search: F,
+) -> Result<(Vec<SearchResponseDto>, Option<String>), SearchError>
+where
+    F: Fn(Option<usize>) -> Fut,
+    Fut: Future<Output = Result<(Vec<Vec<SearchResultItemDto>>, Option<String>), SearchError>>,
+{
+    let Some(GroupBy::DocumentId) = grouping.group_by else {
+        let (items_list, warning) = search(top_k).await?;
+        let responses = items_list
             .into_iter()
             .map(|results| SearchResponseDto {
-                results: results
-                    .into_iter()
-                    .map(|(id, document_id, score, text)| SearchResultItemDto {
-                        id,
-                        document_id,
-                        score,
-                        text,
-                    })
-                    .collect(),
+                results,
+                groups: None,
                 warning: None,
             })
-            .collect(),
+            .collect();
+        return Ok((responses, warning));
+    };
+
+    let groups_limit = grouping
+        .groups_limit
+        .or(top_k)
+        .unwrap_or(DEFAULT_GROUPS_LIMIT);
+    let group_size = grouping.group_size.unwrap_or(DEFAULT_GROUP_SIZE).max(1);
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or_else(|| SearchError::CollectionNotFound(collection_id.to_string()))?;
+    let mut searched = (groups_limit * group_size).clamp(1, MAX_GROUPED_RESULTS);
+    loop {
+        let (items_list, warning) = search(Some(searched)).await?;
+        let has_all_groups = items_list.iter().all(|items| {
+            items.len() < searched
+                || count_complete_groups(&collection, items, group_size) >= groups_limit
+        });
+        if has_all_groups || searched == MAX_GROUPED_RESULTS {
+            let responses = items_list
+                .into_iter()
+                .map(|items| SearchResponseDto {
+                    results: Vec::new(),
+                    groups: Some(group_results(items, grouping, groups_limit, group_size)),
+                    warning: None,
+                })
+                .collect();
+            return Ok((responses, warning));
+        }
+        searched = (searched * 4).min(MAX_GROUPED_RESULTS);
+    }
+}
+
+/// Sets the selected fields of the vectors of the results and groups
+async fn fill_result_fields(
+    ctx: &AppContext,
+    collection_id: &str,
+    result_fields: &ResultFieldsDto,
+    responses: &mut [SearchResponseDto],
+) -> Result<(), SearchError> {
+    let items = responses
+        .iter_mut()
+        .flat_map(|response| {
+            let hits = response
+                .groups
+                .iter_mut()
+                .flatten()
+                .flat_map(|group| group.hits.iter_mut());
+            response.results.iter_mut().chain(hits)
+        })
+        .collect();
+    repo::fill_result_fields(ctx, collection_id, result_fields, items).await
+}
+
+fn single_response(
+    responses: Vec<SearchResponseDto>,
+    warning: Option<String>,
+) -> SearchResponseDto {
+    let response = responses
+        .into_iter()
+        .next()
+        .expect("a single search returns a single response");
+    SearchResponseDto {
         warning,
-    })
+        ..response
+    }
+}
+
+// vectors without a document are groups of their own
+fn group_key(item: &SearchResultItemDto) -> (Option<DocumentId>, Option<VectorId>) {
+    match &item.document_id {
+        Some(document_id) => (Some(document_id.clone()), None),
+        None => (None, Some(item.id.clone())),
+    }
+}
+
+// groups with `group_size` results, or with all the vectors of their
+// document if it has fewer, so that short documents don't make the search
+// look for more results
+fn count_complete_groups(
+    collection: &Collection,
+    items: &[SearchResultItemDto],
+    group_size: usize,
+) -> usize {
+    let mut sizes = HashMap::new();
+    for item in items {
+        *sizes.entry(group_key(item)).or_insert(0) += 1;
+    }
+    sizes
+        .into_iter()
+        .filter(|((document_id, _), size)| match document_id {
+            Some(document_id) => {
+                *size >= group_size || *size >= collection.vectors_of_document(document_id).len()
+            }
+            None => true,
+        })
+        .count()
+}
+
+/// Groups results sorted by score, keeping the best `group_size` results
+/// of each group, and returns the best `groups_limit` groups by their
+/// aggregated score
+fn group_results(
+    items: Vec<SearchResultItemDto>,
+    grouping: &GroupingDto,
+    groups_limit: usize,
+    group_size: usize,
+) -> Vec<SearchResultGroupDto> {
+    let mut groups: Vec<SearchResultGroupDto> = Vec::new();
+    let mut group_indices = HashMap::new();
+    for item in items {
+        let index = *group_indices.entry(group_key(&item)).or_insert_with(|| {
+            groups.push(SearchResultGroupDto {
+                document_id: item.document_id.clone(),
+                score: 0.0,
+                hits: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        if groups[index].hits.len() < group_size {
+            groups[index].hits.push(item);
+        }
+    }
+
+    for group in &mut groups {
+        let scores = group.hits.iter().map(|hit| hit.score);
+        group.score = match grouping.group_aggregation {
+            GroupAggregation::Max => scores.fold(f32::NEG_INFINITY, f32::max),
+            GroupAggregation::Sum => scores.sum(),
+            GroupAggregation::Mean => scores.sum::<f32>() / group.hits.len() as f32,
+        };
+    }
+    groups.sort_by(|a, b| b.score.total_cmp(&a.score));
+    groups.truncate(groups_limit);
+    groups
+}
+
+/// Converts search results into response items, attaching the score
+/// explanations (if requested) in the same order
+fn into_result_items(
+    results: Vec<SearchResult>,
+    explanations: Option<Vec<ScoreExplanationDto>>,
+) -> Vec<SearchResultItemDto> {
+    let mut explanations = explanations.map(|explanations| explanations.into_iter());
+    results
+        .into_iter()
+        .map(|result| SearchResultItemDto {
+            explanation: explanations.as_mut().and_then(|iter| iter.next()),
+            ..SearchResultItemDto::from(result)
+        })
+        .collect()
 }
@@ -0,0 +1,255 @@
+use std::{fs::File, io::Read};
+
+use actix_web::{
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web, HttpResponse,
+};
+use futures_util::Stream;
+
+use crate::{
+    api::vectordb::tenants::tenant_scope_middleware::TenantScope,
+    app_context::AppContext,
+    models::tenants::{qualified_collection_name, split_collection_name, TENANT_SEPARATOR},
+};
+
+use super::{
+    dtos::{CreateSnapshotDto, RestoreSnapshotDto, RestoreSnapshotResponseDto, SnapshotDto},
+    error::SnapshotsError,
+    service,
+};
+
+const DOWNLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Streams the contents of `file`, read in chunks on the blocking thread
+/// pool
+pub(crate) fn file_stream(
+    file: File,
+) -> impl Stream<Item = Result<web::Bytes, std::io::Error>> + 'static {
+    futures_util::stream::unfold(Some(file), |file| async move {
+        let mut file = file?;
+        let result = web::block(move || {
+            let mut chunk = vec![0u8; DOWNLOAD_CHUNK_SIZE];
+            let read = file.read(&mut chunk)?;
+            chunk.truncate(read);
+            Ok::<_, std::io::Error>((chunk, file))
+        })
+        .await
+        .map_err(std::io::Error::other)
+        .and_then(|result| result);
+        match result {
+            Ok((chunk, _)) if chunk.is_empty() => None,
+            Ok((chunk, file)) => Some((Ok(web::Bytes::from(chunk)), Some(file))),
+            Err(err) => Some((Err(err), None)),
+        }
+    })
+}
+
+/// Create a snapshot
+///
+/// Writes a self-contained archive of the collection's current version,
+/// including its metadata, version history, id mappings and index files.
+/// Pending streamed writes are committed first. The snapshot can be
+/// downloaded, and restored as a new collection on this or another server.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/snapshots",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier")
+    ),
+    request_body(content = Option<CreateSnapshotDto>, description = "Name of the snapshot"),
+    responses(
+        (status = 201, description = "Snapshot created", body = SnapshotDto),
+        (status = 400, description = "Invalid snapshot name"),
+        (status = 404, description = "Collection not found"),
+        (status = 409, description = "Snapshot already exists, or the collection has a transaction in progress"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "snapshots"
+)]
+pub(crate) async fn create_snapshot(
+    collection_id: web::Path<String>,
+    body: Option<web::Json<CreateSnapshotDto>>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, SnapshotsError> {
+    let create_snapshot_dto = body.map(|body| body.into_inner()).unwrap_or_default();
+    let snapshot =
+        service::create_snapshot(ctx.into_inner(), &collection_id, create_snapshot_dto).await?;
+    Ok(HttpResponse::Created().json(snapshot))
+}
+
+/// List snapshots
+///
+/// Returns the snapshots stored for the collection, oldest first. Snapshots
+/// are kept when the collection is deleted.
+#[utoipa::path(
+    get,
+    path = "/vectordb/collections/{collection_id}/snapshots",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier")
+    ),
+    responses(
+        (status = 200, description = "List of snapshots", body = Vec<SnapshotDto>),
+        (status = 404, description = "Collection not found"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "snapshots"
+)]
+pub(crate) async fn list_snapshots(
+    collection_id: web::Path<String>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, SnapshotsError> {
+    let snapshots = service::list_snapshots(ctx.into_inner(), &collection_id).await?;
+    Ok(HttpResponse::Ok().json(snapshots))
+}
+
+/// Download a snapshot
+///
+/// Returns the snapshot archive.
+#[utoipa::path(
+    get,
+    path = "/vectordb/collections/{collection_id}/snapshots/{snapshot}",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        ("snapshot" = String, Path, description = "Snapshot name")
+    ),
+    responses(
+        (status = 200, description = "Snapshot archive", content_type = "application/octet-stream"),
+        (status = 404, description = "Snapshot not found"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "snapshots"
+)]
+pub(crate) async fn download_snapshot(
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, SnapshotsError> {
+    let (collection_id, name) = path.into_inner();
+    let snapshot_path = service::get_snapshot_path(&collection_id, &name)?;
+    let file = File::open(&snapshot_path)?;
+    let len = file.metadata()?.len();
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(
+                snapshot_path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            )],
+        })
+        .no_chunking(len)
+        .streaming(file_stream(file)))
+}
+
+/// Upload a snapshot
+///
+/// Stores a snapshot archive, typically downloaded from another server,
+/// under the given name of an existing collection so that it can be
+/// restored. Replaces any snapshot with the same name.
+#[utoipa::path(
+    put,
+    path = "/vectordb/collections/{collection_id}/snapshots/{snapshot}",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        ("snapshot" = String, Path, description = "Snapshot name")
+    ),
+    request_body(content = Vec<u8>, description = "Snapshot archive", content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Snapshot stored", body = SnapshotDto),
+        (status = 400, description = "Invalid snapshot name or archive"),
+        (status = 404, description = "Collection not found"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "snapshots"
+)]
+pub(crate) async fn upload_snapshot(
+    path: web::Path<(String, String)>,
+    ctx: web::Data<AppContext>,
+    payload: web::Payload,
+) -> Result<HttpResponse, SnapshotsError> {
+    let (collection_id, name) = path.into_inner();
+    let snapshot =
+        service::upload_snapshot(ctx.into_inner(), &collection_id, &name, payload).await?;
+    Ok(HttpResponse::Ok().json(snapshot))
+}
+
+/// Delete a snapshot
+#[utoipa::path(
+    delete,
+    path = "/vectordb/collections/{collection_id}/snapshots/{snapshot}",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        ("snapshot" = String, Path, description = "Snapshot name")
+    ),
+    responses(
+        (status = 204, description = "Snapshot deleted"),
+        (status = 404, description = "Snapshot not found"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "snapshots"
+)]
+pub(crate) async fn delete_snapshot(
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, SnapshotsError> {
+    let (collection_id, name) = path.into_inner();
+    service::delete_snapshot(&collection_id, &name).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Restore a snapshot
+///
+/// Creates a collection from the snapshot, named after the collection the
+/// snapshot is stored under unless a name is given. A collection with that
+/// name must not exist.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/snapshots/{snapshot}/restore",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        ("snapshot" = String, Path, description = "Snapshot name")
+    ),
+    request_body(content = Option<RestoreSnapshotDto>, description = "Name of the collection to create"),
+    responses(
+        (status = 201, description = "Collection restored", body = RestoreSnapshotResponseDto),
+        (status = 400, description = "Invalid collection name or archive"),
+        (status = 403, description = "Tenant quota exceeded"),
+        (status = 404, description = "Snapshot not found"),
+        (status = 409, description = "Collection already exists"),
+        (status = 500, description = "Server error")
+    ),
+    tag = "snapshots"
+)]
+pub(crate) async fn restore_snapshot(
+    path: web::Path<(String, String)>,
+    body: Option<web::Json<RestoreSnapshotDto>>,
+    tenant: Option<web::ReqData<TenantScope>>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, SnapshotsError> {
+    let (collection_id, name) = path.into_inner();
+    let RestoreSnapshotDto { collection_name } =
+        body.map(|body| body.into_inner()).unwrap_or_default();
+    let collection_name = match (collection_name, &tenant) {
+        (Some(collection_name), Some(tenant)) => {
+            if collection_name.contains(TENANT_SEPARATOR) {
+                return Err(SnapshotsError::InvalidName(format!(
+                    "collection names must not contain '{}'",
+                    TENANT_SEPARATOR
+                )));
+            }
+            qualified_collection_name(&tenant.0, &collection_name)
+        }
+        (Some(collection_name), None) => collection_name,
+        (None, _) => collection_id.clone(),
+    };
+
+    let mut response =
+        service::restore_snapshot(ctx.into_inner(), &collection_id, &name, collection_name).await?;
+    if tenant.is_some() {
+        response.collection_name = split_collection_name(&response.collection_name)
+            .1
+            .to_string();
+    }
+    Ok(HttpResponse::Created().json(response))
+}
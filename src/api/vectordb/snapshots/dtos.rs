@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// DTO for creating a snapshot
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub(crate) struct CreateSnapshotDto {
+    /// Name of the snapshot, may only contain ASCII letters, digits, `-`, `_`
+    /// and `.`. Named after the time it's taken at if omitted.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct SnapshotDto {
+    pub name: String,
+    /// Version of the collection the snapshot was taken at
+    pub version: u32,
+    #[schema(value_type = String, example = "2023-01-01T12:00:00Z")]
+    pub created_at: DateTime<Utc>,
+    /// Size of the snapshot archive in bytes
+    pub size: u64,
+}
+
+/// DTO for restoring a snapshot
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub(crate) struct RestoreSnapshotDto {
+    /// Name of the collection to create, defaults to the collection the
+    /// snapshot is stored under
+    pub collection_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct RestoreSnapshotResponseDto {
+    pub collection_name: String,
+    /// Version of the collection the snapshot was taken at
+    pub version: u32,
+}
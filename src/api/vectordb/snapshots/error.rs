@@ -0,0 +1,77 @@
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    HttpResponse, ResponseError,
+};
+use std::fmt::Display;
+
+use crate::models::{common::WaCustomError, tenants::QuotaError};
+
+#[derive(Debug)]
+pub enum SnapshotsError {
+    CollectionNotFound,
+    SnapshotNotFound(String),
+    InvalidName(String),
+    AlreadyExists(String),
+    Busy(String),
+    InvalidArchive(String),
+    QuotaExceeded(String),
+    ServerError(String),
+}
+
+impl Display for SnapshotsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CollectionNotFound => write!(f, "Collection not found"),
+            Self::SnapshotNotFound(name) => write!(f, "Snapshot '{}' not found", name),
+            Self::InvalidName(msg) => write!(f, "Invalid name: {}", msg),
+            Self::AlreadyExists(msg) => write!(f, "{} already exists", msg),
+            Self::Busy(msg) => write!(f, "Collection is busy: {}", msg),
+            Self::InvalidArchive(msg) => write!(f, "{}", msg),
+            Self::QuotaExceeded(msg) => write!(f, "Quota exceeded: {}", msg),
+            Self::ServerError(msg) => write!(f, "Server error: {}", msg),
+        }
+    }
+}
+
+impl ResponseError for SnapshotsError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .insert_header(ContentType::html())
+            .body(self.to_string())
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::CollectionNotFound | Self::SnapshotNotFound(_) => StatusCode::NOT_FOUND,
+            Self::InvalidName(_) | Self::InvalidArchive(_) => StatusCode::BAD_REQUEST,
+            Self::AlreadyExists(_) | Self::Busy(_) => StatusCode::CONFLICT,
+            Self::QuotaExceeded(_) => StatusCode::FORBIDDEN,
+            Self::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<WaCustomError> for SnapshotsError {
+    fn from(error: WaCustomError) -> Self {
+        match error {
+            WaCustomError::LockError(msg) => Self::Busy(msg),
+            WaCustomError::InvalidData(msg) => Self::InvalidArchive(msg),
+            _ => Self::ServerError(error.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for SnapshotsError {
+    fn from(error: std::io::Error) -> Self {
+        Self::ServerError(error.to_string())
+    }
+}
+
+impl From<QuotaError> for SnapshotsError {
+    fn from(error: QuotaError) -> Self {
+        match error {
+            QuotaError::Io(err) => Self::ServerError(err.to_string()),
+            err => Self::QuotaExceeded(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use actix_web::{web, Scope};
+
+pub mod controller;
+pub(crate) mod dtos;
+mod error;
+mod service;
+
+pub(crate) fn snapshots_module() -> Scope {
+    web::scope("/collections/{collection_id}/snapshots")
+        .route("", web::post().to(controller::create_snapshot))
+        .route("", web::get().to(controller::list_snapshots))
+        .route("/{snapshot}", web::get().to(controller::download_snapshot))
+        .route("/{snapshot}", web::put().to(controller::upload_snapshot))
+        .route("/{snapshot}", web::delete().to(controller::delete_snapshot))
+        .route(
+            "/{snapshot}/restore",
+            web::post().to(controller::restore_snapshot),
+        )
+}
@@ -0,0 +1,222 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use actix_web::web;
+use chrono::Utc;
+use futures_util::StreamExt;
+
+use crate::{
+    app_context::AppContext,
+    models::snapshot::{
+        get_snapshots_path, is_valid_snapshot_name, read_manifest, SnapshotManifest,
+        SNAPSHOT_EXTENSION,
+    },
+};
+
+use super::{
+    dtos::{CreateSnapshotDto, RestoreSnapshotResponseDto, SnapshotDto},
+    error::SnapshotsError,
+};
+
+fn validate_snapshot_name(name: &str) -> Result<(), SnapshotsError> {
+    if !is_valid_snapshot_name(name) {
+        return Err(SnapshotsError::InvalidName(format!(
+            "'{}', snapshot names may only contain ASCII letters, digits, '-', '_' and '.'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn snapshot_file_path(collection_id: &str, name: &str) -> PathBuf {
+    get_snapshots_path(collection_id).join(format!("{}.{}", name, SNAPSHOT_EXTENSION))
+}
+
+/// Returns the path of an existing snapshot
+pub(crate) fn get_snapshot_path(
+    collection_id: &str,
+    name: &str,
+) -> Result<PathBuf, SnapshotsError> {
+    validate_snapshot_name(name)?;
+    let path = snapshot_file_path(collection_id, name);
+    if !path.is_file() {
+        return Err(SnapshotsError::SnapshotNotFound(name.to_string()));
+    }
+    Ok(path)
+}
+
+fn snapshot_dto(
+    name: String,
+    manifest: SnapshotManifest,
+    path: &Path,
+) -> Result<SnapshotDto, SnapshotsError> {
+    Ok(SnapshotDto {
+        name,
+        version: manifest.version,
+        created_at: manifest.created_at,
+        size: fs::metadata(path)?.len(),
+    })
+}
+
+pub(crate) async fn create_snapshot(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    CreateSnapshotDto { name }: CreateSnapshotDto,
+) -> Result<SnapshotDto, SnapshotsError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or(SnapshotsError::CollectionNotFound)?;
+    let name = name.unwrap_or_else(|| Utc::now().format("%Y%m%dT%H%M%SZ").to_string());
+    validate_snapshot_name(&name)?;
+
+    fs::create_dir_all(get_snapshots_path(collection_id))?;
+    let path = snapshot_file_path(collection_id, &name);
+    if path.exists() {
+        return Err(SnapshotsError::AlreadyExists(format!(
+            "Snapshot '{}'",
+            name
+        )));
+    }
+
+    let snapshot_path = path.clone();
+    let manifest = web::block(move || {
+        ctx.ain_env
+            .collections_map
+            .write_snapshot(&collection, &ctx.config, &snapshot_path)
+    })
+    .await
+    .map_err(|e| SnapshotsError::ServerError(e.to_string()))??;
+
+    snapshot_dto(name, manifest, &path)
+}
+
+/// lists the snapshots stored for a collection, which remain available
+/// after the collection is deleted
+pub(crate) async fn list_snapshots(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+) -> Result<Vec<SnapshotDto>, SnapshotsError> {
+    let dir = get_snapshots_path(collection_id);
+    if !dir.is_dir() {
+        if ctx
+            .ain_env
+            .collections_map
+            .get_collection(collection_id)
+            .is_none()
+        {
+            return Err(SnapshotsError::CollectionNotFound);
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SNAPSHOT_EXTENSION) {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        match read_manifest(&path) {
+            Ok(manifest) => snapshots.push(snapshot_dto(name.to_string(), manifest, &path)?),
+            Err(err) => log::warn!("Skipping snapshot {}: {}", path.display(), err),
+        }
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.created_at);
+    Ok(snapshots)
+}
+
+pub(crate) async fn delete_snapshot(collection_id: &str, name: &str) -> Result<(), SnapshotsError> {
+    let path = get_snapshot_path(collection_id, name)?;
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// stores an uploaded snapshot of an existing collection, replacing any
+/// snapshot with the same name
+pub(crate) async fn upload_snapshot(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    name: &str,
+    mut payload: web::Payload,
+) -> Result<SnapshotDto, SnapshotsError> {
+    ctx.ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or(SnapshotsError::CollectionNotFound)?;
+    validate_snapshot_name(name)?;
+    let dir = get_snapshots_path(collection_id);
+    fs::create_dir_all(&dir)?;
+
+    // the archive is only moved in place once it's complete and its
+    // manifest could be read
+    let mut file = tempfile::NamedTempFile::new_in(&dir)?;
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| SnapshotsError::ServerError(e.to_string()))?;
+        file.write_all(&chunk)?;
+    }
+    file.as_file().sync_all()?;
+    let manifest = read_manifest(file.path())?;
+
+    let path = snapshot_file_path(collection_id, name);
+    file.persist(&path).map_err(|e| e.error)?;
+    snapshot_dto(name.to_string(), manifest, &path)
+}
+
+/// creates the collection `collection_name` from a snapshot stored for
+/// `collection_id`
+pub(crate) async fn restore_snapshot(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    name: &str,
+    collection_name: String,
+) -> Result<RestoreSnapshotResponseDto, SnapshotsError> {
+    let path = get_snapshot_path(collection_id, name)?;
+    if collection_name.is_empty()
+        || collection_name.contains(['/', '\\'])
+        || collection_name.starts_with('.')
+    {
+        return Err(SnapshotsError::InvalidName(format!(
+            "'{}' is not a valid collection name",
+            collection_name
+        )));
+    }
+    if ctx
+        .ain_env
+        .collections_map
+        .get_collection(&collection_name)
+        .is_some()
+    {
+        return Err(SnapshotsError::AlreadyExists(format!(
+            "Collection '{}'",
+            collection_name
+        )));
+    }
+    ctx.ain_env
+        .tenants_map
+        .check_collection_quota(&ctx.ain_env.collections_map, &collection_name)?;
+
+    let collection = web::block(move || {
+        ctx.ain_env.collections_map.restore_snapshot(
+            &path,
+            &collection_name,
+            &ctx.config,
+            &ctx.threadpool,
+        )
+    })
+    .await
+    .map_err(|e| SnapshotsError::ServerError(e.to_string()))??;
+
+    let version = **collection.current_version.read();
+    Ok(RestoreSnapshotResponseDto {
+        collection_name: collection.meta.name.clone(),
+        version,
+    })
+}
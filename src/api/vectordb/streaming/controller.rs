@@ -2,7 +2,14 @@ use actix_web::{web, HttpResponse};
 
 use super::service;
 use crate::{
-    api::vectordb::transactions::{dtos::UpsertDto, error::TransactionError},
+    api::vectordb::{
+        transactions::{
+            dtos::{DeleteVectorsDto, DeleteVectorsResponseDto, UpsertDto},
+            error::TransactionError,
+            upsert_body::UpsertBody,
+        },
+        vectors::dtos::CreateVectorDto,
+    },
     app_context::AppContext,
     models::collection_cache::CollectionCacheExt,
 };
@@ -11,31 +18,47 @@ use crate::{
 ///
 /// This API provides a simplified way to upsert vectors without managing transaction lifecycle.
 /// A transaction is created, vectors are upserted, and the transaction is committed in a single request.
+/// NDJSON bodies are upserted in batches as they're received.
+/// Vectors with `if_absent` are only written if their id doesn't exist yet, and vectors with
+/// `if_version` only if they were last written at that version.
 #[utoipa::path(
     post,
     path = "/vectordb/collections/{collection_id}/streaming/upsert",
     tag = "streaming",
     params(
-        ("collection_id" = String, Path, description = "Collection ID")
+        ("collection_id" = String, Path, description = "Collection ID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
+    ),
+    request_body(
+        description = "Vectors to upsert, in any of the formats accepted by the transaction upsert endpoint",
+        content(
+            (UpsertDto = "application/json"),
+            (UpsertDto = "application/cbor"),
+            (CreateVectorDto = "application/x-ndjson")
+        )
     ),
-    request_body = UpsertDto,
     responses(
         (status = 200, description = "Vectors upserted successfully"),
         (status = 404, description = "Collection not found"),
+        (status = 412, description = "The precondition of a vector isn't met, no vector of the batch is upserted"),
+        (status = 413, description = "The CBOR body or a line of the NDJSON body is too large"),
         (status = 500, description = "Internal server error")
     )
 )]
 pub(crate) async fn upsert(
     collection_id: web::Path<String>,
     ctx: web::Data<AppContext>,
-    web::Json(upsert_dto): web::Json<UpsertDto>,
+    mut body: UpsertBody,
 ) -> Result<HttpResponse, TransactionError> {
     let collection_id = collection_id.into_inner();
 
     ctx.update_collection_for_transaction(&collection_id)
         .map_err(|e| TransactionError::FailedToCreateTransaction(format!("Cache error: {}", e)))?;
 
-    service::upsert_vectors(ctx.into_inner(), &collection_id, upsert_dto.vectors).await?;
+    let ctx = ctx.into_inner();
+    while let Some(vectors) = body.next_batch().await? {
+        service::upsert_vectors(ctx.clone(), &collection_id, vectors).await?;
+    }
 
     Ok(HttpResponse::Ok().finish())
 }
@@ -50,7 +73,8 @@ pub(crate) async fn upsert(
     tag = "streaming",
     params(
         ("collection_id" = String, Path, description = "Collection ID"),
-        ("vector_id" = String, Path, description = "Vector ID to delete")
+        ("vector_id" = String, Path, description = "Vector ID to delete"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
     ),
     responses(
         (status = 204, description = "Vector deleted successfully"),
@@ -71,3 +95,38 @@ pub(crate) async fn delete_vector_by_id(
 
     Ok(HttpResponse::NoContent().finish())
 }
+
+/// Delete vectors using a synchronous transaction
+///
+/// Deletes the vectors with the given IDs, all the vectors of a document or all the vectors
+/// whose metadata match a filter, without managing transaction lifecycle.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/streaming/delete",
+    tag = "streaming",
+    params(
+        ("collection_id" = String, Path, description = "Collection ID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
+    ),
+    request_body = DeleteVectorsDto,
+    responses(
+        (status = 200, description = "Vectors deleted successfully", body = DeleteVectorsResponseDto),
+        (status = 400, description = "Invalid selection of the vectors to delete"),
+        (status = 404, description = "Collection not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub(crate) async fn delete_vectors(
+    collection_id: web::Path<String>,
+    ctx: web::Data<AppContext>,
+    web::Json(delete_dto): web::Json<DeleteVectorsDto>,
+) -> Result<HttpResponse, TransactionError> {
+    let collection_id = collection_id.into_inner();
+
+    ctx.update_collection_for_transaction(&collection_id)
+        .map_err(|e| TransactionError::FailedToCreateTransaction(format!("Cache error: {}", e)))?;
+
+    let response = service::delete_vectors(ctx.into_inner(), &collection_id, delete_dto).await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -4,11 +4,17 @@ mod service;
 
 use actix_web::{web, Scope};
 
+use super::transactions::idempotency_middleware::IdempotencyMiddleware;
+
 pub(crate) fn streaming_module() -> Scope {
-    web::scope("/collections/{collection_id}/streaming")
-        .route("/upsert", web::post().to(controller::upsert))
-        .route(
-            "/vectors/{vector_id}",
-            web::delete().to(controller::delete_vector_by_id),
-        )
+    web::scope("/collections/{collection_id}/streaming").service(
+        web::scope("")
+            .wrap(IdempotencyMiddleware)
+            .route("/upsert", web::post().to(controller::upsert))
+            .route("/delete", web::post().to(controller::delete_vectors))
+            .route(
+                "/vectors/{vector_id}",
+                web::delete().to(controller::delete_vector_by_id),
+            ),
+    )
 }
@@ -1,7 +1,14 @@
 use std::sync::Arc;
 
 use crate::{
-    api::vectordb::{transactions::error::TransactionError, vectors::dtos::CreateVectorDto},
+    api::vectordb::{
+        transactions::{
+            dtos::DeleteVectorsDto,
+            error::TransactionError,
+            repo::{check_preconditions, check_vector_quota, embed_texts, vector_ids_to_delete},
+        },
+        vectors::dtos::CreateVectorDto,
+    },
     app_context::AppContext,
     models::{indexing_manager::IndexingManager, types::VectorId},
 };
@@ -16,6 +23,15 @@ pub(crate) async fn upsert_vectors(
         .collections_map
         .get_collection(collection_id)
         .ok_or(TransactionError::CollectionNotFound)?;
+    let vectors = embed_texts(&ctx, &collection, vectors).await?;
+
+    check_vector_quota(&ctx, &collection, vectors.iter().map(|vector| &vector.id))?;
+
+    let _conditional_writes_guard = vectors
+        .iter()
+        .any(|vector| vector.if_absent || vector.if_version.is_some())
+        .then(|| collection.conditional_writes_lock.lock());
+    check_preconditions(&collection, &vectors)?;
 
     let txn = collection.current_implicit_transaction.read();
 
@@ -48,3 +64,26 @@ pub(crate) async fn delete_vector_by_id(
 
     Ok(())
 }
+
+pub(crate) async fn delete_vectors(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    delete_dto: DeleteVectorsDto,
+) -> Result<usize, TransactionError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or(TransactionError::CollectionNotFound)?;
+
+    let vector_ids = vector_ids_to_delete(&collection, delete_dto)?;
+    let count = vector_ids.len();
+
+    let txn = collection.current_implicit_transaction.read();
+    for vector_id in vector_ids {
+        IndexingManager::implicit_txn_delete(&collection, &txn, &ctx.config, vector_id)
+            .map_err(|err| TransactionError::FailedToDeleteVector(err.to_string()))?;
+    }
+
+    Ok(count)
+}
@@ -2,7 +2,13 @@ use std::sync::Arc;
 
 use super::repo;
 use crate::{
-    api::vectordb::{transactions::error::TransactionError, vectors::dtos::CreateVectorDto},
+    api::vectordb::{
+        transactions::{
+            dtos::{DeleteVectorsDto, DeleteVectorsResponseDto},
+            error::TransactionError,
+        },
+        vectors::dtos::CreateVectorDto,
+    },
     app_context::AppContext,
     models::types::VectorId,
 };
@@ -22,3 +28,12 @@ pub(crate) async fn delete_vector_by_id(
 ) -> Result<(), TransactionError> {
     repo::delete_vector_by_id(ctx, collection_id, vector_id).await
 }
+
+pub(crate) async fn delete_vectors(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    delete_dto: DeleteVectorsDto,
+) -> Result<DeleteVectorsResponseDto, TransactionError> {
+    let count = repo::delete_vectors(ctx, collection_id, delete_dto).await?;
+    Ok(DeleteVectorsResponseDto { count })
+}
@@ -0,0 +1,224 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    api::auth::dtos::Claims, app_context::AppContext, models::tenants::TenantQuotas,
+    models::types::ADMIN_USERNAME,
+};
+
+use super::{
+    dtos::{CreateTenantDto, CreateTenantUserDto, TenantDto},
+    error::TenantsError,
+    service,
+};
+
+fn require_admin(claims: &Claims) -> Result<(), TenantsError> {
+    if claims.username != ADMIN_USERNAME {
+        return Err(TenantsError::Forbidden);
+    }
+    Ok(())
+}
+
+/// Create a tenant
+///
+/// Creates a namespace for collections. The tenant's collections are then
+/// accessed through `/vectordb/tenants/{tenant}/collections/...`, which
+/// accepts the same requests as `/vectordb/collections/...`. Only available
+/// to the admin user.
+#[utoipa::path(
+    post,
+    path = "/vectordb/tenants",
+    request_body = CreateTenantDto,
+    responses(
+        (status = 201, description = "Tenant created successfully", body = TenantDto),
+        (status = 400, description = "Invalid tenant name", body = serde_json::Value),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 409, description = "Tenant already exists", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "tenants"
+)]
+pub(crate) async fn create_tenant(
+    claims: Claims,
+    web::Json(create_tenant_dto): web::Json<CreateTenantDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, TenantsError> {
+    require_admin(&claims)?;
+    let tenant = service::create_tenant(create_tenant_dto, ctx.into_inner()).await?;
+    Ok(HttpResponse::Created().json(tenant))
+}
+
+/// List tenants
+///
+/// Returns all tenants for the admin user, and the tenants the user is a
+/// member of otherwise.
+#[utoipa::path(
+    get,
+    path = "/vectordb/tenants",
+    responses(
+        (status = 200, description = "List of tenants", body = Vec<TenantDto>),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "tenants"
+)]
+pub(crate) async fn list_tenants(
+    claims: Claims,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, TenantsError> {
+    let tenants = service::list_tenants(&claims.username, ctx.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(tenants))
+}
+
+/// Get a tenant
+///
+/// Returns the tenant along with its quotas and current usage.
+#[utoipa::path(
+    get,
+    path = "/vectordb/tenants/{tenant}",
+    params(
+        ("tenant" = String, Path, description = "Tenant name")
+    ),
+    responses(
+        (status = 200, description = "Tenant information", body = TenantDto),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not a member of the tenant", body = serde_json::Value),
+        (status = 404, description = "Tenant not found", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "tenants"
+)]
+pub(crate) async fn get_tenant(
+    claims: Claims,
+    tenant: web::Path<String>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, TenantsError> {
+    let tenant = service::get_tenant(&claims.username, &tenant, ctx.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(tenant))
+}
+
+/// Delete a tenant
+///
+/// The tenant's collections must be deleted first. Only available to the
+/// admin user.
+#[utoipa::path(
+    delete,
+    path = "/vectordb/tenants/{tenant}",
+    params(
+        ("tenant" = String, Path, description = "Tenant name")
+    ),
+    responses(
+        (status = 204, description = "Tenant deleted successfully"),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 404, description = "Tenant not found", body = serde_json::Value),
+        (status = 409, description = "Tenant still has collections", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "tenants"
+)]
+pub(crate) async fn delete_tenant(
+    claims: Claims,
+    tenant: web::Path<String>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, TenantsError> {
+    require_admin(&claims)?;
+    service::delete_tenant(&tenant, ctx.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Update tenant quotas
+///
+/// Replaces the tenant's quotas, omitted limits are removed. Only available
+/// to the admin user.
+#[utoipa::path(
+    put,
+    path = "/vectordb/tenants/{tenant}/quotas",
+    params(
+        ("tenant" = String, Path, description = "Tenant name")
+    ),
+    request_body = TenantQuotas,
+    responses(
+        (status = 200, description = "Quotas updated successfully", body = TenantDto),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 404, description = "Tenant not found", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "tenants"
+)]
+pub(crate) async fn update_tenant_quotas(
+    claims: Claims,
+    tenant: web::Path<String>,
+    web::Json(quotas): web::Json<TenantQuotas>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, TenantsError> {
+    require_admin(&claims)?;
+    let tenant = service::update_tenant_quotas(&tenant, quotas, ctx.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(tenant))
+}
+
+/// Create a tenant user
+///
+/// Creates a user that can access the tenant's collections, and only those.
+/// Only available to the admin user.
+#[utoipa::path(
+    post,
+    path = "/vectordb/tenants/{tenant}/users",
+    params(
+        ("tenant" = String, Path, description = "Tenant name")
+    ),
+    request_body = CreateTenantUserDto,
+    responses(
+        (status = 201, description = "User created successfully", body = TenantDto),
+        (status = 400, description = "Invalid username", body = serde_json::Value),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 404, description = "Tenant not found", body = serde_json::Value),
+        (status = 409, description = "User already exists", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "tenants"
+)]
+pub(crate) async fn create_tenant_user(
+    claims: Claims,
+    tenant: web::Path<String>,
+    web::Json(create_tenant_user_dto): web::Json<CreateTenantUserDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, TenantsError> {
+    require_admin(&claims)?;
+    let tenant =
+        service::create_tenant_user(&tenant, create_tenant_user_dto, ctx.into_inner()).await?;
+    Ok(HttpResponse::Created().json(tenant))
+}
+
+/// Remove a user from a tenant
+///
+/// The user loses access to the tenant's collections. Only available to the
+/// admin user.
+#[utoipa::path(
+    delete,
+    path = "/vectordb/tenants/{tenant}/users/{username}",
+    params(
+        ("tenant" = String, Path, description = "Tenant name"),
+        ("username" = String, Path, description = "Username")
+    ),
+    responses(
+        (status = 204, description = "User removed successfully"),
+        (status = 401, description = "Invalid authentication token"),
+        (status = 403, description = "Not the admin user", body = serde_json::Value),
+        (status = 404, description = "Tenant not found or user not a member", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "tenants"
+)]
+pub(crate) async fn remove_tenant_user(
+    claims: Claims,
+    path: web::Path<(String, String)>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, TenantsError> {
+    require_admin(&claims)?;
+    let (tenant, username) = path.into_inner();
+    service::remove_tenant_user(&tenant, &username, ctx.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::tenants::TenantQuotas;
+
+/// DTO for creating a tenant
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct CreateTenantDto {
+    /// Name of the tenant, may only contain ASCII letters, digits, `-` and
+    /// `_`
+    pub name: String,
+    /// Resource limits of the tenant, unlimited if omitted
+    #[serde(default)]
+    pub quotas: TenantQuotas,
+}
+
+/// Resources currently used by a tenant
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct TenantUsageDto {
+    pub collections: u32,
+    pub vectors: u64,
+    /// Size in bytes of the tenant's collections on disk
+    pub disk_usage: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct TenantDto {
+    pub name: String,
+    pub quotas: TenantQuotas,
+    /// Users allowed to access the tenant's collections
+    pub users: Vec<String>,
+    pub created_at: u64,
+    pub usage: TenantUsageDto,
+}
+
+/// DTO for creating a user that is a member of a tenant
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct CreateTenantUserDto {
+    pub username: String,
+    pub password: String,
+}
@@ -0,0 +1,81 @@
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    HttpResponse, ResponseError,
+};
+use std::fmt::Display;
+
+use crate::models::tenants::QuotaError;
+
+#[derive(Debug)]
+pub enum TenantsError {
+    NotFound(String),
+    AlreadyExists(String),
+    InvalidName(String),
+    NotEmpty(String),
+    UserAlreadyExists(String),
+    UserNotFound(String),
+    Forbidden,
+    DatabaseError(String),
+    ServerError(String),
+}
+
+impl Display for TenantsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(name) => write!(f, "Tenant '{}' not found", name),
+            Self::AlreadyExists(name) => write!(f, "Tenant '{}' already exists", name),
+            Self::InvalidName(msg) => write!(f, "Invalid name: {}", msg),
+            Self::NotEmpty(name) => write!(f, "Tenant '{}' still has collections", name),
+            Self::UserAlreadyExists(username) => {
+                write!(f, "User '{}' already exists", username)
+            }
+            Self::UserNotFound(username) => {
+                write!(f, "User '{}' is not a member of the tenant", username)
+            }
+            Self::Forbidden => write!(f, "Not allowed to access this tenant"),
+            Self::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+            Self::ServerError(msg) => write!(f, "Server error: {}", msg),
+        }
+    }
+}
+
+impl ResponseError for TenantsError {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let message = self.to_string();
+        HttpResponse::build(status)
+            .insert_header(ContentType::json())
+            .json(serde_json::json!({
+                "error": status.canonical_reason().unwrap_or("Error"),
+                "code": status.as_u16(),
+                "message": message
+            }))
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::NotFound(_) | Self::UserNotFound(_) => StatusCode::NOT_FOUND,
+            Self::AlreadyExists(_) | Self::NotEmpty(_) | Self::UserAlreadyExists(_) => {
+                StatusCode::CONFLICT
+            }
+            Self::InvalidName(_) => StatusCode::BAD_REQUEST,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::DatabaseError(_) | Self::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<lmdb::Error> for TenantsError {
+    fn from(err: lmdb::Error) -> Self {
+        Self::DatabaseError(err.to_string())
+    }
+}
+
+impl From<QuotaError> for TenantsError {
+    fn from(err: QuotaError) -> Self {
+        match err {
+            QuotaError::TenantNotFound(name) => Self::NotFound(name),
+            err => Self::ServerError(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use actix_web::{web, Scope};
+
+pub mod controller;
+pub(crate) mod dtos;
+mod error;
+mod service;
+pub(crate) mod tenant_scope_middleware;
+
+pub(crate) fn tenants_module() -> Scope {
+    web::scope("/tenants")
+        .route("", web::post().to(controller::create_tenant))
+        .route("", web::get().to(controller::list_tenants))
+        .route("/{tenant}", web::get().to(controller::get_tenant))
+        .route("/{tenant}", web::delete().to(controller::delete_tenant))
+        .route(
+            "/{tenant}/quotas",
+            web::put().to(controller::update_tenant_quotas),
+        )
+        .route(
+            "/{tenant}/users",
+            web::post().to(controller::create_tenant_user),
+        )
+        .route(
+            "/{tenant}/users/{username}",
+            web::delete().to(controller::remove_tenant_user),
+        )
+}
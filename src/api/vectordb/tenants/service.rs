@@ -0,0 +1,166 @@
+use std::{str::FromStr, sync::Arc};
+
+use crate::{
+    app_context::AppContext,
+    models::{
+        crypto::{get_current_timestamp, DoubleSHA256Hash},
+        tenants::{is_valid_tenant_name, tenant_collections, Tenant, TenantQuotas},
+        types::ADMIN_USERNAME,
+    },
+};
+
+use super::{
+    dtos::{CreateTenantDto, CreateTenantUserDto, TenantDto, TenantUsageDto},
+    error::TenantsError,
+};
+
+fn to_dto(tenant: Tenant, ctx: &AppContext) -> Result<TenantDto, TenantsError> {
+    let usage = ctx
+        .ain_env
+        .tenants_map
+        .usage(&tenant.name, &ctx.ain_env.collections_map)?;
+
+    Ok(TenantDto {
+        name: tenant.name,
+        quotas: tenant.quotas,
+        users: tenant.users,
+        created_at: tenant.created_at,
+        usage: TenantUsageDto {
+            collections: usage.collections,
+            vectors: usage.vectors,
+            disk_usage: usage.disk_usage,
+        },
+    })
+}
+
+pub(crate) async fn create_tenant(
+    create_tenant_dto: CreateTenantDto,
+    ctx: Arc<AppContext>,
+) -> Result<TenantDto, TenantsError> {
+    if !is_valid_tenant_name(&create_tenant_dto.name) {
+        return Err(TenantsError::InvalidName(format!(
+            "'{}' is not a valid tenant name",
+            create_tenant_dto.name
+        )));
+    }
+
+    let tenant = Tenant {
+        name: create_tenant_dto.name,
+        quotas: create_tenant_dto.quotas,
+        users: Vec::new(),
+        created_at: get_current_timestamp(),
+    };
+    if !ctx.ain_env.tenants_map.insert(tenant.clone())? {
+        return Err(TenantsError::AlreadyExists(tenant.name));
+    }
+
+    to_dto(tenant, &ctx)
+}
+
+pub(crate) async fn list_tenants(
+    username: &str,
+    ctx: Arc<AppContext>,
+) -> Result<Vec<TenantDto>, TenantsError> {
+    ctx.ain_env
+        .tenants_map
+        .list()
+        .into_iter()
+        .filter(|tenant| username == ADMIN_USERNAME || tenant.has_user(username))
+        .map(|tenant| to_dto(tenant, &ctx))
+        .collect()
+}
+
+pub(crate) async fn get_tenant(
+    username: &str,
+    name: &str,
+    ctx: Arc<AppContext>,
+) -> Result<TenantDto, TenantsError> {
+    let tenant = ctx
+        .ain_env
+        .tenants_map
+        .get(name)
+        .ok_or_else(|| TenantsError::NotFound(name.to_string()))?;
+    if username != ADMIN_USERNAME && !tenant.has_user(username) {
+        return Err(TenantsError::Forbidden);
+    }
+
+    to_dto(tenant, &ctx)
+}
+
+pub(crate) async fn delete_tenant(name: &str, ctx: Arc<AppContext>) -> Result<(), TenantsError> {
+    if !tenant_collections(name, &ctx.ain_env.collections_map).is_empty() {
+        return Err(TenantsError::NotEmpty(name.to_string()));
+    }
+
+    ctx.ain_env
+        .tenants_map
+        .remove(name)?
+        .ok_or_else(|| TenantsError::NotFound(name.to_string()))?;
+    Ok(())
+}
+
+pub(crate) async fn update_tenant_quotas(
+    name: &str,
+    quotas: TenantQuotas,
+    ctx: Arc<AppContext>,
+) -> Result<TenantDto, TenantsError> {
+    let tenant = ctx
+        .ain_env
+        .tenants_map
+        .update(name, |tenant| tenant.quotas = quotas)?
+        .ok_or_else(|| TenantsError::NotFound(name.to_string()))?;
+
+    to_dto(tenant, &ctx)
+}
+
+pub(crate) async fn create_tenant_user(
+    name: &str,
+    create_tenant_user_dto: CreateTenantUserDto,
+    ctx: Arc<AppContext>,
+) -> Result<TenantDto, TenantsError> {
+    let CreateTenantUserDto { username, password } = create_tenant_user_dto;
+    if username.is_empty() {
+        return Err(TenantsError::InvalidName(
+            "username must not be empty".to_string(),
+        ));
+    }
+    if ctx.ain_env.tenants_map.get(name).is_none() {
+        return Err(TenantsError::NotFound(name.to_string()));
+    }
+    if ctx.ain_env.users_map.get_user(&username).is_some() {
+        return Err(TenantsError::UserAlreadyExists(username));
+    }
+
+    let password_hash = DoubleSHA256Hash::from_str(&password).unwrap();
+    ctx.ain_env
+        .users_map
+        .add_user(username.clone(), password_hash)?;
+
+    let tenant = ctx
+        .ain_env
+        .tenants_map
+        .update(name, |tenant| tenant.users.push(username))?
+        .ok_or_else(|| TenantsError::NotFound(name.to_string()))?;
+
+    to_dto(tenant, &ctx)
+}
+
+pub(crate) async fn remove_tenant_user(
+    name: &str,
+    username: &str,
+    ctx: Arc<AppContext>,
+) -> Result<(), TenantsError> {
+    let tenant = ctx
+        .ain_env
+        .tenants_map
+        .get(name)
+        .ok_or_else(|| TenantsError::NotFound(name.to_string()))?;
+    if !tenant.has_user(username) {
+        return Err(TenantsError::UserNotFound(username.to_string()));
+    }
+
+    ctx.ain_env
+        .tenants_map
+        .update(name, |tenant| tenant.users.retain(|user| user != username))?;
+    Ok(())
+}
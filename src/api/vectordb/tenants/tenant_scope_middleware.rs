@@ -0,0 +1,148 @@
+use crate::api::auth::dtos::Claims;
+use crate::models::tenants::qualified_collection_name;
+use crate::models::types::{AppEnv, ADMIN_USERNAME};
+
+use super::error::TenantsError;
+
+use std::future::{ready, Ready};
+use std::sync::Arc;
+
+use actix_web::http::Uri;
+use actix_web::HttpMessage;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+
+const TENANTS_PATH: &str = "/vectordb/tenants";
+
+/// Set on requests made through `/vectordb/tenants/{tenant}/collections`,
+/// holds the name of the tenant
+#[derive(Debug, Clone)]
+pub(crate) struct TenantScope(pub String);
+
+// Scopes the `/vectordb` routes to tenants, must run after the
+// authentication middleware.
+//
+// Requests to `/vectordb/tenants/{tenant}/collections/{collection}/...` are
+// rewritten to `/vectordb/collections/{tenant}::{collection}/...` once the
+// user is known to have access to the tenant, so that the collection
+// modules serve tenant collections unchanged. Users other than admin are
+// limited to the tenant routes.
+pub(crate) struct TenantScopeMiddleware(pub Arc<AppEnv>);
+
+impl<S, B> Transform<S, ServiceRequest> for TenantScopeMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TenantScopeMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TenantScopeMiddlewareService {
+            service,
+            ain_env: self.0.clone(),
+        }))
+    }
+}
+
+pub(crate) struct TenantScopeMiddlewareService<S> {
+    service: S,
+    ain_env: Arc<AppEnv>,
+}
+
+impl<S, B> Service<ServiceRequest> for TenantScopeMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if let Err(e) = scope_request(&mut req, &self.ain_env) {
+            return Box::pin(async move { Err(e.into()) });
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            Ok(res)
+        })
+    }
+}
+
+fn scope_request(req: &mut ServiceRequest, ain_env: &AppEnv) -> Result<(), TenantsError> {
+    let username = req
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.username.clone())
+        .ok_or(TenantsError::Forbidden)?;
+    let is_admin = username == ADMIN_USERNAME;
+
+    let path = req.path();
+    let Some(tenant_path) = path.strip_prefix(TENANTS_PATH) else {
+        return if is_admin {
+            Ok(())
+        } else {
+            Err(TenantsError::Forbidden)
+        };
+    };
+
+    // tenant management routes check permissions themselves
+    let Some((tenant_name, collections_path)) = tenant_path
+        .strip_prefix('/')
+        .and_then(|tenant_path| tenant_path.split_once('/'))
+        .and_then(|(tenant_name, rest)| Some((tenant_name, rest.strip_prefix("collections")?)))
+        .filter(|(_, collections_path)| {
+            collections_path.is_empty() || collections_path.starts_with('/')
+        })
+    else {
+        return Ok(());
+    };
+
+    let tenant = ain_env
+        .tenants_map
+        .get(tenant_name)
+        .ok_or_else(|| TenantsError::NotFound(tenant_name.to_string()))?;
+    if !is_admin && !tenant.has_user(&username) {
+        return Err(TenantsError::Forbidden);
+    }
+
+    let new_path = match collections_path.strip_prefix('/') {
+        Some(collection_path) if !collection_path.is_empty() => format!(
+            "/vectordb/collections/{}",
+            qualified_collection_name(tenant_name, collection_path)
+        ),
+        _ => format!("/vectordb/collections{}", collections_path),
+    };
+    let path_and_query = match req.query_string() {
+        "" => new_path,
+        query => format!("{}?{}", new_path, query),
+    };
+
+    let mut uri_parts = req.uri().clone().into_parts();
+    uri_parts.path_and_query = Some(
+        path_and_query
+            .parse()
+            .map_err(|_| TenantsError::ServerError("Failed to rewrite path".to_string()))?,
+    );
+    let uri = Uri::from_parts(uri_parts)
+        .map_err(|_| TenantsError::ServerError("Failed to rewrite path".to_string()))?;
+
+    req.match_info_mut().get_mut().update(&uri);
+    req.head_mut().uri = uri;
+    req.extensions_mut().insert(TenantScope(tenant.name));
+
+    Ok(())
+}
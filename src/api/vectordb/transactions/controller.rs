@@ -9,25 +9,31 @@ use crate::{
 };
 
 use super::{
-    dtos::{CreateTransactionResponseDto, UpsertDto},
+    dtos::{
+        CommitTransactionQueryDto, CreateTransactionResponseDto, DeleteVectorsDto,
+        DeleteVectorsResponseDto, UpsertDto,
+    },
     error::TransactionError,
     service,
+    upsert_body::UpsertBody,
 };
 
 /// Create a new transaction for a collection
 ///
-/// Creates a new transaction for modifying vectors in a collection.
+/// Creates a new transaction for modifying vectors in a collection. Several
+/// transactions can be open on a collection at once, each one is written to
+/// its own WAL and gets its version when it's committed.
 #[utoipa::path(
     post,
     path = "/vectordb/collections/{collection_id}/transactions",
     tag = "transactions",
     params(
-        ("collection_id" = String, Path, description = "Collection identifier")
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
     ),
     responses(
         (status = 200, description = "Transaction created successfully", body = CreateTransactionResponseDto),
-        (status = 400, description = "Failed to create transaction"),
-        (status = 409, description = "There is an ongoing transaction")
+        (status = 400, description = "Failed to create transaction")
     )
 )]
 pub(crate) async fn create_transaction(
@@ -46,22 +52,37 @@ pub(crate) async fn create_transaction(
 
 /// Commit a transaction
 ///
-/// Commits all changes in the transaction to the collection.
+/// Commits all changes in the transaction to the collection. Transactions
+/// get their versions in commit order, and are indexed in that order.
+///
+/// With `on_conflict=last_committer_wins`, the default, vectors that were
+/// also written by a transaction committed since this one was opened are
+/// overwritten by this one. With `on_conflict=abort`, the transaction is
+/// aborted instead, and `409` is returned.
+///
+/// The `if_absent` and `if_version` preconditions of the transaction's
+/// writes are checked again, the transaction is aborted and `412` returned
+/// if a vector was written since its precondition was checked.
 #[utoipa::path(
     post,
     path = "/vectordb/collections/{collection_id}/transactions/{transaction_id}/commit",
     tag = "transactions",
     params(
         ("collection_id" = String, Path, description = "Collection identifier"),
-        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier")
+        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier"),
+        CommitTransactionQueryDto,
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
     ),
     responses(
         (status = 204, description = "Transaction committed successfully"),
-        (status = 400, description = "Failed to commit transaction")
+        (status = 400, description = "Failed to commit transaction"),
+        (status = 409, description = "The transaction conflicts with a committed one and was aborted"),
+        (status = 412, description = "A precondition of the transaction no longer holds, the transaction was aborted")
     )
 )]
 pub(crate) async fn commit_transaction(
     params: web::Path<(String, ExplicitTransactionID)>,
+    web::Query(query): web::Query<CommitTransactionQueryDto>,
     ctx: web::Data<AppContext>,
 ) -> Result<HttpResponse, TransactionError> {
     let (collection_id, transaction_id) = params.into_inner();
@@ -69,7 +90,13 @@ pub(crate) async fn commit_transaction(
     ctx.update_collection_for_transaction(&collection_id)
         .map_err(|e| TransactionError::FailedToCommitTransaction(format!("Cache error: {}", e)))?;
 
-    service::commit_transaction(ctx.into_inner(), &collection_id, transaction_id).await?;
+    service::commit_transaction(
+        ctx.into_inner(),
+        &collection_id,
+        transaction_id,
+        query.on_conflict,
+    )
+    .await?;
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -109,19 +136,22 @@ pub(crate) async fn get_transaction_status(
 
 /// Create a vector in a transaction
 ///
-/// Creates a new vector as part of an ongoing transaction.
+/// Creates a new vector as part of an ongoing transaction. An existing vector
+/// is only replaced if its `if_version` precondition is met.
 #[utoipa::path(
     post,
     path = "/vectordb/collections/{collection_id}/transactions/{transaction_id}/vectors",
     tag = "transactions",
     params(
         ("collection_id" = String, Path, description = "Collection identifier"),
-        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier")
+        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
     ),
     request_body = CreateVectorDto,
     responses(
         (status = 200, description = "Vector created successfully"),
         (status = 400, description = "Failed to create vector"),
+        (status = 412, description = "The precondition of the vector isn't met"),
         (status = 404, description = "Transaction not found")
     )
 )]
@@ -153,7 +183,8 @@ pub(crate) async fn create_vector_in_transaction(
     tag = "transactions",
     params(
         ("collection_id" = String, Path, description = "Collection identifier"),
-        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier")
+        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
     ),
     responses(
         (status = 204, description = "Transaction aborted successfully"),
@@ -183,7 +214,8 @@ pub(crate) async fn abort_transaction(
     params(
         ("collection_id" = String, Path, description = "Collection identifier"),
         ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier"),
-        ("vector_id" = String, Path, description = "Vector identifier")
+        ("vector_id" = String, Path, description = "Vector identifier"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
     ),
     responses(
         (status = 204, description = "Vector deleted successfully"),
@@ -210,39 +242,89 @@ pub(crate) async fn delete_vector_by_id(
     Ok(HttpResponse::NoContent().finish())
 }
 
+/// Delete vectors in a transaction
+///
+/// Deletes the vectors with the given IDs, all the vectors of a document or all the vectors
+/// whose metadata match a filter, as part of an ongoing transaction. The vectors of a document
+/// or matching a filter are selected when the request is handled, among the vectors written
+/// so far outside of the transaction.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/transactions/{transaction_id}/delete",
+    tag = "transactions",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
+    ),
+    request_body = DeleteVectorsDto,
+    responses(
+        (status = 200, description = "Vectors deleted successfully", body = DeleteVectorsResponseDto),
+        (status = 400, description = "Failed to delete vectors or transaction not found")
+    )
+)]
+pub(crate) async fn delete_vectors(
+    params: web::Path<(String, ExplicitTransactionID)>,
+    web::Json(delete_dto): web::Json<DeleteVectorsDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, TransactionError> {
+    let (collection_id, transaction_id) = params.into_inner();
+
+    ctx.update_collection_for_transaction(&collection_id)
+        .map_err(|e| TransactionError::FailedToDeleteVector(format!("Cache error: {}", e)))?;
+
+    let response =
+        service::delete_vectors(ctx.into_inner(), &collection_id, transaction_id, delete_dto)
+            .await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
 /// Upsert vectors in a transaction
 ///
 /// Creates or updates multiple vectors in a single operation as part of an ongoing transaction.
+/// NDJSON bodies are upserted in batches as they're received, so the vectors before a line
+/// that fails to parse are upserted.
+///
+/// Vectors with `if_absent` are only written if their id doesn't exist, and existing vectors
+/// are only replaced by vectors with an `if_version` matching the version they were last
+/// written at. Preconditions are checked when the vectors are written to the transaction.
 #[utoipa::path(
     post,
     path = "/vectordb/collections/{collection_id}/transactions/{transaction_id}/upsert",
     tag = "transactions",
     params(
         ("collection_id" = String, Path, description = "Collection identifier"),
-        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier")
+        ("transaction_id" = ExplicitTransactionID, Path, description = "Transaction identifier"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the response of a request with the same key handled within the idempotency window instead of handling it again, a request with another body gets 422")
+    ),
+    request_body(
+        description = "Vectors to upsert, as JSON, as CBOR with dense and sparse values optionally sent as byte strings of little-endian f32s, or as NDJSON with one vector per line",
+        content(
+            (UpsertDto = "application/json"),
+            (UpsertDto = "application/cbor"),
+            (CreateVectorDto = "application/x-ndjson")
+        )
     ),
-    request_body = UpsertDto,
     responses(
         (status = 200, description = "Vectors upserted successfully"),
-        (status = 400, description = "Failed to upsert vectors")
+        (status = 400, description = "Failed to upsert vectors"),
+        (status = 412, description = "The precondition of a vector isn't met, no vector is upserted"),
+        (status = 413, description = "The CBOR body or a line of the NDJSON body is too large")
     )
 )]
 pub(crate) async fn upsert(
     path: web::Path<(String, ExplicitTransactionID)>,
     ctx: web::Data<AppContext>,
-    web::Json(upsert_dto): web::Json<UpsertDto>,
+    mut body: UpsertBody,
 ) -> Result<HttpResponse, TransactionError> {
     let (collection_id, transaction_id) = path.into_inner();
 
     ctx.update_collection_for_transaction(&collection_id)
         .map_err(|e| TransactionError::FailedToCreateVector(format!("Cache error: {}", e)))?;
 
-    service::upsert_vectors(
-        ctx.into_inner(),
-        &collection_id,
-        transaction_id,
-        upsert_dto.vectors,
-    )
-    .await?;
+    let ctx = ctx.into_inner();
+    while let Some(vectors) = body.next_batch().await? {
+        service::upsert_vectors(ctx.clone(), &collection_id, transaction_id, vectors).await?;
+    }
     Ok(HttpResponse::Ok().finish())
 }
@@ -1,10 +1,14 @@
 use crate::{
     api::vectordb::vectors::dtos::CreateVectorDto,
-    models::collection_transaction::ExplicitTransactionID,
+    metadata::query_filtering::Filter,
+    models::{
+        collection_transaction::{ConflictResolution, ExplicitTransactionID},
+        types::{DocumentId, VectorId},
+    },
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 #[derive(Serialize, ToSchema)]
 pub struct CreateTransactionResponseDto {
@@ -17,3 +21,32 @@ pub struct CreateTransactionResponseDto {
 pub struct UpsertDto {
     pub vectors: Vec<CreateVectorDto>,
 }
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub(crate) struct CommitTransactionQueryDto {
+    /// How vectors written by a transaction committed since this one was
+    /// opened are treated, `last_committer_wins` by default
+    #[serde(default)]
+    #[param(value_type = Option<ConflictResolution>)]
+    pub on_conflict: ConflictResolution,
+}
+
+/// Vectors to delete, selected by exactly one of the fields
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct DeleteVectorsDto {
+    /// IDs of the vectors to delete
+    #[schema(value_type = Option<Vec<String>>)]
+    pub ids: Option<Vec<VectorId>>,
+    /// Deletes all the vectors of the document
+    #[schema(value_type = Option<String>)]
+    pub document_id: Option<DocumentId>,
+    /// Deletes all the vectors whose metadata match the filter
+    #[schema(value_type = Option<String>)]
+    pub filter: Option<Filter>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct DeleteVectorsResponseDto {
+    /// Number of vectors selected for deletion
+    pub count: usize,
+}
@@ -11,13 +11,18 @@ pub(crate) enum TransactionError {
     NotFound,
     CollectionNotFound,
     IndexNotFound,
-    OnGoingTransaction,
     FailedToGetAppEnv,
     FailedToGetTransactionStatus(String),
     FailedToCreateTransaction(String),
     FailedToCommitTransaction(String),
+    FailedToAbortTransaction(String),
     FailedToCreateVector(String),
     FailedToDeleteVector(String),
+    QuotaExceeded(String),
+    Conflict(String),
+    PreconditionFailed(String),
+    EmbeddingFailed(String),
+    PayloadTooLarge(String),
     NotImplemented,
 }
 
@@ -28,7 +33,6 @@ impl Display for TransactionError {
             Self::CollectionNotFound => write!(f, "Collection not found!"),
             Self::IndexNotFound => write!(f, "Index not found!"),
             Self::FailedToGetAppEnv => write!(f, "Failed to get App Env!"),
-            Self::OnGoingTransaction => write!(f, "There is an on-going transaction!"),
             Self::FailedToGetTransactionStatus(msg) => {
                 write!(f, "Failed to get transaction status due to {}", msg)
             }
@@ -38,6 +42,9 @@ impl Display for TransactionError {
             Self::FailedToCommitTransaction(msg) => {
                 write!(f, "Failed to commit transaction due to {}", msg)
             }
+            Self::FailedToAbortTransaction(msg) => {
+                write!(f, "Failed to abort transaction due to {}", msg)
+            }
             Self::NotImplemented => {
                 write!(f, "This is not supported yet!")
             }
@@ -47,6 +54,11 @@ impl Display for TransactionError {
             Self::FailedToDeleteVector(msg) => {
                 write!(f, "Failed to delete vector in transaction due to: {}", msg)
             }
+            Self::QuotaExceeded(msg) => write!(f, "Quota exceeded: {}", msg),
+            Self::Conflict(msg) => write!(f, "Conflicting transaction: {}", msg),
+            Self::PreconditionFailed(msg) => write!(f, "Precondition failed: {}", msg),
+            Self::EmbeddingFailed(msg) => write!(f, "Failed to embed text: {}", msg),
+            Self::PayloadTooLarge(msg) => write!(f, "Payload too large: {}", msg),
         }
     }
 }
@@ -66,10 +78,15 @@ impl ResponseError for TransactionError {
             Self::FailedToGetTransactionStatus(_) => StatusCode::BAD_REQUEST,
             Self::FailedToCreateTransaction(_) => StatusCode::BAD_REQUEST,
             Self::FailedToCommitTransaction(_) => StatusCode::BAD_REQUEST,
-            Self::OnGoingTransaction => StatusCode::CONFLICT,
+            Self::FailedToAbortTransaction(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::NotImplemented => StatusCode::BAD_REQUEST,
             Self::FailedToCreateVector(_) => StatusCode::BAD_REQUEST,
             Self::FailedToDeleteVector(_) => StatusCode::BAD_REQUEST,
+            Self::QuotaExceeded(_) => StatusCode::FORBIDDEN,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            Self::EmbeddingFailed(_) => StatusCode::BAD_GATEWAY,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
         }
     }
 }
@@ -0,0 +1,243 @@
+use std::cell::RefCell;
+use std::future::{ready, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::LazyLock;
+use std::task::{Context, Poll};
+
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{self, PayloadError},
+    http::{
+        header::{HeaderName, HeaderValue},
+        StatusCode,
+    },
+    web::{self, Bytes},
+    Error, HttpMessage, HttpResponse,
+};
+use dashmap::{mapref::entry::Entry, DashMap};
+use futures_util::{future::LocalBoxFuture, Stream, StreamExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    api::auth::dtos::Claims,
+    app_context::AppContext,
+    models::{crypto::get_current_timestamp, idempotency::IdempotentResponse},
+};
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+// hashes of the keys of the requests being handled
+static IN_PROGRESS: LazyLock<DashMap<[u8; 32], ()>> = LazyLock::new(DashMap::new);
+
+// Replays the response of a write sent again with the `Idempotency-Key` of
+// a write handled within the last `transactions.idempotency_window` seconds,
+// so that clients can retry writes whose response they didn't get without
+// applying them twice. Responses are persisted, along with the hash of the
+// request body, a retry with a different body gets `422`. Keys are scoped to
+// the user, method and path of the request, and a retry sent while the write
+// is still being handled gets `409`. Server errors aren't remembered, so
+// that the write can be retried.
+pub(crate) struct IdempotencyMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for IdempotencyMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IdempotencyMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotencyMiddlewareService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub(crate) struct IdempotencyMiddlewareService<S> {
+    // shared with the futures of the requests, which call it once the
+    // stored responses are looked up
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let Some(ctx) = req
+            .app_data::<web::Data<AppContext>>()
+            .filter(|ctx| ctx.config.transactions.idempotency_window > 0)
+            .cloned()
+        else {
+            let fut = self.service.call(req);
+            return Box::pin(async move { Ok(fut.await?.map_into_boxed_body()) });
+        };
+        let Some(key) = idempotency_key(&req) else {
+            let fut = self.service.call(req);
+            return Box::pin(async move { Ok(fut.await?.map_into_boxed_body()) });
+        };
+
+        // the entry is released before any I/O, a retry sent meanwhile gets
+        // `409`
+        let in_progress = match IN_PROGRESS.entry(key) {
+            Entry::Occupied(_) => {
+                return Box::pin(async move {
+                    Err(error::ErrorConflict(
+                        "A request with this idempotency key is in progress",
+                    ))
+                });
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(());
+                InProgressGuard(key)
+            }
+        };
+        // the body is hashed as the handler reads it
+        let body = Rc::new(RefCell::new(HashedBody {
+            payload: req.take_payload(),
+            hasher: Sha256::new(),
+        }));
+        req.set_payload(Payload::from(
+            Box::pin(HashingPayload(body.clone())) as Pin<Box<dyn Stream<Item = _>>>
+        ));
+        let service = self.service.clone();
+        Box::pin(async move {
+            let responses_ctx = ctx.clone();
+            let stored = web::block(move || responses_ctx.ain_env.idempotent_responses.get(&key))
+                .await
+                .map_err(error::ErrorInternalServerError)?
+                .map_err(error::ErrorInternalServerError)?;
+            if let Some(stored) = stored {
+                if read_body_hash(&body).await? != stored.request_hash {
+                    return Err(error::ErrorUnprocessableEntity(
+                        "The idempotency key was used for a request with another body",
+                    ));
+                }
+                return Ok(req.into_response(replayed_response(stored)));
+            }
+
+            let res = service.call(req).await?;
+            if res.status().is_server_error() {
+                return Ok(res.map_into_boxed_body());
+            }
+            let (req, res) = res.into_parts();
+            let (response, res_body) = res.into_parts();
+            let Ok(res_body) = body::to_bytes(res_body).await else {
+                return Err(error::ErrorInternalServerError(
+                    "Failed to read the response",
+                ));
+            };
+            // the handler may not have read the whole body
+            let request_hash = read_body_hash(&body).await?;
+            let stored = IdempotentResponse {
+                status: response.status().as_u16(),
+                headers: response
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+                    .collect(),
+                body: res_body.to_vec(),
+                request_hash,
+                expires_at: get_current_timestamp() + ctx.config.transactions.idempotency_window,
+            };
+            let inserted =
+                web::block(move || ctx.ain_env.idempotent_responses.insert(&key, &stored)).await;
+            if let Err(err) = inserted
+                .map_err(Error::from)
+                .and_then(|result| result.map_err(error::ErrorInternalServerError))
+            {
+                log::warn!(
+                    "Failed to store the response of an idempotent request: {}",
+                    err
+                );
+            }
+            drop(in_progress);
+            let response = response.set_body(BoxBody::new(res_body));
+            Ok(ServiceResponse::new(req, response))
+        })
+    }
+}
+
+struct HashedBody {
+    payload: Payload,
+    hasher: Sha256,
+}
+
+// the request payload, hashed as it's read
+struct HashingPayload(Rc<RefCell<HashedBody>>);
+
+impl Stream for HashingPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut body = self.0.borrow_mut();
+        let item = body.payload.poll_next_unpin(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &item {
+            body.hasher.update(chunk);
+        }
+        item
+    }
+}
+
+// reads what's left of the body and returns its hash
+async fn read_body_hash(body: &Rc<RefCell<HashedBody>>) -> Result<[u8; 32], Error> {
+    let mut payload = HashingPayload(body.clone());
+    while let Some(chunk) = payload.next().await {
+        chunk?;
+    }
+    Ok(body.borrow().hasher.clone().finalize().into())
+}
+
+fn replayed_response(stored: IdempotentResponse) -> HttpResponse {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = HttpResponse::with_body(status, BoxBody::new(stored.body));
+    for (name, value) in stored.headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::from_bytes(&value))
+        {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response.headers_mut().insert(
+        REPLAYED_HEADER.try_into().unwrap(),
+        "true".try_into().unwrap(),
+    );
+    response
+}
+
+// forgets the key of a request once it's handled, including when its
+// handling is cancelled, so that it can be retried
+struct InProgressGuard([u8; 32]);
+
+impl Drop for InProgressGuard {
+    fn drop(&mut self) {
+        IN_PROGRESS.remove(&self.0);
+    }
+}
+
+// hash of the user, method, path and key of the request
+fn idempotency_key(req: &ServiceRequest) -> Option<[u8; 32]> {
+    let key = req.headers().get(IDEMPOTENCY_KEY_HEADER)?.to_str().ok()?;
+    let username = req
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.username.clone())
+        .unwrap_or_default();
+    let key = format!("{}\n{}\n{}\n{}", username, req.method(), req.path(), key);
+    Some(Sha256::digest(key.as_bytes()).into())
+}
@@ -1,18 +1,176 @@
+use std::collections::HashSet;
+use std::io;
 use std::sync::Arc;
 
+use actix_web::web;
+
 use self::vectors::dtos::CreateVectorDto;
 
-use super::{dtos::CreateTransactionResponseDto, error::TransactionError};
+use super::{
+    dtos::{CreateTransactionResponseDto, DeleteVectorsDto},
+    error::TransactionError,
+};
+use crate::models::collection::Collection;
 use crate::models::collection_transaction::{
-    ExplicitTransaction, ExplicitTransactionID, TransactionStatus,
+    ConflictResolution, ExplicitTransaction, ExplicitTransactionID, TransactionStatus,
 };
 use crate::models::meta_persist::update_current_version;
+use crate::models::tenants::QuotaError;
 use crate::models::types::VectorId;
 use crate::models::versioning::VersionNumber;
 use crate::models::wal::VectorOp;
 use crate::{api::vectordb::vectors, app_context::AppContext};
 use chrono::Utc;
 
+// checks the collection's and its tenant's quotas before writing the
+// vectors `ids`, the ones replacing existing vectors don't count against them
+pub(crate) fn check_vector_quota<'a>(
+    ctx: &AppContext,
+    collection: &Collection,
+    ids: impl IntoIterator<Item = &'a VectorId>,
+) -> Result<(), TransactionError> {
+    let new_ids: HashSet<_> = ids
+        .into_iter()
+        .filter(|id| collection.external_to_internal_map.get_latest(id).is_none())
+        .collect();
+    ctx.ain_env
+        .tenants_map
+        .check_vector_quota(
+            &ctx.ain_env.collections_map,
+            collection,
+            new_ids.len() as u32,
+        )
+        .map_err(|err| match err {
+            QuotaError::Io(_) => TransactionError::FailedToCreateVector(err.to_string()),
+            err => TransactionError::QuotaExceeded(err.to_string()),
+        })
+}
+
+// checks the `if_absent` and `if_version` preconditions of `vectors` against
+// the latest versions of the collection's vectors, and returns the ids of
+// the existing vectors they allow to be replaced
+pub(crate) fn check_preconditions(
+    collection: &Collection,
+    vectors: &[CreateVectorDto],
+) -> Result<HashSet<VectorId>, TransactionError> {
+    let mut replaced = HashSet::new();
+    for vector in vectors {
+        if !vector.if_absent && vector.if_version.is_none() {
+            continue;
+        }
+        let stored_version = collection.vector_version(&vector.id);
+        if vector.if_absent {
+            if let Some(version) = stored_version {
+                return Err(TransactionError::PreconditionFailed(format!(
+                    "vector `{}` already exists, written at version {}",
+                    vector.id, *version
+                )));
+            }
+        }
+        if let Some(expected_version) = vector.if_version {
+            match stored_version {
+                Some(version) if *version == expected_version => {
+                    replaced.insert(vector.id.clone());
+                }
+                Some(version) => {
+                    return Err(TransactionError::PreconditionFailed(format!(
+                        "vector `{}` was last written at version {}, not {}",
+                        vector.id, *version, expected_version
+                    )))
+                }
+                None => {
+                    return Err(TransactionError::PreconditionFailed(format!(
+                        "vector `{}` doesn't exist",
+                        vector.id
+                    )))
+                }
+            }
+        }
+    }
+    Ok(replaced)
+}
+
+// records the preconditions of `vectors` written to `transaction`, so that
+// they're checked again when it's committed
+fn add_preconditions(
+    collection: &Collection,
+    transaction: &ExplicitTransaction,
+    vectors: &[CreateVectorDto],
+) -> Result<(), TransactionError> {
+    let checked = vectors.iter().filter_map(|vector| {
+        if vector.if_absent {
+            Some((vector.id.clone(), None))
+        } else {
+            vector
+                .if_version
+                .map(|version| (vector.id.clone(), Some(version)))
+        }
+    });
+    transaction
+        .add_preconditions(collection, checked)
+        .map_err(|err| TransactionError::FailedToCreateVector(err.to_string()))
+}
+
+// positions and texts of the vectors with a `text` but no values
+fn texts_missing_values<T>(
+    vectors: &[CreateVectorDto],
+    values: impl Fn(&CreateVectorDto) -> &Option<T>,
+) -> (Vec<usize>, Vec<&str>) {
+    vectors
+        .iter()
+        .enumerate()
+        .filter(|(_, vector)| values(vector).is_none())
+        .filter_map(|(i, vector)| Some((i, vector.text.as_deref()?)))
+        .unzip()
+}
+
+// computes the dense and sparse values missing from the vectors with a
+// `text`, with the embedding providers of the collection
+pub(crate) async fn embed_texts(
+    ctx: &AppContext,
+    collection: &Collection,
+    mut vectors: Vec<CreateVectorDto>,
+) -> Result<Vec<CreateVectorDto>, TransactionError> {
+    let dense_vector = collection.meta.dense_vector.clone();
+    let sparse_vector = collection.meta.sparse_vector.clone();
+    let embeds_dense = dense_vector.enabled && dense_vector.embedding.is_some();
+    let embeds_sparse = sparse_vector.enabled && sparse_vector.embedding.is_some();
+    let is_missing_values = |vector: &CreateVectorDto| {
+        vector.text.is_some()
+            && ((embeds_dense && vector.dense_values.is_none())
+                || (embeds_sparse && vector.sparse_values.is_none()))
+    };
+    if !vectors.iter().any(is_missing_values) {
+        return Ok(vectors);
+    }
+
+    let providers = ctx.embedding_providers.clone();
+    web::block(move || -> io::Result<_> {
+        if embeds_dense {
+            let (missing, texts) = texts_missing_values(&vectors, |vector| &vector.dense_values);
+            if !texts.is_empty() {
+                let embeddings = providers.embed_dense(&dense_vector, &texts)?;
+                for (i, values) in missing.into_iter().zip(embeddings) {
+                    vectors[i].dense_values = Some(values);
+                }
+            }
+        }
+        if embeds_sparse {
+            let (missing, texts) = texts_missing_values(&vectors, |vector| &vector.sparse_values);
+            if !texts.is_empty() {
+                let embeddings = providers.embed_sparse(&sparse_vector, &texts)?;
+                for (i, values) in missing.into_iter().zip(embeddings) {
+                    vectors[i].sparse_values = Some(values);
+                }
+            }
+        }
+        Ok(vectors)
+    })
+    .await
+    .map_err(|err| TransactionError::FailedToCreateVector(err.to_string()))?
+    .map_err(|err| TransactionError::EmbeddingFailed(err.to_string()))
+}
+
 // creates a transaction for a specific collection
 pub(crate) async fn create_transaction(
     ctx: Arc<AppContext>,
@@ -24,17 +182,13 @@ pub(crate) async fn create_transaction(
         .get_collection(collection_id)
         .ok_or(TransactionError::CollectionNotFound)?;
 
-    let mut current_open_transaction_guard = collection.current_explicit_transaction.write();
-
-    if current_open_transaction_guard.is_some() {
-        return Err(TransactionError::OnGoingTransaction);
-    }
+    let mut open_transactions_guard = collection.open_explicit_transactions.write();
 
     let transaction = ExplicitTransaction::new(&collection, &ctx.config)
         .map_err(|err| TransactionError::FailedToCreateTransaction(err.to_string()))?;
     let transaction_id = transaction.id;
 
-    *current_open_transaction_guard = Some(transaction);
+    open_transactions_guard.insert(transaction_id, transaction);
 
     Ok(CreateTransactionResponseDto {
         transaction_id,
@@ -47,6 +201,7 @@ pub(crate) async fn commit_transaction(
     ctx: Arc<AppContext>,
     collection_id: &str,
     transaction_id: ExplicitTransactionID,
+    on_conflict: ConflictResolution,
 ) -> Result<(), TransactionError> {
     let collection = ctx
         .ain_env
@@ -54,29 +209,63 @@ pub(crate) async fn commit_transaction(
         .get_collection(collection_id)
         .ok_or(TransactionError::CollectionNotFound)?;
 
-    let mut current_version_guard = collection.current_version.write();
+    commit_open_transaction(&collection, transaction_id, on_conflict)
+}
 
-    let mut current_open_transaction_guard = collection.current_explicit_transaction.write();
-    let Some(current_open_transaction) = current_open_transaction_guard.take() else {
+// commits the open transaction `transaction_id` of a collection and starts
+// indexing it, versions are allotted in commit order
+pub(crate) fn commit_open_transaction(
+    collection: &Collection,
+    transaction_id: ExplicitTransactionID,
+    on_conflict: ConflictResolution,
+) -> Result<(), TransactionError> {
+    // taken first, as streamed conditional writes take it before allotting
+    // a version, the other locks are taken in the order documented on
+    // `Collection`
+    let _conditional_writes_guard = collection.conditional_writes_lock.lock();
+    let mut open_transactions_guard = collection.open_explicit_transactions.write();
+    let Some(current_open_transaction) = open_transactions_guard.remove(&transaction_id) else {
         return Err(TransactionError::NotFound);
     };
     let current_transaction_id = current_open_transaction.id;
 
-    if current_transaction_id != transaction_id {
-        return Err(TransactionError::NotFound);
+    if let Some(vector_id) = current_open_transaction.find_failed_precondition(collection) {
+        current_open_transaction
+            .abort(collection)
+            .map_err(|err| TransactionError::FailedToAbortTransaction(err.to_string()))?;
+        return Err(TransactionError::PreconditionFailed(format!(
+            "vector `{}` was written since its precondition was checked, the transaction is \
+             aborted",
+            vector_id
+        )));
+    }
+
+    if on_conflict == ConflictResolution::Abort {
+        if let Some(vector_id) = current_open_transaction.find_conflict(collection) {
+            current_open_transaction
+                .abort(collection)
+                .map_err(|err| TransactionError::FailedToAbortTransaction(err.to_string()))?;
+            return Err(TransactionError::Conflict(format!(
+                "vector `{}` was written by a transaction committed after this one was opened, \
+                 the transaction is aborted",
+                vector_id
+            )));
+        }
     }
+    let written_ids = current_open_transaction.written_ids();
 
     let mut last_allotted_version = collection.last_allotted_version.write();
+    let mut current_version_guard = collection.current_version.write();
     *last_allotted_version = VersionNumber::from(**last_allotted_version + 1);
 
     let allotted_version = *last_allotted_version;
 
-    let records_upserted = current_open_transaction.wal.records_upserted();
-    let records_deleted = current_open_transaction.wal.records_deleted();
-    let total_operations = current_open_transaction.wal.total_operations();
+    let records_upserted = current_open_transaction.records_upserted();
+    let records_deleted = current_open_transaction.records_deleted();
+    let total_operations = current_open_transaction.total_operations();
 
     current_open_transaction
-        .pre_commit(&collection, allotted_version)
+        .pre_commit(collection, allotted_version)
         .map_err(|err| TransactionError::FailedToCommitTransaction(err.to_string()))?;
 
     *current_version_guard = allotted_version;
@@ -93,6 +282,19 @@ pub(crate) async fn commit_transaction(
         .map_err(|err| TransactionError::FailedToCommitTransaction(err.to_string()))?;
     update_current_version(&collection.lmdb, allotted_version)
         .map_err(|err| TransactionError::FailedToCommitTransaction(err.to_string()))?;
+
+    // only the transactions that are still open can conflict with a commit
+    let oldest_start_version = open_transactions_guard
+        .values()
+        .map(|transaction| *transaction.start_version)
+        .min();
+    let mut recent_commits = collection.recent_commits.lock();
+    recent_commits.push((allotted_version, written_ids));
+    recent_commits.retain(|(version, _)| {
+        oldest_start_version.is_some_and(|start_version| **version > start_version)
+    });
+    drop(recent_commits);
+
     collection.trigger_indexing(current_transaction_id, allotted_version);
 
     Ok(())
@@ -129,29 +331,34 @@ pub(crate) async fn create_vector_in_transaction(
         .collections_map
         .get_collection(collection_id)
         .ok_or(TransactionError::CollectionNotFound)?;
+    let create_vector_dto = embed_texts(&ctx, &collection, vec![create_vector_dto])
+        .await?
+        .remove(0);
 
-    let current_open_transaction_guard = collection.current_explicit_transaction.read();
-    let Some(current_open_transaction) = &*current_open_transaction_guard else {
+    let open_transactions_guard = collection.open_explicit_transactions.read();
+    let Some(current_open_transaction) = open_transactions_guard.get(&transaction_id) else {
         return Err(TransactionError::NotFound);
     };
-
-    if current_open_transaction.id != transaction_id {
-        return Err(TransactionError::FailedToCreateVector(
-            "This is not the currently open transaction!".into(),
-        ));
-    }
+    let replaced = check_preconditions(&collection, std::slice::from_ref(&create_vector_dto))?;
+    check_vector_quota(&ctx, &collection, [&create_vector_dto.id])?;
+    add_preconditions(
+        &collection,
+        current_open_transaction,
+        std::slice::from_ref(&create_vector_dto),
+    )?;
 
     vectors::repo::create_vector_in_transaction(
         &collection,
         current_open_transaction,
         create_vector_dto,
+        &replaced,
     )
     .map_err(|e| TransactionError::FailedToCreateVector(e.to_string()))?;
 
     Ok(())
 }
 
-// aborts the currently open transaction of a ollection
+// aborts an open transaction of a collection
 pub(crate) async fn abort_transaction(
     ctx: Arc<AppContext>,
     collection_id: &str,
@@ -163,17 +370,25 @@ pub(crate) async fn abort_transaction(
         .get_collection(collection_id)
         .ok_or(TransactionError::CollectionNotFound)?;
 
-    let mut current_open_transaction_guard = collection.current_explicit_transaction.write();
-    let Some(current_open_transaction) = current_open_transaction_guard.take() else {
-        return Err(TransactionError::NotFound);
-    };
-    let current_transaction_id = current_open_transaction.id;
+    abort_open_transaction(&collection, transaction_id)
+}
 
-    if current_transaction_id != transaction_id {
+// aborts the open transaction `transaction_id` of a collection
+pub(crate) fn abort_open_transaction(
+    collection: &Collection,
+    transaction_id: ExplicitTransactionID,
+) -> Result<(), TransactionError> {
+    let Some(current_open_transaction) = collection
+        .open_explicit_transactions
+        .write()
+        .remove(&transaction_id)
+    else {
         return Err(TransactionError::NotFound);
-    }
+    };
 
-    Ok(())
+    current_open_transaction
+        .abort(collection)
+        .map_err(|err| TransactionError::FailedToAbortTransaction(err.to_string()))
 }
 
 pub(crate) async fn delete_vector_by_id(
@@ -188,25 +403,74 @@ pub(crate) async fn delete_vector_by_id(
         .get_collection(collection_id)
         .ok_or(TransactionError::CollectionNotFound)?;
 
-    let current_open_transaction_guard = collection.current_explicit_transaction.read();
-    let Some(current_open_transaction) = &*current_open_transaction_guard else {
+    let open_transactions_guard = collection.open_explicit_transactions.read();
+    let Some(current_open_transaction) = open_transactions_guard.get(&transaction_id) else {
         return Err(TransactionError::NotFound);
     };
 
-    if current_open_transaction.id != transaction_id {
-        return Err(TransactionError::FailedToCreateVector(
-            "This is not the currently open transaction!".into(),
-        ));
-    }
-
     current_open_transaction
-        .wal
-        .append(VectorOp::Delete(vector_id))
+        .append_to_wal(VectorOp::Delete(vector_id))
         .map_err(|e| TransactionError::FailedToDeleteVector(e.to_string()))?;
 
     Ok(())
 }
 
+// returns the ids of the vectors selected by a bulk delete, the ones of a
+// document or matching a filter being looked up as of the latest writes
+pub(crate) fn vector_ids_to_delete(
+    collection: &Collection,
+    delete_dto: DeleteVectorsDto,
+) -> Result<Vec<VectorId>, TransactionError> {
+    match delete_dto {
+        DeleteVectorsDto {
+            ids: Some(ids),
+            document_id: None,
+            filter: None,
+        } => Ok(ids),
+        DeleteVectorsDto {
+            ids: None,
+            document_id: Some(document_id),
+            filter: None,
+        } => Ok(collection.vector_ids_of_document(&document_id)),
+        DeleteVectorsDto {
+            ids: None,
+            document_id: None,
+            filter: Some(filter),
+        } => Ok(collection.vector_ids_matching(&filter)),
+        _ => Err(TransactionError::FailedToDeleteVector(
+            "exactly one of `ids`, `document_id` and `filter` must be given".to_owned(),
+        )),
+    }
+}
+
+pub(crate) async fn delete_vectors(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    transaction_id: ExplicitTransactionID,
+    delete_dto: DeleteVectorsDto,
+) -> Result<usize, TransactionError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or(TransactionError::CollectionNotFound)?;
+
+    let open_transactions_guard = collection.open_explicit_transactions.read();
+    let Some(current_open_transaction) = open_transactions_guard.get(&transaction_id) else {
+        return Err(TransactionError::NotFound);
+    };
+
+    let vector_ids = vector_ids_to_delete(&collection, delete_dto)?;
+    let count = vector_ids.len();
+    for vector_id in vector_ids {
+        current_open_transaction
+            .append_to_wal(VectorOp::Delete(vector_id))
+            .map_err(|e| TransactionError::FailedToDeleteVector(e.to_string()))?;
+    }
+
+    Ok(count)
+}
+
 pub(crate) async fn upsert_vectors(
     ctx: Arc<AppContext>,
     collection_id: &str,
@@ -218,20 +482,23 @@ pub(crate) async fn upsert_vectors(
         .collections_map
         .get_collection(collection_id)
         .ok_or(TransactionError::CollectionNotFound)?;
+    let vectors = embed_texts(&ctx, &collection, vectors).await?;
 
-    let current_open_transaction_guard = collection.current_explicit_transaction.read();
-    let Some(current_open_transaction) = &*current_open_transaction_guard else {
+    let open_transactions_guard = collection.open_explicit_transactions.read();
+    let Some(current_open_transaction) = open_transactions_guard.get(&transaction_id) else {
         return Err(TransactionError::NotFound);
     };
+    let replaced = check_preconditions(&collection, &vectors)?;
+    check_vector_quota(&ctx, &collection, vectors.iter().map(|vector| &vector.id))?;
+    add_preconditions(&collection, current_open_transaction, &vectors)?;
 
-    if current_open_transaction.id != transaction_id {
-        return Err(TransactionError::FailedToCreateVector(
-            "This is not the currently open transaction!".into(),
-        ));
-    }
-
-    vectors::repo::upsert_vectors_in_transaction(&collection, current_open_transaction, vectors)
-        .map_err(|e| TransactionError::FailedToCreateVector(e.to_string()))?;
+    vectors::repo::upsert_vectors_in_transaction(
+        &collection,
+        current_open_transaction,
+        vectors,
+        &replaced,
+    )
+    .map_err(|e| TransactionError::FailedToCreateVector(e.to_string()))?;
 
     Ok(())
 }
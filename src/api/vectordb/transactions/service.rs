@@ -4,12 +4,16 @@ use crate::{
     api::vectordb::vectors::dtos::CreateVectorDto,
     app_context::AppContext,
     models::{
-        collection_transaction::{ExplicitTransactionID, TransactionStatus},
+        collection_transaction::{ConflictResolution, ExplicitTransactionID, TransactionStatus},
         types::VectorId,
     },
 };
 
-use super::{dtos::CreateTransactionResponseDto, error::TransactionError, repo};
+use super::{
+    dtos::{CreateTransactionResponseDto, DeleteVectorsDto, DeleteVectorsResponseDto},
+    error::TransactionError,
+    repo,
+};
 
 pub(crate) async fn create_transaction(
     ctx: Arc<AppContext>,
@@ -22,8 +26,9 @@ pub(crate) async fn commit_transaction(
     ctx: Arc<AppContext>,
     collection_id: &str,
     transaction_id: ExplicitTransactionID,
+    on_conflict: ConflictResolution,
 ) -> Result<(), TransactionError> {
-    repo::commit_transaction(ctx, collection_id, transaction_id).await
+    repo::commit_transaction(ctx, collection_id, transaction_id, on_conflict).await
 }
 
 pub(crate) async fn get_transaction_status(
@@ -60,6 +65,16 @@ pub(crate) async fn delete_vector_by_id(
     repo::delete_vector_by_id(ctx, collection_id, transaction_id, vector_id).await
 }
 
+pub(crate) async fn delete_vectors(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    transaction_id: ExplicitTransactionID,
+    delete_dto: DeleteVectorsDto,
+) -> Result<DeleteVectorsResponseDto, TransactionError> {
+    let count = repo::delete_vectors(ctx, collection_id, transaction_id, delete_dto).await?;
+    Ok(DeleteVectorsResponseDto { count })
+}
+
 pub(crate) async fn upsert_vectors(
     ctx: Arc<AppContext>,
     collection_id: &str,
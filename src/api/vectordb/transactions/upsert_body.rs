@@ -0,0 +1,221 @@
+use actix_web::{
+    dev::Payload,
+    error::{ErrorBadRequest, ErrorPayloadTooLarge},
+    web, FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::{future::LocalBoxFuture, StreamExt};
+
+use crate::api::vectordb::vectors::dtos::CreateVectorDto;
+
+use super::{dtos::UpsertDto, error::TransactionError};
+
+const CBOR_CONTENT_TYPE: &str = "application/cbor";
+const NDJSON_CONTENT_TYPES: [&str; 2] = ["application/x-ndjson", "application/jsonl"];
+// same as the limit of JSON bodies
+const MAX_CBOR_BODY_SIZE: usize = 8_388_608;
+// a line holds a single vector, so the limit of whole bodies is plenty
+const MAX_NDJSON_LINE_SIZE: usize = MAX_CBOR_BODY_SIZE;
+// number of vectors of an NDJSON body upserted at once
+const NDJSON_BATCH_SIZE: usize = 1000;
+
+/// The vectors of an upsert request, depending on its content type:
+///
+/// - `application/json`: an `UpsertDto`
+/// - `application/cbor`: an `UpsertDto`, whose dense and sparse values may
+///   be byte strings of little-endian `f32`s
+/// - `application/x-ndjson` or `application/jsonl`: one `CreateVectorDto`
+///   per line, read in batches as the body is received rather than
+///   buffered whole. Lines are limited to the size of CBOR bodies.
+pub(crate) enum UpsertBody {
+    Vectors(Option<Vec<CreateVectorDto>>),
+    Ndjson(NdjsonVectors),
+}
+
+impl UpsertBody {
+    /// Returns the next vectors to upsert, `None` once all are returned.
+    /// Vectors of NDJSON bodies returned before a line fails to parse may
+    /// already be upserted.
+    pub(crate) async fn next_batch(
+        &mut self,
+    ) -> Result<Option<Vec<CreateVectorDto>>, TransactionError> {
+        match self {
+            Self::Vectors(vectors) => Ok(vectors.take()),
+            Self::Ndjson(vectors) => vectors.next_batch().await,
+        }
+    }
+}
+
+pub(crate) struct NdjsonVectors {
+    payload: Payload,
+    buffer: web::BytesMut,
+    line: usize,
+    done: bool,
+}
+
+impl NdjsonVectors {
+    async fn next_batch(&mut self) -> Result<Option<Vec<CreateVectorDto>>, TransactionError> {
+        let mut vectors = Vec::new();
+        while vectors.len() < NDJSON_BATCH_SIZE {
+            if let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line = self.buffer.split_to(end + 1);
+                self.parse_line(&line, &mut vectors)?;
+                continue;
+            }
+            if self.buffer.len() > MAX_NDJSON_LINE_SIZE {
+                return Err(TransactionError::PayloadTooLarge(format!(
+                    "line {} is longer than {} bytes",
+                    self.line + 1,
+                    MAX_NDJSON_LINE_SIZE
+                )));
+            }
+            if self.done {
+                // the last line may not end with a newline
+                if !self.buffer.is_empty() {
+                    let line = self.buffer.split();
+                    self.parse_line(&line, &mut vectors)?;
+                }
+                break;
+            }
+            match self.payload.next().await {
+                Some(chunk) => {
+                    let chunk =
+                        chunk.map_err(|e| TransactionError::FailedToCreateVector(e.to_string()))?;
+                    self.buffer.extend_from_slice(&chunk);
+                }
+                None => self.done = true,
+            }
+        }
+        Ok((!vectors.is_empty()).then_some(vectors))
+    }
+
+    fn parse_line(
+        &mut self,
+        line: &[u8],
+        vectors: &mut Vec<CreateVectorDto>,
+    ) -> Result<(), TransactionError> {
+        self.line += 1;
+        if line.trim_ascii().is_empty() {
+            return Ok(());
+        }
+        let vector = serde_json::from_slice(line).map_err(|err| {
+            TransactionError::FailedToCreateVector(format!("line {}: {}", self.line, err))
+        })?;
+        vectors.push(vector);
+        Ok(())
+    }
+}
+
+impl FromRequest for UpsertBody {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let content_type = req.content_type().to_ascii_lowercase();
+
+        if content_type == CBOR_CONTENT_TYPE {
+            let mut payload = payload.take();
+            return Box::pin(async move {
+                let mut body = web::BytesMut::new();
+                while let Some(chunk) = payload.next().await {
+                    let chunk = chunk?;
+                    if body.len() + chunk.len() > MAX_CBOR_BODY_SIZE {
+                        return Err(ErrorPayloadTooLarge(format!(
+                            "CBOR bodies are limited to {} bytes",
+                            MAX_CBOR_BODY_SIZE
+                        )));
+                    }
+                    body.extend_from_slice(&chunk);
+                }
+                let upsert_dto: UpsertDto =
+                    serde_cbor::from_slice(&body).map_err(ErrorBadRequest)?;
+                Ok(Self::Vectors(Some(upsert_dto.vectors)))
+            });
+        }
+
+        if NDJSON_CONTENT_TYPES.contains(&content_type.as_str()) {
+            let vectors = NdjsonVectors {
+                payload: payload.take(),
+                buffer: web::BytesMut::new(),
+                line: 0,
+                done: false,
+            };
+            return Box::pin(async move { Ok(Self::Ndjson(vectors)) });
+        }
+
+        let upsert_dto = web::Json::<UpsertDto>::from_request(req, payload);
+        Box::pin(async move { Ok(Self::Vectors(Some(upsert_dto.await?.into_inner().vectors))) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use serde_cbor::Value;
+
+    use super::*;
+
+    async fn read_all(mut body: UpsertBody) -> Vec<Vec<CreateVectorDto>> {
+        let mut batches = Vec::new();
+        while let Some(vectors) = body.next_batch().await.unwrap() {
+            batches.push(vectors);
+        }
+        batches
+    }
+
+    #[actix_web::test]
+    async fn test_cbor_body() {
+        let vector = Value::Map(
+            [
+                (Value::Text("id".into()), Value::Text("a".into())),
+                (
+                    Value::Text("dense_values".into()),
+                    Value::Bytes(
+                        [0.5f32, -1.0]
+                            .iter()
+                            .flat_map(|value| value.to_le_bytes())
+                            .collect(),
+                    ),
+                ),
+            ]
+            .into(),
+        );
+        let body = Value::Map([(Value::Text("vectors".into()), Value::Array(vec![vector]))].into());
+        let (req, mut payload) = TestRequest::post()
+            .insert_header(("content-type", CBOR_CONTENT_TYPE))
+            .set_payload(serde_cbor::to_vec(&body).unwrap())
+            .to_http_parts();
+
+        let body = UpsertBody::from_request(&req, &mut payload).await.unwrap();
+        let batches = read_all(body).await;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0][0].id.to_string(), "a");
+        assert_eq!(batches[0][0].dense_values, Some(vec![0.5, -1.0]));
+    }
+
+    #[actix_web::test]
+    async fn test_ndjson_body() {
+        let lines: Vec<_> = (0..NDJSON_BATCH_SIZE + 1)
+            .map(|id| format!("{{\"id\": \"{}\", \"dense_values\": [1.0]}}", id))
+            .collect();
+        let (req, mut payload) = TestRequest::post()
+            .insert_header(("content-type", "application/x-ndjson"))
+            .set_payload(lines.join("\n\n"))
+            .to_http_parts();
+
+        let body = UpsertBody::from_request(&req, &mut payload).await.unwrap();
+        let batches = read_all(body).await;
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].len(), NDJSON_BATCH_SIZE);
+        assert_eq!(batches[1][0].id.to_string(), NDJSON_BATCH_SIZE.to_string());
+
+        let (req, mut payload) = TestRequest::post()
+            .insert_header(("content-type", "application/x-ndjson"))
+            .set_payload("{\"id\": \"a\", \"dense_values\": [1.0]}\n{\"id\": 1}\n")
+            .to_http_parts();
+        let mut body = UpsertBody::from_request(&req, &mut payload).await.unwrap();
+        assert!(matches!(
+            body.next_batch().await,
+            Err(TransactionError::FailedToCreateVector(msg)) if msg.starts_with("line 2:")
+        ));
+    }
+}
@@ -1,6 +1,9 @@
 use actix_web::{web, HttpResponse, Result};
 
-use super::dtos::{CreateVectorDto, SimilarVector, VectorsQueryDto};
+use super::dtos::{
+    CreateVectorDto, ScrollVectorsDto, ScrollVectorsResponseDto, SimilarVector,
+    VectorFieldsQueryDto, VectorsQueryDto,
+};
 use super::{error::VectorsError, service};
 
 use crate::models::collection_cache::CollectionCacheExt;
@@ -12,11 +15,14 @@ use crate::{
 /// Get vectors for a document
 ///
 /// Returns all vectors associated with a specific document ID within a collection.
+/// `with_payload` and `fields` select the fields of the vectors to return.
 #[utoipa::path(
     get,
     path = "/collections/{collection_id}/vectors",
     params(
         ("collection_id" = String, Path, description = "Collection identifier"),
+        ("document_id" = String, Query, description = "Document identifier"),
+        VectorFieldsQueryDto,
     ),
     responses(
         (status = 200, description = "List of vectors", body = [CreateVectorDto]),
@@ -29,6 +35,7 @@ use crate::{
 pub(crate) async fn query_vectors(
     collection_id: web::Path<String>,
     web::Query(query): web::Query<VectorsQueryDto>,
+    web::Query(fields): web::Query<VectorFieldsQueryDto>,
     ctx: web::Data<AppContext>,
 ) -> Result<HttpResponse> {
     let collection_id = collection_id.into_inner();
@@ -37,20 +44,61 @@ pub(crate) async fn query_vectors(
         .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Cache error: {}", e)))?;
 
     let vectors =
-        service::query_vectors(ctx.into_inner(), &collection_id, query.document_id).await?;
+        service::query_vectors(ctx.into_inner(), &collection_id, query.document_id, &fields)
+            .await?;
 
     Ok(HttpResponse::Ok().json(vectors))
 }
 
+/// Scroll through all vectors of a collection
+///
+/// Returns a page of the vectors stored in a collection, optionally filtered
+/// by metadata, in a stable order. Pass the returned `next_cursor` and
+/// `version` with the next request to fetch the following page of the same
+/// version of the collection.
+#[utoipa::path(
+    post,
+    path = "/vectordb/collections/{collection_id}/vectors/scroll",
+    params(
+        ("collection_id" = String, Path, description = "Collection identifier"),
+    ),
+    request_body = ScrollVectorsDto,
+    responses(
+        (status = 200, description = "A page of vectors", body = ScrollVectorsResponseDto),
+        (status = 400, description = "Invalid cursor, limit or version, or collection not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "vectors"
+)]
+pub(crate) async fn scroll_vectors(
+    collection_id: web::Path<String>,
+    web::Json(scroll_dto): web::Json<ScrollVectorsDto>,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, VectorsError> {
+    let collection_id = collection_id.into_inner();
+
+    ctx.update_collection_for_query(&collection_id)
+        .map_err(|e| {
+            VectorsError::WaCustom(WaCustomError::DatabaseError(format!("Cache error: {}", e)))
+        })?;
+
+    let page = service::scroll_vectors(ctx.into_inner(), &collection_id, scroll_dto).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
 /// Get a specific vector by ID
 ///
-/// Returns a vector with the specified ID from a collection.
+/// Returns a vector with the specified ID from a collection, along with the
+/// version it was last written at, which upserts can require with
+/// `if_version`. `with_payload` and `fields` select the fields of the
+/// vector to return.
 #[utoipa::path(
     get,
     path = "/vectordb/collections/{collection_id}/vectors/{vector_id}",
     params(
         ("collection_id" = String, Path, description = "Collection identifier"),
-        ("vector_id" = String, Path, description = "Vector identifier")
+        ("vector_id" = String, Path, description = "Vector identifier"),
+        VectorFieldsQueryDto,
     ),
     responses(
         (status = 200, description = "The requested vector", body = CreateVectorDto),
@@ -62,6 +110,7 @@ pub(crate) async fn query_vectors(
 )]
 pub(crate) async fn get_vector_by_id(
     path: web::Path<(String, String)>,
+    web::Query(fields): web::Query<VectorFieldsQueryDto>,
     ctx: web::Data<AppContext>,
 ) -> Result<HttpResponse> {
     let (collection_id, vector_id) = path.into_inner();
@@ -69,9 +118,13 @@ pub(crate) async fn get_vector_by_id(
     ctx.update_collection_for_query(&collection_id)
         .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Cache error: {}", e)))?;
 
-    let vector =
-        service::get_vector_by_id(ctx.into_inner(), &collection_id, VectorId::from(vector_id))
-            .await?;
+    let vector = service::get_vector_by_id(
+        ctx.into_inner(),
+        &collection_id,
+        VectorId::from(vector_id),
+        &fields,
+    )
+    .await?;
     Ok(HttpResponse::Ok().json(vector))
 }
 
@@ -1,8 +1,10 @@
 use std::fmt;
 
+use chrono::{DateTime, Utc};
+
 use crate::{
-    metadata::MetadataFields,
-    models::{collection::RawVectorEmbedding, types::DocumentId},
+    metadata::{query_filtering::Filter, MetadataFields},
+    models::{collection::RawVectorEmbedding, f32_values::F32Values, types::DocumentId},
 };
 
 use serde::{
@@ -31,6 +33,21 @@ pub(crate) struct CreateVectorDto {
     #[schema(value_type = Object, nullable = true)]
     pub sparse_values: Option<Vec<SparsePair>>,
     pub text: Option<String>,
+    /// Time after which the vector is deleted, the collection's `ttl` after
+    /// the write by default
+    #[schema(value_type = Option<String>, example = "2023-01-01T12:00:00Z")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Only writes the vector if no vector with its id exists
+    #[serde(skip_serializing)]
+    pub if_absent: bool,
+    /// Only writes the vector if the vector with its id was last written at
+    /// this version
+    #[serde(skip_serializing)]
+    pub if_version: Option<u32>,
+    /// Version the vector was last written at, returned when fetching it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
 }
 
 impl From<CreateVectorDto> for RawVectorEmbedding {
@@ -42,6 +59,7 @@ impl From<CreateVectorDto> for RawVectorEmbedding {
             metadata: dto.metadata,
             sparse_values: dto.sparse_values,
             text: dto.text,
+            expires_at: dto.expires_at,
         }
     }
 }
@@ -55,6 +73,28 @@ impl From<RawVectorEmbedding> for CreateVectorDto {
             metadata: emb.metadata,
             sparse_values: emb.sparse_values,
             text: emb.text,
+            expires_at: emb.expires_at,
+            if_absent: false,
+            if_version: None,
+            version: None,
+        }
+    }
+}
+
+impl CreateVectorDto {
+    /// Clears the fields of the vector that aren't selected
+    pub(crate) fn retain_fields(&mut self, has_field: impl Fn(VectorField) -> bool) {
+        if !has_field(VectorField::DenseValues) {
+            self.dense_values = None;
+        }
+        if !has_field(VectorField::SparseValues) {
+            self.sparse_values = None;
+        }
+        if !has_field(VectorField::Text) {
+            self.text = None;
+        }
+        if !has_field(VectorField::Metadata) {
+            self.metadata = None;
         }
     }
 }
@@ -86,6 +126,9 @@ impl<'de> Deserialize<'de> for CreateVectorDto {
                 let mut metadata = None;
                 let mut sparse_values_raw: Option<(Vec<u32>, Vec<f32>)> = None;
                 let mut text = None;
+                let mut expires_at = None;
+                let mut if_absent = None;
+                let mut if_version = None;
 
                 while let Some(key) = map.next_key::<String>()? {
                     match key.as_str() {
@@ -105,7 +148,7 @@ impl<'de> Deserialize<'de> for CreateVectorDto {
                             if dense_values.is_some() {
                                 return Err(de::Error::duplicate_field("dense_values"));
                             }
-                            dense_values = Some(map.next_value()?);
+                            dense_values = Some(map.next_value::<F32Values>()?.0);
                         }
                         "metadata" => {
                             if metadata.is_some() {
@@ -114,7 +157,7 @@ impl<'de> Deserialize<'de> for CreateVectorDto {
                             metadata = map.next_value()?;
                         }
                         "sparse_values" => {
-                            let values: Vec<f32> = map.next_value()?;
+                            let values = map.next_value::<F32Values>()?.0;
                             if let Some((indices, _)) = sparse_values_raw.take() {
                                 sparse_values_raw = Some((indices, values));
                             } else {
@@ -135,6 +178,24 @@ impl<'de> Deserialize<'de> for CreateVectorDto {
                             }
                             text = Some(map.next_value()?);
                         }
+                        "expires_at" => {
+                            if expires_at.is_some() {
+                                return Err(de::Error::duplicate_field("expires_at"));
+                            }
+                            expires_at = map.next_value()?;
+                        }
+                        "if_absent" => {
+                            if if_absent.is_some() {
+                                return Err(de::Error::duplicate_field("if_absent"));
+                            }
+                            if_absent = Some(map.next_value()?);
+                        }
+                        "if_version" => {
+                            if if_version.is_some() {
+                                return Err(de::Error::duplicate_field("if_version"));
+                            }
+                            if_version = Some(map.next_value()?);
+                        }
                         _ => {
                             return Err(de::Error::unknown_field(
                                 &key,
@@ -146,6 +207,9 @@ impl<'de> Deserialize<'de> for CreateVectorDto {
                                     "sparse_values",
                                     "sparse_indices",
                                     "text",
+                                    "expires_at",
+                                    "if_absent",
+                                    "if_version",
                                 ],
                             ));
                         }
@@ -179,6 +243,10 @@ impl<'de> Deserialize<'de> for CreateVectorDto {
                     metadata,
                     sparse_values,
                     text,
+                    expires_at,
+                    if_absent: if_absent.unwrap_or(false),
+                    if_version,
+                    version: None,
                 })
             }
         }
@@ -193,3 +261,78 @@ pub(crate) struct SimilarVector {
     pub id: VectorId,
     pub score: f32,
 }
+
+/// Fields of a vector that a scroll or search may return besides its ids
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum VectorField {
+    DenseValues,
+    SparseValues,
+    Text,
+    Metadata,
+}
+
+/// Fields of the fetched vectors to return besides the ids, all unless
+/// either parameter is passed
+#[derive(Deserialize, Debug, Default, utoipa::IntoParams)]
+pub(crate) struct VectorFieldsQueryDto {
+    /// Returns the metadata of the vectors, same as `fields=metadata`
+    pub with_payload: Option<bool>,
+    /// Comma-separated fields of the vectors to return
+    #[serde(default, deserialize_with = "deserialize_vector_fields")]
+    #[param(value_type = Option<String>, example = "dense_values,metadata")]
+    pub fields: Option<Vec<VectorField>>,
+}
+
+impl VectorFieldsQueryDto {
+    pub fn has_field(&self, field: VectorField) -> bool {
+        if self.with_payload.is_none() && self.fields.is_none() {
+            return true;
+        }
+        self.fields
+            .as_ref()
+            .is_some_and(|fields| fields.contains(&field))
+            || (self.with_payload == Some(true) && field == VectorField::Metadata)
+    }
+}
+
+fn deserialize_vector_fields<'de, D>(deserializer: D) -> Result<Option<Vec<VectorField>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let fields = String::deserialize(deserializer)?;
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            VectorField::deserialize(de::IntoDeserializer::<D::Error>::into_deserializer(field))
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub(crate) struct ScrollVectorsDto {
+    /// The `next_cursor` of the previous page, omitted for the first page
+    pub cursor: Option<String>,
+    /// Maximum number of vectors to return, 100 by default
+    pub limit: Option<usize>,
+    /// Version to read the vectors at, the current version by default.
+    /// Pass the `version` of the first page with the later pages to keep
+    /// the scan consistent. Versions removed by a compaction are rejected.
+    pub version: Option<u32>,
+    #[schema(value_type = Option<String>)]
+    pub filter: Option<Filter>,
+    /// Fields to return besides the ids, all by default
+    pub fields: Option<Vec<VectorField>>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub(crate) struct ScrollVectorsResponseDto {
+    pub vectors: Vec<CreateVectorDto>,
+    /// Cursor of the next page, `null` once all vectors are returned
+    pub next_cursor: Option<String>,
+    /// Version the vectors were read at
+    pub version: u32,
+}
@@ -18,6 +18,7 @@ pub(crate) enum VectorsError {
     FailedToFindSimilarVectors(String),
     FailedToDeleteVector(String),
     NotImplemented,
+    InvalidRequest(String),
     DatabaseError(String),
     InternalServerError,
     WaCustom(WaCustomError),
@@ -36,6 +37,7 @@ impl Display for VectorsError {
             Self::NotImplemented => {
                 write!(f, "This is not supported yet!")
             }
+            Self::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
             Self::DatabaseError(msg) => write!(f, "Failed to fetch vector due to: {}", msg),
             Self::InternalServerError => {
                 write!(f, "Internal server error while trying to fetch vector!")
@@ -70,6 +72,7 @@ impl ResponseError for VectorsError {
             Self::IndexNotFound => StatusCode::NOT_FOUND,
             Self::FailedToCreateVector(_) => StatusCode::BAD_REQUEST,
             Self::NotImplemented => StatusCode::BAD_REQUEST,
+            Self::InvalidRequest(_) => StatusCode::BAD_REQUEST,
             Self::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             Self::FailedToUpdateVector(_) => StatusCode::BAD_REQUEST,
@@ -1,6 +1,8 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use crate::models::types::DocumentId;
+use crate::models::versioning::VersionNumber;
 use crate::models::{
     collection::Collection, collection_transaction::ExplicitTransaction, types::VectorId,
 };
@@ -8,17 +10,24 @@ use crate::models::{
 use crate::app_context::AppContext;
 
 use super::{
-    dtos::{CreateVectorDto, SimilarVector},
+    dtos::{
+        CreateVectorDto, ScrollVectorsDto, ScrollVectorsResponseDto, SimilarVector,
+        VectorFieldsQueryDto,
+    },
     error::VectorsError,
 };
 
+const DEFAULT_SCROLL_LIMIT: usize = 100;
+const MAX_SCROLL_LIMIT: usize = 1000;
+
 pub(crate) fn create_vector_in_transaction(
     collection: &Collection,
     transaction: &ExplicitTransaction,
     create_vector_dto: CreateVectorDto,
+    replaced: &HashSet<VectorId>,
 ) -> Result<(), VectorsError> {
     collection
-        .run_upload(vec![create_vector_dto.into()], transaction)
+        .run_upload(vec![create_vector_dto.into()], replaced, transaction)
         .map_err(VectorsError::WaCustom)
 }
 
@@ -26,6 +35,7 @@ pub(crate) async fn query_vectors(
     ctx: Arc<AppContext>,
     collection_id: &str,
     document_id: DocumentId,
+    fields: &VectorFieldsQueryDto,
 ) -> Result<Vec<CreateVectorDto>, VectorsError> {
     let collection = ctx
         .ain_env
@@ -40,19 +50,109 @@ pub(crate) async fn query_vectors(
     internal_ids
         .iter()
         .map(|internal_id| {
-            Ok(collection
-                .get_raw_emb_by_internal_id(&internal_id)
-                .ok_or(VectorsError::NotFound)?
-                .clone()
-                .into())
+            let mut vector = CreateVectorDto::from(
+                collection
+                    .get_raw_emb_by_internal_id(&internal_id)
+                    .ok_or(VectorsError::NotFound)?
+                    .clone(),
+            );
+            vector.retain_fields(|field| fields.has_field(field));
+            Ok(vector)
         })
         .collect()
 }
 
+pub(crate) async fn scroll_vectors(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    scroll_dto: ScrollVectorsDto,
+) -> Result<ScrollVectorsResponseDto, VectorsError> {
+    let collection = ctx
+        .ain_env
+        .collections_map
+        .get_collection(collection_id)
+        .ok_or(VectorsError::CollectionNotFound)?;
+
+    let limit = scroll_dto.limit.unwrap_or(DEFAULT_SCROLL_LIMIT);
+    if limit == 0 || limit > MAX_SCROLL_LIMIT {
+        return Err(VectorsError::InvalidRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_SCROLL_LIMIT
+        )));
+    }
+    let cursor = match &scroll_dto.cursor {
+        Some(cursor) => cursor
+            .parse()
+            .map_err(|_| VectorsError::InvalidRequest(format!("invalid cursor `{}`", cursor)))?,
+        None => 0,
+    };
+    let current_version = *collection.current_version.read();
+    let version = match scroll_dto.version {
+        Some(version) => {
+            let version = VersionNumber::from(version);
+            if *version > *current_version {
+                return Err(VectorsError::InvalidRequest(format!(
+                    "version {} is not committed",
+                    *version
+                )));
+            }
+            check_compaction_floor(&collection, version)?;
+            collection
+                .vcs
+                .get_version(version)
+                .map_err(|err| match err {
+                    lmdb::Error::NotFound => {
+                        VectorsError::InvalidRequest(format!("version {} does not exist", *version))
+                    }
+                    err => VectorsError::DatabaseError(err.to_string()),
+                })?;
+            version
+        }
+        None => current_version,
+    };
+
+    let (vectors, next_cursor) =
+        collection.scroll_vectors(cursor, limit, version, scroll_dto.filter.as_ref());
+    // a compaction that started meanwhile may have rebased the history the
+    // page was read from
+    check_compaction_floor(&collection, version)?;
+    let fields = scroll_dto.fields;
+    let has_field = |field| fields.as_ref().is_none_or(|fields| fields.contains(&field));
+    let vectors = vectors
+        .into_iter()
+        .map(|vector| {
+            let mut vector = CreateVectorDto::from(vector);
+            vector.retain_fields(has_field);
+            vector
+        })
+        .collect();
+
+    Ok(ScrollVectorsResponseDto {
+        vectors,
+        next_cursor: next_cursor.map(|cursor| cursor.to_string()),
+        version: *version,
+    })
+}
+
+fn check_compaction_floor(
+    collection: &Collection,
+    version: VersionNumber,
+) -> Result<(), VectorsError> {
+    let floor = collection.compaction_floor();
+    if *version < *floor {
+        return Err(VectorsError::InvalidRequest(format!(
+            "version {} was compacted, the oldest readable version is {}, restart the scroll without a version",
+            *version, *floor
+        )));
+    }
+    Ok(())
+}
+
 pub(crate) async fn get_vector_by_id(
     ctx: Arc<AppContext>,
     collection_id: &str,
     vector_id: VectorId,
+    fields: &VectorFieldsQueryDto,
 ) -> Result<CreateVectorDto, VectorsError> {
     let collection = ctx
         .ain_env
@@ -63,20 +163,34 @@ pub(crate) async fn get_vector_by_id(
         .external_to_internal_map
         .get_latest(&vector_id)
         .ok_or(VectorsError::NotFound)?;
+    let version = collection
+        .external_to_internal_map
+        .get_versioned(&vector_id)
+        .map(|item| *item.latest_item().version);
     let vector = collection
         .get_raw_emb_by_internal_id(internal_id)
         .ok_or(VectorsError::NotFound)?
         .clone();
-    Ok(vector.into())
+    let mut vector = CreateVectorDto {
+        version,
+        ..vector.into()
+    };
+    vector.retain_fields(|field| fields.has_field(field));
+    Ok(vector)
 }
 
 pub(crate) fn upsert_vectors_in_transaction(
     collection: &Collection,
     transaction: &ExplicitTransaction,
     vectors: Vec<CreateVectorDto>,
+    replaced: &HashSet<VectorId>,
 ) -> Result<(), VectorsError> {
     collection
-        .run_upload(vectors.into_iter().map(Into::into).collect(), transaction)
+        .run_upload(
+            vectors.into_iter().map(Into::into).collect(),
+            replaced,
+            transaction,
+        )
         .map_err(VectorsError::WaCustom)
 }
 
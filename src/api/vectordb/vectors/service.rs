@@ -6,7 +6,10 @@ use crate::{
 };
 
 use super::{
-    dtos::{CreateVectorDto, SimilarVector},
+    dtos::{
+        CreateVectorDto, ScrollVectorsDto, ScrollVectorsResponseDto, SimilarVector,
+        VectorFieldsQueryDto,
+    },
     error::VectorsError,
     repo,
 };
@@ -15,16 +18,26 @@ pub(crate) async fn query_vectors(
     ctx: Arc<AppContext>,
     collection_id: &str,
     document_id: DocumentId,
+    fields: &VectorFieldsQueryDto,
 ) -> Result<Vec<CreateVectorDto>, VectorsError> {
-    repo::query_vectors(ctx, collection_id, document_id).await
+    repo::query_vectors(ctx, collection_id, document_id, fields).await
+}
+
+pub(crate) async fn scroll_vectors(
+    ctx: Arc<AppContext>,
+    collection_id: &str,
+    scroll_dto: ScrollVectorsDto,
+) -> Result<ScrollVectorsResponseDto, VectorsError> {
+    repo::scroll_vectors(ctx, collection_id, scroll_dto).await
 }
 
 pub(crate) async fn get_vector_by_id(
     ctx: Arc<AppContext>,
     collection_id: &str,
     vector_id: VectorId,
+    fields: &VectorFieldsQueryDto,
 ) -> Result<CreateVectorDto, VectorsError> {
-    repo::get_vector_by_id(ctx, collection_id, vector_id).await
+    repo::get_vector_by_id(ctx, collection_id, vector_id, fields).await
 }
 
 pub(crate) async fn check_vector_existence(
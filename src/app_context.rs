@@ -4,8 +4,11 @@ use crate::args::CosdataArgs;
 use crate::config_loader::Config;
 use crate::models::collection_cache::CollectionCacheManager;
 use crate::models::common::WaCustomError;
+use crate::models::embeddings::EmbeddingProviders;
 use crate::models::paths::get_data_path;
+use crate::models::replication::ReplicationState;
 use crate::models::types::{get_app_env, AppEnv};
+use chrono::{DateTime, Utc};
 use rayon::ThreadPool;
 
 #[allow(unused)]
@@ -14,6 +17,9 @@ pub struct AppContext {
     pub threadpool: Arc<ThreadPool>,
     pub ain_env: Arc<AppEnv>,
     pub collection_cache_manager: Arc<CollectionCacheManager>,
+    pub embedding_providers: Arc<EmbeddingProviders>,
+    pub replication: ReplicationState,
+    pub started_at: DateTime<Utc>,
 }
 
 impl AppContext {
@@ -38,11 +44,19 @@ impl AppContext {
             ain_env.clone(),
         ));
 
+        let embedding_providers = Arc::new(
+            EmbeddingProviders::new(&config.embeddings.providers)
+                .map_err(|e| WaCustomError::ConfigError(e.to_string()))?,
+        );
+
         Ok(Self {
             config,
             ain_env,
             threadpool,
             collection_cache_manager,
+            embedding_providers,
+            replication: ReplicationState::default(),
+            started_at: Utc::now(),
         })
     }
 }
@@ -1,6 +1,8 @@
 use super::models::common::WaCustomError;
-use super::models::paths::get_config_path;
+use super::models::embeddings::EmbeddingProviderConfig;
+use super::models::paths::{get_config_path, get_data_path};
 use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
 use std::path::PathBuf;
 use std::{io, vec};
@@ -25,6 +27,24 @@ pub struct Config {
     #[serde(default)]
     pub cache: CacheConfig,
     pub epoch_length: u64,
+    #[serde(default)]
+    pub lmdb: LmdbConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub transactions: TransactionsConfig,
+    #[serde(default)]
+    pub expiry: ExpiryConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    #[serde(default)]
+    pub tracing: TracingConfig,
+    #[serde(default)]
+    pub imports: ImportsConfig,
+    #[serde(default)]
+    pub embeddings: EmbeddingsConfig,
 }
 
 #[derive(Deserialize, Clone)]
@@ -148,6 +168,10 @@ pub struct Server {
     pub port: Port,
     pub ssl: Ssl,
     pub mode: ServerMode,
+    // `/metrics` is served without authentication, for scrapers that can't
+    // send the admin's credentials
+    #[serde(default)]
+    pub public_metrics: bool,
 }
 
 impl Server {
@@ -223,3 +247,329 @@ impl Default for CacheConfig {
         }
     }
 }
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LmdbConfig {
+    // Initial size of the memory map in bytes, it's doubled whenever it fills up
+    #[serde(default = "default_lmdb_map_size")]
+    pub map_size: usize,
+    // Size in bytes the memory map is never grown beyond, unbounded if not set
+    #[serde(default)]
+    pub max_map_size: Option<usize>,
+    // Maximum number of named databases, every collection takes one
+    #[serde(default = "default_lmdb_max_dbs")]
+    pub max_dbs: u32,
+}
+
+fn default_lmdb_map_size() -> usize {
+    1_048_576_000 // 1000 MiB
+}
+
+fn default_lmdb_max_dbs() -> u32 {
+    128
+}
+
+impl Default for LmdbConfig {
+    fn default() -> Self {
+        Self {
+            map_size: default_lmdb_map_size(),
+            max_map_size: None,
+            max_dbs: default_lmdb_max_dbs(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct RetentionConfig {
+    // Number of most recent versions to keep
+    #[serde(default)]
+    pub keep_versions: Option<u32>,
+    // Keep the versions created within this many hours
+    #[serde(default)]
+    pub keep_hours: Option<u64>,
+    // Interval in seconds between runs of the background compactor, which
+    // is disabled if not set
+    #[serde(default)]
+    pub compaction_interval: Option<u64>,
+    // Fraction of the vectors in the indexes that have to be deleted or
+    // replaced for a compaction to rebuild the indexes without them
+    #[serde(default = "default_rebuild_threshold")]
+    pub rebuild_threshold: f32,
+}
+
+fn default_rebuild_threshold() -> f32 {
+    0.2
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            keep_versions: None,
+            keep_hours: None,
+            compaction_interval: None,
+            rebuild_threshold: default_rebuild_threshold(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct TransactionsConfig {
+    // Explicit transactions with no operations for this many seconds are
+    // aborted, they are kept open indefinitely if not set
+    #[serde(default)]
+    pub idle_timeout: Option<u64>,
+    // Seconds the responses of writes sent with an `Idempotency-Key` header
+    // are replayed for, keys are ignored if 0
+    #[serde(default = "default_idempotency_window")]
+    pub idempotency_window: u64,
+}
+
+fn default_idempotency_window() -> u64 {
+    86400 // 24 hours
+}
+
+impl Default for TransactionsConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: None,
+            idempotency_window: default_idempotency_window(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct ExpiryConfig {
+    // Interval in seconds between the deletions of expired vectors, which
+    // are only skipped by searches if 0
+    #[serde(default = "default_sweep_interval")]
+    pub sweep_interval: u64,
+}
+
+fn default_sweep_interval() -> u64 {
+    60
+}
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        Self {
+            sweep_interval: default_sweep_interval(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackendKind {
+    #[default]
+    Local,
+    S3,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct S3Config {
+    // e.g. `https://s3.us-east-1.amazonaws.com`, or `http://127.0.0.1:9000`
+    // for a local MinIO
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+    // Objects are stored under this prefix, which allows several nodes to
+    // share a bucket
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default = "default_s3_access_key")]
+    pub access_key: String,
+    #[serde(default = "default_s3_secret_key")]
+    pub secret_key: String,
+    // PEM file with the certificates trusted for https endpoints, the
+    // system's certificates are used if not set
+    #[serde(default)]
+    pub ca_file: Option<PathBuf>,
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_s3_access_key() -> String {
+    std::env::var("AWS_ACCESS_KEY_ID").unwrap_or_default()
+}
+
+fn default_s3_secret_key() -> String {
+    std::env::var("AWS_SECRET_ACCESS_KEY").unwrap_or_default()
+}
+
+#[derive(Deserialize, Clone)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackendKind,
+    // Required by the `s3` backend
+    #[serde(default)]
+    pub s3: Option<S3Config>,
+    // Size in bytes of the regions downloaded from object storage when
+    // reading parts of files that aren't cached locally
+    #[serde(default = "default_fetch_chunk_size")]
+    pub fetch_chunk_size: u64,
+    // Interval in seconds between uploads of the changed files to object
+    // storage
+    #[serde(default = "default_sync_interval")]
+    pub sync_interval: u64,
+}
+
+fn default_fetch_chunk_size() -> u64 {
+    4_194_304 // 4 MiB
+}
+
+fn default_sync_interval() -> u64 {
+    60
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackendKind::default(),
+            s3: None,
+            fetch_chunk_size: default_fetch_chunk_size(),
+            sync_interval: default_sync_interval(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplicationRole {
+    #[default]
+    Leader,
+    Follower,
+}
+
+impl ReplicationRole {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Leader => "leader",
+            Self::Follower => "follower",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ReplicationConfig {
+    // Followers are read replicas of the collections with a
+    // `replication_factor` above 1 on the leader
+    #[serde(default)]
+    pub role: ReplicationRole,
+    // Required on followers, e.g. `http://10.0.0.1:8443`
+    #[serde(default)]
+    pub leader_url: Option<String>,
+    // API key of the admin user on the leader, required on followers
+    #[serde(default = "default_replication_api_key")]
+    pub api_key: Option<String>,
+    // Name of the follower in the leader's replication status, defaults to
+    // the follower's listen address
+    #[serde(default)]
+    pub node_id: Option<String>,
+    // PEM file with the certificates trusted for an https leader, the
+    // system's certificates are used if not set
+    #[serde(default)]
+    pub ca_file: Option<PathBuf>,
+    // Interval in milliseconds between polls of the leader
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    // Maximum number of committed versions the leader keeps per collection
+    // for followers that haven't applied them yet, followers falling
+    // further behind restore a snapshot instead
+    #[serde(default = "default_max_log_versions")]
+    pub max_log_versions: u32,
+}
+
+fn default_replication_api_key() -> Option<String> {
+    std::env::var("COSDATA_REPLICATION_API_KEY").ok()
+}
+
+fn default_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_max_log_versions() -> u32 {
+    1000
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            role: ReplicationRole::default(),
+            leader_url: None,
+            api_key: default_replication_api_key(),
+            node_id: None,
+            ca_file: None,
+            poll_interval_ms: default_poll_interval_ms(),
+            max_log_versions: default_max_log_versions(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TracingConfig {
+    // Searches taking at least this many milliseconds are written to the
+    // slow query log, which is disabled if not set
+    #[serde(default)]
+    pub slow_query_threshold_ms: Option<u64>,
+    // File the slow queries are appended to as JSON lines, relative to the
+    // data directory. They are logged as warnings if not set.
+    #[serde(default)]
+    pub slow_query_log: Option<PathBuf>,
+    // OTLP/HTTP collector the spans are exported to, e.g.
+    // `http://127.0.0.1:4318`, spans aren't exported if not set
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    // PEM file with the certificates trusted for an https collector, the
+    // system's certificates are used if not set
+    #[serde(default)]
+    pub ca_file: Option<PathBuf>,
+    // Name of the service in the exported spans
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+fn default_service_name() -> String {
+    "cosdata".to_string()
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            slow_query_threshold_ms: None,
+            slow_query_log: None,
+            otlp_endpoint: None,
+            ca_file: None,
+            service_name: default_service_name(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct ImportsConfig {
+    // Directory the server-side files imported with `POST .../import` must
+    // be in, relative to the data directory. Defaults to `imports` in the
+    // data directory.
+    #[serde(default)]
+    pub root: Option<PathBuf>,
+}
+
+impl ImportsConfig {
+    pub fn root(&self) -> PathBuf {
+        match &self.root {
+            Some(root) => get_data_path().join(root),
+            None => get_data_path().join("imports"),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct EmbeddingsConfig {
+    // Providers the collections can compute their dense and sparse vectors
+    // with, by name. Their URLs and keys are only set here, collections
+    // refer to them by name.
+    #[serde(default)]
+    pub providers: HashMap<String, EmbeddingProviderConfig>,
+}
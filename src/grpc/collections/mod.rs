@@ -37,10 +37,12 @@ crate::cfg_grpc! {
                     .as_ref()
                     .map_or(0, |d| d.dimension as usize),
                 enabled: req.dense_vector.as_ref().is_some_and(|d| d.enabled),
+                embedding: None,
             };
 
             let sparse_vector = SparseVectorOptions {
                 enabled: req.sparse_vector.as_ref().is_some_and(|d| d.enabled),
+                embedding: None,
             };
 
             let tf_idf_options = TFIDFOptions {
@@ -50,6 +52,7 @@ crate::cfg_grpc! {
             let config = CollectionConfig {
                 max_vectors: req.config.as_ref().and_then(|c| c.max_vectors),
                 replication_factor: req.config.as_ref().and_then(|c| c.replication_factor),
+                ttl: None,
             };
 
             let env = &self.context.ain_env.persist;
@@ -1,12 +1,12 @@
 pub(crate) mod offset_counter;
 pub(crate) mod types;
 
-use super::{IndexOps, InternalSearchResult};
+use super::{IndexDir, IndexOps, InternalSearchResult, TopK};
 use crate::{
     config_loader::Config,
     metadata::{
         query_filtering::{filter_encoded_dimensions, Filter},
-        MetadataFields,
+        MetadataFields, SharedMetadataSchema,
     },
     models::{
         cache_loader::HNSWIndexCache,
@@ -14,6 +14,7 @@ use crate::{
         common::{TSHashTable, WaCustomError},
         meta_persist::store_values_range,
         prob_node::SharedLatestNode,
+        tracing,
         types::{DistanceMetric, FileOffset, HNSWLevel, InternalId, MetaDb, QuantizationMetric},
         versioning::VersionNumber,
     },
@@ -37,12 +38,23 @@ pub struct DenseInputEmbedding(
     pub bool,
 );
 
+#[derive(Clone)]
 pub struct DenseSearchInput(pub Vec<f32>, pub Option<Filter>);
 
 pub struct DenseSearchOptions {
     pub top_k: Option<usize>,
 }
 
+impl TopK for DenseSearchOptions {
+    fn top_k(&self) -> Option<usize> {
+        self.top_k
+    }
+
+    fn with_top_k(&self, top_k: usize) -> Self {
+        Self { top_k: Some(top_k) }
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct HNSWIndexData {
     pub hnsw_params: HNSWHyperParams,
@@ -54,6 +66,13 @@ pub struct HNSWIndexData {
     pub distance_metric: DistanceMetric,
     pub storage_type: StorageType,
     pub sample_threshold: usize,
+    // generation of the index directory, see `IndexDir`
+    #[serde(default)]
+    pub generation: u32,
+    // step between the internal ids of consecutive vectors, the no. of
+    // replicas per node if not set
+    #[serde(default)]
+    pub id_stride: Option<u32>,
 }
 
 pub struct HNSWIndex {
@@ -73,8 +92,17 @@ pub struct HNSWIndex {
     pub vectors_collected: AtomicUsize,
     pub sample_threshold: usize,
     pub max_replica_per_node: u8,
+    // the schema the metadata replicas are indexed with, the collection's
+    // one except while the index is rebuilt for a new one
+    pub metadata_schema: SharedMetadataSchema,
+    // step between the internal ids of consecutive vectors, which stays
+    // the same when the index is rebuilt with a different no. of replicas
+    // per node, see `base_node_id`
+    pub id_stride: u32,
     pub offset_counter: RwLock<HNSWIndexFileOffsetCounter>,
     pub versions_synchronization_map: TSHashTable<SharedLatestNode, ()>,
+    // dropped last, as it may remove the files of the index
+    pub dir: IndexDir,
 }
 
 #[derive(Default)]
@@ -114,9 +142,14 @@ impl HNSWIndex {
         values_range: (f32, f32),
         sample_threshold: usize,
         is_configured: bool,
-        max_replica_per_node: u8,
+        metadata_schema: SharedMetadataSchema,
+        id_stride: u32,
         offset_counter: HNSWIndexFileOffsetCounter,
+        dir: IndexDir,
     ) -> Self {
+        let max_replica_per_node = metadata_schema
+            .get()
+            .map_or(1, |schema| schema.max_num_replicas());
         Self {
             root_vec,
             pseudo_root_vec,
@@ -134,8 +167,11 @@ impl HNSWIndex {
             vectors_collected: AtomicUsize::new(0),
             sample_threshold,
             max_replica_per_node,
+            metadata_schema,
+            id_stride,
             offset_counter: RwLock::new(offset_counter),
             versions_synchronization_map: TSHashTable::new(16),
+            dir,
         }
     }
 
@@ -152,6 +188,18 @@ impl HNSWIndex {
         unsafe { &*self.root_vec }.file_offset
     }
 
+    /// Returns the id of the base node of the vector stored under
+    /// `internal_id`, its metadata replicas following it
+    pub fn base_node_id(&self, internal_id: InternalId) -> InternalId {
+        InternalId::from(*internal_id / self.id_stride * self.max_replica_per_node as u32)
+    }
+
+    /// Returns the internal id of the vector a node of the index belongs
+    /// to, the inverse of `base_node_id` for any of its replicas
+    pub fn vector_internal_id(&self, node_id: InternalId) -> InternalId {
+        InternalId::from(*node_id / self.max_replica_per_node as u32 * self.id_stride)
+    }
+
     /// Returns FileIndex (offset) corresponding to the pseudo root node.
     pub fn pseudo_root_vec_ptr_offset(&self) -> Option<FileOffset> {
         let node = unsafe { self.get_pseudo_root_vec().map(|node| &*node) };
@@ -181,12 +229,12 @@ impl IndexOps for HNSWIndex {
 
     fn index_embeddings(
         &self,
-        collection: &Collection,
+        _: &Collection,
         embeddings: Vec<Self::IndexingInput>,
         version: VersionNumber,
         config: &Config,
     ) -> Result<(), WaCustomError> {
-        index_embeddings(config, collection, self, version, embeddings)
+        index_embeddings(config, self, version, embeddings)
     }
 
     fn delete_embedding(
@@ -384,6 +432,8 @@ impl IndexOps for HNSWIndex {
             distance_metric: *self.distance_metric.read().unwrap(),
             storage_type: *self.storage_type.read().unwrap(),
             sample_threshold: self.sample_threshold,
+            generation: self.dir.generation(),
+            id_stride: Some(self.id_stride),
         }
     }
 
@@ -409,8 +459,8 @@ impl IndexOps for HNSWIndex {
         let hnsw_params_guard = self.hnsw_params.read().unwrap();
 
         let query_filter_dims = query.1.as_ref().map(|filter| {
-            let metadata_schema = collection.meta.metadata_schema.as_ref().unwrap();
-            filter_encoded_dimensions(metadata_schema, filter).unwrap()
+            let metadata_schema = self.metadata_schema.get().unwrap();
+            filter_encoded_dimensions(&metadata_schema, filter).unwrap()
         });
 
         let root_node = if query.1.is_some() {
@@ -429,6 +479,10 @@ impl IndexOps for HNSWIndex {
             &hnsw_params_guard,
         )?;
         drop(hnsw_params_guard);
+        let _span = tracing::span(
+            "finalize_results",
+            vec![("candidates", results.len().into())],
+        );
         finalize_ann_results(
             collection,
             self,
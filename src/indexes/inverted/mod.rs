@@ -1,5 +1,5 @@
 pub(crate) mod types;
-use super::{IndexOps, InternalSearchResult};
+use super::{IndexDir, IndexOps, InternalSearchResult, TopK};
 use crate::{
     config_loader::Config,
     models::{
@@ -13,17 +13,15 @@ use crate::{
         versioning::VersionNumber,
     },
 };
-use std::{
-    path::PathBuf,
-    sync::{
-        atomic::{AtomicBool, AtomicUsize, Ordering},
-        RwLock,
-    },
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    RwLock,
 };
 use types::{SamplingData, SparsePair};
 
 pub struct SparseInputEmbedding(pub InternalId, pub Vec<SparsePair>);
 
+#[derive(Clone)]
 pub struct SparseSearchInput(pub Vec<SparsePair>);
 
 pub struct SparseSearchOptions {
@@ -31,10 +29,26 @@ pub struct SparseSearchOptions {
     pub early_terminate_threshold: Option<f32>,
 }
 
+impl TopK for SparseSearchOptions {
+    fn top_k(&self) -> Option<usize> {
+        self.top_k
+    }
+
+    fn with_top_k(&self, top_k: usize) -> Self {
+        Self {
+            top_k: Some(top_k),
+            early_terminate_threshold: self.early_terminate_threshold,
+        }
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct InvertedIndexData {
     pub quantization_bits: u8,
     pub sample_threshold: usize,
+    // generation of the index directory, see `IndexDir`
+    #[serde(default)]
+    pub generation: u32,
 }
 
 pub struct InvertedIndex {
@@ -45,6 +59,8 @@ pub struct InvertedIndex {
     pub vectors: RwLock<Vec<SparseInputEmbedding>>,
     pub vectors_collected: AtomicUsize,
     pub sample_threshold: usize,
+    // dropped last, as it may remove the files of the index
+    pub dir: IndexDir,
 }
 
 unsafe impl Send for InvertedIndex {}
@@ -52,11 +68,11 @@ unsafe impl Sync for InvertedIndex {}
 
 impl InvertedIndex {
     pub fn new(
-        root_path: PathBuf,
+        dir: IndexDir,
         quantization_bits: u8,
         sample_threshold: usize,
     ) -> Result<Self, BufIoError> {
-        let root = InvertedIndexRoot::new(root_path, quantization_bits)?;
+        let root = InvertedIndexRoot::new(dir.path().to_path_buf(), quantization_bits)?;
 
         Ok(Self {
             root,
@@ -66,6 +82,7 @@ impl InvertedIndex {
             vectors: RwLock::new(Vec::new()),
             vectors_collected: AtomicUsize::new(0),
             sample_threshold,
+            dir,
         })
     }
 
@@ -272,6 +289,7 @@ impl IndexOps for InvertedIndex {
         Self::Data {
             quantization_bits: self.root.root.quantization_bits,
             sample_threshold: self.sample_threshold,
+            generation: self.dir.generation(),
         }
     }
 
@@ -1,6 +1,16 @@
+use chrono::Utc;
 use rayon::prelude::*;
 
-use std::{hash::Hasher, sync::RwLock};
+use std::{
+    fs,
+    hash::Hasher,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+};
 
 use lmdb::{Transaction, WriteFlags};
 use siphasher::sip::SipHasher24;
@@ -10,6 +20,7 @@ use crate::{
     models::{
         collection::{Collection, RawVectorEmbedding},
         common::WaCustomError,
+        lmdb_map::{txn_guard, with_rw_txn},
         types::{DocumentId, InternalId, MetaDb, VectorId},
         versioning::VersionNumber,
     },
@@ -29,10 +40,120 @@ pub type InternalSearchResult = (
 
 pub type SearchResult = (VectorId, Option<DocumentId>, f32, Option<String>);
 
+/// Directory of the dense index inside the collection directory
+pub const DENSE_INDEX_DIR: &str = "dense_hnsw";
+/// Directory of the sparse index inside the collection directory
+pub const SPARSE_INDEX_DIR: &str = "sparse_inverted_index";
+/// Directory of the TF-IDF index inside the collection directory
+pub const TF_IDF_INDEX_DIR: &str = "tf_idf_index";
+
+/// Directory an index stores its files in
+///
+/// Rebuilding an index writes it to the directory of the next generation,
+/// the directory of the replaced index is removed once it's dropped.
+pub struct IndexDir {
+    path: PathBuf,
+    name: &'static str,
+    generation: u32,
+    retired: AtomicBool,
+}
+
+impl IndexDir {
+    pub fn new(collection_path: &Path, name: &'static str, generation: u32) -> Self {
+        // the first generation keeps the name directories had before
+        // indexes could be rebuilt
+        let path = if generation == 0 {
+            collection_path.join(name)
+        } else {
+            collection_path.join(format!("{}.{}", name, generation))
+        };
+        Self {
+            path,
+            name,
+            generation,
+            retired: AtomicBool::new(false),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Marks the directory to be removed once the index is dropped
+    pub fn retire(&self) {
+        self.retired.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the directory of the next generation of the index
+    pub fn next(&self) -> Self {
+        let collection_path = self.path.parent().unwrap_or(Path::new(""));
+        Self::new(collection_path, self.name, self.generation + 1)
+    }
+
+    /// Removes the directories of the other generations of the index, left
+    /// behind by rebuilds that were interrupted or whose replaced index was
+    /// still in use at shutdown
+    pub fn remove_stale(&self) -> io::Result<()> {
+        let name = self.name;
+        let Some(collection_path) = self.path.parent() else {
+            return Ok(());
+        };
+        for entry in fs::read_dir(collection_path)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let Some(file_name) = file_name.to_str() else {
+                continue;
+            };
+            let generation = if file_name == name {
+                0
+            } else if let Some(generation) = file_name
+                .strip_prefix(name)
+                .and_then(|suffix| suffix.strip_prefix('.'))
+                .and_then(|suffix| suffix.parse().ok())
+            {
+                generation
+            } else {
+                continue;
+            };
+            if generation != self.generation {
+                fs::remove_dir_all(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for IndexDir {
+    fn drop(&mut self) {
+        if self.retired.load(Ordering::Relaxed) {
+            if let Err(err) = fs::remove_dir_all(&self.path) {
+                log::warn!(
+                    "Failed to remove the index directory {:?}: {}",
+                    self.path,
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Options of a search that limit the number of its results
+pub trait TopK {
+    /// Maximum number of results, `None` for all of them
+    fn top_k(&self) -> Option<usize>;
+
+    /// Returns the same options, limited to `top_k` results
+    fn with_top_k(&self, top_k: usize) -> Self;
+}
+
 pub trait IndexOps: Send + Sync {
     type IndexingInput: Send + Sync;
-    type SearchInput: Send + Sync;
-    type SearchOptions: Send + Sync;
+    type SearchInput: Clone + Send + Sync;
+    type SearchOptions: TopK + Send + Sync;
     type Data: serde::Serialize + serde::de::DeserializeOwned;
 
     fn validate_embedding(&self, embedding: Self::IndexingInput) -> Result<(), WaCustomError>;
@@ -156,20 +277,24 @@ pub trait IndexOps: Send + Sync {
 
     fn get_data(&self) -> Self::Data;
 
+    // the key and value the index data is stored under
+    fn serialize_data(&self, collection_name: &str) -> Result<([u8; 8], Vec<u8>), WaCustomError> {
+        let data = self.get_data();
+        let key = Self::get_key_for_name(collection_name).to_le_bytes();
+        let val = serde_cbor::to_vec(&data)
+            .map_err(|e| WaCustomError::SerializationError(e.to_string()))?;
+        Ok((key, val))
+    }
+
     fn persist(
         &self,
         collection_name: &str,
         env: &lmdb::Environment,
         db: lmdb::Database,
     ) -> Result<(), WaCustomError> {
-        let data = self.get_data();
-        let key = Self::get_key_for_name(collection_name).to_le_bytes();
-        let val = serde_cbor::to_vec(&data)
-            .map_err(|e| WaCustomError::SerializationError(e.to_string()))?;
+        let (key, val) = self.serialize_data(collection_name)?;
 
-        let mut txn = env.begin_rw_txn()?;
-        txn.put(db, &key, &val, WriteFlags::empty())?;
-        txn.commit()?;
+        with_rw_txn(env, |txn| txn.put(db, &key, &val, WriteFlags::empty()))?;
         Ok(())
     }
 
@@ -178,6 +303,7 @@ pub trait IndexOps: Send + Sync {
         db: lmdb::Database,
         collection_name: &str,
     ) -> Result<Option<Self::Data>, WaCustomError> {
+        let _guard = txn_guard();
         let txn = env.begin_ro_txn()?;
         let key = Self::get_key_for_name(collection_name).to_le_bytes();
         let data_bytes = match txn.get(db, &key) {
@@ -197,9 +323,7 @@ pub trait IndexOps: Send + Sync {
         collection_name: &str,
     ) -> Result<(), WaCustomError> {
         let key = Self::get_key_for_name(collection_name).to_le_bytes();
-        let mut txn = env.begin_rw_txn()?;
-        txn.del(db, &key, None)?;
-        txn.commit()?;
+        with_rw_txn(env, |txn| txn.del(db, &key, None))?;
         Ok(())
     }
 
@@ -218,19 +342,24 @@ pub trait IndexOps: Send + Sync {
         results: Vec<InternalSearchResult>,
         return_raw_text: bool,
     ) -> Result<Vec<SearchResult>, WaCustomError> {
+        let now = Utc::now();
         results
             .into_iter()
-            .map(|(internal_id, id, document_id, score, text)| {
-                Ok(if let Some(id) = id {
-                    (id, document_id, score, text)
+            .filter_map(|(internal_id, id, document_id, score, text)| {
+                let raw_emb = collection.get_raw_emb_by_internal_id(&internal_id);
+                // expired vectors are skipped until the sweeper deletes them
+                if raw_emb.is_some_and(|raw_emb| raw_emb.is_expired(now)) {
+                    return None;
+                }
+                Some(if let Some(id) = id {
+                    Ok((id, document_id, score, text))
                 } else {
-                    let raw_emb = collection
-                        .get_raw_emb_by_internal_id(&internal_id)
-                        .ok_or_else(|| {
-                            WaCustomError::NotFound("raw embedding not found".to_string())
-                        })?
-                        .clone();
-                    (
+                    let Some(raw_emb) = raw_emb else {
+                        return Some(Err(WaCustomError::NotFound(
+                            "raw embedding not found".to_string(),
+                        )));
+                    };
+                    Ok((
                         raw_emb.id.clone(),
                         raw_emb.document_id.clone(),
                         score,
@@ -239,7 +368,7 @@ pub trait IndexOps: Send + Sync {
                         } else {
                             None
                         },
-                    )
+                    ))
                 })
             })
             .collect()
@@ -253,8 +382,31 @@ pub trait IndexOps: Send + Sync {
         config: &Config,
         return_raw_text: bool,
     ) -> Result<Vec<SearchResult>, WaCustomError> {
-        let results = self.search_internal(collection, query, options, config, return_raw_text)?;
-        self.remap_search_results(collection, results, return_raw_text)
+        let Some(top_k) = options.top_k() else {
+            let results =
+                self.search_internal(collection, query, options, config, return_raw_text)?;
+            return self.remap_search_results(collection, results, return_raw_text);
+        };
+        // expired vectors are dropped from the results, so the search is
+        // repeated for more of them until `top_k` live ones are found or the
+        // index has no more
+        let mut limit = top_k;
+        loop {
+            let results = self.search_internal(
+                collection,
+                query.clone(),
+                &options.with_top_k(limit),
+                config,
+                return_raw_text,
+            )?;
+            let is_exhausted = results.len() < limit;
+            let mut results = self.remap_search_results(collection, results, return_raw_text)?;
+            if results.len() >= top_k || is_exhausted {
+                results.truncate(top_k);
+                return Ok(results);
+            }
+            limit = limit.saturating_mul(2);
+        }
     }
 
     fn batch_search(
@@ -1,4 +1,4 @@
-use super::{IndexOps, InternalSearchResult};
+use super::{IndexDir, IndexOps, InternalSearchResult, TopK};
 use crate::{
     config_loader::Config,
     models::{
@@ -6,17 +6,16 @@ use crate::{
         collection::{Collection, RawVectorEmbedding},
         common::WaCustomError,
         meta_persist::store_average_document_length,
-        sparse_ann_query::SparseAnnQueryBasic,
+        sparse_ann_query::{get_idf, SparseAnnQueryBasic},
         tf_idf_index::TFIDFIndexRoot,
         types::{InternalId, MetaDb, SparseVector},
         versioning::VersionNumber,
     },
 };
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use snowball_stemmer::Stemmer;
 use std::{
     hash::Hasher,
-    path::PathBuf,
     sync::{
         atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
         RwLock,
@@ -32,17 +31,66 @@ pub struct SamplingData {
 
 pub struct TFIDFInputEmbedding(pub InternalId, pub String);
 
+#[derive(Clone)]
 pub struct TFIDFSearchInput(pub String);
 
 pub struct TFIDFSearchOptions {
     pub top_k: Option<usize>,
 }
 
+impl TopK for TFIDFSearchOptions {
+    fn top_k(&self) -> Option<usize> {
+        self.top_k
+    }
+
+    fn with_top_k(&self, top_k: usize) -> Self {
+        Self { top_k: Some(top_k) }
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct TFIDFIndexData {
     pub sample_threshold: usize,
     pub k1: f32,
     pub b: f32,
+    // generation of the index directory, see `IndexDir`
+    #[serde(default)]
+    pub generation: u32,
+}
+
+/// Breakdown of a single query term's share of a document's BM25 score
+pub struct BM25TermExplanation {
+    pub term: String,
+    pub document_frequency: u32,
+    pub idf: f32,
+    // raw occurrences of the term in the document, only known when the
+    // collection stores raw text
+    pub term_frequency: Option<u32>,
+    // `1 - b + b * (document_length / average_document_length)`, only
+    // known when the collection stores raw text
+    pub length_normalization: Option<f32>,
+    // the saturated, length normalized term frequency stored in the index
+    pub bm25_term_frequency: f32,
+    pub contribution: f32,
+}
+
+pub struct BM25Explanation {
+    pub document_length: Option<u32>,
+    pub terms: Vec<BM25TermExplanation>,
+}
+
+pub struct TermStats {
+    pub term: String,
+    pub document_frequency: u32,
+    pub idf: f32,
+}
+
+pub struct TFIDFCorpusStats {
+    pub total_documents_count: u32,
+    pub average_document_length: f32,
+    pub k1: f32,
+    pub b: f32,
+    pub terms: Vec<TermStats>,
 }
 
 pub struct TFIDFIndex {
@@ -55,6 +103,8 @@ pub struct TFIDFIndex {
     pub sample_threshold: usize,
     pub k1: f32,
     pub b: f32,
+    // dropped last, as it may remove the files of the index
+    pub dir: IndexDir,
 }
 
 unsafe impl Send for TFIDFIndex {}
@@ -62,12 +112,12 @@ unsafe impl Sync for TFIDFIndex {}
 
 impl TFIDFIndex {
     pub fn new(
-        root_path: PathBuf,
+        dir: IndexDir,
         sample_threshold: usize,
         k1: f32,
         b: f32,
     ) -> Result<Self, BufIoError> {
-        let root = TFIDFIndexRoot::new(root_path)?;
+        let root = TFIDFIndexRoot::new(dir.path().to_path_buf())?;
 
         Ok(Self {
             root,
@@ -79,6 +129,7 @@ impl TFIDFIndex {
             sample_threshold,
             k1,
             b,
+            dir,
         })
     }
 
@@ -135,6 +186,108 @@ impl TFIDFIndex {
 
         Ok(())
     }
+
+    /// Explains how the BM25 scores of `documents` for `query` are made
+    /// up, one explanation per document in the same order.
+    ///
+    /// Each document is passed along with its raw text, if the collection
+    /// stores it, which is needed to report the raw term frequency and
+    /// document length normalization.
+    pub fn explain(
+        &self,
+        query: &str,
+        documents: &[(InternalId, Option<&str>)],
+    ) -> Result<Vec<BM25Explanation>, BufIoError> {
+        let average_document_length = *self.average_document_length.read().unwrap();
+        let documents_count = self.root.total_documents_count.load(Ordering::Relaxed);
+        let documents_terms: Vec<_> = documents
+            .iter()
+            .map(|(_, text)| text.map(|text| (count_tokens(text, 40), term_frequencies(text, 40))))
+            .collect();
+        let mut explanations: Vec<_> = documents_terms
+            .iter()
+            .map(|terms| BM25Explanation {
+                document_length: terms.as_ref().map(|(length, _)| *length),
+                terms: Vec::new(),
+            })
+            .collect();
+
+        for (term_hash, (term, _)) in term_frequencies(query, 40) {
+            let Some(term_info) = self.root.get_term(term_hash)? else {
+                continue;
+            };
+            let postings = term_info.documents.read().unwrap();
+            // same as the document frequency used while scoring
+            let document_frequency = postings.len() as u32;
+            let idf = get_idf(documents_count, document_frequency);
+
+            for (i, (document_id, _)) in documents.iter().enumerate() {
+                let Some(bm25_term_frequency) = postings.get(**document_id) else {
+                    continue;
+                };
+                let (term_frequency, length_normalization) = match &documents_terms[i] {
+                    Some((document_length, frequencies)) => (
+                        frequencies.get(&term_hash).map(|(_, count)| *count),
+                        Some(
+                            1.0 - self.b
+                                + self.b * (*document_length as f32 / average_document_length),
+                        ),
+                    ),
+                    None => (None, None),
+                };
+                explanations[i].terms.push(BM25TermExplanation {
+                    term: term.clone(),
+                    document_frequency,
+                    idf,
+                    term_frequency,
+                    length_normalization,
+                    bm25_term_frequency,
+                    contribution: bm25_term_frequency * idf,
+                });
+            }
+        }
+
+        for explanation in &mut explanations {
+            explanation
+                .terms
+                .sort_unstable_by(|a, b| b.contribution.total_cmp(&a.contribution));
+        }
+
+        Ok(explanations)
+    }
+
+    /// Returns the corpus level statistics used for BM25 scoring, along
+    /// with the document frequency of each of the given terms
+    pub fn corpus_stats(&self, terms: &[String]) -> Result<TFIDFCorpusStats, BufIoError> {
+        let total_documents_count = self.root.total_documents_count.load(Ordering::Relaxed);
+        let mut seen = FxHashSet::default();
+        let mut terms_stats = Vec::new();
+
+        for input in terms {
+            for (term_hash, (term, _)) in term_frequencies(input, 40) {
+                if !seen.insert(term_hash) {
+                    continue;
+                }
+                let document_frequency = match self.root.get_term(term_hash)? {
+                    Some(term_info) => term_info.documents.read().unwrap().len() as u32,
+                    None => 0,
+                };
+                terms_stats.push(TermStats {
+                    term,
+                    document_frequency,
+                    idf: get_idf(total_documents_count, document_frequency),
+                });
+            }
+        }
+
+        Ok(TFIDFCorpusStats {
+            total_documents_count,
+            average_document_length: *self.average_document_length.read().unwrap(),
+            k1: self.k1,
+            b: self.b,
+            terms: terms_stats,
+        })
+    }
 }
 
 impl IndexOps for TFIDFIndex {
@@ -237,6 +390,7 @@ impl IndexOps for TFIDFIndex {
             sample_threshold: self.sample_threshold,
             k1: self.k1,
             b: self.b,
+            generation: self.dir.generation(),
         }
     }
 
@@ -314,11 +468,26 @@ pub fn process_text(
     k1: f32,
     b: f32,
 ) -> Vec<(u32, f32)> {
+    let document_length = count_tokens(input, max_token_len);
+
+    term_frequencies(input, max_token_len)
+        .into_iter()
+        .map(|(hash, (_, count))| {
+            (
+                hash,
+                compute_bm25_term_frequency(count, document_length, average_document_length, k1, b),
+            )
+        })
+        .collect()
+}
+
+/// Returns every distinct term of `input`, keyed by its hash, along
+/// with the stemmed term and the number of times it occurs
+pub fn term_frequencies(input: &str, max_token_len: usize) -> FxHashMap<u32, (String, u32)> {
     // Create an English stemmer.
     let stemmer = Stemmer::create();
     // Create a fast hash map for counting; FxHashMap is chosen for performance.
-    let mut freq: FxHashMap<u32, u32> = FxHashMap::default();
-    let document_length = count_tokens(input, max_token_len);
+    let mut freq: FxHashMap<u32, (String, u32)> = FxHashMap::default();
 
     // Split input by whitespace to minimize unnecessary cloning.
     for token in tokenize(input) {
@@ -345,18 +514,10 @@ pub fn process_text(
         let token_hash = hasher.finish() as u32;
 
         // Increment the count for this hash.
-        *freq.entry(token_hash).or_insert(0) += 1;
+        freq.entry(token_hash).or_insert((stemmed, 0)).1 += 1;
     }
 
-    // Convert the hash map into a Vec of (hash, count) pairs.
-    freq.into_iter()
-        .map(|(hash, count)| {
-            (
-                hash,
-                compute_bm25_term_frequency(count, document_length, average_document_length, k1, b),
-            )
-        })
-        .collect()
+    freq
 }
 
 fn compute_bm25_term_frequency(
@@ -387,3 +548,171 @@ pub fn count_tokens(input: &str, max_token_len: usize) -> u32 {
 
     count
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const DOCUMENTS: [&str; 4] = ["quick brown fox", "red dog", "fox dog fox", "cat"];
+
+    // Indexes `DOCUMENTS` at version 0 and deletes the first one at version 1
+    fn setup_index(collection_path: &std::path::Path) -> TFIDFIndex {
+        let dir = IndexDir::new(collection_path, "tf_idf_index", 0);
+        fs::create_dir_all(dir.path()).unwrap();
+        let index = TFIDFIndex::new(dir, 0, 1.2, 0.75).unwrap();
+        let total_length: u32 = DOCUMENTS
+            .iter()
+            .map(|document| count_tokens(document, 40))
+            .sum();
+        *index.average_document_length.write().unwrap() =
+            total_length as f32 / DOCUMENTS.len() as f32;
+        for (id, document) in DOCUMENTS.iter().enumerate() {
+            index
+                .insert(
+                    VersionNumber::from(0),
+                    InternalId::from(id as u32),
+                    document.to_string(),
+                )
+                .unwrap();
+        }
+        index
+            .mark_embedding_as_deleted(VersionNumber::from(1), InternalId::from(0), DOCUMENTS[0])
+            .unwrap();
+        index
+    }
+
+    fn term_hash(term: &str) -> u32 {
+        *term_frequencies(term, 40).keys().next().unwrap()
+    }
+
+    #[test]
+    fn test_get_term() {
+        let collection_path = tempdir().unwrap();
+        let index = setup_index(collection_path.path());
+
+        let fox = index.root.get_term(term_hash("fox")).unwrap().unwrap();
+        let documents: Vec<_> = fox
+            .documents
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(documents, vec![2]);
+        assert!(fox.documents.read().unwrap().get(0).is_none());
+        assert!(fox.documents.read().unwrap().get(2).is_some());
+        assert!(index.root.get_term(term_hash("wolf")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_corpus_stats() {
+        let collection_path = tempdir().unwrap();
+        let index = setup_index(collection_path.path());
+
+        let stats = index
+            .corpus_stats(&["fox dog".to_string(), "the fox wolf".to_string()])
+            .unwrap();
+        assert_eq!(stats.total_documents_count, 3);
+        assert_eq!(stats.average_document_length, 9.0 / 4.0);
+        assert_eq!((stats.k1, stats.b), (1.2, 0.75));
+        let mut terms: Vec<_> = stats
+            .terms
+            .iter()
+            .map(|term| (term.term.as_str(), term.document_frequency, term.idf))
+            .collect();
+        terms.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        let dog = index.root.get_term(term_hash("dog")).unwrap().unwrap();
+        let dog_frequency = dog.documents.read().unwrap().len() as u32;
+        let fox = index.root.get_term(term_hash("fox")).unwrap().unwrap();
+        let fox_frequency = fox.documents.read().unwrap().len() as u32;
+        // stopwords aren't terms and repeated terms are reported once
+        assert_eq!(
+            terms,
+            vec![
+                ("dog", dog_frequency, get_idf(3, dog_frequency)),
+                ("fox", fox_frequency, get_idf(3, fox_frequency)),
+                ("wolf", 0, get_idf(3, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explain_matches_search_scores() {
+        let collection_path = tempdir().unwrap();
+        let index = setup_index(collection_path.path());
+        let query = "fox dog";
+
+        let entries = process_text(
+            query,
+            40,
+            *index.average_document_length.read().unwrap(),
+            index.k1,
+            index.b,
+        );
+        let results = SparseAnnQueryBasic::new(SparseVector {
+            vector_id: u32::MAX,
+            entries,
+        })
+        .search_bm25(&index.root, None)
+        .unwrap();
+        let mut scores: Vec<_> = results
+            .iter()
+            .map(|result| (result.document_id, result.score))
+            .collect();
+        scores.sort_unstable_by_key(|(id, _)| *id);
+        assert_eq!(
+            scores.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let documents: Vec<_> = (0..DOCUMENTS.len())
+            .map(|id| {
+                // the second document is explained without its text
+                let text = (id != 1).then_some(DOCUMENTS[id]);
+                (InternalId::from(id as u32), text)
+            })
+            .collect();
+        let explanations = index.explain(query, &documents).unwrap();
+        assert_eq!(explanations.len(), DOCUMENTS.len());
+        // deleted and non matching documents have nothing to explain
+        assert!(explanations[0].terms.is_empty());
+        assert!(explanations[3].terms.is_empty());
+
+        for (id, score) in scores {
+            let explanation = &explanations[id as usize];
+            let total: f32 = explanation.terms.iter().map(|term| term.contribution).sum();
+            assert!((total - score).abs() < 1e-5, "{} != {}", total, score);
+            for term in &explanation.terms {
+                assert_eq!(term.contribution, term.bm25_term_frequency * term.idf);
+            }
+            assert!(explanation
+                .terms
+                .windows(2)
+                .all(|terms| terms[0].contribution >= terms[1].contribution));
+        }
+
+        assert_eq!(explanations[1].document_length, None);
+        assert!(explanations[1]
+            .terms
+            .iter()
+            .all(|term| term.term_frequency.is_none() && term.length_normalization.is_none()));
+        let explanation = &explanations[2];
+        assert_eq!(explanation.document_length, Some(3));
+        let fox = explanation
+            .terms
+            .iter()
+            .find(|term| term.term == "fox")
+            .unwrap();
+        assert_eq!(fox.term_frequency, Some(2));
+        let length_normalization = 1.0 - index.b + index.b * (3.0 / (9.0 / 4.0));
+        assert_eq!(fox.length_normalization, Some(length_normalization));
+        assert_eq!(
+            fox.bm25_term_frequency,
+            compute_bm25_term_frequency(2, 3, 9.0 / 4.0, index.k1, index.b)
+        );
+    }
+}
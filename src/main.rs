@@ -31,9 +31,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = CosdataArgs::parse();
     env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
     let config = config_loader::load_config()?;
+    // must be set up before the LMDB environment is opened
+    models::storage_backend::init(&config.storage)?;
+    models::tracing::init(&config.tracing)?;
     // Create context
     let context = Data::new(AppContext::new(config, args)?);
 
+    models::recovery::spawn_recovery(context.clone().into_inner());
+    models::compaction::spawn_compactor(context.clone().into_inner());
+    models::transaction_timeout::spawn_transaction_timeout(
+        context.ain_env.clone(),
+        context.config.transactions,
+    );
+    models::idempotency::spawn_idempotency_pruner(
+        context.ain_env.clone(),
+        context.config.transactions,
+    );
+    models::expiry::spawn_expiry_sweeper(context.ain_env.clone(), context.config.clone());
+    models::storage_sync::spawn_storage_sync(context.ain_env.clone(), context.config.clone());
+    models::replication::spawn_follower(context.clone().into_inner())?;
+
     // Start gRPC server
     #[cfg(feature = "grpc-server")]
     let grpc_context = context.clone().into_inner();
@@ -41,6 +58,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(feature = "grpc-server")]
     actix_web::rt::spawn(async move {
         const DEFAULT_GRPC_PORT: u16 = 50051;
+        // the gRPC services have no readiness check, so they are only
+        // started once the collections are recovered
+        if !matches!(
+            actix_web::web::block(models::recovery::wait_until_ready).await,
+            Ok(true)
+        ) {
+            log::error!("gRPC server not started, as the collections failed to recover");
+            return;
+        }
         if let Err(e) = grpc::server::start_grpc_server(grpc_context, DEFAULT_GRPC_PORT).await {
             log::error!("gRPC server error: {}", e);
         }
@@ -11,7 +11,7 @@ pub mod query_filtering;
 pub mod schema;
 
 pub use query_filtering::{Filter, Operator, Predicate, QueryFilterDimensions};
-pub use schema::MetadataSchema;
+pub use schema::{MetadataSchema, SharedMetadataSchema};
 
 use crate::models::common::generate_level_probs;
 use crate::models::types::InternalId;
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
-use super::{decimal_to_binary_vec, schema::MetadataSchema, Error, FieldName, FieldValue};
+use super::{
+    decimal_to_binary_vec, schema::MetadataSchema, Error, FieldName, FieldValue, MetadataFields,
+};
 use serde::Deserialize;
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
@@ -24,6 +26,31 @@ pub enum Filter {
     Or(Vec<Predicate>),
 }
 
+impl Predicate {
+    fn matches(&self, fields: Option<&MetadataFields>) -> bool {
+        let is_equal = fields
+            .and_then(|fields| fields.get(&self.field_name))
+            .is_some_and(|value| *value == self.field_value);
+        match self.operator {
+            Operator::Equal => is_equal,
+            Operator::NotEqual => !is_equal,
+        }
+    }
+}
+
+impl Filter {
+    /// Returns whether the metadata `fields` of a vector match the
+    /// filter, evaluated on the fields themselves rather than the
+    /// encoded dimensions
+    pub fn matches(&self, fields: Option<&MetadataFields>) -> bool {
+        match self {
+            Filter::Is(pred) => pred.matches(fields),
+            Filter::And(preds) => preds.iter().all(|pred| pred.matches(fields)),
+            Filter::Or(preds) => preds.iter().any(|pred| pred.matches(fields)),
+        }
+    }
+}
+
 pub type QueryFilterDimensions = Vec<i8>;
 
 fn query_filter_encoding(value_id: u16, size: usize, operator: &Operator) -> QueryFilterDimensions {
@@ -210,4 +237,32 @@ mod tests {
             qfed
         );
     }
+
+    #[test]
+    fn test_filter_matches() {
+        let pred = |name: &str, value: FieldValue, operator| Predicate {
+            field_name: name.to_owned(),
+            field_value: value,
+            operator,
+        };
+        let fields = MetadataFields::from([
+            ("age".to_owned(), FieldValue::Int(5)),
+            ("color".to_owned(), FieldValue::String("red".to_owned())),
+        ]);
+
+        let is_red = pred(
+            "color",
+            FieldValue::String("red".to_owned()),
+            Operator::Equal,
+        );
+        assert!(Filter::Is(is_red.clone()).matches(Some(&fields)));
+        assert!(!Filter::Is(is_red.clone()).matches(None));
+
+        let not_5 = pred("age", FieldValue::Int(5), Operator::NotEqual);
+        assert!(!Filter::Is(not_5.clone()).matches(Some(&fields)));
+        assert!(Filter::Is(not_5.clone()).matches(None));
+
+        assert!(!Filter::And(vec![is_red.clone(), not_5.clone()]).matches(Some(&fields)));
+        assert!(Filter::Or(vec![is_red, not_5]).matches(Some(&fields)));
+    }
 }
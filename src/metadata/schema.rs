@@ -1,4 +1,5 @@
-use serde::{Deserialize, Serialize};
+use parking_lot::RwLock;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::metadata::gen_combinations;
 
@@ -8,6 +9,7 @@ use super::{
 use std::{
     cmp::Reverse,
     collections::{HashMap, HashSet},
+    sync::Arc,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,6 +126,40 @@ impl MetadataField {
     pub fn max_cardinality(&self) -> u8 {
         2u8.pow(self.num_dims as u32) - 1
     }
+
+    /// Adds `values` to the field, ignoring the ones it already has
+    ///
+    /// The new values get the identifiers following the existing ones,
+    /// so that the dimensions of the existing values don't change. The
+    /// number of dimensions grows if the values don't fit in the
+    /// current ones.
+    pub fn add_values(&mut self, values: HashSet<FieldValue>) -> Result<(), Error> {
+        let values = values
+            .into_iter()
+            .filter(|value| !self.value_index.contains_key(value))
+            .collect::<HashSet<FieldValue>>();
+        let unique_types = self
+            .value_index
+            .keys()
+            .chain(&values)
+            .map(|value| value.type_as_str())
+            .collect::<HashSet<&str>>();
+        if unique_types.len() > 1 {
+            return Err(Error::InvalidFieldValues(
+                "Field values must be homogeneous in type".to_owned(),
+            ));
+        }
+        let last_id = self.value_index.values().copied().max().unwrap_or(0);
+        let cardinality = last_id as usize + values.len();
+        let num_dims = nearest_power_of_two((cardinality + 1) as u16).ok_or(
+            Error::InvalidFieldCardinality(format!("Field = {}", self.name)),
+        )?;
+        for (value, id) in set_to_value_index(values) {
+            self.value_index.insert(value, last_id + id);
+        }
+        self.num_dims = self.num_dims.max(num_dims);
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -440,6 +476,53 @@ impl MetadataSchema {
             .ok_or(Error::InvalidField(name.to_string()))
     }
 
+    /// Returns the schema with `values` added to its existing fields,
+    /// and the new `fields` and `conditions`, along with whether the
+    /// dense index has to be rebuilt for it, which is the case when the
+    /// dimensions or the no. of replica nodes per vector change
+    ///
+    /// Existing fields, values and conditions are never removed or
+    /// renumbered, so vectors indexed with this schema remain valid
+    /// for the returned one.
+    pub fn extend(
+        &self,
+        values: Vec<(FieldName, HashSet<FieldValue>)>,
+        fields: Vec<MetadataField>,
+        conditions: Vec<SupportedCondition>,
+    ) -> Result<(Self, bool), Error> {
+        let mut new_fields = self.fields.clone();
+        for (name, values) in values {
+            new_fields
+                .iter_mut()
+                .find(|field| field.name == name)
+                .ok_or(Error::InvalidField(name))?
+                .add_values(values)?;
+        }
+        for field in fields {
+            if self.get_field(&field.name).is_ok() {
+                return Err(Error::InvalidField(format!(
+                    "{} already exists",
+                    field.name
+                )));
+            }
+            new_fields.push(field);
+        }
+        let mut new_conditions = self.conditions.clone();
+        new_conditions.extend(conditions);
+
+        let schema = Self::new(new_fields, new_conditions)?;
+        let dims = |schema: &Self| {
+            schema
+                .fields
+                .iter()
+                .map(|field| field.num_dims)
+                .collect::<Vec<u8>>()
+        };
+        let needs_reindex =
+            dims(&schema) != dims(self) || schema.max_num_replicas() != self.max_num_replicas();
+        Ok((schema, needs_reindex))
+    }
+
     /// Returns the max no. of replica nodes that will be created per
     /// vector inserted into the index.
     pub fn max_num_replicas(&self) -> u8 {
@@ -456,6 +539,51 @@ impl MetadataSchema {
     }
 }
 
+/// The metadata schema of a collection, which may be replaced by an
+/// extended one while the collection is in use
+///
+/// `get` returns the schema in effect at the time of the call, which
+/// callers hold on to for the duration of an operation. It's
+/// (de)serialized as an `Option<MetadataSchema>`.
+#[derive(Debug, Default)]
+pub struct SharedMetadataSchema(RwLock<Option<Arc<MetadataSchema>>>);
+
+impl SharedMetadataSchema {
+    pub fn new(schema: Option<MetadataSchema>) -> Self {
+        Self(RwLock::new(schema.map(Arc::new)))
+    }
+
+    pub fn get(&self) -> Option<Arc<MetadataSchema>> {
+        self.0.read().clone()
+    }
+
+    pub fn is_some(&self) -> bool {
+        self.0.read().is_some()
+    }
+
+    pub fn set(&self, schema: MetadataSchema) {
+        *self.0.write() = Some(Arc::new(schema));
+    }
+}
+
+impl Clone for SharedMetadataSchema {
+    fn clone(&self) -> Self {
+        Self(RwLock::new(self.get()))
+    }
+}
+
+impl Serialize for SharedMetadataSchema {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.read().as_deref().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedMetadataSchema {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<MetadataSchema>::deserialize(deserializer).map(Self::new)
+    }
+}
+
 pub type MetadataDimensions = Vec<i32>;
 
 #[cfg(test)]
@@ -1002,4 +1130,105 @@ mod tests {
         let schema = MetadataSchema::new(vec![age, group, level], conditions).unwrap();
         assert_eq!(6, schema.max_num_replicas());
     }
+
+    #[test]
+    fn test_metadata_field_add_values() {
+        let values: HashSet<FieldValue> = (1..=2).map(FieldValue::Int).collect();
+        let mut field = MetadataField::new("age".to_owned(), values).unwrap();
+        assert_eq!(2, field.num_dims);
+
+        // existing values keep their ids, and the values that are new
+        // get the following ones
+        let values: HashSet<FieldValue> = vec![0, 2].into_iter().map(FieldValue::Int).collect();
+        field.add_values(values).unwrap();
+        assert_eq!(2, field.num_dims);
+        assert_eq!(&2, field.value_index.get(&FieldValue::Int(2)).unwrap());
+        assert_eq!(&3, field.value_index.get(&FieldValue::Int(0)).unwrap());
+
+        field
+            .add_values(HashSet::from([FieldValue::Int(4)]))
+            .unwrap();
+        assert_eq!(3, field.num_dims);
+        assert_eq!(&4, field.value_index.get(&FieldValue::Int(4)).unwrap());
+
+        match field.add_values(HashSet::from([FieldValue::String("a".to_owned())])) {
+            Err(Error::InvalidFieldValues(_)) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_metadata_schema_extend() {
+        let age_values: HashSet<FieldValue> = (1..=2).map(FieldValue::Int).collect();
+        let age = MetadataField::new("age".to_owned(), age_values).unwrap();
+        let group_values: HashSet<FieldValue> = vec!["a", "b"]
+            .into_iter()
+            .map(|x| FieldValue::String(String::from(x)))
+            .collect();
+        let group = MetadataField::new("group".to_owned(), group_values).unwrap();
+        let schema = MetadataSchema::new(vec![age, group], vec![]).unwrap();
+
+        // a value fitting in the dimensions of the field, and an `Or`
+        // condition don't change the index
+        let (extended, needs_reindex) = schema
+            .extend(
+                vec![("age".to_owned(), HashSet::from([FieldValue::Int(3)]))],
+                vec![],
+                vec![SupportedCondition::Or(hashset(vec!["age", "group"]))],
+            )
+            .unwrap();
+        assert!(!needs_reindex);
+        assert_eq!(3, extended.get_field("age").unwrap().value_index.len());
+        assert_eq!(1, extended.conditions.len());
+
+        let (_, needs_reindex) = extended
+            .extend(
+                vec![("age".to_owned(), HashSet::from([FieldValue::Int(4)]))],
+                vec![],
+                vec![],
+            )
+            .unwrap();
+        assert!(needs_reindex);
+
+        let (_, needs_reindex) = extended
+            .extend(
+                vec![],
+                vec![],
+                vec![SupportedCondition::And(hashset(vec!["age", "group"]))],
+            )
+            .unwrap();
+        assert!(needs_reindex);
+
+        let level = MetadataField::new(
+            "level".to_owned(),
+            HashSet::from([FieldValue::String("first".to_owned())]),
+        )
+        .unwrap();
+        let (extended, needs_reindex) = extended.extend(vec![], vec![level], vec![]).unwrap();
+        assert!(needs_reindex);
+        assert_eq!(3, extended.fields.len());
+
+        let group = MetadataField::new("group".to_owned(), HashSet::new()).unwrap();
+        assert!(extended.extend(vec![], vec![group], vec![]).is_err());
+        assert!(extended
+            .extend(vec![("size".to_owned(), HashSet::new())], vec![], vec![])
+            .is_err());
+    }
+
+    #[test]
+    fn test_shared_metadata_schema_serde() {
+        let age_values: HashSet<FieldValue> = (1..=2).map(FieldValue::Int).collect();
+        let age = MetadataField::new("age".to_owned(), age_values).unwrap();
+        let schema = MetadataSchema::new(vec![age], vec![]).unwrap();
+
+        // serialized the same way as an `Option<MetadataSchema>`
+        let shared = SharedMetadataSchema::new(Some(schema.clone()));
+        assert_eq!(to_vec(&Some(schema)).unwrap(), to_vec(&shared).unwrap());
+        let shared: SharedMetadataSchema = from_slice(&to_vec(&shared).unwrap()).unwrap();
+        assert_eq!(1, shared.get().unwrap().fields.len());
+
+        let none: SharedMetadataSchema =
+            from_slice(&to_vec(&None::<MetadataSchema>).unwrap()).unwrap();
+        assert!(!none.is_some());
+    }
 }
@@ -10,6 +10,8 @@ use std::sync::{Arc, RwLock};
 use std::{fmt, fs};
 
 use super::lru_cache::LRUCache;
+use super::metrics;
+use super::storage_backend::{self, LocalFile, StorageBackend, StorageFile};
 use super::versioning::VersionNumber;
 
 #[derive(Debug)]
@@ -40,11 +42,11 @@ struct BufferRegion {
     buffer: RwLock<Vec<u8>>,
     dirty: AtomicBool,
     end: AtomicUsize,
-    file: Arc<RwLock<File>>,
+    file: Arc<dyn StorageFile>,
 }
 
 impl BufferRegion {
-    fn new(start: u64, file: Arc<RwLock<File>>, buffer_size: usize) -> Self {
+    fn new(start: u64, file: Arc<dyn StorageFile>, buffer_size: usize) -> Self {
         BufferRegion {
             start,
             buffer: RwLock::new(vec![0; buffer_size]),
@@ -59,13 +61,14 @@ impl BufferRegion {
     }
 
     fn flush(&self) -> Result<(), BufIoError> {
-        let mut file = self.file.write().map_err(|_| BufIoError::Locking)?;
-        file.seek(SeekFrom::Start(self.start))
-            .map_err(BufIoError::Io)?;
         let buffer = self.buffer.read().map_err(|_| BufIoError::Locking)?;
         let end = self.end.load(Ordering::SeqCst);
-        file.write_all(&buffer[..end]).map_err(BufIoError::Io)?;
+        self.file
+            .write_all_at(&buffer[..end], self.start)
+            .map_err(BufIoError::Io)?;
         self.dirty.store(false, Ordering::SeqCst);
+        metrics::BUFFER_FLUSHES.fetch_add(1, Ordering::Relaxed);
+        metrics::BUFFER_FLUSHED_BYTES.fetch_add(end as u64, Ordering::Relaxed);
         Ok(())
     }
 }
@@ -93,19 +96,37 @@ pub struct BufferManagerFactory<K> {
     root_path: Arc<Path>,
     path_function: fn(&Path, &K) -> PathBuf,
     buffer_size: usize,
+    backend: Arc<dyn StorageBackend>,
 }
 
 impl<K: Hash + Eq + Clone> BufferManagerFactory<K> {
+    /// Creates a factory opening its files through the configured storage
+    /// backend
     pub fn new(
         root_path: Arc<Path>,
         path_function: fn(&Path, &K) -> PathBuf,
         buffer_size: usize,
+    ) -> Self {
+        Self::with_backend(
+            root_path,
+            path_function,
+            buffer_size,
+            storage_backend::backend(),
+        )
+    }
+
+    pub fn with_backend(
+        root_path: Arc<Path>,
+        path_function: fn(&Path, &K) -> PathBuf,
+        buffer_size: usize,
+        backend: Arc<dyn StorageBackend>,
     ) -> Self {
         Self {
             bufmans: Arc::new(DashMap::new()),
             root_path,
             path_function,
             buffer_size,
+            backend,
         }
     }
 
@@ -115,13 +136,8 @@ impl<K: Hash + Eq + Clone> BufferManagerFactory<K> {
             .or_try_insert_with(|| {
                 let path = (self.path_function)(&self.root_path, &key);
 
-                let file = OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .truncate(false)
-                    .open(&path)?;
-                let bufman = Arc::new(BufferManager::new(file, self.buffer_size)?);
+                let file = self.backend.open(&path)?;
+                let bufman = Arc::new(BufferManager::from_storage(file, self.buffer_size)?);
 
                 Ok(bufman)
             })
@@ -134,10 +150,17 @@ impl<K: Hash + Eq + Clone> BufferManagerFactory<K> {
         }
         Ok(())
     }
+
+    /// Closes the buffer manager for `key`, if open, and deletes its file
+    pub fn remove_file(&self, key: &K) -> Result<(), BufIoError> {
+        self.bufmans.remove(key);
+        let path = (self.path_function)(&self.root_path, key);
+        self.backend.remove(&path).map_err(BufIoError::Io)
+    }
 }
 
 pub struct BufferManager {
-    file: Arc<RwLock<File>>,
+    file: Arc<dyn StorageFile>,
     regions: LRUCache<u64, Arc<BufferRegion>>,
     cursors: RwLock<HashMap<u64, Cursor>>,
     next_cursor_id: AtomicU64,
@@ -146,18 +169,28 @@ pub struct BufferManager {
 }
 
 impl BufferManager {
-    pub fn new(mut file: File, buffer_size: usize) -> io::Result<Self> {
-        let file_size = file.seek(SeekFrom::End(0))?;
-        file.seek(SeekFrom::Start(0))?;
+    pub fn new(file: File, buffer_size: usize) -> io::Result<Self> {
+        Self::from_storage(Arc::new(LocalFile::new(file)), buffer_size)
+    }
+
+    /// Opens the file at `path` through the configured storage backend,
+    /// creating it if it doesn't exist
+    pub fn open(path: &Path, buffer_size: usize) -> io::Result<Self> {
+        Self::from_storage(storage_backend::backend().open(path)?, buffer_size)
+    }
+
+    pub fn from_storage(file: Arc<dyn StorageFile>, buffer_size: usize) -> io::Result<Self> {
+        let file_size = file.size()?;
         let regions = LRUCache::with_prob_eviction(10000, 0.03125);
         let mut this = Self {
-            file: Arc::new(RwLock::new(file)),
+            file,
             regions,
             cursors: RwLock::new(HashMap::new()),
             next_cursor_id: AtomicU64::new(0),
             file_size: RwLock::new(file_size),
             buffer_size,
         };
+        this.regions.set_stats(Some(&metrics::BUFFER_REGION_CACHE));
         this.regions.set_evict_hook(Some(|region| {
             if region.should_final_flush() {
                 region.flush().unwrap();
@@ -190,10 +223,11 @@ impl BufferManager {
         let start = position - (position % self.buffer_size as u64);
         let cached_region = self.regions.get_or_insert::<BufIoError>(start, || {
             let mut region = BufferRegion::new(start, self.file.clone(), self.buffer_size);
-            let mut file = self.file.write().map_err(|_| BufIoError::Locking)?;
-            file.seek(SeekFrom::Start(start)).map_err(BufIoError::Io)?;
             let buffer = region.buffer.get_mut().map_err(|_| BufIoError::Locking)?;
-            let bytes_read = file.read(&mut buffer[..]).map_err(BufIoError::Io)?;
+            let bytes_read = self
+                .file
+                .read_at(&mut buffer[..], start)
+                .map_err(BufIoError::Io)?;
             region.end.store(bytes_read, Ordering::SeqCst);
             Ok(Arc::new(region))
         });
@@ -431,11 +465,7 @@ impl BufferManager {
                 region.flush()?;
             }
         }
-        self.file
-            .write()
-            .map_err(|_| BufIoError::Locking)?
-            .flush()
-            .map_err(BufIoError::Io)
+        self.file.flush().map_err(BufIoError::Io)
     }
 
     pub fn file_size(&self) -> u64 {
@@ -550,6 +580,7 @@ impl FilelessBufferManager {
 
         for (region_id, (_, path)) in region_versions_map {
             let offset = region_id * buffer_size as u64;
+            storage_backend::backend().fetch(&path)?;
             let mut file = OpenOptions::new().read(true).open(path)?;
             let mut region = FilelessBufferRegion::new(offset, buffer_size);
             let buffer = region.buffer.get_mut().map_err(|_| BufIoError::Locking)?;
@@ -602,6 +633,12 @@ impl FilelessBufferManager {
         Ok(i32::from_le_bytes(buffer))
     }
 
+    pub fn read_i64_with_cursor(&self, cursor_id: u64) -> Result<i64, BufIoError> {
+        let mut buffer = [0u8; 8];
+        self.read_with_cursor(cursor_id, &mut buffer)?;
+        Ok(i64::from_le_bytes(buffer))
+    }
+
     pub fn read_u32_with_cursor(&self, cursor_id: u64) -> Result<u32, BufIoError> {
         let mut buffer = [0u8; 4];
         self.read_with_cursor(cursor_id, &mut buffer)?;
@@ -1503,9 +1540,8 @@ mod tests {
 
         let mut read_data = vec![0; 100 * 1024 * 1024];
 
-        let mut file = bufman.file.write().unwrap();
-        file.seek(SeekFrom::Start(0)).unwrap();
-        file.read_exact(&mut read_data).unwrap();
+        let read = bufman.file.read_at(&mut read_data, 0).unwrap();
+        assert_eq!(read, read_data.len());
 
         for (i, (r, w)) in read_data.into_iter().zip(written_data).enumerate() {
             assert_eq!(r, w, "mismatch at {}", i);
@@ -6,11 +6,13 @@ use super::file_persist::{read_prop_metadata_from_file, read_prop_value_from_fil
 use super::inverted_index::InvertedIndexNodeData;
 use super::lazy_item::{FileIndex, LazyItem};
 use super::lru_cache::LRUCache;
+use super::metrics;
 use super::prob_node::{ProbNode, SharedNode};
 use super::serializer::hnsw::HNSWIndexSerialize;
 use super::serializer::inverted::InvertedIndexSerialize;
 use super::serializer::tf_idf::TFIDFIndexSerialize;
 use super::tf_idf_index::TFIDFIndexNodeData;
+use super::tracing;
 use super::types::*;
 use super::versioning::VersionNumber;
 use dashmap::DashMap;
@@ -56,7 +58,8 @@ impl HNSWIndexCache {
         prop_file: RwLock<File>,
         distance_metric: Arc<RwLock<DistanceMetric>>,
     ) -> Self {
-        let registry = LRUCache::with_prob_eviction(100_000_000, 0.03125);
+        let mut registry = LRUCache::with_prob_eviction(100_000_000, 0.03125);
+        registry.set_stats(Some(&metrics::HNSW_NODE_CACHE));
         let props_registry = DashMap::new();
         let metadata_registry = DashMap::new();
         Self {
@@ -224,6 +227,7 @@ impl HNSWIndexCache {
             break;
         }
 
+        tracing::record_node_load();
         let bufman = self.bufmans.get(file_index.file_id)?;
         let data = ProbNode::deserialize(
             &bufman,
@@ -336,6 +340,7 @@ impl InvertedIndexCache {
             break;
         }
 
+        tracing::record_node_load();
         let data = InvertedIndexNodeData::deserialize(
             &self.dim_bufman,
             &self.data_bufmans,
@@ -441,6 +446,7 @@ impl TFIDFIndexCache {
             break;
         }
 
+        tracing::record_node_load();
         let data = TFIDFIndexNodeData::deserialize(
             &self.dim_bufman,
             &self.data_bufmans,
@@ -3,28 +3,40 @@ use super::collection_transaction::{
     ExplicitTransaction, ExplicitTransactionID, ImplicitTransaction, TransactionStatus,
 };
 use super::common::WaCustomError;
+use super::embeddings::EmbeddingConfig;
+use super::index_rebuild::rebuild_indexes;
 use super::indexing_manager::IndexingManager;
-use super::meta_persist::store_highest_internal_id;
+use super::lmdb_map::with_rw_txn;
+use super::meta_persist::{
+    retrieve_background_version, retrieve_dropped_vector_count, store_compaction_floor,
+    store_dropped_vector_count, store_highest_internal_id, store_vector_count,
+};
 use super::paths::get_data_path;
 use super::tree_map::{TreeMap, TreeMapVec};
-use super::types::{get_collections_path, DocumentId, InternalId, MetaDb, VectorId};
+use super::types::{
+    get_collections_path, CollectionsMap, DocumentId, InternalId, MetaDb, VectorId,
+};
+use super::utils::dir_size;
 use super::versioning::{VersionControl, VersionNumber, VersionSource};
 use super::wal::VectorOp;
 use crate::app_context::AppContext;
-use crate::config_loader::Config;
+use crate::config_loader::{Config, RetentionConfig};
 use crate::indexes::hnsw::{DenseInputEmbedding, HNSWIndex};
 use crate::indexes::inverted::types::SparsePair;
 use crate::indexes::inverted::{InvertedIndex, SparseInputEmbedding};
 use crate::indexes::tf_idf::{TFIDFIndex, TFIDFInputEmbedding};
 use crate::indexes::IndexOps;
-use crate::metadata::{MetadataFields, MetadataSchema};
+use crate::metadata::query_filtering::Filter;
+use crate::metadata::{MetadataFields, MetadataSchema, SharedMetadataSchema};
 use chrono::{DateTime, TimeZone, Utc};
-use lmdb::{Database, Environment, Transaction, WriteFlags};
-use parking_lot::RwLock;
+use lmdb::{Database, Environment, WriteFlags};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use serde_cbor::to_vec;
 use siphasher::sip::SipHasher24;
-use std::fs::{create_dir_all, OpenOptions};
+use std::collections::{HashMap, HashSet};
+use std::fs::create_dir_all;
+use std::ops::Range;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::thread;
 use std::{fs, hash::Hasher, path::Path, sync::Arc};
@@ -34,11 +46,17 @@ use utoipa::ToSchema;
 pub struct DenseVectorOptions {
     pub enabled: bool,
     pub dimension: usize,
+    /// Computes the values of the vectors upserted with only a `text`
+    #[serde(default)]
+    pub embedding: Option<EmbeddingConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SparseVectorOptions {
     pub enabled: bool,
+    /// Computes the values of the vectors upserted with only a `text`
+    #[serde(default)]
+    pub embedding: Option<EmbeddingConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -50,6 +68,10 @@ pub struct TFIDFOptions {
 pub struct CollectionConfig {
     pub max_vectors: Option<u32>,
     pub replication_factor: Option<u32>,
+    /// Seconds after their write that vectors written without an
+    /// `expires_at` expire, they don't expire if not set
+    #[serde(default)]
+    pub ttl: Option<u64>,
 }
 
 #[derive(Debug, Clone)]
@@ -60,6 +82,14 @@ pub struct RawVectorEmbedding {
     pub metadata: Option<MetadataFields>,
     pub sparse_values: Option<Vec<SparsePair>>,
     pub text: Option<String>,
+    // expired vectors are skipped by searches, and deleted by the sweeper
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl RawVectorEmbedding {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
 }
 
 #[derive(Deserialize, Clone, Serialize, Debug)]
@@ -69,11 +99,23 @@ pub struct CollectionMetadata {
     pub dense_vector: DenseVectorOptions,
     pub sparse_vector: SparseVectorOptions,
     pub tf_idf_options: TFIDFOptions,
-    pub metadata_schema: Option<MetadataSchema>,
+    pub metadata_schema: SharedMetadataSchema,
     pub config: CollectionConfig,
     pub store_raw_text: bool,
 }
 
+/// Outcome of compacting a collection
+#[derive(Debug, Clone, Copy)]
+pub struct CompactionStats {
+    // oldest version still available after the compaction
+    pub retained_from: VersionNumber,
+    pub versions_removed: u32,
+    // whether the indexes were rebuilt without the deleted vectors
+    pub indexes_rebuilt: bool,
+    pub disk_usage_before: u64,
+    pub disk_usage_after: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CollectionIndexingStatusSummary {
     pub total_transactions: u32,
@@ -102,9 +144,17 @@ pub struct CollectionIndexingStatus {
 pub struct Collection {
     pub meta: CollectionMetadata,
     pub lmdb: MetaDb,
+    // the locks of `open_explicit_transactions`,
+    // `current_implicit_transaction`, `last_allotted_version` and
+    // `current_version` are always taken in this order, any of them may be
+    // skipped
     pub current_version: RwLock<VersionNumber>,
     pub last_allotted_version: RwLock<VersionNumber>,
-    pub current_explicit_transaction: RwLock<Option<ExplicitTransaction>>,
+    pub open_explicit_transactions: RwLock<HashMap<ExplicitTransactionID, ExplicitTransaction>>,
+    // versions committed by explicit transactions, along with the ids of
+    // the vectors they wrote, kept as long as a transaction that was opened
+    // before them is open, to detect conflicts with it
+    pub recent_commits: Mutex<Vec<(VersionNumber, HashSet<VectorId>)>>,
     pub current_implicit_transaction: RwLock<ImplicitTransaction>,
     pub vcs: VersionControl,
     pub internal_to_external_map: TreeMap<InternalId, RawVectorEmbedding>,
@@ -112,6 +162,11 @@ pub struct Collection {
     pub document_to_internals_map: TreeMapVec<DocumentId, InternalId>,
     pub transaction_status_map: TreeMap<ExplicitTransactionID, RwLock<TransactionStatus>>,
     pub internal_id_counter: AtomicU32,
+    // number of vectors currently in the collection
+    pub vector_count: AtomicU32,
+    // oldest version that can still be read, compactions rebase the history
+    // preceding it onto it
+    pub compaction_floor: AtomicU32,
     pub hnsw_index: RwLock<Option<Arc<HNSWIndex>>>,
     pub inverted_index: RwLock<Option<Arc<InvertedIndex>>>,
     pub tf_idf_index: RwLock<Option<Arc<TFIDFIndex>>>,
@@ -121,6 +176,14 @@ pub struct Collection {
     // a reference to the collection
     pub indexing_manager: RwLock<Option<IndexingManager>>,
     pub is_indexing: AtomicBool,
+    // held while the collection is being compacted
+    pub compaction_lock: Mutex<()>,
+    // held while the preconditions of streamed writes are checked and the
+    // writes are applied, so that they can't both pass
+    pub conditional_writes_lock: Mutex<()>,
+    // whether vectors with an expiry may exist, so that the sweeper skips
+    // the collections without any
+    pub has_expiring_vectors: AtomicBool,
 }
 
 impl Collection {
@@ -146,16 +209,8 @@ impl Collection {
         let collection_path: Arc<Path> = get_collections_path().join(&name).into();
         fs::create_dir_all(&collection_path).map_err(|e| WaCustomError::FsError(e.to_string()))?;
 
-        let internal_to_external_map_dim_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .truncate(false)
-            .create(true)
-            .open(collection_path.join("itoe.dim"))
-            .map_err(BufIoError::Io)?;
-
         let internal_to_external_map_dim_bufman =
-            BufferManager::new(internal_to_external_map_dim_file, 8192).map_err(BufIoError::Io)?;
+            BufferManager::open(&collection_path.join("itoe.dim"), 8192).map_err(BufIoError::Io)?;
 
         let internal_to_external_map_data_bufmans = BufferManagerFactory::new(
             collection_path.clone(),
@@ -163,16 +218,8 @@ impl Collection {
             8192,
         );
 
-        let external_to_internal_map_dim_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .truncate(false)
-            .create(true)
-            .open(collection_path.join("etoi.dim"))
-            .map_err(BufIoError::Io)?;
-
         let external_to_internal_map_dim_bufman =
-            BufferManager::new(external_to_internal_map_dim_file, 8192).map_err(BufIoError::Io)?;
+            BufferManager::open(&collection_path.join("etoi.dim"), 8192).map_err(BufIoError::Io)?;
 
         let external_to_internal_map_data_bufmans = BufferManagerFactory::new(
             collection_path.clone(),
@@ -180,16 +227,8 @@ impl Collection {
             8192,
         );
 
-        let document_to_internals_map_dim_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .truncate(false)
-            .create(true)
-            .open(collection_path.join("dtoi.dim"))
-            .map_err(BufIoError::Io)?;
-
         let document_to_internals_map_dim_bufman =
-            BufferManager::new(document_to_internals_map_dim_file, 8192).map_err(BufIoError::Io)?;
+            BufferManager::open(&collection_path.join("dtoi.dim"), 8192).map_err(BufIoError::Io)?;
 
         let document_to_internals_map_data_bufmans = BufferManagerFactory::new(
             collection_path.clone(),
@@ -197,16 +236,9 @@ impl Collection {
             8192,
         );
 
-        let transaction_status_map_dim_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .truncate(false)
-            .create(true)
-            .open(collection_path.join("txn_status.dim"))
-            .map_err(BufIoError::Io)?;
-
         let transaction_status_map_dim_bufman =
-            BufferManager::new(transaction_status_map_dim_file, 8192).map_err(BufIoError::Io)?;
+            BufferManager::open(&collection_path.join("txn_status.dim"), 8192)
+                .map_err(BufIoError::Io)?;
 
         let transaction_status_map_data_bufmans = BufferManagerFactory::new(
             collection_path.clone(),
@@ -221,14 +253,15 @@ impl Collection {
                 dense_vector: dense_vector_options,
                 sparse_vector: sparse_vector_options,
                 tf_idf_options,
-                metadata_schema,
+                metadata_schema: SharedMetadataSchema::new(metadata_schema),
                 config: collection_config,
                 store_raw_text,
             },
             lmdb,
             current_version: RwLock::new(current_version),
             last_allotted_version: RwLock::new(current_version),
-            current_explicit_transaction: RwLock::new(None),
+            open_explicit_transactions: RwLock::new(HashMap::new()),
+            recent_commits: Mutex::new(Vec::new()),
             current_implicit_transaction: RwLock::new(ImplicitTransaction::default()),
             vcs,
             internal_to_external_map: TreeMap::new(
@@ -248,11 +281,16 @@ impl Collection {
                 transaction_status_map_data_bufmans,
             ),
             internal_id_counter: AtomicU32::new(0),
+            vector_count: AtomicU32::new(0),
+            compaction_floor: AtomicU32::new(0),
             hnsw_index: RwLock::new(None),
             inverted_index: RwLock::new(None),
             tf_idf_index: RwLock::new(None),
             indexing_manager: RwLock::new(None),
             is_indexing: AtomicBool::new(false),
+            compaction_lock: Mutex::new(()),
+            conditional_writes_lock: Mutex::new(()),
+            has_expiring_vectors: AtomicBool::new(false),
         });
 
         *collection.indexing_manager.write() = Some(IndexingManager::new(
@@ -269,7 +307,7 @@ impl Collection {
                 loop {
                     std::thread::sleep(std::time::Duration::from_secs(config.epoch_length));
 
-                    let _explicit_txn_guard = collection.current_explicit_transaction.write();
+                    let _explicit_txn_guard = collection.open_explicit_transactions.write();
                     let mut implicit_txn_guard = collection.current_implicit_transaction.write();
                     std::mem::take(&mut *implicit_txn_guard).pre_commit(&collection, &config)?;
                 }
@@ -280,10 +318,51 @@ impl Collection {
         Ok(collection)
     }
 
+    /// Commits the pending streamed writes and runs `f` while writes to the
+    /// collection are blocked, so that its files don't change meanwhile
+    ///
+    /// Fails with `WaCustomError::LockError` if an explicit transaction is
+    /// open or still being indexed.
+    pub fn with_writes_blocked<T>(
+        &self,
+        config: &Config,
+        f: impl FnOnce() -> Result<T, WaCustomError>,
+    ) -> Result<T, WaCustomError> {
+        let _compaction_guard = self.compaction_lock.lock();
+        self.block_writes(config, f)
+    }
+
+    /// Same as `with_writes_blocked`, for callers already holding the
+    /// `compaction_lock`
+    pub fn block_writes<T>(
+        &self,
+        config: &Config,
+        f: impl FnOnce() -> Result<T, WaCustomError>,
+    ) -> Result<T, WaCustomError> {
+        let explicit_txn_guard = self.open_explicit_transactions.write();
+        if !explicit_txn_guard.is_empty() || self.is_indexing() {
+            return Err(WaCustomError::LockError(
+                "Collection has a transaction in progress".to_string(),
+            ));
+        }
+        let mut implicit_txn_guard = self.current_implicit_transaction.write();
+        std::mem::take(&mut *implicit_txn_guard).pre_commit(self, config)?;
+        f()
+    }
+
     pub fn is_indexing(&self) -> bool {
         self.is_indexing.load(Ordering::Relaxed)
     }
 
+    pub fn vector_count(&self) -> u32 {
+        self.vector_count.load(Ordering::Relaxed)
+    }
+
+    /// Oldest version whose state can still be read
+    pub fn compaction_floor(&self) -> VersionNumber {
+        VersionNumber::from(self.compaction_floor.load(Ordering::SeqCst))
+    }
+
     /// Computes the SipHash of the collection name
     pub fn get_hash(&self) -> u64 {
         let mut hasher = SipHasher24::new();
@@ -314,13 +393,7 @@ impl Collection {
         let key = self.get_key();
         let value = self.serialize()?;
 
-        let mut txn = env
-            .begin_rw_txn()
-            .map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
-
-        txn.put(db, &key, &value, WriteFlags::empty())
-            .map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
-        txn.commit()
+        with_rw_txn(env, |txn| txn.put(db, &key, &value, WriteFlags::empty()))
             .map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
 
         Ok(())
@@ -331,13 +404,7 @@ impl Collection {
     pub fn delete(&self, env: &Environment, db: Database) -> Result<(), WaCustomError> {
         let key = self.get_key();
 
-        let mut txn = env
-            .begin_rw_txn()
-            .map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
-
-        txn.del(db, &key, None)
-            .map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
-        txn.commit()
+        with_rw_txn(env, |txn| txn.del(db, &key, None))
             .map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
 
         Ok(())
@@ -355,6 +422,15 @@ impl Collection {
         self.tf_idf_index.read().clone()
     }
 
+    /// Returns the version a vector was last written at, `None` if it
+    /// doesn't exist or was deleted
+    pub fn vector_version(&self, vector_id: &VectorId) -> Option<VersionNumber> {
+        self.external_to_internal_map.get_latest(vector_id)?;
+        self.external_to_internal_map
+            .get_versioned(vector_id)
+            .map(|item| item.latest_item().version)
+    }
+
     /// Returns the raw embedding mapped to an internal id
     ///
     /// It's recommended to call this method instead of directly
@@ -370,12 +446,8 @@ impl Collection {
         internal_id: &InternalId,
     ) -> Option<&RawVectorEmbedding> {
         let mapped_internal_id = if let Some(hnsw_index) = self.get_hnsw_index() {
-            if self.meta.metadata_schema.is_some() {
-                let id = **internal_id;
-                InternalId::from(id - id % hnsw_index.max_replica_per_node as u32)
-            } else {
-                *internal_id
-            }
+            let id = **internal_id;
+            InternalId::from(id - id % hnsw_index.id_stride)
         } else {
             *internal_id
         };
@@ -383,17 +455,195 @@ impl Collection {
             .get_latest(&mapped_internal_id)
     }
 
+    /// Returns up to `limit` of the vectors stored at `version` that match
+    /// `filter`, in the order of their internal ids starting at `cursor`,
+    /// along with the cursor of the next page, `None` once all vectors
+    /// are returned. Since entries are read as of `version`, a scan with
+    /// the same version is consistent across pages, regardless of writes
+    /// committed in between.
+    pub fn scroll_vectors(
+        &self,
+        cursor: u32,
+        limit: usize,
+        version: VersionNumber,
+        filter: Option<&Filter>,
+    ) -> (Vec<RawVectorEmbedding>, Option<u32>) {
+        let step = self.base_node_step();
+        let end = self.internal_id_counter.load(Ordering::Relaxed);
+        let mut vectors = Vec::new();
+        let mut id = cursor.next_multiple_of(step);
+        while id < end {
+            if vectors.len() == limit {
+                return (vectors, Some(id));
+            }
+            let internal_id = InternalId::from(id);
+            id += step;
+            let Some(item) = self.internal_to_external_map.get_versioned(&internal_id) else {
+                continue;
+            };
+            let Some(embedding) = item.at(version) else {
+                continue;
+            };
+            // upserting an existing id maps it to a new internal id, leaving
+            // the previous mapping in place
+            let is_current = self
+                .external_to_internal_map
+                .get_versioned(&embedding.id)
+                .is_some_and(|item| item.at(version) == Some(&internal_id));
+            if !is_current
+                || filter.is_some_and(|filter| !filter.matches(embedding.metadata.as_ref()))
+            {
+                continue;
+            }
+            vectors.push(embedding.clone());
+        }
+        (vectors, None)
+    }
+
+    /// Returns the ids of the vectors whose metadata match `filter`,
+    /// including the ones written by implicit transactions whose version
+    /// isn't committed yet
+    pub fn vector_ids_matching(&self, filter: &Filter) -> Vec<VectorId> {
+        let end = self.internal_id_counter.load(Ordering::Relaxed);
+        (0..end)
+            .step_by(self.base_node_step() as usize)
+            .filter_map(|id| {
+                let internal_id = InternalId::from(id);
+                let embedding = self.internal_to_external_map.get_latest(&internal_id)?;
+                (self.is_current_mapping(&embedding.id, internal_id)
+                    && filter.matches(embedding.metadata.as_ref()))
+                .then(|| embedding.id.clone())
+            })
+            .collect()
+    }
+
+    /// Returns the ids of the vectors of a document
+    pub fn vector_ids_of_document(&self, document_id: &DocumentId) -> Vec<VectorId> {
+        self.vectors_of_document(document_id)
+            .into_iter()
+            .map(|embedding| embedding.id.clone())
+            .collect()
+    }
+
+    /// Returns the vectors of a document
+    pub fn vectors_of_document(&self, document_id: &DocumentId) -> Vec<&RawVectorEmbedding> {
+        let Some(internal_ids) = self.document_to_internals_map.get(document_id) else {
+            return Vec::new();
+        };
+        let mut vectors: Vec<&RawVectorEmbedding> = Vec::new();
+        for internal_id in internal_ids.iter() {
+            let Some(embedding) = self.get_raw_emb_by_internal_id(&internal_id) else {
+                continue;
+            };
+            // replacing a vector leaves its previous internal id mapped to
+            // the document
+            if self.is_current_mapping(&embedding.id, internal_id)
+                && !vectors.iter().any(|vector| vector.id == embedding.id)
+            {
+                vectors.push(embedding);
+            }
+        }
+        vectors
+    }
+
+    /// Returns the ids of the vectors expired at `now`, unless no vector
+    /// with an expiry was written since the previous call found none
+    pub fn expired_vector_ids(&self, now: DateTime<Utc>) -> Vec<VectorId> {
+        // cleared before the scan, so that vectors with an expiry indexed
+        // during it set it again
+        if !self.has_expiring_vectors.swap(false, Ordering::Relaxed) {
+            return Vec::new();
+        }
+        let end = self.internal_id_counter.load(Ordering::Relaxed);
+        let mut has_expiring_vectors = false;
+        let mut vector_ids = Vec::new();
+        for id in (0..end).step_by(self.base_node_step() as usize) {
+            let internal_id = InternalId::from(id);
+            let Some(embedding) = self.internal_to_external_map.get_latest(&internal_id) else {
+                continue;
+            };
+            if embedding.expires_at.is_none()
+                || !self.is_current_mapping(&embedding.id, internal_id)
+            {
+                continue;
+            }
+            has_expiring_vectors = true;
+            if embedding.is_expired(now) {
+                vector_ids.push(embedding.id.clone());
+            }
+        }
+        if has_expiring_vectors {
+            self.has_expiring_vectors.store(true, Ordering::Relaxed);
+        }
+        vector_ids
+    }
+
+    /// Returns the embedding of the vector stored under `internal_id`,
+    /// unless it was deleted or replaced since
+    pub fn live_embedding(&self, internal_id: InternalId) -> Option<&RawVectorEmbedding> {
+        let embedding = self.internal_to_external_map.get_latest(&internal_id)?;
+        self.is_current_mapping(&embedding.id, internal_id)
+            .then_some(embedding)
+    }
+
+    /// Returns the internal ids in `range` of the vectors currently in the
+    /// collection
+    pub fn live_internal_ids(&self, range: Range<u32>) -> Vec<InternalId> {
+        let step = self.base_node_step();
+        (range.start.next_multiple_of(step)..range.end)
+            .step_by(step as usize)
+            .map(InternalId::from)
+            .filter(|internal_id| self.live_embedding(*internal_id).is_some())
+            .collect()
+    }
+
+    fn is_current_mapping(&self, vector_id: &VectorId, internal_id: InternalId) -> bool {
+        self.external_to_internal_map.get_latest(vector_id) == Some(&internal_id)
+    }
+
+    /// Returns the no. of internal ids below `end` vectors are stored
+    /// under, deleted and replaced ones included
+    pub fn base_node_count(&self, end: u32) -> u32 {
+        end.div_ceil(self.base_node_step())
+    }
+
+    // fraction of the vectors in the indexes that were deleted or replaced
+    // since the indexes were last rebuilt
+    fn deleted_fraction(&self) -> Result<f32, WaCustomError> {
+        let end = self.internal_id_counter.load(Ordering::Relaxed);
+        let dropped = retrieve_dropped_vector_count(&self.lmdb)?.unwrap_or_default();
+        let indexed = self.base_node_count(end).saturating_sub(dropped);
+        if indexed == 0 {
+            return Ok(0.0);
+        }
+        let live = self.live_internal_ids(0..end).len() as u32;
+        Ok(indexed.saturating_sub(live) as f32 / indexed as f32)
+    }
+
+    // only base node ids have mappings, see `get_raw_emb_by_internal_id`
+    fn base_node_step(&self) -> u32 {
+        self.get_hnsw_index()
+            .map_or(1, |hnsw_index| hnsw_index.id_stride)
+    }
+
+    /// Validates `embeddings` and writes them to `transaction`
+    ///
+    /// Existing vectors are only replaced if their ids are in `replaced`,
+    /// the upload fails otherwise.
     pub fn run_upload(
         &self,
-        embeddings: Vec<RawVectorEmbedding>,
+        mut embeddings: Vec<RawVectorEmbedding>,
+        replaced: &HashSet<VectorId>,
         transaction: &ExplicitTransaction,
     ) -> Result<(), WaCustomError> {
+        self.apply_default_ttl(&mut embeddings);
         // Check if any of the IDs already exist in the transaction
         for embedding in &embeddings {
-            if self
-                .external_to_internal_map
-                .get_latest(&embedding.id)
-                .is_some()
+            if !replaced.contains(&embedding.id)
+                && self
+                    .external_to_internal_map
+                    .get_latest(&embedding.id)
+                    .is_some()
             {
                 return Err(WaCustomError::InvalidData(format!(
                     "Vector ID already exists: {}",
@@ -431,11 +681,24 @@ impl Collection {
             }
         }
 
-        transaction.wal.append(VectorOp::Upsert(embeddings))?;
+        transaction.append_to_wal(VectorOp::Upsert(embeddings))?;
 
         Ok(())
     }
 
+    /// Sets the expiry of the embeddings without one according to the
+    /// collection's `ttl`, before they're written to the WAL so that
+    /// replaying it doesn't extend their lifetime
+    pub fn apply_default_ttl(&self, embeddings: &mut [RawVectorEmbedding]) {
+        let Some(ttl) = self.meta.config.ttl else {
+            return;
+        };
+        let expires_at = Utc::now() + chrono::Duration::seconds(ttl as i64);
+        for embedding in embeddings {
+            embedding.expires_at.get_or_insert(expires_at);
+        }
+    }
+
     pub fn index_embeddings(
         &self,
         embeddings: Vec<RawVectorEmbedding>,
@@ -443,7 +706,7 @@ impl Collection {
         config: &Config,
     ) -> Result<(), WaCustomError> {
         let num_nodes_per_emb = if let Some(hnsw_index) = &*self.hnsw_index.read() {
-            hnsw_index.max_replica_per_node as usize
+            hnsw_index.id_stride as usize
         } else {
             1
         };
@@ -463,6 +726,7 @@ impl Collection {
                         metadata,
                         sparse_values,
                         text,
+                        expires_at,
                     } = embedding.clone();
 
                     let internal_id = InternalId::from(id_start + (i * num_nodes_per_emb) as u32);
@@ -482,6 +746,13 @@ impl Collection {
                         embedding.text = None;
                     }
 
+                    if self.external_to_internal_map.get_latest(&id).is_none() {
+                        self.vector_count.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if expires_at.is_some() {
+                        self.has_expiring_vectors.store(true, Ordering::Relaxed);
+                    }
+
                     self.internal_to_external_map
                         .insert(version, &internal_id, embedding);
                     self.external_to_internal_map
@@ -549,6 +820,13 @@ impl Collection {
 
         self.internal_to_external_map.delete(version, &internal_id);
         self.external_to_internal_map.delete(version, &vector_id);
+        // saturating, collections persisted before the count was tracked
+        // start out at 0
+        let _ = self
+            .vector_count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                Some(count.saturating_sub(1))
+            });
         if let Some(document_id) = &raw_emb.document_id {
             self.document_to_internals_map
                 .delete(version, document_id, internal_id);
@@ -571,9 +849,99 @@ impl Collection {
         self.document_to_internals_map.serialize()?;
         self.transaction_status_map.serialize()?;
         store_highest_internal_id(&self.lmdb, self.internal_id_counter.load(Ordering::Relaxed))?;
+        store_vector_count(&self.lmdb, self.vector_count())?;
         Ok(())
     }
 
+    /// Drops the versions `policy` doesn't retain, along with their files
+    ///
+    /// The indexes are rebuilt without the deleted vectors, and the history
+    /// of the id mappings and transaction statuses is rewritten from the
+    /// oldest retained version onwards. Versions that are still being
+    /// indexed are always retained. Returns `Ok(None)` if there is nothing
+    /// to compact.
+    pub fn compact(
+        &self,
+        collections_map: &CollectionsMap,
+        config: &Config,
+        policy: &RetentionConfig,
+    ) -> Result<Option<CompactionStats>, WaCustomError> {
+        let Some(_guard) = self.compaction_lock.try_lock() else {
+            return Err(WaCustomError::LockError(
+                "Collection is already being compacted".to_string(),
+            ));
+        };
+        if !self.open_explicit_transactions.read().is_empty() || self.is_indexing() {
+            return Err(WaCustomError::LockError(
+                "Collection has a transaction in progress".to_string(),
+            ));
+        }
+
+        let Some(cutoff) = self.vcs.retention_cutoff(policy, Utc::now())? else {
+            return Ok(None);
+        };
+        let background_version = retrieve_background_version(&self.lmdb)?;
+        let retain_from = VersionNumber::from(
+            (*cutoff)
+                .min(*background_version)
+                .min(**self.current_version.read()),
+        );
+
+        let dead_versions: Vec<_> = self
+            .vcs
+            .get_versions()?
+            .into_iter()
+            .map(|version_info| version_info.version)
+            .filter(|version| **version < *retain_from)
+            .collect();
+        if dead_versions.is_empty() {
+            return Ok(None);
+        }
+
+        let path = self.get_path();
+        let disk_usage_before =
+            dir_size(&path).map_err(|e| WaCustomError::FsError(e.to_string()))?;
+
+        // the deleted vectors are only marked as such in the indexes, which
+        // are rebuilt without them once they make up enough of them
+        let deleted_fraction = self.deleted_fraction()?;
+        let indexes_rebuilt =
+            deleted_fraction > 0.0 && deleted_fraction >= policy.rebuild_threshold;
+        if indexes_rebuilt {
+            let dropped = rebuild_indexes(collections_map, self, config)?;
+            store_dropped_vector_count(&self.lmdb, dropped)?;
+        }
+
+        // raised before the history is rebased, so that reads of older
+        // versions are rejected instead of seeing partial results
+        store_compaction_floor(&self.lmdb, retain_from)?;
+        self.compaction_floor
+            .fetch_max(*retain_from, Ordering::SeqCst);
+
+        self.internal_to_external_map
+            .compact(retain_from, &dead_versions)?;
+        self.external_to_internal_map
+            .compact(retain_from, &dead_versions)?;
+        self.document_to_internals_map
+            .compact(retain_from, &dead_versions)?;
+        self.transaction_status_map
+            .compact(retain_from, &dead_versions)?;
+        // the metadata goes last, if the compaction is interrupted it's
+        // simply picked up by the next run
+        self.vcs.delete_versions_before(retain_from)?;
+
+        let disk_usage_after =
+            dir_size(&path).map_err(|e| WaCustomError::FsError(e.to_string()))?;
+
+        Ok(Some(CompactionStats {
+            retained_from: retain_from,
+            versions_removed: dead_versions.len() as u32,
+            indexes_rebuilt,
+            disk_usage_before,
+            disk_usage_after,
+        }))
+    }
+
     pub fn indexing_status(&self) -> Result<CollectionIndexingStatus, WaCustomError> {
         let mut active_transactions = Vec::new();
         let mut last_synced = Utc.timestamp_opt(0, 0).unwrap();
@@ -10,6 +10,7 @@ use crate::indexes::hnsw::HNSWIndex;
 use crate::indexes::inverted::InvertedIndex;
 use crate::models::common::WaCustomError;
 use crate::models::lru_cache::{EvictStrategy, LRUCache, ProbEviction};
+use crate::models::metrics;
 use crate::models::types::AppEnv;
 
 #[allow(dead_code)]
@@ -66,7 +67,9 @@ impl CollectionCacheManager {
         let strategy = EvictStrategy::Probabilistic(ProbEviction::new(prob_f16));
 
         // Create the LRUCache
-        let cache = Arc::new(LRUCache::new(max_collections, strategy));
+        let mut cache = LRUCache::new(max_collections, strategy);
+        cache.set_stats(Some(&metrics::COLLECTION_CACHE));
+        let cache = Arc::new(cache);
 
         // Create the manager instance
         let manager = Self {
@@ -145,6 +148,18 @@ impl CollectionCacheManager {
         Ok(entry)
     }
 
+    // Loads a cached collection again, so that its entry holds the current
+    // indexes of the collection, e.g. once they're rebuilt
+    pub fn reload_collection(&self, name: &str) -> Result<(), WaCustomError> {
+        let Some(key) = self.name_to_key.get(name).map(|key| key.clone()) else {
+            return Ok(());
+        };
+        if self.cache.remove(&key).is_some() {
+            self.load_collection(name)?;
+        }
+        Ok(())
+    }
+
     pub fn unload_collection(&self, name: &str) -> Result<(), WaCustomError> {
         // Clean up mappings
         self.name_to_key.remove(name);
@@ -1,7 +1,15 @@
-use std::{fmt, fs, mem, ops::Deref, sync::mpsc, thread};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io, mem,
+    ops::Deref,
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
 
 use chrono::{DateTime, Utc};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rand::random;
 use serde::{
     de::{self, Visitor},
@@ -16,12 +24,16 @@ use utoipa::{
 use crate::{config_loader::Config, indexes::IndexOps};
 
 use super::{
-    buffered_io::BufIoError,
     collection::Collection,
     common::WaCustomError,
     durable_wal::DurableWALFile,
-    meta_persist::{update_background_version, update_current_version},
+    meta_persist::{
+        add_open_transaction, remove_open_transaction, update_background_version,
+        update_current_version,
+    },
+    replication,
     tree_map::TreeMapKey,
+    types::VectorId,
     versioning::VersionNumber,
     wal::{VectorOp, WALFile},
 };
@@ -140,16 +152,51 @@ impl From<u32> for ExplicitTransactionID {
     }
 }
 
+/// How a commit treats vectors that were written by another transaction
+/// since the committing one was opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// The commit is applied over the other writes, as the versions are
+    /// indexed in commit order
+    #[default]
+    LastCommitterWins,
+    /// The transaction is aborted instead of being committed
+    Abort,
+}
+
+// Operations of an explicit transaction are written through to
+// `txn_{id}.wal` in the collection's directory, which is renamed to the
+// WAL of the version it's committed as. The ids of the open transactions
+// are kept in lmdb, so that they are restored on restart.
+//
+// Several transactions can be open at once, each one gets its version when
+// it's committed. The ids of the vectors it writes are tracked to detect
+// conflicts with the transactions committed since it started.
 pub struct ExplicitTransaction {
     pub id: ExplicitTransactionID,
-    pub wal: WALFile,
+    // last version allotted when the transaction was opened
+    pub start_version: VersionNumber,
+    wal: Mutex<DurableWALFile>,
+    written_ids: Mutex<HashSet<VectorId>>,
+    // (the version checked by the `if_version` precondition of a write,
+    // `None` for `if_absent`, and the last version allotted when it was
+    // checked) by vector, checked again on commit
+    preconditions: Mutex<HashMap<VectorId, (Option<u32>, u32)>>,
+    last_activity: Mutex<Instant>,
 }
 
 impl ExplicitTransaction {
+    /// Opens a transaction, which must be done while holding the write lock
+    /// of `collection.open_explicit_transactions`, so that commits see it
+    /// once its start version is taken
     pub fn new(collection: &Collection, config: &Config) -> Result<Self, WaCustomError> {
         let mut current_implicit_txn = collection.current_implicit_transaction.write();
         mem::take(&mut *current_implicit_txn).pre_commit(collection, config)?;
+        let start_version = *collection.last_allotted_version.read();
         let id = ExplicitTransactionID(random());
+        let wal = DurableWALFile::create(&Self::wal_path(collection, id))?;
+        add_open_transaction(&collection.lmdb, *id, start_version)?;
         collection.transaction_status_map.insert(
             *collection.current_version.read(),
             &id,
@@ -159,17 +206,209 @@ impl ExplicitTransaction {
         );
         Ok(Self {
             id,
-            wal: WALFile::new()?,
+            start_version,
+            wal: Mutex::new(wal),
+            written_ids: Mutex::new(HashSet::new()),
+            preconditions: Mutex::new(HashMap::new()),
+            last_activity: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Reopens a transaction that was open when the server stopped. Its
+    /// idle time starts over.
+    pub fn restore(
+        collection: &Collection,
+        id: ExplicitTransactionID,
+        start_version: VersionNumber,
+    ) -> Result<Self, WaCustomError> {
+        let wal_path = Self::wal_path(collection, id);
+        let mut written_ids = HashSet::new();
+        let written = WALFile::open(&wal_path)?;
+        while let Some(op) = written.read()? {
+            written_ids.extend(Self::op_ids(&op));
+        }
+        drop(written);
+        let wal = DurableWALFile::from_existing(&wal_path)?;
+        let preconditions = match fs::read(Self::preconditions_path(collection, id)) {
+            Ok(bytes) => serde_cbor::from_slice(&bytes)
+                .map_err(|e| WaCustomError::DeserializationError(e.to_string()))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(WaCustomError::FsError(err.to_string())),
+        };
+        // the status is only persisted with the next flush of the collection
+        if collection.transaction_status_map.get_latest(&id).is_none() {
+            collection.transaction_status_map.insert(
+                *collection.current_version.read(),
+                &id,
+                RwLock::new(TransactionStatus::NotStarted {
+                    last_updated: Utc::now(),
+                }),
+            );
+        }
+        Ok(Self {
+            id,
+            start_version,
+            wal: Mutex::new(wal),
+            written_ids: Mutex::new(written_ids),
+            preconditions: Mutex::new(preconditions),
+            last_activity: Mutex::new(Instant::now()),
         })
     }
 
+    fn wal_path(collection: &Collection, id: ExplicitTransactionID) -> PathBuf {
+        collection.get_path().join(format!("txn_{}.wal", *id))
+    }
+
+    fn preconditions_path(collection: &Collection, id: ExplicitTransactionID) -> PathBuf {
+        collection
+            .get_path()
+            .join(format!("txn_{}.preconditions", *id))
+    }
+
+    /// Records the versions checked by the preconditions of a write, `None`
+    /// for vectors that must not exist, so that they're checked again on
+    /// commit. They're persisted along with the WAL.
+    pub fn add_preconditions(
+        &self,
+        collection: &Collection,
+        checked: impl IntoIterator<Item = (VectorId, Option<u32>)>,
+    ) -> Result<(), WaCustomError> {
+        let checked_at = **collection.last_allotted_version.read();
+        let mut checked = checked.into_iter().peekable();
+        if checked.peek().is_none() {
+            return Ok(());
+        }
+        let mut preconditions = self.preconditions.lock();
+        preconditions.extend(checked.map(|(id, version)| (id, (version, checked_at))));
+        let bytes = serde_cbor::to_vec(&*preconditions)
+            .map_err(|e| WaCustomError::SerializationError(e.to_string()))?;
+        let path = Self::preconditions_path(collection, self.id);
+        let tmp_path = path.with_extension("preconditions.tmp");
+        fs::write(&tmp_path, bytes)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .map_err(|e| WaCustomError::FsError(e.to_string()))
+    }
+
+    /// Finds a precondition of the transaction's writes that no longer
+    /// holds, because the vector was written since it was checked, either
+    /// by a transaction that is yet to be indexed or by an indexed one.
+    /// Must be called while holding the write lock of
+    /// `collection.open_explicit_transactions`.
+    pub fn find_failed_precondition(&self, collection: &Collection) -> Option<VectorId> {
+        let preconditions = self.preconditions.lock();
+        let recent_commits = collection.recent_commits.lock();
+        preconditions
+            .iter()
+            .find(|(id, (version, checked_at))| {
+                // writes committed after the checked version, or after the
+                // check for vectors that didn't exist
+                let written_after = version.unwrap_or(*checked_at);
+                recent_commits.iter().any(|(commit_version, ids)| {
+                    **commit_version > written_after && ids.contains(id)
+                }) || collection.vector_version(id).map(|version| *version) != *version
+            })
+            .map(|(id, _)| id.clone())
+    }
+
+    fn remove_preconditions(
+        collection: &Collection,
+        id: ExplicitTransactionID,
+    ) -> Result<(), WaCustomError> {
+        match fs::remove_file(Self::preconditions_path(collection, id)) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(WaCustomError::FsError(err.to_string())),
+        }
+    }
+
+    fn op_ids(op: &VectorOp) -> Vec<VectorId> {
+        match op {
+            VectorOp::Upsert(embeddings) => embeddings
+                .iter()
+                .map(|embedding| embedding.id.clone())
+                .collect(),
+            VectorOp::Delete(vector_id) => vec![vector_id.clone()],
+        }
+    }
+
+    pub fn append_to_wal(&self, op: VectorOp) -> Result<(), WaCustomError> {
+        let ids = Self::op_ids(&op);
+        self.wal.lock().append(op)?;
+        self.written_ids.lock().extend(ids);
+        *self.last_activity.lock() = Instant::now();
+        Ok(())
+    }
+
+    pub fn written_ids(&self) -> HashSet<VectorId> {
+        self.written_ids.lock().clone()
+    }
+
+    /// Finds a vector written by the transaction that was also written by
+    /// a version committed after it started, either by a transaction that
+    /// is yet to be indexed or by an indexed one
+    pub fn find_conflict(&self, collection: &Collection) -> Option<VectorId> {
+        let written_ids = self.written_ids.lock();
+        let recent_commits = collection.recent_commits.lock();
+        let committed_since = recent_commits
+            .iter()
+            .filter(|(version, _)| **version > *self.start_version);
+        for (_, ids) in committed_since {
+            if let Some(id) = ids.intersection(&written_ids).next() {
+                return Some(id.clone());
+            }
+        }
+        written_ids
+            .iter()
+            .find(|id| {
+                collection
+                    .external_to_internal_map
+                    .get_versioned(id)
+                    .is_some_and(|item| *item.latest_item().version > *self.start_version)
+            })
+            .cloned()
+    }
+
+    /// Time since the transaction was opened or restored, or its last
+    /// operation
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.lock().elapsed()
+    }
+
+    pub fn records_upserted(&self) -> u32 {
+        self.wal.lock().records_upserted()
+    }
+
+    pub fn records_deleted(&self) -> u32 {
+        self.wal.lock().records_deleted()
+    }
+
+    pub fn total_operations(&self) -> u32 {
+        self.wal.lock().total_operations()
+    }
+
     pub fn pre_commit(
         self,
         collection: &Collection,
         version: VersionNumber,
     ) -> Result<(), WaCustomError> {
-        self.wal.flush(&collection.get_path(), version)?;
-        Ok(())
+        let wal_path = Self::wal_path(collection, self.id);
+        self.wal.into_inner().flush()?;
+        fs::rename(
+            wal_path,
+            collection.get_path().join(format!("{}.wal", *version)),
+        )
+        .map_err(|e| WaCustomError::FsError(e.to_string()))?;
+        remove_open_transaction(&collection.lmdb, *self.id)?;
+        Self::remove_preconditions(collection, self.id)
+    }
+
+    /// Discards the transaction along with its WAL
+    pub fn abort(self, collection: &Collection) -> Result<(), WaCustomError> {
+        let wal_path = Self::wal_path(collection, self.id);
+        Self::remove_preconditions(collection, self.id)?;
+        drop(self.wal);
+        remove_open_transaction(&collection.lmdb, *self.id)?;
+        fs::remove_file(wal_path).map_err(|e| WaCustomError::FsError(e.to_string()))
     }
 }
 
@@ -273,8 +512,7 @@ impl ImplicitTransaction {
             wal.total_operations(),
         )?;
         drop(wal);
-        fs::remove_file(collection.get_path().join(format!("{}.wal", *data.version)))
-            .map_err(BufIoError::Io)?;
+        replication::retire_wal(collection, config, data.version)?;
         Ok(())
     }
 }
@@ -0,0 +1,87 @@
+// Background compaction of collections
+//
+// Every `retention.compaction_interval` seconds each collection is compacted
+// according to the retention policy. Collections with a transaction in
+// progress are skipped until the next run.
+
+use std::{sync::Arc, thread, time::Duration};
+
+use crate::app_context::AppContext;
+
+use super::{common::WaCustomError, recovery};
+
+/// Starts the background compactor, unless it's disabled by the config
+pub fn spawn_compactor(ctx: Arc<AppContext>) {
+    let Some(interval) = ctx
+        .config
+        .retention
+        .compaction_interval
+        .filter(|interval| *interval > 0)
+    else {
+        return;
+    };
+
+    thread::Builder::new()
+        .name("compactor".to_string())
+        .spawn(move || {
+            if !recovery::wait_until_ready() {
+                return;
+            }
+            loop {
+                thread::sleep(Duration::from_secs(interval));
+                compact_all(&ctx);
+            }
+        })
+        .expect("Failed to spawn the compactor thread");
+}
+
+fn compact_all(ctx: &AppContext) {
+    // collected first, so that no shard of the map stays locked while
+    // compacting
+    let collections: Vec<_> = ctx
+        .ain_env
+        .collections_map
+        .iter_collections()
+        .map(|collection| collection.value().clone())
+        .collect();
+
+    for collection in collections {
+        let result = collection.compact(
+            &ctx.ain_env.collections_map,
+            &ctx.config,
+            &ctx.config.retention,
+        );
+        match result {
+            Ok(Some(stats)) => {
+                log::info!(
+                    "Compacted collection '{}', removed {} versions, disk usage {} -> {} bytes",
+                    collection.meta.name,
+                    stats.versions_removed,
+                    stats.disk_usage_before,
+                    stats.disk_usage_after
+                );
+                if let Err(err) = ctx
+                    .collection_cache_manager
+                    .reload_collection(&collection.meta.name)
+                {
+                    log::error!(
+                        "Failed to reload collection '{}': {}",
+                        collection.meta.name,
+                        err
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(WaCustomError::LockError(msg)) => log::debug!(
+                "Skipped compaction of collection '{}': {}",
+                collection.meta.name,
+                msg
+            ),
+            Err(err) => log::error!(
+                "Failed to compact collection '{}': {}",
+                collection.meta.name,
+                err
+            ),
+        }
+    }
+}
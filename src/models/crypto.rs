@@ -1,7 +1,11 @@
 // Cryptographic utility functions and data types
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-use ring::{constant_time::verify_slices_are_equal, digest, hmac};
+use ring::{
+    constant_time::verify_slices_are_equal,
+    digest, hmac,
+    rand::{SecureRandom, SystemRandom},
+};
 use rkyv::Infallible;
 use std::{
     str::FromStr,
@@ -157,10 +161,39 @@ pub fn create_session(
     (access_token, timestamp)
 }
 
+// Prefix of every API key, used to tell them apart from session tokens
+pub const API_KEY_PREFIX: &str = "cdk_";
+
+// Generates a random URL-safe base64 token from `len` random bytes
+pub fn generate_random_token(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to generate random bytes");
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// Creates a new API key, returned along with its id
+// Format: `cdk_<id>.<secret>`, only the double hash of the whole key is
+// persisted, the id is used to look it up
+pub fn create_api_key() -> (String, String) {
+    let id = generate_random_token(9);
+    let secret = generate_random_token(32);
+    let api_key = format!("{}{}.{}", API_KEY_PREFIX, id, secret);
+    (id, api_key)
+}
+
+// Extracts the id from an API key, returns `None` if it is not one
+pub fn parse_api_key_id(api_key: &str) -> Option<&str> {
+    let (id, secret) = api_key.strip_prefix(API_KEY_PREFIX)?.split_once('.')?;
+    if id.is_empty() || secret.is_empty() {
+        return None;
+    }
+    Some(id)
+}
+
 #[cfg(test)]
 mod tests {
-    use ring::rand::{SecureRandom, SystemRandom};
-
     use super::*;
 
     fn random_bytes() -> [u8; 32] {
@@ -178,4 +211,13 @@ mod tests {
         let single_step_double_hash = DoubleSHA256Hash::new(&input);
         assert_eq!(two_step_double_hash.0, single_step_double_hash.0);
     }
+
+    #[test]
+    fn test_api_key_id_round_trip() {
+        let (id, api_key) = create_api_key();
+        assert!(api_key.starts_with(API_KEY_PREFIX));
+        assert_eq!(parse_api_key_id(&api_key), Some(id.as_str()));
+        assert_eq!(parse_api_key_id("not-an-api-key"), None);
+        assert_eq!(parse_api_key_id("cdk_missing-secret"), None);
+    }
 }
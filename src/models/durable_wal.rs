@@ -4,7 +4,8 @@ use crate::metadata::FieldValue;
 
 use super::{
     buffered_io::{BufIoError, BufferManager},
-    serializer::write_len,
+    serializer::{write_len, write_vector_id},
+    storage_backend,
     versioning::VersionNumber,
     wal::VectorOp,
 };
@@ -20,18 +21,22 @@ pub struct DurableWALFile {
 impl DurableWALFile {
     pub fn new(root_path: &Path, version: VersionNumber) -> Result<Self, BufIoError> {
         let file_path: Arc<Path> = root_path.join(format!("{}.wal", *version)).into();
+        Self::create(&file_path)
+    }
 
+    pub fn create(file_path: &Path) -> Result<Self, BufIoError> {
         let file = OpenOptions::new()
             .write(true)
             .read(true)
             .create(true)
             .truncate(false)
-            .open(&file_path)?;
+            .open(file_path)?;
         let bufman = BufferManager::new(file, 8192)?;
         let cursor = bufman.open_cursor()?;
         bufman.update_u32_with_cursor(cursor, 0)?;
         bufman.update_u32_with_cursor(cursor, 0)?;
         bufman.update_u32_with_cursor(cursor, 0)?;
+        bufman.flush()?;
 
         Ok(Self {
             bufman,
@@ -42,6 +47,26 @@ impl DurableWALFile {
         })
     }
 
+    /// Reopens a WAL file written by an earlier process, further ops are
+    /// appended after the ones it already has
+    pub fn from_existing(file_path: &Path) -> Result<Self, BufIoError> {
+        storage_backend::backend().fetch(file_path)?;
+        let file = OpenOptions::new().write(true).read(true).open(file_path)?;
+        let bufman = BufferManager::new(file, 8192)?;
+        let cursor = bufman.open_cursor()?;
+        let records_upserted = bufman.read_u32_with_cursor(cursor)?;
+        let records_deleted = bufman.read_u32_with_cursor(cursor)?;
+        let total_operations = bufman.read_u32_with_cursor(cursor)?;
+
+        Ok(Self {
+            bufman,
+            cursor,
+            records_upserted,
+            records_deleted,
+            total_operations,
+        })
+    }
+
     pub fn records_upserted(&self) -> u32 {
         self.records_upserted
     }
@@ -76,8 +101,7 @@ impl DurableWALFile {
                 self.records_upserted += vectors.len() as u32;
                 write_len(&mut buf, vectors.len() as u32);
                 for vector in &*vectors {
-                    write_len(&mut buf, vector.id.len() as u32);
-                    buf.extend(vector.id.as_bytes());
+                    write_vector_id(&mut buf, &vector.id, vector.expires_at);
                     if let Some(document_id) = &vector.document_id {
                         write_len(&mut buf, document_id.len() as u32);
                         buf.extend(document_id.as_bytes());
@@ -135,6 +159,7 @@ impl DurableWALFile {
                 buf[0..4].copy_from_slice(&len.to_le_bytes());
             }
             VectorOp::Delete(id) => {
+                self.records_deleted += 1;
                 write_len(&mut buf, id.len() as u32);
                 buf.extend(id.as_bytes());
                 let len = buf.len() as u32 - 4;
@@ -142,6 +167,8 @@ impl DurableWALFile {
             }
         }
 
+        self.total_operations += 1;
+
         self.bufman.write_to_end_of_file(self.cursor, &buf)?;
         let cursor = self.bufman.open_cursor()?;
         self.bufman
@@ -163,6 +190,7 @@ mod tests {
     use crate::models::collection::RawVectorEmbedding;
     use crate::models::types::VectorId;
     use crate::{indexes::inverted::types::SparsePair, models::wal::WALFile};
+    use chrono::DateTime;
     use rand::{distributions::Alphanumeric, thread_rng, Rng};
     use std::collections::HashMap;
     use tempfile::tempdir;
@@ -203,6 +231,9 @@ mod tests {
                     .collect(),
             ),
             text: Some(random_string(16)),
+            expires_at: rng
+                .gen_bool(0.5)
+                .then(|| DateTime::from_timestamp_millis(rng.gen_range(0..1 << 42)).unwrap()),
         }
     }
 
@@ -308,13 +339,45 @@ mod tests {
 
         for (expected, actual) in entries.iter().zip(ops.iter()) {
             match (expected, actual) {
-                (VectorOp::Upsert(ev), VectorOp::Upsert(rv)) => assert_eq!(ev[0].id, rv[0].id),
+                (VectorOp::Upsert(ev), VectorOp::Upsert(rv)) => {
+                    assert_eq!(ev[0].id, rv[0].id);
+                    assert_eq!(ev[0].expires_at, rv[0].expires_at);
+                }
                 (VectorOp::Delete(eid), VectorOp::Delete(rid)) => assert_eq!(eid, rid),
                 _ => panic!("Mismatched operation types"),
             }
         }
     }
 
+    #[test]
+    fn test_reopen_and_append() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("txn.wal");
+        let del_id = VectorId::from(random_string(10));
+
+        {
+            let mut wal = DurableWALFile::create(&path).unwrap();
+            wal.append(VectorOp::Upsert(vec![random_vector(), random_vector()]))
+                .unwrap();
+        }
+
+        let mut wal = DurableWALFile::from_existing(&path).unwrap();
+        assert_eq!(wal.records_upserted(), 2);
+        assert_eq!(wal.total_operations(), 1);
+        wal.append(VectorOp::Delete(del_id.clone())).unwrap();
+        assert_eq!(wal.records_deleted(), 1);
+        assert_eq!(wal.total_operations(), 2);
+        wal.flush().unwrap();
+
+        std::fs::rename(&path, dir.path().join("0.wal")).unwrap();
+        let wal = WALFile::from_existing(dir.path(), VersionNumber::from(0)).unwrap();
+        assert_eq!(wal.records_upserted(), 2);
+        assert_eq!(wal.records_deleted(), 1);
+        assert!(matches!(wal.read().unwrap(), Some(VectorOp::Upsert(vecs)) if vecs.len() == 2));
+        assert!(matches!(wal.read().unwrap(), Some(VectorOp::Delete(id)) if id == del_id));
+        assert!(wal.read().unwrap().is_none());
+    }
+
     #[test]
     fn test_durability() {
         let dir = tempdir().unwrap();
@@ -0,0 +1,362 @@
+// Server-side embedding of text
+//
+// The embedding providers are configured on the server, by name, and built
+// once at startup. The dense and sparse vectors of a collection can each
+// use one of them, by name. Vectors upserted with a `text` but without
+// values then get them computed by the provider, and searches can take a
+// `query_text` instead of a query vector. Providers are called from
+// blocking threads, with all the texts of a request at once. Local models
+// run on the CPU with candle, they're only available with the
+// `local-embeddings` feature.
+
+use std::{collections::HashMap, io, path::PathBuf, sync::Arc};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use url::{Position, Url};
+use utoipa::ToSchema;
+
+use crate::indexes::inverted::types::SparsePair;
+
+#[cfg(feature = "local-embeddings")]
+use super::local_embeddings::{BertEmbeddingProvider, SpladeEmbeddingProvider};
+use super::{
+    collection::{DenseVectorOptions, SparseVectorOptions},
+    http_client::HttpClient,
+};
+
+/// Embedding provider of the dense or sparse vectors of a collection
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EmbeddingConfig {
+    /// Name of a provider configured on the server
+    pub provider: String,
+}
+
+/// Embedding provider configured on the server
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EmbeddingProviderConfig {
+    /// OpenAI-compatible embeddings endpoint, as served by vLLM or
+    /// text-embeddings-inference. Sparse embeddings are expected as
+    /// `{"indices": [...], "values": [...]}` objects.
+    Http {
+        /// URL of the endpoint, e.g. `http://localhost:8000/v1/embeddings`
+        url: String,
+        /// Model sent with the requests
+        #[serde(default)]
+        model: Option<String>,
+        /// Environment variable holding the API key, sent as a bearer token
+        #[serde(default)]
+        api_key_env: Option<String>,
+    },
+    /// Local BERT sentence embedding model of dense vectors, e.g.
+    /// all-MiniLM-L6-v2
+    Bert {
+        /// Directory with the `config.json`, `tokenizer.json` and
+        /// `model.safetensors` files of the model, relative to the data
+        /// directory
+        model_dir: PathBuf,
+        /// How the token embeddings are pooled into the embedding of the
+        /// text, which is then normalized
+        #[serde(default)]
+        pooling: Pooling,
+    },
+    /// Local SPLADE model of sparse vectors, e.g.
+    /// splade-cocondenser-ensembledistil, whose terms are the ids of the
+    /// tokens of its vocabulary
+    Splade {
+        /// Directory with the `config.json`, `tokenizer.json` and
+        /// `model.safetensors` files of the model, relative to the data
+        /// directory
+        model_dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pooling {
+    /// Mean of the token embeddings, as sentence-transformers models do
+    #[default]
+    Mean,
+    /// Embedding of the `[CLS]` token, as BGE models do
+    Cls,
+}
+
+impl EmbeddingProviderConfig {
+    fn provider(&self) -> io::Result<Arc<dyn EmbeddingProvider>> {
+        match self {
+            Self::Http {
+                url,
+                model,
+                api_key_env,
+            } => Ok(Arc::new(HttpEmbeddingProvider::new(
+                url,
+                model.clone(),
+                api_key_env.as_deref(),
+            )?)),
+            #[cfg(feature = "local-embeddings")]
+            Self::Bert { model_dir, pooling } => {
+                Ok(Arc::new(BertEmbeddingProvider::new(model_dir, *pooling)?))
+            }
+            #[cfg(feature = "local-embeddings")]
+            Self::Splade { model_dir } => Ok(Arc::new(SpladeEmbeddingProvider::new(model_dir)?)),
+            #[cfg(not(feature = "local-embeddings"))]
+            Self::Bert { .. } | Self::Splade { .. } => Err(invalid_input(
+                "Local models need the server to be built with the `local-embeddings` feature"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+pub trait EmbeddingProvider: Send + Sync {
+    /// Whether the provider computes dense embeddings
+    fn is_dense(&self) -> bool;
+
+    /// Whether the provider computes sparse embeddings
+    fn is_sparse(&self) -> bool;
+
+    /// Dimension of the dense embeddings, if known before any is computed
+    fn dense_dimension(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the dense embedding of each text, in order
+    fn embed_dense(&self, texts: &[&str]) -> io::Result<Vec<Vec<f32>>>;
+
+    /// Returns the sparse embedding of each text, in order
+    fn embed_sparse(&self, texts: &[&str]) -> io::Result<Vec<Vec<SparsePair>>>;
+}
+
+pub(super) fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+pub(super) fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// The embedding providers configured on the server, by name
+#[derive(Default)]
+pub struct EmbeddingProviders(HashMap<String, Arc<dyn EmbeddingProvider>>);
+
+impl EmbeddingProviders {
+    /// Builds the providers of the `[embeddings]` config section
+    pub fn new(configs: &HashMap<String, EmbeddingProviderConfig>) -> io::Result<Self> {
+        configs
+            .iter()
+            .map(|(name, config)| {
+                let provider = config.provider().map_err(|err| {
+                    io::Error::new(
+                        err.kind(),
+                        format!("Failed to set up embedding provider `{}`: {}", name, err),
+                    )
+                })?;
+                Ok((name.clone(), provider))
+            })
+            .collect::<io::Result<_>>()
+            .map(Self)
+    }
+
+    fn get(&self, config: &EmbeddingConfig) -> io::Result<&Arc<dyn EmbeddingProvider>> {
+        self.0.get(&config.provider).ok_or_else(|| {
+            invalid_input(format!(
+                "No embedding provider `{}` configured on the server",
+                config.provider
+            ))
+        })
+    }
+
+    /// Checks that the providers of a new collection are configured, and
+    /// compute the kind of embeddings they are used for
+    pub fn validate(
+        &self,
+        dense_vector: &DenseVectorOptions,
+        sparse_vector: &SparseVectorOptions,
+    ) -> io::Result<()> {
+        if let Some(config) = &dense_vector.embedding {
+            let provider = self.get(config)?;
+            if !provider.is_dense() {
+                return Err(invalid_input(format!(
+                    "Embedding provider `{}` doesn't compute dense embeddings",
+                    config.provider
+                )));
+            }
+            if let Some(dimension) = provider
+                .dense_dimension()
+                .filter(|dimension| *dimension != dense_vector.dimension)
+            {
+                return Err(invalid_input(format!(
+                    "Embedding provider `{}` computes embeddings of dimension {}",
+                    config.provider, dimension
+                )));
+            }
+        }
+        if let Some(config) = &sparse_vector.embedding {
+            if !self.get(config)?.is_sparse() {
+                return Err(invalid_input(format!(
+                    "Embedding provider `{}` doesn't compute sparse embeddings",
+                    config.provider
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Embeds `texts` with the provider of the dense vectors, checking the
+    /// dimension of the embeddings
+    pub fn embed_dense(
+        &self,
+        options: &DenseVectorOptions,
+        texts: &[&str],
+    ) -> io::Result<Vec<Vec<f32>>> {
+        let config = options
+            .embedding
+            .as_ref()
+            .ok_or_else(|| invalid_input("No dense embedding provider configured".to_string()))?;
+        let embeddings = self.get(config)?.embed_dense(texts)?;
+        if let Some(embedding) = embeddings
+            .iter()
+            .find(|embedding| embedding.len() != options.dimension)
+        {
+            return Err(invalid_data(format!(
+                "Embedding provider returned {} dimensions instead of {}",
+                embedding.len(),
+                options.dimension
+            )));
+        }
+        Ok(embeddings)
+    }
+
+    /// Embeds `texts` with the provider of the sparse vectors
+    pub fn embed_sparse(
+        &self,
+        options: &SparseVectorOptions,
+        texts: &[&str],
+    ) -> io::Result<Vec<Vec<SparsePair>>> {
+        let config = options
+            .embedding
+            .as_ref()
+            .ok_or_else(|| invalid_input("No sparse embedding provider configured".to_string()))?;
+        self.get(config)?.embed_sparse(texts)
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse<T> {
+    data: Vec<EmbeddingData<T>>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData<T> {
+    #[serde(default)]
+    index: usize,
+    embedding: T,
+}
+
+#[derive(Deserialize)]
+struct SparseEmbedding {
+    indices: Vec<u32>,
+    values: Vec<f32>,
+}
+
+struct HttpEmbeddingProvider {
+    client: HttpClient,
+    path: String,
+    model: Option<String>,
+    api_key: Option<String>,
+}
+
+impl HttpEmbeddingProvider {
+    fn new(url: &str, model: Option<String>, api_key_env: Option<&str>) -> io::Result<Self> {
+        let url = Url::parse(url)
+            .map_err(|err| invalid_input(format!("Invalid URL `{}`: {}", url, err)))?;
+        let client = HttpClient::new(&url[..Position::BeforePath], None)?;
+        let api_key = api_key_env
+            .map(|var| {
+                std::env::var(var)
+                    .map_err(|_| invalid_input(format!("Environment variable `{}` isn't set", var)))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            client,
+            path: url[Position::BeforePath..].to_string(),
+            model,
+            api_key,
+        })
+    }
+
+    // sends `texts` to the endpoint and returns their embeddings, in order
+    fn embed<T: DeserializeOwned>(&self, texts: &[&str]) -> io::Result<Vec<T>> {
+        let mut body = json!({ "input": texts });
+        if let Some(model) = &self.model {
+            body["model"] = json!(model);
+        }
+        let body = body.to_string();
+        let mut headers = vec![("content-type", "application/json".to_string())];
+        if let Some(api_key) = &self.api_key {
+            headers.push(("authorization", format!("Bearer {}", api_key)));
+        }
+
+        let response = self.client.send(
+            "POST",
+            &self.path,
+            &headers,
+            Some((&mut body.as_bytes(), body.len() as u64)),
+        )?;
+        if !response.is_success() {
+            return Err(io::Error::other(format!(
+                "Embedding provider responded with status {}: {}",
+                response.status,
+                String::from_utf8_lossy(&response.body)
+            )));
+        }
+        let mut data = serde_json::from_slice::<EmbeddingsResponse<T>>(&response.body)
+            .map_err(|err| invalid_data(format!("Invalid embeddings response: {}", err)))?
+            .data;
+        if data.len() != texts.len() {
+            return Err(invalid_data(format!(
+                "Embedding provider returned {} embeddings for {} texts",
+                data.len(),
+                texts.len()
+            )));
+        }
+        data.sort_by_key(|data| data.index);
+        Ok(data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+impl EmbeddingProvider for HttpEmbeddingProvider {
+    fn is_dense(&self) -> bool {
+        true
+    }
+
+    fn is_sparse(&self) -> bool {
+        true
+    }
+
+    fn embed_dense(&self, texts: &[&str]) -> io::Result<Vec<Vec<f32>>> {
+        self.embed(texts)
+    }
+
+    fn embed_sparse(&self, texts: &[&str]) -> io::Result<Vec<Vec<SparsePair>>> {
+        self.embed::<SparseEmbedding>(texts)?
+            .into_iter()
+            .map(|embedding| {
+                if embedding.indices.len() != embedding.values.len() {
+                    return Err(invalid_data(
+                        "Sparse embedding with different numbers of indices and values".to_string(),
+                    ));
+                }
+                Ok(embedding
+                    .indices
+                    .into_iter()
+                    .zip(embedding.values)
+                    .map(|(index, value)| SparsePair(index, value))
+                    .collect())
+            })
+            .collect()
+    }
+}
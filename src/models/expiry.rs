@@ -0,0 +1,88 @@
+// Background deletion of expired vectors
+//
+// Every `expiry.sweep_interval` seconds the vectors whose `expires_at` has
+// passed are deleted from each collection through its implicit transaction,
+// like streamed deletes. Searches skip expired vectors in the meantime.
+// Followers apply the deletes of their leader instead of sweeping.
+
+use std::{sync::Arc, thread, time::Duration};
+
+use chrono::Utc;
+
+use crate::config_loader::{Config, ReplicationRole};
+
+use super::{
+    collection::Collection, common::WaCustomError, indexing_manager::IndexingManager, recovery,
+    types::AppEnv,
+};
+
+/// Starts the background sweeper, unless it's disabled by the config
+pub fn spawn_expiry_sweeper(ain_env: Arc<AppEnv>, config: Arc<Config>) {
+    if config.expiry.sweep_interval == 0 || config.replication.role == ReplicationRole::Follower {
+        return;
+    }
+
+    thread::Builder::new()
+        .name("expiry-sweeper".to_string())
+        .spawn(move || {
+            if !recovery::wait_until_ready() {
+                return;
+            }
+            loop {
+                thread::sleep(Duration::from_secs(config.expiry.sweep_interval));
+                sweep_all(&ain_env, &config);
+            }
+        })
+        .expect("Failed to spawn the expiry sweeper thread");
+}
+
+fn sweep_all(ain_env: &AppEnv, config: &Config) {
+    // collected first, so that no shard of the map stays locked while
+    // deleting
+    let collections: Vec<_> = ain_env
+        .collections_map
+        .iter_collections()
+        .map(|collection| collection.value().clone())
+        .collect();
+
+    for collection in collections {
+        match sweep(&collection, config) {
+            Ok(0) => {}
+            Ok(deleted) => log::info!(
+                "Deleted {} expired vectors of collection '{}'",
+                deleted,
+                collection.meta.name
+            ),
+            Err(err) => log::error!(
+                "Failed to delete the expired vectors of collection '{}': {}",
+                collection.meta.name,
+                err
+            ),
+        }
+    }
+}
+
+fn sweep(collection: &Collection, config: &Config) -> Result<usize, WaCustomError> {
+    let now = Utc::now();
+    let expired_ids = collection.expired_vector_ids(now);
+    if expired_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let txn = collection.current_implicit_transaction.read();
+    let mut deleted = 0;
+    for vector_id in expired_ids {
+        // skips the vectors replaced since the scan
+        let is_expired = collection
+            .external_to_internal_map
+            .get_latest(&vector_id)
+            .and_then(|internal_id| collection.get_raw_emb_by_internal_id(internal_id))
+            .is_some_and(|raw_emb| raw_emb.is_expired(now));
+        if !is_expired {
+            continue;
+        }
+        IndexingManager::implicit_txn_delete(collection, &txn, config, vector_id)?;
+        deleted += 1;
+    }
+    Ok(deleted)
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+/// `f32` values deserialized from either a sequence of numbers, or a byte
+/// string holding the values as little-endian `f32`s, as sent in binary
+/// formats like CBOR to avoid formatting and parsing the values as text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct F32Values(pub Vec<f32>);
+
+struct F32ValuesVisitor;
+
+impl<'de> Visitor<'de> for F32ValuesVisitor {
+    type Value = F32Values;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of numbers, or a byte string of little-endian f32s")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(F32Values(values))
+    }
+
+    fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if bytes.len() % 4 != 0 {
+            return Err(E::invalid_length(bytes.len(), &"a multiple of 4 bytes"));
+        }
+        Ok(F32Values(
+            bytes
+                .chunks_exact(4)
+                .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                .collect(),
+        ))
+    }
+}
+
+impl<'de> Deserialize<'de> for F32Values {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(F32ValuesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_cbor::Value;
+
+    use super::*;
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_deserialize_f32_values() {
+        let values = vec![0.5, -1.25, f32::MAX];
+
+        let json: F32Values = serde_json::from_str("[0.5, -1.25, 3.4028235e38]").unwrap();
+        assert_eq!(json.0, values);
+
+        let cbor = serde_cbor::to_vec(&Value::Bytes(f32_bytes(&values))).unwrap();
+        assert_eq!(
+            serde_cbor::from_slice::<F32Values>(&cbor).unwrap().0,
+            values
+        );
+
+        let cbor = serde_cbor::to_vec(&values).unwrap();
+        assert_eq!(
+            serde_cbor::from_slice::<F32Values>(&cbor).unwrap().0,
+            values
+        );
+
+        let cbor = serde_cbor::to_vec(&Value::Bytes(vec![0; 5])).unwrap();
+        assert!(serde_cbor::from_slice::<F32Values>(&cbor).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+// Minimal blocking HTTP/1.1 client
+//
+// Used to talk to object stores and to the leader of a replicated
+// deployment, from threads outside of the actix runtime. A new connection
+// is opened for every request.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use url::{Host, Url};
+
+const SYSTEM_CA_FILES: [&str; 3] = [
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/cert.pem",
+];
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    // empty if the body was written to a sink
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+pub struct HttpClient {
+    host: Host,
+    port: u16,
+    tls: Option<Arc<ClientConfig>>,
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn malformed_response() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Malformed HTTP response")
+}
+
+/// Percent-encodes everything but unreserved characters, and `/` if
+/// `keep_slash` is set
+pub fn uri_encode(value: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Loads the certificates in `ca_file`, or in the system's bundle if `None`
+fn load_root_certs(ca_file: Option<&Path>) -> io::Result<RootCertStore> {
+    let path = match ca_file {
+        Some(path) => path.to_path_buf(),
+        None => SYSTEM_CA_FILES
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists())
+            .ok_or_else(|| invalid_input("No CA certificates found".to_string()))?,
+    };
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut BufReader::new(File::open(path)?)) {
+        roots
+            .add(cert?)
+            .map_err(|err| invalid_input(err.to_string()))?;
+    }
+    Ok(roots)
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Connection closed before the end of the response",
+        ));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// Copies a `Transfer-Encoding: chunked` body to `sink`
+fn copy_chunked(reader: &mut impl BufRead, sink: &mut dyn Write) -> io::Result<()> {
+    loop {
+        let line = read_line(reader)?;
+        let size_str = line.split(';').next().unwrap_or_default().trim();
+        let size = u64::from_str_radix(size_str, 16).map_err(|_| malformed_response())?;
+        if size == 0 {
+            // trailers, up to the final empty line
+            while !read_line(reader)?.is_empty() {}
+            return Ok(());
+        }
+        if io::copy(&mut reader.take(size), sink)? != size {
+            return Err(malformed_response());
+        }
+        if !read_line(reader)?.is_empty() {
+            return Err(malformed_response());
+        }
+    }
+}
+
+/// Reads a response to a `method` request, its body is written to `sink`
+pub(crate) fn read_response(
+    reader: &mut impl BufRead,
+    method: &str,
+    sink: &mut dyn Write,
+) -> io::Result<Response> {
+    let status = read_line(reader)?
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(malformed_response)?;
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let response = Response {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+
+    if method == "HEAD" || status == 204 || status == 304 || (100..200).contains(&status) {
+        return Ok(response);
+    }
+    if response
+        .header("transfer-encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        copy_chunked(reader, sink)?;
+    } else if let Some(len) = response.header("content-length") {
+        let len: u64 = len.parse().map_err(|_| malformed_response())?;
+        if io::copy(&mut reader.take(len), sink)? != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Response shorter than its length",
+            ));
+        }
+    } else {
+        match io::copy(reader, sink) {
+            Ok(_) => {}
+            // servers commonly close TLS connections without a close_notify
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(response)
+}
+
+impl HttpClient {
+    /// Creates a client for the server at `base_url`, e.g.
+    /// `https://127.0.0.1:8443` or `http://[::1]:9000`. Certificates of
+    /// https servers are verified against `ca_file`, or the system's bundle
+    /// if `None`.
+    pub fn new(base_url: &str, ca_file: Option<&Path>) -> io::Result<Self> {
+        let url = Url::parse(base_url)
+            .map_err(|err| invalid_input(format!("Invalid URL `{}`: {}", base_url, err)))?;
+        if url.path() != "/" || url.query().is_some() {
+            return Err(invalid_input(format!(
+                "URL `{}` can't have a path or query",
+                base_url
+            )));
+        }
+        let tls = match url.scheme() {
+            "http" => None,
+            "https" => Some(Arc::new(
+                ClientConfig::builder_with_provider(Arc::new(
+                    rustls::crypto::aws_lc_rs::default_provider(),
+                ))
+                .with_safe_default_protocol_versions()
+                .map_err(|err| invalid_input(err.to_string()))?
+                .with_root_certificates(load_root_certs(ca_file)?)
+                .with_no_client_auth(),
+            )),
+            scheme => {
+                return Err(invalid_input(format!(
+                    "Unsupported URL scheme `{}`",
+                    scheme
+                )))
+            }
+        };
+        let host = url
+            .host()
+            .ok_or_else(|| invalid_input(format!("URL `{}` has no host", base_url)))?
+            .to_owned();
+        // only http and https are accepted, which have default ports
+        let port = url.port_or_known_default().unwrap_or_default();
+
+        Ok(Self { host, port, tls })
+    }
+
+    /// Value of the `Host` header of the requests, IPv6 addresses being
+    /// enclosed in brackets
+    pub fn host_header(&self) -> String {
+        match (&self.tls, self.port) {
+            (None, 80) | (Some(_), 443) => self.host.to_string(),
+            _ => format!("{}:{}", self.host, self.port),
+        }
+    }
+
+    // the host to connect to and verify the certificate of, without the
+    // brackets of IPv6 addresses
+    fn host_name(&self) -> String {
+        match &self.host {
+            Host::Domain(domain) => domain.clone(),
+            Host::Ipv4(addr) => addr.to_string(),
+            Host::Ipv6(addr) => addr.to_string(),
+        }
+    }
+
+    fn connect(&self) -> io::Result<Box<dyn ReadWrite>> {
+        let host_name = self.host_name();
+        let stream = TcpStream::connect((host_name.as_str(), self.port))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        match &self.tls {
+            None => Ok(Box::new(stream)),
+            Some(config) => {
+                let server_name = ServerName::try_from(host_name)
+                    .map_err(|err| invalid_input(err.to_string()))?;
+                let connection =
+                    ClientConnection::new(config.clone(), server_name).map_err(io::Error::other)?;
+                Ok(Box::new(StreamOwned::new(connection, stream)))
+            }
+        }
+    }
+
+    /// Sends a request for `target`, the path and query of the URL, and
+    /// writes the body of the response to `sink`
+    pub fn send_to(
+        &self,
+        method: &str,
+        target: &str,
+        headers: &[(&str, String)],
+        body: Option<(&mut dyn Read, u64)>,
+        sink: &mut dyn Write,
+    ) -> io::Result<Response> {
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nhost: {}\r\n",
+            method,
+            target,
+            self.host_header()
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str(&format!(
+            "content-length: {}\r\nconnection: close\r\n\r\n",
+            body.as_ref().map_or(0, |(_, len)| *len)
+        ));
+
+        let mut stream = self.connect()?;
+        stream.write_all(request.as_bytes())?;
+        if let Some((reader, len)) = body {
+            let copied = io::copy(&mut reader.take(len), &mut stream)?;
+            if copied != len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Request body shorter than its length",
+                ));
+            }
+        }
+        stream.flush()?;
+
+        read_response(&mut BufReader::new(stream), method, sink)
+    }
+
+    /// Sends a request for `target`, the path and query of the URL
+    pub fn send(
+        &self,
+        method: &str,
+        target: &str,
+        headers: &[(&str, String)],
+        body: Option<(&mut dyn Read, u64)>,
+    ) -> io::Result<Response> {
+        let mut response_body = Vec::new();
+        let mut response = self.send_to(method, target, headers, body, &mut response_body)?;
+        response.body = response_body;
+        Ok(response)
+    }
+}
+
+trait ReadWrite: Read + Write {}
+
+impl<T: Read + Write> ReadWrite for T {}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{SocketAddr, TcpListener},
+        thread::{self, JoinHandle},
+    };
+
+    use super::*;
+
+    // Answers a single request on `listener` with `response`, returning the
+    // head of the request
+    fn serve(listener: TcpListener, response: &'static [u8]) -> JoinHandle<String> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            while reader.read_line(&mut head).unwrap() > 2 {}
+            reader.into_inner().write_all(response).unwrap();
+            head
+        })
+    }
+
+    fn client_for(addr: SocketAddr) -> HttpClient {
+        let base_url = match addr {
+            SocketAddr::V4(addr) => format!("http://{}", addr),
+            SocketAddr::V6(addr) => format!("http://[{}]:{}", addr.ip(), addr.port()),
+        };
+        HttpClient::new(&base_url, None).unwrap()
+    }
+
+    #[test]
+    fn test_parse_base_url() {
+        let client = HttpClient::new("http://[::1]:9000", None).unwrap();
+        assert_eq!(client.host_name(), "::1");
+        assert_eq!(client.port, 9000);
+        assert_eq!(client.host_header(), "[::1]:9000");
+
+        let client = HttpClient::new("http://example.com/", None).unwrap();
+        assert_eq!(
+            (client.host_name().as_str(), client.port),
+            ("example.com", 80)
+        );
+        assert_eq!(client.host_header(), "example.com");
+        let client = HttpClient::new("http://10.0.0.1:8443", None).unwrap();
+        assert_eq!(client.host_header(), "10.0.0.1:8443");
+
+        for base_url in [
+            "example.com:9000",
+            "ftp://example.com",
+            "http://example.com/path",
+            "http://example.com:99999",
+            "http://[::1",
+        ] {
+            let err = HttpClient::new(base_url, None).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", base_url);
+        }
+    }
+
+    #[test]
+    fn test_send_chunked_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = client_for(listener.local_addr().unwrap());
+        let server = serve(
+            listener,
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;name=value\r\nhello\r\n6\r\n world\r\n0\r\nx-checksum: 1\r\n\r\n",
+        );
+        let response = client.send("GET", "/chunked", &[], None).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"hello world");
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with("GET /chunked HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn test_send_over_ipv6() {
+        // IPv6 may be disabled where the tests run
+        let Ok(listener) = TcpListener::bind("[::1]:0") else {
+            return;
+        };
+        let addr = listener.local_addr().unwrap();
+        let client = client_for(addr);
+        let server = serve(listener, b"HTTP/1.1 204 No Content\r\n\r\n");
+        let response = client.send("DELETE", "/", &[], None).unwrap();
+        assert_eq!(response.status, 204);
+        let head = server.join().unwrap();
+        assert!(
+            head.contains(&format!("\r\nhost: [::1]:{}\r\n", addr.port())),
+            "{}",
+            head
+        );
+    }
+
+    #[test]
+    fn test_connection_errors() {
+        // nothing listens on the port of a closed listener
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = client_for(listener.local_addr().unwrap());
+        drop(listener);
+        let err = client.send("GET", "/", &[], None).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+
+        for (response, kind) in [
+            (&b""[..], io::ErrorKind::UnexpectedEof),
+            (
+                b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello",
+                io::ErrorKind::UnexpectedEof,
+            ),
+            (
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel",
+                io::ErrorKind::InvalidData,
+            ),
+            (
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+                io::ErrorKind::InvalidData,
+            ),
+        ] {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = client_for(listener.local_addr().unwrap());
+            // the connection is closed once the response is written
+            let server = serve(listener, response);
+            let err = client.send("GET", "/", &[], None).err().unwrap();
+            assert_eq!(err.kind(), kind, "{}", String::from_utf8_lossy(response));
+            server.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_read_chunked_response() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+        let mut body = Vec::new();
+        let response = read_response(&mut &raw[..], "GET", &mut body).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(body, b"hello world");
+    }
+
+    #[test]
+    fn test_read_head_response() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 42\r\n\r\n";
+        let mut body = Vec::new();
+        let response = read_response(&mut &raw[..], "HEAD", &mut body).unwrap();
+        assert_eq!(response.header("content-length"), Some("42"));
+        assert!(body.is_empty());
+    }
+}
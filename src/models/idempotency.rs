@@ -0,0 +1,128 @@
+// Responses of the writes sent with an `Idempotency-Key` header, persisted
+// in the `idempotency` LMDB database so that retries are recognized across
+// restarts for as long as the idempotency window lasts. Records are keyed by
+// the SHA256 hash of the request's user, method, path and key, and deleted
+// once expired.
+
+use std::{sync::Arc, thread, time::Duration};
+
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, Transaction, WriteFlags};
+use serde::{Deserialize, Serialize};
+
+use crate::config_loader::TransactionsConfig;
+
+use super::{
+    crypto::get_current_timestamp,
+    lmdb_map::{txn_guard, with_rw_txn},
+    types::AppEnv,
+};
+
+const IDEMPOTENCY_DB_NAME: &str = "idempotency";
+// interval between the removals of the expired responses
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotentResponse {
+    pub status: u16,
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Vec<u8>,
+    // SHA256 hash of the body of the request, retries must send the same
+    pub request_hash: [u8; 32],
+    // Unix timestamp in seconds
+    pub expires_at: u64,
+}
+
+pub struct IdempotentResponses {
+    env: Arc<Environment>,
+    db: Database,
+}
+
+impl IdempotentResponses {
+    pub fn new(env: Arc<Environment>) -> lmdb::Result<Self> {
+        let _guard = txn_guard();
+        let db = env.create_db(Some(IDEMPOTENCY_DB_NAME), DatabaseFlags::empty())?;
+        Ok(Self { env, db })
+    }
+
+    /// Returns the response stored for the key, unless it's expired.
+    /// Corrupt records are ignored, and replaced once the request is
+    /// handled again.
+    pub fn get(&self, key: &[u8; 32]) -> lmdb::Result<Option<IdempotentResponse>> {
+        let _guard = txn_guard();
+        let txn = self.env.begin_ro_txn()?;
+        let response = match txn.get(self.db, key) {
+            Ok(bytes) => match serde_cbor::from_slice::<IdempotentResponse>(bytes) {
+                Ok(response) => Some(response),
+                Err(err) => {
+                    log::warn!("Ignoring corrupt idempotent response: {}", err);
+                    None
+                }
+            },
+            Err(lmdb::Error::NotFound) => None,
+            Err(err) => return Err(err),
+        };
+        txn.abort();
+        let now = get_current_timestamp();
+        Ok(response.filter(|response| response.expires_at > now))
+    }
+
+    pub fn insert(&self, key: &[u8; 32], response: &IdempotentResponse) -> lmdb::Result<()> {
+        let bytes = serde_cbor::to_vec(response).map_err(|_| lmdb::Error::Invalid)?;
+        with_rw_txn(&self.env, |txn| {
+            txn.put(self.db, key, &bytes, WriteFlags::empty())
+        })
+    }
+
+    /// Deletes the expired and corrupt responses
+    pub fn prune_expired(&self) -> lmdb::Result<()> {
+        let now = get_current_timestamp();
+        // released before the deletes, which may grow the map
+        let expired: Vec<Vec<u8>> = {
+            let _guard = txn_guard();
+            let txn = self.env.begin_ro_txn()?;
+            let mut cursor = txn.open_ro_cursor(self.db)?;
+            let expired = cursor
+                .iter()
+                .filter(|(_, bytes)| {
+                    serde_cbor::from_slice::<IdempotentResponse>(bytes)
+                        .map_or(true, |response| response.expires_at <= now)
+                })
+                .map(|(key, _)| key.to_vec())
+                .collect();
+            drop(cursor);
+            txn.abort();
+            expired
+        };
+
+        if expired.is_empty() {
+            return Ok(());
+        }
+        with_rw_txn(&self.env, |txn| {
+            for key in &expired {
+                match txn.del(self.db, key, None) {
+                    Ok(()) | Err(lmdb::Error::NotFound) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Starts removing the expired responses, unless idempotency keys are
+/// ignored by the config
+pub fn spawn_idempotency_pruner(ain_env: Arc<AppEnv>, config: TransactionsConfig) {
+    if config.idempotency_window == 0 {
+        return;
+    }
+
+    thread::Builder::new()
+        .name("idempotency-pruner".to_string())
+        .spawn(move || loop {
+            thread::sleep(PRUNE_INTERVAL);
+            if let Err(err) = ain_env.idempotent_responses.prune_expired() {
+                log::warn!("Failed to remove the expired idempotent responses: {}", err);
+            }
+        })
+        .expect("Failed to spawn the idempotency pruner thread");
+}
@@ -0,0 +1,466 @@
+// Rebuilding the indexes of a collection
+//
+// The vectors are indexed again, under the internal ids they're stored
+// with, into new indexes written to the next generation of the index
+// directories, while the current indexes keep serving searches and writes.
+// Writes are then blocked while the vectors written meanwhile are caught up
+// on, and the new indexes replace the current ones, along with the metadata
+// schema the dense index is rebuilt for if any, in a single LMDB
+// transaction, so that a crash at any point leaves either of them in place.
+// The directories of the replaced indexes are removed once they're no
+// longer in use.
+
+use std::{
+    fs,
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    config_loader::Config,
+    indexes::{
+        hnsw::{DenseInputEmbedding, HNSWIndex},
+        inverted::{InvertedIndex, SparseInputEmbedding},
+        tf_idf::TFIDFIndex,
+        IndexOps,
+    },
+    metadata::{pseudo_node_vector, pseudo_root_id, MetadataSchema, SharedMetadataSchema},
+    vector_store::create_hnsw_index,
+};
+
+use super::{
+    collection::{Collection, RawVectorEmbedding},
+    common::WaCustomError,
+    types::{CollectionsMap, InternalId},
+    versioning::VersionNumber,
+};
+
+// number of vectors indexed at once
+const BATCH_SIZE: usize = 1000;
+
+// wait between attempts to block writes while transactions are in progress
+const BLOCK_WRITES_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Indexes {
+    hnsw_index: Option<Arc<HNSWIndex>>,
+    inverted_index: Option<Arc<InvertedIndex>>,
+    tf_idf_index: Option<Arc<TFIDFIndex>>,
+}
+
+impl Indexes {
+    fn of(collection: &Collection) -> Self {
+        Self {
+            hnsw_index: collection.get_hnsw_index(),
+            inverted_index: collection.get_inverted_index(),
+            tf_idf_index: collection.get_tf_idf_index(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.hnsw_index.is_none() && self.inverted_index.is_none() && self.tf_idf_index.is_none()
+    }
+
+    // whether the indexes are still the ones of the collection
+    fn is_current(&self, collection: &Collection) -> bool {
+        fn is_current<T>(index: &Option<Arc<T>>, current: Option<Arc<T>>) -> bool {
+            index
+                .as_ref()
+                .is_none_or(|index| current.is_some_and(|current| Arc::ptr_eq(index, &current)))
+        }
+        is_current(&self.hnsw_index, collection.get_hnsw_index())
+            && is_current(&self.inverted_index, collection.get_inverted_index())
+            && is_current(&self.tf_idf_index, collection.get_tf_idf_index())
+    }
+
+    // the directories are removed once the last reference to the indexes
+    // is dropped
+    fn retire(&self) {
+        if let Some(index) = &self.hnsw_index {
+            index.dir.retire();
+        }
+        if let Some(index) = &self.inverted_index {
+            index.dir.retire();
+        }
+        if let Some(index) = &self.tf_idf_index {
+            index.dir.retire();
+        }
+    }
+}
+
+/// Rebuilds the indexes of `collection` from the vectors it currently
+/// stores, which drops the nodes and postings of the deleted ones. Returns
+/// the no. of vectors the indexes were rebuilt without, see
+/// `Collection::base_node_count`.
+///
+/// The caller has to hold the collection's `compaction_lock`. Fails with
+/// `WaCustomError::LockError` if a transaction is in progress when writes
+/// are blocked to swap the indexes.
+pub fn rebuild_indexes(
+    collections_map: &CollectionsMap,
+    collection: &Collection,
+    config: &Config,
+) -> Result<u32, WaCustomError> {
+    rebuild(
+        collections_map,
+        collection,
+        config,
+        Indexes::of(collection),
+        None,
+        |_, _| {},
+    )
+}
+
+/// Rebuilds the dense index of `collection` for `metadata_schema`, which
+/// replaces the schema of the collection along with the index. `progress`
+/// is called with the no. of vectors indexed so far and their total.
+///
+/// The vectors keep their internal ids and versions. The caller has to
+/// hold the collection's `compaction_lock`, as for `rebuild_indexes`, but
+/// transactions in progress are waited for rather than failing the rebuild.
+pub fn rebuild_dense_index(
+    collections_map: &CollectionsMap,
+    collection: &Collection,
+    config: &Config,
+    metadata_schema: MetadataSchema,
+    progress: impl Fn(usize, usize),
+) -> Result<(), WaCustomError> {
+    let indexes = Indexes {
+        hnsw_index: collection.get_hnsw_index(),
+        inverted_index: None,
+        tf_idf_index: None,
+    };
+    rebuild(
+        collections_map,
+        collection,
+        config,
+        indexes,
+        Some(metadata_schema),
+        progress,
+    )
+    .map(|_| ())
+}
+
+fn rebuild(
+    collections_map: &CollectionsMap,
+    collection: &Collection,
+    config: &Config,
+    indexes: Indexes,
+    metadata_schema: Option<MetadataSchema>,
+    progress: impl Fn(usize, usize),
+) -> Result<u32, WaCustomError> {
+    if indexes.is_empty() {
+        return Ok(0);
+    }
+
+    // rebuilds for a new schema wait for the transactions in progress,
+    // rather than being done again
+    let wait = metadata_schema.is_some();
+    let (version, end, internal_ids) = block_writes(collection, config, &indexes, wait, || {
+        let end = collection.internal_id_counter.load(Ordering::Relaxed);
+        Ok((
+            *collection.current_version.read(),
+            end,
+            collection.live_internal_ids(0..end),
+        ))
+    })?;
+
+    let rebuilt = Indexes {
+        hnsw_index: indexes
+            .hnsw_index
+            .as_deref()
+            .map(|index| {
+                let metadata_schema = match &metadata_schema {
+                    Some(metadata_schema) => {
+                        SharedMetadataSchema::new(Some(metadata_schema.clone()))
+                    }
+                    None => index.metadata_schema.clone(),
+                };
+                new_hnsw_index(config, collection, index, metadata_schema, version)
+            })
+            .transpose()?,
+        inverted_index: indexes
+            .inverted_index
+            .as_deref()
+            .map(new_inverted_index)
+            .transpose()?,
+        tf_idf_index: indexes
+            .tf_idf_index
+            .as_deref()
+            .map(new_tf_idf_index)
+            .transpose()?,
+    };
+
+    let result = build(
+        collection,
+        config,
+        &rebuilt,
+        &internal_ids,
+        version,
+        progress,
+    )
+    .and_then(|_| {
+        block_writes(collection, config, &indexes, wait, || {
+            if !indexes.is_current(collection) {
+                return Err(WaCustomError::LockError(
+                    "Indexes of the collection changed while being rebuilt".to_string(),
+                ));
+            }
+            catch_up(
+                collection,
+                config,
+                &indexes,
+                &rebuilt,
+                &internal_ids,
+                version,
+                end,
+            )?;
+            collections_map.replace_indexes(
+                collection,
+                rebuilt.hnsw_index.clone(),
+                rebuilt.inverted_index.clone(),
+                rebuilt.tf_idf_index.clone(),
+                metadata_schema.clone(),
+            )
+        })
+    });
+    match result {
+        Ok(()) => indexes.retire(),
+        Err(_) => rebuilt.retire(),
+    }
+    result.map(|_| collection.base_node_count(end) - internal_ids.len() as u32)
+}
+
+// runs `f` with writes to the collection blocked, retrying while
+// transactions are in progress if `wait`, as long as `indexes` are current
+fn block_writes<T>(
+    collection: &Collection,
+    config: &Config,
+    indexes: &Indexes,
+    wait: bool,
+    mut f: impl FnMut() -> Result<T, WaCustomError>,
+) -> Result<T, WaCustomError> {
+    loop {
+        match collection.block_writes(config, &mut f) {
+            Err(WaCustomError::LockError(_)) if wait && indexes.is_current(collection) => {
+                thread::sleep(BLOCK_WRITES_RETRY_INTERVAL);
+            }
+            result => return result,
+        }
+    }
+}
+
+// indexes the vectors as they were at `version`
+fn build(
+    collection: &Collection,
+    config: &Config,
+    rebuilt: &Indexes,
+    internal_ids: &[InternalId],
+    version: VersionNumber,
+    progress: impl Fn(usize, usize),
+) -> Result<(), WaCustomError> {
+    let mut indexed = 0;
+    for batch in internal_ids.chunks(BATCH_SIZE) {
+        let embeddings = batch
+            .iter()
+            .filter_map(|internal_id| {
+                let item = collection
+                    .internal_to_external_map
+                    .get_versioned(internal_id)?;
+                let embedding = item.at(version)?.clone();
+                Some((*internal_id, embedding))
+            })
+            .collect();
+        index_vectors(collection, config, rebuilt, embeddings, version)?;
+        indexed += batch.len();
+        progress(indexed, internal_ids.len());
+    }
+    Ok(())
+}
+
+// applies the writes since `version`, `end` being the internal id counter
+// at the time, with writes blocked
+fn catch_up(
+    collection: &Collection,
+    config: &Config,
+    indexes: &Indexes,
+    rebuilt: &Indexes,
+    internal_ids: &[InternalId],
+    version: VersionNumber,
+    end: u32,
+) -> Result<(), WaCustomError> {
+    let current_version = *collection.current_version.read();
+    if let Some(hnsw_index) = &rebuilt.hnsw_index {
+        hnsw_index.offset_counter.write().unwrap().next_file_id();
+    }
+
+    // written vectors always get new internal ids
+    let new_end = collection.internal_id_counter.load(Ordering::Relaxed);
+    let embeddings = collection
+        .live_internal_ids(end..new_end)
+        .into_iter()
+        .filter_map(|internal_id| {
+            let embedding = collection.live_embedding(internal_id)?.clone();
+            Some((internal_id, embedding))
+        })
+        .collect();
+    index_vectors(collection, config, rebuilt, embeddings, current_version)?;
+
+    for internal_id in internal_ids {
+        if collection.live_embedding(*internal_id).is_some() {
+            continue;
+        }
+        let Some(item) = collection
+            .internal_to_external_map
+            .get_versioned(internal_id)
+        else {
+            continue;
+        };
+        let Some(embedding) = item.at(version) else {
+            continue;
+        };
+        if let Some(hnsw_index) = &rebuilt.hnsw_index {
+            hnsw_index.delete_embedding(*internal_id, embedding, current_version, config)?;
+        }
+        if let Some(inverted_index) = &rebuilt.inverted_index {
+            inverted_index.delete_embedding(*internal_id, embedding, current_version, config)?;
+        }
+    }
+
+    // the postings are copied, as the text they're made of isn't stored
+    // unless the collection stores raw text
+    if let (Some(tf_idf_index), Some(rebuilt_tf_idf_index)) =
+        (&indexes.tf_idf_index, &rebuilt.tf_idf_index)
+    {
+        tf_idf_index
+            .root
+            .copy_to(&rebuilt_tf_idf_index.root, current_version, |id| {
+                collection.live_embedding(InternalId::from(id)).is_some()
+            })?;
+    }
+
+    if let Some(hnsw_index) = &rebuilt.hnsw_index {
+        hnsw_index.pre_commit_transaction(collection, current_version, config)?;
+    }
+    if let Some(inverted_index) = &rebuilt.inverted_index {
+        inverted_index.pre_commit_transaction(collection, current_version, config)?;
+    }
+    if let Some(tf_idf_index) = &rebuilt.tf_idf_index {
+        tf_idf_index.flush(collection, current_version)?;
+    }
+    Ok(())
+}
+
+fn index_vectors(
+    collection: &Collection,
+    config: &Config,
+    rebuilt: &Indexes,
+    embeddings: Vec<(InternalId, RawVectorEmbedding)>,
+    version: VersionNumber,
+) -> Result<(), WaCustomError> {
+    let mut dense_embs = Vec::new();
+    let mut sparse_embs = Vec::new();
+    for (internal_id, embedding) in embeddings {
+        if let Some(values) = embedding.dense_values {
+            dense_embs.push(DenseInputEmbedding(
+                internal_id,
+                values,
+                embedding.metadata,
+                false,
+            ));
+        }
+        if let Some(values) = embedding.sparse_values {
+            sparse_embs.push(SparseInputEmbedding(internal_id, values));
+        }
+    }
+
+    if let Some(hnsw_index) = &rebuilt.hnsw_index {
+        if !dense_embs.is_empty() {
+            hnsw_index.run_upload(collection, dense_embs, version, config)?;
+        }
+    }
+    if let Some(inverted_index) = &rebuilt.inverted_index {
+        if !sparse_embs.is_empty() {
+            inverted_index.run_upload(collection, sparse_embs, version, config)?;
+        }
+    }
+    Ok(())
+}
+
+fn new_hnsw_index(
+    config: &Config,
+    collection: &Collection,
+    hnsw_index: &HNSWIndex,
+    metadata_schema: SharedMetadataSchema,
+    version: VersionNumber,
+) -> Result<Arc<HNSWIndex>, WaCustomError> {
+    let dir = hnsw_index.dir.next();
+    // left behind by an interrupted rebuild
+    if dir.path().exists() {
+        fs::remove_dir_all(dir.path()).map_err(|e| WaCustomError::FsError(e.to_string()))?;
+    }
+    let has_metadata_schema = metadata_schema.is_some();
+    let new_index = Arc::new(create_hnsw_index(
+        config,
+        collection,
+        metadata_schema,
+        // the vectors keep their internal ids
+        hnsw_index.id_stride,
+        dir,
+        *hnsw_index.values_range.read().unwrap(),
+        hnsw_index.hnsw_params.read().unwrap().clone(),
+        hnsw_index.quantization_metric.read().unwrap().clone(),
+        *hnsw_index.distance_metric.read().unwrap(),
+        *hnsw_index.storage_type.read().unwrap(),
+        hnsw_index.sample_threshold,
+        hnsw_index.is_configured(),
+    )?);
+
+    new_index.offset_counter.write().unwrap().next_file_id();
+    if has_metadata_schema {
+        let pseudo_vals = pseudo_node_vector(collection.meta.dense_vector.dimension);
+        let pseudo_vec = DenseInputEmbedding(pseudo_root_id(), pseudo_vals, None, true);
+        // pseudo nodes skip sampling, as when the index is created
+        new_index.index_embeddings(collection, vec![pseudo_vec], version, config)?;
+    }
+    Ok(new_index)
+}
+
+fn new_inverted_index(inverted_index: &InvertedIndex) -> Result<Arc<InvertedIndex>, WaCustomError> {
+    let dir = inverted_index.dir.next();
+    if dir.path().exists() {
+        fs::remove_dir_all(dir.path()).map_err(|e| WaCustomError::FsError(e.to_string()))?;
+    }
+    fs::create_dir_all(dir.path()).map_err(|e| WaCustomError::FsError(e.to_string()))?;
+    let new_index = InvertedIndex::new(
+        dir,
+        inverted_index.root.root.quantization_bits,
+        inverted_index.sample_threshold,
+    )?;
+    *new_index.values_upper_bound.write().unwrap() =
+        *inverted_index.values_upper_bound.read().unwrap();
+    new_index
+        .is_configured
+        .store(inverted_index.is_configured(), Ordering::Release);
+    Ok(Arc::new(new_index))
+}
+
+fn new_tf_idf_index(tf_idf_index: &TFIDFIndex) -> Result<Arc<TFIDFIndex>, WaCustomError> {
+    let dir = tf_idf_index.dir.next();
+    if dir.path().exists() {
+        fs::remove_dir_all(dir.path()).map_err(|e| WaCustomError::FsError(e.to_string()))?;
+    }
+    fs::create_dir_all(dir.path()).map_err(|e| WaCustomError::FsError(e.to_string()))?;
+    let new_index = TFIDFIndex::new(
+        dir,
+        tf_idf_index.sample_threshold,
+        tf_idf_index.k1,
+        tf_idf_index.b,
+    )?;
+    *new_index.average_document_length.write().unwrap() =
+        *tf_idf_index.average_document_length.read().unwrap();
+    new_index
+        .is_configured
+        .store(tf_idf_index.is_configured(), Ordering::Release);
+    Ok(Arc::new(new_index))
+}
@@ -1,12 +1,12 @@
 use super::{
-    buffered_io::BufIoError,
     collection::{Collection, RawVectorEmbedding},
     collection_transaction::{
         BackgroundExplicitTransaction, ExplicitTransactionID, ImplicitTransaction, ProcessingStats,
         TransactionStatus,
     },
     common::WaCustomError,
-    meta_persist::update_background_version,
+    meta_persist::{retrieve_background_version, update_background_version},
+    metrics, replication, tracing,
     types::VectorId,
     versioning::{VersionNumber, VersionSource},
     wal::{VectorOp, WALFile},
@@ -19,7 +19,6 @@ use rayon::{
     ThreadPool,
 };
 use std::{
-    fs,
     sync::{
         atomic::{AtomicU32, Ordering},
         mpsc, Arc,
@@ -64,6 +63,14 @@ impl IndexingManager {
         txn_id: ExplicitTransactionID,
         version: VersionNumber,
     ) -> Result<(), WaCustomError> {
+        let _trace = tracing::start(
+            "index_transaction",
+            vec![
+                ("collection", collection.meta.name.as_str().into()),
+                ("version", (*version).into()),
+                ("transaction_id", (*txn_id).into()),
+            ],
+        );
         collection.is_indexing.store(true, Ordering::Relaxed);
         let txn = BackgroundExplicitTransaction::from_version_id_and_number(collection, version);
         let wal = WALFile::from_existing(&collection.get_path(), version)?;
@@ -92,6 +99,7 @@ impl IndexingManager {
         let records_indexed = AtomicU32::new(0);
         let errors = RwLock::new(Vec::new());
         let mut vectors_to_be_deleted = Vec::new();
+        let span = tracing::span("index_embeddings", Vec::new());
         threadpool.scope(|s| {
             while let Some(op) = wal.read()? {
                 match op {
@@ -167,19 +175,35 @@ impl IndexingManager {
             }
             Ok::<_, WaCustomError>(())
         })?;
+        drop(span);
         let errors = errors.into_inner();
         if let Some(err) = errors.into_iter().next() {
             return Err(err);
         }
+        let records_deleted = vectors_to_be_deleted.len() as u64;
+        let span = tracing::span(
+            "delete_embeddings",
+            vec![("records", records_deleted.into())],
+        );
         for vector_id in vectors_to_be_deleted {
             collection.delete_embedding(vector_id, txn.version, config)?;
         }
-        status.write().complete(version);
-        txn.pre_commit(collection, config)?;
+        drop(span);
+        {
+            let _span = tracing::span("pre_commit", Vec::new());
+            txn.pre_commit(collection, config)?;
+        }
         update_background_version(&collection.lmdb, version)?;
-        fs::remove_file(collection.get_path().join(format!("{}.wal", *version)))
-            .map_err(BufIoError::Io)
-            .unwrap();
+        status.write().complete(version);
+        metrics::record_indexed(
+            &collection.meta.name,
+            records_indexed.into_inner() as u64,
+            records_deleted,
+        );
+        if let TransactionStatus::Complete { stats, .. } = &*status.read() {
+            metrics::record_indexing_complete(&collection.meta.name, stats);
+        }
+        replication::retire_wal(collection, config, version).unwrap();
         collection.is_indexing.store(false, Ordering::Relaxed);
         Ok(())
     }
@@ -190,59 +214,92 @@ impl IndexingManager {
         threadpool: &ThreadPool,
         version: VersionNumber,
     ) -> Result<(), WaCustomError> {
+        let _trace = tracing::start(
+            "index_version",
+            vec![
+                ("collection", collection.meta.name.as_str().into()),
+                ("version", (*version).into()),
+            ],
+        );
         collection.is_indexing.store(true, Ordering::Relaxed);
         let txn = BackgroundExplicitTransaction::from_version_id_and_number(collection, version);
         let wal = WALFile::from_existing(&collection.get_path(), version)?;
         let errors = RwLock::new(Vec::new());
+        let mut vectors_to_be_deleted = Vec::new();
+        let span = tracing::span("index_embeddings", Vec::new());
         threadpool.scope(|s| {
             while let Some(op) = wal.read()? {
-                s.spawn(|_| {
-                    let fallible = || match op {
-                        VectorOp::Upsert(embeddings) => {
-                            match config.indexing.mode {
-                                VectorsIndexingMode::Sequential => {
-                                    collection.index_embeddings(embeddings, txn.version, config)?;
-                                }
-                                VectorsIndexingMode::Batch { batch_size } => {
-                                    embeddings.into_par_iter().chunks(batch_size).try_for_each(
-                                        |embeddings| {
-                                            collection.index_embeddings(
-                                                embeddings,
-                                                txn.version,
-                                                config,
-                                            )
-                                        },
-                                    )?;
+                match op {
+                    VectorOp::Upsert(embeddings) => {
+                        s.spawn(|_| {
+                            let fallible = || {
+                                match config.indexing.mode {
+                                    VectorsIndexingMode::Sequential => {
+                                        collection.index_embeddings(
+                                            embeddings,
+                                            txn.version,
+                                            config,
+                                        )?;
+                                    }
+                                    VectorsIndexingMode::Batch { batch_size } => {
+                                        embeddings
+                                            .into_par_iter()
+                                            .chunks(batch_size)
+                                            .try_for_each(|embeddings| {
+                                                collection.index_embeddings(
+                                                    embeddings,
+                                                    txn.version,
+                                                    config,
+                                                )
+                                            })?;
+                                    }
                                 }
-                            }
 
-                            Ok::<_, WaCustomError>(())
-                        }
-                        VectorOp::Delete(_vector_id) => unimplemented!(),
-                    };
+                                Ok::<_, WaCustomError>(())
+                            };
 
-                    if let Err(err) = fallible() {
-                        errors.write().push(err);
+                            if let Err(err) = fallible() {
+                                errors.write().push(err);
+                            }
+                        });
                     }
-                });
+                    VectorOp::Delete(vector_id) => {
+                        vectors_to_be_deleted.push(vector_id);
+                    }
+                }
             }
             Ok::<_, WaCustomError>(())
         })?;
+        drop(span);
         let errors = errors.into_inner();
         if let Some(err) = errors.into_iter().next() {
             return Err(err);
         }
-        txn.pre_commit(collection, config)?;
+        let span = tracing::span(
+            "delete_embeddings",
+            vec![("records", vectors_to_be_deleted.len().into())],
+        );
+        for vector_id in vectors_to_be_deleted {
+            collection.delete_embedding(vector_id, txn.version, config)?;
+        }
+        drop(span);
+        {
+            let _span = tracing::span("pre_commit", Vec::new());
+            txn.pre_commit(collection, config)?;
+        }
         update_background_version(&collection.lmdb, version)?;
-        fs::remove_file(collection.get_path().join(format!("{}.wal", *version)))
-            .map_err(BufIoError::Io)
-            .unwrap();
+        metrics::record_indexed(
+            &collection.meta.name,
+            wal.records_upserted() as u64,
+            wal.records_deleted() as u64,
+        );
         collection.vcs.update_version_metadata(
             version,
             wal.records_upserted(),
             wal.records_deleted(),
             wal.total_operations(),
         )?;
+        replication::retire_wal(collection, config, version).unwrap();
         collection.is_indexing.store(false, Ordering::Relaxed);
         Ok(())
     }
@@ -266,12 +323,36 @@ impl IndexingManager {
         Ok(())
     }
 
+    /// Indexes the versions committed after the background version, i.e.
+    /// those that weren't indexed before the server stopped
+    pub fn index_pending_versions(
+        collection: &Collection,
+        config: &Config,
+        threadpool: &ThreadPool,
+    ) -> Result<(), WaCustomError> {
+        let current_version = *collection.current_version.read();
+        let background_version = retrieve_background_version(&collection.lmdb)?;
+        for version in (*background_version + 1)..=*current_version {
+            Self::index_version_on_restart(collection, config, threadpool, version.into())?;
+        }
+        Ok(())
+    }
+
     pub fn implicit_txn_upsert(
         collection: &Collection,
         transaction: &ImplicitTransaction,
         config: &Config,
-        embeddings: Vec<RawVectorEmbedding>,
+        mut embeddings: Vec<RawVectorEmbedding>,
     ) -> Result<(), WaCustomError> {
+        collection.apply_default_ttl(&mut embeddings);
+        let records_upserted = embeddings.len() as u64;
+        let _trace = tracing::start(
+            "index_upsert",
+            vec![
+                ("collection", collection.meta.name.as_str().into()),
+                ("records", records_upserted.into()),
+            ],
+        );
         let version = transaction.version(collection)?;
         transaction.append_to_wal(collection, VectorOp::Upsert(embeddings.clone()))?;
         match config.indexing.mode {
@@ -287,6 +368,7 @@ impl IndexingManager {
                     })?;
             }
         }
+        metrics::record_indexed(&collection.meta.name, records_upserted, 0);
         Ok(())
     }
 
@@ -299,6 +381,7 @@ impl IndexingManager {
         let version = transaction.version(collection)?;
         transaction.append_to_wal(collection, VectorOp::Delete(vector_id.clone()))?;
         collection.delete_embedding(vector_id, version, config)?;
+        metrics::record_indexed(&collection.meta.name, 0, 1);
         Ok(())
     }
 }
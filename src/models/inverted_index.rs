@@ -1,6 +1,5 @@
 use rayon::prelude::*;
 use std::{
-    fs::OpenOptions,
     path::PathBuf,
     sync::{
         atomic::{AtomicBool, AtomicU32, Ordering},
@@ -231,14 +230,11 @@ impl InvertedIndexNode {
 
 impl InvertedIndexRoot {
     pub fn new(root_path: PathBuf, quantization_bits: u8) -> Result<Self, BufIoError> {
-        let dim_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(root_path.join("index-tree.dim"))?;
         let node_size = InvertedIndexNode::get_serialized_size(quantization_bits);
-        let dim_bufman = Arc::new(BufferManager::new(dim_file, node_size as usize * 1000)?);
+        let dim_bufman = Arc::new(BufferManager::open(
+            &root_path.join("index-tree.dim"),
+            node_size as usize * 1000,
+        )?);
         let offset_counter = AtomicU32::new(node_size);
         let data_bufmans = Arc::new(BufferManagerFactory::new(
             root_path.into(),
@@ -335,14 +331,11 @@ impl InvertedIndexRoot {
     }
 
     pub fn deserialize(root_path: PathBuf, quantization_bits: u8) -> Result<Self, BufIoError> {
-        let dim_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(root_path.join("index-tree.dim"))?;
         let node_size = InvertedIndexNode::get_serialized_size(quantization_bits);
-        let dim_bufman = Arc::new(BufferManager::new(dim_file, node_size as usize * 1000)?);
+        let dim_bufman = Arc::new(BufferManager::open(
+            &root_path.join("index-tree.dim"),
+            node_size as usize * 1000,
+        )?);
         let offset_counter = AtomicU32::new(dim_bufman.file_size() as u32);
         let data_bufmans = Arc::new(BufferManagerFactory::new(
             root_path.into(),
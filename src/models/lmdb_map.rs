@@ -0,0 +1,178 @@
+// Growable memory map for the shared LMDB environment
+//
+// LMDB fails writes with `MDB_MAP_FULL` once the data outgrows the map size
+// the environment was opened with. The map can be resized at runtime, but
+// only while no transaction is active in the process, so every transaction
+// on the environment must hold a `txn_guard` while it's alive; growing the
+// map takes the exclusive side of the same lock.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lmdb::{Environment, RwTransaction, Transaction};
+use parking_lot::{const_rwlock, RwLock, RwLockReadGuard};
+
+static RESIZE_LOCK: RwLock<()> = const_rwlock(());
+
+// 0 means the map is allowed to grow without bound
+static MAX_MAP_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Usage statistics of the LMDB memory map
+#[derive(Debug, Clone, Copy)]
+pub struct MapUsage {
+    pub map_size: usize,
+    pub used_size: usize,
+    pub max_map_size: Option<usize>,
+    pub page_size: u32,
+    pub max_readers: u32,
+    pub readers: u32,
+}
+
+/// Sets the size the map is never grown beyond, `None` for no limit
+pub fn set_max_map_size(max_map_size: Option<usize>) {
+    MAX_MAP_SIZE.store(max_map_size.unwrap_or(0), Ordering::Relaxed);
+}
+
+fn max_map_size() -> Option<usize> {
+    let max_map_size = MAX_MAP_SIZE.load(Ordering::Relaxed);
+    (max_map_size != 0).then_some(max_map_size)
+}
+
+/// Must be held for as long as a transaction is alive, prevents the map from
+/// being resized underneath it
+pub fn txn_guard() -> RwLockReadGuard<'static, ()> {
+    // recursive, so that a thread already holding a guard can't deadlock
+    // against a pending resize
+    RESIZE_LOCK.read_recursive()
+}
+
+/// Runs `f` in a read-write transaction and commits it. If LMDB reports
+/// that the map is full, the transaction is aborted, the map grown and `f`
+/// retried.
+pub fn with_rw_txn<T>(
+    env: &Environment,
+    mut f: impl FnMut(&mut RwTransaction<'_>) -> lmdb::Result<T>,
+) -> lmdb::Result<T> {
+    loop {
+        let (map_size, result) = {
+            let _guard = txn_guard();
+            let map_size = env_info(env)?.me_mapsize;
+            let mut txn = env.begin_rw_txn()?;
+            // the transaction is aborted on drop if `f` fails
+            let result = f(&mut txn).and_then(|value| txn.commit().map(|_| value));
+            (map_size, result)
+        };
+
+        match result {
+            Err(lmdb::Error::MapFull) => grow_map(env, map_size)?,
+            result => return result,
+        }
+    }
+}
+
+/// Returns the current usage of the map
+pub fn map_usage(env: &Environment) -> lmdb::Result<MapUsage> {
+    let _guard = txn_guard();
+    let info = env_info(env)?;
+    let page_size = env.stat()?.page_size();
+
+    Ok(MapUsage {
+        map_size: info.me_mapsize,
+        used_size: (info.me_last_pgno + 1) * page_size as usize,
+        max_map_size: max_map_size(),
+        page_size,
+        max_readers: info.me_maxreaders,
+        readers: info.me_numreaders,
+    })
+}
+
+// Doubles the map size, capped at the configured maximum. `observed_map_size`
+// is the size the failed transaction ran with, if another thread has grown
+// the map since then this is a no-op.
+fn grow_map(env: &Environment, observed_map_size: usize) -> lmdb::Result<()> {
+    let _guard = RESIZE_LOCK.write();
+    let map_size = env_info(env)?.me_mapsize;
+    if map_size > observed_map_size {
+        return Ok(());
+    }
+
+    let mut new_map_size = map_size.saturating_mul(2);
+    if let Some(max_map_size) = max_map_size() {
+        new_map_size = new_map_size.min(max_map_size);
+    }
+    if new_map_size <= map_size {
+        log::error!(
+            "LMDB map is full and already at its maximum size of {} bytes",
+            map_size
+        );
+        return Err(lmdb::Error::MapFull);
+    }
+
+    log::info!(
+        "LMDB map is full, growing it from {} to {} bytes",
+        map_size,
+        new_map_size
+    );
+    lmdb_result(unsafe { lmdb_sys::mdb_env_set_mapsize(env.env(), new_map_size) })
+}
+
+/// Writes a compacted copy of the environment to the directory `path`,
+/// which must exist and not contain an environment already
+pub fn copy_env(env: &Environment, path: &Path) -> lmdb::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|_| lmdb::Error::Invalid)?;
+    let _guard = txn_guard();
+    lmdb_result(unsafe {
+        lmdb_sys::mdb_env_copy2(env.env(), path.as_ptr(), lmdb_sys::MDB_CP_COMPACT)
+    })
+}
+
+fn env_info(env: &Environment) -> lmdb::Result<lmdb_sys::MDB_envinfo> {
+    let mut info = unsafe { std::mem::zeroed::<lmdb_sys::MDB_envinfo>() };
+    lmdb_result(unsafe { lmdb_sys::mdb_env_info(env.env(), &mut info) })?;
+    Ok(info)
+}
+
+fn lmdb_result(err_code: i32) -> lmdb::Result<()> {
+    if err_code == lmdb_sys::MDB_SUCCESS {
+        Ok(())
+    } else {
+        Err(lmdb::Error::from_err_code(err_code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lmdb::{DatabaseFlags, WriteFlags};
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_map_grows_when_full() {
+        let dir = tempdir().unwrap();
+        let env = Environment::new()
+            .set_max_dbs(1)
+            .set_map_size(64 * 1024)
+            .open(dir.path())
+            .unwrap();
+        let db = env.create_db(Some("test"), DatabaseFlags::empty()).unwrap();
+        let initial_map_size = map_usage(&env).unwrap().map_size;
+
+        let value = vec![7u8; 1024];
+        for i in 0u32..256 {
+            with_rw_txn(&env, |txn| {
+                txn.put(db, &i.to_le_bytes(), &value, WriteFlags::empty())
+            })
+            .unwrap();
+        }
+
+        let usage = map_usage(&env).unwrap();
+        assert!(usage.map_size > initial_map_size);
+        assert!(usage.used_size <= usage.map_size);
+
+        let txn = env.begin_ro_txn().unwrap();
+        assert_eq!(txn.get(db, &255u32.to_le_bytes()).unwrap(), &value[..]);
+    }
+}
@@ -0,0 +1,225 @@
+// Local embedding models, run on the CPU with candle
+
+use std::{fs, io, path::Path};
+
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{self, BertForMaskedLM, BertModel};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+use crate::indexes::inverted::types::SparsePair;
+
+use super::{
+    embeddings::{invalid_data, invalid_input, EmbeddingProvider, Pooling},
+    paths::get_data_path,
+};
+
+// number of texts run through a local model at once
+const LOCAL_BATCH_SIZE: usize = 32;
+
+fn model_error(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::other(err)
+}
+
+// the config and tokenizer of a local BERT model
+struct LocalModel {
+    config: bert::Config,
+    tokenizer: Tokenizer,
+}
+
+impl LocalModel {
+    // loads the files of `model_dir` in the data directory, returning the
+    // weights of the model with its config and tokenizer
+    fn load(model_dir: &Path) -> io::Result<(Self, VarBuilder<'static>)> {
+        let model_dir = get_data_path().join(model_dir);
+        let read = |file: &str| {
+            fs::read(model_dir.join(file)).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!(
+                        "Failed to read `{}`: {}",
+                        model_dir.join(file).display(),
+                        err
+                    ),
+                )
+            })
+        };
+        let config: bert::Config = serde_json::from_slice(&read("config.json")?)
+            .map_err(|err| invalid_data(format!("Invalid model config: {}", err)))?;
+        let mut tokenizer = Tokenizer::from_bytes(read("tokenizer.json")?).map_err(model_error)?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(model_error)?;
+        let weights = VarBuilder::from_buffered_safetensors(
+            read("model.safetensors")?,
+            DType::F32,
+            &Device::Cpu,
+        )
+        .map_err(model_error)?;
+
+        Ok((Self { config, tokenizer }, weights))
+    }
+
+    // tokenizes `texts` into their padded token ids, token type ids and
+    // attention mask
+    fn tokenize(&self, texts: &[&str]) -> io::Result<(Tensor, Tensor, Tensor)> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(model_error)?;
+        let stack = |rows: Vec<&[u32]>| -> candle_core::Result<Tensor> {
+            let rows = rows
+                .into_iter()
+                .map(|row| Tensor::new(row, &Device::Cpu))
+                .collect::<candle_core::Result<Vec<_>>>()?;
+            Tensor::stack(&rows, 0)
+        };
+        let input_ids =
+            stack(encodings.iter().map(|e| e.get_ids()).collect()).map_err(model_error)?;
+        let attention_mask = stack(encodings.iter().map(|e| e.get_attention_mask()).collect())
+            .map_err(model_error)?;
+        let token_type_ids = input_ids.zeros_like().map_err(model_error)?;
+        Ok((input_ids, token_type_ids, attention_mask))
+    }
+}
+
+pub(super) struct BertEmbeddingProvider {
+    local: LocalModel,
+    model: BertModel,
+    pooling: Pooling,
+}
+
+impl BertEmbeddingProvider {
+    pub(super) fn new(model_dir: &Path, pooling: Pooling) -> io::Result<Self> {
+        let (local, weights) = LocalModel::load(model_dir)?;
+        let model = BertModel::load(weights, &local.config).map_err(model_error)?;
+        Ok(Self {
+            local,
+            model,
+            pooling,
+        })
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> candle_core::Result<Vec<Vec<f32>>> {
+        let (input_ids, token_type_ids, attention_mask) = self
+            .local
+            .tokenize(texts)
+            .map_err(candle_core::Error::wrap)?;
+        let output = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+        let pooled = match self.pooling {
+            Pooling::Mean => {
+                let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+                output
+                    .broadcast_mul(&mask)?
+                    .sum(1)?
+                    .broadcast_div(&mask.sum(1)?)?
+            }
+            Pooling::Cls => output.narrow(1, 0, 1)?.squeeze(1)?,
+        };
+        pooled
+            .broadcast_div(&pooled.sqr()?.sum_keepdim(1)?.sqrt()?)?
+            .to_vec2()
+    }
+}
+
+impl EmbeddingProvider for BertEmbeddingProvider {
+    fn is_dense(&self) -> bool {
+        true
+    }
+
+    fn is_sparse(&self) -> bool {
+        false
+    }
+
+    fn dense_dimension(&self) -> Option<usize> {
+        Some(self.local.config.hidden_size)
+    }
+
+    fn embed_dense(&self, texts: &[&str]) -> io::Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(LOCAL_BATCH_SIZE) {
+            embeddings.extend(self.embed_batch(batch).map_err(model_error)?);
+        }
+        Ok(embeddings)
+    }
+
+    fn embed_sparse(&self, _texts: &[&str]) -> io::Result<Vec<Vec<SparsePair>>> {
+        Err(invalid_input(
+            "BERT models don't compute sparse embeddings".to_string(),
+        ))
+    }
+}
+
+pub(super) struct SpladeEmbeddingProvider {
+    local: LocalModel,
+    model: BertForMaskedLM,
+}
+
+impl SpladeEmbeddingProvider {
+    pub(super) fn new(model_dir: &Path) -> io::Result<Self> {
+        let (local, weights) = LocalModel::load(model_dir)?;
+        let model = BertForMaskedLM::load(weights, &local.config).map_err(model_error)?;
+        Ok(Self { local, model })
+    }
+
+    // the weight of each token of the vocabulary is the max over the text's
+    // tokens of log(1 + relu(logit))
+    fn embed_batch(&self, texts: &[&str]) -> candle_core::Result<Vec<Vec<SparsePair>>> {
+        let (input_ids, token_type_ids, attention_mask) = self
+            .local
+            .tokenize(texts)
+            .map_err(candle_core::Error::wrap)?;
+        let logits = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+        let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+        let weights = logits
+            .relu()?
+            .affine(1.0, 1.0)?
+            .log()?
+            .broadcast_mul(&mask)?
+            .max(1)?
+            .to_vec2::<f32>()?;
+        Ok(weights
+            .into_iter()
+            .map(|weights| {
+                weights
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, weight)| *weight > 0.0)
+                    .map(|(term, weight)| SparsePair(term as u32, weight))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+impl EmbeddingProvider for SpladeEmbeddingProvider {
+    fn is_dense(&self) -> bool {
+        false
+    }
+
+    fn is_sparse(&self) -> bool {
+        true
+    }
+
+    fn embed_dense(&self, _texts: &[&str]) -> io::Result<Vec<Vec<f32>>> {
+        Err(invalid_input(
+            "SPLADE models don't compute dense embeddings".to_string(),
+        ))
+    }
+
+    fn embed_sparse(&self, texts: &[&str]) -> io::Result<Vec<Vec<SparsePair>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(LOCAL_BATCH_SIZE) {
+            embeddings.extend(self.embed_batch(batch).map_err(model_error)?);
+        }
+        Ok(embeddings)
+    }
+}
@@ -6,6 +6,8 @@ use rand::Rng;
 use std::iter::Iterator;
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
+use super::metrics::CacheStats;
+
 // Calculates counter age, while considering a possibility of
 // wraparound (with the assumption that wraparound will happen at most
 // once)
@@ -156,6 +158,7 @@ where
     evict_strategy: EvictStrategy,
     index: EvictionIndex,
     evict_hook: Option<fn(&V)>,
+    stats: Option<&'static CacheStats>,
 }
 
 /// Wrapper for the value that's returned from the LRUCache when
@@ -187,6 +190,7 @@ where
             counter: AtomicU32::new(0),
             index: EvictionIndex::new(),
             evict_hook: None,
+            stats: None,
             capacity,
             evict_strategy,
         }
@@ -202,6 +206,18 @@ where
         self.evict_hook = hook;
     }
 
+    /// Sets the counters to record the hits, misses and evictions of the
+    /// cache in
+    pub fn set_stats(&mut self, stats: Option<&'static CacheStats>) {
+        self.stats = stats;
+    }
+
+    fn record_lookup(&self, hit: bool) {
+        if let Some(stats) = self.stats {
+            stats.record_lookup(hit);
+        }
+    }
+
     /// Returns an entry from the cache
     ///
     /// None will be returned if the cache doesn't contain the key
@@ -213,8 +229,10 @@ where
             *counter_val = new_counter;
             self.index
                 .on_cache_hit(old_counter, new_counter, key.clone().into());
+            self.record_lookup(true);
             Some(value.clone())
         } else {
+            self.record_lookup(false);
             None
         }
     }
@@ -262,6 +280,7 @@ where
         // it causes some deadlock
         match res {
             Ok(v) => {
+                self.record_lookup(!inserted);
                 if inserted {
                     self.evict();
                     Ok(CachedValue::Miss(v))
@@ -310,6 +329,8 @@ where
             let removed = self.map.remove(&key);
             if removed.is_none() {
                 log::warn!("Item already evicted by another thread");
+            } else if let Some(stats) = self.stats {
+                stats.record_evictions(1);
             }
         }
     }
@@ -343,7 +364,11 @@ where
                 if let Some(evict_hook) = self.evict_hook {
                     evict_hook(&value)
                 }
-                self.map.remove(&key);
+                if self.map.remove(&key).is_some() {
+                    if let Some(stats) = self.stats {
+                        stats.record_evictions(1);
+                    }
+                }
                 self.index.remove(idx);
             }
         }
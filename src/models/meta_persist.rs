@@ -1,5 +1,6 @@
 use crate::macros::key;
 use crate::models::common::*;
+use crate::models::lmdb_map::{txn_guard, with_rw_txn};
 use crate::models::types::*;
 use crate::models::versioning::*;
 use lmdb::{Cursor, Database, DatabaseFlags, Environment, Transaction, WriteFlags};
@@ -15,20 +16,12 @@ pub fn update_current_version(
     let env = lmdb.env.clone();
     let db = lmdb.db;
 
-    let mut txn = env
-        .begin_rw_txn()
-        .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
-
     let key = key!(m:current_version);
     let bytes = version_hash.to_le_bytes();
 
-    txn.put(db, &key, &bytes, WriteFlags::empty())
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
         .map_err(|e| WaCustomError::DatabaseError(format!("Failed to put data: {}", e)))?;
 
-    txn.commit().map_err(|e| {
-        WaCustomError::DatabaseError(format!("Failed to commit transaction: {}", e))
-    })?;
-
     Ok(())
 }
 
@@ -40,20 +33,12 @@ pub fn update_background_version(
     let env = lmdb.env.clone();
     let db = lmdb.db;
 
-    let mut txn = env
-        .begin_rw_txn()
-        .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
-
     let key = key!(m:background_version);
     let bytes = version.to_le_bytes();
 
-    txn.put(db, &key, &bytes, WriteFlags::empty())
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
         .map_err(|e| WaCustomError::DatabaseError(format!("Failed to put data: {}", e)))?;
 
-    txn.commit().map_err(|e| {
-        WaCustomError::DatabaseError(format!("Failed to commit transaction: {}", e))
-    })?;
-
     Ok(())
 }
 
@@ -61,60 +46,139 @@ pub fn store_values_range(lmdb: &MetaDb, range: (f32, f32)) -> lmdb::Result<()>
     let env = lmdb.env.clone();
     let db = lmdb.db;
 
-    let mut txn = env.begin_rw_txn()?;
     let key = key!(m:values_range);
     let mut bytes = Vec::with_capacity(8);
     bytes.extend(range.0.to_le_bytes());
     bytes.extend(range.1.to_le_bytes());
 
-    txn.put(db, &key, &bytes, WriteFlags::empty())?;
-    txn.commit()?;
-    Ok(())
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
 }
 
 pub fn store_values_upper_bound(lmdb: &MetaDb, bound: f32) -> lmdb::Result<()> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
 
-    let mut txn = env.begin_rw_txn()?;
     let key = key!(m:values_upper_bound);
     let bytes = bound.to_le_bytes();
 
-    txn.put(db, &key, &bytes, WriteFlags::empty())?;
-    txn.commit()?;
-    Ok(())
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
 }
 
 pub fn store_average_document_length(lmdb: &MetaDb, len: f32) -> lmdb::Result<()> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
 
-    let mut txn = env.begin_rw_txn()?;
     let key = key!(m:average_document_length);
     let bytes = len.to_le_bytes();
 
-    txn.put(db, &key, &bytes, WriteFlags::empty())?;
-    txn.commit()?;
-    Ok(())
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
 }
 
 pub fn store_highest_internal_id(lmdb: &MetaDb, id: u32) -> lmdb::Result<()> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
 
-    let mut txn = env.begin_rw_txn()?;
     let key = key!(m:highest_internal_id);
     let bytes = id.to_le_bytes();
 
-    txn.put(db, &key, &bytes, WriteFlags::empty())?;
-    txn.commit()?;
-    Ok(())
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
+}
+
+pub fn store_vector_count(lmdb: &MetaDb, count: u32) -> lmdb::Result<()> {
+    let env = lmdb.env.clone();
+    let db = lmdb.db;
+
+    let key = key!(m:vector_count);
+    let bytes = count.to_le_bytes();
+
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
+}
+
+pub fn store_compaction_floor(lmdb: &MetaDb, version: VersionNumber) -> lmdb::Result<()> {
+    let env = lmdb.env.clone();
+    let db = lmdb.db;
+
+    let key = key!(m:compaction_floor);
+    let bytes = version.to_le_bytes();
+
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
+}
+
+/// records the no. of vectors the indexes were last rebuilt without, out of
+/// the internal ids handed out until then
+pub fn store_dropped_vector_count(lmdb: &MetaDb, count: u32) -> lmdb::Result<()> {
+    let env = lmdb.env.clone();
+    let db = lmdb.db;
+
+    let key = key!(m:dropped_vector_count);
+    let bytes = count.to_le_bytes();
+
+    with_rw_txn(&env, |txn| txn.put(db, &key, &bytes, WriteFlags::empty()))
+}
+
+/// records `transaction_id` as an explicit transaction that is open, along
+/// with the version it started at, so that it's restored on restart
+pub fn add_open_transaction(
+    lmdb: &MetaDb,
+    transaction_id: u32,
+    start_version: VersionNumber,
+) -> lmdb::Result<()> {
+    update_open_transactions(lmdb, |transactions| {
+        transactions.push((transaction_id, start_version))
+    })
+}
+
+pub fn remove_open_transaction(lmdb: &MetaDb, transaction_id: u32) -> lmdb::Result<()> {
+    update_open_transactions(lmdb, |transactions| {
+        transactions.retain(|(id, _)| *id != transaction_id)
+    })
+}
+
+fn update_open_transactions(
+    lmdb: &MetaDb,
+    f: impl Fn(&mut Vec<(u32, VersionNumber)>),
+) -> lmdb::Result<()> {
+    let env = lmdb.env.clone();
+    let db = lmdb.db;
+
+    let key = key!(m:open_transactions);
+
+    with_rw_txn(&env, |txn| {
+        let mut transactions = match txn.get(db, &key) {
+            Ok(bytes) => decode_open_transactions(bytes),
+            Err(lmdb::Error::NotFound) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        f(&mut transactions);
+        let bytes: Vec<u8> = transactions
+            .iter()
+            .flat_map(|(id, start_version)| {
+                id.to_le_bytes()
+                    .into_iter()
+                    .chain(start_version.to_le_bytes())
+            })
+            .collect();
+        txn.put(db, &key, &bytes, WriteFlags::empty())
+    })
+}
+
+// pairs of little-endian transaction ids and start versions
+fn decode_open_transactions(bytes: &[u8]) -> Vec<(u32, VersionNumber)> {
+    bytes
+        .chunks_exact(8)
+        .map(|chunk| {
+            let id = u32::from_le_bytes(chunk[..4].try_into().unwrap());
+            let start_version = u32::from_le_bytes(chunk[4..].try_into().unwrap());
+            (id, VersionNumber::from(start_version))
+        })
+        .collect()
 }
 
 /// retrieves the current version of a collection
 pub fn retrieve_current_version(lmdb: &MetaDb) -> Result<VersionNumber, WaCustomError> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
+    let _guard = txn_guard();
     let txn = env
         .begin_ro_txn()
         .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
@@ -141,6 +205,7 @@ pub fn retrieve_current_version(lmdb: &MetaDb) -> Result<VersionNumber, WaCustom
 pub fn retrieve_background_version(lmdb: &MetaDb) -> Result<VersionNumber, WaCustomError> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
+    let _guard = txn_guard();
     let txn = env
         .begin_ro_txn()
         .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
@@ -166,6 +231,7 @@ pub fn retrieve_background_version(lmdb: &MetaDb) -> Result<VersionNumber, WaCus
 pub fn retrieve_values_range(lmdb: &MetaDb) -> Result<Option<(f32, f32)>, WaCustomError> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
+    let _guard = txn_guard();
     let txn = env
         .begin_ro_txn()
         .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
@@ -191,6 +257,7 @@ pub fn retrieve_values_range(lmdb: &MetaDb) -> Result<Option<(f32, f32)>, WaCust
 pub fn retrieve_values_upper_bound(lmdb: &MetaDb) -> Result<Option<f32>, WaCustomError> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
+    let _guard = txn_guard();
     let txn = env
         .begin_ro_txn()
         .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
@@ -215,6 +282,7 @@ pub fn retrieve_values_upper_bound(lmdb: &MetaDb) -> Result<Option<f32>, WaCusto
 pub fn retrieve_average_document_length(lmdb: &MetaDb) -> Result<Option<f32>, WaCustomError> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
+    let _guard = txn_guard();
     let txn = env
         .begin_ro_txn()
         .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
@@ -239,6 +307,7 @@ pub fn retrieve_average_document_length(lmdb: &MetaDb) -> Result<Option<f32>, Wa
 pub fn retrieve_highest_internal_id(lmdb: &MetaDb) -> Result<Option<u32>, WaCustomError> {
     let env = lmdb.env.clone();
     let db = lmdb.db;
+    let _guard = txn_guard();
     let txn = env
         .begin_ro_txn()
         .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
@@ -260,12 +329,106 @@ pub fn retrieve_highest_internal_id(lmdb: &MetaDb) -> Result<Option<u32>, WaCust
     Ok(Some(id))
 }
 
+pub fn retrieve_compaction_floor(lmdb: &MetaDb) -> Result<Option<VersionNumber>, WaCustomError> {
+    let env = lmdb.env.clone();
+    let db = lmdb.db;
+    let _guard = txn_guard();
+    let txn = env
+        .begin_ro_txn()
+        .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
+    let key = key!(m:compaction_floor);
+
+    let serialized = match txn.get(db, &key) {
+        Ok(bytes) => bytes,
+        Err(lmdb::Error::NotFound) => return Ok(None),
+        Err(e) => return Err(WaCustomError::DatabaseError(e.to_string())),
+    };
+
+    let bytes: [u8; 4] = serialized.try_into().map_err(|_| {
+        WaCustomError::DeserializationError(
+            "Failed to deserialize compaction floor: length mismatch".to_string(),
+        )
+    })?;
+
+    Ok(Some(VersionNumber::from(u32::from_le_bytes(bytes))))
+}
+
+pub fn retrieve_dropped_vector_count(lmdb: &MetaDb) -> Result<Option<u32>, WaCustomError> {
+    let env = lmdb.env.clone();
+    let db = lmdb.db;
+    let _guard = txn_guard();
+    let txn = env
+        .begin_ro_txn()
+        .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
+    let key = key!(m:dropped_vector_count);
+
+    let serialized = match txn.get(db, &key) {
+        Ok(bytes) => bytes,
+        Err(lmdb::Error::NotFound) => return Ok(None),
+        Err(e) => return Err(WaCustomError::DatabaseError(e.to_string())),
+    };
+
+    let bytes: [u8; 4] = serialized.try_into().map_err(|_| {
+        WaCustomError::DeserializationError(
+            "Failed to deserialize dropped vector count: length mismatch".to_string(),
+        )
+    })?;
+
+    Ok(Some(u32::from_le_bytes(bytes)))
+}
+
+pub fn retrieve_vector_count(lmdb: &MetaDb) -> Result<Option<u32>, WaCustomError> {
+    let env = lmdb.env.clone();
+    let db = lmdb.db;
+    let _guard = txn_guard();
+    let txn = env
+        .begin_ro_txn()
+        .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
+    let key = key!(m:vector_count);
+
+    let serialized = match txn.get(db, &key) {
+        Ok(bytes) => bytes,
+        Err(lmdb::Error::NotFound) => return Ok(None),
+        Err(e) => return Err(WaCustomError::DatabaseError(e.to_string())),
+    };
+
+    let bytes: [u8; 4] = serialized.try_into().map_err(|_| {
+        WaCustomError::DeserializationError(
+            "Failed to deserialize vector count: length mismatch".to_string(),
+        )
+    })?;
+
+    Ok(Some(u32::from_le_bytes(bytes)))
+}
+
+/// retrieves the ids of the explicit transactions that are open, along with
+/// the versions they started at
+pub fn retrieve_open_transactions(
+    lmdb: &MetaDb,
+) -> Result<Vec<(u32, VersionNumber)>, WaCustomError> {
+    let env = lmdb.env.clone();
+    let db = lmdb.db;
+    let _guard = txn_guard();
+    let txn = env
+        .begin_ro_txn()
+        .map_err(|e| WaCustomError::DatabaseError(format!("Failed to begin transaction: {}", e)))?;
+    let key = key!(m:open_transactions);
+
+    match txn.get(db, &key) {
+        Ok(bytes) => Ok(decode_open_transactions(bytes)),
+        Err(lmdb::Error::NotFound) => Ok(Vec::new()),
+        Err(e) => Err(WaCustomError::DatabaseError(e.to_string())),
+    }
+}
+
 // TODO use lmdb_init_db function inside this function
 pub fn lmdb_init_collections_db(env: &Environment) -> lmdb::Result<Database> {
+    let _guard = txn_guard();
     env.create_db(Some("collections"), DatabaseFlags::empty())
 }
 
 pub fn lmdb_init_db(env: &Environment, name: &str) -> lmdb::Result<Database> {
+    let _guard = txn_guard();
     env.create_db(Some(name), DatabaseFlags::empty())
 }
 
@@ -274,6 +437,7 @@ pub(crate) fn load_collections(
     db: Database,
 ) -> lmdb::Result<Vec<CollectionMetadata>> {
     let mut collections = Vec::new();
+    let _guard = txn_guard();
     let txn = env.begin_ro_txn().unwrap();
     let mut cursor = txn.open_ro_cursor(db).unwrap();
     for (_k, v) in cursor.iter() {
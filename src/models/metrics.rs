@@ -0,0 +1,501 @@
+// Process-wide metrics, exposed in the Prometheus text format at `/metrics`
+//
+// Counters updated on hot paths (searches, caches, buffer flushes, indexing)
+// are atomics. Gauges that can be read from existing state, such as the
+// loaded collections, active sessions and WAL backlog, are computed when
+// the metrics are scraped.
+
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        LazyLock,
+    },
+    time::Duration,
+};
+
+use dashmap::DashMap;
+
+use super::{collection_transaction::ProcessingStats, meta_persist::retrieve_background_version};
+use crate::app_context::AppContext;
+
+/// Upper bounds of the search latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Hit, miss and eviction counts of an `LRUCache`
+pub struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl CacheStats {
+    pub const fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_lookup(&self, hit: bool) {
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_evictions(&self, count: u64) {
+        self.evictions.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+impl Default for CacheStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Nodes of the HNSW indexes, cached by `HNSWIndexCache`
+pub static HNSW_NODE_CACHE: CacheStats = CacheStats::new();
+/// File regions buffered by the `BufferManager`s
+pub static BUFFER_REGION_CACHE: CacheStats = CacheStats::new();
+/// Collections loaded by the `CollectionCacheManager`
+pub static COLLECTION_CACHE: CacheStats = CacheStats::new();
+
+/// Buffer regions written to their files by the `BufferManager`s
+pub static BUFFER_FLUSHES: AtomicU64 = AtomicU64::new(0);
+pub static BUFFER_FLUSHED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Default)]
+struct Histogram {
+    // not cumulative, summed up when rendered
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(idx) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+struct SearchMetrics {
+    latency: Histogram,
+    errors: AtomicU64,
+}
+
+#[derive(Default)]
+struct IndexingMetrics {
+    records_upserted: AtomicU64,
+    records_deleted: AtomicU64,
+    transactions: AtomicU64,
+    processing_seconds: AtomicU64,
+    // `f32` bits of the average throughput of the last indexed transaction
+    throughput: AtomicU32,
+}
+
+#[derive(Default)]
+struct Metrics {
+    // keyed by (collection, search type)
+    searches: DashMap<(String, &'static str), SearchMetrics>,
+    indexing: DashMap<String, IndexingMetrics>,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+pub fn record_search(collection: &str, search_type: &'static str, elapsed: Duration, ok: bool) {
+    let key = (collection.to_string(), search_type);
+    let search = METRICS.searches.entry(key).or_default();
+    search.latency.observe(elapsed);
+    if !ok {
+        search.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Records records indexed from a transaction's WAL
+pub fn record_indexed(collection: &str, records_upserted: u64, records_deleted: u64) {
+    let indexing = METRICS.indexing.entry(collection.to_string()).or_default();
+    indexing
+        .records_upserted
+        .fetch_add(records_upserted, Ordering::Relaxed);
+    indexing
+        .records_deleted
+        .fetch_add(records_deleted, Ordering::Relaxed);
+}
+
+/// Records the stats of an explicit transaction once indexed
+pub fn record_indexing_complete(collection: &str, stats: &ProcessingStats) {
+    let indexing = METRICS.indexing.entry(collection.to_string()).or_default();
+    indexing.transactions.fetch_add(1, Ordering::Relaxed);
+    if let Some(seconds) = stats.processing_time_seconds {
+        indexing
+            .processing_seconds
+            .fetch_add(seconds as u64, Ordering::Relaxed);
+    }
+    // transactions indexed within a second have an infinite throughput
+    if let Some(throughput) = stats.average_throughput.filter(|t| t.is_finite()) {
+        indexing
+            .throughput
+            .store(throughput.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Drops the metrics of a deleted collection
+pub fn remove_collection(collection: &str) {
+    METRICS
+        .searches
+        .retain(|(name, _), _| name.as_str() != collection);
+    METRICS.indexing.remove(collection);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// Writes the `# HELP` and `# TYPE` lines of a metric
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Renders every metric in the Prometheus text exposition format
+pub fn render(ctx: &AppContext) -> String {
+    let mut out = String::new();
+    render_searches(&mut out);
+    render_indexing(&mut out, ctx);
+    render_caches(&mut out);
+
+    header(
+        &mut out,
+        "cosdata_buffer_flushes_total",
+        "counter",
+        "Buffer regions written to their files",
+    );
+    let _ = writeln!(
+        out,
+        "cosdata_buffer_flushes_total {}",
+        BUFFER_FLUSHES.load(Ordering::Relaxed)
+    );
+    header(
+        &mut out,
+        "cosdata_buffer_flushed_bytes_total",
+        "counter",
+        "Bytes of buffer regions written to their files",
+    );
+    let _ = writeln!(
+        out,
+        "cosdata_buffer_flushed_bytes_total {}",
+        BUFFER_FLUSHED_BYTES.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut out,
+        "cosdata_collections",
+        "gauge",
+        "Collections in the database",
+    );
+    let _ = writeln!(
+        out,
+        "cosdata_collections {}",
+        ctx.ain_env.collections_map.iter_collections().count()
+    );
+    header(
+        &mut out,
+        "cosdata_loaded_collections",
+        "gauge",
+        "Collections loaded in the collection cache",
+    );
+    let _ = writeln!(
+        out,
+        "cosdata_loaded_collections {}",
+        ctx.collection_cache_manager.get_loaded_collections().len()
+    );
+    header(
+        &mut out,
+        "cosdata_active_sessions",
+        "gauge",
+        "Sessions that have not expired",
+    );
+    let _ = writeln!(
+        out,
+        "cosdata_active_sessions {}",
+        ctx.ain_env.active_sessions.active_count()
+    );
+
+    out
+}
+
+fn render_searches(out: &mut String) {
+    let mut searches: Vec<_> = METRICS.searches.iter().collect();
+    searches.sort_by(|a, b| a.key().cmp(b.key()));
+
+    header(
+        out,
+        "cosdata_search_requests_total",
+        "counter",
+        "Search requests",
+    );
+    for search in &searches {
+        let (collection, search_type) = search.key();
+        let _ = writeln!(
+            out,
+            "cosdata_search_requests_total{{collection=\"{}\",search_type=\"{}\"}} {}",
+            escape_label(collection),
+            search_type,
+            search.latency.count.load(Ordering::Relaxed)
+        );
+    }
+
+    header(
+        out,
+        "cosdata_search_errors_total",
+        "counter",
+        "Search requests that failed",
+    );
+    for search in &searches {
+        let (collection, search_type) = search.key();
+        let _ = writeln!(
+            out,
+            "cosdata_search_errors_total{{collection=\"{}\",search_type=\"{}\"}} {}",
+            escape_label(collection),
+            search_type,
+            search.errors.load(Ordering::Relaxed)
+        );
+    }
+
+    header(
+        out,
+        "cosdata_search_duration_seconds",
+        "histogram",
+        "Latency of search requests",
+    );
+    for search in &searches {
+        let (collection, search_type) = search.key();
+        let labels = format!(
+            "collection=\"{}\",search_type=\"{}\"",
+            escape_label(collection),
+            search_type
+        );
+        let latency = &search.latency;
+        let mut cumulative = 0;
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&latency.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(
+                out,
+                "cosdata_search_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                labels, bound, cumulative
+            );
+        }
+        let count = latency.count.load(Ordering::Relaxed);
+        let _ = writeln!(
+            out,
+            "cosdata_search_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+            labels, count
+        );
+        let _ = writeln!(
+            out,
+            "cosdata_search_duration_seconds_sum{{{}}} {}",
+            labels,
+            latency.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+        let _ = writeln!(
+            out,
+            "cosdata_search_duration_seconds_count{{{}}} {}",
+            labels, count
+        );
+    }
+}
+
+fn render_indexing(out: &mut String, ctx: &AppContext) {
+    let mut indexing: Vec<_> = METRICS.indexing.iter().collect();
+    indexing.sort_by(|a, b| a.key().cmp(b.key()));
+
+    header(
+        out,
+        "cosdata_indexed_records_total",
+        "counter",
+        "Records indexed from committed transactions",
+    );
+    for entry in &indexing {
+        let collection = escape_label(entry.key());
+        let _ = writeln!(
+            out,
+            "cosdata_indexed_records_total{{collection=\"{}\",operation=\"upsert\"}} {}",
+            collection,
+            entry.records_upserted.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            out,
+            "cosdata_indexed_records_total{{collection=\"{}\",operation=\"delete\"}} {}",
+            collection,
+            entry.records_deleted.load(Ordering::Relaxed)
+        );
+    }
+
+    header(
+        out,
+        "cosdata_indexed_transactions_total",
+        "counter",
+        "Explicit transactions indexed",
+    );
+    for entry in &indexing {
+        let _ = writeln!(
+            out,
+            "cosdata_indexed_transactions_total{{collection=\"{}\"}} {}",
+            escape_label(entry.key()),
+            entry.transactions.load(Ordering::Relaxed)
+        );
+    }
+
+    header(
+        out,
+        "cosdata_indexing_seconds_total",
+        "counter",
+        "Time spent indexing explicit transactions",
+    );
+    for entry in &indexing {
+        let _ = writeln!(
+            out,
+            "cosdata_indexing_seconds_total{{collection=\"{}\"}} {}",
+            escape_label(entry.key()),
+            entry.processing_seconds.load(Ordering::Relaxed)
+        );
+    }
+
+    header(
+        out,
+        "cosdata_indexing_throughput",
+        "gauge",
+        "Records indexed per second by the last indexed explicit transaction",
+    );
+    for entry in &indexing {
+        let _ = writeln!(
+            out,
+            "cosdata_indexing_throughput{{collection=\"{}\"}} {}",
+            escape_label(entry.key()),
+            f32::from_bits(entry.throughput.load(Ordering::Relaxed))
+        );
+    }
+
+    let mut collections: Vec<_> = ctx
+        .ain_env
+        .collections_map
+        .iter_collections()
+        .map(|collection| collection.value().clone())
+        .collect();
+    collections.sort_by(|a, b| a.meta.name.cmp(&b.meta.name));
+
+    header(
+        out,
+        "cosdata_wal_backlog_versions",
+        "gauge",
+        "Committed versions whose WAL has not been indexed yet",
+    );
+    for collection in collections {
+        let Ok(background_version) = retrieve_background_version(&collection.lmdb) else {
+            continue;
+        };
+        let current_version = **collection.current_version.read();
+        let _ = writeln!(
+            out,
+            "cosdata_wal_backlog_versions{{collection=\"{}\"}} {}",
+            escape_label(&collection.meta.name),
+            current_version.saturating_sub(*background_version)
+        );
+    }
+}
+
+fn render_caches(out: &mut String) {
+    let caches = [
+        ("hnsw_nodes", &HNSW_NODE_CACHE),
+        ("buffer_regions", &BUFFER_REGION_CACHE),
+        ("collections", &COLLECTION_CACHE),
+    ];
+
+    header(
+        out,
+        "cosdata_cache_hits_total",
+        "counter",
+        "Cache lookups that found the entry",
+    );
+    for (cache, stats) in caches {
+        let _ = writeln!(
+            out,
+            "cosdata_cache_hits_total{{cache=\"{}\"}} {}",
+            cache,
+            stats.hits.load(Ordering::Relaxed)
+        );
+    }
+    header(
+        out,
+        "cosdata_cache_misses_total",
+        "counter",
+        "Cache lookups that did not find the entry",
+    );
+    for (cache, stats) in caches {
+        let _ = writeln!(
+            out,
+            "cosdata_cache_misses_total{{cache=\"{}\"}} {}",
+            cache,
+            stats.misses.load(Ordering::Relaxed)
+        );
+    }
+    header(
+        out,
+        "cosdata_cache_evictions_total",
+        "counter",
+        "Entries evicted from the cache",
+    );
+    for (cache, stats) in caches {
+        let _ = writeln!(
+            out,
+            "cosdata_cache_evictions_total{{cache=\"{}\"}} {}",
+            cache,
+            stats.evictions.load(Ordering::Relaxed)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let histogram = Histogram::default();
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(30));
+        histogram.observe(Duration::from_secs(10));
+
+        let buckets: Vec<_> = histogram
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+        assert_eq!(buckets[0], 1);
+        assert_eq!(buckets[5], 1);
+        assert_eq!(buckets.iter().sum::<u64>(), 2);
+        assert_eq!(histogram.count.load(Ordering::Relaxed), 3);
+        assert_eq!(histogram.sum_micros.load(Ordering::Relaxed), 10_030_500);
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
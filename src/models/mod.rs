@@ -5,26 +5,45 @@ pub mod collection;
 pub mod collection_cache;
 pub mod collection_transaction;
 pub mod common;
+pub mod compaction;
 pub mod crypto;
 pub mod dot_product;
 pub mod durable_wal;
+pub mod embeddings;
 pub mod encoding_format;
+pub mod expiry;
+pub mod f32_values;
 pub mod file_persist;
 pub mod fixedset;
+pub mod http_client;
+pub mod idempotency;
 pub mod index_file_manager;
+pub mod index_rebuild;
 pub mod indexing_manager;
 pub mod inverted_index;
 pub mod kmeans;
 pub mod lazy_item;
+#[cfg(feature = "local-embeddings")]
+pub mod local_embeddings;
+pub mod lmdb_map;
 pub mod lru_cache;
 pub mod meta_persist;
+pub mod metrics;
 pub mod paths;
 pub mod prob_node;
+pub mod recovery;
+pub mod replication;
 pub mod rpc;
 pub mod schema_traits;
 pub mod serializer;
+pub mod snapshot;
 pub mod sparse_ann_query;
+pub mod storage_backend;
+pub mod storage_sync;
+pub mod tenants;
 pub mod tf_idf_index;
+pub mod tracing;
+pub mod transaction_timeout;
 pub mod tree_map;
 pub mod types;
 pub mod user;
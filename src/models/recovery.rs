@@ -0,0 +1,94 @@
+// Restart recovery
+//
+// The versions committed but not yet indexed when the server stopped are
+// indexed again from their WALs in the background, once the server has
+// started. The server reports being ready once all collections are
+// recovered, and the background tasks that modify collections wait for it.
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Instant,
+};
+
+use crate::app_context::AppContext;
+
+use super::indexing_manager::IndexingManager;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecoveryStatus {
+    Recovering { pending_collections: usize },
+    Ready,
+    Failed(String),
+}
+
+static STATUS: Mutex<RecoveryStatus> = Mutex::new(RecoveryStatus::Recovering {
+    pending_collections: 0,
+});
+static STATUS_CHANGED: Condvar = Condvar::new();
+
+fn set_status(status: RecoveryStatus) {
+    *STATUS.lock().unwrap() = status;
+    STATUS_CHANGED.notify_all();
+}
+
+pub fn status() -> RecoveryStatus {
+    STATUS.lock().unwrap().clone()
+}
+
+/// Blocks until the recovery finishes, returns `false` if it failed
+pub fn wait_until_ready() -> bool {
+    let status = STATUS_CHANGED
+        .wait_while(STATUS.lock().unwrap(), |status| {
+            matches!(status, RecoveryStatus::Recovering { .. })
+        })
+        .unwrap();
+    *status == RecoveryStatus::Ready
+}
+
+/// Indexes the pending versions of the loaded collections in the background
+pub fn spawn_recovery(ctx: Arc<AppContext>) {
+    let collections: Vec<_> = ctx
+        .ain_env
+        .collections_map
+        .iter_collections()
+        .map(|collection| collection.value().clone())
+        .collect();
+    set_status(RecoveryStatus::Recovering {
+        pending_collections: collections.len(),
+    });
+
+    thread::Builder::new()
+        .name("recovery".to_string())
+        .spawn(move || {
+            let start = Instant::now();
+            for (idx, collection) in collections.iter().enumerate() {
+                if let Err(err) = IndexingManager::index_pending_versions(
+                    collection,
+                    &ctx.config,
+                    &ctx.threadpool,
+                ) {
+                    log::error!(
+                        "Failed to recover collection '{}': {}",
+                        collection.meta.name,
+                        err
+                    );
+                    set_status(RecoveryStatus::Failed(format!(
+                        "Failed to recover collection '{}': {}",
+                        collection.meta.name, err
+                    )));
+                    return;
+                }
+                set_status(RecoveryStatus::Recovering {
+                    pending_collections: collections.len() - idx - 1,
+                });
+            }
+            log::info!(
+                "Recovered {} collections in {:.2?}",
+                collections.len(),
+                start.elapsed()
+            );
+            set_status(RecoveryStatus::Ready);
+        })
+        .expect("Failed to spawn the recovery thread");
+}
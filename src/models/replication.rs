@@ -0,0 +1,694 @@
+// Leader-follower replication of collections
+//
+// Collections created with a `replication_factor` above 1 are replicated
+// from the leader to its followers, which serve searches on them. Instead
+// of being deleted once indexed, the WAL file of every committed version is
+// kept in the collection's `replication_log` directory, next to a copy of
+// its `VersionInfo`. Followers poll the leader for the versions they
+// haven't applied yet and replay them through the `IndexingManager`, the
+// same way versions are replayed on restart, so that they end up with the
+// leader's version history. A follower without a replica of a collection,
+// or too far behind for the log, restores a snapshot of it first.
+//
+// Versions are pruned from the log once the followers have applied them,
+// and past `replication.max_log_versions` regardless.
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use utoipa::ToSchema;
+
+use crate::{
+    app_context::AppContext,
+    config_loader::{Config, ReplicationRole},
+};
+
+use super::{
+    collection::Collection,
+    collection_transaction::{ExplicitTransactionID, TransactionStatus},
+    common::WaCustomError,
+    http_client::{uri_encode, HttpClient, Response},
+    indexing_manager::IndexingManager,
+    meta_persist::update_current_version,
+    paths::get_data_path,
+    recovery,
+    types::AppEnv,
+    versioning::{VersionInfo, VersionNumber, VersionSource},
+};
+
+pub const REPLICATION_LOG_DIR: &str = "replication_log";
+// created in the directory of the collections a follower restored from the
+// leader, the others are left alone
+pub const REPLICA_MARKER: &str = ".replica";
+// holds the version the log has been pruned through, as a little endian u32
+const PRUNED_MARKER: &str = "pruned";
+// limit on the WAL bytes returned by a single request for versions
+const MAX_BATCH_BYTES: usize = 64 * 1024 * 1024;
+// followers not heard from for longer aren't counted as in sync, and don't
+// hold back the pruning of the log
+const FOLLOWER_TIMEOUT_SECS: i64 = 30;
+
+fn fs_error(err: io::Error) -> WaCustomError {
+    WaCustomError::FsError(err.to_string())
+}
+
+/// Whether the collection is replicated to the followers of the leader
+pub fn is_replicated(collection: &Collection) -> bool {
+    collection.meta.config.replication_factor.unwrap_or(1) > 1
+}
+
+/// Whether the collection was restored from the leader by a follower
+pub fn is_replica(collection: &Collection) -> bool {
+    collection.get_path().join(REPLICA_MARKER).exists()
+}
+
+/// Names of the indexes the collection has
+pub fn index_names(collection: &Collection) -> Vec<String> {
+    let mut names = Vec::new();
+    if collection.hnsw_index.read().is_some() {
+        names.push("dense".to_string());
+    }
+    if collection.inverted_index.read().is_some() {
+        names.push("sparse".to_string());
+    }
+    if collection.tf_idf_index.read().is_some() {
+        names.push("tf-idf".to_string());
+    }
+    names
+}
+
+fn log_dir(collection: &Collection) -> PathBuf {
+    collection.get_path().join(REPLICATION_LOG_DIR)
+}
+
+/// Disposes of the WAL file of a version once it has been indexed
+///
+/// On the leader, the WAL files of replicated collections are moved to the
+/// replication log, they are deleted otherwise.
+pub fn retire_wal(
+    collection: &Collection,
+    config: &Config,
+    version: VersionNumber,
+) -> Result<(), WaCustomError> {
+    let wal_path = collection.get_path().join(format!("{}.wal", *version));
+    if config.replication.role != ReplicationRole::Leader || !is_replicated(collection) {
+        return fs::remove_file(wal_path).map_err(fs_error);
+    }
+
+    let dir = log_dir(collection);
+    fs::create_dir_all(&dir).map_err(fs_error)?;
+    // the info is written first, a WAL file in the log always has one
+    let info = collection.vcs.get_version(version)?;
+    fs::write(dir.join(format!("{}.info", *version)), info.serialize()).map_err(fs_error)?;
+    fs::rename(wal_path, dir.join(format!("{}.wal", *version))).map_err(fs_error)?;
+
+    let max_log_versions = config.replication.max_log_versions;
+    if *version > max_log_versions {
+        let through = *version - max_log_versions;
+        if through > pruned_through(collection).map_err(fs_error)? {
+            prune_log(collection, through).map_err(fs_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Version the replication log of the collection has been pruned through,
+/// followers that haven't applied it must restore a snapshot
+pub fn pruned_through(collection: &Collection) -> io::Result<u32> {
+    match fs::read(log_dir(collection).join(PRUNED_MARKER)) {
+        Ok(bytes) => bytes
+            .try_into()
+            .map(u32::from_le_bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Malformed pruned marker")),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err),
+    }
+}
+
+// Versions in the log, unordered
+fn log_versions(dir: &Path, extension: &str) -> io::Result<Vec<u32>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut versions = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(extension) {
+            continue;
+        }
+        if let Some(version) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        {
+            versions.push(version);
+        }
+    }
+    Ok(versions)
+}
+
+/// Removes the versions up to and including `through` from the replication
+/// log of the collection
+pub fn prune_log(collection: &Collection, through: u32) -> io::Result<()> {
+    let dir = log_dir(collection);
+    fs::create_dir_all(&dir)?;
+    // recorded first, so that followers are never served a partial log
+    fs::write(dir.join(PRUNED_MARKER), through.to_le_bytes())?;
+    for extension in ["wal", "info"] {
+        for version in log_versions(&dir, extension)? {
+            if version <= through {
+                fs::remove_file(dir.join(format!("{}.{}", version, extension)))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the versions following `after` from the replication log, stopping
+/// at the first one missing or once enough WAL bytes have been read
+pub fn read_log(
+    collection: &Collection,
+    after: u32,
+) -> Result<Vec<ReplicatedVersion>, WaCustomError> {
+    let dir = log_dir(collection);
+    let mut versions = Vec::new();
+    let mut batch_bytes = 0;
+    let mut version = after;
+    while batch_bytes < MAX_BATCH_BYTES {
+        version += 1;
+        let wal = match fs::read(dir.join(format!("{}.wal", version))) {
+            Ok(wal) => wal,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => break,
+            Err(err) => return Err(fs_error(err)),
+        };
+        let info = fs::read(dir.join(format!("{}.info", version))).map_err(fs_error)?;
+        let info = VersionInfo::deserialize(&info)
+            .map_err(|err| WaCustomError::DeserializationError(err.to_string()))?;
+        batch_bytes += wal.len();
+        versions.push(ReplicatedVersion::new(&info, &wal));
+    }
+    Ok(versions)
+}
+
+/// A collection replicated by the leader
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReplicatedCollection {
+    pub name: String,
+    pub replication_factor: u32,
+    /// Current version of the collection on the leader
+    pub version: u32,
+    /// Indexes of the collection, followers restore a snapshot when they
+    /// change
+    pub indexes: Vec<String>,
+}
+
+/// A committed version of a replicated collection
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReplicatedVersion {
+    pub version: u32,
+    /// Set if the version was created by an explicit transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<ExplicitTransactionID>,
+    /// Set if the version was created by an implicit transaction epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epoch_id: Option<u32>,
+    #[schema(value_type = String, example = "2023-01-01T12:00:00Z")]
+    pub created_at: DateTime<Utc>,
+    pub records_upserted: u32,
+    pub records_deleted: u32,
+    pub total_operations: u32,
+    /// Base64 encoded WAL file of the version
+    pub wal: String,
+}
+
+impl ReplicatedVersion {
+    fn new(info: &VersionInfo, wal: &[u8]) -> Self {
+        let (transaction_id, epoch_id) = match info.source {
+            VersionSource::Explicit { transaction_id } => (Some(transaction_id), None),
+            VersionSource::Implicit { epoch_id } => (None, Some(epoch_id)),
+        };
+        Self {
+            version: *info.version,
+            transaction_id,
+            epoch_id,
+            created_at: info.created_at,
+            records_upserted: info.records_upserted,
+            records_deleted: info.records_deleted,
+            total_operations: info.total_operations,
+            wal: STANDARD.encode(wal),
+        }
+    }
+}
+
+/// Versions of a collection a follower hasn't applied yet
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReplicationBatch {
+    /// Current version of the collection on the leader
+    pub leader_version: u32,
+    /// Consecutive versions, an empty list means the follower is up to date
+    pub versions: Vec<ReplicatedVersion>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FollowerProgress {
+    pub last_seen: DateTime<Utc>,
+    // last version applied, by collection
+    pub applied: HashMap<String, u32>,
+}
+
+impl FollowerProgress {
+    pub fn is_active(&self) -> bool {
+        (Utc::now() - self.last_seen).num_seconds() <= FOLLOWER_TIMEOUT_SECS
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LeaderContact {
+    pub last_contact: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    // current version of the collections on the leader
+    pub versions: HashMap<String, u32>,
+}
+
+/// Replication progress, of the followers on the leader and of the node
+/// itself on followers
+#[derive(Default)]
+pub struct ReplicationState {
+    followers: DashMap<String, FollowerProgress>,
+    leader: RwLock<LeaderContact>,
+}
+
+impl ReplicationState {
+    pub fn record_follower(&self, node_id: &str) {
+        self.followers
+            .entry(node_id.to_string())
+            .and_modify(|follower| follower.last_seen = Utc::now())
+            .or_insert_with(|| FollowerProgress {
+                last_seen: Utc::now(),
+                applied: HashMap::new(),
+            });
+    }
+
+    pub fn record_progress(&self, node_id: &str, collection: &str, applied: u32) {
+        self.record_follower(node_id);
+        if let Some(mut follower) = self.followers.get_mut(node_id) {
+            follower.applied.insert(collection.to_string(), applied);
+        }
+    }
+
+    /// Followers by node id
+    pub fn followers(&self) -> Vec<(String, FollowerProgress)> {
+        let mut followers: Vec<_> = self
+            .followers
+            .iter()
+            .map(|follower| (follower.key().clone(), follower.value().clone()))
+            .collect();
+        followers.sort_by(|a, b| a.0.cmp(&b.0));
+        followers
+    }
+
+    /// Active followers that applied the collection's latest version
+    pub fn in_sync_replicas(&self, collection: &str, version: u32) -> u32 {
+        self.followers
+            .iter()
+            .filter(|follower| {
+                follower.is_active() && follower.applied.get(collection) == Some(&version)
+            })
+            .count() as u32
+    }
+
+    /// Latest version of the collection all its replicas have applied, if
+    /// enough active followers have replicas for its replication factor
+    pub fn applied_by_all(&self, collection: &str, replication_factor: u32) -> Option<u32> {
+        let applied: Vec<u32> = self
+            .followers
+            .iter()
+            .filter(|follower| follower.is_active())
+            .filter_map(|follower| follower.applied.get(collection).copied())
+            .collect();
+        if (applied.len() as u32) < replication_factor.saturating_sub(1) {
+            return None;
+        }
+        applied.into_iter().min()
+    }
+
+    pub fn leader(&self) -> LeaderContact {
+        self.leader.read().clone()
+    }
+
+    fn record_contact(&self, result: &Result<(), SyncError>) {
+        let mut leader = self.leader.write();
+        match result {
+            Ok(()) => {
+                leader.last_contact = Some(Utc::now());
+                leader.last_error = None;
+            }
+            Err(err) => {
+                let err = err.to_string();
+                if leader.last_error.as_ref() != Some(&err) {
+                    log::error!("Failed to sync with the replication leader: {}", err);
+                }
+                leader.last_error = Some(err);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SyncError {
+    Io(io::Error),
+    Leader(u16, String),
+    Collection(WaCustomError),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Request to the leader failed: {}", err),
+            Self::Leader(status, msg) => {
+                write!(f, "Leader responded with status {}: {}", status, msg)
+            }
+            Self::Collection(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SyncError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<WaCustomError> for SyncError {
+    fn from(err: WaCustomError) -> Self {
+        Self::Collection(err)
+    }
+}
+
+impl From<lmdb::Error> for SyncError {
+    fn from(err: lmdb::Error) -> Self {
+        Self::Collection(err.into())
+    }
+}
+
+struct LeaderClient {
+    http: HttpClient,
+    api_key: String,
+    node_id: String,
+}
+
+impl LeaderClient {
+    /// Sends a GET request for `path` and writes the body of the response to
+    /// `sink`, returns `None` if the leader responds that the follower must
+    /// restore the collection from a snapshot
+    fn get(&self, path: &str, sink: &mut dyn Write) -> Result<Option<Response>, SyncError> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let target = format!(
+            "{}{}follower_id={}",
+            path,
+            separator,
+            uri_encode(&self.node_id, false)
+        );
+        let headers = [("authorization", format!("Bearer {}", self.api_key))];
+        let response = self.http.send_to("GET", &target, &headers, None, sink)?;
+        match response.status {
+            409 | 410 => Ok(None),
+            _ => Ok(Some(response)),
+        }
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>, SyncError> {
+        let mut body = Vec::new();
+        let Some(response) = self.get(path, &mut body)? else {
+            return Ok(None);
+        };
+        if !response.is_success() {
+            return Err(SyncError::Leader(
+                response.status,
+                String::from_utf8_lossy(&body).into_owned(),
+            ));
+        }
+        serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|err| SyncError::Io(io::Error::new(io::ErrorKind::InvalidData, err)))
+    }
+}
+
+/// Starts replicating the collections of the leader, if the node is a
+/// follower
+pub fn spawn_follower(ctx: Arc<AppContext>) -> Result<(), WaCustomError> {
+    let replication = &ctx.config.replication;
+    if replication.role != ReplicationRole::Follower {
+        return Ok(());
+    }
+    let leader_url = replication.leader_url.as_deref().ok_or_else(|| {
+        WaCustomError::ConfigError("replication.leader_url is required on followers".to_string())
+    })?;
+    let api_key = replication.api_key.clone().ok_or_else(|| {
+        WaCustomError::ConfigError("replication.api_key is required on followers".to_string())
+    })?;
+    let http = HttpClient::new(leader_url, replication.ca_file.as_deref())
+        .map_err(|err| WaCustomError::ConfigError(err.to_string()))?;
+    let node_id = replication
+        .node_id
+        .clone()
+        .unwrap_or_else(|| format!("{}:{}", ctx.config.server.host, ctx.config.server.port));
+    let client = LeaderClient {
+        http,
+        api_key,
+        node_id,
+    };
+    let poll_interval = Duration::from_millis(replication.poll_interval_ms);
+
+    log::info!("Replicating collections from the leader at {}", leader_url);
+    thread::Builder::new()
+        .name("replication".to_string())
+        .spawn(move || {
+            if !recovery::wait_until_ready() {
+                return;
+            }
+            loop {
+                let result = sync_with_leader(&ctx, &client);
+                ctx.replication.record_contact(&result);
+                thread::sleep(poll_interval);
+            }
+        })
+        .map_err(fs_error)?;
+    Ok(())
+}
+
+fn sync_with_leader(ctx: &AppContext, client: &LeaderClient) -> Result<(), SyncError> {
+    let collections: Vec<ReplicatedCollection> = client
+        .get_json("/vectordb/replication/collections")?
+        .ok_or_else(|| SyncError::Leader(409, "Unexpected conflict".to_string()))?;
+
+    // replicas of the collections the leader deleted, or stopped replicating
+    let dropped: Vec<_> = ctx
+        .ain_env
+        .collections_map
+        .iter_collections()
+        .map(|collection| collection.value().clone())
+        .filter(|collection| {
+            is_replica(collection)
+                && !collections
+                    .iter()
+                    .any(|remote| remote.name == collection.meta.name)
+        })
+        .collect();
+    for collection in dropped {
+        log::info!("Dropping replica of collection '{}'", collection.meta.name);
+        drop_replica(&ctx.ain_env, &collection)?;
+        ctx.replication
+            .leader
+            .write()
+            .versions
+            .remove(&collection.meta.name);
+    }
+
+    for remote in &collections {
+        sync_collection(ctx, client, remote)?;
+    }
+    Ok(())
+}
+
+fn sync_collection(
+    ctx: &AppContext,
+    client: &LeaderClient,
+    remote: &ReplicatedCollection,
+) -> Result<(), SyncError> {
+    let mut restored = false;
+    let mut collection = match ctx.ain_env.collections_map.get_collection(&remote.name) {
+        Some(collection) if !is_replica(&collection) => {
+            return Err(SyncError::Collection(WaCustomError::InvalidData(format!(
+                "Collection '{}' exists and isn't a replica",
+                remote.name
+            ))));
+        }
+        Some(collection) if index_names(&collection) == remote.indexes => collection,
+        _ => {
+            restored = true;
+            restore_replica(ctx, client, &remote.name)?
+        }
+    };
+
+    loop {
+        let after = **collection.current_version.read();
+        let path = format!(
+            "/vectordb/replication/collections/{}/versions?after={}",
+            uri_encode(&remote.name, false),
+            after
+        );
+        match client.get_json::<ReplicationBatch>(&path)? {
+            Some(batch) => {
+                ctx.replication
+                    .leader
+                    .write()
+                    .versions
+                    .insert(remote.name.clone(), batch.leader_version);
+                if batch.versions.is_empty() {
+                    return Ok(());
+                }
+                for version in batch.versions {
+                    apply_version(ctx, &collection, version)?;
+                }
+            }
+            // restored at most once per poll, a conflict right after a
+            // restore is retried on the next one
+            None if !restored => {
+                log::info!(
+                    "Replica of collection '{}' is out of date, restoring it from the leader",
+                    remote.name
+                );
+                restored = true;
+                collection = restore_replica(ctx, client, &remote.name)?;
+            }
+            None => {
+                return Err(SyncError::Leader(
+                    409,
+                    format!("Collection '{}' changed while restoring it", remote.name),
+                ))
+            }
+        }
+    }
+}
+
+fn drop_replica(ain_env: &AppEnv, collection: &Collection) -> Result<(), WaCustomError> {
+    let collections_map = &ain_env.collections_map;
+    collection.delete(&ain_env.persist, collections_map.lmdb_collections_db)?;
+    collections_map.remove_collection(&collection.meta.name)?;
+    Ok(())
+}
+
+fn restore_replica(
+    ctx: &AppContext,
+    client: &LeaderClient,
+    name: &str,
+) -> Result<Arc<Collection>, SyncError> {
+    let snapshots_path = get_data_path().join("snapshots");
+    fs::create_dir_all(&snapshots_path)?;
+    let mut file = NamedTempFile::new_in(&snapshots_path)?;
+    let path = format!(
+        "/vectordb/replication/collections/{}/snapshot",
+        uri_encode(name, false)
+    );
+    match client.get(&path, file.as_file_mut())? {
+        Some(response) if response.is_success() => {}
+        Some(response) => {
+            return Err(SyncError::Leader(
+                response.status,
+                format!("Failed to download a snapshot of collection '{}'", name),
+            ))
+        }
+        None => {
+            return Err(SyncError::Leader(
+                409,
+                format!("Collection '{}' is busy on the leader", name),
+            ))
+        }
+    }
+    file.as_file_mut().flush()?;
+
+    if let Some(collection) = ctx.ain_env.collections_map.get_collection(name) {
+        drop_replica(&ctx.ain_env, &collection)?;
+    }
+    let collection = ctx.ain_env.collections_map.restore_snapshot(
+        file.path(),
+        name,
+        &ctx.config,
+        &ctx.threadpool,
+    )?;
+    fs::File::create(collection.get_path().join(REPLICA_MARKER))?;
+    log::info!(
+        "Restored replica of collection '{}' at version {}",
+        name,
+        **collection.current_version.read()
+    );
+    Ok(collection)
+}
+
+// Commits a version of the leader and indexes it, as if replayed on restart
+fn apply_version(
+    ctx: &AppContext,
+    collection: &Collection,
+    replicated: ReplicatedVersion,
+) -> Result<(), SyncError> {
+    // compaction of the replica must not run meanwhile
+    let _compaction_guard = collection.compaction_lock.lock();
+    let current_version = *collection.current_version.read();
+    if replicated.version != *current_version + 1 {
+        return Err(SyncError::Collection(WaCustomError::InvalidData(format!(
+            "Expected version {} of collection '{}', got {}",
+            *current_version + 1,
+            collection.meta.name,
+            replicated.version
+        ))));
+    }
+    let wal = STANDARD.decode(&replicated.wal).map_err(|err| {
+        SyncError::Collection(WaCustomError::DeserializationError(err.to_string()))
+    })?;
+    let version = VersionNumber::from(replicated.version);
+    fs::write(collection.get_path().join(format!("{}.wal", *version)), wal)?;
+
+    *collection.last_allotted_version.write() = version;
+    match replicated.transaction_id {
+        Some(transaction_id) => {
+            collection.transaction_status_map.insert(
+                current_version,
+                &transaction_id,
+                RwLock::new(TransactionStatus::NotStarted {
+                    last_updated: Utc::now(),
+                }),
+            );
+            collection.vcs.set_current_version_explicit(
+                version,
+                transaction_id,
+                replicated.records_upserted,
+                replicated.records_deleted,
+                replicated.total_operations,
+            )?;
+        }
+        None => collection
+            .vcs
+            .set_current_version_implicit(version, replicated.epoch_id.unwrap_or_default())?,
+    }
+    *collection.current_version.write() = version;
+    update_current_version(&collection.lmdb, version)?;
+
+    IndexingManager::index_version_on_restart(collection, &ctx.config, &ctx.threadpool, version)?;
+    Ok(())
+}
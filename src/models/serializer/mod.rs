@@ -13,6 +13,7 @@ mod tests;
 
 use std::io;
 
+use chrono::{DateTime, Utc};
 use parking_lot::RwLock;
 
 use super::buffered_io::{BufIoError, BufferManager};
@@ -56,6 +57,51 @@ pub fn read_len(bufman: &BufferManager, cursor: u64) -> Result<u32, BufIoError>
     Ok(low14 | (b2 << 14))
 }
 
+/// Writes the id of a vector, preceded by its expiry if it has one
+///
+/// The expiry is marked by an empty id, followed by a flag and the expiry
+/// in milliseconds since the epoch, so that vectors written before expiries
+/// existed are read as is. Empty ids are written after the marker too.
+pub fn write_vector_id(buf: &mut Vec<u8>, id: &VectorId, expires_at: Option<DateTime<Utc>>) {
+    if expires_at.is_some() || id.is_empty() {
+        write_len(buf, 0);
+        match expires_at {
+            Some(expires_at) => {
+                buf.push(1);
+                buf.extend(expires_at.timestamp_millis().to_le_bytes());
+            }
+            None => buf.push(0),
+        }
+    }
+    write_len(buf, id.len() as u32);
+    buf.extend(id.as_bytes());
+}
+
+/// Reads the id and expiry of a vector written by `write_vector_id`
+pub fn read_vector_id(
+    bufman: &BufferManager,
+    cursor: u64,
+) -> Result<(VectorId, Option<DateTime<Utc>>), BufIoError> {
+    let id = read_string(bufman, cursor)?;
+    if !id.is_empty() {
+        return Ok((VectorId::from(id), None));
+    }
+    let expires_at = match bufman.read_u8_with_cursor(cursor)? {
+        0 => None,
+        _ => Some(decode_expiry(bufman.read_i64_with_cursor(cursor)?)?),
+    };
+    Ok((VectorId::from(read_string(bufman, cursor)?), expires_at))
+}
+
+pub fn decode_expiry(millis: i64) -> Result<DateTime<Utc>, BufIoError> {
+    DateTime::from_timestamp_millis(millis).ok_or_else(|| {
+        BufIoError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid expiry `{}`", millis),
+        ))
+    })
+}
+
 pub fn read_string(bufman: &BufferManager, cursor: u64) -> Result<String, BufIoError> {
     let len = read_len(bufman, cursor)? as usize;
     let mut buf = vec![0; len];
@@ -6,17 +6,19 @@ use crate::{
     models::{
         buffered_io::{BufIoError, BufferManager},
         collection::RawVectorEmbedding,
-        types::{DocumentId, FileOffset, VectorId},
+        types::{DocumentId, FileOffset},
     },
 };
 
-use super::{read_len, read_opt_string, read_string, write_len, SimpleSerialize};
+use super::{
+    read_len, read_opt_string, read_string, read_vector_id, write_len, write_vector_id,
+    SimpleSerialize,
+};
 
 impl SimpleSerialize for RawVectorEmbedding {
     fn serialize(&self, bufman: &BufferManager, cursor: u64) -> Result<u32, BufIoError> {
         let mut buf = Vec::new();
-        write_len(&mut buf, self.id.len() as u32);
-        buf.extend(self.id.as_bytes());
+        write_vector_id(&mut buf, &self.id, self.expires_at);
         if let Some(document_id) = &self.document_id {
             write_len(&mut buf, document_id.len() as u32);
             buf.extend(document_id.as_bytes());
@@ -76,7 +78,7 @@ impl SimpleSerialize for RawVectorEmbedding {
     fn deserialize(bufman: &BufferManager, offset: FileOffset) -> Result<Self, BufIoError> {
         let cursor = bufman.open_cursor()?;
         bufman.seek_with_cursor(cursor, offset.0 as u64)?;
-        let id = VectorId::from(read_string(bufman, cursor)?);
+        let (id, expires_at) = read_vector_id(bufman, cursor)?;
         let document_id = read_opt_string(bufman, cursor)?.map(DocumentId::from);
         let dense_values_len = read_len(bufman, cursor)? as usize;
         let dense_values = if dense_values_len == 0 {
@@ -140,6 +142,7 @@ impl SimpleSerialize for RawVectorEmbedding {
             metadata,
             sparse_values,
             text,
+            expires_at,
         })
     }
 }
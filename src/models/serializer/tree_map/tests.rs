@@ -107,6 +107,7 @@ fn random_raw_vector_embedding(rng: &mut impl Rng) -> RawVectorEmbedding {
         metadata: None,
         sparse_values: None,
         text: None,
+        expires_at: None,
     }
 }
 
@@ -0,0 +1,457 @@
+// Self-contained snapshots of collections
+//
+// A snapshot archive holds everything needed to recreate a collection as of
+// a committed version: its metadata, the entries of its own LMDB database
+// (version control, current and background versions, id counters, ...), the
+// parameters of its indexes and every file in its directory. It's laid out
+// as
+//
+//   magic, format version (u8)
+//   manifest length (u32), CBOR encoded `SnapshotManifest`
+//   entries, each a kind byte followed by its fields
+//   end marker (u8)
+//
+// with all integers little endian.
+
+use std::{
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use lmdb::{Cursor, Database, Transaction, WriteFlags};
+use rayon::ThreadPool;
+use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher24;
+
+use crate::config_loader::Config;
+
+use super::{
+    collection::{Collection, CollectionMetadata},
+    common::WaCustomError,
+    indexing_manager::IndexingManager,
+    lmdb_map::{txn_guard, with_rw_txn},
+    paths::get_data_path,
+    replication::{REPLICATION_LOG_DIR, REPLICA_MARKER},
+    storage_backend,
+    types::{get_collections_path, CollectionsMap, MetaDb},
+};
+
+const MAGIC: &[u8; 8] = b"COSDSNAP";
+const FORMAT_VERSION: u8 = 1;
+pub const SNAPSHOT_EXTENSION: &str = "snapshot";
+
+const ENTRY_META_DB: u8 = 0;
+const ENTRY_INDEX_DATA: u8 = 1;
+const ENTRY_FILE: u8 = 2;
+const ENTRY_END: u8 = u8::MAX;
+
+const INDEX_HNSW: u8 = 0;
+const INDEX_INVERTED: u8 = 1;
+const INDEX_TF_IDF: u8 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub metadata: CollectionMetadata,
+    // version of the collection the snapshot was taken at
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Directory the snapshots of `collection_name` are stored in
+pub fn get_snapshots_path(collection_name: &str) -> PathBuf {
+    get_data_path().join("snapshots").join(collection_name)
+}
+
+/// Snapshot names become file names, they may only contain ASCII
+/// alphanumerics, `-`, `_` and `.`, and can't start with a `.`
+pub fn is_valid_snapshot_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn fs_error(err: io::Error) -> WaCustomError {
+    WaCustomError::FsError(err.to_string())
+}
+
+fn invalid_archive(msg: &str) -> WaCustomError {
+    WaCustomError::InvalidData(format!("Invalid snapshot archive: {}", msg))
+}
+
+// same key the collection and its indexes are stored under in the shared
+// databases
+fn name_key(name: &str) -> [u8; 8] {
+    let mut hasher = SipHasher24::new();
+    hasher.write(name.as_bytes());
+    hasher.finish().to_le_bytes()
+}
+
+impl CollectionsMap {
+    fn index_db(&self, index: u8) -> Result<Database, WaCustomError> {
+        match index {
+            INDEX_HNSW => Ok(self.lmdb_hnsw_index_db),
+            INDEX_INVERTED => Ok(self.lmdb_inverted_index_db),
+            INDEX_TF_IDF => Ok(self.lmdb_tf_idf_index_db),
+            _ => Err(invalid_archive("unknown index kind")),
+        }
+    }
+
+    /// Writes a snapshot of the collection to `path`
+    ///
+    /// Writes to the collection are blocked while the snapshot is taken, see
+    /// `Collection::with_writes_blocked`.
+    pub fn write_snapshot(
+        &self,
+        collection: &Collection,
+        config: &Config,
+        path: &Path,
+    ) -> Result<SnapshotManifest, WaCustomError> {
+        collection.with_writes_blocked(config, || {
+            let manifest = SnapshotManifest {
+                metadata: collection.meta.clone(),
+                version: **collection.current_version.read(),
+                created_at: Utc::now(),
+            };
+
+            // written next to the final path and renamed, so that a failed
+            // snapshot doesn't leave a truncated archive behind
+            let tmp_path = path.with_extension("tmp");
+            let result = (|| {
+                let mut writer = BufWriter::new(File::create(&tmp_path).map_err(fs_error)?);
+                self.write_archive(collection, &manifest, &mut writer)?;
+                writer
+                    .into_inner()
+                    .map_err(|err| fs_error(err.into_error()))?
+                    .sync_all()
+                    .map_err(fs_error)
+            })();
+            if let Err(err) = result {
+                let _ = fs::remove_file(&tmp_path);
+                return Err(err);
+            }
+            fs::rename(&tmp_path, path).map_err(fs_error)?;
+            Ok(manifest)
+        })
+    }
+
+    fn write_archive(
+        &self,
+        collection: &Collection,
+        manifest: &SnapshotManifest,
+        writer: &mut impl Write,
+    ) -> Result<(), WaCustomError> {
+        let manifest_bytes = serde_cbor::to_vec(manifest)
+            .map_err(|e| WaCustomError::SerializationError(e.to_string()))?;
+        writer.write_all(MAGIC).map_err(fs_error)?;
+        writer.write_all(&[FORMAT_VERSION]).map_err(fs_error)?;
+        write_bytes(writer, &manifest_bytes)?;
+
+        {
+            let _guard = txn_guard();
+            let txn = self.lmdb_env.begin_ro_txn()?;
+            let mut cursor = txn.open_ro_cursor(collection.lmdb.db)?;
+            for (key, value) in cursor.iter_start() {
+                writer.write_all(&[ENTRY_META_DB]).map_err(fs_error)?;
+                write_bytes(writer, key)?;
+                write_bytes(writer, value)?;
+            }
+            drop(cursor);
+
+            let key = name_key(&collection.meta.name);
+            for index in [INDEX_HNSW, INDEX_INVERTED, INDEX_TF_IDF] {
+                let value = match txn.get(self.index_db(index)?, &key) {
+                    Ok(value) => value,
+                    Err(lmdb::Error::NotFound) => continue,
+                    Err(err) => return Err(err.into()),
+                };
+                writer
+                    .write_all(&[ENTRY_INDEX_DATA, index])
+                    .map_err(fs_error)?;
+                write_bytes(writer, value)?;
+            }
+        }
+
+        write_files(writer, &collection.get_path(), Path::new(""))?;
+        writer.write_all(&[ENTRY_END]).map_err(fs_error)?;
+        Ok(())
+    }
+
+    /// Creates a collection named `name` from the snapshot at `path`
+    ///
+    /// The caller must make sure no collection with that name exists.
+    pub fn restore_snapshot(
+        &self,
+        path: &Path,
+        name: &str,
+        config: &Arc<Config>,
+        threadpool: &Arc<ThreadPool>,
+    ) -> Result<Arc<Collection>, WaCustomError> {
+        let mut reader = BufReader::new(File::open(path).map_err(fs_error)?);
+        let mut manifest = read_header(&mut reader)?;
+        manifest.metadata.name = name.to_string();
+
+        // the files are unpacked into a staging directory, any leftovers of
+        // a deleted collection with the same name are only replaced once the
+        // archive has been read in full
+        let collection_path = get_collections_path().join(name);
+        let staging_path = get_collections_path().join(format!("{}.restoring", name));
+        if staging_path.exists() {
+            fs::remove_dir_all(&staging_path).map_err(fs_error)?;
+        }
+        fs::create_dir_all(&staging_path).map_err(fs_error)?;
+
+        let lmdb = MetaDb::from_env(self.lmdb_env.clone(), name)?;
+        let result = self.read_entries(&mut reader, &lmdb, name, &staging_path);
+        if let Err(err) = result {
+            let _ = fs::remove_dir_all(&staging_path);
+            let _ = with_rw_txn(&self.lmdb_env, |txn| txn.clear_db(lmdb.db));
+            return Err(err);
+        }
+
+        if collection_path.exists() {
+            fs::remove_dir_all(&collection_path).map_err(fs_error)?;
+        }
+        fs::rename(&staging_path, &collection_path).map_err(fs_error)?;
+
+        let metadata_bytes = serde_cbor::to_vec(&manifest.metadata)
+            .map_err(|e| WaCustomError::SerializationError(e.to_string()))?;
+        with_rw_txn(&self.lmdb_env, |txn| {
+            txn.put(
+                self.lmdb_collections_db,
+                &name_key(name),
+                &metadata_bytes,
+                WriteFlags::empty(),
+            )
+        })?;
+
+        let collection = self.load_collection(manifest.metadata, config, threadpool)?;
+        IndexingManager::index_pending_versions(&collection, config, threadpool)?;
+        self.insert_collection(collection.clone())?;
+        Ok(collection)
+    }
+
+    fn read_entries(
+        &self,
+        reader: &mut impl Read,
+        lmdb: &MetaDb,
+        name: &str,
+        staging_path: &Path,
+    ) -> Result<(), WaCustomError> {
+        let mut meta_entries = Vec::new();
+        let mut index_entries = Vec::new();
+
+        loop {
+            match read_u8(reader)? {
+                ENTRY_META_DB => {
+                    let key = read_bytes(reader)?;
+                    let value = read_bytes(reader)?;
+                    meta_entries.push((key, value));
+                }
+                ENTRY_INDEX_DATA => {
+                    let db = self.index_db(read_u8(reader)?)?;
+                    index_entries.push((db, read_bytes(reader)?));
+                }
+                ENTRY_FILE => {
+                    let relative_path = String::from_utf8(read_bytes(reader)?)
+                        .map_err(|_| invalid_archive("file path is not valid UTF-8"))?;
+                    let relative_path = Path::new(&relative_path);
+                    // never write outside of the collection's directory
+                    if !relative_path
+                        .components()
+                        .all(|component| matches!(component, Component::Normal(_)))
+                    {
+                        return Err(invalid_archive("file path escapes the collection"));
+                    }
+                    let file_path = staging_path.join(relative_path);
+                    if let Some(parent) = file_path.parent() {
+                        fs::create_dir_all(parent).map_err(fs_error)?;
+                    }
+                    let len = read_u64(reader)?;
+                    let mut file = File::create(&file_path).map_err(fs_error)?;
+                    let copied = io::copy(&mut reader.take(len), &mut file).map_err(fs_error)?;
+                    if copied != len {
+                        return Err(invalid_archive("unexpected end of file"));
+                    }
+                }
+                ENTRY_END => break,
+                _ => return Err(invalid_archive("unknown entry kind")),
+            }
+        }
+
+        let key = name_key(name);
+        with_rw_txn(&self.lmdb_env, |txn| {
+            // a previously deleted collection may have left entries behind
+            txn.clear_db(lmdb.db)?;
+            for (entry_key, value) in &meta_entries {
+                txn.put(lmdb.db, entry_key, value, WriteFlags::empty())?;
+            }
+            for (db, value) in &index_entries {
+                txn.put(*db, &key, value, WriteFlags::empty())?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+/// Reads the manifest of the snapshot at `path`
+pub fn read_manifest(path: &Path) -> Result<SnapshotManifest, WaCustomError> {
+    let mut reader = BufReader::new(File::open(path).map_err(fs_error)?);
+    read_header(&mut reader)
+}
+
+fn read_header(reader: &mut impl Read) -> Result<SnapshotManifest, WaCustomError> {
+    let mut magic = [0u8; MAGIC.len()];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| invalid_archive("not a snapshot"))?;
+    if &magic != MAGIC {
+        return Err(invalid_archive("not a snapshot"));
+    }
+    if read_u8(reader)? != FORMAT_VERSION {
+        return Err(invalid_archive("unsupported format version"));
+    }
+    serde_cbor::from_slice(&read_bytes(reader)?)
+        .map_err(|e| invalid_archive(&format!("malformed manifest: {}", e)))
+}
+
+// Appends an entry for every file under `dir`, `relative_dir` is the path of
+// `dir` within the collection's directory
+fn write_files(
+    writer: &mut impl Write,
+    dir: &Path,
+    relative_dir: &Path,
+) -> Result<(), WaCustomError> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(fs_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(fs_error)?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        // the replication log is only of use to the followers of the
+        // collection, and replicas restored from snapshots are no longer
+        // replicas
+        if relative_dir.as_os_str().is_empty()
+            && (entry.file_name() == REPLICATION_LOG_DIR || entry.file_name() == REPLICA_MARKER)
+        {
+            continue;
+        }
+        let relative_path = relative_dir.join(entry.file_name());
+        let file_type = entry.file_type().map_err(fs_error)?;
+        if file_type.is_dir() {
+            write_files(writer, &entry.path(), &relative_path)?;
+            continue;
+        }
+
+        let relative_path = relative_path
+            .to_str()
+            .ok_or_else(|| WaCustomError::FsError("Non UTF-8 file name".to_string()))?
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        storage_backend::backend()
+            .fetch(&entry.path())
+            .map_err(fs_error)?;
+        let mut file = File::open(entry.path()).map_err(fs_error)?;
+        let len = file.metadata().map_err(fs_error)?.len();
+        writer.write_all(&[ENTRY_FILE]).map_err(fs_error)?;
+        write_bytes(writer, relative_path.as_bytes())?;
+        writer.write_all(&len.to_le_bytes()).map_err(fs_error)?;
+        let copied = io::copy(&mut (&mut file).take(len), writer).map_err(fs_error)?;
+        if copied != len {
+            return Err(WaCustomError::FsError(format!(
+                "File {} changed while taking the snapshot",
+                relative_path
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> Result<(), WaCustomError> {
+    writer
+        .write_all(&(bytes.len() as u32).to_le_bytes())
+        .map_err(fs_error)?;
+    writer.write_all(bytes).map_err(fs_error)
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8, WaCustomError> {
+    let mut buf = [0u8; 1];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| invalid_archive("unexpected end of file"))?;
+    Ok(buf[0])
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, WaCustomError> {
+    let mut buf = [0u8; 8];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| invalid_archive("unexpected end of file"))?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes(reader: &mut impl Read) -> Result<Vec<u8>, WaCustomError> {
+    let mut len = [0u8; 4];
+    reader
+        .read_exact(&mut len)
+        .map_err(|_| invalid_archive("unexpected end of file"))?;
+    let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|_| invalid_archive("unexpected end of file"))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_name_validation() {
+        assert!(is_valid_snapshot_name("v12-20261018T101500Z"));
+        assert!(is_valid_snapshot_name("backup_1.old"));
+        assert!(!is_valid_snapshot_name(""));
+        assert!(!is_valid_snapshot_name(".."));
+        assert!(!is_valid_snapshot_name("../etc"));
+        assert!(!is_valid_snapshot_name("a/b"));
+    }
+
+    #[test]
+    fn test_file_entries_roundtrip() {
+        let source = tempfile::tempdir().unwrap();
+        fs::create_dir_all(source.path().join("dense_hnsw")).unwrap();
+        fs::write(source.path().join("itoe.1.data"), b"itoe").unwrap();
+        fs::write(
+            source.path().join("dense_hnsw").join("0.index"),
+            vec![7u8; 10_000],
+        )
+        .unwrap();
+
+        let mut archive = Vec::new();
+        write_files(&mut archive, source.path(), Path::new("")).unwrap();
+
+        let mut reader = &archive[..];
+        let mut files = Vec::new();
+        while !reader.is_empty() {
+            assert_eq!(read_u8(&mut reader).unwrap(), ENTRY_FILE);
+            let path = String::from_utf8(read_bytes(&mut reader).unwrap()).unwrap();
+            let len = read_u64(&mut reader).unwrap() as usize;
+            files.push((path, reader[..len].to_vec()));
+            reader = &reader[len..];
+        }
+
+        assert_eq!(
+            files,
+            [
+                ("dense_hnsw/0.index".to_string(), vec![7u8; 10_000]),
+                ("itoe.1.data".to_string(), b"itoe".to_vec()),
+            ]
+        );
+    }
+}
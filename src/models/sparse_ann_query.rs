@@ -1,6 +1,7 @@
 use rustc_hash::FxHashMap;
 use serde::Serialize;
 
+use crate::indexes::inverted::types::SparsePair;
 use crate::models::buffered_io::BufIoError;
 
 use crate::models::types::SparseVector;
@@ -27,6 +28,14 @@ pub struct SparseAnnIDFResult {
     pub score: f32,
 }
 
+/// Share of a single query dimension in a sparse dot product score
+pub struct SparseTermContribution {
+    pub index: u32,
+    pub query_value: f32,
+    pub document_value: f32,
+    pub contribution: f32,
+}
+
 impl Eq for SparseAnnResult {}
 impl Eq for SparseAnnIDFResult {}
 
@@ -295,8 +304,65 @@ impl PartialEq for PostingListHead<'_> {
     }
 }
 
-fn get_idf(documents_count: u32, documents_containing_term: u32) -> f32 {
+pub fn get_idf(documents_count: u32, documents_containing_term: u32) -> f32 {
     (((documents_count - documents_containing_term) as f32 + 0.5)
         / (documents_containing_term as f32 + 0.5))
         .ln_1p()
 }
+
+/// Breaks down the raw (unquantized) dot product of `query` and
+/// `document` into the contribution of every dimension they share,
+/// highest contribution first
+pub fn explain_sparse_score(
+    query: &[SparsePair],
+    document: &[SparsePair],
+) -> Vec<SparseTermContribution> {
+    let document_values: FxHashMap<u32, f32> =
+        document.iter().map(|pair| (pair.0, pair.1)).collect();
+
+    let mut contributions: Vec<_> = query
+        .iter()
+        .filter_map(|pair| {
+            let document_value = *document_values.get(&pair.0)?;
+            Some(SparseTermContribution {
+                index: pair.0,
+                query_value: pair.1,
+                document_value,
+                contribution: pair.1 * document_value,
+            })
+        })
+        .collect();
+
+    contributions.sort_unstable_by(|a, b| b.contribution.total_cmp(&a.contribution));
+    contributions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_sparse_score() {
+        let query = [SparsePair(1, 0.5), SparsePair(3, 2.0), SparsePair(7, 1.0)];
+        let document = [SparsePair(9, 1.0), SparsePair(3, 1.5), SparsePair(1, 4.0)];
+
+        let contributions = explain_sparse_score(&query, &document);
+        let terms: Vec<_> = contributions
+            .iter()
+            .map(|term| {
+                (
+                    term.index,
+                    term.query_value,
+                    term.document_value,
+                    term.contribution,
+                )
+            })
+            .collect();
+        // dimensions missing from either side don't contribute
+        assert_eq!(terms, vec![(3, 2.0, 1.5, 3.0), (1, 0.5, 4.0, 2.0)]);
+        let total: f32 = contributions.iter().map(|term| term.contribution).sum();
+        assert_eq!(total, 0.5 * 4.0 + 2.0 * 1.5);
+
+        assert!(explain_sparse_score(&query, &[]).is_empty());
+    }
+}
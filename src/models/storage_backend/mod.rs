@@ -0,0 +1,181 @@
+// Storage backends of the files read and written through `BufferManager`
+//
+// Files are always read and written locally. The local backend uses them
+// as-is. The object storage backend treats the data directory as a cache
+// of a bucket: changed files are uploaded in the background, and missing
+// regions are fetched on demand. That allows a node to be replaced and
+// rehydrated from the bucket.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use lmdb::Environment;
+use parking_lot::Mutex;
+
+use crate::config_loader::{StorageBackendKind, StorageConfig};
+
+use super::paths::get_data_path;
+
+pub mod object;
+pub mod s3;
+
+use object::ObjectStorage;
+use s3::S3Client;
+
+/// A file opened through a `StorageBackend`
+pub trait StorageFile: Send + Sync {
+    /// Reads from `offset` until `buf` is full or the end of the file is
+    /// reached, returns the number of bytes read
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    fn write_all_at(&self, buf: &[u8], offset: u64) -> io::Result<()>;
+
+    fn size(&self) -> io::Result<u64>;
+
+    fn flush(&self) -> io::Result<()>;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncStats {
+    pub files_uploaded: u32,
+    pub bytes_uploaded: u64,
+}
+
+pub trait StorageBackend: Send + Sync {
+    /// Opens the file at `path` for reading and writing, creating it if it
+    /// doesn't exist
+    fn open(&self, path: &Path) -> io::Result<Arc<dyn StorageFile>>;
+
+    /// Deletes the file at `path`, doesn't fail if it doesn't exist
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Makes sure the file at `path` is complete locally, must be called
+    /// before reading files that aren't opened through the backend
+    fn fetch(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Persists the files under `dir` that changed since the last call
+    fn sync_dir(&self, _dir: &Path) -> io::Result<SyncStats> {
+        Ok(SyncStats::default())
+    }
+
+    /// Persists a consistent copy of the LMDB environment stored at `path`
+    fn sync_lmdb(&self, _env: &Environment, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads from `offset` until `buf` is full or the end of the file
+pub(crate) fn read_file_at(file: &mut File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut total_read = 0;
+    while total_read < buf.len() {
+        match file.read(&mut buf[total_read..]) {
+            Ok(0) => break,
+            Ok(read) => total_read += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(total_read)
+}
+
+pub(crate) fn write_file_at(file: &mut File, buf: &[u8], offset: u64) -> io::Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(buf)
+}
+
+pub struct LocalFile {
+    file: Mutex<File>,
+}
+
+impl LocalFile {
+    pub fn new(file: File) -> Self {
+        Self {
+            file: Mutex::new(file),
+        }
+    }
+}
+
+impl StorageFile for LocalFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        read_file_at(&mut self.file.lock(), buf, offset)
+    }
+
+    fn write_all_at(&self, buf: &[u8], offset: u64) -> io::Result<()> {
+        write_file_at(&mut self.file.lock(), buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.file.lock().metadata()?.len())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.file.lock().flush()
+    }
+}
+
+/// Keeps the files on the local file system only
+pub struct LocalStorage;
+
+pub(crate) fn open_local_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+impl StorageBackend for LocalStorage {
+    fn open(&self, path: &Path) -> io::Result<Arc<dyn StorageFile>> {
+        Ok(Arc::new(LocalFile::new(open_local_file(path)?)))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+static BACKEND: OnceLock<Arc<dyn StorageBackend>> = OnceLock::new();
+
+/// Sets up the configured storage backend, must be called before the LMDB
+/// environment is opened, as it may have to be fetched from object storage
+pub fn init(config: &StorageConfig) -> io::Result<()> {
+    let backend: Arc<dyn StorageBackend> = match config.backend {
+        StorageBackendKind::Local => Arc::new(LocalStorage),
+        StorageBackendKind::S3 => {
+            let s3_config = config.s3.as_ref().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The S3 storage backend requires a [storage.s3] section",
+                )
+            })?;
+            let storage = ObjectStorage::new(
+                Arc::new(S3Client::new(s3_config)?),
+                get_data_path(),
+                config.fetch_chunk_size,
+            );
+            storage.rehydrate()?;
+            Arc::new(storage)
+        }
+    };
+    BACKEND
+        .set(backend)
+        .map_err(|_| io::Error::other("Storage backend already initialized"))
+}
+
+/// The storage backend in use, local unless another one was set up with
+/// `init`
+pub fn backend() -> Arc<dyn StorageBackend> {
+    BACKEND.get_or_init(|| Arc::new(LocalStorage)).clone()
+}
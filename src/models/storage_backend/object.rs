@@ -0,0 +1,488 @@
+// Object storage backend
+//
+// Every file under the data directory is stored in the bucket under its
+// path relative to the data directory. Local files are a cache of those
+// objects: on startup, files that only exist in the bucket are created as
+// sparse placeholders of the same size, whose regions are downloaded on
+// first access. Which regions of a placeholder are still missing is
+// tracked in `.object-cache/{key}.part`, so that a restart doesn't lose
+// that information.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+    time::SystemTime,
+};
+
+use lmdb::Environment;
+use parking_lot::Mutex;
+
+use super::super::lmdb_map::copy_env;
+use super::{
+    open_local_file, read_file_at, write_file_at, LocalFile, StorageBackend, StorageFile, SyncStats,
+};
+
+const CACHE_DIR: &str = ".object-cache";
+const LMDB_DATA_FILE: &str = "data.mdb";
+
+/// Minimal interface of an object store such as S3
+pub trait ObjectStore: Send + Sync {
+    /// Uploads `len` bytes read from `reader` to `key`
+    fn put(&self, key: &str, reader: &mut dyn Read, len: u64) -> io::Result<()>;
+
+    /// Downloads the bytes `start..end` of `key`
+    fn get_range(&self, key: &str, start: u64, end: u64) -> io::Result<Vec<u8>>;
+
+    /// Returns the size of `key`, `None` if it doesn't exist
+    fn size(&self, key: &str) -> io::Result<Option<u64>>;
+
+    fn delete(&self, key: &str) -> io::Result<()>;
+
+    /// Returns the key and size of every object
+    fn list(&self) -> io::Result<Vec<(String, u64)>>;
+}
+
+/// In-process object store, for tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryObjectStore {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+    // number of `get_range` calls
+    pub fetches: std::sync::atomic::AtomicU32,
+}
+
+#[cfg(test)]
+impl MemoryObjectStore {
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.objects.lock().get(key).cloned()
+    }
+}
+
+#[cfg(test)]
+impl ObjectStore for MemoryObjectStore {
+    fn put(&self, key: &str, reader: &mut dyn Read, len: u64) -> io::Result<()> {
+        let mut data = Vec::with_capacity(len as usize);
+        reader.take(len).read_to_end(&mut data)?;
+        self.objects.lock().insert(key.to_string(), data);
+        Ok(())
+    }
+
+    fn get_range(&self, key: &str, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        self.fetches
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let objects = self.objects.lock();
+        let data = objects
+            .get(key)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, key.to_string()))?;
+        let end = (end as usize).min(data.len());
+        Ok(data[(start as usize).min(end)..end].to_vec())
+    }
+
+    fn size(&self, key: &str) -> io::Result<Option<u64>> {
+        Ok(self.objects.lock().get(key).map(|data| data.len() as u64))
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        self.objects.lock().remove(key);
+        Ok(())
+    }
+
+    fn list(&self) -> io::Result<Vec<(String, u64)>> {
+        Ok(self
+            .objects
+            .lock()
+            .iter()
+            .map(|(key, data)| (key.clone(), data.len() as u64))
+            .collect())
+    }
+}
+
+/// Local file backed by an object, whose regions are downloaded on first
+/// access
+struct CachedFile {
+    file: Mutex<File>,
+    store: Arc<dyn ObjectStore>,
+    key: String,
+    chunk_size: u64,
+    // size of the object, regions beyond it only exist locally
+    remote_len: u64,
+    // `None` once every chunk has been downloaded
+    missing: Mutex<Option<Vec<bool>>>,
+    part_path: PathBuf,
+}
+
+impl CachedFile {
+    /// Downloads the chunks overlapping `offset..offset + len` that are
+    /// still missing
+    fn fetch_range(&self, offset: u64, len: u64) -> io::Result<()> {
+        let mut missing_guard = self.missing.lock();
+        let Some(missing) = missing_guard.as_mut() else {
+            return Ok(());
+        };
+        let end = (offset + len).min(self.remote_len);
+        if offset >= end {
+            return Ok(());
+        }
+
+        let mut changed = false;
+        for chunk in (offset / self.chunk_size)..end.div_ceil(self.chunk_size) {
+            if !missing[chunk as usize] {
+                continue;
+            }
+            let start = chunk * self.chunk_size;
+            let data = self.store.get_range(
+                &self.key,
+                start,
+                (start + self.chunk_size).min(self.remote_len),
+            )?;
+            write_file_at(&mut self.file.lock(), &data, start)?;
+            missing[chunk as usize] = false;
+            changed = true;
+        }
+
+        if !changed {
+            return Ok(());
+        }
+        if missing.iter().any(|missing| *missing) {
+            write_missing_chunks(&self.part_path, missing)?;
+        } else {
+            *missing_guard = None;
+            match fs::remove_file(&self.part_path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn fetch_all(&self) -> io::Result<()> {
+        self.fetch_range(0, self.remote_len)
+    }
+}
+
+impl StorageFile for CachedFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.fetch_range(offset, buf.len() as u64)?;
+        read_file_at(&mut self.file.lock(), buf, offset)
+    }
+
+    fn write_all_at(&self, buf: &[u8], offset: u64) -> io::Result<()> {
+        // the file is downloaded in full before it's modified, so that only
+        // files that match their objects have missing regions
+        self.fetch_all()?;
+        write_file_at(&mut self.file.lock(), buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.file.lock().metadata()?.len())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read_missing_chunks(path: &Path) -> io::Result<Vec<bool>> {
+    Ok(fs::read(path)?.into_iter().map(|byte| byte != 0).collect())
+}
+
+fn write_missing_chunks(path: &Path, missing: &[bool]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, missing.iter().map(|m| *m as u8).collect::<Vec<_>>())
+}
+
+fn modified_at(path: &Path) -> io::Result<(u64, SystemTime)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.len(), metadata.modified()?))
+}
+
+pub struct ObjectStorage {
+    store: Arc<dyn ObjectStore>,
+    root: PathBuf,
+    chunk_size: u64,
+    // files with missing regions that are currently open
+    open_files: Mutex<HashMap<PathBuf, Weak<CachedFile>>>,
+    // size and modification time of the local files when they were last
+    // uploaded, every file is uploaded again after a restart
+    synced: Mutex<HashMap<PathBuf, (u64, SystemTime)>>,
+}
+
+impl ObjectStorage {
+    pub fn new(store: Arc<dyn ObjectStore>, root: PathBuf, chunk_size: u64) -> Self {
+        Self {
+            store,
+            root,
+            chunk_size: chunk_size.max(1),
+            open_files: Mutex::new(HashMap::new()),
+            synced: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cache_dir(&self) -> PathBuf {
+        self.root.join(CACHE_DIR)
+    }
+
+    /// Key of the object backing `path`, `None` for files outside of the
+    /// data directory, which are only stored locally
+    fn key(&self, path: &Path) -> Option<String> {
+        let relative_path = path.strip_prefix(&self.root).ok()?;
+        if relative_path.starts_with(CACHE_DIR) {
+            return None;
+        }
+        let parts = relative_path
+            .components()
+            .map(|component| match component {
+                std::path::Component::Normal(part) => part.to_str(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(parts.join("/"))
+    }
+
+    fn part_path(&self, key: &str) -> PathBuf {
+        self.cache_dir().join(format!("{}.part", key))
+    }
+
+    /// Creates placeholders for the objects that don't exist locally, and
+    /// downloads the LMDB environment, which can't be fetched lazily
+    pub fn rehydrate(&self) -> io::Result<()> {
+        for (key, size) in self.store.list()? {
+            let path = self.root.join(&key);
+            if path.exists() {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            if key.ends_with(&format!("_mdb/{}", LMDB_DATA_FILE)) {
+                log::info!("Downloading {} from object storage", key);
+                let data = self.store.get_range(&key, 0, size)?;
+                fs::write(&path, data)?;
+                continue;
+            }
+
+            let file = File::create(&path)?;
+            file.set_len(size)?;
+            if size > 0 {
+                write_missing_chunks(
+                    &self.part_path(&key),
+                    &vec![true; size.div_ceil(self.chunk_size) as usize],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn open_cached(&self, path: &Path, key: String) -> io::Result<Option<Arc<CachedFile>>> {
+        let mut open_files = self.open_files.lock();
+        if let Some(file) = open_files.get(path).and_then(Weak::upgrade) {
+            return Ok(Some(file));
+        }
+
+        let part_path = self.part_path(&key);
+        let missing = match read_missing_chunks(&part_path) {
+            Ok(missing) => missing,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let remote_len = self.store.size(&key)?.unwrap_or(0);
+        let file = Arc::new(CachedFile {
+            file: Mutex::new(open_local_file(path)?),
+            store: self.store.clone(),
+            key,
+            chunk_size: self.chunk_size,
+            remote_len,
+            missing: Mutex::new(Some(missing)),
+            part_path,
+        });
+        open_files.retain(|_, file| file.strong_count() > 0);
+        open_files.insert(path.to_path_buf(), Arc::downgrade(&file));
+        Ok(Some(file))
+    }
+
+    fn upload(&self, path: &Path, key: &str) -> io::Result<u64> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        self.store.put(key, &mut file, len)?;
+        Ok(len)
+    }
+}
+
+impl StorageBackend for ObjectStorage {
+    fn open(&self, path: &Path) -> io::Result<Arc<dyn StorageFile>> {
+        if let Some(key) = self.key(path) {
+            if let Some(file) = self.open_cached(path, key)? {
+                return Ok(file);
+            }
+        }
+        Ok(Arc::new(LocalFile::new(open_local_file(path)?)))
+    }
+
+    fn fetch(&self, path: &Path) -> io::Result<()> {
+        let Some(key) = self.key(path) else {
+            return Ok(());
+        };
+        match self.open_cached(path, key)? {
+            Some(file) => file.fetch_all(),
+            None => Ok(()),
+        }
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        self.open_files.lock().remove(path);
+        self.synced.lock().remove(path);
+        if let Some(key) = self.key(path) {
+            let _ = fs::remove_file(self.part_path(&key));
+            self.store.delete(&key)?;
+        }
+        Ok(())
+    }
+
+    fn sync_dir(&self, dir: &Path) -> io::Result<SyncStats> {
+        let mut stats = SyncStats::default();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if entry.file_type()?.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let Some(key) = self.key(&path) else {
+                    continue;
+                };
+                // files with missing regions haven't been modified
+                if self.part_path(&key).exists() {
+                    continue;
+                }
+                let modified = modified_at(&path)?;
+                if self.synced.lock().get(&path) == Some(&modified) {
+                    continue;
+                }
+
+                stats.bytes_uploaded += self.upload(&path, &key)?;
+                stats.files_uploaded += 1;
+                self.synced.lock().insert(path, modified);
+            }
+        }
+        Ok(stats)
+    }
+
+    fn sync_lmdb(&self, env: &Environment, path: &Path) -> io::Result<()> {
+        let Some(key) = self.key(&path.join(LMDB_DATA_FILE)) else {
+            return Ok(());
+        };
+
+        // the environment is copied first, as the data file itself may be
+        // written to while it's being uploaded
+        let copy_dir = self.cache_dir().join("lmdb-copy");
+        if copy_dir.exists() {
+            fs::remove_dir_all(&copy_dir)?;
+        }
+        fs::create_dir_all(&copy_dir)?;
+        copy_env(env, &copy_dir).map_err(io::Error::other)?;
+
+        let copy_path = copy_dir.join(LMDB_DATA_FILE);
+        let mut file = File::open(&copy_path)?;
+        let len = file.metadata()?.len();
+        self.store.put(&key, &mut file, len)?;
+        fs::remove_dir_all(&copy_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    #[test]
+    fn test_sync_and_rehydrate() {
+        let store = Arc::new(MemoryObjectStore::default());
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+
+        let node_1 = tempfile::tempdir().unwrap();
+        let collection_dir = node_1.path().join("collections").join("test");
+        fs::create_dir_all(&collection_dir).unwrap();
+        let storage = ObjectStorage::new(store.clone(), node_1.path().to_path_buf(), 1024);
+        let file = storage.open(&collection_dir.join("0.index")).unwrap();
+        file.write_all_at(&data, 0).unwrap();
+
+        let stats = storage
+            .sync_dir(&node_1.path().join("collections"))
+            .unwrap();
+        assert_eq!(stats.files_uploaded, 1);
+        assert_eq!(stats.bytes_uploaded, data.len() as u64);
+        assert_eq!(store.get("collections/test/0.index"), Some(data.clone()));
+        // unchanged files aren't uploaded again
+        let stats = storage
+            .sync_dir(&node_1.path().join("collections"))
+            .unwrap();
+        assert_eq!(stats, SyncStats::default());
+
+        let node_2 = tempfile::tempdir().unwrap();
+        let storage = ObjectStorage::new(store.clone(), node_2.path().to_path_buf(), 1024);
+        storage.rehydrate().unwrap();
+        let path = node_2.path().join("collections/test/0.index");
+        assert_eq!(fs::metadata(&path).unwrap().len(), data.len() as u64);
+
+        let file = storage.open(&path).unwrap();
+        let mut buf = [0u8; 100];
+        assert_eq!(file.read_at(&mut buf, 2100).unwrap(), 100);
+        assert_eq!(&buf[..], &data[2100..2200]);
+        assert_eq!(store.fetches.load(Ordering::Relaxed), 1);
+        // already cached
+        file.read_at(&mut buf, 2048).unwrap();
+        assert_eq!(store.fetches.load(Ordering::Relaxed), 1);
+
+        // the regions that are still missing survive a restart
+        drop(file);
+        let storage = ObjectStorage::new(store.clone(), node_2.path().to_path_buf(), 1024);
+        let file = storage.open(&path).unwrap();
+        let mut all = vec![0u8; data.len()];
+        assert_eq!(file.read_at(&mut all, 0).unwrap(), data.len());
+        assert_eq!(all, data);
+        assert_eq!(store.fetches.load(Ordering::Relaxed), 10);
+        assert!(!storage.part_path("collections/test/0.index").exists());
+    }
+
+    #[test]
+    fn test_writes_to_placeholders() {
+        let store = Arc::new(MemoryObjectStore::default());
+        let data = vec![1u8; 4096];
+        store
+            .put("collections/test/1.data", &mut &data[..], data.len() as u64)
+            .unwrap();
+
+        let node = tempfile::tempdir().unwrap();
+        let storage = ObjectStorage::new(store.clone(), node.path().to_path_buf(), 1024);
+        storage.rehydrate().unwrap();
+        let path = node.path().join("collections/test/1.data");
+        let file = storage.open(&path).unwrap();
+        file.write_all_at(&[2u8; 10], 1500).unwrap();
+        file.write_all_at(&[3u8; 10], 4096).unwrap();
+
+        storage.sync_dir(&node.path().join("collections")).unwrap();
+        let mut expected = data.clone();
+        expected[1500..1510].copy_from_slice(&[2u8; 10]);
+        expected.extend_from_slice(&[3u8; 10]);
+        assert_eq!(store.get("collections/test/1.data"), Some(expected));
+
+        storage.remove(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(store.get("collections/test/1.data"), None);
+    }
+}
@@ -0,0 +1,86 @@
+// Background upload of the data directory to object storage
+//
+// Every `storage.sync_interval` seconds the files of each collection that
+// changed are uploaded, followed by a copy of the LMDB environment, which
+// is uploaded last so that it never refers to versions whose files are
+// missing. Collections with a transaction in progress are skipped until the
+// next run.
+
+use std::{sync::Arc, thread, time::Duration};
+
+use crate::config_loader::{Config, StorageBackendKind};
+
+use super::{
+    common::WaCustomError,
+    recovery,
+    storage_backend::{self, SyncStats},
+    types::{get_lmdb_path, AppEnv},
+};
+
+/// Starts the background upload, if the data is stored in object storage
+pub fn spawn_storage_sync(ain_env: Arc<AppEnv>, config: Arc<Config>) {
+    if config.storage.backend == StorageBackendKind::Local || config.storage.sync_interval == 0 {
+        return;
+    }
+
+    thread::Builder::new()
+        .name("storage-sync".to_string())
+        .spawn(move || {
+            if !recovery::wait_until_ready() {
+                return;
+            }
+            loop {
+                thread::sleep(Duration::from_secs(config.storage.sync_interval));
+                sync_all(&ain_env, &config);
+            }
+        })
+        .expect("Failed to spawn the storage sync thread");
+}
+
+fn sync_all(ain_env: &AppEnv, config: &Config) {
+    let backend = storage_backend::backend();
+    // collected first, so that no shard of the map stays locked while
+    // uploading
+    let collections: Vec<_> = ain_env
+        .collections_map
+        .iter_collections()
+        .map(|collection| collection.value().clone())
+        .collect();
+
+    let mut stats = SyncStats::default();
+    for collection in collections {
+        let result = collection.with_writes_blocked(config, || {
+            backend
+                .sync_dir(&collection.get_path())
+                .map_err(|err| WaCustomError::FsError(err.to_string()))
+        });
+        match result {
+            Ok(collection_stats) => {
+                stats.files_uploaded += collection_stats.files_uploaded;
+                stats.bytes_uploaded += collection_stats.bytes_uploaded;
+            }
+            Err(WaCustomError::LockError(msg)) => log::debug!(
+                "Skipped upload of collection '{}': {}",
+                collection.meta.name,
+                msg
+            ),
+            Err(err) => log::error!(
+                "Failed to upload collection '{}': {}",
+                collection.meta.name,
+                err
+            ),
+        }
+    }
+
+    if let Err(err) = backend.sync_lmdb(&ain_env.persist, &get_lmdb_path()) {
+        log::error!("Failed to upload the LMDB environment: {}", err);
+        return;
+    }
+    if stats.files_uploaded > 0 {
+        log::info!(
+            "Uploaded {} files ({} bytes) to object storage",
+            stats.files_uploaded,
+            stats.bytes_uploaded
+        );
+    }
+}
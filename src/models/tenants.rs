@@ -0,0 +1,325 @@
+// Tenants partition collections into isolated namespaces
+//
+// A tenant's collections live in the same `CollectionsMap` as every other
+// collection, under their qualified name `{tenant}::{collection}`, which is
+// also the name of their directory on disk. Tenants themselves, their
+// quotas and members, are persisted in the `tenants` LMDB database.
+
+use std::{fmt, io, sync::Arc};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, Transaction, WriteFlags};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    collection::Collection,
+    lmdb_map::{txn_guard, with_rw_txn},
+    types::CollectionsMap,
+    utils::dir_size,
+};
+
+pub const TENANT_SEPARATOR: &str = "::";
+
+const TENANTS_DB_NAME: &str = "tenants";
+const MAX_TENANT_NAME_LEN: usize = 64;
+
+/// Limits on the resources a tenant may use, unlimited if absent
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TenantQuotas {
+    /// Maximum number of collections
+    pub max_collections: Option<u32>,
+    /// Maximum number of vectors across all collections
+    pub max_vectors: Option<u64>,
+    /// Maximum size in bytes of the collections' files on disk
+    pub max_disk_usage: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tenant {
+    pub name: String,
+    pub quotas: TenantQuotas,
+    // usernames of the users allowed to access the tenant's collections
+    pub users: Vec<String>,
+    pub created_at: u64,
+}
+
+impl Tenant {
+    pub fn has_user(&self, username: &str) -> bool {
+        self.users.iter().any(|user| user == username)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TenantUsage {
+    pub collections: u32,
+    pub vectors: u64,
+    pub disk_usage: u64,
+}
+
+#[derive(Debug)]
+pub enum QuotaError {
+    TenantNotFound(String),
+    MaxCollections(u32),
+    MaxVectors(u64),
+    MaxCollectionVectors(u32),
+    MaxDiskUsage(u64),
+    Io(io::Error),
+}
+
+impl fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TenantNotFound(tenant) => write!(f, "Tenant '{}' not found", tenant),
+            Self::MaxCollections(limit) => {
+                write!(f, "Tenant is limited to {} collections", limit)
+            }
+            Self::MaxVectors(limit) => write!(f, "Tenant is limited to {} vectors", limit),
+            Self::MaxCollectionVectors(limit) => {
+                write!(f, "Collection is limited to {} vectors", limit)
+            }
+            Self::MaxDiskUsage(limit) => {
+                write!(f, "Tenant is limited to {} bytes of disk usage", limit)
+            }
+            Self::Io(err) => write!(f, "Failed to compute disk usage: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for QuotaError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Checks that `name` can be used as a tenant name, it becomes part of
+/// collection names and therefore of paths and URLs
+pub fn is_valid_tenant_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_TENANT_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn qualified_collection_name(tenant: &str, collection_name: &str) -> String {
+    format!("{}{}{}", tenant, TENANT_SEPARATOR, collection_name)
+}
+
+/// Splits a collection name into its tenant, if any, and the name of the
+/// collection within the tenant
+pub fn split_collection_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once(TENANT_SEPARATOR) {
+        Some((tenant, collection_name)) => (Some(tenant), collection_name),
+        None => (None, name),
+    }
+}
+
+pub struct TenantsMap {
+    env: Arc<Environment>,
+    db: Database,
+    // (tenant name, tenant)
+    map: DashMap<String, Tenant>,
+}
+
+impl TenantsMap {
+    pub fn new(env: Arc<Environment>) -> lmdb::Result<Self> {
+        let _guard = txn_guard();
+        let db = env.create_db(Some(TENANTS_DB_NAME), DatabaseFlags::empty())?;
+        let txn = env.begin_ro_txn()?;
+        let mut cursor = txn.open_ro_cursor(db)?;
+        let map = DashMap::new();
+
+        for (_, tenant_bytes) in cursor.iter() {
+            let tenant: Tenant =
+                serde_cbor::from_slice(tenant_bytes).map_err(|_| lmdb::Error::Corrupted)?;
+            map.insert(tenant.name.clone(), tenant);
+        }
+
+        drop(cursor);
+        txn.abort();
+
+        Ok(Self { env, db, map })
+    }
+
+    /// Adds the tenant, returns `Ok(false)` if a tenant with the same name
+    /// already exists
+    pub fn insert(&self, tenant: Tenant) -> lmdb::Result<bool> {
+        match self.map.entry(tenant.name.clone()) {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(entry) => {
+                self.persist(&tenant)?;
+                entry.insert(tenant);
+                Ok(true)
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Tenant> {
+        self.map.get(name).map(|tenant| tenant.value().clone())
+    }
+
+    pub fn list(&self) -> Vec<Tenant> {
+        let mut tenants: Vec<_> = self
+            .map
+            .iter()
+            .map(|tenant| tenant.value().clone())
+            .collect();
+        tenants.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        tenants
+    }
+
+    /// Applies `f` to the tenant and persists the result, returns `Ok(None)`
+    /// if there is no such tenant
+    pub fn update(&self, name: &str, f: impl FnOnce(&mut Tenant)) -> lmdb::Result<Option<Tenant>> {
+        let Some(mut tenant) = self.map.get_mut(name) else {
+            return Ok(None);
+        };
+        let mut updated = tenant.clone();
+        f(&mut updated);
+        self.persist(&updated)?;
+        *tenant = updated.clone();
+        Ok(Some(updated))
+    }
+
+    pub fn remove(&self, name: &str) -> lmdb::Result<Option<Tenant>> {
+        let Some((_, tenant)) = self.map.remove(name) else {
+            return Ok(None);
+        };
+
+        with_rw_txn(&self.env, |txn| match txn.del(self.db, &name, None) {
+            Ok(()) | Err(lmdb::Error::NotFound) => Ok(()),
+            Err(err) => Err(err),
+        })?;
+
+        Ok(Some(tenant))
+    }
+
+    fn persist(&self, tenant: &Tenant) -> lmdb::Result<()> {
+        let tenant_bytes = serde_cbor::to_vec(tenant).unwrap();
+        with_rw_txn(&self.env, |txn| {
+            txn.put(self.db, &tenant.name, &tenant_bytes, WriteFlags::empty())
+        })
+    }
+
+    /// Current resource usage of the tenant's collections
+    pub fn usage(
+        &self,
+        tenant: &str,
+        collections_map: &CollectionsMap,
+    ) -> Result<TenantUsage, QuotaError> {
+        let mut usage = TenantUsage::default();
+        for collection in tenant_collections(tenant, collections_map) {
+            usage.collections += 1;
+            usage.vectors += collection.vector_count() as u64;
+            usage.disk_usage += dir_size(&collection.get_path())?;
+        }
+        Ok(usage)
+    }
+
+    /// Checks whether a collection named `collection_name` may be created,
+    /// collections outside of any tenant are not limited
+    pub fn check_collection_quota(
+        &self,
+        collections_map: &CollectionsMap,
+        collection_name: &str,
+    ) -> Result<(), QuotaError> {
+        let (Some(tenant_name), _) = split_collection_name(collection_name) else {
+            return Ok(());
+        };
+        let tenant = self
+            .get(tenant_name)
+            .ok_or_else(|| QuotaError::TenantNotFound(tenant_name.to_string()))?;
+
+        if tenant.quotas.max_collections.is_none() && tenant.quotas.max_disk_usage.is_none() {
+            return Ok(());
+        }
+        let usage = self.usage(tenant_name, collections_map)?;
+        if let Some(max_collections) = tenant.quotas.max_collections {
+            if usage.collections >= max_collections {
+                return Err(QuotaError::MaxCollections(max_collections));
+            }
+        }
+        if let Some(max_disk_usage) = tenant.quotas.max_disk_usage {
+            if usage.disk_usage >= max_disk_usage {
+                return Err(QuotaError::MaxDiskUsage(max_disk_usage));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks whether `new_vectors` vectors may be added to the collection,
+    /// against the collection's `max_vectors` and the quotas of its tenant.
+    ///
+    /// Vectors still pending in an open transaction aren't counted.
+    pub fn check_vector_quota(
+        &self,
+        collections_map: &CollectionsMap,
+        collection: &Collection,
+        new_vectors: u32,
+    ) -> Result<(), QuotaError> {
+        if new_vectors == 0 {
+            return Ok(());
+        }
+
+        if let Some(max_vectors) = collection.meta.config.max_vectors {
+            if collection.vector_count().saturating_add(new_vectors) > max_vectors {
+                return Err(QuotaError::MaxCollectionVectors(max_vectors));
+            }
+        }
+
+        let (Some(tenant_name), _) = split_collection_name(&collection.meta.name) else {
+            return Ok(());
+        };
+        let Some(tenant) = self.get(tenant_name) else {
+            return Ok(());
+        };
+        if tenant.quotas.max_vectors.is_none() && tenant.quotas.max_disk_usage.is_none() {
+            return Ok(());
+        }
+
+        let usage = self.usage(tenant_name, collections_map)?;
+        if let Some(max_vectors) = tenant.quotas.max_vectors {
+            if usage.vectors + new_vectors as u64 > max_vectors {
+                return Err(QuotaError::MaxVectors(max_vectors));
+            }
+        }
+        if let Some(max_disk_usage) = tenant.quotas.max_disk_usage {
+            if usage.disk_usage >= max_disk_usage {
+                return Err(QuotaError::MaxDiskUsage(max_disk_usage));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the collections belonging to `tenant`
+pub fn tenant_collections(tenant: &str, collections_map: &CollectionsMap) -> Vec<Arc<Collection>> {
+    collections_map
+        .iter_collections()
+        .filter(|collection| split_collection_name(&collection.meta.name).0 == Some(tenant))
+        .map(|collection| collection.value().clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_name_qualification() {
+        let name = qualified_collection_name("acme", "products");
+        assert_eq!(name, "acme::products");
+        assert_eq!(split_collection_name(&name), (Some("acme"), "products"));
+        assert_eq!(split_collection_name("products"), (None, "products"));
+    }
+
+    #[test]
+    fn test_tenant_name_validation() {
+        assert!(is_valid_tenant_name("acme-corp_1"));
+        assert!(!is_valid_tenant_name(""));
+        assert!(!is_valid_tenant_name("acme::corp"));
+        assert!(!is_valid_tenant_name("acme/corp"));
+        assert!(!is_valid_tenant_name(&"a".repeat(MAX_TENANT_NAME_LEN + 1)));
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    fs::OpenOptions,
+    collections::HashSet,
     path::PathBuf,
     sync::{
         atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering},
@@ -272,14 +272,11 @@ impl TFIDFIndexNode {
 
 impl TFIDFIndexRoot {
     pub fn new(root_path: PathBuf) -> Result<Self, BufIoError> {
-        let dim_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(root_path.join("index-tree.dim"))?;
         let node_size = TFIDFIndexNode::get_serialized_size();
-        let dim_bufman = Arc::new(BufferManager::new(dim_file, node_size as usize * 1000)?);
+        let dim_bufman = Arc::new(BufferManager::open(
+            &root_path.join("index-tree.dim"),
+            node_size as usize * 1000,
+        )?);
         let offset_counter = AtomicU32::new(node_size + 4);
         let data_bufmans = Arc::new(BufferManagerFactory::new(
             root_path.clone().into(),
@@ -351,6 +348,56 @@ impl TFIDFIndexRoot {
         node.delete(quotient, document_id, &self.cache, version)
     }
 
+    /// Returns the posting list of a term, if the term has been indexed
+    pub fn get_term(&self, hash_dim: u32) -> Result<Option<Arc<TermInfo>>, BufIoError> {
+        // Split the hash dimension
+        let storage_dim = hash_dim & (u16::MAX as u32);
+        let quotient = (hash_dim >> 16) as TermQuotient;
+
+        let Some(node) = self.find_node(storage_dim) else {
+            return Ok(None);
+        };
+        let data = unsafe { &*node.data }.try_get_data(&self.cache)?;
+        Ok(data.map.lookup(&quotient))
+    }
+
+    /// Inserts the postings of the documents `retain` keeps into `target`
+    /// at `version`, without the deleted ones
+    pub fn copy_to(
+        &self,
+        target: &TFIDFIndexRoot,
+        version: VersionNumber,
+        retain: impl Fn(u32) -> bool,
+    ) -> Result<(), BufIoError> {
+        let mut documents = HashSet::new();
+        let mut nodes = vec![&self.root];
+        while let Some(node) = nodes.pop() {
+            for child_index in 0..16 {
+                if let Some(child) = node.children.get(child_index) {
+                    nodes.push(unsafe { &*child });
+                }
+            }
+            let data = unsafe { &*node.data }.try_get_data(&self.cache)?;
+            let mut postings = Vec::new();
+            data.map.for_each(|quotient, term| {
+                let hash_dim = ((*quotient as u32) << 16) | node.dim_index;
+                for (document_id, value) in term.documents.read().unwrap().iter() {
+                    if retain(document_id) {
+                        postings.push((hash_dim, value, document_id));
+                    }
+                }
+            });
+            for (hash_dim, value, document_id) in postings {
+                documents.insert(document_id);
+                target.insert(hash_dim, value, document_id, version)?;
+            }
+        }
+        target
+            .total_documents_count
+            .fetch_add(documents.len() as u32, Ordering::Relaxed);
+        Ok(())
+    }
+
     pub fn serialize(&self) -> Result<(), BufIoError> {
         let cursor = self.cache.dim_bufman.open_cursor()?;
         self.cache
@@ -367,14 +414,11 @@ impl TFIDFIndexRoot {
     }
 
     pub fn deserialize(root_path: PathBuf) -> Result<Self, BufIoError> {
-        let dim_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(root_path.join("index-tree.dim"))?;
         let node_size = TFIDFIndexNode::get_serialized_size();
-        let dim_bufman = Arc::new(BufferManager::new(dim_file, node_size as usize * 1000)?);
+        let dim_bufman = Arc::new(BufferManager::open(
+            &root_path.join("index-tree.dim"),
+            node_size as usize * 1000,
+        )?);
         let offset_counter = AtomicU32::new(dim_bufman.file_size() as u32);
         let data_bufmans = Arc::new(BufferManagerFactory::new(
             root_path.clone().into(),
@@ -0,0 +1,704 @@
+// Per-request tracing of searches and indexing
+//
+// A trace is started on the thread that serves a search or indexes a
+// transaction. It records spans for the phases of the work, along with the
+// number of index nodes visited and loaded from disk in each span. Finished
+// search traces taking longer than the configured threshold are written to
+// the slow query log, and every finished trace is exported to an OTLP/HTTP
+// collector if one is configured. Nothing is recorded if neither is set up.
+//
+// The active trace is kept in a thread local, so work spawned on other
+// threads, e.g. the queries of batch searches running on the thread pool,
+// isn't attributed to it. Async requests wrap their future with `traced`,
+// which only installs their trace while the future is polled, so that the
+// requests a worker thread serves while they're suspended aren't either.
+
+use std::{
+    cell::RefCell,
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    future::Future,
+    io::{self, Write},
+    marker::PhantomData,
+    pin::Pin,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+        Mutex, OnceLock,
+    },
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::Utc;
+use rand::Rng;
+use serde_json::{json, Value};
+
+use super::{http_client::HttpClient, paths::get_data_path};
+use crate::config_loader::TracingConfig;
+
+/// Traces waiting to be exported, further traces are dropped
+const EXPORT_QUEUE_SIZE: usize = 1024;
+/// Spans sent to the collector in a single request
+const EXPORT_BATCH_SPANS: usize = 512;
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    String(String),
+    Int(i64),
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<u32> for AttributeValue {
+    fn from(value: u32) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+impl From<u64> for AttributeValue {
+    fn from(value: u64) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+impl From<usize> for AttributeValue {
+    fn from(value: usize) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+pub type Attributes = Vec<(&'static str, AttributeValue)>;
+
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub name: &'static str,
+    pub span_id: [u8; 8],
+    pub parent_span_id: Option<[u8; 8]>,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Attributes,
+    // counters when the span was opened, replaced by the `nodes_visited`
+    // and `node_loads` attributes once closed
+    nodes_visited_at_start: u64,
+    node_loads_at_start: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trace {
+    pub trace_id: [u8; 16],
+    pub spans: Vec<Span>,
+    pub nodes_visited: u64,
+    pub node_loads: u64,
+    pub elapsed: Duration,
+}
+
+struct ActiveTrace {
+    trace: Trace,
+    start: Instant,
+    // indices in `trace.spans` of the spans still open, innermost last
+    open: Vec<usize>,
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<ActiveTrace>> = const { RefCell::new(None) };
+}
+
+struct Tracer {
+    slow_query_threshold: Option<Duration>,
+    slow_query_log: Option<Mutex<File>>,
+    exporter: Option<SyncSender<Trace>>,
+}
+
+static TRACER: OnceLock<Tracer> = OnceLock::new();
+
+/// Sets up the slow query log and the OTLP exporter, traces aren't
+/// recorded if neither is configured
+pub fn init(config: &TracingConfig) -> io::Result<()> {
+    let slow_query_log = match &config.slow_query_log {
+        Some(path) => {
+            let path = get_data_path().join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            ))
+        }
+        None => None,
+    };
+    let exporter = match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let client = HttpClient::new(endpoint, config.ca_file.as_deref())?;
+            let (sender, receiver) = mpsc::sync_channel(EXPORT_QUEUE_SIZE);
+            let service_name = config.service_name.clone();
+            thread::Builder::new()
+                .name("otlp-exporter".to_string())
+                .spawn(move || export_traces(client, &service_name, receiver))?;
+            log::info!("Exporting search and indexing spans to {}", endpoint);
+            Some(sender)
+        }
+        None => None,
+    };
+
+    TRACER
+        .set(Tracer {
+            slow_query_threshold: config.slow_query_threshold_ms.map(Duration::from_millis),
+            slow_query_log,
+            exporter,
+        })
+        .map_err(|_| io::Error::other("Tracing already initialized"))
+}
+
+fn is_enabled() -> bool {
+    TRACER
+        .get()
+        .is_some_and(|tracer| tracer.slow_query_threshold.is_some() || tracer.exporter.is_some())
+}
+
+fn with_active<T>(f: impl FnOnce(&mut ActiveTrace) -> T) -> Option<T> {
+    ACTIVE
+        .try_with(|active| active.borrow_mut().as_mut().map(f))
+        .ok()
+        .flatten()
+}
+
+fn open_span(active: &mut ActiveTrace, name: &'static str, attributes: Attributes) {
+    let parent_span_id = active
+        .open
+        .last()
+        .map(|idx| active.trace.spans[*idx].span_id);
+    active.trace.spans.push(Span {
+        name,
+        span_id: rand::thread_rng().gen(),
+        parent_span_id,
+        start: SystemTime::now(),
+        end: SystemTime::now(),
+        attributes,
+        nodes_visited_at_start: active.trace.nodes_visited,
+        node_loads_at_start: active.trace.node_loads,
+    });
+    active.open.push(active.trace.spans.len() - 1);
+}
+
+fn close_span(active: &mut ActiveTrace) {
+    let Some(idx) = active.open.pop() else {
+        return;
+    };
+    let span = &mut active.trace.spans[idx];
+    span.end = SystemTime::now();
+    let nodes_visited = active.trace.nodes_visited - span.nodes_visited_at_start;
+    let node_loads = active.trace.node_loads - span.node_loads_at_start;
+    if nodes_visited > 0 {
+        span.attributes
+            .push(("nodes_visited", nodes_visited.into()));
+    }
+    if node_loads > 0 {
+        span.attributes.push(("node_loads", node_loads.into()));
+    }
+}
+
+/// Trace of the work done by the current thread, finished when dropped
+pub struct TraceGuard {
+    active: bool,
+    slow_query: Option<SlowQuery>,
+    // the trace is kept in a thread local, futures holding the guard
+    // across `.await`s must be wrapped with `traced`
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Starts a trace on the current thread with a root span `name`. If the
+/// thread is already traced, a child span is opened instead.
+pub fn start(name: &'static str, attributes: Attributes) -> TraceGuard {
+    let mut guard = TraceGuard {
+        active: false,
+        slow_query: None,
+        _not_send: PhantomData,
+    };
+    if !is_enabled() {
+        return guard;
+    }
+    let _ = ACTIVE.try_with(|active| {
+        let mut active = active.borrow_mut();
+        if let Some(active) = active.as_mut() {
+            open_span(active, name, attributes);
+            guard.active = true;
+            return;
+        }
+        let mut trace = ActiveTrace {
+            trace: Trace {
+                trace_id: rand::thread_rng().gen(),
+                spans: Vec::new(),
+                nodes_visited: 0,
+                node_loads: 0,
+                elapsed: Duration::ZERO,
+            },
+            start: Instant::now(),
+            open: Vec::new(),
+        };
+        open_span(&mut trace, name, attributes);
+        *active = Some(trace);
+        guard.active = true;
+    });
+    guard
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        let finished = ACTIVE
+            .try_with(|active| {
+                let mut active = active.borrow_mut();
+                let trace = active.as_mut()?;
+                close_span(trace);
+                if !trace.open.is_empty() {
+                    return None;
+                }
+                let mut trace = active.take()?;
+                trace.trace.elapsed = trace.start.elapsed();
+                Some(trace.trace)
+            })
+            .ok()
+            .flatten();
+        if let Some(trace) = finished {
+            finish_trace(trace, self.slow_query.take());
+        }
+    }
+}
+
+fn finish_trace(trace: Trace, slow_query: Option<SlowQuery>) {
+    let Some(tracer) = TRACER.get() else {
+        return;
+    };
+    if let (Some(slow_query), Some(threshold)) = (slow_query, tracer.slow_query_threshold) {
+        if trace.elapsed >= threshold {
+            log_slow_query(tracer, &slow_query, &trace);
+        }
+    }
+    if let Some(exporter) = &tracer.exporter {
+        if let Err(TrySendError::Full(_)) = exporter.try_send(trace) {
+            log::warn!("Tracing export queue is full, dropping a trace");
+        }
+    }
+}
+
+/// Span of a phase of the traced work, closed when dropped
+pub struct SpanGuard {
+    active: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Opens a child span of the current span, does nothing if the thread
+/// isn't traced
+pub fn span(name: &'static str, attributes: Attributes) -> SpanGuard {
+    let active = with_active(|active| open_span(active, name, attributes)).is_some();
+    SpanGuard {
+        active,
+        _not_send: PhantomData,
+    }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if self.active {
+            with_active(close_span);
+        }
+    }
+}
+
+/// Future carrying the trace started while it's polled, see `traced`
+pub struct Traced<F> {
+    future: Option<Pin<Box<F>>>,
+    trace: Option<ActiveTrace>,
+}
+
+/// Runs `future` with a trace of its own, installed in the thread local
+/// only while the future is polled and set aside while it's suspended
+pub fn traced<F: Future>(future: F) -> Traced<F> {
+    Traced {
+        future: Some(Box::pin(future)),
+        trace: None,
+    }
+}
+
+impl<F> Traced<F> {
+    // installs the trace of the future, then sets it aside again along
+    // with the spans opened by `f`
+    fn with_trace<T>(&mut self, f: impl FnOnce(&mut Option<Pin<Box<F>>>) -> T) -> T {
+        let previous = ACTIVE
+            .try_with(|active| active.replace(self.trace.take()))
+            .ok()
+            .flatten();
+        let result = f(&mut self.future);
+        self.trace = ACTIVE
+            .try_with(|active| active.replace(previous))
+            .ok()
+            .flatten();
+        result
+    }
+}
+
+impl<F: Future> Future for Traced<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        self.get_mut().with_trace(|future| {
+            future
+                .as_mut()
+                .expect("polled after completion")
+                .as_mut()
+                .poll(cx)
+        })
+    }
+}
+
+impl<F> Drop for Traced<F> {
+    // a future dropped while suspended closes its spans and finishes its
+    // trace, instead of those of the request being served
+    fn drop(&mut self) {
+        if self.trace.is_some() {
+            self.with_trace(|future| drop(future.take()));
+        }
+    }
+}
+
+/// Records index nodes visited by the current thread's search
+pub fn record_nodes_visited(count: u32) {
+    with_active(|active| active.trace.nodes_visited += count as u64);
+}
+
+/// Records an index node loaded from disk, i.e. missing from the cache
+pub fn record_node_load() {
+    with_active(|active| active.trace.node_loads += 1);
+}
+
+/// Details of a search, written to the slow query log
+pub struct SlowQuery {
+    pub collection: String,
+    pub query_type: &'static str,
+    pub top_k: Option<usize>,
+    pub filter: Option<String>,
+}
+
+/// Starts the trace of a search, logged as a slow query if it takes longer
+/// than the configured threshold
+pub fn start_search(query: SlowQuery) -> TraceGuard {
+    let mut attributes: Attributes = vec![
+        ("collection", query.collection.as_str().into()),
+        ("query_type", query.query_type.into()),
+    ];
+    if let Some(top_k) = query.top_k {
+        attributes.push(("top_k", top_k.into()));
+    }
+    if let Some(filter) = &query.filter {
+        attributes.push(("filter", filter.as_str().into()));
+    }
+    let mut guard = start("search", attributes);
+    if guard.active {
+        guard.slow_query = Some(query);
+    }
+    guard
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+fn log_slow_query(tracer: &Tracer, query: &SlowQuery, trace: &Trace) {
+    let record = json!({
+        "timestamp": Utc::now().to_rfc3339(),
+        "trace_id": hex(&trace.trace_id),
+        "collection": query.collection,
+        "query_type": query.query_type,
+        "top_k": query.top_k,
+        "filter": query.filter,
+        "nodes_visited": trace.nodes_visited,
+        "cache_misses": trace.node_loads,
+        "wall_time_ms": trace.elapsed.as_secs_f64() * 1000.0,
+    });
+    match &tracer.slow_query_log {
+        Some(file) => {
+            let mut file = file.lock().unwrap();
+            if let Err(err) = writeln!(file, "{}", record) {
+                log::error!("Failed to write to the slow query log: {}", err);
+            }
+        }
+        None => log::warn!("Slow query: {}", record),
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn otlp_attributes(attributes: &Attributes) -> Vec<Value> {
+    attributes
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                AttributeValue::String(value) => json!({ "stringValue": value }),
+                // 64 bit integers are encoded as strings in OTLP/JSON
+                AttributeValue::Int(value) => json!({ "intValue": value.to_string() }),
+            };
+            json!({ "key": key, "value": value })
+        })
+        .collect()
+}
+
+/// Encodes traces as an OTLP/JSON `ExportTraceServiceRequest`
+fn otlp_request(service_name: &str, traces: &[Trace]) -> Value {
+    let spans: Vec<Value> = traces
+        .iter()
+        .flat_map(|trace| {
+            trace.spans.iter().map(|span| {
+                let mut value = json!({
+                    "traceId": hex(&trace.trace_id),
+                    "spanId": hex(&span.span_id),
+                    "name": span.name,
+                    // SPAN_KIND_INTERNAL
+                    "kind": 1,
+                    "startTimeUnixNano": unix_nanos(span.start),
+                    "endTimeUnixNano": unix_nanos(span.end),
+                    "attributes": otlp_attributes(&span.attributes),
+                });
+                if let Some(parent_span_id) = &span.parent_span_id {
+                    value["parentSpanId"] = json!(hex(parent_span_id));
+                }
+                value
+            })
+        })
+        .collect();
+
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [{ "key": "service.name", "value": { "stringValue": service_name } }]
+            },
+            "scopeSpans": [{
+                "scope": { "name": "cosdata", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans
+            }]
+        }]
+    })
+}
+
+fn send_traces(client: &HttpClient, service_name: &str, traces: &[Trace]) -> io::Result<()> {
+    let body = otlp_request(service_name, traces).to_string();
+    let response = client.send(
+        "POST",
+        "/v1/traces",
+        &[("content-type", "application/json".to_string())],
+        Some((&mut body.as_bytes(), body.len() as u64)),
+    )?;
+    if !response.is_success() {
+        return Err(io::Error::other(format!(
+            "Collector responded with status {}",
+            response.status
+        )));
+    }
+    Ok(())
+}
+
+// Sends the traces to the collector in batches, until the sender is dropped
+fn export_traces(client: HttpClient, service_name: &str, receiver: Receiver<Trace>) {
+    let mut batch: Vec<Trace> = Vec::new();
+    let mut batch_spans = 0;
+    let mut deadline = Instant::now() + EXPORT_INTERVAL;
+    let mut failing = false;
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let disconnected = match receiver.recv_timeout(timeout) {
+            Ok(trace) => {
+                batch_spans += trace.spans.len();
+                batch.push(trace);
+                if batch_spans < EXPORT_BATCH_SPANS {
+                    continue;
+                }
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+
+        if !batch.is_empty() {
+            match send_traces(&client, service_name, &batch) {
+                Ok(()) => failing = false,
+                Err(err) => {
+                    // logged once until exports succeed again
+                    if !failing {
+                        log::error!("Failed to export spans: {}", err);
+                    }
+                    failing = true;
+                }
+            }
+            batch.clear();
+            batch_spans = 0;
+        }
+        if disconnected {
+            return;
+        }
+        deadline = Instant::now() + EXPORT_INTERVAL;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    fn test_trace() -> Trace {
+        let mut active = ActiveTrace {
+            trace: Trace {
+                trace_id: [1; 16],
+                spans: Vec::new(),
+                nodes_visited: 0,
+                node_loads: 0,
+                elapsed: Duration::ZERO,
+            },
+            start: Instant::now(),
+            open: Vec::new(),
+        };
+        open_span(&mut active, "search", vec![("collection", "test".into())]);
+        open_span(&mut active, "ann_search", vec![("level", 0u32.into())]);
+        active.trace.nodes_visited += 12;
+        active.trace.node_loads += 3;
+        close_span(&mut active);
+        close_span(&mut active);
+        active.trace
+    }
+
+    #[test]
+    fn test_spans_record_counters() {
+        let trace = test_trace();
+        let (root, child) = (&trace.spans[0], &trace.spans[1]);
+        assert_eq!(root.parent_span_id, None);
+        assert_eq!(child.parent_span_id, Some(root.span_id));
+        assert!(child
+            .attributes
+            .contains(&("nodes_visited", AttributeValue::Int(12))));
+        assert!(child
+            .attributes
+            .contains(&("node_loads", AttributeValue::Int(3))));
+        assert!(root
+            .attributes
+            .contains(&("nodes_visited", AttributeValue::Int(12))));
+    }
+
+    // A trace set aside while its future is suspended isn't seen by the
+    // futures polled meanwhile on the same thread
+    #[test]
+    fn test_traced_futures_interleave() {
+        let trace_id = || with_active(|active| active.trace.trace_id);
+        let mut first = traced(async move {
+            ACTIVE.with(|active| {
+                *active.borrow_mut() = Some(ActiveTrace {
+                    trace: test_trace(),
+                    start: Instant::now(),
+                    open: Vec::new(),
+                })
+            });
+            let mut yielded = false;
+            std::future::poll_fn(|cx| {
+                if yielded {
+                    return Poll::Ready(());
+                }
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+            .await;
+            trace_id()
+        });
+        let mut second = traced(async move { trace_id() });
+
+        let waker = futures_util::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut first).poll(&mut cx).is_pending());
+        assert_eq!(trace_id(), None);
+        assert_eq!(Pin::new(&mut second).poll(&mut cx), Poll::Ready(None));
+        assert_eq!(
+            Pin::new(&mut first).poll(&mut cx),
+            Poll::Ready(Some([1; 16]))
+        );
+        assert_eq!(trace_id(), None);
+    }
+
+    // Exports a trace to a collector stub listening on localhost
+    #[test]
+    fn test_export_to_collector() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let collector = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            (
+                request_line,
+                serde_json::from_slice::<Value>(&body).unwrap(),
+            )
+        });
+
+        let client = HttpClient::new(&endpoint, None).unwrap();
+        send_traces(&client, "cosdata-test", &[test_trace()]).unwrap();
+
+        let (request_line, body) = collector.join().unwrap();
+        assert!(request_line.starts_with("POST /v1/traces "));
+        let resource_spans = &body["resourceSpans"][0];
+        assert_eq!(
+            resource_spans["resource"]["attributes"][0]["value"]["stringValue"],
+            "cosdata-test"
+        );
+        let spans = resource_spans["scopeSpans"][0]["spans"].as_array().unwrap();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0]["traceId"], hex(&[1; 16]));
+        assert_eq!(spans[1]["name"], "ann_search");
+        assert_eq!(spans[1]["parentSpanId"], spans[0]["spanId"]);
+        assert!(spans[1]["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&json!({ "key": "nodes_visited", "value": { "intValue": "12" } })));
+    }
+}
@@ -0,0 +1,75 @@
+// Aborting of idle explicit transactions
+//
+// Explicit transactions that receive no operations for
+// `transactions.idle_timeout` seconds are aborted, so that a client that
+// went away doesn't block the compaction of its collection indefinitely.
+
+use std::{sync::Arc, thread, time::Duration};
+
+use crate::config_loader::TransactionsConfig;
+
+use super::{recovery, types::AppEnv};
+
+// upper bound of the interval between checks for idle transactions
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Starts aborting idle transactions, unless it's disabled by the config
+pub fn spawn_transaction_timeout(ain_env: Arc<AppEnv>, config: TransactionsConfig) {
+    let Some(timeout) = config.idle_timeout.filter(|timeout| *timeout > 0) else {
+        return;
+    };
+    let timeout = Duration::from_secs(timeout);
+
+    thread::Builder::new()
+        .name("transaction-timeout".to_string())
+        .spawn(move || {
+            if !recovery::wait_until_ready() {
+                return;
+            }
+            loop {
+                thread::sleep(CHECK_INTERVAL.min(timeout));
+                abort_idle_transactions(&ain_env, timeout);
+            }
+        })
+        .expect("Failed to spawn the transaction timeout thread");
+}
+
+fn abort_idle_transactions(ain_env: &AppEnv, timeout: Duration) {
+    let collections: Vec<_> = ain_env
+        .collections_map
+        .iter_collections()
+        .map(|collection| collection.value().clone())
+        .collect();
+
+    for collection in collections {
+        let idle_transactions: Vec<_> = {
+            let mut transactions = collection.open_explicit_transactions.write();
+            let idle_ids: Vec<_> = transactions
+                .values()
+                .filter(|transaction| transaction.idle_time() >= timeout)
+                .map(|transaction| transaction.id)
+                .collect();
+            idle_ids
+                .iter()
+                .filter_map(|id| transactions.remove(id))
+                .collect()
+        };
+        for transaction in idle_transactions {
+            let transaction_id = transaction.id;
+            match transaction.abort(&collection) {
+                Ok(()) => log::info!(
+                    "Aborted transaction {} of collection '{}', idle for over {} seconds",
+                    *transaction_id,
+                    collection.meta.name,
+                    timeout.as_secs()
+                ),
+                Err(err) => log::error!(
+                    "Failed to abort idle transaction {} of collection '{}': {}",
+                    *transaction_id,
+                    collection.meta.name,
+                    err
+                ),
+            }
+        }
+    }
+}
@@ -267,6 +267,21 @@ impl<T> VersionedItem<T> {
         self.value.as_ref()
     }
 
+    /// Returns the value in effect at `version`, `None` if there was
+    /// none or it was deleted
+    pub fn at(&self, version: VersionNumber) -> Option<&T> {
+        if *self.version > *version {
+            return None;
+        }
+        if let Some(next) = &self.next {
+            if *next.version <= *version {
+                return next.at(version);
+            }
+        }
+
+        self.value.as_ref()
+    }
+
     pub fn latest_item(&self) -> &Self {
         if let Some(next) = &self.next {
             return next.latest_item();
@@ -274,6 +289,46 @@ impl<T> VersionedItem<T> {
 
         self
     }
+
+    /// Drops the history preceding `retain_from`, the item in effect at
+    /// `retain_from` is rebased onto that version. Returns `true` if the
+    /// chain changed and needs to be serialized again.
+    ///
+    /// Only superseded values are dropped and the boxed items are moved by
+    /// pointer, so references handed out by `get_latest` stay valid.
+    pub fn compact(&mut self, retain_from: VersionNumber) -> bool {
+        if *self.version >= *retain_from {
+            return false;
+        }
+
+        let mut next = self.next.take();
+        let mut base = None;
+        while let Some(mut item) = next {
+            if *item.version > *retain_from {
+                next = Some(item);
+                break;
+            }
+            next = item.next.take();
+            base = Some(item);
+        }
+
+        match base {
+            // `self` is still the item in effect at `retain_from`
+            None => self.next = next,
+            Some(mut base) => {
+                base.version = retain_from;
+                *base.serialized_at.get_mut() = None;
+                base.next = next;
+                // `self` lives inline in the quotient, it becomes an empty
+                // placeholder in front of the rebased item
+                self.value = None;
+                self.next = Some(base);
+            }
+        }
+        self.version = retain_from;
+        *self.serialized_at.get_mut() = None;
+        true
+    }
 }
 
 impl<T> TreeMapNode<T> {
@@ -316,6 +371,17 @@ impl<T> TreeMapNode<T> {
     pub fn get_versioned(&self, quotient: u64) -> Option<RwLockReadGuard<'_, VersionedItem<T>>> {
         self.quotients.get_versioned(quotient)
     }
+
+    fn compact(&self, retain_from: VersionNumber) {
+        if self.quotients.compact(retain_from) {
+            self.dirty.store(true, Ordering::Release);
+        }
+        for child in &self.children.items {
+            if let Some(child) = unsafe { child.load(Ordering::Relaxed).as_ref() } {
+                child.compact(retain_from);
+            }
+        }
+    }
 }
 
 impl<T: VersionedVecItem> TreeMapVecNode<T> {
@@ -357,6 +423,17 @@ impl<T: VersionedVecItem> TreeMapVecNode<T> {
     pub fn get(&self, quotient: u64) -> Option<RwLockReadGuard<'_, VersionedVec<T>>> {
         self.quotients.get(quotient)
     }
+
+    fn compact(&self, retain_from: VersionNumber) {
+        if self.quotients.compact(retain_from) {
+            self.dirty.store(true, Ordering::Release);
+        }
+        for child in &self.children.items {
+            if let Some(child) = unsafe { child.load(Ordering::Relaxed).as_ref() } {
+                child.compact(retain_from);
+            }
+        }
+    }
 }
 
 impl<T> Default for QuotientsMap<T> {
@@ -423,6 +500,14 @@ impl<T> QuotientsMap<T> {
             unsafe { std::mem::transmute(q.value.read()) }
         })
     }
+
+    fn compact(&self, retain_from: VersionNumber) -> bool {
+        let mut changed = false;
+        for (_, quotient) in self.map.to_list() {
+            changed |= quotient.value.write().compact(retain_from);
+        }
+        changed
+    }
 }
 
 impl<T: VersionedVecItem> QuotientsMapVec<T> {
@@ -461,6 +546,14 @@ impl<T: VersionedVecItem> QuotientsMapVec<T> {
             >(q.value.read())
         })
     }
+
+    fn compact(&self, retain_from: VersionNumber) -> bool {
+        let mut changed = false;
+        for (_, quotient) in self.map.to_list() {
+            changed |= quotient.value.write().compact(retain_from);
+        }
+        changed
+    }
 }
 
 #[cfg(test)]
@@ -560,6 +653,22 @@ impl<K, V: SimpleSerialize> TreeMap<K, V> {
         Ok(())
     }
 
+    /// Drops the history preceding `retain_from`, rewrites the surviving
+    /// items and deletes the data files of `dead_versions`, which must all
+    /// be older than `retain_from`
+    pub fn compact(
+        &self,
+        retain_from: VersionNumber,
+        dead_versions: &[VersionNumber],
+    ) -> Result<(), BufIoError> {
+        self.root.compact(retain_from);
+        self.serialize()?;
+        for version in dead_versions {
+            self.data_bufmans.remove_file(version)?;
+        }
+        Ok(())
+    }
+
     pub fn deserialize(
         dim_bufman: BufferManager,
         data_bufmans: BufferManagerFactory<VersionNumber>,
@@ -641,6 +750,22 @@ where
         Ok(())
     }
 
+    /// Drops the history preceding `retain_from`, rewrites the surviving
+    /// items and deletes the data files of `dead_versions`, which must all
+    /// be older than `retain_from`
+    pub fn compact(
+        &self,
+        retain_from: VersionNumber,
+        dead_versions: &[VersionNumber],
+    ) -> Result<(), BufIoError> {
+        self.root.compact(retain_from);
+        self.serialize()?;
+        for version in dead_versions {
+            self.data_bufmans.remove_file(version)?;
+        }
+        Ok(())
+    }
+
     pub fn deserialize(
         dim_bufman: BufferManager,
         data_bufmans: BufferManagerFactory<VersionNumber>,
@@ -708,4 +833,77 @@ mod tests {
             })
         );
     }
+
+    fn open_bufmans(
+        root: &std::path::Path,
+    ) -> (BufferManager, BufferManagerFactory<VersionNumber>) {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .truncate(false)
+            .create(true)
+            .open(root.join("tree_map.dim"))
+            .unwrap();
+        let dim_bufman = BufferManager::new(file, 8192).unwrap();
+        let data_bufmans = BufferManagerFactory::new(
+            root.into(),
+            |root, version: &VersionNumber| root.join(format!("tree_map.{}.data", **version)),
+            8192,
+        );
+        (dim_bufman, data_bufmans)
+    }
+
+    #[test]
+    fn test_compaction() {
+        let tempdir = tempdir().unwrap();
+        let (dim_bufman, data_bufmans) = open_bufmans(tempdir.as_ref());
+        let map: TreeMap<u64, u64> = TreeMap::new(dim_bufman, data_bufmans);
+        map.insert(0.into(), &0, 1);
+        map.insert(0.into(), &1, 10);
+        map.insert(1.into(), &0, 2);
+        map.serialize().unwrap();
+        map.insert(2.into(), &0, 3);
+        map.insert(3.into(), &0, 4);
+        map.serialize().unwrap();
+
+        map.compact(2.into(), &[0.into(), 1.into()]).unwrap();
+        assert!(!tempdir.as_ref().join("tree_map.0.data").exists());
+        assert!(!tempdir.as_ref().join("tree_map.1.data").exists());
+        assert_eq!(map.get_latest(&0), Some(&4));
+        assert_eq!(map.get_latest(&1), Some(&10));
+        drop(map);
+
+        let (dim_bufman, data_bufmans) = open_bufmans(tempdir.as_ref());
+        let map: TreeMap<u64, u64> = TreeMap::deserialize(dim_bufman, data_bufmans).unwrap();
+        assert_eq!(map.get_latest(&0), Some(&4));
+        assert_eq!(map.get_latest(&1), Some(&10));
+        let item = map.get_versioned(&0).unwrap();
+        assert_eq!(*item.version, 2);
+        assert_eq!(item.next.as_ref().unwrap().value, Some(3));
+        assert_eq!(*map.get_versioned(&1).unwrap().version, 2);
+    }
+
+    #[test]
+    fn test_vec_compaction() {
+        let tempdir = tempdir().unwrap();
+        let (dim_bufman, data_bufmans) = open_bufmans(tempdir.as_ref());
+        let map: TreeMapVec<u64, u32> = TreeMapVec::new(dim_bufman, data_bufmans);
+        map.push(0.into(), &0, 1);
+        map.push(0.into(), &0, 2);
+        map.push(1.into(), &0, 3);
+        map.delete(2.into(), &0, 1);
+        map.push(3.into(), &0, 4);
+        map.serialize().unwrap();
+
+        map.compact(1.into(), &[0.into()]).unwrap();
+        assert!(!tempdir.as_ref().join("tree_map.0.data").exists());
+        assert_eq!(map.get(&0).unwrap().iter().collect::<Vec<_>>(), [2, 3, 4]);
+        drop(map);
+
+        let (dim_bufman, data_bufmans) = open_bufmans(tempdir.as_ref());
+        let map: TreeMapVec<u64, u32> = TreeMapVec::deserialize(dim_bufman, data_bufmans).unwrap();
+        let list = map.get(&0).unwrap();
+        assert_eq!(*list.version, 1);
+        assert_eq!(list.iter().collect::<Vec<_>>(), [2, 3, 4]);
+    }
 }
@@ -2,17 +2,23 @@ use super::{
     buffered_io::{BufIoError, BufferManagerFactory},
     cache_loader::HNSWIndexCache,
     collection::{Collection, CollectionMetadata},
-    collection_transaction::ImplicitTransaction,
-    crypto::{DoubleSHA256Hash, SingleSHA256Hash},
+    collection_transaction::{ExplicitTransaction, ImplicitTransaction},
+    crypto::{get_current_timestamp, DoubleSHA256Hash, SingleSHA256Hash},
+    idempotency::IdempotentResponses,
     indexing_manager::IndexingManager,
     inverted_index::InvertedIndexRoot,
+    lmdb_map::{self, txn_guard, with_rw_txn},
     meta_persist::{
-        lmdb_init_collections_db, lmdb_init_db, load_collections, retrieve_average_document_length,
-        retrieve_background_version, retrieve_current_version, retrieve_highest_internal_id,
-        retrieve_values_upper_bound,
+        lmdb_init_collections_db, lmdb_init_db, load_collections, remove_open_transaction,
+        retrieve_average_document_length, retrieve_compaction_floor, retrieve_current_version,
+        retrieve_highest_internal_id, retrieve_open_transactions, retrieve_values_upper_bound,
+        retrieve_vector_count,
     },
+    metrics,
     paths::get_data_path,
     prob_node::ProbNode,
+    storage_backend,
+    tenants::TenantsMap,
     tf_idf_index::TFIDFIndexRoot,
     tree_map::{TreeMap, TreeMapKey, TreeMapVec},
     versioning::{VersionControl, VersionNumber},
@@ -34,9 +40,12 @@ use crate::{
         },
         inverted::InvertedIndex,
         tf_idf::TFIDFIndex,
-        IndexOps,
+        IndexDir, IndexOps, DENSE_INDEX_DIR, SPARSE_INDEX_DIR, TF_IDF_INDEX_DIR,
+    },
+    metadata::{
+        schema::MetadataDimensions, MetadataSchema, QueryFilterDimensions, SharedMetadataSchema,
+        HIGH_WEIGHT,
     },
-    metadata::{schema::MetadataDimensions, QueryFilterDimensions, HIGH_WEIGHT},
     models::{
         buffered_io::{BufferManager, FilelessBufferManager},
         common::*,
@@ -53,12 +62,13 @@ use crate::{
 };
 use crossbeam::channel;
 use dashmap::DashMap;
-use lmdb::{Cursor, Database, DatabaseFlags, Environment, Transaction, WriteFlags};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
 use rayon::ThreadPool;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use siphasher::sip::SipHasher24;
 use std::{
+    collections::HashMap,
     fmt,
     fs::{self, create_dir_all, OpenOptions},
     hash::{Hash as StdHash, Hasher},
@@ -537,7 +547,10 @@ pub struct MetaDb {
 
 impl MetaDb {
     pub fn from_env(env: Arc<Environment>, collection_name: &str) -> lmdb::Result<Self> {
-        let db = env.create_db(Some(collection_name), DatabaseFlags::empty())?;
+        let db = {
+            let _guard = txn_guard();
+            env.create_db(Some(collection_name), DatabaseFlags::empty())?
+        };
 
         Ok(Self { env, db })
     }
@@ -545,13 +558,14 @@ impl MetaDb {
 
 pub struct CollectionsMap {
     inner_collections: DashMap<String, Arc<Collection>>,
-    lmdb_env: Arc<Environment>,
+    pub(crate) lmdb_env: Arc<Environment>,
     // made it public temporarily
     // just to be able to persist collections from outside CollectionsMap
     pub(crate) lmdb_collections_db: Database,
-    lmdb_hnsw_index_db: Database,
-    lmdb_inverted_index_db: Database,
-    lmdb_tf_idf_index_db: Database,
+    // also needed to snapshot and restore collections
+    pub(crate) lmdb_hnsw_index_db: Database,
+    pub(crate) lmdb_inverted_index_db: Database,
+    pub(crate) lmdb_tf_idf_index_db: Database,
 }
 
 impl CollectionsMap {
@@ -587,183 +601,161 @@ impl CollectionsMap {
         .map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
 
         for collection_meta in collections {
-            let lmdb = MetaDb::from_env(collections_map.lmdb_env.clone(), &collection_meta.name)?;
-            let current_version = retrieve_current_version(&lmdb)?;
-            let vcs = VersionControl::from_existing(lmdb.env.clone(), lmdb.db);
-
-            // if collection has dense index load it from the lmdb
-            let hnsw_index = if collection_meta.dense_vector.enabled {
-                collections_map
-                    .load_hnsw_index(
-                        &collection_meta,
-                        &lmdb,
-                        &config,
-                        collection_meta
-                            .metadata_schema
-                            .as_ref()
-                            .map_or(1, |schema| schema.max_num_replicas()),
-                        current_version,
-                    )
-                    .unwrap()
-                    .map(Arc::new)
-            } else {
-                None
-            };
-
-            // if collection has inverted index load it from the lmdb
-            let inverted_index = if collection_meta.sparse_vector.enabled {
-                collections_map
-                    .load_inverted_index(&collection_meta, &lmdb)?
-                    .map(Arc::new)
-            } else {
-                None
-            };
-
-            let tf_idf_index = if collection_meta.tf_idf_options.enabled {
-                collections_map
-                    .load_tf_idf_index(&collection_meta, &lmdb)?
-                    .map(Arc::new)
-            } else {
-                None
-            };
+            let collection =
+                collections_map.load_collection(collection_meta, &config, &threadpool)?;
+            collections_map
+                .inner_collections
+                .insert(collection.meta.name.clone(), collection);
+        }
+        Ok(collections_map)
+    }
 
-            let collection_path: Arc<Path> =
-                get_collections_path().join(&collection_meta.name).into();
+    /// Loads a single collection from its metadata, its files and lmdb
+    /// entries. Its indexes are brought up to date with its current version
+    /// by `IndexingManager::index_pending_versions`.
+    pub(crate) fn load_collection(
+        &self,
+        collection_meta: CollectionMetadata,
+        config: &Arc<Config>,
+        threadpool: &Arc<ThreadPool>,
+    ) -> Result<Arc<Collection>, WaCustomError> {
+        let lmdb = MetaDb::from_env(self.lmdb_env.clone(), &collection_meta.name)?;
+        let current_version = retrieve_current_version(&lmdb)?;
+        let vcs = VersionControl::from_existing(lmdb.env.clone(), lmdb.db);
+
+        // if collection has dense index load it from the lmdb
+        let hnsw_index = if collection_meta.dense_vector.enabled {
+            self.load_hnsw_index(&collection_meta, &lmdb, config, current_version)
+                .unwrap()
+                .map(Arc::new)
+        } else {
+            None
+        };
 
-            let internal_to_external_map_dim_file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .truncate(false)
-                .create(true)
-                .open(collection_path.join("itoe.dim"))
-                .map_err(BufIoError::Io)?;
+        // if collection has inverted index load it from the lmdb
+        let inverted_index = if collection_meta.sparse_vector.enabled {
+            self.load_inverted_index(&collection_meta, &lmdb)?
+                .map(Arc::new)
+        } else {
+            None
+        };
 
-            let internal_to_external_map_dim_bufman =
-                BufferManager::new(internal_to_external_map_dim_file, 8192)
-                    .map_err(BufIoError::Io)?;
+        let tf_idf_index = if collection_meta.tf_idf_options.enabled {
+            self.load_tf_idf_index(&collection_meta, &lmdb)?
+                .map(Arc::new)
+        } else {
+            None
+        };
 
-            let internal_to_external_map_data_bufmans = BufferManagerFactory::new(
-                collection_path.clone(),
-                |root, version: &VersionNumber| root.join(format!("itoe.{}.data", **version)),
-                8192,
-            );
+        let collection_path: Arc<Path> = get_collections_path().join(&collection_meta.name).into();
 
-            let external_to_internal_map_dim_file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .truncate(false)
-                .create(true)
-                .open(collection_path.join("etoi.dim"))
-                .map_err(BufIoError::Io)?;
+        let internal_to_external_map_dim_bufman =
+            BufferManager::open(&collection_path.join("itoe.dim"), 8192).map_err(BufIoError::Io)?;
 
-            let external_to_internal_map_dim_bufman =
-                BufferManager::new(external_to_internal_map_dim_file, 8192)
-                    .map_err(BufIoError::Io)?;
+        let internal_to_external_map_data_bufmans = BufferManagerFactory::new(
+            collection_path.clone(),
+            |root, version: &VersionNumber| root.join(format!("itoe.{}.data", **version)),
+            8192,
+        );
 
-            let external_to_internal_map_data_bufmans = BufferManagerFactory::new(
-                collection_path.clone(),
-                |root, version: &VersionNumber| root.join(format!("etoi.{}.data", **version)),
-                8192,
-            );
+        let external_to_internal_map_dim_bufman =
+            BufferManager::open(&collection_path.join("etoi.dim"), 8192).map_err(BufIoError::Io)?;
 
-            let document_to_internals_map_dim_file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .truncate(false)
-                .create(true)
-                .open(collection_path.join("dtoi.dim"))
-                .map_err(BufIoError::Io)?;
+        let external_to_internal_map_data_bufmans = BufferManagerFactory::new(
+            collection_path.clone(),
+            |root, version: &VersionNumber| root.join(format!("etoi.{}.data", **version)),
+            8192,
+        );
 
-            let document_to_internals_map_dim_bufman =
-                BufferManager::new(document_to_internals_map_dim_file, 8192)
-                    .map_err(BufIoError::Io)?;
+        let document_to_internals_map_dim_bufman =
+            BufferManager::open(&collection_path.join("dtoi.dim"), 8192).map_err(BufIoError::Io)?;
 
-            let document_to_internals_map_data_bufmans = BufferManagerFactory::new(
-                collection_path.clone(),
-                |root, version: &VersionNumber| root.join(format!("dtoi.{}.data", **version)),
-                8192,
-            );
+        let document_to_internals_map_data_bufmans = BufferManagerFactory::new(
+            collection_path.clone(),
+            |root, version: &VersionNumber| root.join(format!("dtoi.{}.data", **version)),
+            8192,
+        );
 
-            let transaction_status_map_dim_file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .truncate(false)
-                .create(true)
-                .open(collection_path.join("txn_status.dim"))
+        let transaction_status_map_dim_bufman =
+            BufferManager::open(&collection_path.join("txn_status.dim"), 8192)
                 .map_err(BufIoError::Io)?;
 
-            let transaction_status_map_dim_bufman =
-                BufferManager::new(transaction_status_map_dim_file, 8192)
-                    .map_err(BufIoError::Io)?;
-
-            let transaction_status_map_data_bufmans = BufferManagerFactory::new(
-                collection_path.clone(),
-                |root, version: &VersionNumber| root.join(format!("txn_status.{}.data", **version)),
-                8192,
-            );
-
-            let id_counter_value = retrieve_highest_internal_id(&lmdb)?.unwrap_or_default();
-
-            let collection = Arc::new(Collection {
-                meta: collection_meta,
-                lmdb,
-                current_version: parking_lot::RwLock::new(current_version),
-                last_allotted_version: parking_lot::RwLock::new(current_version),
-                current_explicit_transaction: parking_lot::RwLock::new(None),
-                current_implicit_transaction: parking_lot::RwLock::new(
-                    ImplicitTransaction::default(),
-                ),
-                vcs,
-                internal_to_external_map: TreeMap::deserialize(
-                    internal_to_external_map_dim_bufman,
-                    internal_to_external_map_data_bufmans,
-                )?,
-                external_to_internal_map: TreeMap::deserialize(
-                    external_to_internal_map_dim_bufman,
-                    external_to_internal_map_data_bufmans,
-                )?,
-                document_to_internals_map: TreeMapVec::deserialize(
-                    document_to_internals_map_dim_bufman,
-                    document_to_internals_map_data_bufmans,
-                )?,
-                transaction_status_map: TreeMap::deserialize(
-                    transaction_status_map_dim_bufman,
-                    transaction_status_map_data_bufmans,
-                )?,
-                internal_id_counter: AtomicU32::new(id_counter_value),
-                hnsw_index: parking_lot::RwLock::new(hnsw_index),
-                inverted_index: parking_lot::RwLock::new(inverted_index),
-                tf_idf_index: parking_lot::RwLock::new(tf_idf_index),
-                indexing_manager: parking_lot::RwLock::new(None),
-                is_indexing: AtomicBool::new(false),
-            });
-
-            *collection.indexing_manager.write() = Some(IndexingManager::new(
-                collection.clone(),
-                config.clone(),
-                threadpool.clone(),
-            ));
+        let transaction_status_map_data_bufmans = BufferManagerFactory::new(
+            collection_path.clone(),
+            |root, version: &VersionNumber| root.join(format!("txn_status.{}.data", **version)),
+            8192,
+        );
 
-            let background_version = retrieve_background_version(&collection.lmdb)?;
-
-            if background_version != current_version {
-                for version in *background_version..*current_version {
-                    let version = VersionNumber::from(version + 1);
-                    IndexingManager::index_version_on_restart(
-                        &collection,
-                        &config,
-                        &threadpool,
-                        version,
-                    )
-                    .unwrap();
+        let id_counter_value = retrieve_highest_internal_id(&lmdb)?.unwrap_or_default();
+        let vector_count = retrieve_vector_count(&lmdb)?.unwrap_or_default();
+        let compaction_floor = retrieve_compaction_floor(&lmdb)?.map_or(0, |version| *version);
+
+        let collection = Arc::new(Collection {
+            meta: collection_meta,
+            lmdb,
+            current_version: parking_lot::RwLock::new(current_version),
+            last_allotted_version: parking_lot::RwLock::new(current_version),
+            open_explicit_transactions: parking_lot::RwLock::new(HashMap::new()),
+            recent_commits: parking_lot::Mutex::new(Vec::new()),
+            current_implicit_transaction: parking_lot::RwLock::new(ImplicitTransaction::default()),
+            vcs,
+            internal_to_external_map: TreeMap::deserialize(
+                internal_to_external_map_dim_bufman,
+                internal_to_external_map_data_bufmans,
+            )?,
+            external_to_internal_map: TreeMap::deserialize(
+                external_to_internal_map_dim_bufman,
+                external_to_internal_map_data_bufmans,
+            )?,
+            document_to_internals_map: TreeMapVec::deserialize(
+                document_to_internals_map_dim_bufman,
+                document_to_internals_map_data_bufmans,
+            )?,
+            transaction_status_map: TreeMap::deserialize(
+                transaction_status_map_dim_bufman,
+                transaction_status_map_data_bufmans,
+            )?,
+            internal_id_counter: AtomicU32::new(id_counter_value),
+            vector_count: AtomicU32::new(vector_count),
+            compaction_floor: AtomicU32::new(compaction_floor),
+            hnsw_index: parking_lot::RwLock::new(hnsw_index),
+            inverted_index: parking_lot::RwLock::new(inverted_index),
+            tf_idf_index: parking_lot::RwLock::new(tf_idf_index),
+            indexing_manager: parking_lot::RwLock::new(None),
+            is_indexing: AtomicBool::new(false),
+            compaction_lock: parking_lot::Mutex::new(()),
+            conditional_writes_lock: parking_lot::Mutex::new(()),
+            // unknown until the first sweep
+            has_expiring_vectors: AtomicBool::new(true),
+        });
+
+        *collection.indexing_manager.write() = Some(IndexingManager::new(
+            collection.clone(),
+            config.clone(),
+            threadpool.clone(),
+        ));
+
+        for (transaction_id, start_version) in retrieve_open_transactions(&collection.lmdb)? {
+            match ExplicitTransaction::restore(&collection, transaction_id.into(), start_version) {
+                Ok(transaction) => {
+                    collection
+                        .open_explicit_transactions
+                        .write()
+                        .insert(transaction.id, transaction);
+                }
+                Err(err) => {
+                    log::warn!(
+                        "Dropped open transaction {} of collection '{}', as its WAL can't be read: {}",
+                        transaction_id,
+                        collection.meta.name,
+                        err
+                    );
+                    remove_open_transaction(&collection.lmdb, transaction_id)?;
                 }
             }
-
-            collections_map
-                .inner_collections
-                .insert(collection.meta.name.clone(), collection);
         }
-        Ok(collections_map)
+
+        Ok(collection)
     }
 
     /// loads and initiates the dense index of a collection from lmdb
@@ -775,16 +767,9 @@ impl CollectionsMap {
         collection_meta: &CollectionMetadata,
         lmdb: &MetaDb,
         config: &Config,
-        max_replicas_per_node: u8,
         current_version: VersionNumber,
     ) -> Result<Option<HNSWIndex>, WaCustomError> {
         let collection_path: Arc<Path> = get_collections_path().join(&collection_meta.name).into();
-        let index_path = collection_path.join("dense_hnsw");
-
-        // Check if the path exists before proceeding
-        if !index_path.exists() {
-            return Ok(None);
-        }
 
         let Some(hnsw_index_data) = HNSWIndex::load_data(
             &self.lmdb_env,
@@ -794,7 +779,23 @@ impl CollectionsMap {
         else {
             return Ok(None);
         };
+        let dir = IndexDir::new(
+            &collection_path,
+            DENSE_INDEX_DIR,
+            hnsw_index_data.generation,
+        );
+        let index_path = dir.path().to_path_buf();
+
+        // Check if the path exists before proceeding
+        if !index_path.exists() {
+            return Ok(None);
+        }
+        dir.remove_stale()
+            .map_err(|e| WaCustomError::FsError(e.to_string()))?;
         let prop_file_path = index_path.join("prop.data");
+        storage_backend::backend()
+            .fetch(&prop_file_path)
+            .map_err(|e| WaCustomError::FsError(e.to_string()))?;
         let prop_file_result = OpenOptions::new()
             .create(true)
             .read(true)
@@ -842,9 +843,13 @@ impl CollectionsMap {
                 current_version,
             )?
         } else {
+            let nodes_path = index_path.join("nodes.ptr");
+            storage_backend::backend()
+                .fetch(&nodes_path)
+                .map_err(BufIoError::Io)?;
             let mut file = OpenOptions::new()
                 .read(true)
-                .open(index_path.join("nodes.ptr"))
+                .open(nodes_path)
                 .map_err(BufIoError::Io)?;
             FilelessBufferManager::from_file(&mut file, 8192)?
         };
@@ -1081,6 +1086,12 @@ impl CollectionsMap {
             .latest_version_links_bufman
             .close_cursor(latest_version_links_cursor)?;
 
+        let id_stride = hnsw_index_data.id_stride.unwrap_or_else(|| {
+            collection_meta
+                .metadata_schema
+                .get()
+                .map_or(1, |schema| schema.max_num_replicas() as u32)
+        });
         let hnsw_index = HNSWIndex::new(
             root_ptr,
             pseudo_root_ptr,
@@ -1094,8 +1105,10 @@ impl CollectionsMap {
             values_range.unwrap_or((-1.0, 1.0)),
             hnsw_index_data.sample_threshold,
             values_range.is_some(),
-            max_replicas_per_node,
+            collection_meta.metadata_schema.clone(),
+            id_stride,
             offset_counter,
+            dir,
         );
 
         Ok(Some(hnsw_index))
@@ -1108,11 +1121,6 @@ impl CollectionsMap {
         lmdb: &MetaDb,
     ) -> Result<Option<InvertedIndex>, WaCustomError> {
         let collection_path: Arc<Path> = get_collections_path().join(&collection_meta.name).into();
-        let index_path = collection_path.join("sparse_inverted_index");
-
-        if !index_path.exists() {
-            return Ok(None);
-        }
 
         let Some(inverted_index_data) = InvertedIndex::load_data(
             &self.lmdb_env,
@@ -1122,6 +1130,18 @@ impl CollectionsMap {
         else {
             return Ok(None);
         };
+        let dir = IndexDir::new(
+            &collection_path,
+            SPARSE_INDEX_DIR,
+            inverted_index_data.generation,
+        );
+        let index_path = dir.path().to_path_buf();
+
+        if !index_path.exists() {
+            return Ok(None);
+        }
+        dir.remove_stale()
+            .map_err(|e| WaCustomError::FsError(e.to_string()))?;
 
         let values_upper_bound = retrieve_values_upper_bound(lmdb)?;
         let inverted_index = InvertedIndex {
@@ -1135,6 +1155,7 @@ impl CollectionsMap {
             vectors_collected: AtomicUsize::new(0),
             sampling_data: crate::indexes::inverted::types::SamplingData::default(),
             sample_threshold: inverted_index_data.sample_threshold,
+            dir,
         };
 
         Ok(Some(inverted_index))
@@ -1147,11 +1168,6 @@ impl CollectionsMap {
         lmdb: &MetaDb,
     ) -> Result<Option<TFIDFIndex>, WaCustomError> {
         let collection_path: Arc<Path> = get_collections_path().join(&collection_meta.name).into();
-        let index_path = collection_path.join("tf_idf_index");
-
-        if !index_path.exists() {
-            return Ok(None);
-        }
 
         let Some(inverted_index_data) = TFIDFIndex::load_data(
             &self.lmdb_env,
@@ -1161,6 +1177,18 @@ impl CollectionsMap {
         else {
             return Ok(None);
         };
+        let dir = IndexDir::new(
+            &collection_path,
+            TF_IDF_INDEX_DIR,
+            inverted_index_data.generation,
+        );
+        let index_path = dir.path().to_path_buf();
+
+        if !index_path.exists() {
+            return Ok(None);
+        }
+        dir.remove_stale()
+            .map_err(|e| WaCustomError::FsError(e.to_string()))?;
 
         let average_document_length = retrieve_average_document_length(lmdb)?;
         let inverted_index = TFIDFIndex {
@@ -1173,6 +1201,7 @@ impl CollectionsMap {
             sample_threshold: inverted_index_data.sample_threshold,
             k1: inverted_index_data.k1,
             b: inverted_index_data.b,
+            dir,
         };
 
         Ok(Some(inverted_index))
@@ -1220,6 +1249,65 @@ impl CollectionsMap {
         Ok(())
     }
 
+    /// Replaces the indexes of the collection with the given ones, along
+    /// with its metadata schema if given, which are persisted in a single
+    /// LMDB transaction
+    pub fn replace_indexes(
+        &self,
+        collection: &Collection,
+        hnsw_index: Option<Arc<HNSWIndex>>,
+        inverted_index: Option<Arc<InvertedIndex>>,
+        tf_idf_index: Option<Arc<TFIDFIndex>>,
+        metadata_schema: Option<MetadataSchema>,
+    ) -> Result<(), WaCustomError> {
+        let name = &collection.meta.name;
+        let mut entries = Vec::new();
+        if let Some(metadata_schema) = &metadata_schema {
+            let meta = CollectionMetadata {
+                metadata_schema: SharedMetadataSchema::new(Some(metadata_schema.clone())),
+                ..collection.meta.clone()
+            };
+            let value = serde_cbor::to_vec(&meta)
+                .map_err(|e| WaCustomError::SerializationError(e.to_string()))?;
+            entries.push((self.lmdb_collections_db, (collection.get_key(), value)));
+        }
+        if let Some(hnsw_index) = &hnsw_index {
+            entries.push((self.lmdb_hnsw_index_db, hnsw_index.serialize_data(name)?));
+        }
+        if let Some(inverted_index) = &inverted_index {
+            entries.push((
+                self.lmdb_inverted_index_db,
+                inverted_index.serialize_data(name)?,
+            ));
+        }
+        if let Some(tf_idf_index) = &tf_idf_index {
+            entries.push((
+                self.lmdb_tf_idf_index_db,
+                tf_idf_index.serialize_data(name)?,
+            ));
+        }
+        with_rw_txn(&self.lmdb_env, |txn| {
+            for (db, (key, val)) in &entries {
+                txn.put(*db, key, val, WriteFlags::empty())?;
+            }
+            Ok(())
+        })?;
+
+        if let Some(metadata_schema) = metadata_schema {
+            collection.meta.metadata_schema.set(metadata_schema);
+        }
+        if let Some(hnsw_index) = hnsw_index {
+            *collection.hnsw_index.write() = Some(hnsw_index);
+        }
+        if let Some(inverted_index) = inverted_index {
+            *collection.inverted_index.write() = Some(inverted_index);
+        }
+        if let Some(tf_idf_index) = tf_idf_index {
+            *collection.tf_idf_index.write() = Some(tf_idf_index);
+        }
+        Ok(())
+    }
+
     /// inserts a collection into the collections map
     #[allow(dead_code)]
     pub fn insert_collection(&self, collection: Arc<Collection>) -> Result<(), WaCustomError> {
@@ -1298,7 +1386,10 @@ impl CollectionsMap {
     #[allow(dead_code)]
     pub fn remove_collection(&self, name: &str) -> Result<Arc<Collection>, WaCustomError> {
         match self.inner_collections.remove(name) {
-            Some((_, collection)) => Ok(collection),
+            Some((_, collection)) => {
+                metrics::remove_collection(name);
+                Ok(collection)
+            }
             None => {
                 // collection not found, return an error response
                 Err(WaCustomError::NotFound("collection".into()))
@@ -1320,6 +1411,9 @@ impl CollectionsMap {
     }
 }
 
+// Username of the built-in admin user, whose password is the admin key
+pub const ADMIN_USERNAME: &str = "admin";
+
 pub struct UsersMap {
     env: Arc<Environment>,
     users_db: Database,
@@ -1351,21 +1445,20 @@ impl UsersMap {
             username: username.clone(),
             password_hash,
         };
-        let user_bytes = user.serialize();
-        let username_bytes = username.as_bytes();
+        with_rw_txn(&self.env, |txn| self.put(txn, &user))?;
+        self.map.insert(username, user);
+        Ok(())
+    }
 
-        let mut txn = self.env.begin_rw_txn()?;
+    // Writes the user in `txn`, the map is left to the caller to update once
+    // the transaction is committed
+    fn put(&self, txn: &mut RwTransaction<'_>, user: &User) -> lmdb::Result<()> {
         txn.put(
             self.users_db,
-            &username_bytes,
-            &user_bytes,
+            &user.username.as_bytes(),
+            &user.serialize(),
             WriteFlags::empty(),
-        )?;
-        txn.commit()?;
-
-        self.map.insert(username, user);
-
-        Ok(())
+        )
     }
 
     pub fn get_user(&self, username: &str) -> Option<User> {
@@ -1403,12 +1496,352 @@ impl User {
     }
 }
 
+// Sessions and API keys share a single LMDB database, told apart by the key
+// prefix, so that they don't eat into the number of named databases
+// available for collections
+const AUTH_DB_NAME: &str = "auth";
+const SESSION_KEY_PREFIX: &str = "session:";
+const API_KEY_KEY_PREFIX: &str = "api_key:";
+
+#[derive(Clone)]
 pub struct SessionDetails {
     pub created_at: u64,
     pub expires_at: u64,
     pub user: User,
 }
 
+impl SessionDetails {
+    fn serialize(&self) -> Vec<u8> {
+        let username_bytes = self.user.username.as_bytes();
+        let mut buf = Vec::with_capacity(16 + username_bytes.len());
+        buf.extend_from_slice(&self.created_at.to_le_bytes());
+        buf.extend_from_slice(&self.expires_at.to_le_bytes());
+        buf.extend_from_slice(username_bytes);
+        buf
+    }
+
+    // Returns the timestamps and the username, the user itself is resolved
+    // from `UsersMap`
+    fn deserialize(buf: &[u8]) -> Result<(u64, u64, String), String> {
+        if buf.len() < 16 {
+            return Err("Input must be at least 16 bytes".to_string());
+        }
+        let created_at = u64::from_le_bytes(buf[..8].try_into().unwrap());
+        let expires_at = u64::from_le_bytes(buf[8..16].try_into().unwrap());
+        let username = String::from_utf8(buf[16..].to_vec()).map_err(|err| err.to_string())?;
+        Ok((created_at, expires_at, username))
+    }
+}
+
+// Sessions persisted to LMDB, so that they survive restarts. Sessions are
+// keyed by the SHA256 hash of their access token, so that the tokens
+// themselves are never written to disk
+pub struct SessionsMap {
+    env: Arc<Environment>,
+    db: Database,
+    // (hash of the access token, session details)
+    map: DashMap<[u8; 32], SessionDetails>,
+}
+
+fn session_key(token_hash: &[u8; 32]) -> Vec<u8> {
+    [SESSION_KEY_PREFIX.as_bytes(), token_hash].concat()
+}
+
+fn hash_access_token(access_token: &str) -> [u8; 32] {
+    SingleSHA256Hash::new(access_token.as_bytes()).0
+}
+
+impl SessionsMap {
+    pub fn new(env: Arc<Environment>, users_map: &UsersMap) -> lmdb::Result<Self> {
+        let db = env.create_db(Some(AUTH_DB_NAME), DatabaseFlags::empty())?;
+        let txn = env.begin_ro_txn()?;
+        let mut cursor = txn.open_ro_cursor(db)?;
+        let map = DashMap::new();
+        // expired, orphaned or corrupt records, deleted once loaded
+        let mut stale_keys = Vec::new();
+        let current_time = get_current_timestamp();
+
+        for (key, session_bytes) in cursor.iter() {
+            let Some(token_hash) = key.strip_prefix(SESSION_KEY_PREFIX.as_bytes()) else {
+                continue;
+            };
+            let session = <[u8; 32]>::try_from(token_hash)
+                .map_err(|_| "invalid access token hash".to_string())
+                .and_then(|token_hash| {
+                    SessionDetails::deserialize(session_bytes).map(|details| (token_hash, details))
+                });
+            match session {
+                Ok((token_hash, (created_at, expires_at, username))) => {
+                    match users_map.get_user(&username) {
+                        Some(user) if expires_at > current_time => {
+                            map.insert(
+                                token_hash,
+                                SessionDetails {
+                                    created_at,
+                                    expires_at,
+                                    user,
+                                },
+                            );
+                        }
+                        _ => stale_keys.push(key.to_vec()),
+                    }
+                }
+                Err(err) => {
+                    log::warn!("Deleting corrupt session record: {}", err);
+                    stale_keys.push(key.to_vec());
+                }
+            }
+        }
+
+        drop(cursor);
+        txn.abort();
+
+        if !stale_keys.is_empty() {
+            with_rw_txn(&env, |txn| {
+                for key in &stale_keys {
+                    match txn.del(db, key, None) {
+                        Ok(()) | Err(lmdb::Error::NotFound) => {}
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(Self { env, db, map })
+    }
+
+    pub fn insert(&self, access_token: &str, session: SessionDetails) -> lmdb::Result<()> {
+        let token_hash = hash_access_token(access_token);
+
+        with_rw_txn(&self.env, |txn| {
+            txn.put(
+                self.db,
+                &session_key(&token_hash),
+                &session.serialize(),
+                WriteFlags::empty(),
+            )
+        })?;
+
+        self.map.insert(token_hash, session);
+
+        Ok(())
+    }
+
+    pub fn get(&self, access_token: &str) -> Option<SessionDetails> {
+        self.map
+            .get(&hash_access_token(access_token))
+            .map(|session| session.value().clone())
+    }
+
+    /// Number of sessions that have not expired
+    pub fn active_count(&self) -> usize {
+        let current_time = get_current_timestamp();
+        self.map
+            .iter()
+            .filter(|session| session.expires_at > current_time)
+            .count()
+    }
+
+    pub fn remove(&self, access_token: &str) -> lmdb::Result<Option<SessionDetails>> {
+        let token_hash = hash_access_token(access_token);
+        let Some((_, session)) = self.map.remove(&token_hash) else {
+            return Ok(None);
+        };
+        with_rw_txn(&self.env, |txn| self.delete_in(txn, &[token_hash]))?;
+        Ok(Some(session))
+    }
+
+    /// Returns the token hashes of all sessions, to be ended with
+    /// `delete_in` and `forget`
+    fn token_hashes(&self) -> Vec<[u8; 32]> {
+        self.map.iter().map(|session| *session.key()).collect()
+    }
+
+    // Deletes the sessions in `txn`, they must be forgotten once the
+    // transaction is committed
+    fn delete_in(
+        &self,
+        txn: &mut RwTransaction<'_>,
+        token_hashes: &[[u8; 32]],
+    ) -> lmdb::Result<()> {
+        for token_hash in token_hashes {
+            match txn.del(self.db, &session_key(token_hash), None) {
+                Ok(()) | Err(lmdb::Error::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn forget(&self, token_hashes: &[[u8; 32]]) {
+        for token_hash in token_hashes {
+            self.map.remove(token_hash);
+        }
+    }
+}
+
+// Long-lived API key for service-to-service auth, only the double hash of
+// the key is stored
+#[derive(Clone)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    pub username: String,
+    pub key_hash: DoubleSHA256Hash,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+impl ApiKey {
+    fn serialize(&self) -> Vec<u8> {
+        let username_bytes = self.username.as_bytes();
+        let name_bytes = self.name.as_bytes();
+        let mut buf = Vec::with_capacity(52 + username_bytes.len() + name_bytes.len());
+        buf.extend_from_slice(&self.key_hash.0);
+        buf.extend_from_slice(&self.created_at.to_le_bytes());
+        // 0 means the key never expires
+        buf.extend_from_slice(&self.expires_at.unwrap_or(0).to_le_bytes());
+        buf.extend_from_slice(&(username_bytes.len() as u32).to_le_bytes());
+        buf.extend_from_slice(username_bytes);
+        buf.extend_from_slice(name_bytes);
+        buf
+    }
+
+    fn deserialize(id: String, buf: &[u8]) -> Result<Self, String> {
+        if buf.len() < 52 {
+            return Err("Input must be at least 52 bytes".to_string());
+        }
+        let mut key_hash = [0u8; 32];
+        key_hash.copy_from_slice(&buf[..32]);
+        let created_at = u64::from_le_bytes(buf[32..40].try_into().unwrap());
+        let expires_at = u64::from_le_bytes(buf[40..48].try_into().unwrap());
+        let username_len = u32::from_le_bytes(buf[48..52].try_into().unwrap()) as usize;
+        if buf.len() < 52 + username_len {
+            return Err("Input is shorter than the encoded username".to_string());
+        }
+        let username = String::from_utf8(buf[52..52 + username_len].to_vec())
+            .map_err(|err| err.to_string())?;
+        let name =
+            String::from_utf8(buf[52 + username_len..].to_vec()).map_err(|err| err.to_string())?;
+        Ok(Self {
+            id,
+            name,
+            username,
+            key_hash: DoubleSHA256Hash(key_hash),
+            created_at,
+            expires_at: (expires_at != 0).then_some(expires_at),
+        })
+    }
+}
+
+pub struct ApiKeysMap {
+    env: Arc<Environment>,
+    db: Database,
+    // (key id, API key details)
+    map: DashMap<String, ApiKey>,
+}
+
+impl ApiKeysMap {
+    pub fn new(env: Arc<Environment>) -> lmdb::Result<Self> {
+        let db = env.create_db(Some(AUTH_DB_NAME), DatabaseFlags::empty())?;
+        let txn = env.begin_ro_txn()?;
+        let mut cursor = txn.open_ro_cursor(db)?;
+        let map = DashMap::new();
+        // corrupt records, deleted once loaded
+        let mut corrupt_keys = Vec::new();
+
+        for (key, api_key_bytes) in cursor.iter() {
+            let Some(id) = key.strip_prefix(API_KEY_KEY_PREFIX.as_bytes()) else {
+                continue;
+            };
+            let api_key = String::from_utf8(id.to_vec())
+                .map_err(|err| err.to_string())
+                .and_then(|id| ApiKey::deserialize(id, api_key_bytes));
+            match api_key {
+                Ok(api_key) => {
+                    map.insert(api_key.id.clone(), api_key);
+                }
+                Err(err) => {
+                    log::warn!("Deleting corrupt API key record: {}", err);
+                    corrupt_keys.push(key.to_vec());
+                }
+            }
+        }
+
+        drop(cursor);
+        txn.abort();
+
+        if !corrupt_keys.is_empty() {
+            with_rw_txn(&env, |txn| {
+                for key in &corrupt_keys {
+                    match txn.del(db, key, None) {
+                        Ok(()) | Err(lmdb::Error::NotFound) => {}
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(Self { env, db, map })
+    }
+
+    pub fn add(&self, api_key: ApiKey) -> lmdb::Result<()> {
+        let key = format!("{}{}", API_KEY_KEY_PREFIX, api_key.id);
+
+        with_rw_txn(&self.env, |txn| {
+            txn.put(self.db, &key, &api_key.serialize(), WriteFlags::empty())
+        })?;
+
+        self.map.insert(api_key.id.clone(), api_key);
+
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<ApiKey> {
+        self.map.get(id).map(|api_key| api_key.value().clone())
+    }
+
+    pub fn list_for_user(&self, username: &str) -> Vec<ApiKey> {
+        let mut api_keys: Vec<_> = self
+            .map
+            .iter()
+            .filter(|api_key| api_key.username == username)
+            .map(|api_key| api_key.value().clone())
+            .collect();
+        api_keys.sort_unstable_by_key(|api_key| api_key.created_at);
+        api_keys
+    }
+
+    pub fn remove(&self, id: &str) -> lmdb::Result<Option<ApiKey>> {
+        let Some((_, api_key)) = self.map.remove(id) else {
+            return Ok(None);
+        };
+        with_rw_txn(&self.env, |txn| self.delete_in(txn, &[api_key.id.clone()]))?;
+        Ok(Some(api_key))
+    }
+
+    // Deletes the API keys in `txn`, they must be forgotten once the
+    // transaction is committed
+    fn delete_in(&self, txn: &mut RwTransaction<'_>, ids: &[String]) -> lmdb::Result<()> {
+        for id in ids {
+            let key = format!("{}{}", API_KEY_KEY_PREFIX, id);
+            match txn.del(self.db, &key, None) {
+                Ok(()) | Err(lmdb::Error::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn forget(&self, ids: &[String]) {
+        for id in ids {
+            self.map.remove(id);
+        }
+    }
+}
+
 // Define the AppEnv struct
 pub struct AppEnv {
     pub collections_map: CollectionsMap,
@@ -1416,8 +1849,69 @@ pub struct AppEnv {
     pub persist: Arc<Environment>,
     // Single hash, must not be persisted to disk, only the double hash must be
     // written to disk
-    pub admin_key: SingleSHA256Hash,
-    pub active_sessions: Arc<DashMap<String, SessionDetails>>,
+    pub admin_key: parking_lot::RwLock<SingleSHA256Hash>,
+    pub active_sessions: SessionsMap,
+    pub api_keys: ApiKeysMap,
+    pub tenants_map: TenantsMap,
+    pub idempotent_responses: IdempotentResponses,
+}
+
+impl AppEnv {
+    /// Replaces the admin key, returns `Ok(false)` without changing anything
+    /// if `old_key` isn't the current admin key.
+    ///
+    /// The admin user's password is the admin key, so it's changed along
+    /// with it. Session tokens are derived from the admin key, all active
+    /// sessions are therefore ended, and the admin's API keys are revoked.
+    /// All of it is written in a single LMDB transaction.
+    pub fn rotate_admin_key(&self, old_key: &str, new_key: &str) -> lmdb::Result<bool> {
+        // held until the rotation is complete, so that no session can be
+        // created with the old key in the meantime
+        let mut admin_key = self.admin_key.write();
+
+        let old_key_hash = SingleSHA256Hash::from_str(old_key).unwrap();
+        if !old_key_hash.verify_eq(&admin_key) {
+            return Ok(false);
+        }
+
+        let new_key_hash = SingleSHA256Hash::from_str(new_key).unwrap();
+        let admin = User {
+            username: ADMIN_USERNAME.to_string(),
+            password_hash: new_key_hash.hash_again(),
+        };
+        let token_hashes = self.active_sessions.token_hashes();
+        let api_key_ids: Vec<String> = self
+            .api_keys
+            .list_for_user(ADMIN_USERNAME)
+            .into_iter()
+            .map(|api_key| api_key.id)
+            .collect();
+        with_rw_txn(&self.persist, |txn| {
+            store_admin_key(txn, &admin.password_hash)?;
+            self.users_map.put(txn, &admin)?;
+            self.active_sessions.delete_in(txn, &token_hashes)?;
+            self.api_keys.delete_in(txn, &api_key_ids)
+        })?;
+
+        self.users_map.map.insert(admin.username.clone(), admin);
+        self.active_sessions.forget(&token_hashes);
+        self.api_keys.forget(&api_key_ids);
+        *admin_key = new_key_hash;
+
+        log::info!(
+            "Admin key rotated, all active sessions have been ended and {} API keys of the admin revoked",
+            api_key_ids.len()
+        );
+        Ok(true)
+    }
+}
+
+fn store_admin_key(
+    txn: &mut RwTransaction<'_>,
+    admin_key_hash: &DoubleSHA256Hash,
+) -> lmdb::Result<()> {
+    let db = unsafe { txn.open_db(Some("meta"))? };
+    txn.put(db, &"admin_key", &admin_key_hash.0, WriteFlags::empty())
 }
 
 fn get_admin_key(env: Arc<Environment>, args: CosdataArgs) -> lmdb::Result<SingleSHA256Hash> {
@@ -1463,15 +1957,7 @@ fn get_admin_key(env: Arc<Environment>, args: CosdataArgs) -> lmdb::Result<Singl
         let arg_admin_key_double_hash = arg_admin_key_hash.hash_again();
 
         // Store the admin key double hash in the database
-        let mut txn = env.begin_rw_txn()?;
-        let db = unsafe { txn.open_db(Some("meta"))? };
-        txn.put(
-            db,
-            &"admin_key",
-            &arg_admin_key_double_hash.0,
-            WriteFlags::empty(),
-        )?;
-        txn.commit()?;
+        with_rw_txn(&env, |txn| store_admin_key(txn, &arg_admin_key_double_hash))?;
         arg_admin_key_hash
     };
     Ok(admin_key_hash)
@@ -1481,17 +1967,14 @@ pub fn get_collections_path() -> PathBuf {
     get_data_path().join("collections")
 }
 
-pub fn get_app_env(
-    config: Arc<Config>,
-    threadpool: Arc<ThreadPool>,
-    args: CosdataArgs,
-) -> Result<Arc<AppEnv>, WaCustomError> {
+/// Path of the LMDB environment
+pub fn get_lmdb_path() -> PathBuf {
     // Check both possible db path locations
     let db_path_1 = get_data_path().join("_mdb");
     let db_path_2 = get_data_path().join("data/_mdb");
 
     // Use whichever path exists, or default to db_path_2
-    let db_path = if db_path_1.exists() {
+    if db_path_1.exists() {
         //println!("Using existing database at {}", db_path_1.display());
         db_path_1
     } else if db_path_2.exists() {
@@ -1500,7 +1983,15 @@ pub fn get_app_env(
     } else {
         //println!("Creating new database at {}", db_path_2.display());
         db_path_2 // Default for first-time setup
-    };
+    }
+}
+
+pub fn get_app_env(
+    config: Arc<Config>,
+    threadpool: Arc<ThreadPool>,
+    args: CosdataArgs,
+) -> Result<Arc<AppEnv>, WaCustomError> {
+    let db_path = get_lmdb_path();
 
     // Check if this is first-time setup
     let is_first_time = !db_path.exists();
@@ -1541,10 +2032,12 @@ pub fn get_app_env(
     create_dir_all(&db_path).map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
     // Initialize the environment
     let env = Environment::new()
-        .set_max_dbs(10)
-        .set_map_size(1048576000) // Set the maximum size of the database to 1GB
+        .set_max_dbs(config.lmdb.max_dbs)
+        // initial size, grown on demand, see `lmdb_map`
+        .set_map_size(config.lmdb.map_size)
         .open(&db_path)
         .map_err(|e| WaCustomError::DatabaseError(e.to_string()))?;
+    lmdb_map::set_max_map_size(config.lmdb.max_map_size);
 
     let env_arc = Arc::new(env);
 
@@ -1563,7 +2056,7 @@ pub fn get_app_env(
     };
 
     // Use the admin key as the password instead of hardcoded "admin"
-    let username = "admin".to_string();
+    let username = ADMIN_USERNAME.to_string();
     let password = args.admin_key.clone();
     let password_hash = DoubleSHA256Hash::from_str(&password).unwrap();
 
@@ -1578,12 +2071,24 @@ pub fn get_app_env(
         }
     };
 
+    let active_sessions = SessionsMap::new(env_arc.clone(), &users_map)
+        .map_err(|err| WaCustomError::DatabaseError(err.to_string()))?;
+    let api_keys = ApiKeysMap::new(env_arc.clone())
+        .map_err(|err| WaCustomError::DatabaseError(err.to_string()))?;
+    let tenants_map = TenantsMap::new(env_arc.clone())
+        .map_err(|err| WaCustomError::DatabaseError(err.to_string()))?;
+    let idempotent_responses = IdempotentResponses::new(env_arc.clone())
+        .map_err(|err| WaCustomError::DatabaseError(err.to_string()))?;
+
     Ok(Arc::new(AppEnv {
         collections_map,
         users_map,
         persist: env_arc,
-        admin_key,
-        active_sessions: Arc::new(DashMap::new()),
+        admin_key: parking_lot::RwLock::new(admin_key),
+        active_sessions,
+        api_keys,
+        tenants_map,
+        idempotent_responses,
     }))
 }
 
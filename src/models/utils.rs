@@ -1,3 +1,5 @@
+use std::{fs, io, path::Path};
+
 /// Returns the largest power of 4 that is less than or equal to `n`.
 /// Iteratively multiplies by 4 until the result exceeds `n`.
 pub fn largest_power_of_4_below(n: u32) -> (u8, u32) {
@@ -22,3 +24,17 @@ pub fn calculate_path(target_dim_index: u32, current_dim_index: u32) -> Vec<u8>
 
     path
 }
+/// Total size in bytes of the files under `path`
+pub fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
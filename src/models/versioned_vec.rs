@@ -202,6 +202,62 @@ impl<T: VersionedVecItem> VersionedVec<T> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Merges the lists of all versions up to `retain_from` into a single
+    /// list at `retain_from`, without the deleted items. Returns `true` if
+    /// anything changed and needs to be serialized again.
+    pub fn compact(&mut self, retain_from: VersionNumber) -> bool {
+        if *self.version >= *retain_from {
+            return false;
+        }
+
+        let mut list = Vec::new();
+        let mut next = Some(Box::new(Self {
+            serialized_at: RwLock::new(None),
+            version: self.version,
+            list: std::mem::take(&mut self.list),
+            next: self.next.take(),
+            _marker: PhantomData,
+        }));
+        while let Some(mut node) = next.take_if(|node| *node.version <= *retain_from) {
+            // deleted items are already marked in memory, which makes the
+            // delete markers redundant
+            list.extend(
+                node.list
+                    .iter()
+                    .copied()
+                    .filter(|item| *item != u64::MAX && (item & (1 << 63)) == 0),
+            );
+            next = node.next.take();
+        }
+
+        // markers in the remaining versions may point at items that were just
+        // merged, they are blanked out instead of removed to keep the indices
+        // of the other items stable
+        let mut node = next.as_deref_mut();
+        while let Some(current) = node {
+            let mut changed = false;
+            for item in &mut current.list {
+                if *item != u64::MAX
+                    && (*item & (1 << 63)) != 0
+                    && ((*item >> 32) & 0x7FFFFFFF) as u32 <= *retain_from
+                {
+                    *item = u64::MAX;
+                    changed = true;
+                }
+            }
+            if changed {
+                *current.serialized_at.get_mut().unwrap() = None;
+            }
+            node = current.next.as_deref_mut();
+        }
+
+        self.version = retain_from;
+        self.list = list;
+        self.next = next;
+        *self.serialized_at.get_mut().unwrap() = None;
+        true
+    }
 }
 
 impl VersionedVec<(u32, f32)> {
@@ -223,6 +279,28 @@ impl VersionedVec<(u32, f32)> {
             self.next = Some(new_next);
         }
     }
+
+    /// Returns the value stored for the document `id`, binary searching
+    /// the list of each version, which `push_sorted` keeps sorted apart
+    /// from the deleted items and delete markers
+    pub fn get(&self, id: u32) -> Option<f32> {
+        let is_item = |item: u64| item != u64::MAX && (item & (1 << 63)) == 0;
+        let (mut low, mut high) = (0, self.list.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let Some(i) = (middle..high).find(|&i| is_item(self.list[i])) else {
+                high = middle;
+                continue;
+            };
+            let (item_id, value) = <(u32, f32)>::from_storage(self.list[i]);
+            match item_id.cmp(&id) {
+                std::cmp::Ordering::Equal => return Some(value),
+                std::cmp::Ordering::Less => low = i + 1,
+                std::cmp::Ordering::Greater => high = middle,
+            }
+        }
+        self.next.as_ref().and_then(|next| next.get(id))
+    }
 }
 
 impl<T> PartialEq for VersionedVec<T> {
@@ -1,3 +1,4 @@
+use crate::config_loader::RetentionConfig;
 use crate::macros::key;
 use chrono::{DateTime, Utc};
 use lmdb::{Cursor, Database, Environment, Transaction, WriteFlags};
@@ -8,6 +9,7 @@ use std::sync::Arc;
 use utoipa::ToSchema;
 
 use super::collection_transaction::ExplicitTransactionID;
+use super::lmdb_map::{txn_guard, with_rw_txn};
 use super::tree_map::TreeMapKey;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
@@ -95,7 +97,7 @@ impl VersionInfo {
         }
     }
 
-    fn serialize(&self) -> Vec<u8> {
+    pub(crate) fn serialize(&self) -> Vec<u8> {
         let mut result = Vec::with_capacity(29);
 
         result.extend_from_slice(&self.version.to_le_bytes());
@@ -119,7 +121,7 @@ impl VersionInfo {
         result
     }
 
-    fn deserialize(bytes: &[u8]) -> Result<Self, &'static str> {
+    pub(crate) fn deserialize(bytes: &[u8]) -> Result<Self, &'static str> {
         if bytes.len() != 29 {
             return Err("Input must be exactly 29 bytes");
         }
@@ -172,15 +174,15 @@ impl VersionControl {
         let current_version_key = key!(m:current_version);
         let version_bytes = version_meta.serialize();
 
-        let mut txn = env.begin_rw_txn()?;
-        txn.put(db, &version_key, &version_bytes, WriteFlags::empty())?;
-        txn.put(
-            db,
-            &current_version_key,
-            &version.to_le_bytes(),
-            WriteFlags::empty(),
-        )?;
-        txn.commit()?;
+        with_rw_txn(&env, |txn| {
+            txn.put(db, &version_key, &version_bytes, WriteFlags::empty())?;
+            txn.put(
+                db,
+                &current_version_key,
+                &version.to_le_bytes(),
+                WriteFlags::empty(),
+            )
+        })?;
 
         Ok((Self { env, db }, version))
     }
@@ -205,6 +207,7 @@ impl VersionControl {
     }
 
     pub fn get_current_version(&self) -> lmdb::Result<VersionNumber> {
+        let _guard = txn_guard();
         let txn = self.env.begin_ro_txn()?;
         let version = self.get_current_version_inner(&txn)?;
         txn.abort();
@@ -219,7 +222,6 @@ impl VersionControl {
         records_deleted: u32,
         total_operations: u32,
     ) -> lmdb::Result<()> {
-        let mut txn = self.env.begin_rw_txn()?;
         let current_version_key = key!(m:current_version);
         let version_key = key!(v:version);
 
@@ -233,21 +235,20 @@ impl VersionControl {
         );
         let version_info_serialized = version_info.serialize();
 
-        txn.put(
-            self.db,
-            &current_version_key,
-            &version.to_le_bytes(),
-            WriteFlags::empty(),
-        )?;
-        txn.put(
-            self.db,
-            &version_key,
-            &version_info_serialized,
-            WriteFlags::empty(),
-        )?;
-
-        txn.commit()?;
-        Ok(())
+        with_rw_txn(&self.env, |txn| {
+            txn.put(
+                self.db,
+                &current_version_key,
+                &version.to_le_bytes(),
+                WriteFlags::empty(),
+            )?;
+            txn.put(
+                self.db,
+                &version_key,
+                &version_info_serialized,
+                WriteFlags::empty(),
+            )
+        })
     }
 
     pub fn set_current_version_implicit(
@@ -255,28 +256,26 @@ impl VersionControl {
         version: VersionNumber,
         epoch_id: u32,
     ) -> lmdb::Result<()> {
-        let mut txn = self.env.begin_rw_txn()?;
         let current_version_key = key!(m:current_version);
         let version_key = key!(v:version);
 
         let version_info = VersionInfo::new_implicit(version, epoch_id, Utc::now(), 0, 0, 0);
         let version_info_serialized = version_info.serialize();
 
-        txn.put(
-            self.db,
-            &current_version_key,
-            &version.to_le_bytes(),
-            WriteFlags::empty(),
-        )?;
-        txn.put(
-            self.db,
-            &version_key,
-            &version_info_serialized,
-            WriteFlags::empty(),
-        )?;
-
-        txn.commit()?;
-        Ok(())
+        with_rw_txn(&self.env, |txn| {
+            txn.put(
+                self.db,
+                &current_version_key,
+                &version.to_le_bytes(),
+                WriteFlags::empty(),
+            )?;
+            txn.put(
+                self.db,
+                &version_key,
+                &version_info_serialized,
+                WriteFlags::empty(),
+            )
+        })
     }
 
     pub fn update_version_metadata(
@@ -286,26 +285,25 @@ impl VersionControl {
         records_deleted: u32,
         total_operations: u32,
     ) -> lmdb::Result<()> {
-        let mut txn = self.env.begin_rw_txn()?;
         let version_key = key!(v:version);
 
-        let bytes = txn.get(self.db, &version_key)?;
-        let mut version_info = VersionInfo::deserialize(bytes).unwrap();
-        version_info.records_upserted = records_upserted;
-        version_info.records_deleted = records_deleted;
-        version_info.total_operations = total_operations;
-        txn.put(
-            self.db,
-            &version_key,
-            &version_info.serialize(),
-            WriteFlags::empty(),
-        )?;
-
-        txn.commit()?;
-        Ok(())
+        with_rw_txn(&self.env, |txn| {
+            let bytes = txn.get(self.db, &version_key)?;
+            let mut version_info = VersionInfo::deserialize(bytes).unwrap();
+            version_info.records_upserted = records_upserted;
+            version_info.records_deleted = records_deleted;
+            version_info.total_operations = total_operations;
+            txn.put(
+                self.db,
+                &version_key,
+                &version_info.serialize(),
+                WriteFlags::empty(),
+            )
+        })
     }
 
     pub fn get_version(&self, version: VersionNumber) -> lmdb::Result<VersionInfo> {
+        let _guard = txn_guard();
         let txn = self.env.begin_ro_txn()?;
         let version_key = key!(v:version);
         let bytes = txn.get(self.db, &version_key)?;
@@ -314,6 +312,7 @@ impl VersionControl {
     }
 
     pub fn get_versions(&self) -> lmdb::Result<Vec<VersionInfo>> {
+        let _guard = txn_guard();
         let txn = self.env.begin_ro_txn()?;
         let mut cursor = txn.open_ro_cursor(self.db)?;
         let current_version = self.get_current_version_inner(&txn)?;
@@ -333,6 +332,62 @@ impl VersionControl {
         Ok(versions)
     }
 
+    /// Returns the oldest version retained by `policy`, `None` if the policy
+    /// doesn't limit retention. The current version is always retained.
+    pub fn retention_cutoff(
+        &self,
+        policy: &RetentionConfig,
+        now: DateTime<Utc>,
+    ) -> lmdb::Result<Option<VersionNumber>> {
+        if policy.keep_versions.is_none() && policy.keep_hours.is_none() {
+            return Ok(None);
+        }
+        let versions = self.get_versions()?;
+        let Some(last) = versions.last() else {
+            return Ok(None);
+        };
+
+        // a version is retained if either rule retains it
+        let mut cutoff = last.version;
+        if let Some(keep_versions) = policy.keep_versions {
+            let idx = versions.len().saturating_sub(keep_versions.max(1) as usize);
+            cutoff = VersionNumber(u32::min(*cutoff, *versions[idx].version));
+        }
+        if let Some(keep_hours) = policy.keep_hours {
+            let since = now - chrono::Duration::hours(keep_hours as i64);
+            if let Some(oldest) = versions.iter().find(|v| v.created_at >= since) {
+                cutoff = VersionNumber(u32::min(*cutoff, *oldest.version));
+            }
+        }
+        Ok(Some(cutoff))
+    }
+
+    /// Deletes the metadata of the versions older than `version`, returns the
+    /// deleted versions
+    pub fn delete_versions_before(
+        &self,
+        version: VersionNumber,
+    ) -> lmdb::Result<Vec<VersionNumber>> {
+        let versions: Vec<_> = self
+            .get_versions()?
+            .into_iter()
+            .map(|v| v.version)
+            .filter(|v| **v < *version)
+            .collect();
+
+        with_rw_txn(&self.env, |txn| {
+            for version in &versions {
+                match txn.del(self.db, &key!(v:version), None) {
+                    Ok(()) | Err(lmdb::Error::NotFound) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        })?;
+
+        Ok(versions)
+    }
+
     pub fn get_versions_starting_from_exclusive(
         &self,
         from_version: VersionNumber,
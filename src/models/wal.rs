@@ -9,6 +9,7 @@ use std::{
     },
 };
 
+use chrono::{DateTime, Utc};
 use parking_lot::Mutex;
 
 use crate::{indexes::inverted::types::SparsePair, metadata::FieldValue};
@@ -16,6 +17,8 @@ use crate::{indexes::inverted::types::SparsePair, metadata::FieldValue};
 use super::{
     buffered_io::{BufIoError, FilelessBufferManager},
     collection::RawVectorEmbedding,
+    serializer::decode_expiry,
+    storage_backend,
     types::{DocumentId, VectorId},
     versioning::VersionNumber,
 };
@@ -35,26 +38,6 @@ pub struct WALFile {
     total_operations: AtomicU32,
 }
 
-/// Encode `len` into 1–3 bytes:
-/// - 1 byte if < 2⁷  
-/// - 2 bytes if < 2¹⁴  
-/// - 3 bytes otherwise (uses all 8 bits of the final byte, giving 22 bits total)
-pub fn write_len(buf: &mut Vec<u8>, len: u32) {
-    if len < (1 << 7) {
-        // [0vvvvvvv]
-        buf.push(len as u8);
-    } else if len < (1 << 14) {
-        // [1vvvvvvv] [0vvvvvvv]
-        buf.push(((len) as u8 & 0x7F) | 0x80);
-        buf.push((len >> 7) as u8);
-    } else {
-        // [1vvvvvvv] [1vvvvvvv] [vvvvvvvv]
-        buf.push(((len) as u8 & 0x7F) | 0x80);
-        buf.push(((len >> 7) as u8 & 0x7F) | 0x80);
-        buf.push((len >> 14) as u8);
-    }
-}
-
 /// Decode a 1–3 byte varint back to `u32`. Any bits beyond 22 are ignored.
 pub fn read_len(bufman: &FilelessBufferManager, cursor: u64) -> Result<u32, BufIoError> {
     let b0 = bufman.read_u8_with_cursor(cursor)? as u32;
@@ -81,6 +64,22 @@ pub fn read_string(bufman: &FilelessBufferManager, cursor: u64) -> Result<String
         .map_err(|err| BufIoError::Io(io::Error::new(io::ErrorKind::InvalidData, err)))
 }
 
+/// Reads the id and expiry of a vector, see `serializer::write_vector_id`
+pub fn read_vector_id(
+    bufman: &FilelessBufferManager,
+    cursor: u64,
+) -> Result<(VectorId, Option<DateTime<Utc>>), BufIoError> {
+    let id = read_string(bufman, cursor)?;
+    if !id.is_empty() {
+        return Ok((VectorId::from(id), None));
+    }
+    let expires_at = match bufman.read_u8_with_cursor(cursor)? {
+        0 => None,
+        _ => Some(decode_expiry(bufman.read_i64_with_cursor(cursor)?)?),
+    };
+    Ok((VectorId::from(read_string(bufman, cursor)?), expires_at))
+}
+
 pub fn read_opt_string(
     bufman: &FilelessBufferManager,
     cursor: u64,
@@ -97,27 +96,14 @@ pub fn read_opt_string(
 }
 
 impl WALFile {
-    pub fn new() -> Result<Self, BufIoError> {
-        let bufman = FilelessBufferManager::new(8192)?;
-        let cursor = bufman.open_cursor()?;
-        bufman.update_u32_with_cursor(cursor, 0)?;
-        bufman.update_u32_with_cursor(cursor, 0)?;
-        bufman.update_u32_with_cursor(cursor, 0)?;
-
-        Ok(Self {
-            bufman,
-            cursor,
-            read_lock: Mutex::new(()),
-            records_upserted: AtomicU32::new(0),
-            records_deleted: AtomicU32::new(0),
-            total_operations: AtomicU32::new(0),
-        })
-    }
-
     pub fn from_existing(root_path: &Path, version: VersionNumber) -> Result<Self, BufIoError> {
         let file_path: Arc<Path> = root_path.join(format!("{}.wal", *version)).into();
+        Self::open(&file_path)
+    }
 
-        let mut file = OpenOptions::new().read(true).open(&file_path)?;
+    pub fn open(file_path: &Path) -> Result<Self, BufIoError> {
+        storage_backend::backend().fetch(file_path)?;
+        let mut file = OpenOptions::new().read(true).open(file_path)?;
 
         let bufman = FilelessBufferManager::from_file(&mut file, 8192)?;
         let cursor = bufman.open_cursor()?;
@@ -151,108 +137,6 @@ impl WALFile {
         self.total_operations.load(Ordering::Relaxed)
     }
 
-    pub fn flush(self, root_path: &Path, version: VersionNumber) -> Result<(), BufIoError> {
-        let cursor = self.bufman.open_cursor()?;
-        self.bufman
-            .update_u32_with_cursor(cursor, self.records_upserted.load(Ordering::Acquire))?;
-        self.bufman
-            .update_u32_with_cursor(cursor, self.records_deleted.load(Ordering::Acquire))?;
-        self.bufman
-            .update_u32_with_cursor(cursor, self.total_operations.load(Ordering::Acquire))?;
-        self.bufman.close_cursor(cursor)?;
-        let file_path: Arc<Path> = root_path.join(format!("{}.wal", *version)).into();
-
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(&file_path)?;
-        self.bufman.flush(&mut file)
-    }
-
-    pub fn append(&self, op: VectorOp) -> Result<(), BufIoError> {
-        let mut buf = Vec::new();
-
-        buf.extend([u8::MAX; 4]);
-
-        match op {
-            VectorOp::Upsert(vectors) => {
-                self.records_upserted
-                    .fetch_add(vectors.len() as u32, Ordering::Relaxed);
-                write_len(&mut buf, vectors.len() as u32);
-                for vector in &*vectors {
-                    write_len(&mut buf, vector.id.len() as u32);
-                    buf.extend(vector.id.as_bytes());
-                    if let Some(document_id) = &vector.document_id {
-                        write_len(&mut buf, document_id.len() as u32);
-                        buf.extend(document_id.as_bytes());
-                    } else {
-                        write_len(&mut buf, 0);
-                    }
-                    if let Some(dense_values) = &vector.dense_values {
-                        write_len(&mut buf, dense_values.len() as u32);
-                        for val in dense_values {
-                            buf.extend(val.to_le_bytes());
-                        }
-                    } else {
-                        write_len(&mut buf, 0);
-                    }
-                    if let Some(metadata) = &vector.metadata {
-                        write_len(&mut buf, metadata.len() as u32);
-                        for (field, val) in metadata {
-                            write_len(&mut buf, field.len() as u32);
-                            buf.extend(field.as_bytes());
-
-                            match val {
-                                FieldValue::Int(int) => {
-                                    buf.push(0);
-                                    buf.extend(int.to_le_bytes());
-                                }
-                                FieldValue::String(str) => {
-                                    buf.push(1);
-                                    write_len(&mut buf, str.len() as u32);
-                                    buf.extend(str.as_bytes());
-                                }
-                            }
-                        }
-                    } else {
-                        write_len(&mut buf, 0);
-                    }
-
-                    if let Some(sparse_values) = &vector.sparse_values {
-                        write_len(&mut buf, sparse_values.len() as u32);
-                        for pair in sparse_values {
-                            buf.extend(pair.0.to_le_bytes());
-                            buf.extend(pair.1.to_le_bytes());
-                        }
-                    } else {
-                        write_len(&mut buf, 0);
-                    }
-
-                    if let Some(text) = &vector.text {
-                        write_len(&mut buf, text.len() as u32);
-                        buf.extend(text.as_bytes());
-                    } else {
-                        write_len(&mut buf, 0);
-                    }
-                }
-                let len = buf.len() as u32 - 4;
-                buf[0..4].copy_from_slice(&len.to_le_bytes());
-            }
-            VectorOp::Delete(id) => {
-                self.records_deleted.fetch_add(1, Ordering::Relaxed);
-                write_len(&mut buf, id.len() as u32);
-                buf.extend(id.as_bytes());
-                let len = buf.len() as u32 - 4;
-                buf[0..4].copy_from_slice(&(len | (1u32 << 31)).to_le_bytes());
-            }
-        }
-        self.total_operations.fetch_add(1, Ordering::Relaxed);
-
-        self.bufman.write_to_end_of_file(self.cursor, &buf)?;
-        Ok(())
-    }
-
     pub fn read(&self) -> Result<Option<VectorOp>, BufIoError> {
         let guard = self.read_lock.lock();
 
@@ -284,7 +168,7 @@ impl WALFile {
             let mut vectors = Vec::with_capacity(len);
 
             for _ in 0..len {
-                let id = VectorId::from(read_string(&self.bufman, cursor)?);
+                let (id, expires_at) = read_vector_id(&self.bufman, cursor)?;
                 let document_id = read_opt_string(&self.bufman, cursor)?.map(DocumentId::from);
                 let dense_values_len = read_len(&self.bufman, cursor)? as usize;
                 let dense_values = if dense_values_len == 0 {
@@ -348,6 +232,7 @@ impl WALFile {
                     metadata,
                     sparse_values,
                     text,
+                    expires_at,
                 };
                 vectors.push(vector);
             }
@@ -364,6 +249,7 @@ mod tests {
     use super::*;
     use crate::indexes::inverted::types::SparsePair;
     use crate::metadata::FieldValue;
+    use crate::models::durable_wal::DurableWALFile;
     use rand::{distributions::Alphanumeric, thread_rng, Rng};
     use std::collections::HashMap;
     use tempfile::tempdir;
@@ -404,6 +290,9 @@ mod tests {
                     .collect(),
             ),
             text: Some(random_string(16)),
+            expires_at: rng
+                .gen_bool(0.5)
+                .then(|| DateTime::from_timestamp_millis(rng.gen_range(0..1 << 42)).unwrap()),
         }
     }
 
@@ -417,11 +306,10 @@ mod tests {
         let version = 0;
 
         {
-            let wal = WALFile::new().unwrap();
+            let mut wal = DurableWALFile::new(dir.path(), VersionNumber::from(version)).unwrap();
             let vectors: Vec<_> = (0..3).map(|_| random_vector()).collect();
             wal.append(VectorOp::Upsert(vectors.clone())).unwrap();
-            wal.flush(dir.as_ref(), VersionNumber::from(version))
-                .unwrap();
+            wal.flush().unwrap();
         }
 
         {
@@ -443,10 +331,9 @@ mod tests {
         let id = VectorId::from(random_string(10));
 
         {
-            let wal = WALFile::new().unwrap();
+            let mut wal = DurableWALFile::new(dir.path(), VersionNumber::from(version)).unwrap();
             wal.append(VectorOp::Delete(id.clone())).unwrap();
-            wal.flush(dir.as_ref(), VersionNumber::from(version))
-                .unwrap();
+            wal.flush().unwrap();
         }
 
         {
@@ -461,21 +348,20 @@ mod tests {
     #[test]
     fn test_mixed_ops_persistence() {
         let dir = tempdir().unwrap();
-        let txn_id = 0;
+        let version = 0;
 
         let vecs: Vec<_> = (0..2).map(|_| random_vector()).collect();
         let del_id = VectorId::from(random_string(10));
 
         {
-            let wal = WALFile::new().unwrap();
+            let mut wal = DurableWALFile::new(dir.path(), VersionNumber::from(version)).unwrap();
             wal.append(VectorOp::Upsert(vecs.clone())).unwrap();
             wal.append(VectorOp::Delete(del_id.clone())).unwrap();
-            wal.flush(dir.as_ref(), VersionNumber::from(txn_id))
-                .unwrap();
+            wal.flush().unwrap();
         }
 
         {
-            let wal = reopen_wal(dir.path(), txn_id);
+            let wal = reopen_wal(dir.path(), version);
             match wal.read().unwrap() {
                 Some(VectorOp::Upsert(read_vecs)) => assert_eq!(read_vecs.len(), 2),
                 _ => panic!("Expected VectorOp::Upsert"),
@@ -503,12 +389,11 @@ mod tests {
             .collect();
 
         {
-            let wal = WALFile::new().unwrap();
+            let mut wal = DurableWALFile::new(dir.path(), VersionNumber::from(version)).unwrap();
             for op in &entries {
                 wal.append(op.clone()).unwrap();
             }
-            wal.flush(dir.as_ref(), VersionNumber::from(version))
-                .unwrap();
+            wal.flush().unwrap();
         }
 
         {
@@ -516,7 +401,10 @@ mod tests {
             for expected in &entries {
                 let read = wal.read().unwrap().expect("Expected some op");
                 match (expected, &read) {
-                    (VectorOp::Upsert(ev), VectorOp::Upsert(rv)) => assert_eq!(ev[0].id, rv[0].id),
+                    (VectorOp::Upsert(ev), VectorOp::Upsert(rv)) => {
+                        assert_eq!(ev[0].id, rv[0].id);
+                        assert_eq!(ev[0].expires_at, rv[0].expires_at);
+                    }
                     (VectorOp::Delete(eid), VectorOp::Delete(rid)) => assert_eq!(eid, rid),
                     _ => panic!("Mismatched operation types"),
                 }
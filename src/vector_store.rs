@@ -9,13 +9,18 @@ use crate::indexes::hnsw::types::QuantizedDenseVectorEmbedding;
 use crate::indexes::hnsw::types::RawDenseVectorEmbedding;
 use crate::indexes::hnsw::DenseInputEmbedding;
 use crate::indexes::hnsw::HNSWIndex;
+use crate::indexes::IndexDir;
 use crate::indexes::InternalSearchResult;
 use crate::metadata;
 use crate::metadata::fields_to_dimensions;
 use crate::metadata::pseudo_level_probs;
+use crate::metadata::pseudo_node_vector;
+use crate::metadata::pseudo_root_id;
 use crate::metadata::MetadataFields;
 use crate::metadata::MetadataSchema;
+use crate::metadata::SharedMetadataSchema;
 use crate::metadata::HIGH_WEIGHT;
+use crate::models::buffered_io::{BufferManagerFactory, FilelessBufferManager};
 use crate::models::cache_loader::HNSWIndexCache;
 use crate::models::collection::Collection;
 use crate::models::collection::RawVectorEmbedding;
@@ -27,6 +32,7 @@ use crate::models::lazy_item::LazyItem;
 use crate::models::prob_node::LatestNode;
 use crate::models::prob_node::ProbNode;
 use crate::models::prob_node::SharedLatestNode;
+use crate::models::tracing;
 use crate::models::types::*;
 use crate::models::versioning::VersionNumber;
 use crate::quantization::{Quantization, StorageType};
@@ -34,6 +40,8 @@ use crate::storage::Storage;
 use rand::Rng;
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
+use std::collections::HashSet;
+use std::fs;
 use std::fs::File;
 use std::ptr;
 use std::sync::atomic::Ordering;
@@ -253,6 +261,159 @@ pub fn create_pseudo_root_node(
     Ok(root_ptr)
 }
 
+/// Creates an empty dense index in `dir`, with its root nodes, without
+/// adding it to the collection
+#[allow(clippy::too_many_arguments)]
+pub fn create_hnsw_index(
+    config: &Config,
+    collection: &Collection,
+    metadata_schema: SharedMetadataSchema,
+    id_stride: u32,
+    dir: IndexDir,
+    values_range: (f32, f32),
+    hnsw_params: HNSWHyperParams,
+    quantization_metric: QuantizationMetric,
+    distance_metric: DistanceMetric,
+    storage_type: StorageType,
+    sample_threshold: usize,
+    is_configured: bool,
+) -> Result<HNSWIndex, WaCustomError> {
+    let schema = metadata_schema.get();
+    let index_path = dir.path().to_path_buf();
+    // ensuring that the index has a separate directory created inside the collection directory
+    fs::create_dir_all(&index_path).map_err(|e| WaCustomError::FsError(e.to_string()))?;
+
+    // Note that setting .write(true).append(true) has the same effect
+    // as setting only .append(true)
+    //
+    // what is the prop file exactly?
+    // a file that stores the quantized version of raw vec
+    let prop_file = RwLock::new(
+        fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(index_path.join("prop.data"))
+            .map_err(|e| WaCustomError::FsError(e.to_string()))?,
+    );
+
+    let index_manager = BufferManagerFactory::new(
+        index_path.clone().into(),
+        |root, ver: &IndexFileId| root.join(format!("{}.index", **ver)),
+        ProbNode::get_serialized_size(hnsw_params.neighbors_count) * 1000,
+    );
+    let latest_version_links_bufman = FilelessBufferManager::new(8192)?;
+
+    let distance_metric = Arc::new(RwLock::new(distance_metric));
+
+    let cache = HNSWIndexCache::new(
+        index_manager,
+        latest_version_links_bufman,
+        index_path.clone(),
+        config.enable_context_history,
+        prop_file,
+        distance_metric.clone(),
+    );
+    let offset_counter = HNSWIndexFileOffsetCounter::new(
+        config.index_file_min_size,
+        hnsw_params.level_0_neighbors_count,
+        hnsw_params.neighbors_count,
+    );
+
+    let root = create_root_node(
+        &quantization_metric,
+        storage_type,
+        collection.meta.dense_vector.dimension,
+        &cache.prop_file,
+        *collection.current_version.read(),
+        &offset_counter,
+        &cache,
+        values_range,
+        &hnsw_params,
+        *distance_metric.read().unwrap(),
+        schema.as_deref(),
+    )?;
+
+    cache.flush_all(VersionNumber::from(0))?;
+    // ---------------------------
+    // -- TODO level entry ratio
+    // ---------------------------
+    let factor_levels = 4.0;
+
+    // If metadata schema is supported, the level_probs needs to be
+    // adjusted to accommodate only pseudo nodes in the higher layers
+    let lp = match schema.as_deref() {
+        Some(metadata_schema) => {
+            // @TODO(vineet): Unnecessary computation of
+            // pseudo_weighted_dimensions. Just the no. of pseudo
+            // replicas should be sufficient.
+            let replica_dims = metadata_schema.pseudo_weighted_dimensions(1);
+            let plp = pseudo_level_probs(hnsw_params.num_layers, replica_dims.len() as u16);
+            // @TODO(vineet): Super hacky
+            let num_lower_layers = plp.iter().filter(|(p, _)| *p == 0.0).count() - 1;
+            let num_higher_layers = hnsw_params.num_layers - (num_lower_layers as u8);
+            let mut lp = vec![];
+            for i in 0..num_higher_layers {
+                // no actual replica nodes in higher layers
+                lp.push((1.0, hnsw_params.num_layers - i))
+            }
+            let mut lower_lp = generate_level_probs(factor_levels, num_lower_layers as u8);
+            lp.append(&mut lower_lp);
+            lp
+        }
+        None => generate_level_probs(factor_levels, hnsw_params.num_layers),
+    };
+
+    // If the collection has metadata_schema defined, we create pseudo
+    // nodes. But first, we create the pseudo root node separately as
+    // it's an independent root node much like the main root
+    // node. Once pseudo root is created, it can be passed when
+    // instantiating the HNSWIndex. And the rest of the non-root
+    // pseudo nodes can be created through the index's methods
+    let pseudo_root = match schema.as_deref() {
+        Some(metadata_schema) => {
+            let num_dims = collection.meta.dense_vector.dimension;
+            let pseudo_vals = pseudo_node_vector(num_dims);
+            let pseudo_root_id = pseudo_root_id();
+            let node = create_pseudo_root_node(
+                &quantization_metric,
+                storage_type,
+                &cache.prop_file,
+                *collection.current_version.read(),
+                &offset_counter,
+                &cache,
+                values_range,
+                &hnsw_params,
+                *distance_metric.read().unwrap(),
+                metadata_schema,
+                pseudo_vals.clone(),
+                pseudo_root_id,
+            )?;
+            Some(node)
+        }
+        None => None,
+    };
+
+    Ok(HNSWIndex::new(
+        root,
+        pseudo_root,
+        lp,
+        collection.meta.dense_vector.dimension,
+        quantization_metric,
+        distance_metric,
+        storage_type,
+        hnsw_params,
+        cache,
+        values_range,
+        sample_threshold,
+        is_configured,
+        metadata_schema,
+        id_stride,
+        offset_counter,
+        dir,
+    ))
+}
+
 pub fn ann_search(
     config: &Config,
     hnsw_index: &HNSWIndex,
@@ -262,6 +423,7 @@ pub fn ann_search(
     cur_level: HNSWLevel,
     hnsw_params: &HNSWHyperParams,
 ) -> Result<Vec<(SharedLatestNode, MetricResult)>, WaCustomError> {
+    let span = tracing::span("ann_search", vec![("level", (cur_level.0 as u32).into())]);
     let fvec = vector_emb.quantized_vec.clone();
     let mut skipm = PerformantFixedSet::new(if cur_level.0 == 0 {
         hnsw_params.level_0_neighbors_count
@@ -274,7 +436,8 @@ pub fn ann_search(
         Some(qf_dims) => {
             let mut z_candidates: Vec<(SharedLatestNode, MetricResult)> = vec![];
             // @TODO: Can we compute the z_candidates in parallel?
-            for qfd in qf_dims {
+            for (replica, qfd) in qf_dims.iter().enumerate() {
+                let _span = tracing::span("traverse_replica", vec![("replica", replica.into())]);
                 let mdims = Metadata::from(qfd);
                 let z_with_mdims = traverse_find_nearest(
                     config,
@@ -383,6 +546,7 @@ pub fn ann_search(
     let top_lazy_item = unsafe { &*top_lazy_item_latest_ptr }.latest;
     let top_node = unsafe { &*top_lazy_item }.try_get_data(&hnsw_index.cache)?;
     let child = top_node.get_child();
+    drop(span);
 
     if cur_level.0 != 0 {
         let results = ann_search(
@@ -413,7 +577,13 @@ pub fn finalize_ann_results(
     let mut results = Vec::with_capacity(top_k.unwrap_or(filtered.len()));
     let mag_query = query.iter().map(|x| x * x).sum::<f32>().sqrt();
 
-    for (internal_id, _) in filtered {
+    let mut seen = HashSet::new();
+    for (node_id, _) in filtered {
+        // the replicas of a vector are found as separate nodes
+        let internal_id = hnsw_index.vector_internal_id(node_id);
+        if !seen.insert(internal_id) {
+            continue;
+        }
         let raw_emb = collection
             .get_raw_emb_by_internal_id(&internal_id)
             .ok_or_else(|| {
@@ -627,7 +797,6 @@ fn pseudo_metadata_replicas(
 /// input raw embedding may result in multiple `IndexableEmbedding`
 /// instances.
 fn preprocess_embedding(
-    collection: &Collection,
     hnsw_index: &HNSWIndex,
     quantization_metric: &RwLock<QuantizationMetric>,
     raw_emb: &RawDenseVectorEmbedding,
@@ -641,11 +810,12 @@ fn preprocess_embedding(
 
     let base_id = raw_emb.hash_vec;
 
-    let metadata_schema = collection.meta.metadata_schema.as_ref();
+    let metadata_schema = hnsw_index.metadata_schema.get();
     let prop_file = &hnsw_index.cache.prop_file;
 
     let embeddings = if raw_emb.is_pseudo {
-        let replicas = pseudo_metadata_replicas(metadata_schema.unwrap(), prop_file, &base_id)?;
+        let replicas =
+            pseudo_metadata_replicas(metadata_schema.as_deref().unwrap(), prop_file, &base_id)?;
         let num_levels = hnsw_index.levels_prob.len() - 1;
         let plp = pseudo_level_probs(num_levels as u8, replicas.len() as u16);
 
@@ -679,7 +849,7 @@ fn preprocess_embedding(
         });
 
         let metadata_replicas = prop_metadata_replicas(
-            collection.meta.metadata_schema.as_ref(),
+            metadata_schema.as_deref(),
             raw_emb.raw_metadata.as_ref(),
             &hnsw_index.cache.prop_file,
             &base_id,
@@ -713,7 +883,6 @@ fn preprocess_embedding(
 
 pub fn index_embeddings(
     config: &Config,
-    collection: &Collection,
     hnsw_index: &HNSWIndex,
     version: VersionNumber,
     vecs: Vec<DenseInputEmbedding>,
@@ -724,20 +893,17 @@ pub fn index_embeddings(
         .map(|vec| {
             let DenseInputEmbedding(id, values, metadata, is_pseudo) = vec;
             RawDenseVectorEmbedding {
-                hash_vec: id,
+                hash_vec: if is_pseudo {
+                    id
+                } else {
+                    hnsw_index.base_node_id(id)
+                },
                 raw_vec: Arc::new(values),
                 raw_metadata: metadata,
                 is_pseudo,
             }
         })
-        .map(|emb| {
-            preprocess_embedding(
-                collection,
-                hnsw_index,
-                &hnsw_index.quantization_metric,
-                &emb,
-            )
-        })
+        .map(|emb| preprocess_embedding(hnsw_index, &hnsw_index.quantization_metric, &emb))
         .collect::<Result<Vec<Vec<IndexableEmbedding>>, WaCustomError>>()?
         .into_iter()
         .flatten()
@@ -1124,6 +1290,7 @@ fn traverse_find_nearest(
 ) -> Result<Vec<(SharedLatestNode, MetricResult)>, WaCustomError> {
     let mut candidate_queue = BinaryHeap::new();
     let mut results = Vec::new();
+    let nodes_visited_at_start = *nodes_visited;
 
     let start_lazy_item = unsafe { &*start_lazy_item_latest_ptr }.latest;
     let start_node = unsafe { &*start_lazy_item }.try_get_data(&hnsw_index.cache)?;
@@ -1191,6 +1358,8 @@ fn traverse_find_nearest(
         }
     }
 
+    tracing::record_nodes_visited(*nodes_visited - nodes_visited_at_start);
+
     let final_len = if is_indexing { 64 } else { 100 };
 
     if results.len() > final_len {
@@ -1213,6 +1382,7 @@ pub fn delete_embedding(
     let Some(raw_vec) = &raw_emb.dense_values else {
         return Ok(());
     };
+    let id = hnsw_index.base_node_id(id);
 
     let quantized_vec = hnsw_index.quantization_metric.read().unwrap().quantize(
         raw_vec,
@@ -1,14 +1,25 @@
 use crate::api::auth::{auth_module, authentication_middleware::AuthenticationMiddleware};
 use crate::api::docs::api_docs_module;
+use crate::api::monitoring::{monitoring_module, readiness_middleware::ReadinessMiddleware};
+use crate::api::vectordb::admin::admin_module;
 use crate::api::vectordb::collections::collections_module;
+use crate::api::vectordb::imports::imports_module;
 use crate::api::vectordb::indexes::indexes_module;
+use crate::api::vectordb::metadata_schema::metadata_schema_module;
+use crate::api::vectordb::replication::{
+    read_only_middleware::ReadOnlyReplicaMiddleware, replication_module,
+};
 use crate::api::vectordb::search::search_module;
+use crate::api::vectordb::snapshots::snapshots_module;
 use crate::api::vectordb::streaming::streaming_module;
+use crate::api::vectordb::tenants::{
+    tenant_scope_middleware::TenantScopeMiddleware, tenants_module,
+};
 use crate::api::vectordb::transactions::transactions_module;
 use crate::api::vectordb::vectors::vectors_module;
 use crate::api::vectordb::versions::version_module;
 use crate::app_context::AppContext;
-use crate::config_loader::{ServerMode, Ssl};
+use crate::config_loader::{ReplicationRole, ServerMode, Ssl};
 use actix_cors::Cors;
 use actix_web::web::Data;
 use actix_web::{middleware, web, App, HttpServer};
@@ -42,6 +53,7 @@ pub async fn run_actix_server_with_context(ctx: Data<AppContext>) -> std::io::Re
         &config.server.port,
     );
 
+    let read_only = config.replication.role == ReplicationRole::Follower;
     let server = HttpServer::new(move || {
         App::new()
             // enable logger
@@ -53,12 +65,16 @@ pub async fn run_actix_server_with_context(ctx: Data<AppContext>) -> std::io::Re
             .app_data(web::JsonConfig::default().limit(8_388_608)) // 8 MB)
             .app_data(ctx.clone())
             .service(api_docs_module())
-            .service(auth_module())
+            .configure(monitoring_module)
+            .service(auth_module(ctx.ain_env.clone()))
             .service(
                 web::scope("/vectordb")
-                    .wrap(AuthenticationMiddleware(
-                        ctx.ain_env.active_sessions.clone(),
-                    ))
+                    // wrapped first so that they run after authentication
+                    .wrap(ReadOnlyReplicaMiddleware(read_only))
+                    .wrap(TenantScopeMiddleware(ctx.ain_env.clone()))
+                    .wrap(AuthenticationMiddleware(ctx.ain_env.clone()))
+                    // runs first, nothing is served until collections are recovered
+                    .wrap(ReadinessMiddleware)
                     // vectors module must be registered before collections module
                     // as its scope path is more specific than collections module
                     .service(search_module())
@@ -67,6 +83,12 @@ pub async fn run_actix_server_with_context(ctx: Data<AppContext>) -> std::io::Re
                     .service(transactions_module())
                     .service(streaming_module())
                     .service(version_module())
+                    .service(snapshots_module())
+                    .service(imports_module())
+                    .service(metadata_schema_module())
+                    .service(admin_module())
+                    .service(replication_module())
+                    .service(tenants_module())
                     .service(collections_module()),
             )
     })
@@ -0,0 +1,201 @@
+mod common;
+
+use common::{login, request, start_server, wait_until_ready, Server, ADMIN_KEY};
+use lmdb::{Database, Environment, RwTransaction, Transaction, WriteFlags};
+use serde_json::{json, Value};
+
+const NEW_ADMIN_KEY: &str = "rotated-admin-key";
+
+fn create_session(server: &Server, password: &str) -> (u16, Value) {
+    request(
+        server.port,
+        "POST",
+        "/auth/create-session",
+        None,
+        Some(json!({ "username": "admin", "password": password })),
+    )
+}
+
+// Logs in with `password` once the server is ready
+fn login_with(server: &Server, password: &str) -> String {
+    wait_until_ready(server);
+    let (status, session) = create_session(server, password);
+    assert_eq!(status, 200, "{}", session);
+    session["access_token"].as_str().unwrap().to_string()
+}
+
+fn rotate_admin_key(server: &Server, token: &str, old_key: &str, new_key: &str) -> u16 {
+    let (status, _) = request(
+        server.port,
+        "POST",
+        "/vectordb/admin/rotate-key",
+        Some(token),
+        Some(json!({ "old_key": old_key, "new_key": new_key })),
+    );
+    status
+}
+
+fn storage_usage_status(server: &Server, token: &str) -> u16 {
+    request(
+        server.port,
+        "GET",
+        "/vectordb/admin/storage",
+        Some(token),
+        None,
+    )
+    .0
+}
+
+fn is_authenticated(server: &Server, token: &str) -> bool {
+    let (status, response) = request(server.port, "GET", "/auth/api-keys", Some(token), None);
+    assert!(status == 200 || status == 401, "{} {}", status, response);
+    status == 200
+}
+
+fn refresh(server: &Server, token: &str) -> String {
+    let (status, session) = request(
+        server.port,
+        "POST",
+        "/auth/refresh-session",
+        Some(token),
+        None,
+    );
+    assert_eq!(status, 200, "{}", session);
+    session["access_token"].as_str().unwrap().to_string()
+}
+
+fn logout(server: &Server, token: &str) -> u16 {
+    let (status, _) = request(server.port, "POST", "/auth/logout", Some(token), None);
+    status
+}
+
+#[test]
+fn test_sessions_and_api_keys_survive_restart() {
+    let mut server = start_server();
+    // sessions created within the same second share their token, refreshed
+    // ones get random tokens
+    let logged_out = refresh(&server, &login(&server));
+    let refreshed = refresh(&server, &login(&server));
+    let token = login(&server);
+    let (status, api_key) = request(
+        server.port,
+        "POST",
+        "/auth/api-keys",
+        Some(&token),
+        Some(json!({ "name": "service" })),
+    );
+    assert_eq!(status, 201, "{}", api_key);
+    let api_key = api_key["key"].as_str().unwrap().to_string();
+
+    let new_token = refresh(&server, &refreshed);
+    assert_eq!(logout(&server, &logged_out), 204);
+    // API keys aren't sessions
+    assert_eq!(logout(&server, &api_key), 400);
+
+    for restarted in [false, true] {
+        if restarted {
+            server.restart();
+            wait_until_ready(&server);
+        }
+        assert!(is_authenticated(&server, &token));
+        assert!(is_authenticated(&server, &new_token));
+        assert!(is_authenticated(&server, &api_key));
+        assert!(!is_authenticated(&server, &refreshed));
+        assert!(!is_authenticated(&server, &logged_out));
+    }
+
+    // sessions loaded from disk can be refreshed and ended
+    let refreshed_again = refresh(&server, &new_token);
+    assert!(!is_authenticated(&server, &new_token));
+    assert_eq!(logout(&server, &token), 204);
+    assert!(!is_authenticated(&server, &token));
+    server.restart();
+    wait_until_ready(&server);
+    assert!(is_authenticated(&server, &refreshed_again));
+    assert!(!is_authenticated(&server, &new_token));
+    assert!(!is_authenticated(&server, &token));
+
+    // corrupt session and API key records are skipped and deleted
+    server.stop();
+    let corrupt_keys = [
+        b"session:short".to_vec(),
+        [b"session:".as_slice(), &[7; 32]].concat(),
+        b"api_key:short".to_vec(),
+        [b"api_key:".as_slice(), &[0xff; 8]].concat(),
+    ];
+    with_auth_db(&server, |txn, db| {
+        for key in &corrupt_keys {
+            txn.put(db, key, b"corrupt", WriteFlags::empty()).unwrap();
+        }
+    });
+    server.restart();
+    wait_until_ready(&server);
+    assert!(is_authenticated(&server, &refreshed_again));
+    assert!(is_authenticated(&server, &api_key));
+    server.stop();
+    with_auth_db(&server, |txn, db| {
+        for key in &corrupt_keys {
+            assert_eq!(txn.get(db, key), Err(lmdb::Error::NotFound));
+        }
+    });
+}
+
+// Opens the database the server keeps its sessions in, the server must be
+// stopped
+fn with_auth_db(server: &Server, f: impl FnOnce(&mut RwTransaction<'_>, Database)) {
+    let path = ["_mdb", "data/_mdb"]
+        .iter()
+        .map(|path| server.data_path().join(path))
+        .find(|path| path.exists())
+        .unwrap();
+    let env = Environment::new().set_max_dbs(16).open(&path).unwrap();
+    let db = env.open_db(Some("auth")).unwrap();
+    let mut txn = env.begin_rw_txn().unwrap();
+    f(&mut txn, db);
+    txn.commit().unwrap();
+}
+
+#[test]
+fn test_rotate_admin_key() {
+    let mut server = start_server();
+    let token = login(&server);
+    let other_token = login(&server);
+    let (status, api_key) = request(
+        server.port,
+        "POST",
+        "/auth/api-keys",
+        Some(&token),
+        Some(json!({ "name": "admin" })),
+    );
+    assert_eq!(status, 201, "{}", api_key);
+    let api_key = api_key["key"].as_str().unwrap();
+
+    assert_eq!(
+        rotate_admin_key(&server, &token, "wrong-admin-key", NEW_ADMIN_KEY),
+        401
+    );
+    assert_eq!(rotate_admin_key(&server, &token, ADMIN_KEY, ""), 400);
+    // a rejected rotation leaves the sessions alone
+    assert_eq!(storage_usage_status(&server, &other_token), 200);
+
+    assert_eq!(
+        rotate_admin_key(&server, &token, ADMIN_KEY, NEW_ADMIN_KEY),
+        204
+    );
+    // the sessions and the API keys of the admin are no longer accepted
+    for old_token in [token.as_str(), other_token.as_str(), api_key] {
+        assert_eq!(storage_usage_status(&server, old_token), 401);
+    }
+    // the old key is rejected as the admin's password
+    assert_eq!(create_session(&server, ADMIN_KEY).0, 400);
+    let token = login_with(&server, NEW_ADMIN_KEY);
+    let (status, api_keys) = request(server.port, "GET", "/auth/api-keys", Some(&token), None);
+    assert_eq!(status, 200, "{}", api_keys);
+    assert_eq!(api_keys, json!([]));
+
+    // the new key is the one the server has to be started with
+    server.restart_with_admin_key(NEW_ADMIN_KEY);
+    let token = login_with(&server, NEW_ADMIN_KEY);
+    assert_eq!(storage_usage_status(&server, &token), 200);
+    assert_eq!(create_session(&server, ADMIN_KEY).0, 400);
+}
@@ -0,0 +1,108 @@
+// Compares decoding upsert bodies sent as JSON, which is what dominates the
+// CPU time of JSON upserts, with decoding them sent as CBOR with the dense
+// values as byte strings of little-endian f32s.
+
+use cosdata::models::f32_values::F32Values;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::Rng;
+use serde::Deserialize;
+use serde_cbor::Value;
+use serde_json::json;
+
+const VECTORS: usize = 1000;
+
+// the fields of `CreateVectorDto` whose decoding differs between formats
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Vector {
+    id: String,
+    dense_values: F32Values,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct UpsertBody {
+    vectors: Vec<Vector>,
+}
+
+fn random_vectors(dimension: usize) -> Vec<Vec<f32>> {
+    let mut rng = rand::thread_rng();
+    (0..VECTORS)
+        .map(|_| (0..dimension).map(|_| rng.gen_range(-1.0..1.0)).collect())
+        .collect()
+}
+
+fn json_body(vectors: &[Vec<f32>]) -> Vec<u8> {
+    let vectors: Vec<_> = vectors
+        .iter()
+        .enumerate()
+        .map(|(id, values)| json!({ "id": id.to_string(), "dense_values": values }))
+        .collect();
+    serde_json::to_vec(&json!({ "vectors": vectors })).unwrap()
+}
+
+fn cbor_body(vectors: &[Vec<f32>], raw_values: bool) -> Vec<u8> {
+    let vectors = vectors
+        .iter()
+        .enumerate()
+        .map(|(id, values)| {
+            let values = if raw_values {
+                Value::Bytes(
+                    values
+                        .iter()
+                        .flat_map(|value| value.to_le_bytes())
+                        .collect(),
+                )
+            } else {
+                Value::Array(
+                    values
+                        .iter()
+                        .map(|value| Value::Float(*value as f64))
+                        .collect(),
+                )
+            };
+            Value::Map(
+                [
+                    (Value::Text("id".into()), Value::Text(id.to_string())),
+                    (Value::Text("dense_values".into()), values),
+                ]
+                .into(),
+            )
+        })
+        .collect();
+    serde_cbor::to_vec(&Value::Map(
+        [(Value::Text("vectors".into()), Value::Array(vectors))].into(),
+    ))
+    .unwrap()
+}
+
+fn bench_upsert_body(c: &mut Criterion) {
+    let mut group = c.benchmark_group("upsert_body");
+    group.throughput(Throughput::Elements(VECTORS as u64));
+
+    for dimension in [128, 768] {
+        let vectors = random_vectors(dimension);
+        let json = json_body(&vectors);
+        let cbor_floats = cbor_body(&vectors, false);
+        let cbor_raw = cbor_body(&vectors, true);
+
+        group.bench_with_input(BenchmarkId::new("json", dimension), &json, |b, body| {
+            b.iter(|| serde_json::from_slice::<UpsertBody>(black_box(body)).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("cbor_floats", dimension),
+            &cbor_floats,
+            |b, body| b.iter(|| serde_cbor::from_slice::<UpsertBody>(black_box(body)).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("cbor_raw_f32", dimension),
+            &cbor_raw,
+            |b, body| b.iter(|| serde_cbor::from_slice::<UpsertBody>(black_box(body)).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_upsert_body);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+mod common;
+
+use common::{
+    create_collection, get_vector, login, open_transaction, request, start_server,
+    streaming_upsert, vector, wait_for, Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "bulk_deleted";
+
+fn vector_exists(server: &Server, token: &str, id: usize) -> bool {
+    get_vector(server, token, COLLECTION, &format!("v{}", id)).0 == 200
+}
+
+fn streaming_delete(server: &Server, token: &str, body: Value) -> (u16, Value) {
+    request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/streaming/delete", COLLECTION),
+        Some(token),
+        Some(body),
+    )
+}
+
+#[test]
+fn test_bulk_delete() {
+    let server = start_server();
+    let token = login(&server);
+    create_collection(
+        &server,
+        &token,
+        COLLECTION,
+        json!({
+            "metadata_schema": {
+                "fields": [{ "name": "color", "values": ["red", "blue"] }],
+                "supported_conditions": []
+            }
+        }),
+    );
+
+    // v0 to v3 are the chunks of a document, even vectors are red
+    let vectors: Vec<_> = (0..12)
+        .map(|id| {
+            let color = if id % 2 == 0 { "red" } else { "blue" };
+            let document_id = (id < 4).then_some("doc");
+            json!({
+                "id": format!("v{}", id),
+                "dense_values": vector(id),
+                "metadata": { "color": color },
+                "document_id": document_id
+            })
+        })
+        .collect();
+    let status = streaming_upsert(&server, &token, COLLECTION, json!(vectors));
+    assert_eq!(status, 200);
+
+    let (status, response) = streaming_delete(&server, &token, json!({ "document_id": "doc" }));
+    assert_eq!(status, 200, "{}", response);
+    assert_eq!(response["count"], 4);
+    assert!((0..4).all(|id| !vector_exists(&server, &token, id)));
+    assert!(vector_exists(&server, &token, 4));
+
+    let filter = json!({
+        "Is": { "field_name": "color", "field_value": "red", "operator": "Equal" }
+    });
+    let (status, response) = streaming_delete(&server, &token, json!({ "filter": filter }));
+    assert_eq!(status, 200, "{}", response);
+    assert_eq!(response["count"], 4);
+    assert!((4..12).all(|id| vector_exists(&server, &token, id) == (id % 2 == 1)));
+
+    // within an explicit transaction, the deletes apply on commit
+    let path = open_transaction(&server, &token, COLLECTION);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/delete", path),
+        Some(&token),
+        Some(json!({ "ids": ["v5", "v7"] })),
+    );
+    assert_eq!(status, 200, "{}", response);
+    assert_eq!(response["count"], 2);
+    assert!(vector_exists(&server, &token, 5));
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/commit", path),
+        Some(&token),
+        None,
+    );
+    assert_eq!(status, 204, "{}", response);
+    wait_for("v5 and v7 to be deleted", || {
+        (!vector_exists(&server, &token, 5) && !vector_exists(&server, &token, 7)).then_some(())
+    });
+    assert!(vector_exists(&server, &token, 9));
+
+    // the vectors are selected by exactly one of the fields
+    let (status, _) = streaming_delete(
+        &server,
+        &token,
+        json!({ "ids": ["v9"], "document_id": "doc" }),
+    );
+    assert_eq!(status, 400);
+    let (status, _) = streaming_delete(&server, &token, json!({}));
+    assert_eq!(status, 400);
+    assert!(vector_exists(&server, &token, 9));
+}
@@ -0,0 +1,412 @@
+// Helpers to run the server as a separate process on localhost and send it
+// requests, shared by the integration tests.
+
+// each test uses only some of the helpers
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+pub const ADMIN_KEY: &str = "integration-test-admin-key";
+pub const DIMENSION: usize = 8;
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+pub struct Server {
+    process: Child,
+    pub port: u16,
+    // removed once the process is killed
+    home: TempDir,
+}
+
+impl Server {
+    /// Kills the server, its data directory is kept until it's dropped
+    pub fn stop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+
+    /// Kills the server and starts it again with the same data directory
+    pub fn restart(&mut self) {
+        self.restart_with_admin_key(ADMIN_KEY);
+    }
+
+    /// Kills the server and starts it again with the same data directory,
+    /// for the admin key `admin_key`
+    pub fn restart_with_admin_key(&mut self, admin_key: &str) {
+        self.stop();
+        self.process = spawn_process(self.home.path(), admin_key);
+    }
+
+    /// Directory the server keeps its collections and metadata in
+    pub fn data_path(&self) -> PathBuf {
+        self.home.path().join("data")
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Starts a server with the default config
+pub fn start_server() -> Server {
+    start_server_with_overrides(&[])
+}
+
+/// Starts a server with each of `overrides` replacing a line, or the
+/// header of a section, of the default config
+pub fn start_server_with_overrides(overrides: &[(&str, &str)]) -> Server {
+    let home = TempDir::new().unwrap();
+    let port = free_port();
+    let base_config =
+        fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("config.toml")).unwrap();
+    let config = overrides
+        .iter()
+        .fold(base_config, |config, (line, replacement)| {
+            assert!(config.contains(line), "no `{}` in the config", line);
+            config.replace(line, replacement)
+        })
+        .replace("port = 8443", &format!("port = {}", port))
+        .replace("pool_size = 64", "pool_size = 4");
+    fs::create_dir_all(home.path().join("config")).unwrap();
+    fs::write(home.path().join("config/config.toml"), config).unwrap();
+
+    Server {
+        process: spawn_process(home.path(), ADMIN_KEY),
+        port,
+        home,
+    }
+}
+
+fn spawn_process(home: &Path, admin_key: &str) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_cosdata"))
+        .args(["--admin-key", admin_key, "--skip-confirmation"])
+        .env("COSDATA_HOME", home)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+/// Sends a request and returns the status and body of the response
+pub fn request_text(
+    port: u16,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (u16, String) {
+    let (status, _, body) = request_with_headers(port, method, path, token, &[], body);
+    (status, body)
+}
+
+/// Sends a request with extra headers and returns the status, head and body
+/// of the response
+pub fn request_with_headers(
+    port: u16,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> (u16, String, String) {
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let mut all_headers = vec![("content-type", "application/json")];
+    all_headers.extend_from_slice(headers);
+    send(port, method, path, token, &all_headers, body.as_bytes())
+}
+
+/// Sends a request with a body of the given content type and returns the
+/// status and body of the response
+pub fn request_bytes(
+    port: u16,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    content_type: &str,
+    body: &[u8],
+) -> (u16, String) {
+    let (status, _, body) = send(
+        port,
+        method,
+        path,
+        token,
+        &[("content-type", content_type)],
+        body,
+    );
+    (status, body)
+}
+
+/// Sends a GET request and returns the status and raw body of the response
+pub fn download(port: u16, path: &str, token: Option<&str>) -> (u16, Vec<u8>) {
+    let (status, _, body) = send_raw(port, "GET", path, token, &[], b"");
+    (status, body)
+}
+
+fn send(
+    port: u16,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> (u16, String, String) {
+    let (status, head, body) = send_raw(port, method, path, token, headers, body);
+    (status, head, String::from_utf8_lossy(&body).into_owned())
+}
+
+fn send_raw(
+    port: u16,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> (u16, String, Vec<u8>) {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(60)))
+        .unwrap();
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nhost: 127.0.0.1:{}\r\ncontent-length: {}\r\nconnection: close\r\n",
+        method,
+        path,
+        port,
+        body.len()
+    );
+    if let Some(token) = token {
+        request.push_str(&format!("authorization: Bearer {}\r\n", token));
+    }
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    // the server may respond before reading the whole body
+    let _ = stream.write_all(body);
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let head_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .unwrap();
+    let head = String::from_utf8_lossy(&response[..head_end]).into_owned();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, head, response[head_end + 4..].to_vec())
+}
+
+/// Sends a request and returns the status and JSON body of the response
+pub fn request(
+    port: u16,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (u16, Value) {
+    let (status, body) = request_text(port, method, path, token, body);
+    (status, serde_json::from_str(&body).unwrap_or(Value::Null))
+}
+
+pub fn wait_for<T>(what: &str, mut f: impl FnMut() -> Option<T>) -> T {
+    let start = Instant::now();
+    loop {
+        if let Some(value) = f() {
+            return value;
+        }
+        assert!(start.elapsed() < TIMEOUT, "Timed out waiting for {}", what);
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+pub fn wait_until_ready(server: &Server) {
+    wait_for("the server to be ready", || {
+        TcpStream::connect(("127.0.0.1", server.port)).ok()?;
+        let (status, _) = request(server.port, "GET", "/ready", None, None);
+        (status == 200).then_some(())
+    })
+}
+
+pub fn login(server: &Server) -> String {
+    wait_until_ready(server);
+    wait_for("a session", || {
+        let (status, session) = request(
+            server.port,
+            "POST",
+            "/auth/create-session",
+            None,
+            Some(json!({ "username": "admin", "password": ADMIN_KEY })),
+        );
+        (status == 200).then(|| session["access_token"].as_str().unwrap().to_string())
+    })
+}
+
+pub fn vector(seed: usize) -> Vec<f32> {
+    (0..DIMENSION)
+        .map(|i| ((seed * DIMENSION + i) as f32).sin())
+        .collect()
+}
+
+pub fn upsert(server: &Server, token: &str, collection: &str, ids: &[usize]) {
+    let path = open_transaction(server, token, collection);
+    let vectors: Vec<_> = ids
+        .iter()
+        .map(|id| json!({ "id": format!("v{}", id), "dense_values": vector(*id) }))
+        .collect();
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/upsert", path),
+        Some(token),
+        Some(json!({ "vectors": vectors })),
+    );
+    assert!(status < 300, "{} {}", status, response);
+
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/commit", path),
+        Some(token),
+        None,
+    );
+    assert!(status < 300, "{} {}", status, response);
+}
+
+// Waits for a search for the vector `id` to return it as the best match
+pub fn wait_for_search(server: &Server, token: &str, collection: &str, id: usize) {
+    wait_for(&format!("v{} to be searchable", id), || {
+        let (status, response) = search(
+            server,
+            token,
+            collection,
+            "dense",
+            json!({ "query_vector": vector(id), "top_k": 1 }),
+        );
+        (status == 200 && response["results"][0]["id"] == format!("v{}", id)).then_some(())
+    })
+}
+
+/// Creates a collection of dense vectors with a dense HNSW index, with the
+/// fields of `options` replacing the ones of its default definition
+pub fn create_collection(server: &Server, token: &str, collection: &str, options: Value) {
+    let mut definition = json!({
+        "name": collection,
+        "dense_vector": { "enabled": true, "dimension": DIMENSION },
+        "sparse_vector": { "enabled": false },
+        "tf_idf_options": { "enabled": false },
+        "config": { "max_vectors": null, "replication_factor": 1 }
+    });
+    for (field, value) in options.as_object().unwrap() {
+        definition[field] = value.clone();
+    }
+    let (status, response) = request(
+        server.port,
+        "POST",
+        "/vectordb/collections",
+        Some(token),
+        Some(definition),
+    );
+    assert!(status < 300, "{}", response);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/indexes/dense", collection),
+        Some(token),
+        Some(json!({
+            "name": "dense",
+            "distance_metric_type": "cosine",
+            "quantization": {
+                "type": "scalar",
+                "properties": { "data_type": "f32", "range": { "min": -1.0, "max": 1.0 } }
+            },
+            "index": { "type": "hnsw", "properties": {} }
+        })),
+    );
+    assert!(status < 300, "{}", response);
+}
+
+/// Creates a collection with a dense HNSW index
+pub fn create_dense_collection(
+    server: &Server,
+    token: &str,
+    collection: &str,
+    replication_factor: u16,
+) {
+    create_collection(
+        server,
+        token,
+        collection,
+        json!({ "config": { "max_vectors": null, "replication_factor": replication_factor } }),
+    );
+}
+
+/// Upserts `vectors` through the streaming endpoint
+pub fn streaming_upsert(server: &Server, token: &str, collection: &str, vectors: Value) -> u16 {
+    let (status, _) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/streaming/upsert", collection),
+        Some(token),
+        Some(json!({ "vectors": vectors })),
+    );
+    status
+}
+
+pub fn get_vector(server: &Server, token: &str, collection: &str, id: &str) -> (u16, Value) {
+    request(
+        server.port,
+        "GET",
+        &format!("/vectordb/collections/{}/vectors/{}", collection, id),
+        Some(token),
+        None,
+    )
+}
+
+/// Sends a search of type `kind`, e.g. `dense`
+pub fn search(
+    server: &Server,
+    token: &str,
+    collection: &str,
+    kind: &str,
+    body: Value,
+) -> (u16, Value) {
+    request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/search/{}", collection, kind),
+        Some(token),
+        Some(body),
+    )
+}
+
+/// Opens an explicit transaction and returns the path of its endpoints
+pub fn open_transaction(server: &Server, token: &str, collection: &str) -> String {
+    let path = format!("/vectordb/collections/{}/transactions", collection);
+    let (status, transaction) = request(server.port, "POST", &path, Some(token), None);
+    assert_eq!(status, 200, "{}", transaction);
+    format!(
+        "{}/{}",
+        path,
+        transaction["transaction_id"].as_str().unwrap()
+    )
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use common::{
+    create_dense_collection, get_vector, login, open_transaction, request, request_bytes, search,
+    start_server, upsert, vector, wait_for, wait_for_search, wait_until_ready, Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "compacted";
+
+fn compact(server: &Server, token: &str, body: Option<Value>) -> Value {
+    wait_for("the collection to be compacted", || {
+        let (status, response) = request(
+            server.port,
+            "POST",
+            &format!("/vectordb/collections/{}/compact", COLLECTION),
+            Some(token),
+            body.clone(),
+        );
+        (status == 200).then_some(response)
+    })
+}
+
+// Returns the ids of the 10 best matches of the vector `id`
+fn search_ids(server: &Server, token: &str, id: usize) -> Vec<String> {
+    let (status, response) = search(
+        server,
+        token,
+        COLLECTION,
+        "dense",
+        json!({ "query_vector": vector(id), "top_k": 10 }),
+    );
+    assert_eq!(status, 200, "{}", response);
+    response["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["id"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+fn delete(server: &Server, token: &str, ids: &[usize]) {
+    let path = open_transaction(server, token, COLLECTION);
+    let ids: Vec<_> = ids.iter().map(|id| format!("v{}", id)).collect();
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/delete", path),
+        Some(token),
+        Some(json!({ "ids": ids })),
+    );
+    assert_eq!(status, 200, "{}", response);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/commit", path),
+        Some(token),
+        None,
+    );
+    assert!(status < 300, "{} {}", status, response);
+    wait_for("the deletes to be applied", || {
+        (get_vector(server, token, COLLECTION, &ids[0]).0 != 200).then_some(())
+    });
+}
+
+#[test]
+fn test_compaction_rebuilds_indexes() {
+    let mut server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    upsert(&server, &token, COLLECTION, &(0..30).collect::<Vec<_>>());
+    wait_for_search(&server, &token, COLLECTION, 29);
+
+    delete(&server, &token, &(0..10).collect::<Vec<_>>());
+
+    // a malformed body is rejected rather than ignored
+    let (status, body) = request_bytes(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/compact", COLLECTION),
+        Some(&token),
+        "application/json",
+        b"{ \"keep_versions\": ",
+    );
+    assert_eq!(status, 400, "{}", body);
+
+    let response = compact(&server, &token, Some(json!({ "keep_versions": 1 })));
+    assert_eq!(response["compacted"], true, "{}", response);
+    assert_eq!(response["indexes_rebuilt"], true, "{}", response);
+    let collection_path = server.data_path().join("collections").join(COLLECTION);
+    assert!(collection_path.join("dense_hnsw.1").exists());
+    wait_for("the replaced index to be removed", || {
+        (!collection_path.join("dense_hnsw").exists()).then_some(())
+    });
+
+    // the rebuilt index only has the live vectors, and is loaded on restart
+    for restarted in [false, true] {
+        if restarted {
+            server.restart();
+            wait_until_ready(&server);
+        }
+        for id in [0, 5, 15, 29] {
+            let ids = search_ids(&server, &token, id);
+            assert!(!ids.is_empty());
+            assert!(ids.iter().all(|id| id[1..].parse::<usize>().unwrap() >= 10));
+            if id >= 10 {
+                assert_eq!(ids[0], format!("v{}", id));
+            }
+        }
+    }
+
+    // the body may be left empty, for the configured retention policy
+    compact(&server, &token, None);
+
+    // a few deleted vectors are left marked as such in the indexes, 1 out
+    // of the 20 indexed ones being below the 20% rebuild threshold
+    delete(&server, &token, &[10]);
+    let response = compact(&server, &token, Some(json!({ "keep_versions": 1 })));
+    assert_eq!(response["compacted"], true, "{}", response);
+    assert_eq!(response["indexes_rebuilt"], false, "{}", response);
+    assert!(!collection_path.join("dense_hnsw.2").exists());
+    assert!(!search_ids(&server, &token, 10).contains(&"v10".to_string()));
+
+    // vectors written after the first rebuild are indexed again, once 5
+    // out of the 22 indexed vectors are deleted
+    upsert(&server, &token, COLLECTION, &[30, 31]);
+    wait_for_search(&server, &token, COLLECTION, 31);
+    delete(&server, &token, &[11, 12, 13, 14]);
+    let response = compact(&server, &token, Some(json!({ "keep_versions": 1 })));
+    assert_eq!(response["compacted"], true, "{}", response);
+    assert_eq!(response["indexes_rebuilt"], true, "{}", response);
+    assert!(collection_path.join("dense_hnsw.2").exists());
+    wait_for_search(&server, &token, COLLECTION, 30);
+    assert_eq!(search_ids(&server, &token, 20)[0], "v20");
+    let ids = search_ids(&server, &token, 12);
+    assert!(ids.iter().all(|id| id[1..].parse::<usize>().unwrap() >= 15));
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use common::{
+    create_dense_collection, get_vector, login, open_transaction, request, request_with_headers,
+    start_server, streaming_upsert, vector, wait_for, Server,
+};
+use serde_json::json;
+
+const COLLECTION: &str = "conditional";
+
+#[test]
+fn test_preconditions() {
+    let mut server = start_server();
+    let mut token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    let v1 = json!({ "id": "v1", "dense_values": vector(1), "if_absent": true });
+    assert_eq!(
+        streaming_upsert(&server, &token, COLLECTION, json!([v1])),
+        200
+    );
+    assert_eq!(
+        streaming_upsert(&server, &token, COLLECTION, json!([v1])),
+        412
+    );
+
+    let (status, stored) = get_vector(&server, &token, COLLECTION, "v1");
+    assert_eq!(status, 200, "{}", stored);
+    let version = stored["version"].as_u64().unwrap();
+
+    // a failed precondition fails the whole batch
+    let vectors = json!([
+        { "id": "v2", "dense_values": vector(2) },
+        { "id": "v1", "dense_values": vector(3), "if_version": version + 1 },
+    ]);
+    assert_eq!(streaming_upsert(&server, &token, COLLECTION, vectors), 412);
+    assert_eq!(get_vector(&server, &token, COLLECTION, "v2").0, 400);
+    let vectors = json!([{ "id": "v3", "dense_values": vector(3), "if_version": version }]);
+    assert_eq!(streaming_upsert(&server, &token, COLLECTION, vectors), 412);
+    let vectors = json!([{ "id": "v1", "dense_values": vector(3), "if_version": version }]);
+    assert_eq!(streaming_upsert(&server, &token, COLLECTION, vectors), 200);
+
+    // explicit transactions only replace vectors whose version is checked
+    let path = open_transaction(&server, &token, COLLECTION);
+    let version = get_vector(&server, &token, COLLECTION, "v1").1["version"]
+        .as_u64()
+        .unwrap();
+    let (status, _) = request(
+        server.port,
+        "POST",
+        &format!("{}/upsert", path),
+        Some(&token),
+        Some(json!({ "vectors": [{ "id": "v1", "dense_values": vector(4) }] })),
+    );
+    assert_eq!(status, 400);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/upsert", path),
+        Some(&token),
+        Some(json!({
+            "vectors": [{ "id": "v1", "dense_values": vector(4), "if_version": version }]
+        })),
+    );
+    assert_eq!(status, 200, "{}", response);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/commit", path),
+        Some(&token),
+        None,
+    );
+    assert_eq!(status, 204, "{}", response);
+    wait_for("v1 to be replaced", || {
+        let (_, stored) = get_vector(&server, &token, COLLECTION, "v1");
+        (stored["version"].as_u64() > Some(version)).then_some(())
+    });
+    let (_, stored) = get_vector(&server, &token, COLLECTION, "v1");
+    let stored_value = stored["dense_values"][0].as_f64().unwrap();
+    assert!((stored_value - vector(4)[0] as f64).abs() < 1e-5);
+
+    // preconditions are checked again on commit, including after a restart
+    let version = stored["version"].as_u64().unwrap();
+    for (restart, checked) in [
+        (
+            false,
+            json!({ "id": "v1", "dense_values": vector(5), "if_version": version }),
+        ),
+        (
+            true,
+            json!({ "id": "v4", "dense_values": vector(5), "if_absent": true }),
+        ),
+    ] {
+        let path = open_transaction(&server, &token, COLLECTION);
+        let (status, response) = request(
+            server.port,
+            "POST",
+            &format!("{}/upsert", path),
+            Some(&token),
+            Some(json!({ "vectors": [checked.clone()] })),
+        );
+        assert_eq!(status, 200, "{}", response);
+        if restart {
+            server.restart();
+            token = login(&server);
+        }
+        let id = checked["id"].as_str().unwrap();
+        let written = json!([{ "id": id, "dense_values": vector(6) }]);
+        assert_eq!(streaming_upsert(&server, &token, COLLECTION, written), 200);
+        let (status, response) = request(
+            server.port,
+            "POST",
+            &format!("{}/commit", path),
+            Some(&token),
+            None,
+        );
+        assert_eq!(status, 412, "{}", response);
+        let (_, stored) = get_vector(&server, &token, COLLECTION, id);
+        let stored_value = stored["dense_values"][0].as_f64().unwrap();
+        assert!((stored_value - vector(6)[0] as f64).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_idempotency_key() {
+    let mut server = start_server();
+    let mut token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    let create_transaction = || {
+        request_with_headers(
+            server.port,
+            "POST",
+            &format!("/vectordb/collections/{}/transactions", COLLECTION),
+            Some(&token),
+            &[("Idempotency-Key", "create-1")],
+            None,
+        )
+    };
+    let (status, head, first) = create_transaction();
+    assert_eq!(status, 200, "{}", first);
+    assert!(!head.to_lowercase().contains("idempotent-replayed"));
+    let (status, head, second) = create_transaction();
+    assert_eq!(status, 200, "{}", second);
+    assert!(head.to_lowercase().contains("idempotent-replayed: true"));
+    assert_eq!(first, second);
+
+    // a retried write isn't applied again after the vector is deleted
+    let upsert = |server: &Server, token: &str| {
+        request_with_headers(
+            server.port,
+            "POST",
+            &format!("/vectordb/collections/{}/streaming/upsert", COLLECTION),
+            Some(token),
+            &[("Idempotency-Key", "upsert-1")],
+            Some(json!({ "vectors": [{ "id": "v1", "dense_values": vector(1) }] })),
+        )
+    };
+    assert_eq!(upsert(&server, &token).0, 200);
+    assert_eq!(get_vector(&server, &token, COLLECTION, "v1").0, 200);
+    let (status, _) = request(
+        server.port,
+        "DELETE",
+        &format!("/vectordb/collections/{}/streaming/vectors/v1", COLLECTION),
+        Some(&token),
+        None,
+    );
+    assert_eq!(status, 204);
+    assert_eq!(upsert(&server, &token).0, 200);
+    assert_eq!(get_vector(&server, &token, COLLECTION, "v1").0, 400);
+
+    // keys are remembered across restarts, along with the request body
+    server.restart();
+    token = login(&server);
+    let (status, head, _) = upsert(&server, &token);
+    assert_eq!(status, 200);
+    assert!(head.to_lowercase().contains("idempotent-replayed: true"));
+    assert_eq!(get_vector(&server, &token, COLLECTION, "v1").0, 400);
+    let (status, _, response) = request_with_headers(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/streaming/upsert", COLLECTION),
+        Some(&token),
+        &[("Idempotency-Key", "upsert-1")],
+        Some(json!({ "vectors": [{ "id": "v1", "dense_values": vector(2) }] })),
+    );
+    assert_eq!(status, 422, "{}", response);
+
+    // other keys are handled again
+    let (status, _, _) = request_with_headers(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/streaming/upsert", COLLECTION),
+        Some(&token),
+        &[("Idempotency-Key", "upsert-2")],
+        Some(json!({ "vectors": [{ "id": "v1", "dense_values": vector(1) }] })),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(get_vector(&server, &token, COLLECTION, "v1").0, 200);
+}
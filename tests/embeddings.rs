@@ -0,0 +1,343 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    thread,
+};
+
+use common::{
+    create_collection, get_vector, login, request, search, start_server_with_overrides,
+    streaming_upsert, vector, wait_for, Server, DIMENSION,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "embedded";
+
+// the number at the end of the text
+fn text_seed(text: &str) -> usize {
+    text.rsplit(' ').next().unwrap().parse().unwrap()
+}
+
+// Serves OpenAI-style embeddings on `/dense` and `/sparse`, where a text
+// ending with `n` is embedded as `vector(n)` and as the single term `n`.
+// Texts without a number fail the request.
+fn start_embeddings_stub() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            let texts: Vec<&str> = body["input"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|text| text.as_str().unwrap())
+                .collect();
+
+            let response = if texts.iter().any(|text| text.ends_with("fail")) {
+                (500, json!({ "error": "failed" }))
+            } else {
+                let data: Vec<_> = texts
+                    .iter()
+                    .enumerate()
+                    .map(|(index, text)| {
+                        let seed = text_seed(text);
+                        let embedding = if request_line.contains("/sparse") {
+                            json!({ "indices": [seed], "values": [1.0] })
+                        } else {
+                            json!(vector(seed))
+                        };
+                        json!({ "object": "embedding", "index": index, "embedding": embedding })
+                    })
+                    .collect();
+                (200, json!({ "object": "list", "data": data }))
+            };
+            let body = response.1.to_string();
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 {} OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                response.0,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+    port
+}
+
+// Starts a server with the `dense` and `sparse` providers of the stub
+fn start_embedding_server(stub_port: u16) -> Server {
+    let providers = format!(
+        r#"[embeddings]
+[embeddings.providers.dense]
+type = "http"
+url = "http://127.0.0.1:{0}/dense"
+
+[embeddings.providers.sparse]
+type = "http"
+url = "http://127.0.0.1:{0}/sparse"
+model = "splade""#,
+        stub_port
+    );
+    start_server_with_overrides(&[("[embeddings]", &providers)])
+}
+
+fn create_embedded_collection(server: &Server, token: &str) {
+    create_collection(
+        server,
+        token,
+        COLLECTION,
+        json!({
+            "dense_vector": {
+                "enabled": true,
+                "dimension": DIMENSION,
+                "embedding": { "provider": "dense" }
+            },
+            "sparse_vector": {
+                "enabled": true,
+                "embedding": { "provider": "sparse" }
+            },
+            "store_raw_text": true
+        }),
+    );
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/indexes/sparse", COLLECTION),
+        Some(token),
+        Some(json!({ "name": "sparse", "quantization": 64, "sample_threshold": 1 })),
+    );
+    assert!(status < 300, "{}", response);
+}
+
+fn search_text(server: &Server, token: &str, kind: &str, query_text: &str) -> (u16, Value) {
+    search(
+        server,
+        token,
+        COLLECTION,
+        kind,
+        json!({ "query_text": query_text, "top_k": 3 }),
+    )
+}
+
+#[test]
+fn test_embedded_text() {
+    let server = start_embedding_server(start_embeddings_stub());
+    let token = login(&server);
+    create_embedded_collection(&server, &token);
+
+    // collections can only use the providers configured on the server
+    for embedding in [
+        json!({ "provider": "missing" }),
+        json!({ "provider": "sparse", "url": "http://169.254.169.254/" }),
+        json!({ "type": "http", "url": "http://169.254.169.254/" }),
+    ] {
+        let (status, response) = request(
+            server.port,
+            "POST",
+            "/vectordb/collections",
+            Some(&token),
+            Some(json!({
+                "name": "unconfigured",
+                "dense_vector": { "enabled": true, "dimension": DIMENSION, "embedding": embedding },
+                "sparse_vector": { "enabled": false },
+                "tf_idf_options": { "enabled": false },
+                "config": { "max_vectors": null, "replication_factor": 1 }
+            })),
+        );
+        assert_eq!(status, 400, "{}", response);
+    }
+
+    let vectors: Vec<_> = (0..5)
+        .map(|id| json!({ "id": format!("v{}", id), "text": format!("document {}", id) }))
+        .collect();
+    let status = streaming_upsert(&server, &token, COLLECTION, json!(vectors));
+    assert_eq!(status, 200);
+
+    let (status, stored) = get_vector(&server, &token, COLLECTION, "v3");
+    assert_eq!(status, 200, "{}", stored);
+    let stored_values: Vec<f32> = serde_json::from_value(stored["dense_values"].clone()).unwrap();
+    for (stored_value, value) in stored_values.iter().zip(vector(3)) {
+        assert!((stored_value - value).abs() < 1e-5);
+    }
+
+    for kind in ["dense", "sparse", "hybrid"] {
+        wait_for(&format!("the {} search to find v3", kind), || {
+            let (status, response) = search_text(&server, &token, kind, "query 3");
+            (status == 200 && response["results"][0]["id"] == "v3").then_some(())
+        });
+    }
+
+    // failures of the provider fail the write
+    let status = streaming_upsert(
+        &server,
+        &token,
+        COLLECTION,
+        json!([{ "id": "v5", "text": "fail" }]),
+    );
+    assert_eq!(status, 502);
+    assert_eq!(search_text(&server, &token, "dense", "fail").0, 502);
+}
+
+// Local models are only available with the `local-embeddings` feature
+#[cfg(feature = "local-embeddings")]
+mod local_models {
+    use std::{fs, path::Path};
+
+    use candle_core::{DType, Device};
+    use candle_nn::{VarBuilder, VarMap};
+    use candle_transformers::models::bert::{self, BertForMaskedLM, BertModel};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const WORDS: [&str; 8] = [
+        "red", "green", "blue", "apple", "banana", "cherry", "document", "query",
+    ];
+
+    // Writes a BERT model with random weights, with a masked language model
+    // head if `masked_lm`, and a whitespace tokenizer of `WORDS` to `dir`
+    fn write_local_model(dir: &Path, masked_lm: bool) {
+        let special_tokens = ["[PAD]", "[UNK]", "[CLS]", "[SEP]"];
+        let vocab: serde_json::Map<_, _> = special_tokens
+            .iter()
+            .chain(WORDS.iter())
+            .enumerate()
+            .map(|(id, token)| (token.to_string(), json!(id)))
+            .collect();
+        let config = json!({
+            "vocab_size": vocab.len(),
+            "hidden_size": DIMENSION,
+            "num_hidden_layers": 1,
+            "num_attention_heads": 2,
+            "intermediate_size": 16,
+            "hidden_act": "gelu",
+            "hidden_dropout_prob": 0.0,
+            "max_position_embeddings": 16,
+            "type_vocab_size": 2,
+            "initializer_range": 0.02,
+            "layer_norm_eps": 1e-12,
+            "pad_token_id": 0,
+            "model_type": "bert"
+        });
+        let tokenizer = json!({
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": { "type": "Lowercase" },
+            "pre_tokenizer": { "type": "Whitespace" },
+            "post_processor": { "type": "BertProcessing", "sep": ["[SEP]", 3], "cls": ["[CLS]", 2] },
+            "decoder": null,
+            "model": { "type": "WordLevel", "vocab": vocab, "unk_token": "[UNK]" }
+        });
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("config.json"), config.to_string()).unwrap();
+        fs::write(dir.join("tokenizer.json"), tokenizer.to_string()).unwrap();
+
+        let config: bert::Config = serde_json::from_value(config).unwrap();
+        let weights = VarMap::new();
+        let builder = VarBuilder::from_varmap(&weights, DType::F32, &Device::Cpu);
+        if masked_lm {
+            BertForMaskedLM::load(builder, &config).unwrap();
+        } else {
+            BertModel::load(builder, &config).unwrap();
+        }
+        weights.save(dir.join("model.safetensors")).unwrap();
+    }
+
+    #[test]
+    fn test_local_models() {
+        let models = TempDir::new().unwrap();
+        write_local_model(&models.path().join("bert"), false);
+        write_local_model(&models.path().join("splade"), true);
+        let providers = format!(
+            r#"[embeddings]
+    [embeddings.providers.dense]
+    type = "bert"
+    model_dir = "{0}/bert"
+
+    [embeddings.providers.sparse]
+    type = "splade"
+    model_dir = "{0}/splade""#,
+            models.path().display()
+        );
+        let server = start_server_with_overrides(&[("[embeddings]", &providers)]);
+        let token = login(&server);
+        create_embedded_collection(&server, &token);
+
+        let texts = [
+            "red apple document",
+            "green banana document",
+            "blue cherry document",
+        ];
+        let vectors: Vec<_> = texts
+            .iter()
+            .enumerate()
+            .map(|(id, text)| json!({ "id": format!("v{}", id), "text": text }))
+            .collect();
+        let status = streaming_upsert(&server, &token, COLLECTION, json!(vectors));
+        assert_eq!(status, 200);
+
+        // dense embeddings are normalized, and sparse ones are weights of the
+        // vocabulary's tokens
+        let (status, stored) = get_vector(&server, &token, COLLECTION, "v1");
+        assert_eq!(status, 200, "{}", stored);
+        let dense_values: Vec<f32> =
+            serde_json::from_value(stored["dense_values"].clone()).unwrap();
+        assert_eq!(dense_values.len(), DIMENSION);
+        let norm = dense_values.iter().map(|value| value * value).sum::<f32>();
+        assert!((norm - 1.0).abs() < 1e-3, "{}", norm);
+        let sparse_values = stored["sparse_values"].as_array().unwrap();
+        assert!(!sparse_values.is_empty());
+
+        // the same text has the same embedding
+        wait_for("the dense search to find v1", || {
+            let (status, response) = search_text(&server, &token, "dense", texts[1]);
+            (status == 200 && response["results"][0]["id"] == "v1").then_some(())
+        });
+        let (status, response) = search_text(&server, &token, "sparse", texts[1]);
+        assert_eq!(status, 200, "{}", response);
+
+        // the dimension of the collection must be the model's
+        let (status, response) = request(
+            server.port,
+            "POST",
+            "/vectordb/collections",
+            Some(&token),
+            Some(json!({
+                "name": "mismatched",
+                "dense_vector": {
+                    "enabled": true,
+                    "dimension": DIMENSION * 2,
+                    "embedding": { "provider": "dense" }
+                },
+                "sparse_vector": { "enabled": false },
+                "tf_idf_options": { "enabled": false },
+                "config": { "max_vectors": null, "replication_factor": 1 }
+            })),
+        );
+        assert_eq!(status, 400, "{}", response);
+    }
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use common::{
+    create_collection, get_vector, login, search, start_server_with_overrides, streaming_upsert,
+    vector, wait_for, wait_for_search, Server, DIMENSION,
+};
+use serde_json::json;
+
+const COLLECTION: &str = "expiring";
+
+fn create_expiring_collection(server: &Server, token: &str, ttl: Option<u64>) {
+    create_collection(
+        server,
+        token,
+        COLLECTION,
+        json!({ "config": { "max_vectors": null, "replication_factor": 1, "ttl": ttl } }),
+    );
+}
+
+fn search_ids(server: &Server, token: &str, id: usize) -> Vec<String> {
+    let (status, response) = search(
+        server,
+        token,
+        COLLECTION,
+        "dense",
+        json!({ "query_vector": vector(id), "top_k": 10 }),
+    );
+    assert_eq!(status, 200, "{}", response);
+    response["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["id"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+#[test]
+fn test_expired_vectors_are_skipped_by_searches() {
+    let server = start_server_with_overrides(&[("sweep_interval = 60 #", "sweep_interval = 0 #")]);
+    let token = login(&server);
+    create_expiring_collection(&server, &token, None);
+
+    let expired_at = DateTime::from_timestamp(Utc::now().timestamp() - 3600, 0).unwrap();
+    let status = streaming_upsert(
+        &server,
+        &token,
+        COLLECTION,
+        json!([
+            { "id": "v0", "dense_values": vector(0) },
+            { "id": "v1", "dense_values": vector(1), "expires_at": expired_at },
+        ]),
+    );
+    assert_eq!(status, 200);
+    wait_for_search(&server, &token, COLLECTION, 0);
+
+    assert_eq!(search_ids(&server, &token, 1), ["v0"]);
+    // without the sweeper, the expired vector is kept
+    let (status, stored) = get_vector(&server, &token, COLLECTION, "v1");
+    assert_eq!(status, 200, "{}", stored);
+    let stored_expiry: DateTime<Utc> = stored["expires_at"].as_str().unwrap().parse().unwrap();
+    assert_eq!(stored_expiry, expired_at);
+}
+
+#[test]
+fn test_expired_vectors_outranking_top_k_live_ones() {
+    let server = start_server_with_overrides(&[("sweep_interval = 60 #", "sweep_interval = 0 #")]);
+    let token = login(&server);
+    create_expiring_collection(&server, &token, None);
+
+    // the expired vectors are closer to the query than the live ones, and
+    // outnumber `top_k`
+    let axis = |x: f32, y: f32| {
+        let mut values = vec![0.0; DIMENSION];
+        values[0] = x;
+        values[1] = y;
+        values
+    };
+    let expired_at = DateTime::from_timestamp(Utc::now().timestamp() - 3600, 0).unwrap();
+    let mut vectors: Vec<_> = (0..20)
+        .map(|i| {
+            json!({
+                "id": format!("expired{}", i),
+                "dense_values": axis(1.0, i as f32 * 0.01),
+                "expires_at": expired_at,
+            })
+        })
+        .collect();
+    vectors.extend((0..3).map(
+        |i| json!({ "id": format!("live{}", i), "dense_values": axis(0.5, 1.0 + i as f32 * 0.5) }),
+    ));
+    assert_eq!(
+        streaming_upsert(&server, &token, COLLECTION, json!(vectors)),
+        200
+    );
+
+    wait_for("the live vectors to be found", || {
+        let (status, response) = search(
+            &server,
+            &token,
+            COLLECTION,
+            "dense",
+            json!({ "query_vector": axis(1.0, 0.0), "top_k": 3 }),
+        );
+        assert_eq!(status, 200, "{}", response);
+        let ids: Vec<_> = response["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["id"].as_str().unwrap().to_owned())
+            .collect();
+        assert!(ids.iter().all(|id| id.starts_with("live")), "{:?}", ids);
+        (ids == ["live0", "live1", "live2"]).then_some(())
+    });
+}
+
+#[test]
+fn test_expired_vectors_are_deleted() {
+    let server = start_server_with_overrides(&[("sweep_interval = 60 #", "sweep_interval = 1 #")]);
+    let token = login(&server);
+    create_expiring_collection(&server, &token, Some(2));
+
+    let expires_at = Utc::now() + Duration::days(1);
+    let status = streaming_upsert(
+        &server,
+        &token,
+        COLLECTION,
+        json!([
+            { "id": "v0", "dense_values": vector(0) },
+            { "id": "v1", "dense_values": vector(1), "expires_at": expires_at },
+        ]),
+    );
+    assert_eq!(status, 200);
+    let (status, stored) = get_vector(&server, &token, COLLECTION, "v0");
+    assert_eq!(status, 200, "{}", stored);
+    assert!(stored["expires_at"].is_string(), "{}", stored);
+
+    // vectors without an `expires_at` expire after the collection's ttl
+    wait_for("v0 to be deleted", || {
+        (get_vector(&server, &token, COLLECTION, "v0").0 == 400).then_some(())
+    });
+    assert_eq!(get_vector(&server, &token, COLLECTION, "v1").0, 200);
+    assert_eq!(search_ids(&server, &token, 0), ["v1"]);
+}
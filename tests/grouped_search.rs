@@ -0,0 +1,95 @@
+mod common;
+
+use common::{
+    create_dense_collection, login, search, start_server, streaming_upsert, vector, wait_for,
+    Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "grouped";
+
+fn dense_search(server: &Server, token: &str, body: Value) -> Value {
+    let (status, response) = search(server, token, COLLECTION, "dense", body);
+    assert_eq!(status, 200, "{}", response);
+    response
+}
+
+#[test]
+fn test_grouped_search() {
+    let server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    // documents `a` and `b` with three chunks each, and a vector without a
+    // document
+    let vectors: Vec<_> = (0..6)
+        .map(|seed| {
+            let document_id = if seed < 3 { "a" } else { "b" };
+            json!({
+                "id": format!("{}{}", document_id, seed),
+                "document_id": document_id,
+                "dense_values": vector(seed),
+            })
+        })
+        .chain([json!({ "id": "v6", "dense_values": vector(6) })])
+        .collect();
+    let status = streaming_upsert(&server, &token, COLLECTION, json!(vectors));
+    assert_eq!(status, 200);
+    wait_for("the vectors to be searchable", || {
+        let response = dense_search(&server, &token, json!({ "query_vector": vector(6) }));
+        (response["results"][0]["id"] == "v6").then_some(())
+    });
+
+    let response = dense_search(
+        &server,
+        &token,
+        json!({
+            "query_vector": vector(0),
+            "group_by": "document_id",
+            "group_size": 2,
+            "groups_limit": 3,
+        }),
+    );
+    assert!(response["results"].as_array().unwrap().is_empty());
+    let groups = response["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 3, "{}", response);
+    assert_eq!(groups[0]["document_id"], "a", "{}", response);
+    assert_eq!(groups[0]["hits"][0]["id"], "a0", "{}", response);
+    assert_eq!(groups[0]["score"], groups[0]["hits"][0]["score"]);
+    let mut document_ids: Vec<_> = groups
+        .iter()
+        .map(|group| group["document_id"].as_str().unwrap_or("none"))
+        .collect();
+    document_ids.sort();
+    assert_eq!(document_ids, ["a", "b", "none"]);
+    for group in groups {
+        let hits = group["hits"].as_array().unwrap();
+        let expected = if group["document_id"].is_null() { 1 } else { 2 };
+        assert_eq!(hits.len(), expected, "{}", response);
+        assert!(hits
+            .iter()
+            .all(|hit| hit["document_id"] == group["document_id"]));
+    }
+
+    // the summed scores of the best two chunks
+    let response = dense_search(
+        &server,
+        &token,
+        json!({
+            "query_vector": vector(0),
+            "group_by": "document_id",
+            "group_size": 2,
+            "groups_limit": 1,
+            "group_aggregation": "sum",
+        }),
+    );
+    let group = &response["groups"][0];
+    let sum =
+        group["hits"][0]["score"].as_f64().unwrap() + group["hits"][1]["score"].as_f64().unwrap();
+    assert!(
+        (group["score"].as_f64().unwrap() - sum).abs() < 1e-3,
+        "{}",
+        response
+    );
+    assert_eq!(response["groups"].as_array().unwrap().len(), 1);
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{login, request, start_server};
+use serde_json::json;
+
+#[test]
+fn test_health_readiness_and_info() {
+    let server = start_server();
+    // waits for the server to be ready
+    login(&server);
+
+    let (status, health) = request(server.port, "GET", "/health", None, None);
+    assert_eq!(status, 200);
+    assert_eq!(health["status"], "ok");
+
+    let (status, readiness) = request(server.port, "GET", "/ready", None, None);
+    assert_eq!(status, 200);
+    assert_eq!(readiness["status"], "ready");
+
+    let (status, info) = request(server.port, "GET", "/info", None, None);
+    assert_eq!(status, 200);
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    let features: Vec<_> = [
+        ("grpc-server", cfg!(feature = "grpc-server")),
+        ("local-embeddings", cfg!(feature = "local-embeddings")),
+    ]
+    .into_iter()
+    .filter_map(|(feature, enabled)| enabled.then_some(feature))
+    .collect();
+    assert_eq!(info["features"], json!(features));
+    assert!(info["uptime_seconds"].is_u64());
+    assert_eq!(info["config"]["port"], server.port);
+    assert_eq!(info["config"]["replication_role"], "leader");
+    assert_eq!(info["config"]["storage_backend"], "local");
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use std::fs;
+
+use common::{
+    create_dense_collection, get_vector, login, open_transaction, request, start_server, vector,
+    wait_for, Server,
+};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+const COLLECTION: &str = "imported";
+
+// Waits for the import writing to `transaction_id` to finish
+fn wait_for_import(server: &Server, token: &str, transaction_id: &str) -> Value {
+    wait_for("the import to finish", || {
+        let (status, job) = request(
+            server.port,
+            "GET",
+            &format!(
+                "/vectordb/collections/{}/import/{}",
+                COLLECTION, transaction_id
+            ),
+            Some(token),
+            None,
+        );
+        assert_eq!(status, 200, "{}", job);
+        (job["state"] != "reading").then_some(job)
+    })
+}
+
+#[test]
+fn test_import_jsonl_file() {
+    let server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    let imports = server.data_path().join("imports");
+    fs::create_dir_all(&imports).unwrap();
+    let path = imports.join("vectors.jsonl");
+    let lines: Vec<_> = (0..2500)
+        .map(|id| json!({ "id": format!("v{}", id), "dense_values": vector(id) }).to_string())
+        .collect();
+    fs::write(&path, lines.join("\n")).unwrap();
+
+    let (status, job) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/import", COLLECTION),
+        Some(&token),
+        Some(json!({ "path": "vectors.jsonl" })),
+    );
+    assert_eq!(status, 202, "{}", job);
+    assert_eq!(job["format"], "jsonl");
+    let job = wait_for_import(&server, &token, job["transaction_id"].as_str().unwrap());
+    assert_eq!(job["state"], "committed", "{}", job);
+    assert_eq!(job["records_read"], 2500);
+    assert_eq!(job["percentage_read"], 100.0);
+    let imported = wait_for("the vectors to be indexed", || {
+        let (status, imported) = get_vector(&server, &token, COLLECTION, "v2499");
+        (status == 200).then_some(imported)
+    });
+    assert_eq!(
+        imported["dense_values"].as_array().unwrap().len(),
+        vector(0).len()
+    );
+
+    // a vector of the wrong dimension fails the import, and its transaction
+    // is aborted
+    let path = imports.join("invalid.jsonl");
+    fs::write(
+        &path,
+        json!({ "id": "invalid", "dense_values": [1.0] }).to_string(),
+    )
+    .unwrap();
+    let (status, job) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/import", COLLECTION),
+        Some(&token),
+        Some(json!({ "path": path })),
+    );
+    assert_eq!(status, 202, "{}", job);
+    let job = wait_for_import(&server, &token, job["transaction_id"].as_str().unwrap());
+    assert_eq!(job["state"], "failed");
+    assert!(job["error"].is_string());
+    open_transaction(&server, &token, COLLECTION);
+
+    let (status, _) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/import", COLLECTION),
+        Some(&token),
+        Some(json!({ "path": imports.join("missing.npy") })),
+    );
+    assert_eq!(status, 400);
+
+    // files outside the import directory are rejected, including through
+    // `..` and symlinks
+    let outside = TempDir::new().unwrap();
+    let path = outside.path().join("outside.jsonl");
+    fs::write(&path, lines[0].as_str()).unwrap();
+    std::os::unix::fs::symlink(&path, imports.join("link.jsonl")).unwrap();
+    for path in [json!(path), json!(".."), json!("link.jsonl")] {
+        let (status, error) = request(
+            server.port,
+            "POST",
+            &format!("/vectordb/collections/{}/import", COLLECTION),
+            Some(&token),
+            Some(json!({ "path": path })),
+        );
+        assert_eq!(status, 400, "{}", error);
+        assert!(
+            error["message"]
+                .as_str()
+                .unwrap()
+                .contains("outside the import directory"),
+            "{}",
+            error
+        );
+    }
+
+    // the collection is checked before the file
+    let (status, _) = request(
+        server.port,
+        "POST",
+        "/vectordb/collections/missing/import",
+        Some(&token),
+        Some(json!({ "path": ".." })),
+    );
+    assert_eq!(status, 404);
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::{
+    create_collection, get_vector, login, open_transaction, request, search, start_server, vector,
+    wait_for, Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "schema_updated";
+
+fn upsert_with_metadata(server: &Server, token: &str, vectors: &[(usize, Value)]) {
+    let path = open_transaction(server, token, COLLECTION);
+
+    let vectors: Vec<_> = vectors
+        .iter()
+        .map(|(id, metadata)| {
+            json!({ "id": format!("v{}", id), "dense_values": vector(*id), "metadata": metadata })
+        })
+        .collect();
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/upsert", path),
+        Some(token),
+        Some(json!({ "vectors": vectors })),
+    );
+    assert!(status < 300, "{} {}", status, response);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/commit", path),
+        Some(token),
+        None,
+    );
+    assert!(status < 300, "{} {}", status, response);
+}
+
+fn update_schema(server: &Server, token: &str, body: Value) -> (u16, Value) {
+    request(
+        server.port,
+        "PATCH",
+        &format!("/vectordb/collections/{}/metadata_schema", COLLECTION),
+        Some(token),
+        Some(body),
+    )
+}
+
+// Waits for a search for the vector `id`, restricted to vectors whose
+// `field` is `value`, to return it as the best match
+fn wait_for_filtered_search(server: &Server, token: &str, id: usize, field: &str, value: Value) {
+    wait_for(&format!("v{} to be searchable by {}", id, field), || {
+        let (status, response) = search(
+            server,
+            token,
+            COLLECTION,
+            "dense",
+            json!({
+                "query_vector": vector(id),
+                "top_k": 1,
+                "filter": {
+                    "Is": { "field_name": field, "field_value": value, "operator": "Equal" }
+                }
+            }),
+        );
+        (status == 200 && response["results"][0]["id"] == format!("v{}", id)).then_some(())
+    })
+}
+
+#[test]
+fn test_update_metadata_schema() {
+    let server = start_server();
+    let token = login(&server);
+    create_collection(
+        &server,
+        &token,
+        COLLECTION,
+        json!({
+            "metadata_schema": {
+                "fields": [{ "name": "color", "values": ["red", "blue"] }],
+                "supported_conditions": []
+            }
+        }),
+    );
+
+    let vectors: Vec<_> = (0..20)
+        .map(|id| {
+            let color = if id % 2 == 0 { "red" } else { "blue" };
+            (id, json!({ "color": color }))
+        })
+        .collect();
+    upsert_with_metadata(&server, &token, &vectors);
+    wait_for_filtered_search(&server, &token, 19, "color", json!("blue"));
+
+    // two values of a field leave room for a third one in its dimensions
+    let (status, response) = update_schema(
+        &server,
+        &token,
+        json!({ "values": [{ "name": "color", "values": ["green"] }] }),
+    );
+    assert_eq!(status, 200, "{}", response);
+    assert!(response["reindex"].is_null());
+    upsert_with_metadata(&server, &token, &[(20, json!({ "color": "green" }))]);
+    wait_for_filtered_search(&server, &token, 20, "color", json!("green"));
+
+    let version = get_vector(&server, &token, COLLECTION, "v4").1["version"].clone();
+    let (status, response) = update_schema(
+        &server,
+        &token,
+        json!({ "fields": [{ "name": "size", "values": [1, 2, 3] }] }),
+    );
+    assert_eq!(status, 202, "{}", response);
+    assert_eq!(response["reindex"]["vectors_total"], 21);
+
+    let job = wait_for("the dense index to be rebuilt", || {
+        let (status, job) = request(
+            server.port,
+            "GET",
+            &format!(
+                "/vectordb/collections/{}/metadata_schema/reindex",
+                COLLECTION
+            ),
+            Some(&token),
+            None,
+        );
+        assert_eq!(status, 200, "{}", job);
+        (job["state"] != "running").then_some(job)
+    });
+    assert_eq!(job["state"], "completed", "{}", job);
+    assert_eq!(job["vectors_reindexed"], 21);
+    // the vectors aren't written again by the rebuild
+    let (status, stored) = get_vector(&server, &token, COLLECTION, "v4");
+    assert_eq!(status, 200, "{}", stored);
+    assert_eq!(stored["version"], version);
+
+    // the vectors are searchable by their metadata in the rebuilt index,
+    // which also indexes the new field
+    wait_for_filtered_search(&server, &token, 4, "color", json!("red"));
+    wait_for_filtered_search(&server, &token, 20, "color", json!("green"));
+    upsert_with_metadata(
+        &server,
+        &token,
+        &[(21, json!({ "color": "red", "size": 2 }))],
+    );
+    wait_for_filtered_search(&server, &token, 21, "size", json!(2));
+
+    // the rebuilt index replaces the previous one on disk
+    let collection_path = server.data_path().join("collections").join(COLLECTION);
+    assert!(collection_path.join("dense_hnsw.1").exists());
+    wait_for("the replaced index to be removed", || {
+        (!collection_path.join("dense_hnsw").exists()).then_some(())
+    });
+
+    let (status, response) = update_schema(
+        &server,
+        &token,
+        json!({ "fields": [{ "name": "color", "values": ["black"] }] }),
+    );
+    assert_eq!(status, 400, "{}", response);
+    let (status, response) = update_schema(
+        &server,
+        &token,
+        json!({ "values": [{ "name": "color", "values": [1] }] }),
+    );
+    assert_eq!(status, 400, "{}", response);
+}
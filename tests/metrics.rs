@@ -0,0 +1,84 @@
+mod common;
+
+use common::{
+    create_dense_collection, login, request_text, start_server, upsert, wait_for, wait_for_search,
+};
+
+const COLLECTION: &str = "observed";
+
+// Returns the value of the sample `name`, including its labels
+fn sample(metrics: &str, name: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[test]
+fn test_metrics_report_searches_and_indexing() {
+    let server = start_server();
+    let token = login(&server);
+
+    create_dense_collection(&server, &token, COLLECTION, 1);
+    upsert(&server, &token, COLLECTION, &(0..20).collect::<Vec<_>>());
+    wait_for_search(&server, &token, COLLECTION, 3);
+
+    let metrics = wait_for("the transaction to be indexed", || {
+        let (status, metrics) = request_text(server.port, "GET", "/metrics", Some(&token), None);
+        assert_eq!(status, 200);
+        let transactions = sample(
+            &metrics,
+            &format!(
+                "cosdata_indexed_transactions_total{{collection=\"{}\"}}",
+                COLLECTION
+            ),
+        );
+        (transactions == Some(1.0)).then_some(metrics)
+    });
+
+    // the metrics name the collections of all tenants
+    let (status, _) = request_text(server.port, "GET", "/metrics", None, None);
+    assert_eq!(status, 401);
+
+    let labels = format!("collection=\"{}\",search_type=\"dense\"", COLLECTION);
+    assert!(
+        sample(
+            &metrics,
+            &format!("cosdata_search_requests_total{{{}}}", labels)
+        )
+        .unwrap()
+            >= 1.0
+    );
+    assert!(
+        sample(
+            &metrics,
+            &format!("cosdata_search_duration_seconds_count{{{}}}", labels)
+        )
+        .unwrap()
+            >= 1.0
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            &format!(
+                "cosdata_indexed_records_total{{collection=\"{}\",operation=\"upsert\"}}",
+                COLLECTION
+            )
+        ),
+        Some(20.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            &format!(
+                "cosdata_wal_backlog_versions{{collection=\"{}\"}}",
+                COLLECTION
+            )
+        ),
+        Some(0.0)
+    );
+    assert_eq!(sample(&metrics, "cosdata_collections"), Some(1.0));
+    assert_eq!(sample(&metrics, "cosdata_active_sessions"), Some(1.0));
+    assert!(sample(&metrics, "cosdata_buffer_flushes_total").unwrap() > 0.0);
+    assert!(sample(&metrics, "cosdata_cache_hits_total{cache=\"hnsw_nodes\"}").is_some());
+}
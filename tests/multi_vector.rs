@@ -0,0 +1,90 @@
+mod common;
+
+use common::{
+    create_dense_collection, login, search, start_server, streaming_upsert, vector, wait_for,
+    Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "multi_vector";
+
+fn multi_vector_search(server: &Server, token: &str, seeds: &[usize]) -> Value {
+    let query_vectors: Vec<_> = seeds.iter().map(|seed| vector(*seed)).collect();
+    let (status, response) = search(
+        server,
+        token,
+        COLLECTION,
+        "multi-vector",
+        json!({ "query_vectors": query_vectors, "top_k": 2 }),
+    );
+    assert_eq!(status, 200, "{}", response);
+    response
+}
+
+#[test]
+fn test_multi_vector_search() {
+    let server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    // documents `a` and `b` with two token vectors each, and a vector
+    // without a document
+    let vectors = json!([
+        { "id": "a0", "document_id": "a", "dense_values": vector(0) },
+        { "id": "a1", "document_id": "a", "dense_values": vector(1) },
+        { "id": "b2", "document_id": "b", "dense_values": vector(2) },
+        { "id": "b3", "document_id": "b", "dense_values": vector(3) },
+        { "id": "v4", "dense_values": vector(4) },
+    ]);
+    let status = streaming_upsert(&server, &token, COLLECTION, json!(vectors));
+    assert_eq!(status, 200);
+    wait_for("the vectors to be searchable", || {
+        let results = &multi_vector_search(&server, &token, &[4])["results"];
+        (results[0]["id"] == "v4").then_some(())
+    });
+
+    // each query vector matches a vector of the document exactly
+    let response = multi_vector_search(&server, &token, &[2, 3]);
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results[0]["document_id"], "b", "{}", response);
+    assert!((results[0]["score"].as_f64().unwrap() - 2.0).abs() < 1e-3);
+    assert!(results[0]["score"].as_f64() > results[1]["score"].as_f64());
+
+    let response = multi_vector_search(&server, &token, &[1, 0]);
+    assert_eq!(response["results"][0]["document_id"], "a", "{}", response);
+    let response = multi_vector_search(&server, &token, &[4, 4]);
+    assert_eq!(response["results"][0]["id"], "v4", "{}", response);
+    assert!(response["results"][0]["document_id"].is_null());
+
+    // documents are groups of a single hit, their best matching vector
+    let (status, response) = search(
+        &server,
+        &token,
+        COLLECTION,
+        "multi-vector",
+        json!({
+            "query_vectors": [vector(2), vector(3)],
+            "group_by": "document_id",
+            "groups_limit": 2,
+        }),
+    );
+    assert_eq!(status, 200, "{}", response);
+    assert!(response["results"].as_array().unwrap().is_empty());
+    let groups = response["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 2, "{}", response);
+    assert_eq!(groups[0]["document_id"], "b", "{}", response);
+    assert_eq!(groups[0]["hits"].as_array().unwrap().len(), 1);
+    assert_eq!(groups[0]["hits"][0]["document_id"], "b", "{}", response);
+
+    // query vectors of the wrong dimension are rejected
+    for query_vectors in [json!([vector(0), [1.0, 2.0]]), json!([[]]), json!([])] {
+        let (status, response) = search(
+            &server,
+            &token,
+            COLLECTION,
+            "multi-vector",
+            json!({ "query_vectors": query_vectors, "top_k": 2 }),
+        );
+        assert_eq!(status, 400, "{}", response);
+    }
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::{
+    create_dense_collection, login, request, start_server, start_server_with_overrides, upsert,
+    wait_for, wait_for_search,
+};
+use serde_json::json;
+
+const COLLECTION: &str = "replicated";
+
+#[test]
+fn test_follower_replicates_leader() {
+    let leader = start_server();
+    let leader_token = login(&leader);
+    let (status, api_key) = request(
+        leader.port,
+        "POST",
+        "/auth/api-keys",
+        Some(&leader_token),
+        Some(json!({ "name": "replication" })),
+    );
+    assert!(status < 300, "{}", api_key);
+
+    let follower_config = format!(
+        "role = \"follower\"\nleader_url = \"http://127.0.0.1:{}\"\napi_key = \"{}\"\n\
+         node_id = \"follower-1\"\npoll_interval_ms = 200",
+        leader.port,
+        api_key["key"].as_str().unwrap()
+    );
+    let follower = start_server_with_overrides(&[(r#"role = "leader""#, &follower_config)]);
+    let follower_token = login(&follower);
+
+    create_dense_collection(&leader, &leader_token, COLLECTION, 2);
+
+    upsert(
+        &leader,
+        &leader_token,
+        COLLECTION,
+        &(0..50).collect::<Vec<_>>(),
+    );
+    wait_for_search(&leader, &leader_token, COLLECTION, 7);
+    wait_for_search(&follower, &follower_token, COLLECTION, 7);
+
+    // replicated through the WAL of the version, the replica exists by now
+    upsert(
+        &leader,
+        &leader_token,
+        COLLECTION,
+        &(50..60).collect::<Vec<_>>(),
+    );
+    wait_for_search(&follower, &follower_token, COLLECTION, 55);
+
+    // replicas are read-only
+    let (status, _) = request(
+        follower.port,
+        "POST",
+        &format!("/vectordb/collections/{}/transactions", COLLECTION),
+        Some(&follower_token),
+        None,
+    );
+    assert_eq!(status, 403);
+
+    let follower_status = wait_for("the leader to report the follower in sync", || {
+        let (status, response) = request(
+            leader.port,
+            "GET",
+            "/vectordb/replication/status",
+            Some(&leader_token),
+            None,
+        );
+        assert_eq!(status, 200, "{}", response);
+        let follower = response["followers"]
+            .as_array()?
+            .iter()
+            .find(|follower| follower["node_id"] == "follower-1")?
+            .clone();
+        let collection = follower["collections"]
+            .as_array()?
+            .iter()
+            .find(|collection| collection["name"] == COLLECTION)?
+            .clone();
+        (collection["lag_versions"] == 0).then_some(collection)
+    });
+    assert_eq!(follower_status["lag_seconds"], 0);
+
+    let (status, response) = request(
+        follower.port,
+        "GET",
+        "/vectordb/replication/status",
+        Some(&follower_token),
+        None,
+    );
+    assert_eq!(status, 200);
+    assert_eq!(response["role"], "follower");
+    assert_eq!(response["collections"][0]["name"], COLLECTION);
+    assert_eq!(response["collections"][0]["lag_versions"], 0);
+}
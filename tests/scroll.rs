@@ -0,0 +1,118 @@
+mod common;
+
+use std::collections::HashSet;
+
+use common::{
+    create_dense_collection, login, request, request_text, start_server, upsert, wait_for, Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "scrolled";
+
+fn scroll(server: &Server, token: &str, body: Value) -> (u16, Value) {
+    request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/vectors/scroll", COLLECTION),
+        Some(token),
+        Some(body),
+    )
+}
+
+// Returns the ids of all vectors at `version`, read in pages of `limit`
+fn scroll_all(server: &Server, token: &str, limit: usize, version: &Value) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut cursor = Value::Null;
+    loop {
+        let (status, page) = scroll(
+            server,
+            token,
+            json!({ "cursor": cursor, "limit": limit, "version": version }),
+        );
+        assert_eq!(status, 200, "{}", page);
+        assert_eq!(&page["version"], version);
+        let vectors = page["vectors"].as_array().unwrap();
+        assert!(vectors.len() <= limit);
+        ids.extend(vectors.iter().map(|v| v["id"].as_str().unwrap().to_owned()));
+        cursor = page["next_cursor"].clone();
+        if cursor.is_null() {
+            return ids;
+        }
+    }
+}
+
+#[test]
+fn test_scroll_vectors() {
+    let server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    upsert(&server, &token, COLLECTION, &(0..25).collect::<Vec<_>>());
+    let first_page = wait_for("the vectors to be indexed", || {
+        let (status, page) = scroll(&server, &token, json!({ "limit": 1000 }));
+        assert_eq!(status, 200, "{}", page);
+        (page["vectors"].as_array().unwrap().len() == 25).then_some(page)
+    });
+    assert!(first_page["next_cursor"].is_null());
+    let version = first_page["version"].clone();
+
+    let ids = scroll_all(&server, &token, 10, &version);
+    assert_eq!(ids.len(), 25);
+    assert_eq!(
+        ids.into_iter().collect::<HashSet<_>>(),
+        (0..25).map(|id| format!("v{}", id)).collect()
+    );
+
+    // vectors upserted later are not part of a scan pinned to the earlier
+    // version
+    upsert(&server, &token, COLLECTION, &[25, 26]);
+    wait_for("the new vectors to be indexed", || {
+        let (_, page) = scroll(&server, &token, json!({ "limit": 1000 }));
+        (page["vectors"].as_array().unwrap().len() == 27).then_some(())
+    });
+    assert_eq!(scroll_all(&server, &token, 10, &version).len(), 25);
+
+    let (status, page) = scroll(
+        &server,
+        &token,
+        json!({ "limit": 1, "fields": ["metadata"] }),
+    );
+    assert_eq!(status, 200, "{}", page);
+    assert!(page["vectors"][0]["id"].is_string());
+    assert!(page["vectors"][0]["dense_values"].is_null());
+
+    let (status, _) = scroll(&server, &token, json!({ "cursor": "invalid" }));
+    assert_eq!(status, 400);
+    let (status, _) = scroll(&server, &token, json!({ "limit": 1001 }));
+    assert_eq!(status, 400);
+    let (status, _) = scroll(&server, &token, json!({ "version": 1000 }));
+    assert_eq!(status, 400);
+
+    // versions preceding a compaction are rejected instead of returning
+    // the rebased history
+    let response = wait_for("the collection to be compacted", || {
+        let (status, response) = request(
+            server.port,
+            "POST",
+            &format!("/vectordb/collections/{}/compact", COLLECTION),
+            Some(&token),
+            Some(json!({ "keep_versions": 1 })),
+        );
+        (status == 200).then_some(response)
+    });
+    assert_eq!(response["compacted"], true, "{}", response);
+    let (status, body) = request_text(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/vectors/scroll", COLLECTION),
+        Some(&token),
+        Some(json!({ "version": version })),
+    );
+    assert_eq!(status, 400);
+    assert!(body.contains("was compacted"), "{}", body);
+
+    let (status, page) = scroll(&server, &token, json!({ "limit": 10 }));
+    assert_eq!(status, 200, "{}", page);
+    let version = page["version"].clone();
+    assert_eq!(scroll_all(&server, &token, 10, &version).len(), 27);
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use common::{
+    create_collection, login, request, search, start_server, streaming_upsert, vector, wait_for,
+    Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "search_fields";
+
+fn dense_search(server: &Server, token: &str, body: Value) -> Value {
+    let (status, response) = search(server, token, COLLECTION, "dense", body);
+    assert_eq!(status, 200, "{}", response);
+    response
+}
+
+#[test]
+fn test_search_fields() {
+    let server = start_server();
+    let token = login(&server);
+    create_collection(
+        &server,
+        &token,
+        COLLECTION,
+        json!({
+            "metadata_schema": {
+                "fields": [{ "name": "color", "values": ["red", "blue"] }],
+                "supported_conditions": []
+            },
+            "store_raw_text": true
+        }),
+    );
+
+    let vectors: Vec<_> = (0..4)
+        .map(|id| {
+            json!({
+                "id": format!("v{}", id),
+                "document_id": format!("d{}", id / 2),
+                "dense_values": vector(id),
+                "metadata": { "color": if id % 2 == 0 { "red" } else { "blue" } },
+                "text": format!("text {}", id)
+            })
+        })
+        .collect();
+    let status = streaming_upsert(&server, &token, COLLECTION, json!(vectors));
+    assert_eq!(status, 200);
+    wait_for("the vectors to be searchable", || {
+        let response = dense_search(&server, &token, json!({ "query_vector": vector(3) }));
+        (response["results"][0]["id"] == "v3").then_some(())
+    });
+
+    // only ids and scores by default
+    let response = dense_search(
+        &server,
+        &token,
+        json!({ "query_vector": vector(1), "top_k": 1 }),
+    );
+    let result = &response["results"][0];
+    assert_eq!(result["id"], "v1", "{}", response);
+    assert!(result.get("metadata").is_none(), "{}", response);
+    assert!(result.get("dense_values").is_none(), "{}", response);
+
+    let response = dense_search(
+        &server,
+        &token,
+        json!({ "query_vector": vector(1), "top_k": 1, "with_payload": true }),
+    );
+    let result = &response["results"][0];
+    assert_eq!(result["metadata"]["color"], "blue", "{}", response);
+    assert!(result.get("dense_values").is_none(), "{}", response);
+
+    let response = dense_search(
+        &server,
+        &token,
+        json!({ "query_vector": vector(1), "top_k": 1, "fields": ["dense_values", "text"] }),
+    );
+    let result = &response["results"][0];
+    assert!(result.get("metadata").is_none(), "{}", response);
+    assert_eq!(result["text"], "text 1", "{}", response);
+    let dense_values: Vec<f32> = serde_json::from_value(result["dense_values"].clone()).unwrap();
+    for (stored_value, value) in dense_values.iter().zip(vector(1)) {
+        assert!((stored_value - value).abs() < 1e-5);
+    }
+
+    // the hits of groups get the fields too
+    let response = dense_search(
+        &server,
+        &token,
+        json!({
+            "query_vector": vector(0),
+            "group_by": "document_id",
+            "groups_limit": 2,
+            "with_payload": true,
+        }),
+    );
+    let groups = response["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 2, "{}", response);
+    for hit in groups
+        .iter()
+        .flat_map(|group| group["hits"].as_array().unwrap())
+    {
+        assert!(hit["metadata"]["color"].is_string(), "{}", response);
+    }
+
+    // fetched vectors return all fields unless selected
+    let fetch = |path: &str| {
+        let (status, response) = request(
+            server.port,
+            "GET",
+            &format!("/vectordb/collections/{}/vectors{}", COLLECTION, path),
+            Some(&token),
+            None,
+        );
+        assert_eq!(status, 200, "{}", response);
+        response
+    };
+    let response = fetch("/v1");
+    assert_eq!(response["metadata"]["color"], "blue", "{}", response);
+    assert_eq!(response["text"], "text 1", "{}", response);
+    assert!(response["dense_values"].is_array(), "{}", response);
+
+    let response = fetch("/v1?with_payload=true");
+    assert_eq!(response["id"], "v1", "{}", response);
+    assert_eq!(response["metadata"]["color"], "blue", "{}", response);
+    assert!(response["dense_values"].is_null(), "{}", response);
+    assert!(response["text"].is_null(), "{}", response);
+
+    let response = fetch("/v1?fields=dense_values,text");
+    assert!(response["metadata"].is_null(), "{}", response);
+    assert_eq!(response["text"], "text 1", "{}", response);
+    assert!(response["dense_values"].is_array(), "{}", response);
+
+    let response = fetch("?document_id=d0&with_payload=true");
+    let vectors = response.as_array().unwrap();
+    assert_eq!(vectors.len(), 2, "{}", response);
+    for vector in vectors {
+        assert_eq!(vector["document_id"], "d0", "{}", response);
+        assert!(vector["metadata"]["color"].is_string(), "{}", response);
+        assert!(vector["dense_values"].is_null(), "{}", response);
+    }
+
+    let (status, _) = request(
+        server.port,
+        "GET",
+        &format!(
+            "/vectordb/collections/{}/vectors/v1?fields=colour",
+            COLLECTION
+        ),
+        Some(&token),
+        None,
+    );
+    assert_eq!(status, 400);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::{
+    create_dense_collection, download, login, request, request_bytes, search, start_server, upsert,
+    vector, wait_for_search, Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "snapshotted";
+
+// Returns the id of the best match of the vector `id` in `collection`
+fn best_match(server: &Server, token: &str, collection: &str, id: usize) -> Option<String> {
+    let (status, response) = search(
+        server,
+        token,
+        collection,
+        "dense",
+        json!({ "query_vector": vector(id), "top_k": 1 }),
+    );
+    assert_eq!(status, 200, "{}", response);
+    response["results"][0]["id"].as_str().map(str::to_owned)
+}
+
+fn snapshot_path(name: &str) -> String {
+    format!("/vectordb/collections/{}/snapshots/{}", COLLECTION, name)
+}
+
+fn restore(server: &Server, token: &str, snapshot: &str, collection: &str) -> (u16, Value) {
+    request(
+        server.port,
+        "POST",
+        &format!("{}/restore", snapshot_path(snapshot)),
+        Some(token),
+        Some(json!({ "collection_name": collection })),
+    )
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+    upsert(&server, &token, COLLECTION, &(0..20).collect::<Vec<_>>());
+    wait_for_search(&server, &token, COLLECTION, 19);
+
+    let (status, snapshot) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/snapshots", COLLECTION),
+        Some(&token),
+        Some(json!({ "name": "before" })),
+    );
+    assert_eq!(status, 201, "{}", snapshot);
+    assert_eq!(snapshot["name"], "before");
+    let (status, snapshots) = request(
+        server.port,
+        "GET",
+        &format!("/vectordb/collections/{}/snapshots", COLLECTION),
+        Some(&token),
+        None,
+    );
+    assert_eq!(status, 200, "{}", snapshots);
+    assert_eq!(snapshots[0]["name"], "before");
+
+    let (status, archive) = download(server.port, &snapshot_path("before"), Some(&token));
+    assert_eq!(status, 200);
+    assert_eq!(archive.len() as u64, snapshot["size"].as_u64().unwrap());
+
+    // written after the snapshot, so not restored
+    upsert(&server, &token, COLLECTION, &[20]);
+    wait_for_search(&server, &token, COLLECTION, 20);
+
+    let (status, restored) = restore(&server, &token, "before", "restored");
+    assert_eq!(status, 201, "{}", restored);
+    assert_eq!(restored["collection_name"], "restored");
+    assert_eq!(restored["version"], snapshot["version"]);
+    wait_for_search(&server, &token, "restored", 7);
+    assert_ne!(
+        best_match(&server, &token, "restored", 20).as_deref(),
+        Some("v20")
+    );
+    let (status, response) = restore(&server, &token, "before", "restored");
+    assert_eq!(status, 409, "{}", response);
+
+    // the downloaded archive restores the same collection once uploaded
+    let (status, uploaded) = request_bytes(
+        server.port,
+        "PUT",
+        &snapshot_path("uploaded"),
+        Some(&token),
+        "application/octet-stream",
+        &archive,
+    );
+    assert_eq!(status, 200, "{}", uploaded);
+    let (status, response) = restore(&server, &token, "uploaded", "uploaded");
+    assert_eq!(status, 201, "{}", response);
+    wait_for_search(&server, &token, "uploaded", 19);
+
+    let (status, _) = request(
+        server.port,
+        "DELETE",
+        &snapshot_path("before"),
+        Some(&token),
+        None,
+    );
+    assert_eq!(status, 204);
+    let (status, response) = restore(&server, &token, "before", "deleted");
+    assert_eq!(status, 404, "{}", response);
+}
+
+#[test]
+fn test_upload_snapshot() {
+    let server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    let upload = |collection: &str| {
+        request_bytes(
+            server.port,
+            "PUT",
+            &format!("/vectordb/collections/{}/snapshots/uploaded", collection),
+            Some(&token),
+            "application/octet-stream",
+            b"not an archive",
+        )
+    };
+
+    // snapshots are only stored for existing collections
+    let (status, response) = upload("missing");
+    assert_eq!(status, 404, "{}", response);
+    assert!(!server.data_path().join("snapshots/missing").exists());
+
+    let (status, response) = upload(COLLECTION);
+    assert_eq!(status, 400, "{}", response);
+}
@@ -0,0 +1,400 @@
+// Runs a server as a separate process on localhost, with its data stored in
+// an S3 stub, and checks that a server started on an empty data directory
+// serves the same collection from the bucket.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+const ADMIN_KEY: &str = "storage-test-admin-key";
+const BUCKET: &str = "cosdata";
+const COLLECTION: &str = "tiered";
+const DIMENSION: usize = 8;
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Default)]
+struct Bucket {
+    objects: HashMap<String, Vec<u8>>,
+    // no. of uploads of the LMDB environment
+    lmdb_uploads: usize,
+    // no. of reads of parts of objects
+    range_reads: usize,
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            decoded.push(u8::from_str_radix(&value[i + 1..i + 3], 16).unwrap());
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).unwrap()
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &[u8]) {
+    let head = format!(
+        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(body).unwrap();
+}
+
+// Handles a request of the S3 API, for the subset of it used by the server
+fn handle(stream: TcpStream, bucket: &Mutex<Bucket>) {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let Some((name, value)) = line.trim_end().split_once(':') else {
+            break;
+        };
+        headers.insert(name.to_lowercase(), value.trim().to_string());
+    }
+    let len = headers
+        .get("content-length")
+        .map_or(0, |len| len.parse().unwrap());
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).unwrap();
+    let mut stream = reader.into_inner();
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap();
+    let target = parts.next().unwrap();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = percent_decode(path);
+    let key = path
+        .strip_prefix(&format!("/{}/", BUCKET))
+        .map(str::to_owned);
+
+    let mut bucket = bucket.lock().unwrap();
+    match (method, key) {
+        ("PUT", Some(key)) => {
+            if key.ends_with("_mdb/data.mdb") {
+                bucket.lmdb_uploads += 1;
+            }
+            bucket.objects.insert(key, body);
+            respond(&mut stream, "200 OK", b"");
+        }
+        ("GET", Some(key)) => {
+            let Some(object) = bucket.objects.get(&key).cloned() else {
+                return respond(&mut stream, "404 Not Found", b"");
+            };
+            let (start, end) = headers["range"]
+                .strip_prefix("bytes=")
+                .and_then(|range| range.split_once('-'))
+                .unwrap();
+            let start: usize = start.parse().unwrap();
+            let end = (end.parse::<usize>().unwrap() + 1).min(object.len());
+            bucket.range_reads += 1;
+            respond(&mut stream, "206 Partial Content", &object[start..end]);
+        }
+        ("HEAD", Some(key)) => match bucket.objects.get(&key) {
+            // the length of the object, without its body
+            Some(object) => {
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    object.len()
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+            }
+            None => respond(&mut stream, "404 Not Found", b""),
+        },
+        ("DELETE", Some(key)) => {
+            bucket.objects.remove(&key);
+            respond(&mut stream, "204 No Content", b"");
+        }
+        ("GET", None) => {
+            let prefix = query
+                .split('&')
+                .find_map(|param| param.strip_prefix("prefix="))
+                .map(percent_decode)
+                .unwrap_or_default();
+            let mut listing = String::from("<ListBucketResult><IsTruncated>false</IsTruncated>");
+            for (key, object) in &bucket.objects {
+                if key.starts_with(&prefix) {
+                    listing.push_str(&format!(
+                        "<Contents><Key>{}</Key><Size>{}</Size></Contents>",
+                        key,
+                        object.len()
+                    ));
+                }
+            }
+            listing.push_str("</ListBucketResult>");
+            respond(&mut stream, "200 OK", listing.as_bytes());
+        }
+        _ => respond(&mut stream, "400 Bad Request", b""),
+    }
+}
+
+// Starts an S3 stub keeping the objects in memory, returns its endpoint
+fn start_s3() -> (String, Arc<Mutex<Bucket>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let bucket = Arc::new(Mutex::new(Bucket::default()));
+    let stub_bucket = bucket.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            handle(stream.unwrap(), &stub_bucket);
+        }
+    });
+    (endpoint, bucket)
+}
+
+struct Server {
+    process: Child,
+    port: u16,
+    // removed once the process is killed
+    _home: TempDir,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+// Starts a server on an empty data directory, storing its data in the S3
+// bucket at `endpoint`
+fn start_server(endpoint: &str) -> Server {
+    let home = TempDir::new().unwrap();
+    let port = free_port();
+    let config =
+        fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("config.toml")).unwrap();
+    let config = format!(
+        "{}\n[storage.s3]\nendpoint = {:?}\nbucket = {:?}\naccess_key = \"test\"\nsecret_key = \"test\"\n",
+        config
+            .replace("port = 8443", &format!("port = {}", port))
+            .replace("pool_size = 64", "pool_size = 4")
+            .replace(r#"backend = "local""#, r#"backend = "s3""#)
+            .replace("# sync_interval = 60", "sync_interval = 1"),
+        endpoint,
+        BUCKET
+    );
+    fs::create_dir_all(home.path().join("config")).unwrap();
+    fs::write(home.path().join("config/config.toml"), config).unwrap();
+
+    let process = Command::new(env!("CARGO_BIN_EXE_cosdata"))
+        .args(["--admin-key", ADMIN_KEY, "--skip-confirmation"])
+        .env("COSDATA_HOME", home.path())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    Server {
+        process,
+        port,
+        _home: home,
+    }
+}
+
+fn request(
+    port: u16,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (u16, Value) {
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(60)))
+        .unwrap();
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nhost: 127.0.0.1:{}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n",
+        method,
+        path,
+        port,
+        body.len()
+    );
+    if let Some(token) = token {
+        request.push_str(&format!("authorization: Bearer {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    request.push_str(&body);
+    stream.write_all(request.as_bytes()).unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap_or(Value::Null))
+}
+
+fn wait_for<T>(what: &str, mut f: impl FnMut() -> Option<T>) -> T {
+    let start = Instant::now();
+    loop {
+        if let Some(value) = f() {
+            return value;
+        }
+        assert!(start.elapsed() < TIMEOUT, "Timed out waiting for {}", what);
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+fn login(server: &Server) -> String {
+    wait_for("the server to start", || {
+        TcpStream::connect(("127.0.0.1", server.port)).ok()?;
+        let (status, session) = request(
+            server.port,
+            "POST",
+            "/auth/create-session",
+            None,
+            Some(json!({ "username": "admin", "password": ADMIN_KEY })),
+        );
+        (status == 200).then(|| session["access_token"].as_str().unwrap().to_string())
+    })
+}
+
+fn vector(seed: usize) -> Vec<f32> {
+    (0..DIMENSION)
+        .map(|i| ((seed * DIMENSION + i) as f32).sin())
+        .collect()
+}
+
+fn create_collection(server: &Server, token: &str) {
+    let (status, response) = request(
+        server.port,
+        "POST",
+        "/vectordb/collections",
+        Some(token),
+        Some(json!({
+            "name": COLLECTION,
+            "dense_vector": { "enabled": true, "dimension": DIMENSION },
+            "sparse_vector": { "enabled": false },
+            "tf_idf_options": { "enabled": false },
+            "config": { "max_vectors": null, "replication_factor": null }
+        })),
+    );
+    assert!(status < 300, "{}", response);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/indexes/dense", COLLECTION),
+        Some(token),
+        Some(json!({
+            "name": "dense",
+            "distance_metric_type": "cosine",
+            "quantization": {
+                "type": "scalar",
+                "properties": { "data_type": "f32", "range": { "min": -1.0, "max": 1.0 } }
+            },
+            "index": { "type": "hnsw", "properties": {} }
+        })),
+    );
+    assert!(status < 300, "{}", response);
+}
+
+fn upsert(server: &Server, token: &str, ids: &[usize]) {
+    let path = format!("/vectordb/collections/{}/transactions", COLLECTION);
+    let (status, transaction) = request(server.port, "POST", &path, Some(token), None);
+    assert_eq!(status, 200, "{}", transaction);
+    let path = format!(
+        "{}/{}",
+        path,
+        transaction["transaction_id"].as_str().unwrap()
+    );
+
+    let vectors: Vec<_> = ids
+        .iter()
+        .map(|id| json!({ "id": format!("v{}", id), "dense_values": vector(*id) }))
+        .collect();
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/upsert", path),
+        Some(token),
+        Some(json!({ "vectors": vectors })),
+    );
+    assert!(status < 300, "{} {}", status, response);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/commit", path),
+        Some(token),
+        None,
+    );
+    assert!(status < 300, "{} {}", status, response);
+}
+
+// Waits for a search for the vector `id` to return it as the best match
+fn wait_for_search(server: &Server, token: &str, id: usize) {
+    wait_for(&format!("v{} to be searchable", id), || {
+        let (status, response) = request(
+            server.port,
+            "POST",
+            &format!("/vectordb/collections/{}/search/dense", COLLECTION),
+            Some(token),
+            Some(json!({ "query_vector": vector(id), "top_k": 1 })),
+        );
+        (status == 200 && response["results"][0]["id"] == format!("v{}", id)).then_some(())
+    })
+}
+
+#[test]
+fn test_collection_rehydrated_from_s3() {
+    let (endpoint, bucket) = start_s3();
+    let server = start_server(&endpoint);
+    let token = login(&server);
+    create_collection(&server, &token);
+    upsert(&server, &token, &(0..20).collect::<Vec<_>>());
+    wait_for_search(&server, &token, 19);
+
+    // the upload following a complete one has all the writes so far
+    let lmdb_uploads = bucket.lock().unwrap().lmdb_uploads;
+    wait_for("the collection to be uploaded", || {
+        (bucket.lock().unwrap().lmdb_uploads >= lmdb_uploads + 2).then_some(())
+    });
+    drop(server);
+    assert!(bucket
+        .lock()
+        .unwrap()
+        .objects
+        .keys()
+        .any(|key| key.contains(&format!("collections/{}/", COLLECTION))));
+
+    // a replacement node on an empty data directory fetches the files of
+    // the collection from the bucket as they're read
+    let range_reads = bucket.lock().unwrap().range_reads;
+    let server = start_server(&endpoint);
+    let token = login(&server);
+    for id in [0, 7, 19] {
+        wait_for_search(&server, &token, id);
+    }
+    assert!(bucket.lock().unwrap().range_reads > range_reads);
+
+    // and keeps writing to it
+    upsert(&server, &token, &[20]);
+    wait_for_search(&server, &token, 20);
+}
@@ -0,0 +1,236 @@
+mod common;
+
+use common::{login, request, start_server, vector, wait_for, Server, DIMENSION};
+use serde_json::{json, Value};
+
+const TENANT: &str = "acme";
+const OTHER_TENANT: &str = "globex";
+const COLLECTION: &str = "docs";
+
+fn tenant_path(tenant: &str, path: &str) -> String {
+    format!("/vectordb/tenants/{}/collections{}", tenant, path)
+}
+
+fn create_tenant(server: &Server, token: &str, name: &str, quotas: Value) {
+    let (status, response) = request(
+        server.port,
+        "POST",
+        "/vectordb/tenants",
+        Some(token),
+        Some(json!({ "name": name, "quotas": quotas })),
+    );
+    assert_eq!(status, 201, "{}", response);
+}
+
+fn create_tenant_collection(server: &Server, token: &str, tenant: &str, name: &str) -> u16 {
+    let (status, _) = request(
+        server.port,
+        "POST",
+        &tenant_path(tenant, ""),
+        Some(token),
+        Some(json!({
+            "name": name,
+            "dense_vector": { "enabled": true, "dimension": DIMENSION },
+            "sparse_vector": { "enabled": false },
+            "tf_idf_options": { "enabled": false },
+            "config": { "max_vectors": null, "replication_factor": 1 }
+        })),
+    );
+    if status >= 300 {
+        return status;
+    }
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &tenant_path(tenant, &format!("/{}/indexes/dense", name)),
+        Some(token),
+        Some(json!({
+            "name": "dense",
+            "distance_metric_type": "cosine",
+            "quantization": {
+                "type": "scalar",
+                "properties": { "data_type": "f32", "range": { "min": -1.0, "max": 1.0 } }
+            },
+            "index": { "type": "hnsw", "properties": {} }
+        })),
+    );
+    assert!(status < 300, "{}", response);
+    status
+}
+
+fn vectors(ids: &[usize]) -> Value {
+    ids.iter()
+        .map(|id| json!({ "id": format!("v{}", id), "dense_values": vector(*id) }))
+        .collect()
+}
+
+fn streaming_upsert(server: &Server, token: &str, tenant: &str, ids: &[usize]) -> u16 {
+    let (status, _) = request(
+        server.port,
+        "POST",
+        &tenant_path(tenant, &format!("/{}/streaming/upsert", COLLECTION)),
+        Some(token),
+        Some(json!({ "vectors": vectors(ids) })),
+    );
+    status
+}
+
+// Upserts `vectors` in a transaction and returns the status of the upsert
+fn transaction_upsert(server: &Server, token: &str, vectors: Value) -> u16 {
+    let path = tenant_path(TENANT, &format!("/{}/transactions", COLLECTION));
+    let (status, transaction) = request(server.port, "POST", &path, Some(token), None);
+    assert_eq!(status, 200, "{}", transaction);
+    let path = format!(
+        "{}/{}",
+        path,
+        transaction["transaction_id"].as_str().unwrap()
+    );
+    let (upsert_status, _) = request(
+        server.port,
+        "POST",
+        &format!("{}/upsert", path),
+        Some(token),
+        Some(json!({ "vectors": vectors })),
+    );
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/commit", path),
+        Some(token),
+        None,
+    );
+    assert!(status < 300, "{} {}", status, response);
+    upsert_status
+}
+
+fn best_match(server: &Server, token: &str, tenant: &str, id: usize) -> Option<String> {
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &tenant_path(tenant, &format!("/{}/search/dense", COLLECTION)),
+        Some(token),
+        Some(json!({ "query_vector": vector(id), "top_k": 1 })),
+    );
+    assert_eq!(status, 200, "{}", response);
+    response["results"][0]["id"].as_str().map(str::to_owned)
+}
+
+#[test]
+fn test_tenant_collections() {
+    let server = start_server();
+    let admin_token = login(&server);
+    create_tenant(
+        &server,
+        &admin_token,
+        TENANT,
+        json!({ "max_collections": 1, "max_vectors": 3 }),
+    );
+    create_tenant(&server, &admin_token, OTHER_TENANT, json!({}));
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/tenants/{}/users", TENANT),
+        Some(&admin_token),
+        Some(json!({ "username": "alice", "password": "alice-password" })),
+    );
+    assert_eq!(status, 201, "{}", response);
+    let (status, session) = request(
+        server.port,
+        "POST",
+        "/auth/create-session",
+        None,
+        Some(json!({ "username": "alice", "password": "alice-password" })),
+    );
+    assert_eq!(status, 200, "{}", session);
+    let token = session["access_token"].as_str().unwrap().to_string();
+
+    // the tenant routes are rewritten to the qualified collection names
+    assert_eq!(
+        create_tenant_collection(&server, &token, TENANT, COLLECTION),
+        201
+    );
+    let (status, collection) = request(
+        server.port,
+        "GET",
+        &tenant_path(TENANT, &format!("/{}", COLLECTION)),
+        Some(&token),
+        None,
+    );
+    assert_eq!(status, 200, "{}", collection);
+    assert_eq!(collection["name"], COLLECTION);
+    let (status, collection) = request(
+        server.port,
+        "GET",
+        &format!("/vectordb/collections/{}::{}", TENANT, COLLECTION),
+        Some(&admin_token),
+        None,
+    );
+    assert_eq!(status, 200, "{}", collection);
+    assert_eq!(collection["name"], format!("{}::{}", TENANT, COLLECTION));
+
+    // the collections of other tenants are out of reach of the members
+    assert_eq!(
+        create_tenant_collection(&server, &admin_token, OTHER_TENANT, COLLECTION),
+        201
+    );
+    for path in [
+        tenant_path(OTHER_TENANT, &format!("/{}", COLLECTION)),
+        format!("/vectordb/collections/{}::{}", TENANT, COLLECTION),
+    ] {
+        let (status, response) = request(server.port, "GET", &path, Some(&token), None);
+        assert_eq!(status, 403, "{} {}", path, response);
+    }
+    let (status, collections) = request(
+        server.port,
+        "GET",
+        &tenant_path(TENANT, ""),
+        Some(&token),
+        None,
+    );
+    assert_eq!(status, 200, "{}", collections);
+    assert_eq!(
+        collections,
+        json!([{ "name": COLLECTION, "description": null }])
+    );
+
+    assert_eq!(streaming_upsert(&server, &token, TENANT, &[0, 1, 2]), 200);
+    wait_for("the vectors to be counted", || {
+        let (status, tenant) = request(
+            server.port,
+            "GET",
+            &format!("/vectordb/tenants/{}", TENANT),
+            Some(&token),
+            None,
+        );
+        assert_eq!(status, 200, "{}", tenant);
+        (tenant["usage"]["vectors"] == 3).then_some(())
+    });
+    wait_for("v2 to be searchable", || {
+        (best_match(&server, &token, TENANT, 2)? == "v2").then_some(())
+    });
+    assert_eq!(best_match(&server, &admin_token, OTHER_TENANT, 2), None);
+
+    // vectors replacing existing ones don't count against the quotas
+    assert_eq!(
+        create_tenant_collection(&server, &token, TENANT, "other"),
+        403
+    );
+    assert_eq!(streaming_upsert(&server, &token, TENANT, &[3]), 403);
+    assert_eq!(transaction_upsert(&server, &token, vectors(&[3])), 403);
+    assert_eq!(streaming_upsert(&server, &token, TENANT, &[0]), 200);
+    let replacing: Vec<_> = [1, 2]
+        .into_iter()
+        .map(|id| {
+            let (status, stored) = request(
+                server.port,
+                "GET",
+                &tenant_path(TENANT, &format!("/{}/vectors/v{}", COLLECTION, id)),
+                Some(&token),
+                None,
+            );
+            assert_eq!(status, 200, "{}", stored);
+            json!({ "id": format!("v{}", id), "dense_values": vector(id), "if_version": stored["version"] })
+        })
+        .collect();
+    assert_eq!(transaction_upsert(&server, &token, json!(replacing)), 200);
+}
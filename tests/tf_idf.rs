@@ -0,0 +1,96 @@
+mod common;
+
+use common::{
+    create_collection, create_dense_collection, login, request, start_server, streaming_upsert,
+    wait_for, Server, DIMENSION,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "documents";
+const DOCUMENTS: [&str; 4] = ["quick brown fox", "red dog", "fox dog fox", "cat"];
+
+fn stats(server: &Server, token: &str, collection: &str, query: &str) -> (u16, Value) {
+    request(
+        server.port,
+        "GET",
+        &format!(
+            "/vectordb/collections/{}/indexes/tf-idf/stats{}",
+            collection, query
+        ),
+        Some(token),
+        None,
+    )
+}
+
+#[test]
+fn test_tf_idf_stats() {
+    let server = start_server();
+    let token = login(&server);
+    create_collection(
+        &server,
+        &token,
+        COLLECTION,
+        json!({
+            "dense_vector": { "enabled": false, "dimension": DIMENSION },
+            "tf_idf_options": { "enabled": true }
+        }),
+    );
+    let (status, response) = stats(&server, &token, COLLECTION, "");
+    assert_eq!(status, 404, "{}", response);
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("/vectordb/collections/{}/indexes/tf-idf", COLLECTION),
+        Some(&token),
+        Some(json!({ "name": "bm25", "sample_threshold": 2, "k1": 1.5, "b": 0.5 })),
+    );
+    assert!(status < 300, "{}", response);
+
+    let vectors: Vec<_> = DOCUMENTS
+        .iter()
+        .enumerate()
+        .map(|(id, text)| json!({ "id": format!("d{}", id), "text": text }))
+        .collect();
+    assert_eq!(
+        streaming_upsert(&server, &token, COLLECTION, json!(vectors)),
+        200
+    );
+    let corpus = wait_for("the documents to be indexed", || {
+        let (status, response) =
+            stats(&server, &token, COLLECTION, "?terms=fox,the%20dog,wolf,fox");
+        assert_eq!(status, 200, "{}", response);
+        (response["total_documents"] == 4).then_some(response)
+    });
+    assert_eq!(corpus["collection_name"], COLLECTION);
+    assert_eq!(
+        (corpus["k1"].as_f64(), corpus["b"].as_f64()),
+        (Some(1.5), Some(0.5))
+    );
+    assert!(corpus["average_document_length"].as_f64().unwrap() > 0.0);
+    // stopwords aren't terms and repeated terms are reported once
+    let terms: Vec<_> = corpus["terms"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|term| {
+            (
+                term["term"].as_str().unwrap(),
+                term["document_frequency"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(terms, vec![("fox", 2), ("dog", 2), ("wolf", 0)]);
+    let idf = |term: usize| corpus["terms"][term]["idf"].as_f64().unwrap();
+    // rarer terms weigh more
+    assert!(idf(2) > idf(0));
+    assert_eq!(idf(0), idf(1));
+
+    let (status, response) = stats(&server, &token, COLLECTION, "");
+    assert_eq!(status, 200, "{}", response);
+    assert_eq!(response["terms"], json!([]));
+    let (status, response) = stats(&server, &token, "missing", "");
+    assert_eq!(status, 404, "{}", response);
+    create_dense_collection(&server, &token, "dense", 1);
+    let (status, response) = stats(&server, &token, "dense", "?terms=fox");
+    assert_eq!(status, 404, "{}", response);
+}
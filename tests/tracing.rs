@@ -0,0 +1,102 @@
+mod common;
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc,
+    thread,
+};
+
+use common::{
+    create_dense_collection, login, start_server_with_overrides, upsert, wait_for, wait_for_search,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+
+const COLLECTION: &str = "traced";
+
+// Accepts OTLP/HTTP export requests and sends their bodies to the channel
+fn start_collector() -> (String, mpsc::Receiver<Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            assert!(request_line.starts_with("POST /v1/traces "));
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            if sender.send(serde_json::from_slice(&body).unwrap()).is_err() {
+                return;
+            }
+        }
+    });
+    (endpoint, receiver)
+}
+
+fn span_names(request: &Value) -> Vec<String> {
+    request["resourceSpans"][0]["scopeSpans"][0]["spans"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|span| span["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_slow_query_log_and_otlp_export() {
+    let logs = TempDir::new().unwrap();
+    let slow_query_log = logs.path().join("slow_queries.log");
+    let (endpoint, exports) = start_collector();
+    let tracing_config = format!(
+        "[tracing]\nslow_query_threshold_ms = 0\nslow_query_log = {:?}\notlp_endpoint = {:?}",
+        slow_query_log, endpoint
+    );
+    let server = start_server_with_overrides(&[("[tracing]", &tracing_config)]);
+    let token = login(&server);
+
+    create_dense_collection(&server, &token, COLLECTION, 1);
+    upsert(&server, &token, COLLECTION, &(0..20).collect::<Vec<_>>());
+    wait_for_search(&server, &token, COLLECTION, 3);
+
+    let query = wait_for("the search to be logged", || {
+        let log = fs::read_to_string(&slow_query_log).ok()?;
+        log.lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .find(|query| query["collection"] == COLLECTION && query["top_k"] == 1)
+    });
+    assert_eq!(query["query_type"], "dense");
+    assert_eq!(query["filter"], Value::Null);
+    assert!(query["nodes_visited"].as_u64().unwrap() > 0);
+    assert!(query["wall_time_ms"].as_f64().is_some());
+    assert_eq!(query["trace_id"].as_str().unwrap().len(), 32);
+
+    let mut names = Vec::new();
+    wait_for("the spans to be exported", || {
+        names.extend(span_names(&exports.recv().unwrap()));
+        (names.iter().any(|name| name == "index_transaction")
+            && names.iter().any(|name| name == "ann_search"))
+        .then_some(())
+    });
+    assert!(names.iter().any(|name| name == "search"));
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use std::thread;
+
+use common::{
+    create_dense_collection, get_vector, login, open_transaction, request, request_bytes,
+    start_server, start_server_with_overrides, streaming_upsert, vector, wait_for, wait_for_search,
+    Server,
+};
+use serde_json::{json, Value};
+
+const COLLECTION: &str = "transactional";
+
+fn upsert_in_transaction(server: &Server, token: &str, path: &str, ids: &[usize]) {
+    let vectors: Vec<_> = ids.iter().map(|id| (*id, *id)).collect();
+    upsert_seeded(server, token, path, &vectors);
+}
+
+// upserts the vectors `v{id}` with the values of `vector(seed)`, for each
+// `(id, seed)`
+fn upsert_seeded(server: &Server, token: &str, path: &str, vectors: &[(usize, usize)]) {
+    let vectors: Vec<_> = vectors
+        .iter()
+        .map(|(id, seed)| json!({ "id": format!("v{}", id), "dense_values": vector(*seed) }))
+        .collect();
+    let (status, response) = request(
+        server.port,
+        "POST",
+        &format!("{}/upsert", path),
+        Some(token),
+        Some(json!({ "vectors": vectors })),
+    );
+    assert!(status < 300, "{} {}", status, response);
+}
+
+fn commit(server: &Server, token: &str, path: &str, query: &str) -> (u16, Value) {
+    request(
+        server.port,
+        "POST",
+        &format!("{}/commit{}", path, query),
+        Some(token),
+        None,
+    )
+}
+
+// Checks that the stored values of `v{id}` are the ones of `vector(seed)`
+fn assert_values(server: &Server, token: &str, id: usize, seed: usize) {
+    let (status, response) = get_vector(server, token, COLLECTION, &format!("v{}", id));
+    assert_eq!(status, 200, "{}", response);
+    let values = response["dense_values"].as_array().unwrap();
+    for (value, expected) in values.iter().zip(vector(seed)) {
+        assert!((value.as_f64().unwrap() - expected as f64).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_transaction_survives_restart() {
+    let mut server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    let path = open_transaction(&server, &token, COLLECTION);
+    upsert_in_transaction(&server, &token, &path, &(0..10).collect::<Vec<_>>());
+
+    server.restart();
+    let token = login(&server);
+
+    upsert_in_transaction(&server, &token, &path, &(10..15).collect::<Vec<_>>());
+    let (status, response) = commit(&server, &token, &path, "");
+    assert!(status < 300, "{} {}", status, response);
+
+    wait_for_search(&server, &token, COLLECTION, 3);
+    wait_for_search(&server, &token, COLLECTION, 14);
+    assert_eq!(get_vector(&server, &token, COLLECTION, "v7").0, 200);
+}
+
+#[test]
+fn test_idle_transaction_is_aborted() {
+    let server = start_server_with_overrides(&[("# idle_timeout = 300", "idle_timeout = 1")]);
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    let path = open_transaction(&server, &token, COLLECTION);
+    upsert_in_transaction(&server, &token, &path, &[0]);
+
+    let transaction_id = path.rsplit('/').next().unwrap();
+    let id = u32::from_str_radix(transaction_id.trim_start_matches("0x"), 16).unwrap();
+    let wal_path = server
+        .data_path()
+        .join(format!("collections/{}/txn_{}.wal", COLLECTION, id));
+    assert!(wal_path.exists());
+    wait_for("the idle transaction to be aborted", || {
+        (!wal_path.exists()).then_some(())
+    });
+    let (status, _) = commit(&server, &token, &path, "");
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn test_concurrent_transactions() {
+    let server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    // both transactions write v10, the last one committed wins
+    let first = open_transaction(&server, &token, COLLECTION);
+    let second = open_transaction(&server, &token, COLLECTION);
+    upsert_seeded(&server, &token, &second, &[(5, 5), (6, 6), (10, 11)]);
+    upsert_seeded(&server, &token, &first, &[(0, 0), (1, 1), (10, 10)]);
+    let (status, response) = commit(&server, &token, &first, "");
+    assert_eq!(status, 204, "{}", response);
+    let (status, response) = commit(&server, &token, &second, "");
+    assert_eq!(status, 204, "{}", response);
+    wait_for_search(&server, &token, COLLECTION, 1);
+    wait_for_search(&server, &token, COLLECTION, 6);
+    assert_values(&server, &token, 10, 11);
+
+    // a transaction committed with `on_conflict=abort` is aborted if
+    // another one wrote the same vector since it was opened
+    let first = open_transaction(&server, &token, COLLECTION);
+    let second = open_transaction(&server, &token, COLLECTION);
+    let third = open_transaction(&server, &token, COLLECTION);
+    upsert_seeded(&server, &token, &first, &[(20, 20)]);
+    upsert_seeded(&server, &token, &second, &[(20, 21)]);
+    upsert_seeded(&server, &token, &third, &[(30, 30)]);
+    let (status, response) = commit(&server, &token, &first, "");
+    assert_eq!(status, 204, "{}", response);
+    let (status, response) = commit(&server, &token, &second, "?on_conflict=abort");
+    assert_eq!(status, 409, "{}", response);
+    let (status, response) = commit(&server, &token, &second, "");
+    assert_eq!(status, 400, "{}", response);
+    let (status, response) = commit(&server, &token, &third, "?on_conflict=abort");
+    assert_eq!(status, 204, "{}", response);
+    wait_for_search(&server, &token, COLLECTION, 30);
+    assert_values(&server, &token, 20, 20);
+}
+
+#[test]
+fn test_concurrent_open_and_commit() {
+    // every epoch commits the streamed writes, while holding the lock of the
+    // open transactions
+    let server = start_server_with_overrides(&[("epoch_length = 3_600", "epoch_length = 1")]);
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+
+    // a deadlock makes the requests time out
+    thread::scope(|scope| {
+        for writer in 0..4 {
+            let (server, token) = (&server, &token);
+            scope.spawn(move || {
+                for round in 0..10 {
+                    let path = open_transaction(server, token, COLLECTION);
+                    upsert_in_transaction(server, token, &path, &[100 + writer * 10 + round]);
+                    let (status, response) = commit(server, token, &path, "");
+                    assert_eq!(status, 204, "{}", response);
+                }
+            });
+        }
+        scope.spawn(|| {
+            for id in 200..240 {
+                let vectors = json!([{ "id": format!("v{}", id), "dense_values": vector(id) }]);
+                assert_eq!(streaming_upsert(&server, &token, COLLECTION, vectors), 200);
+            }
+        });
+    });
+
+    for id in [100, 139, 200, 239] {
+        wait_for_search(&server, &token, COLLECTION, id);
+    }
+}
+
+#[test]
+fn test_ndjson_upsert() {
+    let server = start_server();
+    let token = login(&server);
+    create_dense_collection(&server, &token, COLLECTION, 1);
+    let path = format!("/vectordb/collections/{}/streaming/upsert", COLLECTION);
+
+    let body: String = (0..3)
+        .map(|id| {
+            json!({ "id": format!("v{}", id), "dense_values": vector(id) }).to_string() + "\n"
+        })
+        .collect();
+    let (status, response) = request_bytes(
+        server.port,
+        "POST",
+        &path,
+        Some(&token),
+        "application/x-ndjson",
+        body.as_bytes(),
+    );
+    assert_eq!(status, 200, "{}", response);
+    wait_for_search(&server, &token, COLLECTION, 2);
+
+    // a line is never buffered beyond the size limit
+    let body = format!("{{\"id\": \"v3\", \"text\": \"{}", "a".repeat(9_000_000));
+    let (status, response) = request_bytes(
+        server.port,
+        "POST",
+        &path,
+        Some(&token),
+        "application/x-ndjson",
+        body.as_bytes(),
+    );
+    assert_eq!(status, 413, "{}", response);
+    assert!(response.contains("line 1"), "{}", response);
+}